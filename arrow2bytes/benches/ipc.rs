@@ -0,0 +1,154 @@
+//! Measure the cost of the IPC conversion that is used to pass data between host and guest.
+//!
+//! # What is Measured
+//! We measure [`array2bytes`], [`bytes2array`], and [`checksum`] for arrays of different data types. Every array has
+//! [`N_ROWS`] rows, every 4th of them is NULL.
+#![expect(
+    // Docs are not strictly required for tests.
+    clippy::missing_docs_in_private_items,
+    // unused-crate-dependencies false positives
+    unused_crate_dependencies,
+)]
+
+use std::{hint::black_box, sync::Arc};
+
+use arrow::{
+    array::{
+        ArrayRef, BinaryArray, BooleanArray, Float64Array, Int64Array, ListArray, StringArray,
+        StructArray,
+    },
+    datatypes::{DataType, Field, Int64Type},
+};
+use datafusion_udf_wasm_arrow2bytes::{array2bytes, bytes2array, checksum};
+use gungraun::{library_benchmark, library_benchmark_group, main};
+
+/// Number of rows per array.
+const N_ROWS: usize = 8192;
+
+#[derive(Debug, Clone, Copy)]
+enum Type {
+    Int64,
+    Float64,
+    Boolean,
+    Utf8,
+    Binary,
+    List,
+    Struct,
+}
+
+impl Type {
+    const ALL: &[Self] = &[
+        Self::Int64,
+        Self::Float64,
+        Self::Boolean,
+        Self::Utf8,
+        Self::Binary,
+        Self::List,
+        Self::Struct,
+    ];
+
+    fn array(self) -> ArrayRef {
+        let valid = |i: usize| !i.is_multiple_of(4);
+
+        match self {
+            Self::Int64 => Arc::new(Int64Array::from_iter(
+                (0..N_ROWS).map(|i| valid(i).then_some(i as i64)),
+            )),
+            Self::Float64 => Arc::new(Float64Array::from_iter(
+                (0..N_ROWS).map(|i| valid(i).then_some(i as f64)),
+            )),
+            Self::Boolean => Arc::new(BooleanArray::from_iter(
+                (0..N_ROWS).map(|i| valid(i).then_some(i % 3 == 0)),
+            )),
+            Self::Utf8 => Arc::new(StringArray::from_iter(
+                (0..N_ROWS).map(|i| valid(i).then(|| format!("value {i}"))),
+            )),
+            Self::Binary => Arc::new(BinaryArray::from_iter(
+                (0..N_ROWS).map(|i| valid(i).then(|| i.to_le_bytes())),
+            )),
+            Self::List => Arc::new(ListArray::from_iter_primitive::<Int64Type, _, _>(
+                (0..N_ROWS).map(|i| valid(i).then(|| (0..(i % 5) as i64).map(Some))),
+            )),
+            Self::Struct => Arc::new(StructArray::from(vec![
+                (
+                    Arc::new(Field::new("a", DataType::Int64, true)),
+                    Self::Int64.array(),
+                ),
+                (
+                    Arc::new(Field::new("b", DataType::Utf8, true)),
+                    Self::Utf8.array(),
+                ),
+            ])),
+        }
+    }
+}
+
+mod actual_benchmark {
+    use super::*;
+
+    fn setup_encode(t: Type) -> ArrayRef {
+        t.array()
+    }
+
+    fn setup_decode(t: Type) -> Vec<u8> {
+        array2bytes(t.array())
+    }
+
+    #[library_benchmark(setup = setup_encode)]
+    #[bench::int64(Type::Int64)]
+    #[bench::float64(Type::Float64)]
+    #[bench::boolean(Type::Boolean)]
+    #[bench::utf8(Type::Utf8)]
+    #[bench::binary(Type::Binary)]
+    #[bench::list(Type::List)]
+    #[bench::structs(Type::Struct)]
+    fn bench_encode(array: ArrayRef) -> Vec<u8> {
+        black_box(array2bytes(array))
+    }
+
+    #[library_benchmark(setup = setup_decode)]
+    #[bench::int64(Type::Int64)]
+    #[bench::float64(Type::Float64)]
+    #[bench::boolean(Type::Boolean)]
+    #[bench::utf8(Type::Utf8)]
+    #[bench::binary(Type::Binary)]
+    #[bench::list(Type::List)]
+    #[bench::structs(Type::Struct)]
+    fn bench_decode(bytes: Vec<u8>) -> ArrayRef {
+        black_box(bytes2array(&bytes).unwrap())
+    }
+
+    #[library_benchmark(setup = setup_decode)]
+    #[bench::int64(Type::Int64)]
+    #[bench::utf8(Type::Utf8)]
+    fn bench_checksum(bytes: Vec<u8>) -> u64 {
+        black_box(checksum(&bytes))
+    }
+
+    library_benchmark_group!(
+        name = ipc;
+        benchmarks = bench_encode, bench_decode, bench_checksum
+    );
+
+    main!(library_benchmark_groups = ipc);
+
+    // re-export `main`
+    pub(crate) fn pub_main() {
+        main();
+    }
+}
+
+fn main() {
+    // Running the actual benchmark under Valgrind is rather expensive, esp. in CI. Hence we just smoke tests.
+    if std::env::args().any(|arg| arg == "--test") {
+        for t in Type::ALL {
+            println!("type={t:?}");
+            let array = t.array();
+            let bytes = array2bytes(Arc::clone(&array));
+            checksum(&bytes);
+            assert_eq!(bytes2array(&bytes).unwrap().as_ref(), array.as_ref());
+        }
+    } else {
+        actual_benchmark::pub_main();
+    }
+}
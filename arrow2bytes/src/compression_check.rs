@@ -1,17 +1,97 @@
 //! Scan IPC data for compressed data.
 //!
 //! This is a workaround until <https://github.com/apache/arrow-rs/issues/8917> is implemented.
+//!
+//! If compression is allowed, this also computes the uncompressed size of the buffers so that limits can be enforced
+//! BEFORE anything is decompressed into memory.
 
-use std::io::{Cursor, Read, Seek};
+use std::{
+    cmp::Ordering,
+    io::{Cursor, Read, Seek},
+    ops::Range,
+};
 
 use arrow::{
+    datatypes::Schema,
     error::ArrowError,
-    ipc::{BodyCompression, MessageHeader, root_as_message},
+    ipc::{
+        BodyCompression, CompressionType, MessageHeader, RecordBatch as IpcRecordBatch,
+        convert::fb_to_schema, root_as_message,
+    },
 };
 
-/// Detect and fail if there's compressed data.
-pub(crate) fn detect_compressed_data(bytes: &[u8]) -> Result<(), ArrowError> {
+/// Information gathered while scanning the IPC data.
+#[derive(Debug, Default)]
+pub(crate) struct Scan {
+    /// Accumulated decoded size of all message bodies, in bytes.
+    ///
+    /// For uncompressed messages, this is the body length. For compressed messages, this is the sum of the
+    /// uncompressed buffer lengths.
+    pub(crate) decoded_len: u64,
+
+    /// Schema of the first schema message.
+    pub(crate) schema: Option<Schema>,
+
+    /// Compressed buffers, see [`check_compressed`](Self::check_compressed).
+    compressed: Vec<CompressedBuffer>,
+}
+
+impl Scan {
+    /// Check that every compressed buffer decompresses to exactly its declared length.
+    ///
+    /// The declared length is written by the untrusted producer, and the decoders of `arrow-ipc` read until the end of
+    /// the compressed stream, no matter which length was declared. So without this check,
+    /// [`decoded_len`](Self::decoded_len) would NOT bound the memory of a decompression bomb. This decompresses through
+    /// a reader that stops right after the declared length and discards the output, i.e. it costs CPU time but no
+    /// memory. Call it AFTER [`decoded_len`](Self::decoded_len) was checked, since that limits the CPU time as well.
+    pub(crate) fn check_compressed(&self, bytes: &[u8]) -> Result<(), ArrowError> {
+        for CompressedBuffer {
+            codec,
+            range,
+            declared,
+        } in &self.compressed
+        {
+            let actual = std::io::copy(
+                &mut decoder(*codec, &bytes[range.clone()])?.take(declared.saturating_add(1)),
+                &mut std::io::sink(),
+            )?;
+            match actual.cmp(declared) {
+                Ordering::Equal => {}
+                Ordering::Greater => {
+                    return Err(ArrowError::ParseError(format!(
+                        "Decompressed buffer exceeds declared length: {declared}"
+                    )));
+                }
+                Ordering::Less => {
+                    return Err(ArrowError::ParseError(format!(
+                        "Decompressed buffer shorter than declared length: got={actual}, declared={declared}"
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Compressed buffer within the IPC data.
+#[derive(Debug)]
+struct CompressedBuffer {
+    /// Codec.
+    codec: CompressionType,
+
+    /// Position of the compressed data, without the length prefix.
+    range: Range<usize>,
+
+    /// Uncompressed length, as declared by the length prefix.
+    declared: u64,
+}
+
+/// Scan IPC data.
+///
+/// Fails if there's compressed data and `allow_compression` is not set.
+pub(crate) fn scan(bytes: &[u8], allow_compression: bool) -> Result<Scan, ArrowError> {
     let mut reader = Cursor::new(bytes);
+    let mut scan = Scan::default();
 
     loop {
         let Some(meta_len) = read_meta_len(&mut reader)? else {
@@ -23,23 +103,58 @@ pub(crate) fn detect_compressed_data(bytes: &[u8]) -> Result<(), ArrowError> {
             ArrowError::ParseError(format!("Unable to get root as message: {err:?}"))
         })?;
 
+        let body_len = msg.bodyLength();
+        if body_len < 0 {
+            return Err(ArrowError::ParseError(format!(
+                "Invalid body length: {body_len}"
+            )));
+        }
+        let body_start = reader.position() as usize;
+        let body = usize::try_from(body_len)
+            .ok()
+            .and_then(|body_len| bytes.get(body_start..body_start.checked_add(body_len)?));
+        let mut decoded_len = body_len.unsigned_abs();
+
         match msg.header_type() {
             MessageHeader::Schema => {
                 // never compressed
+                if scan.schema.is_none()
+                    && let Some(schema) = msg.header_as_schema()
+                {
+                    scan.schema = Some(fb_to_schema(schema));
+                }
             }
             MessageHeader::DictionaryBatch => {
                 if let Some(batch) = msg.header_as_dictionary_batch()
                     && let Some(batch) = batch.data()
                     && let Some(compression) = batch.compression()
                 {
-                    return Err(compression_err("dictionary batch", compression));
+                    if !allow_compression {
+                        return Err(compression_err("dictionary batch", compression));
+                    }
+                    decoded_len = uncompressed_len(
+                        batch,
+                        body,
+                        body_start,
+                        compression.codec(),
+                        &mut scan.compressed,
+                    )?;
                 }
             }
             MessageHeader::RecordBatch => {
                 if let Some(batch) = msg.header_as_record_batch()
                     && let Some(compression) = batch.compression()
                 {
-                    return Err(compression_err("record batch", compression));
+                    if !allow_compression {
+                        return Err(compression_err("record batch", compression));
+                    }
+                    decoded_len = uncompressed_len(
+                        batch,
+                        body,
+                        body_start,
+                        compression.codec(),
+                        &mut scan.compressed,
+                    )?;
                 }
             }
             x => {
@@ -49,16 +164,11 @@ pub(crate) fn detect_compressed_data(bytes: &[u8]) -> Result<(), ArrowError> {
             }
         }
 
-        let body_len = msg.bodyLength();
-        if body_len < 0 {
-            return Err(ArrowError::ParseError(format!(
-                "Invalid body length: {body_len}"
-            )));
-        }
         reader.seek_relative(body_len)?;
+        scan.decoded_len = scan.decoded_len.saturating_add(decoded_len);
     }
 
-    Ok(())
+    Ok(scan)
 }
 
 /// Read the metadata length for the next message from the underlying stream.
@@ -106,6 +216,92 @@ fn read_meta_len(reader: &mut Cursor<&[u8]>) -> Result<Option<usize>, ArrowError
     Ok(Some(meta_len))
 }
 
+/// Compute the uncompressed length of all buffers of a compressed batch.
+///
+/// Every compressed buffer starts with its uncompressed length as a 64-bit little-endian integer. The special value
+/// `-1` signals that the buffer was stored uncompressed. See
+/// <https://arrow.apache.org/docs/format/Columnar.html#compression>.
+///
+/// Buffers that are actually compressed are added to `compressed`, `body_start` is the position of the body within the
+/// IPC data.
+fn uncompressed_len(
+    batch: IpcRecordBatch<'_>,
+    body: Option<&[u8]>,
+    body_start: usize,
+    codec: CompressionType,
+    compressed: &mut Vec<CompressedBuffer>,
+) -> Result<u64, ArrowError> {
+    let Some(body) = body else {
+        return Err(ArrowError::ParseError("Truncated message body".to_owned()));
+    };
+    let Some(buffers) = batch.buffers() else {
+        return Ok(0);
+    };
+
+    let mut total = 0u64;
+    for buffer in buffers {
+        let range = usize::try_from(buffer.offset())
+            .ok()
+            .zip(usize::try_from(buffer.length()).ok())
+            .and_then(|(offset, length)| {
+                let range = offset..offset.checked_add(length)?;
+                body.get(range.clone()).is_some().then_some(range)
+            })
+            .ok_or_else(|| {
+                ArrowError::ParseError(format!(
+                    "Buffer out of bounds: offset={}, length={}",
+                    buffer.offset(),
+                    buffer.length()
+                ))
+            })?;
+        let data = &body[range.clone()];
+        if data.is_empty() {
+            continue;
+        }
+        let Some((prefix, rest)) = data.split_first_chunk::<8>() else {
+            return Err(ArrowError::ParseError(format!(
+                "Compressed buffer too short: {}",
+                data.len()
+            )));
+        };
+        let len = match i64::from_le_bytes(*prefix) {
+            -1 => rest.len() as u64,
+            len => {
+                let len = u64::try_from(len).map_err(|_| {
+                    ArrowError::ParseError(format!("Invalid uncompressed buffer length: {len}"))
+                })?;
+                compressed.push(CompressedBuffer {
+                    codec,
+                    range: body_start + range.start + 8..body_start + range.end,
+                    declared: len,
+                });
+                len
+            }
+        };
+        total = total.saturating_add(len);
+    }
+
+    Ok(total)
+}
+
+/// Create streaming decoder for the given codec.
+#[cfg_attr(
+    not(any(feature = "lz4", feature = "zstd")),
+    expect(unused_variables, reason = "no codec enabled")
+)]
+fn decoder(codec: CompressionType, data: &[u8]) -> Result<Box<dyn Read + '_>, ArrowError> {
+    match codec {
+        #[cfg(feature = "lz4")]
+        CompressionType::LZ4_FRAME => Ok(Box::new(lz4_flex::frame::FrameDecoder::new(data))),
+        #[cfg(feature = "zstd")]
+        CompressionType::ZSTD => Ok(Box::new(zstd::Decoder::with_buffer(data)?)),
+        other => Err(ArrowError::NotYetImplemented(format!(
+            "compression not supported by this build: {}",
+            other.variant_name().unwrap_or("<unknown>")
+        ))),
+    }
+}
+
 /// Generate error for encountered compression.
 fn compression_err(what: &'static str, compression: BodyCompression<'_>) -> ArrowError {
     ArrowError::IpcError(format!(
@@ -2,55 +2,170 @@
 //!
 //! This uses the [Arrow IPC] schema.
 //!
+//! # Features
+//! - `lz4`: Support [`Compression::Lz4Frame`].
+//! - `zstd`: Support [`Compression::Zstd`].
+//!
 //!
 //! [Arrow IPC]: https://arrow.apache.org/docs/format/IPC.html
 use std::{io::Cursor, sync::Arc};
 
 use arrow::{
     array::{Array, ArrayRef, RecordBatch},
-    datatypes::{DataType, Field, Schema},
+    buffer::Buffer,
+    datatypes::{DataType, Field, Schema, SchemaRef},
     error::ArrowError,
     ipc::{
+        CompressionType,
         convert::{IpcSchemaEncoder, fb_to_schema},
-        reader::StreamReader,
+        reader::{StreamDecoder, StreamReader},
         root_as_schema,
-        writer::StreamWriter,
+        writer::{IpcWriteOptions, StreamWriter},
     },
 };
 
+// only used to select compression codecs
+use arrow_ipc as _;
+
 // unused-crate-dependencies false positives
 #[cfg(test)]
+use gungraun as _;
+#[cfg(test)]
 use insta as _;
 
 mod compression_check;
 
+/// Compression codec for the array buffers within the IPC messages.
+///
+/// Which codecs are available depends on the crate features, see [`Compression::supported`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Compression {
+    /// [LZ4 frame format](https://github.com/lz4/lz4/blob/dev/doc/lz4_Frame_format.md).
+    Lz4Frame,
+
+    /// [Zstandard](https://facebook.github.io/zstd/).
+    Zstd,
+}
+
+impl Compression {
+    /// Codecs supported by this build.
+    pub fn supported() -> &'static [Self] {
+        &[
+            #[cfg(feature = "lz4")]
+            Self::Lz4Frame,
+            #[cfg(feature = "zstd")]
+            Self::Zstd,
+        ]
+    }
+
+    /// Returns `true` if this codec is supported by this build.
+    pub fn is_supported(self) -> bool {
+        Self::supported().contains(&self)
+    }
+}
+
+impl From<Compression> for CompressionType {
+    fn from(compression: Compression) -> Self {
+        match compression {
+            Compression::Lz4Frame => Self::LZ4_FRAME,
+            Compression::Zstd => Self::ZSTD,
+        }
+    }
+}
+
 /// Convert an [`Array`] to bytes.
 ///
 /// This is done by encoding writing this as a [`RecordBatch`] with a single [`Field`].
 ///
 /// See [`bytes2array`] for the reverse method.
 pub fn array2bytes(array: ArrayRef) -> Vec<u8> {
-    let buffer = Vec::new();
+    batch2bytes(&array2batch(array))
+}
+
+/// Convert an [`Array`] to bytes using the given [`Compression`].
+///
+/// See [`array2bytes`] for the format and [`buffer2array`] with
+/// [`allow_compression`](DecodeOptions::allow_compression) for the reverse method.
+pub fn array2bytes_compressed(
+    array: ArrayRef,
+    compression: Compression,
+) -> Result<Vec<u8>, ArrowError> {
+    batch2bytes_compressed(&array2batch(array), compression)
+}
 
+/// Wrap [`Array`] into a [`RecordBatch`] with a single [`Field`].
+fn array2batch(array: ArrayRef) -> RecordBatch {
     let schema = Arc::new(Schema::new(vec![Field::new(
         "a",
         array.data_type().clone(),
         array.null_count() > 0,
     )]));
-    let mut writer = StreamWriter::try_new(buffer, &schema).expect("writing to buffer never fails");
-
-    let batch = RecordBatch::try_new(schema, vec![array]).expect("batch always valid");
-    writer.write(&batch).expect("writing to buffer never fails");
-
-    writer.finish().expect("writing to buffer never fails");
-    writer.into_inner().expect("writing to buffer never fails")
+    RecordBatch::try_new(schema, vec![array]).expect("batch always valid")
 }
 
 /// Decodes [`Array`] from bytes.
 ///
-/// See [`array2bytes`] for the reverse method and the format description.
+/// See [`array2bytes`] for the reverse method and the format description. Use [`buffer2array`] to avoid copying the
+/// array buffers.
 pub fn bytes2array(bytes: &[u8]) -> Result<ArrayRef, ArrowError> {
-    compression_check::detect_compressed_data(bytes)?;
+    single_column(bytes2batch(bytes)?)
+}
+
+/// Decodes [`Array`] from a [`Buffer`] without copying the array buffers.
+///
+/// See [`buffer2batch`] for details and [`array2bytes`] for the reverse method and the format description.
+pub fn buffer2array(buffer: Buffer, options: &DecodeOptions) -> Result<ArrayRef, ArrowError> {
+    single_column(buffer2batch(buffer, options)?)
+}
+
+/// Convert a [`RecordBatch`] to bytes.
+///
+/// This is encoded as an [Arrow IPC] stream that contains the schema and exactly one record batch.
+///
+/// See [`bytes2batch`] for the reverse method.
+///
+///
+/// [Arrow IPC]: https://arrow.apache.org/docs/format/Columnar.html#ipc-streaming-format
+pub fn batch2bytes(batch: &RecordBatch) -> Vec<u8> {
+    encode(batch, IpcWriteOptions::default()).expect("writing to buffer never fails")
+}
+
+/// Convert a [`RecordBatch`] to bytes using the given [`Compression`].
+///
+/// Fails if the codec is not [supported](Compression::supported).
+///
+/// See [`batch2bytes`] for the format and [`buffer2batch`] with
+/// [`allow_compression`](DecodeOptions::allow_compression) for the reverse method.
+pub fn batch2bytes_compressed(
+    batch: &RecordBatch,
+    compression: Compression,
+) -> Result<Vec<u8>, ArrowError> {
+    if !compression.is_supported() {
+        return Err(ArrowError::NotYetImplemented(format!(
+            "compression not supported by this build: {compression:?}"
+        )));
+    }
+    let options = IpcWriteOptions::default().try_with_compression(Some(compression.into()))?;
+    encode(batch, options)
+}
+
+/// Encode [`RecordBatch`] as IPC stream.
+fn encode(batch: &RecordBatch, options: IpcWriteOptions) -> Result<Vec<u8>, ArrowError> {
+    let buffer = Vec::new();
+
+    let mut writer = StreamWriter::try_new_with_options(buffer, &batch.schema(), options)?;
+    writer.write(batch)?;
+
+    writer.finish()?;
+    writer.into_inner()
+}
+
+/// Decodes [`RecordBatch`] from bytes.
+///
+/// See [`batch2bytes`] for the reverse method and the format description. Use [`buffer2batch`] to avoid copying the
+/// array buffers and to apply limits.
+pub fn bytes2batch(bytes: &[u8]) -> Result<RecordBatch, ArrowError> {
+    compression_check::scan(bytes, false)?;
 
     let cursor = Cursor::new(bytes);
     let mut reader = StreamReader::try_new(cursor, None)?;
@@ -60,18 +175,162 @@ pub fn bytes2array(bytes: &[u8]) -> Result<ArrayRef, ArrowError> {
         ));
     };
     let batch = res?;
-    let columns = batch.columns();
-    if columns.len() != 1 {
-        return Err(ArrowError::InvalidArgumentError("invalid batch".to_owned()));
-    }
-    let array = Arc::clone(&columns[0]);
     if reader.next().is_some()
         || !reader.is_finished()
         || (reader.get_ref().position() as usize != bytes.len())
     {
         return Err(ArrowError::InvalidArgumentError("trailing data".to_owned()));
     }
-    Ok(array)
+    Ok(batch)
+}
+
+/// End-of-stream marker of the IPC stream format.
+const END_OF_STREAM: [u8; 8] = [0xff, 0xff, 0xff, 0xff, 0, 0, 0, 0];
+
+/// Options for [`buffer2batch`] and [`buffer2array`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DecodeOptions {
+    /// Maximum accumulated size of all IPC message bodies, in bytes.
+    ///
+    /// The bodies contain the array buffers. This is checked BEFORE any buffer is decoded. For compressed bodies, the
+    /// uncompressed buffer lengths are used instead, so this is an upper bound for the memory that the decoded arrays
+    /// can reference. Compressed buffers that do NOT decompress to exactly their declared length are rejected.
+    pub max_decoded_bytes: Option<usize>,
+
+    /// Maximum number of columns.
+    ///
+    /// This is checked BEFORE any buffer is decoded.
+    pub max_columns: Option<usize>,
+
+    /// Expected schema.
+    ///
+    /// If set, the field names, data types, and nullability of the decoded schema must match. Metadata is ignored.
+    /// This is checked BEFORE any buffer is decoded.
+    pub schema: Option<SchemaRef>,
+
+    /// Fail if an array buffer is not aligned to its data type instead of copying it into an aligned allocation.
+    pub require_alignment: bool,
+
+    /// Accept compressed array buffers.
+    ///
+    /// Only the [supported](Compression::supported) codecs can be decoded. Compressed buffers are decompressed into
+    /// new allocations, i.e. they are NOT zero-copy.
+    pub allow_compression: bool,
+}
+
+/// Decodes [`RecordBatch`] from a [`Buffer`] without copying the array buffers.
+///
+/// The buffers of the returned arrays reference the passed memory, which can be created from a [`Vec<u8>`] or a
+/// [`Bytes`](https://docs.rs/bytes/latest/bytes/struct.Bytes.html) without copying. Buffers that are not aligned to
+/// their data type are copied unless [`require_alignment`](DecodeOptions::require_alignment) is set.
+///
+/// All buffers are fully validated, e.g. offsets, UTF-8 strings, and dictionary keys. There is intentionally NO
+/// option to skip the validation, since this is used for data that crosses the security boundary.
+///
+/// See [`batch2bytes`] for the reverse method and the format description.
+pub fn buffer2batch(buffer: Buffer, options: &DecodeOptions) -> Result<RecordBatch, ArrowError> {
+    let DecodeOptions {
+        max_decoded_bytes,
+        max_columns,
+        schema,
+        require_alignment,
+        allow_compression,
+    } = options;
+
+    let scan = compression_check::scan(&buffer, *allow_compression)?;
+    if let Some(limit) = max_decoded_bytes
+        && scan.decoded_len > *limit as u64
+    {
+        return Err(ArrowError::MemoryError(format!(
+            "decoded size: got={}, limit={limit}",
+            scan.decoded_len
+        )));
+    }
+    if let Some(actual) = &scan.schema {
+        check_schema(actual, *max_columns, schema.as_ref())?;
+    }
+    scan.check_compressed(&buffer)?;
+    if !buffer.ends_with(&END_OF_STREAM) {
+        return Err(ArrowError::InvalidArgumentError(
+            "missing end of stream".to_owned(),
+        ));
+    }
+
+    let mut buffer = buffer;
+    let mut decoder = StreamDecoder::new().with_require_alignment(*require_alignment);
+    let Some(batch) = decoder.decode(&mut buffer)? else {
+        return Err(ArrowError::InvalidArgumentError(
+            "no record batch found".to_owned(),
+        ));
+    };
+
+    // the remaining data must only be the end-of-stream marker
+    match decoder.decode(&mut buffer) {
+        Ok(None) if buffer.is_empty() => {}
+        _ => {
+            return Err(ArrowError::InvalidArgumentError("trailing data".to_owned()));
+        }
+    }
+    decoder.finish()?;
+
+    Ok(batch)
+}
+
+/// Check decoded schema against [`DecodeOptions`].
+fn check_schema(
+    actual: &Schema,
+    max_columns: Option<usize>,
+    expected: Option<&SchemaRef>,
+) -> Result<(), ArrowError> {
+    let n_columns = actual.fields().len();
+    if let Some(limit) = max_columns
+        && n_columns > limit
+    {
+        return Err(ArrowError::SchemaError(format!(
+            "number of columns: got={n_columns}, limit={limit}"
+        )));
+    }
+
+    if let Some(expected) = expected {
+        let matches = actual.fields().len() == expected.fields().len()
+            && actual.fields().iter().zip(expected.fields()).all(|(a, e)| {
+                a.name() == e.name()
+                    && a.data_type() == e.data_type()
+                    && a.is_nullable() == e.is_nullable()
+            });
+        if !matches {
+            return Err(ArrowError::SchemaError(format!(
+                "schema mismatch: expected {expected}, got {actual}"
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Extract the only column of a [`RecordBatch`] as produced by [`array2bytes`].
+fn single_column(batch: RecordBatch) -> Result<ArrayRef, ArrowError> {
+    let columns = batch.columns();
+    if columns.len() != 1 {
+        return Err(ArrowError::InvalidArgumentError("invalid batch".to_owned()));
+    }
+    Ok(Arc::clone(&columns[0]))
+}
+
+/// Checksum of serialized bytes, e.g. produced by [`array2bytes`].
+///
+/// This is meant to detect accidental corruption during transfer, it is NOT a cryptographic hash. The algorithm is
+/// 64-bit [FNV-1a] which is stable across platforms and compiler versions.
+///
+///
+/// [FNV-1a]: https://en.wikipedia.org/wiki/Fowler%E2%80%93Noll%E2%80%93Vo_hash_function
+pub fn checksum(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    bytes.iter().fold(OFFSET_BASIS, |hash, b| {
+        (hash ^ u64::from(*b)).wrapping_mul(PRIME)
+    })
 }
 
 /// Encodes [`DataType`] as bytes.
@@ -1,5 +1,9 @@
-// Docs are not strictly required for tests.
-#![expect(missing_docs)]
+#![expect(
+    // Docs are not strictly required for tests.
+    missing_docs,
+    // unused-crate-dependencies false positives
+    unused_crate_dependencies,
+)]
 
 use std::sync::Arc;
 
@@ -7,6 +11,7 @@ use arrow::{
     array::{
         ArrayRef, Int64Array, ListArray, RecordBatch, RecordBatchOptions, StringDictionaryBuilder,
     },
+    buffer::Buffer,
     datatypes::{DataType, Field, Int32Type, Schema},
     error::ArrowError,
     ipc::{
@@ -14,7 +19,10 @@ use arrow::{
         writer::{IpcWriteOptions, StreamWriter},
     },
 };
-use datafusion_udf_wasm_arrow2bytes::{array2bytes, bytes2array};
+use datafusion_udf_wasm_arrow2bytes::{
+    Compression, DecodeOptions, array2bytes, array2bytes_compressed, batch2bytes,
+    batch2bytes_compressed, buffer2array, buffer2batch, bytes2array, bytes2batch, checksum,
+};
 
 #[test]
 fn test_roundtrip() {
@@ -22,6 +30,19 @@ fn test_roundtrip() {
     roundtrip(string_dict_array());
 }
 
+#[test]
+fn test_checksum() {
+    // reference values of 64-bit FNV-1a
+    assert_eq!(checksum(b""), 0xcbf2_9ce4_8422_2325);
+    assert_eq!(checksum(b"a"), 0xaf63_dc4c_8601_ec8c);
+    assert_eq!(checksum(b"foobar"), 0x8594_4171_f739_67e8);
+
+    let bytes = array2bytes(int64_array());
+    let mut corrupted = bytes.clone();
+    corrupted[bytes.len() / 2] ^= 1;
+    assert_ne!(checksum(&bytes), checksum(&corrupted));
+}
+
 #[test]
 fn test_err_invalid_bytes_1() {
     let err = bytes2array(b"foobar").unwrap_err();
@@ -148,11 +169,217 @@ fn test_err_compression() {
     );
 }
 
+#[test]
+fn test_buffer2array_zero_copy() {
+    let buffer = Buffer::from(array2bytes(int64_array()));
+    let range = buffer.as_ptr_range();
+
+    let array = buffer2array(buffer, &DecodeOptions::default()).unwrap();
+    assert_eq!(&array, &int64_array());
+
+    let values = array.to_data().buffers()[0].as_ptr();
+    assert!(range.contains(&values));
+}
+
+#[test]
+fn test_buffer2array_budget() {
+    let bytes = array2bytes(string_dict_array());
+    let budget = bytes.len();
+
+    let options = |max_decoded_bytes| DecodeOptions {
+        max_decoded_bytes: Some(max_decoded_bytes),
+        ..Default::default()
+    };
+    buffer2array(Buffer::from(bytes.clone()), &options(budget)).unwrap();
+
+    // the exact size depends on the IPC padding
+    let err = buffer2array(Buffer::from(bytes), &options(10))
+        .unwrap_err()
+        .to_string();
+    assert!(
+        err.starts_with("Memory error: decoded size: got="),
+        "unexpected error: {err}",
+    );
+    assert!(err.ends_with(", limit=10"), "unexpected error: {err}");
+}
+
+#[test]
+fn test_buffer2array_err_compression() {
+    let bytes = compressed_bytes(int64_array(), CompressionType::ZSTD);
+    let err = buffer2array(Buffer::from(bytes), &DecodeOptions::default()).unwrap_err();
+    insta::assert_snapshot!(
+        err,
+        @"Ipc error: IPC record batch is compressed using ZSTD, but compressed data MUST NOT cross the security boundary. If you want to handle compressed data, please decompress it within the guest.",
+    );
+}
+
+#[test]
+fn test_compressed_roundtrip() {
+    assert_eq!(
+        Compression::supported(),
+        &[Compression::Lz4Frame, Compression::Zstd],
+    );
+
+    let options = DecodeOptions {
+        allow_compression: true,
+        ..Default::default()
+    };
+    for compression in [Compression::Lz4Frame, Compression::Zstd] {
+        for array in [int64_array(), string_dict_array()] {
+            let bytes = array2bytes_compressed(Arc::clone(&array), compression).unwrap();
+            let array2 = buffer2array(Buffer::from(bytes.clone()), &options).unwrap();
+            assert_eq!(&array, &array2);
+
+            // compression must be allowed explicitly
+            bytes2array(&bytes).unwrap_err();
+            buffer2array(Buffer::from(bytes), &DecodeOptions::default()).unwrap_err();
+        }
+
+        let batch = multi_column_batch();
+        let bytes = batch2bytes_compressed(&batch, compression).unwrap();
+        assert_eq!(buffer2batch(Buffer::from(bytes), &options).unwrap(), batch);
+    }
+}
+
+#[test]
+fn test_buffer2array_compressed_budget() {
+    // compresses well
+    let array = Arc::new(Int64Array::from_value(42, 10_000)) as ArrayRef;
+    let bytes = array2bytes_compressed(array, Compression::Lz4Frame).unwrap();
+    assert!(bytes.len() < 10_000);
+
+    // the budget applies to the uncompressed size, i.e. values plus validity bitmap
+    let options = DecodeOptions {
+        max_decoded_bytes: Some(10_000),
+        allow_compression: true,
+        ..Default::default()
+    };
+    let err = buffer2array(Buffer::from(bytes), &options).unwrap_err();
+    insta::assert_snapshot!(
+        err,
+        @"Memory error: decoded size: got=81250, limit=10000",
+    );
+}
+
+#[test]
+fn test_buffer2array_compressed_lying_length() {
+    let array = Arc::new(Int64Array::from_value(42, 10_000)) as ArrayRef;
+    let options = DecodeOptions {
+        max_decoded_bytes: Some(10_000),
+        allow_compression: true,
+        ..Default::default()
+    };
+
+    for compression in [Compression::Lz4Frame, Compression::Zstd] {
+        let mut bytes = array2bytes_compressed(Arc::clone(&array), compression).unwrap();
+
+        // declare a tiny uncompressed length that fits into the budget
+        let prefix = 80_000i64.to_le_bytes();
+        assert_eq!(bytes.windows(8).filter(|w| *w == prefix).count(), 1);
+        let pos = bytes.windows(8).position(|w| w == prefix).unwrap();
+        bytes[pos..pos + 8].copy_from_slice(&8i64.to_le_bytes());
+
+        let err = buffer2array(Buffer::from(bytes), &options).unwrap_err();
+        insta::allow_duplicates! {
+            insta::assert_snapshot!(
+                err,
+                @"Parser error: Decompressed buffer exceeds declared length: 8",
+            );
+        }
+    }
+}
+
+#[test]
+fn test_buffer2array_err_missing_end_of_stream() {
+    let schema = Arc::new(Schema::new(vec![Field::new("a", DataType::Int64, true)]));
+    let batch = RecordBatch::try_new(Arc::clone(&schema), vec![int64_array()]).unwrap();
+    let mut writer =
+        StreamWriter::try_new(Vec::new(), &schema).expect("writing to buffer never fails");
+    writer.write(&batch).unwrap();
+    // `into_inner` would write the end-of-stream marker
+    let bytes = writer.get_ref().clone();
+
+    let err = buffer2array(Buffer::from(bytes), &DecodeOptions::default()).unwrap_err();
+    insta::assert_snapshot!(
+        err,
+        @"Invalid argument error: missing end of stream",
+    );
+}
+
+#[test]
+fn test_buffer2array_err_two_messages() {
+    let mut bytes = array2bytes(Arc::new(Int64Array::new_null(0)));
+    let bytes2 = bytes.clone();
+    bytes.extend_from_slice(&bytes2);
+    let err = buffer2array(Buffer::from(bytes), &DecodeOptions::default()).unwrap_err();
+    insta::assert_snapshot!(
+        err,
+        @"Invalid argument error: trailing data",
+    );
+}
+
+#[test]
+fn test_batch_roundtrip() {
+    let batch = multi_column_batch();
+    let bytes = batch2bytes(&batch);
+    assert_eq!(bytes2batch(&bytes).unwrap(), batch);
+
+    let options = DecodeOptions {
+        max_columns: Some(2),
+        schema: Some(batch.schema()),
+        ..Default::default()
+    };
+    assert_eq!(buffer2batch(Buffer::from(bytes), &options).unwrap(), batch);
+
+    // single-column batches are arrays
+    let bytes = array2bytes(int64_array());
+    let batch = bytes2batch(&bytes).unwrap();
+    assert_eq!(batch.num_columns(), 1);
+    assert_eq!(batch.column(0), &int64_array());
+}
+
+#[test]
+fn test_buffer2batch_err_max_columns() {
+    let options = DecodeOptions {
+        max_columns: Some(1),
+        ..Default::default()
+    };
+    let err = buffer2batch(Buffer::from(batch2bytes(&multi_column_batch())), &options).unwrap_err();
+    insta::assert_snapshot!(
+        err,
+        @"Schema error: number of columns: got=2, limit=1",
+    );
+}
+
+#[test]
+fn test_buffer2batch_err_schema() {
+    let options = DecodeOptions {
+        schema: Some(Arc::new(Schema::new(vec![
+            Field::new("a", DataType::Int64, true),
+            Field::new(
+                "c",
+                DataType::Dictionary(Box::new(DataType::Int32), Box::new(DataType::Utf8)),
+                true,
+            ),
+        ]))),
+        ..Default::default()
+    };
+    let err = buffer2batch(Buffer::from(batch2bytes(&multi_column_batch())), &options)
+        .unwrap_err()
+        .to_string();
+    assert!(
+        err.starts_with("Schema error: schema mismatch: expected "),
+        "unexpected error: {err}",
+    );
+}
+
 #[track_caller]
 fn roundtrip(array: ArrayRef) {
     let bytes = array2bytes(Arc::clone(&array));
     let array2 = bytes2array(&bytes).unwrap();
     assert_eq!(&array, &array2);
+    let array3 = buffer2array(Buffer::from(bytes), &DecodeOptions::default()).unwrap();
+    assert_eq!(&array, &array3);
 }
 
 /// Create a non-empty int64 array.
@@ -160,6 +387,20 @@ fn int64_array() -> ArrayRef {
     Arc::new(Int64Array::from_iter([Some(1), None, Some(3)]))
 }
 
+/// Create a batch with an int64 and a dict-encoded string column.
+fn multi_column_batch() -> RecordBatch {
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("a", DataType::Int64, true),
+        Field::new(
+            "b",
+            DataType::Dictionary(Box::new(DataType::Int32), Box::new(DataType::Utf8)),
+            true,
+        ),
+    ]));
+    let ints = Arc::new(Int64Array::from_iter([Some(1), None, Some(3), Some(4)])) as ArrayRef;
+    RecordBatch::try_new(schema, vec![ints, string_dict_array()]).unwrap()
+}
+
 /// Create a non-empty dict-encoded string array.
 fn string_dict_array() -> ArrayRef {
     let mut builder = StringDictionaryBuilder::<Int32Type>::new();
@@ -172,6 +413,12 @@ fn string_dict_array() -> ArrayRef {
 
 #[track_caller]
 fn compression_err(array: ArrayRef, compression: CompressionType) -> ArrowError {
+    bytes2array(&compressed_bytes(array, compression)).unwrap_err()
+}
+
+/// Serialize array with compression.
+#[track_caller]
+fn compressed_bytes(array: ArrayRef, compression: CompressionType) -> Vec<u8> {
     let schema = Arc::new(Schema::new(vec![Field::new(
         "a",
         array.data_type().clone(),
@@ -187,7 +434,5 @@ fn compression_err(array: ArrayRef, compression: CompressionType) -> ArrowError
     )
     .expect("writing to buffer never fails");
     writer.write(&batch).unwrap();
-    let bytes = writer.into_inner().unwrap();
-
-    bytes2array(&bytes).unwrap_err()
+    writer.into_inner().unwrap()
 }
@@ -1,5 +1,9 @@
-// Docs are not strictly required for tests.
-#![expect(missing_docs)]
+#![expect(
+    // Docs are not strictly required for tests.
+    missing_docs,
+    // unused-crate-dependencies false positives
+    unused_crate_dependencies,
+)]
 
 use std::sync::Arc;
 
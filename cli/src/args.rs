@@ -0,0 +1,218 @@
+//! Command line arguments.
+
+use std::path::PathBuf;
+
+/// Usage information.
+pub(crate) const USAGE: &str = "\
+Usage: udf-wasm-cli [OPTIONS] <LANG> <SOURCE>
+
+Instantiates UDFs from the SOURCE file, prints their signatures, and type-checks them against sample data.
+
+Arguments:
+  <LANG>    Language of the source code. `expr` and `python` use bundled components, every other language requires `--wasm`.
+  <SOURCE>  Path to the source code.
+
+Options:
+  --wasm <PATH>          Use WASM component from PATH instead of the bundled one.
+  --max-udfs <N>         Maximum number of UDFs that the source may define.
+  --stderr-bytes <N>     Maximum number of stderr bytes that are captured from the guest.
+  --env <KEY=VALUE>      Set environment variable for the guest. Can be used multiple times.
+  --rows <N>             Number of sample rows. Defaults to 10.
+  --bench <ITERATIONS>   Benchmark invocation throughput with the given number of iterations.
+  -h, --help             Print this help.";
+
+/// Default for [`Args::rows`].
+const DEFAULT_ROWS: usize = 10;
+
+/// Parsed command line arguments.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct Args {
+    /// Language.
+    pub(crate) lang: String,
+
+    /// Path to source code.
+    pub(crate) source: PathBuf,
+
+    /// Path to WASM component that overrides the bundled one.
+    pub(crate) wasm: Option<PathBuf>,
+
+    /// Maximum number of UDFs.
+    pub(crate) max_udfs: Option<usize>,
+
+    /// Maximum number of captured stderr bytes.
+    pub(crate) stderr_bytes: Option<usize>,
+
+    /// Environment variables.
+    pub(crate) env: Vec<(String, String)>,
+
+    /// Number of sample rows.
+    pub(crate) rows: usize,
+
+    /// Number of benchmark iterations.
+    pub(crate) bench: Option<usize>,
+}
+
+/// Reason why [`Args::parse`] did not produce arguments.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum ArgsError {
+    /// Help was requested.
+    Help,
+
+    /// Arguments are invalid.
+    Invalid(String),
+}
+
+impl Args {
+    /// Parse arguments, excluding the executable name.
+    pub(crate) fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, ArgsError> {
+        let mut args = args.into_iter();
+        let mut positional = vec![];
+        let mut wasm = None;
+        let mut max_udfs = None;
+        let mut stderr_bytes = None;
+        let mut env = vec![];
+        let mut rows = DEFAULT_ROWS;
+        let mut bench = None;
+
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| ArgsError::Invalid(format!("`{arg}` requires a value")))
+            };
+
+            match arg.as_str() {
+                "-h" | "--help" => return Err(ArgsError::Help),
+                "--wasm" => wasm = Some(PathBuf::from(value()?)),
+                "--max-udfs" => max_udfs = Some(parse_number(&arg, &value()?)?),
+                "--stderr-bytes" => stderr_bytes = Some(parse_number(&arg, &value()?)?),
+                "--env" => {
+                    let kv = value()?;
+                    let Some((k, v)) = kv.split_once('=') else {
+                        return Err(ArgsError::Invalid(format!(
+                            "`--env` expects KEY=VALUE but got `{kv}`"
+                        )));
+                    };
+                    env.push((k.to_owned(), v.to_owned()));
+                }
+                "--rows" => rows = parse_number(&arg, &value()?)?,
+                "--bench" => bench = Some(parse_number(&arg, &value()?)?),
+                s if s.starts_with('-') => {
+                    return Err(ArgsError::Invalid(format!("unknown option: `{s}`")));
+                }
+                _ => positional.push(arg),
+            }
+        }
+
+        let [lang, source] = <[String; 2]>::try_from(positional).map_err(|positional| {
+            ArgsError::Invalid(format!(
+                "expected 2 positional arguments but got {}",
+                positional.len()
+            ))
+        })?;
+
+        Ok(Self {
+            lang,
+            source: PathBuf::from(source),
+            wasm,
+            max_udfs,
+            stderr_bytes,
+            env,
+            rows,
+            bench,
+        })
+    }
+}
+
+/// Parse numeric option value.
+fn parse_number(arg: &str, value: &str) -> Result<usize, ArgsError> {
+    value
+        .parse()
+        .map_err(|_| ArgsError::Invalid(format!("`{arg}` expects a number but got `{value}`")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            parse(&["python", "udf.py"]).unwrap(),
+            Args {
+                lang: "python".to_owned(),
+                source: PathBuf::from("udf.py"),
+                wasm: None,
+                max_udfs: None,
+                stderr_bytes: None,
+                env: vec![],
+                rows: DEFAULT_ROWS,
+                bench: None,
+            },
+        );
+
+        assert_eq!(
+            parse(&[
+                "--wasm",
+                "guest.wasm",
+                "rust",
+                "--max-udfs",
+                "3",
+                "--stderr-bytes",
+                "100",
+                "--env",
+                "A=b=c",
+                "--env",
+                "D=",
+                "--rows",
+                "7",
+                "--bench",
+                "1000",
+                "udf.rs",
+            ])
+            .unwrap(),
+            Args {
+                lang: "rust".to_owned(),
+                source: PathBuf::from("udf.rs"),
+                wasm: Some(PathBuf::from("guest.wasm")),
+                max_udfs: Some(3),
+                stderr_bytes: Some(100),
+                env: vec![
+                    ("A".to_owned(), "b=c".to_owned()),
+                    ("D".to_owned(), "".to_owned()),
+                ],
+                rows: 7,
+                bench: Some(1000),
+            },
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(parse(&["python", "--help"]).unwrap_err(), ArgsError::Help);
+        assert_eq!(
+            parse(&["python"]).unwrap_err(),
+            ArgsError::Invalid("expected 2 positional arguments but got 1".to_owned()),
+        );
+        assert_eq!(
+            parse(&["python", "udf.py", "--rows"]).unwrap_err(),
+            ArgsError::Invalid("`--rows` requires a value".to_owned()),
+        );
+        assert_eq!(
+            parse(&["python", "udf.py", "--rows", "many"]).unwrap_err(),
+            ArgsError::Invalid("`--rows` expects a number but got `many`".to_owned()),
+        );
+        assert_eq!(
+            parse(&["python", "udf.py", "--env", "FOO"]).unwrap_err(),
+            ArgsError::Invalid("`--env` expects KEY=VALUE but got `FOO`".to_owned()),
+        );
+        assert_eq!(
+            parse(&["python", "udf.py", "--foo"]).unwrap_err(),
+            ArgsError::Invalid("unknown option: `--foo`".to_owned()),
+        );
+    }
+
+    /// Parse string slices.
+    fn parse(args: &[&str]) -> Result<Args, ArgsError> {
+        Args::parse(args.iter().map(|s| (*s).to_owned()))
+    }
+}
@@ -0,0 +1,231 @@
+//! Validate and benchmark UDF payloads.
+//!
+//! Run with `--help` for usage information.
+
+use std::{
+    process::ExitCode,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use arrow::datatypes::{DataType, Field};
+use datafusion_common::{DataFusionError, Result as DataFusionResult, config::ConfigOptions};
+use datafusion_execution::memory_pool::{MemoryPool, UnboundedMemoryPool};
+use datafusion_expr::{
+    ColumnarValue, ScalarFunctionArgs, ScalarUDFImpl, async_udf::AsyncScalarUDFImpl,
+};
+use datafusion_udf_wasm_host::{
+    CompilationFlags, WasmComponentPrecompiled, WasmPermissions, WasmScalarUdf,
+};
+use tokio::runtime::Handle;
+
+use crate::args::{Args, ArgsError, USAGE};
+
+mod args;
+mod sample;
+
+fn main() -> ExitCode {
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(ArgsError::Help) => {
+            println!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Err(ArgsError::Invalid(msg)) => {
+            eprintln!("{msg}\n\n{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .expect("create tokio runtime");
+
+    match rt.block_on(run(args)) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Create UDFs and check them.
+///
+/// Returns `false` if any type check failed.
+async fn run(args: Args) -> DataFusionResult<bool> {
+    let Args {
+        lang,
+        source,
+        wasm,
+        max_udfs,
+        stderr_bytes,
+        env,
+        rows,
+        bench,
+    } = args;
+
+    let code = std::fs::read_to_string(&source).map_err(|e| {
+        DataFusionError::IoError(e).context(format!("read source from `{}`", source.display()))
+    })?;
+
+    let wasm_binary: Arc<[u8]> = match (wasm, lang.as_str()) {
+        (Some(path), _) => std::fs::read(&path)
+            .map_err(|e| {
+                DataFusionError::IoError(e)
+                    .context(format!("read WASM component from `{}`", path.display()))
+            })?
+            .into(),
+        (None, "expr") => datafusion_udf_wasm_bundle::BIN_EXPR.into(),
+        (None, "python") => datafusion_udf_wasm_bundle::BIN_PYTHON.into(),
+        (None, lang) => {
+            return Err(DataFusionError::Plan(format!(
+                "no bundled component for language `{lang}`, use `--wasm`"
+            )));
+        }
+    };
+    let component =
+        WasmComponentPrecompiled::compile(wasm_binary, &CompilationFlags::default()).await?;
+
+    let mut permissions = WasmPermissions::new();
+    if let Some(limit) = max_udfs {
+        permissions = permissions.with_max_udfs(limit);
+    }
+    if let Some(limit) = stderr_bytes {
+        permissions = permissions.with_stderr_bytes(limit);
+    }
+    for (k, v) in env {
+        permissions = permissions.with_env(k, v);
+    }
+
+    let memory_pool: Arc<dyn MemoryPool> = Arc::new(UnboundedMemoryPool::default());
+    let udfs = WasmScalarUdf::new(
+        &component,
+        &permissions,
+        Handle::current(),
+        &memory_pool,
+        code,
+    )
+    .await?;
+    println!("found {} UDF(s)", udfs.len());
+
+    let mut ok = true;
+    for udf in &udfs {
+        let signature = udf.signature();
+        println!();
+        println!("{}", udf.name());
+        println!("  signature:  {:?}", signature.type_signature);
+        println!("  volatility: {:?}", signature.volatility);
+
+        let all_arg_types = sample::arg_types(&signature.type_signature);
+        if all_arg_types.is_empty() {
+            println!("  no concrete argument types, skipping type check");
+            continue;
+        }
+
+        for arg_types in all_arg_types {
+            let display = arg_types
+                .iter()
+                .map(|t| t.to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            match check(udf, &arg_types, rows).await {
+                Ok(return_type) => {
+                    println!("  ({display}) -> {return_type}: ok");
+                }
+                Err(e) => {
+                    println!("  ({display}): FAILED\n{e}");
+                    ok = false;
+                    continue;
+                }
+            }
+
+            if let Some(iterations) = bench {
+                let elapsed = benchmark(udf, &arg_types, rows, iterations).await?;
+                let total_rows = iterations * rows;
+                println!(
+                    "    {iterations} invocations with {rows} rows in {elapsed:?}: {:.0} rows/s",
+                    total_rows as f64 / elapsed.as_secs_f64()
+                );
+            }
+        }
+    }
+
+    Ok(ok)
+}
+
+/// Type-check UDF with sample data.
+///
+/// Returns the return type.
+async fn check(
+    udf: &WasmScalarUdf,
+    arg_types: &[DataType],
+    rows: usize,
+) -> DataFusionResult<DataType> {
+    let return_type = udf.return_type(arg_types)?;
+    let array = udf
+        .invoke_async_with_args(sample_args(udf, arg_types, &return_type, rows)?)
+        .await?
+        .into_array(rows)?;
+
+    if array.data_type() != &return_type {
+        return Err(DataFusionError::Execution(format!(
+            "UDF returned {} but declared {return_type}",
+            array.data_type()
+        )));
+    }
+    if array.len() != rows {
+        return Err(DataFusionError::Execution(format!(
+            "UDF returned {} rows but got {rows}",
+            array.len()
+        )));
+    }
+
+    Ok(return_type)
+}
+
+/// Measure time of repeated invocations with sample data.
+async fn benchmark(
+    udf: &WasmScalarUdf,
+    arg_types: &[DataType],
+    rows: usize,
+    iterations: usize,
+) -> DataFusionResult<Duration> {
+    let return_type = udf.return_type(arg_types)?;
+    let args = sample_args(udf, arg_types, &return_type, rows)?;
+
+    let start = Instant::now();
+    for _ in 0..iterations {
+        udf.invoke_async_with_args(args.clone()).await?;
+    }
+    Ok(start.elapsed())
+}
+
+/// Create arguments with sample data.
+fn sample_args(
+    udf: &WasmScalarUdf,
+    arg_types: &[DataType],
+    return_type: &DataType,
+    rows: usize,
+) -> DataFusionResult<ScalarFunctionArgs> {
+    let args = arg_types
+        .iter()
+        .map(|t| sample::array(t, rows).map(ColumnarValue::Array))
+        .collect::<DataFusionResult<Vec<_>>>()?;
+    let arg_fields = arg_types
+        .iter()
+        .enumerate()
+        .map(|(i, t)| Arc::new(Field::new(format!("arg{i}"), t.clone(), true)))
+        .collect();
+
+    Ok(ScalarFunctionArgs {
+        args,
+        arg_fields,
+        number_rows: rows,
+        return_field: Arc::new(Field::new(udf.name(), return_type.clone(), true)),
+        config_options: Arc::new(ConfigOptions::default()),
+    })
+}
@@ -0,0 +1,86 @@
+//! Sample data for type checks.
+
+use arrow::{array::ArrayRef, datatypes::DataType};
+use datafusion_common::{Result as DataFusionResult, ScalarValue};
+use datafusion_expr::TypeSignature;
+
+/// Argument types that can be derived from a signature without any coercion.
+///
+/// Returns an empty list if the signature does not name any concrete types.
+pub(crate) fn arg_types(signature: &TypeSignature) -> Vec<Vec<DataType>> {
+    match signature {
+        TypeSignature::Exact(types) => vec![types.clone()],
+        TypeSignature::Nullary => vec![vec![]],
+        TypeSignature::Uniform(n, types) => types.iter().map(|t| vec![t.clone(); *n]).collect(),
+        TypeSignature::Variadic(types) => types.iter().map(|t| vec![t.clone()]).collect(),
+        TypeSignature::OneOf(signatures) => signatures.iter().flat_map(arg_types).collect(),
+        _ => vec![],
+    }
+}
+
+/// Create sample array with the given number of rows.
+///
+/// Every third row is NULL.
+pub(crate) fn array(data_type: &DataType, rows: usize) -> DataFusionResult<ArrayRef> {
+    let null = ScalarValue::try_from(data_type)?;
+    let value = match data_type {
+        DataType::Boolean => ScalarValue::Boolean(Some(true)),
+        DataType::Utf8 => ScalarValue::Utf8(Some("sample".to_owned())),
+        DataType::LargeUtf8 => ScalarValue::LargeUtf8(Some("sample".to_owned())),
+        DataType::Utf8View => ScalarValue::Utf8View(Some("sample".to_owned())),
+        DataType::Binary => ScalarValue::Binary(Some(b"sample".to_vec())),
+        DataType::LargeBinary => ScalarValue::LargeBinary(Some(b"sample".to_vec())),
+        DataType::BinaryView => ScalarValue::BinaryView(Some(b"sample".to_vec())),
+        // numeric types; fall back to NULLs for everything else
+        _ => ScalarValue::new_one(data_type).unwrap_or_else(|_| null.clone()),
+    };
+
+    if rows == 0 {
+        return null.to_array_of_size(0);
+    }
+    ScalarValue::iter_to_array((0..rows).map(|i| {
+        if i % 3 == 2 {
+            null.clone()
+        } else {
+            value.clone()
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::array::{Array, Int64Array, StringArray};
+
+    use super::*;
+
+    #[test]
+    fn test_arg_types() {
+        assert_eq!(
+            arg_types(&TypeSignature::OneOf(vec![
+                TypeSignature::Exact(vec![DataType::Int64]),
+                TypeSignature::Uniform(2, vec![DataType::Utf8, DataType::Float64]),
+                TypeSignature::Nullary,
+                TypeSignature::Any(1),
+            ])),
+            vec![
+                vec![DataType::Int64],
+                vec![DataType::Utf8, DataType::Utf8],
+                vec![DataType::Float64, DataType::Float64],
+                vec![],
+            ],
+        );
+    }
+
+    #[test]
+    fn test_array() {
+        assert_eq!(
+            array(&DataType::Int64, 4).unwrap().as_ref(),
+            &Int64Array::from_iter([Some(1), Some(1), None, Some(1)]) as &dyn Array,
+        );
+        assert_eq!(
+            array(&DataType::Utf8, 3).unwrap().as_ref(),
+            &StringArray::from_iter([Some("sample"), Some("sample"), None]) as &dyn Array,
+        );
+        assert_eq!(array(&DataType::Utf8, 0).unwrap().len(), 0);
+    }
+}
@@ -167,6 +167,9 @@ enum ArtifactType {
 
     /// Example.
     Example(&'static str),
+
+    /// Variant of the library, e.g. with different build steps.
+    Variant(&'static str),
 }
 
 /// Just(file) command.
@@ -209,7 +212,7 @@ impl Feature {
             just_cmds,
         } = self;
 
-        let name_upper = name.to_uppercase();
+        let name_upper = name.to_uppercase().replace("-", "_");
         if std::env::var_os(format!("CARGO_FEATURE_{name_upper}")).is_none() {
             // feature not selected
             return;
@@ -234,8 +237,8 @@ impl Feature {
                 let mut just_cmd = "build-".to_owned();
                 match artifact_type {
                     ArtifactType::Lib => {}
-                    ArtifactType::Example(example) => {
-                        just_cmd.push_str(example);
+                    ArtifactType::Example(name) | ArtifactType::Variant(name) => {
+                        just_cmd.push_str(name);
                         just_cmd.push('-');
                     }
                 }
@@ -249,6 +252,11 @@ impl Feature {
                     ArtifactType::Example(example) => out
                         .join("examples")
                         .join(format!("{}.wasm", example.replace("-", "_"))),
+                    ArtifactType::Variant(variant) => out.join(format!(
+                        "{}_{}.wasm",
+                        package.replace("-", "_"),
+                        variant.replace("-", "_"),
+                    )),
                 }
             };
 
@@ -344,11 +352,21 @@ const FEATURES: &[Feature] = &[
         name: "example",
         package: "datafusion-udf-wasm-guest",
         just_cmds: &[
+            JustCmd {
+                artifact_type: ArtifactType::Example("add-ints"),
+                const_name: "EXAMPLE_ADD_INTS",
+                doc: r#""add-ints" example."#,
+            },
             JustCmd {
                 artifact_type: ArtifactType::Example("add-one"),
                 const_name: "EXAMPLE_ADD_ONE",
                 doc: r#""add-one" example."#,
             },
+            JustCmd {
+                artifact_type: ArtifactType::Example("simple"),
+                const_name: "EXAMPLE_SIMPLE",
+                doc: r#""simple" example."#,
+            },
             JustCmd {
                 artifact_type: ArtifactType::Example("sub-str"),
                 const_name: "EXAMPLE_SUB_STR",
@@ -356,6 +374,15 @@ const FEATURES: &[Feature] = &[
             },
         ],
     },
+    Feature {
+        name: "expr",
+        package: "datafusion-udf-wasm-expr",
+        just_cmds: &[JustCmd {
+            artifact_type: ArtifactType::Lib,
+            const_name: "EXPR",
+            doc: "Expression language UDF.",
+        }],
+    },
     Feature {
         name: "python",
         package: "datafusion-udf-wasm-python",
@@ -365,4 +392,13 @@ const FEATURES: &[Feature] = &[
             doc: "Python UDF.",
         }],
     },
+    Feature {
+        name: "python-preinit",
+        package: "datafusion-udf-wasm-python",
+        just_cmds: &[JustCmd {
+            artifact_type: ArtifactType::Variant("preinit"),
+            const_name: "PYTHON_PREINIT",
+            doc: "Python UDF with a pre-initialized interpreter.",
+        }],
+    },
 ];
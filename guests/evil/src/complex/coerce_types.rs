@@ -0,0 +1,81 @@
+//! Badness related to coerced argument types.
+
+use std::sync::Arc;
+
+use arrow::datatypes::{DataType, Field};
+use datafusion_common::{DataFusionError, Result as DataFusionResult};
+use datafusion_expr::{
+    ColumnarValue, ScalarFunctionArgs, ScalarUDFImpl, Signature, TypeSignature, Volatility,
+};
+
+/// UDF that coerces arguments to specific data types.
+#[derive(Debug, PartialEq, Eq, Hash)]
+struct CoerceTypesUDF {
+    /// Name.
+    name: &'static str,
+
+    /// The coerced types.
+    coerced: Vec<DataType>,
+}
+
+impl CoerceTypesUDF {
+    /// Create new UDF.
+    fn new(name: &'static str, coerced: Vec<DataType>) -> Self {
+        Self { name, coerced }
+    }
+}
+
+impl ScalarUDFImpl for CoerceTypesUDF {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn signature(&self) -> &Signature {
+        static S: Signature = Signature {
+            type_signature: TypeSignature::UserDefined,
+            volatility: Volatility::Immutable,
+            parameter_names: None,
+        };
+
+        &S
+    }
+
+    fn coerce_types(&self, _arg_types: &[DataType]) -> DataFusionResult<Vec<DataType>> {
+        Ok(self.coerced.clone())
+    }
+
+    fn return_type(&self, _arg_types: &[DataType]) -> DataFusionResult<DataType> {
+        Ok(DataType::Null)
+    }
+
+    fn invoke_with_args(&self, _args: ScalarFunctionArgs) -> DataFusionResult<ColumnarValue> {
+        Err(DataFusionError::NotImplemented(
+            "invoke_with_args".to_owned(),
+        ))
+    }
+}
+
+/// Returns our evil UDFs.
+///
+/// The passed `source` is ignored.
+#[expect(clippy::unnecessary_wraps, reason = "public API through export! macro")]
+pub(crate) fn udfs(_source: String) -> DataFusionResult<Vec<Arc<dyn ScalarUDFImpl>>> {
+    let max_depth: usize = std::env::var("max_depth").unwrap().parse().unwrap();
+
+    Ok(vec![
+        Arc::new(CoerceTypesUDF::new(
+            "dt_depth",
+            vec![(0..=max_depth).fold(DataType::Int64, |dt, _| {
+                DataType::List(Arc::new(Field::new("f", dt, true)))
+            })],
+        )),
+        Arc::new(CoerceTypesUDF::new(
+            "too_many",
+            vec![DataType::Int64, DataType::Int64],
+        )),
+    ])
+}
@@ -72,6 +72,19 @@ pub(crate) fn udfs(_source: String) -> DataFusionResult<Vec<Arc<dyn ScalarUDFImp
             DataFusionError::Execution("foo".to_owned())
                 .context(std::iter::repeat_n('x', limit + 1).collect::<String>())
         })),
+        Arc::new(ErrorUDF::new("ansi", || {
+            DataFusionError::Execution(
+                "\x1b[31mred\x1b[0m\u{202e}txet\u{202c}\x07\r\nfake log line".to_owned(),
+            )
+            .context("\x1b]0;title\x07ctx")
+        })),
+        Arc::new(ErrorUDF::new("large_ctx", || {
+            let limit: usize = std::env::var("limit").unwrap().parse().unwrap();
+            let chunk = std::iter::repeat_n('x', limit / 4).collect::<String>();
+            (0..8).fold(DataFusionError::Execution("foo".to_owned()), |err, _| {
+                err.context(chunk.clone())
+            })
+        })),
         Arc::new(ErrorUDF::new("long_msg", || {
             let limit: usize = std::env::var("limit").unwrap().parse().unwrap();
             DataFusionError::Execution(std::iter::repeat_n('x', limit + 1).collect())
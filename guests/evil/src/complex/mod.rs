@@ -6,6 +6,7 @@ use datafusion_expr::{
     ColumnarValue, ScalarFunctionArgs, ScalarUDFImpl, Signature, TypeSignature, Volatility,
 };
 
+pub(crate) mod coerce_types;
 pub(crate) mod error;
 pub(crate) mod many_inputs;
 pub(crate) mod params_long_name;
@@ -13,6 +14,8 @@ pub(crate) mod params_many;
 pub(crate) mod return_type;
 pub(crate) mod return_value;
 pub(crate) mod udf_long_name;
+pub(crate) mod udf_unsafe_name;
+pub(crate) mod udfs_colliding_names;
 pub(crate) mod udfs_duplicate_names;
 pub(crate) mod udfs_many;
 
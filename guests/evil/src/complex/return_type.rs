@@ -2,7 +2,7 @@
 
 use std::{collections::HashMap, sync::Arc};
 
-use arrow::datatypes::{DataType, Field};
+use arrow::datatypes::{DataType, Field, TimeUnit};
 use datafusion_common::{DataFusionError, Result as DataFusionResult};
 use datafusion_expr::{
     ColumnarValue, ScalarFunctionArgs, ScalarUDFImpl, Signature, TypeSignature, Volatility,
@@ -114,5 +114,20 @@ pub(crate) fn udfs(_source: String) -> DataFusionResult<Vec<Arc<dyn ScalarUDFImp
                 ),
             )),
         )),
+        Arc::new(ReturnTypeUDF::new(
+            "timestamp_tz_length",
+            DataType::Timestamp(
+                TimeUnit::Microsecond,
+                Some(
+                    std::iter::repeat_n('x', max_identifier_length + 1)
+                        .collect::<String>()
+                        .into(),
+                ),
+            ),
+        )),
+        Arc::new(ReturnTypeUDF::new(
+            "timestamp_tz_chars",
+            DataType::Timestamp(TimeUnit::Microsecond, Some("Europe/Berlin\nUTC".into())),
+        )),
     ])
 }
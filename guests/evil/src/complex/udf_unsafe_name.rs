@@ -0,0 +1,18 @@
+//! UDF names with unsafe characters.
+use std::sync::Arc;
+
+use datafusion_common::Result as DataFusionResult;
+use datafusion_expr::ScalarUDFImpl;
+
+use crate::complex::TestUdf;
+
+/// Returns our evil UDFs.
+///
+/// The passed `source` is ignored.
+#[expect(clippy::unnecessary_wraps, reason = "public API through export! macro")]
+pub(crate) fn udfs(_source: String) -> DataFusionResult<Vec<Arc<dyn ScalarUDFImpl>>> {
+    Ok(vec![Arc::new(TestUdf {
+        name: "\x1b[2Kudf".to_owned(),
+        ..Default::default()
+    })])
+}
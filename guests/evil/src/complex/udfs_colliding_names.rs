@@ -0,0 +1,25 @@
+//! UDF names that are NOT identical but still collide.
+use std::sync::Arc;
+
+use datafusion_common::Result as DataFusionResult;
+use datafusion_expr::ScalarUDFImpl;
+
+use crate::complex::TestUdf;
+
+/// Returns our evil UDFs.
+///
+/// The names are read from the comma-separated `names` environment variable. The passed `source` is ignored.
+#[expect(clippy::unnecessary_wraps, reason = "public API through export! macro")]
+pub(crate) fn udfs(_source: String) -> DataFusionResult<Vec<Arc<dyn ScalarUDFImpl>>> {
+    let names = std::env::var("names").unwrap();
+
+    Ok(names
+        .split(',')
+        .map(|name| {
+            Arc::new(TestUdf {
+                name: name.to_owned(),
+                ..Default::default()
+            }) as _
+        })
+        .collect())
+}
@@ -0,0 +1,48 @@
+//! Payload that uses the key-value store of the host.
+use std::sync::Arc;
+
+use datafusion_common::Result as DataFusionResult;
+use datafusion_expr::ScalarUDFImpl;
+use datafusion_udf_wasm_guest::kv;
+
+use crate::common::String1Udf;
+
+/// Returns our evil UDFs.
+///
+/// The passed `source` is ignored.
+#[expect(clippy::unnecessary_wraps, reason = "public API through export! macro")]
+pub(crate) fn udfs(_source: String) -> DataFusionResult<Vec<Arc<dyn ScalarUDFImpl>>> {
+    Ok(vec![
+        Arc::new(String1Udf::new("kv_delete", |key| {
+            kv::delete(key.as_bytes())
+                .map(|existed| existed.to_string())
+                .map_err(|e| e.to_string())
+        })),
+        Arc::new(String1Udf::new("kv_get", |key| {
+            kv::get(key.as_bytes())
+                .map(|value| match value {
+                    Some(value) => String::from_utf8_lossy(&value).into_owned(),
+                    None => "<missing>".to_owned(),
+                })
+                .map_err(|e| e.to_string())
+        })),
+        Arc::new(String1Udf::new("kv_incr", |key| {
+            let current = kv::get(key.as_bytes()).map_err(|e| e.to_string())?;
+            let n = match current {
+                Some(value) => String::from_utf8_lossy(&value)
+                    .parse::<u64>()
+                    .map_err(|e| e.to_string())?,
+                None => 0,
+            } + 1;
+            kv::set(key.as_bytes(), n.to_string().as_bytes())
+                .map(|()| n.to_string())
+                .map_err(|e| e.to_string())
+        })),
+        Arc::new(String1Udf::new("kv_set", |input| {
+            let (key, value) = input.split_once('=').unwrap_or((&input, ""));
+            kv::set(key.as_bytes(), value.as_bytes())
+                .map(|()| "set".to_owned())
+                .map_err(|e| e.to_string())
+        })),
+    ])
+}
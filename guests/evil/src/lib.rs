@@ -11,9 +11,11 @@ mod common;
 mod complex;
 mod env;
 mod fs;
+mod kv;
 mod net;
 mod return_data;
 mod runtime;
+mod secrets;
 mod spin;
 
 /// Method that enumerates UDFs.
@@ -29,6 +31,9 @@ impl Evil {
     /// Get evil, multiplexed by env.
     fn get() -> Self {
         match std::env::var("EVIL").expect("evil specified").as_str() {
+            "complex::coerce_types" => Self {
+                udfs: Box::new(complex::coerce_types::udfs),
+            },
             "complex::error" => Self {
                 udfs: Box::new(complex::error::udfs),
             },
@@ -50,6 +55,12 @@ impl Evil {
             "complex::udf_long_name" => Self {
                 udfs: Box::new(complex::udf_long_name::udfs),
             },
+            "complex::udf_unsafe_name" => Self {
+                udfs: Box::new(complex::udf_unsafe_name::udfs),
+            },
+            "complex::udfs_colliding_names" => Self {
+                udfs: Box::new(complex::udfs_colliding_names::udfs),
+            },
             "complex::udfs_duplicate_names" => Self {
                 udfs: Box::new(complex::udfs_duplicate_names::udfs),
             },
@@ -62,6 +73,9 @@ impl Evil {
             "fs" => Self {
                 udfs: Box::new(fs::udfs),
             },
+            "kv" => Self {
+                udfs: Box::new(kv::udfs),
+            },
             "net" => Self {
                 udfs: Box::new(net::udfs),
             },
@@ -71,6 +85,9 @@ impl Evil {
             "runtime" => Self {
                 udfs: Box::new(runtime::udfs),
             },
+            "secrets" => Self {
+                udfs: Box::new(secrets::udfs),
+            },
             "spin::udf_invoke" => Self {
                 udfs: Box::new(spin::udf_invoke::udfs),
             },
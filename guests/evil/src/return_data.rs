@@ -44,10 +44,46 @@ impl ScalarUDFImpl for WrongNumberOfRows {
     }
 }
 
+/// UDF that returns a long string value.
+#[derive(Debug, PartialEq, Eq, Hash)]
+struct LongValue;
+
+impl ScalarUDFImpl for LongValue {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        "long-value"
+    }
+
+    fn signature(&self) -> &Signature {
+        static S: Signature = Signature {
+            type_signature: TypeSignature::Uniform(0, vec![]),
+            volatility: Volatility::Immutable,
+            parameter_names: None,
+        };
+
+        &S
+    }
+
+    fn return_type(&self, _arg_types: &[DataType]) -> DataFusionResult<DataType> {
+        Ok(DataType::Utf8)
+    }
+
+    fn invoke_with_args(&self, args: ScalarFunctionArgs) -> DataFusionResult<ColumnarValue> {
+        Ok(ColumnarValue::Array(Arc::new(
+            (0..args.number_rows)
+                .map(|_| Some("x".repeat(1_000)))
+                .collect::<StringArray>(),
+        )))
+    }
+}
+
 /// Returns our evil UDFs.
 ///
 /// The passed `source` is ignored.
 #[expect(clippy::unnecessary_wraps, reason = "public API through export! macro")]
 pub(crate) fn udfs(_source: String) -> DataFusionResult<Vec<Arc<dyn ScalarUDFImpl>>> {
-    Ok(vec![Arc::new(WrongNumberOfRows)])
+    Ok(vec![Arc::new(WrongNumberOfRows), Arc::new(LongValue)])
 }
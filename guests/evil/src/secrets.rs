@@ -0,0 +1,61 @@
+//! Payload that tries to leak secrets provided by the host.
+use std::sync::Arc;
+
+use arrow::datatypes::DataType;
+use datafusion_common::{DataFusionError, Result as DataFusionResult};
+use datafusion_expr::{
+    ColumnarValue, ScalarFunctionArgs, ScalarUDFImpl, Signature, TypeSignature, Volatility,
+};
+use datafusion_udf_wasm_guest::secrets;
+
+use crate::common::String1Udf;
+
+/// UDF that writes the `token` secret to stderr and returns it within an error.
+#[derive(Debug, PartialEq, Eq, Hash)]
+struct LeakUdf;
+
+impl ScalarUDFImpl for LeakUdf {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        "leak"
+    }
+
+    fn signature(&self) -> &Signature {
+        static S: Signature = Signature {
+            type_signature: TypeSignature::Uniform(0, vec![]),
+            volatility: Volatility::Immutable,
+            parameter_names: None,
+        };
+
+        &S
+    }
+
+    fn return_type(&self, _arg_types: &[DataType]) -> DataFusionResult<DataType> {
+        Ok(DataType::Null)
+    }
+
+    fn invoke_with_args(&self, _args: ScalarFunctionArgs) -> DataFusionResult<ColumnarValue> {
+        let token = secrets::get("token").unwrap_or_default();
+        eprintln!("using token={token}");
+        Err(
+            DataFusionError::Execution(format!("request with token `{token}` failed"))
+                .context(format!("authenticate as {token}")),
+        )
+    }
+}
+
+/// Returns our evil UDFs.
+///
+/// The passed `source` is ignored.
+#[expect(clippy::unnecessary_wraps, reason = "public API through export! macro")]
+pub(crate) fn udfs(_source: String) -> DataFusionResult<Vec<Arc<dyn ScalarUDFImpl>>> {
+    Ok(vec![
+        Arc::new(LeakUdf),
+        Arc::new(String1Udf::new("secret_get", |name| {
+            secrets::get(&name).ok_or_else(|| "missing".to_owned())
+        })),
+    ])
+}
@@ -0,0 +1,290 @@
+//! Evaluation of type-checked expressions.
+use datafusion_common::{DataFusionError, Result as DataFusionResult, exec_err};
+
+use crate::parser::{BinaryOp, Builtin, Expr, UnaryOp};
+
+/// Runtime value.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Value {
+    /// 64-bit signed integer.
+    Int(i64),
+
+    /// 64-bit float.
+    Float(f64),
+
+    /// UTF-8 string.
+    Str(String),
+
+    /// Boolean.
+    Bool(bool),
+}
+
+impl Value {
+    /// Get boolean.
+    ///
+    /// The type checker guarantees that this is only called for booleans.
+    fn as_bool(&self) -> DataFusionResult<bool> {
+        match self {
+            Self::Bool(b) => Ok(*b),
+            other => Self::type_mismatch("bool", other),
+        }
+    }
+
+    /// Get float.
+    fn as_float(&self) -> DataFusionResult<f64> {
+        match self {
+            Self::Float(x) => Ok(*x),
+            other => Self::type_mismatch("float", other),
+        }
+    }
+
+    /// Get string.
+    fn into_str(self) -> DataFusionResult<String> {
+        match self {
+            Self::Str(s) => Ok(s),
+            other => Self::type_mismatch("str", &other),
+        }
+    }
+
+    /// Error for values that do not match the type checker's expectation.
+    fn type_mismatch<T>(expected: &str, actual: &Self) -> DataFusionResult<T> {
+        Err(DataFusionError::Internal(format!(
+            "expected {expected}, got {actual:?}"
+        )))
+    }
+}
+
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Int(i) => write!(f, "{i}"),
+            Self::Float(x) => write!(f, "{x:?}"),
+            Self::Str(s) => write!(f, "{s}"),
+            Self::Bool(b) => write!(f, "{b}"),
+        }
+    }
+}
+
+/// Evaluate expression for the given parameter values.
+pub(crate) fn eval(expr: &Expr, params: &[Value]) -> DataFusionResult<Value> {
+    match expr {
+        Expr::Literal(v) => Ok(v.clone()),
+        Expr::Param(idx) => params
+            .get(*idx)
+            .cloned()
+            .ok_or_else(|| DataFusionError::Internal(format!("parameter {idx} out of bounds"))),
+        Expr::Unary(op, inner) => {
+            let inner = eval(inner, params)?;
+            match (op, inner) {
+                (UnaryOp::Neg, Value::Int(i)) => match i.checked_neg() {
+                    Some(i) => Ok(Value::Int(i)),
+                    None => exec_err!("integer overflow in `-{i}`"),
+                },
+                (UnaryOp::Neg, Value::Float(x)) => Ok(Value::Float(-x)),
+                (UnaryOp::Not, Value::Bool(b)) => Ok(Value::Bool(!b)),
+                (op, other) => Err(DataFusionError::Internal(format!(
+                    "cannot apply {op:?} to {other:?}"
+                ))),
+            }
+        }
+        Expr::Binary(op, lhs, rhs) => binary(*op, eval(lhs, params)?, eval(rhs, params)?),
+        Expr::And(lhs, rhs) => Ok(Value::Bool(
+            eval(lhs, params)?.as_bool()? && eval(rhs, params)?.as_bool()?,
+        )),
+        Expr::Or(lhs, rhs) => Ok(Value::Bool(
+            eval(lhs, params)?.as_bool()? || eval(rhs, params)?.as_bool()?,
+        )),
+        Expr::If {
+            cond,
+            then,
+            otherwise,
+        } => {
+            if eval(cond, params)?.as_bool()? {
+                eval(then, params)
+            } else {
+                eval(otherwise, params)
+            }
+        }
+        Expr::Call(builtin, args) => {
+            let args = args
+                .iter()
+                .map(|arg| eval(arg, params))
+                .collect::<DataFusionResult<Vec<_>>>()?;
+            call(*builtin, args)
+        }
+        Expr::RegexMatch(input, regex) => {
+            let input = eval(input, params)?.into_str()?;
+            Ok(Value::Bool(regex.is_match(&input)))
+        }
+        Expr::RegexReplace(input, regex, replacement) => {
+            let input = eval(input, params)?.into_str()?;
+            let replacement = eval(replacement, params)?.into_str()?;
+            Ok(Value::Str(
+                regex.replace_all(&input, replacement.as_str()).into_owned(),
+            ))
+        }
+    }
+}
+
+/// Evaluate binary operation.
+fn binary(op: BinaryOp, lhs: Value, rhs: Value) -> DataFusionResult<Value> {
+    let res = match (op, lhs, rhs) {
+        (BinaryOp::Add, Value::Int(a), Value::Int(b)) => {
+            Value::Int(checked(a.checked_add(b), a, "+", b)?)
+        }
+        (BinaryOp::Sub, Value::Int(a), Value::Int(b)) => {
+            Value::Int(checked(a.checked_sub(b), a, "-", b)?)
+        }
+        (BinaryOp::Mul, Value::Int(a), Value::Int(b)) => {
+            Value::Int(checked(a.checked_mul(b), a, "*", b)?)
+        }
+        (BinaryOp::Div | BinaryOp::Rem, Value::Int(_), Value::Int(0)) => {
+            return exec_err!("division by zero");
+        }
+        (BinaryOp::Div, Value::Int(a), Value::Int(b)) => {
+            Value::Int(checked(a.checked_div(b), a, "/", b)?)
+        }
+        (BinaryOp::Rem, Value::Int(a), Value::Int(b)) => {
+            Value::Int(checked(a.checked_rem(b), a, "%", b)?)
+        }
+        (BinaryOp::Add, Value::Float(a), Value::Float(b)) => Value::Float(a + b),
+        (BinaryOp::Sub, Value::Float(a), Value::Float(b)) => Value::Float(a - b),
+        (BinaryOp::Mul, Value::Float(a), Value::Float(b)) => Value::Float(a * b),
+        (BinaryOp::Div, Value::Float(a), Value::Float(b)) => Value::Float(a / b),
+        (BinaryOp::Rem, Value::Float(a), Value::Float(b)) => Value::Float(a % b),
+        (BinaryOp::Concat, Value::Str(mut a), Value::Str(b)) => {
+            a.push_str(&b);
+            Value::Str(a)
+        }
+        (BinaryOp::Eq, a, b) => Value::Bool(a == b),
+        (BinaryOp::Ne, a, b) => Value::Bool(a != b),
+        (op @ (BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge), a, b) => {
+            let ordering = match (&a, &b) {
+                (Value::Int(a), Value::Int(b)) => a.partial_cmp(b),
+                (Value::Float(a), Value::Float(b)) => a.partial_cmp(b),
+                (Value::Str(a), Value::Str(b)) => a.partial_cmp(b),
+                _ => {
+                    return Err(DataFusionError::Internal(format!(
+                        "cannot compare {a:?} and {b:?}"
+                    )));
+                }
+            };
+            // NaN is neither smaller nor greater than anything
+            let res = ordering.is_some_and(|ordering| match op {
+                BinaryOp::Lt => ordering.is_lt(),
+                BinaryOp::Le => ordering.is_le(),
+                BinaryOp::Gt => ordering.is_gt(),
+                _ => ordering.is_ge(),
+            });
+            Value::Bool(res)
+        }
+        (op, a, b) => {
+            return Err(DataFusionError::Internal(format!(
+                "cannot apply {op:?} to {a:?} and {b:?}"
+            )));
+        }
+    };
+    Ok(res)
+}
+
+/// Unwrap result of checked integer arithmetic.
+fn checked(res: Option<i64>, a: i64, op: &str, b: i64) -> DataFusionResult<i64> {
+    match res {
+        Some(i) => Ok(i),
+        None => exec_err!("integer overflow in `{a} {op} {b}`"),
+    }
+}
+
+/// Call built-in function.
+fn call(builtin: Builtin, args: Vec<Value>) -> DataFusionResult<Value> {
+    let res = match (builtin, args.as_slice()) {
+        (Builtin::Abs, [Value::Int(i)]) => match i.checked_abs() {
+            Some(i) => Value::Int(i),
+            None => {
+                return exec_err!("integer overflow in `abs({i})`");
+            }
+        },
+        (Builtin::Abs, [Value::Float(x)]) => Value::Float(x.abs()),
+        (Builtin::Floor, [x]) => Value::Float(x.as_float()?.floor()),
+        (Builtin::Ceil, [x]) => Value::Float(x.as_float()?.ceil()),
+        (Builtin::Round, [x]) => Value::Float(x.as_float()?.round()),
+        (Builtin::Sqrt, [x]) => Value::Float(x.as_float()?.sqrt()),
+        (Builtin::Pow, [x, y]) => Value::Float(x.as_float()?.powf(y.as_float()?)),
+        (Builtin::Min, [Value::Int(a), Value::Int(b)]) => Value::Int(*a.min(b)),
+        (Builtin::Max, [Value::Int(a), Value::Int(b)]) => Value::Int(*a.max(b)),
+        (Builtin::Min, [a, b]) => Value::Float(a.as_float()?.min(b.as_float()?)),
+        (Builtin::Max, [a, b]) => Value::Float(a.as_float()?.max(b.as_float()?)),
+        (Builtin::Lower, [Value::Str(s)]) => Value::Str(s.to_lowercase()),
+        (Builtin::Upper, [Value::Str(s)]) => Value::Str(s.to_uppercase()),
+        (Builtin::Trim, [Value::Str(s)]) => Value::Str(s.trim().to_owned()),
+        (Builtin::Length, [Value::Str(s)]) => {
+            Value::Int(i64::try_from(s.chars().count()).unwrap_or(i64::MAX))
+        }
+        (Builtin::Substr, [Value::Str(s), Value::Int(start), Value::Int(len)]) => {
+            if *start < 1 {
+                return exec_err!("`substr` start must be at least 1, got {start}");
+            }
+            if *len < 0 {
+                return exec_err!("`substr` length must not be negative, got {len}");
+            }
+            let start = usize::try_from(*start - 1).unwrap_or(usize::MAX);
+            let len = usize::try_from(*len).unwrap_or(usize::MAX);
+            Value::Str(s.chars().skip(start).take(len).collect())
+        }
+        (Builtin::Replace, [Value::Str(s), Value::Str(from), Value::Str(to)]) => {
+            Value::Str(s.replace(from.as_str(), to))
+        }
+        (Builtin::Contains, [Value::Str(s), Value::Str(sub)]) => {
+            Value::Bool(s.contains(sub.as_str()))
+        }
+        (Builtin::StartsWith, [Value::Str(s), Value::Str(prefix)]) => {
+            Value::Bool(s.starts_with(prefix.as_str()))
+        }
+        (Builtin::EndsWith, [Value::Str(s), Value::Str(suffix)]) => {
+            Value::Bool(s.ends_with(suffix.as_str()))
+        }
+        (Builtin::ToStr, [v]) => Value::Str(v.to_string()),
+        (Builtin::ToInt, [v]) => Value::Int(to_int(v)?),
+        (Builtin::ToFloat, [v]) => Value::Float(to_float(v)?),
+        (builtin, args) => {
+            return Err(DataFusionError::Internal(format!(
+                "cannot call {builtin:?} with {args:?}"
+            )));
+        }
+    };
+    Ok(res)
+}
+
+/// Convert value to integer.
+fn to_int(v: &Value) -> DataFusionResult<i64> {
+    match v {
+        Value::Int(i) => Ok(*i),
+        Value::Float(x) => {
+            let truncated = x.trunc();
+            // `i64::MAX as f64` rounds up to 2^63, so the upper bound is exclusive
+            if truncated.is_nan() || truncated < i64::MIN as f64 || truncated >= i64::MAX as f64 {
+                exec_err!("cannot convert {x:?} to int")
+            } else {
+                Ok(truncated as i64)
+            }
+        }
+        Value::Str(s) => s
+            .trim()
+            .parse()
+            .map_err(|_| DataFusionError::Execution(format!("cannot convert {s:?} to int"))),
+        Value::Bool(b) => Ok(i64::from(*b)),
+    }
+}
+
+/// Convert value to float.
+fn to_float(v: &Value) -> DataFusionResult<f64> {
+    match v {
+        Value::Int(i) => Ok(*i as f64),
+        Value::Float(x) => Ok(*x),
+        Value::Str(s) => s
+            .trim()
+            .parse()
+            .map_err(|_| DataFusionError::Execution(format!("cannot convert {s:?} to float"))),
+        Value::Bool(b) => Ok(f64::from(u8::from(*b))),
+    }
+}
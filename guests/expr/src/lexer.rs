@@ -0,0 +1,203 @@
+//! Tokenizer for the expression language.
+
+use datafusion_common::{DataFusionError, Result as DataFusionResult};
+
+/// Position within the source code, used for error messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Pos {
+    /// Line, 1-based.
+    pub(crate) line: usize,
+
+    /// Column in characters, 1-based.
+    pub(crate) col: usize,
+}
+
+impl std::fmt::Display for Pos {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.col)
+    }
+}
+
+/// Token kind.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum TokenKind {
+    /// Identifier or keyword.
+    Ident(String),
+
+    /// Integer literal.
+    Int(i64),
+
+    /// Float literal.
+    Float(f64),
+
+    /// String literal, with escape sequences resolved.
+    Str(String),
+
+    /// Operator or punctuation.
+    Sym(&'static str),
+
+    /// End of input.
+    Eof,
+}
+
+impl std::fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Ident(s) => write!(f, "`{s}`"),
+            Self::Int(i) => write!(f, "`{i}`"),
+            Self::Float(x) => write!(f, "`{x:?}`"),
+            Self::Str(s) => write!(f, "{s:?}"),
+            Self::Sym(s) => write!(f, "`{s}`"),
+            Self::Eof => write!(f, "end of input"),
+        }
+    }
+}
+
+/// Token with its position.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Token {
+    /// Kind.
+    pub(crate) kind: TokenKind,
+
+    /// Position of the first character.
+    pub(crate) pos: Pos,
+}
+
+/// Operators and punctuation, longest first so that e.g. `<=` is NOT read as `<` followed by `=`.
+const SYMBOLS: &[&str] = &[
+    "->", "==", "!=", "<=", ">=", "||", "(", ")", ",", ":", ";", "=", "<", ">", "+", "-", "*", "/",
+    "%",
+];
+
+/// Split source code into tokens.
+///
+/// The result always ends with [`TokenKind::Eof`]. Comments start with `#` and run until the end of the line.
+pub(crate) fn tokenize(source: &str) -> DataFusionResult<Vec<Token>> {
+    let chars = source.chars().collect::<Vec<_>>();
+    let mut tokens = vec![];
+    let mut i = 0;
+    let mut pos = Pos { line: 1, col: 1 };
+
+    // advance by `n` characters, keeping track of the position
+    let advance = |i: &mut usize, pos: &mut Pos, n: usize| {
+        for c in &chars[*i..*i + n] {
+            if *c == '\n' {
+                pos.line += 1;
+                pos.col = 1;
+            } else {
+                pos.col += 1;
+            }
+        }
+        *i += n;
+    };
+
+    while i < chars.len() {
+        let c = chars[i];
+        let start = pos;
+
+        if c.is_whitespace() {
+            advance(&mut i, &mut pos, 1);
+        } else if c == '#' {
+            let n = chars[i..].iter().take_while(|c| **c != '\n').count();
+            advance(&mut i, &mut pos, n);
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let n = chars[i..]
+                .iter()
+                .take_while(|c| c.is_ascii_alphanumeric() || **c == '_')
+                .count();
+            let ident = chars[i..i + n].iter().collect::<String>();
+            advance(&mut i, &mut pos, n);
+            tokens.push(Token {
+                kind: TokenKind::Ident(ident),
+                pos: start,
+            });
+        } else if c.is_ascii_digit() {
+            let n_int = chars[i..].iter().take_while(|c| c.is_ascii_digit()).count();
+            let is_float = chars.get(i + n_int) == Some(&'.')
+                && chars.get(i + n_int + 1).is_some_and(|c| c.is_ascii_digit());
+            let n = if is_float {
+                n_int
+                    + 1
+                    + chars[i + n_int + 1..]
+                        .iter()
+                        .take_while(|c| c.is_ascii_digit())
+                        .count()
+            } else {
+                n_int
+            };
+            let literal = chars[i..i + n].iter().collect::<String>();
+            advance(&mut i, &mut pos, n);
+            let kind = if is_float {
+                TokenKind::Float(literal.parse().map_err(|_| {
+                    DataFusionError::Plan(format!("{start}: invalid float literal `{literal}`"))
+                })?)
+            } else {
+                TokenKind::Int(literal.parse().map_err(|_| {
+                    DataFusionError::Plan(format!("{start}: integer literal `{literal}` too large"))
+                })?)
+            };
+            tokens.push(Token { kind, pos: start });
+        } else if c == '"' {
+            let mut s = String::new();
+            let mut n = 1;
+            loop {
+                match chars.get(i + n) {
+                    None => {
+                        return Err(DataFusionError::Plan(format!(
+                            "{start}: unterminated string literal"
+                        )));
+                    }
+                    Some('"') => {
+                        n += 1;
+                        break;
+                    }
+                    Some('\\') => {
+                        let escaped = match chars.get(i + n + 1) {
+                            Some('n') => '\n',
+                            Some('t') => '\t',
+                            Some('\\') => '\\',
+                            Some('"') => '"',
+                            other => {
+                                return Err(DataFusionError::Plan(format!(
+                                    "{start}: invalid escape sequence `\\{}` in string literal",
+                                    other.map(|c| c.to_string()).unwrap_or_default()
+                                )));
+                            }
+                        };
+                        s.push(escaped);
+                        n += 2;
+                    }
+                    Some(c) => {
+                        s.push(*c);
+                        n += 1;
+                    }
+                }
+            }
+            advance(&mut i, &mut pos, n);
+            tokens.push(Token {
+                kind: TokenKind::Str(s),
+                pos: start,
+            });
+        } else if let Some(sym) = SYMBOLS.iter().find(|sym| {
+            sym.chars()
+                .enumerate()
+                .all(|(offset, c)| chars.get(i + offset) == Some(&c))
+        }) {
+            advance(&mut i, &mut pos, sym.chars().count());
+            tokens.push(Token {
+                kind: TokenKind::Sym(sym),
+                pos: start,
+            });
+        } else {
+            return Err(DataFusionError::Plan(format!(
+                "{start}: unexpected character {c:?}"
+            )));
+        }
+    }
+
+    tokens.push(Token {
+        kind: TokenKind::Eof,
+        pos,
+    });
+    Ok(tokens)
+}
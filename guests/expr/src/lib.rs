@@ -0,0 +1,71 @@
+//! UDFs written in a small, safe expression language.
+//!
+//! The source code consists of function definitions:
+//!
+//! ```text
+//! # comments start with `#`
+//! add_one(x: int) -> int = x + 1
+//!
+//! grade(score: float) -> str =
+//!     if score >= 90 then "A"
+//!     else if score >= 75 then "B"
+//!     else "C"
+//!
+//! is_email(s: str) -> bool = regex_match(lower(trim(s)), "^[a-z0-9._%+-]+@[a-z0-9.-]+\\.[a-z]+$")
+//! ```
+//!
+//! Every definition becomes one UDF. The language has no loops, no recursion, and no I/O, so every call finishes in
+//! time linear to its inputs. See the `README.md` of this crate for a full reference.
+use std::sync::Arc;
+
+use datafusion_common::{DataFusionError, Result as DataFusionResult};
+use datafusion_expr::ScalarUDFImpl;
+use datafusion_udf_wasm_guest::{Diagnostic, export};
+
+use crate::udf::ExprScalarUDF;
+
+mod eval;
+mod lexer;
+mod parser;
+mod udf;
+
+/// Return UDFs defined in the provided source code.
+pub fn udfs(source: String) -> DataFusionResult<Vec<Arc<dyn ScalarUDFImpl>>> {
+    Ok(parser::parse(&source)?
+        .into_iter()
+        .map(|f| Arc::new(ExprScalarUDF::new(f)) as _)
+        .collect())
+}
+
+/// Check source code for errors without creating UDFs.
+///
+/// Only the first error is reported, because the parser stops at the first error.
+pub fn validate_source(source: &str) -> Vec<Diagnostic> {
+    let Err(e) = parser::parse(source) else {
+        return vec![];
+    };
+
+    // errors are prefixed with their position, e.g. `3:14: unexpected token`
+    let msg = match e {
+        DataFusionError::Plan(msg) => msg,
+        e => e.to_string(),
+    };
+    let parsed = msg.split_once(": ").and_then(|(pos, message)| {
+        let (line, column) = pos.split_once(':')?;
+        Some((line.parse().ok()?, column.parse().ok()?, message))
+    });
+    let (line, column, message) = parsed.unwrap_or((0, 0, &msg));
+
+    vec![Diagnostic {
+        message: message.to_owned(),
+        line,
+        column,
+    }]
+}
+
+export! {
+    scalar_udfs: udfs,
+    validate_source: validate_source,
+    // functions cannot observe NULLs, see `ExprScalarUDF`
+    null_strict: |_udf| true,
+}
@@ -0,0 +1,911 @@
+//! Parser and type checker for the expression language.
+use std::collections::HashSet;
+
+use arrow::datatypes::DataType;
+use datafusion_common::{DataFusionError, Result as DataFusionResult};
+use regex::{Regex, RegexBuilder};
+
+use crate::{
+    eval::Value,
+    lexer::{Pos, Token, TokenKind, tokenize},
+};
+
+/// Maximum nesting depth of expressions.
+///
+/// This protects the parser and the evaluator -- which are both recursive -- from stack overflows.
+const MAX_DEPTH: usize = 64;
+
+/// Maximum size of a compiled regular expression in bytes.
+const REGEX_SIZE_LIMIT: usize = 1024 * 1024;
+
+/// Keywords that cannot be used as names.
+const KEYWORDS: &[&str] = &[
+    "and", "bool", "else", "false", "float", "if", "int", "not", "or", "str", "then", "true",
+];
+
+/// Value type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Type {
+    /// 64-bit signed integer.
+    Int,
+
+    /// 64-bit float.
+    Float,
+
+    /// UTF-8 string.
+    Str,
+
+    /// Boolean.
+    Bool,
+}
+
+impl Type {
+    /// Parse type name.
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "int" => Some(Self::Int),
+            "float" => Some(Self::Float),
+            "str" => Some(Self::Str),
+            "bool" => Some(Self::Bool),
+            _ => None,
+        }
+    }
+
+    /// Arrow type that is used to represent this type.
+    pub(crate) fn data_type(&self) -> DataType {
+        match self {
+            Self::Int => DataType::Int64,
+            Self::Float => DataType::Float64,
+            Self::Str => DataType::Utf8,
+            Self::Bool => DataType::Boolean,
+        }
+    }
+
+    /// Returns `true` for [`Int`](Self::Int) and [`Float`](Self::Float).
+    fn is_numeric(&self) -> bool {
+        matches!(self, Self::Int | Self::Float)
+    }
+}
+
+impl std::fmt::Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Int => "int",
+            Self::Float => "float",
+            Self::Str => "str",
+            Self::Bool => "bool",
+        };
+        f.write_str(s)
+    }
+}
+
+/// Unary operator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum UnaryOp {
+    /// Numeric negation.
+    Neg,
+
+    /// Boolean negation.
+    Not,
+}
+
+/// Binary operator.
+///
+/// Both operands always have the same type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum BinaryOp {
+    /// `+`
+    Add,
+
+    /// `-`
+    Sub,
+
+    /// `*`
+    Mul,
+
+    /// `/`
+    Div,
+
+    /// `%`
+    Rem,
+
+    /// `||`
+    Concat,
+
+    /// `==`
+    Eq,
+
+    /// `!=`
+    Ne,
+
+    /// `<`
+    Lt,
+
+    /// `<=`
+    Le,
+
+    /// `>`
+    Gt,
+
+    /// `>=`
+    Ge,
+}
+
+/// Built-in function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Builtin {
+    /// Absolute value.
+    Abs,
+
+    /// Round towards negative infinity.
+    Floor,
+
+    /// Round towards positive infinity.
+    Ceil,
+
+    /// Round to the nearest integer, half-way cases away from zero.
+    Round,
+
+    /// Square root.
+    Sqrt,
+
+    /// Power.
+    Pow,
+
+    /// Minimum of two numbers.
+    Min,
+
+    /// Maximum of two numbers.
+    Max,
+
+    /// Lowercase string.
+    Lower,
+
+    /// Uppercase string.
+    Upper,
+
+    /// Remove leading and trailing whitespace.
+    Trim,
+
+    /// Number of characters.
+    Length,
+
+    /// Substring by 1-based character position and length.
+    Substr,
+
+    /// Replace all occurrences of a substring.
+    Replace,
+
+    /// Check if a string contains a substring.
+    Contains,
+
+    /// Check if a string starts with a prefix.
+    StartsWith,
+
+    /// Check if a string ends with a suffix.
+    EndsWith,
+
+    /// Convert to string.
+    ToStr,
+
+    /// Convert to integer.
+    ToInt,
+
+    /// Convert to float.
+    ToFloat,
+}
+
+impl Builtin {
+    /// Look up function by name.
+    fn from_name(name: &str) -> Option<Self> {
+        let builtin = match name {
+            "abs" => Self::Abs,
+            "floor" => Self::Floor,
+            "ceil" => Self::Ceil,
+            "round" => Self::Round,
+            "sqrt" => Self::Sqrt,
+            "pow" => Self::Pow,
+            "min" => Self::Min,
+            "max" => Self::Max,
+            "lower" => Self::Lower,
+            "upper" => Self::Upper,
+            "trim" => Self::Trim,
+            "length" => Self::Length,
+            "substr" => Self::Substr,
+            "replace" => Self::Replace,
+            "contains" => Self::Contains,
+            "starts_with" => Self::StartsWith,
+            "ends_with" => Self::EndsWith,
+            "to_str" => Self::ToStr,
+            "to_int" => Self::ToInt,
+            "to_float" => Self::ToFloat,
+            _ => {
+                return None;
+            }
+        };
+        Some(builtin)
+    }
+
+    /// Determine the parameter types and the return type for the given argument types.
+    ///
+    /// Returns [`None`] if the arguments are not accepted.
+    fn check(&self, args: &[Type]) -> Option<(Vec<Type>, Type)> {
+        use Type::*;
+
+        let res = match (self, args) {
+            (Self::Abs, [Int]) => (vec![Int], Int),
+            (Self::Abs, [Float]) => (vec![Float], Float),
+            (Self::Floor | Self::Ceil | Self::Round | Self::Sqrt, [t]) if t.is_numeric() => {
+                (vec![Float], Float)
+            }
+            (Self::Pow, [a, b]) if a.is_numeric() && b.is_numeric() => (vec![Float, Float], Float),
+            (Self::Min | Self::Max, [Int, Int]) => (vec![Int, Int], Int),
+            (Self::Min | Self::Max, [a, b]) if a.is_numeric() && b.is_numeric() => {
+                (vec![Float, Float], Float)
+            }
+            (Self::Lower | Self::Upper | Self::Trim, [Str]) => (vec![Str], Str),
+            (Self::Length, [Str]) => (vec![Str], Int),
+            (Self::Substr, [Str, Int, Int]) => (vec![Str, Int, Int], Str),
+            (Self::Replace, [Str, Str, Str]) => (vec![Str, Str, Str], Str),
+            (Self::Contains | Self::StartsWith | Self::EndsWith, [Str, Str]) => {
+                (vec![Str, Str], Bool)
+            }
+            (Self::ToStr, [t]) => (vec![*t], Str),
+            (Self::ToInt, [t]) => (vec![*t], Int),
+            (Self::ToFloat, [t]) => (vec![*t], Float),
+            _ => {
+                return None;
+            }
+        };
+        Some(res)
+    }
+}
+
+/// Type-checked expression.
+#[derive(Debug, Clone)]
+pub(crate) enum Expr {
+    /// Literal value.
+    Literal(Value),
+
+    /// Function parameter, by position.
+    Param(usize),
+
+    /// Unary operation.
+    Unary(UnaryOp, Box<Self>),
+
+    /// Binary operation.
+    Binary(BinaryOp, Box<Self>, Box<Self>),
+
+    /// Short-circuiting `and`.
+    And(Box<Self>, Box<Self>),
+
+    /// Short-circuiting `or`.
+    Or(Box<Self>, Box<Self>),
+
+    /// Conditional.
+    If {
+        /// Condition.
+        cond: Box<Self>,
+
+        /// Result if the condition is true.
+        then: Box<Self>,
+
+        /// Result if the condition is false.
+        otherwise: Box<Self>,
+    },
+
+    /// Call to a built-in function.
+    Call(Builtin, Vec<Self>),
+
+    /// Check if a string matches a regular expression.
+    RegexMatch(Box<Self>, Regex),
+
+    /// Replace all matches of a regular expression.
+    RegexReplace(Box<Self>, Regex, Box<Self>),
+}
+
+/// Function definition.
+#[derive(Debug)]
+pub(crate) struct Function {
+    /// Name.
+    pub(crate) name: String,
+
+    /// Parameter types.
+    pub(crate) params: Vec<Type>,
+
+    /// Return type.
+    pub(crate) return_type: Type,
+
+    /// Body.
+    pub(crate) body: Expr,
+}
+
+/// Expression with its type.
+#[derive(Debug)]
+struct Typed {
+    /// Expression.
+    expr: Expr,
+
+    /// Type of the expression.
+    t: Type,
+
+    /// Nesting depth of the expression tree.
+    depth: usize,
+}
+
+impl Typed {
+    /// Leaf expression.
+    fn leaf(expr: Expr, t: Type) -> Self {
+        Self { expr, t, depth: 1 }
+    }
+
+    /// Promote integer to float.
+    ///
+    /// This is a no-op for all other types.
+    fn promote(self) -> Self {
+        if self.t == Type::Int {
+            Self {
+                expr: Expr::Call(Builtin::ToFloat, vec![self.expr]),
+                t: Type::Float,
+                depth: self.depth + 1,
+            }
+        } else {
+            self
+        }
+    }
+}
+
+/// Parse source code into function definitions.
+pub(crate) fn parse(source: &str) -> DataFusionResult<Vec<Function>> {
+    let mut parser = Parser {
+        tokens: tokenize(source)?,
+        idx: 0,
+        params: vec![],
+        nesting: 0,
+    };
+
+    let mut functions = vec![];
+    let mut names = HashSet::new();
+    while parser.peek().kind != TokenKind::Eof {
+        let pos = parser.peek().pos;
+        let function = parser.function()?;
+        if !names.insert(function.name.clone()) {
+            return Err(DataFusionError::Plan(format!(
+                "{pos}: function `{}` is defined twice",
+                function.name
+            )));
+        }
+        functions.push(function);
+    }
+    Ok(functions)
+}
+
+/// Recursive-descent parser.
+#[derive(Debug)]
+struct Parser {
+    /// Tokens, ending with [`TokenKind::Eof`].
+    tokens: Vec<Token>,
+
+    /// Index of the next token.
+    idx: usize,
+
+    /// Parameters of the function that is currently parsed.
+    params: Vec<(String, Type)>,
+
+    /// Current recursion depth.
+    nesting: usize,
+}
+
+impl Parser {
+    /// Next token, without consuming it.
+    fn peek(&self) -> &Token {
+        &self.tokens[self.idx]
+    }
+
+    /// Consume next token.
+    fn next(&mut self) -> Token {
+        let token = self.tokens[self.idx].clone();
+        if token.kind != TokenKind::Eof {
+            self.idx += 1;
+        }
+        token
+    }
+
+    /// Consume next token if it is the given symbol.
+    fn eat_sym(&mut self, sym: &str) -> bool {
+        if matches!(self.peek().kind, TokenKind::Sym(s) if s == sym) {
+            self.idx += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Consume next token if it is the given keyword.
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        if matches!(&self.peek().kind, TokenKind::Ident(s) if s == keyword) {
+            self.idx += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Error at the given position.
+    fn err<T>(pos: Pos, msg: impl std::fmt::Display) -> DataFusionResult<T> {
+        Err(DataFusionError::Plan(format!("{pos}: {msg}")))
+    }
+
+    /// Error about an unexpected token.
+    fn unexpected<T>(token: &Token, expected: &str) -> DataFusionResult<T> {
+        Self::err(
+            token.pos,
+            format!("expected {expected}, got {}", token.kind),
+        )
+    }
+
+    /// Consume the given symbol or fail.
+    fn expect_sym(&mut self, sym: &str) -> DataFusionResult<()> {
+        if self.eat_sym(sym) {
+            Ok(())
+        } else {
+            Self::unexpected(self.peek(), &format!("`{sym}`"))
+        }
+    }
+
+    /// Consume the given keyword or fail.
+    fn expect_keyword(&mut self, keyword: &str) -> DataFusionResult<()> {
+        if self.eat_keyword(keyword) {
+            Ok(())
+        } else {
+            Self::unexpected(self.peek(), &format!("`{keyword}`"))
+        }
+    }
+
+    /// Consume a name that is NOT a keyword.
+    fn name(&mut self) -> DataFusionResult<(String, Pos)> {
+        let token = self.next();
+        match token.kind {
+            TokenKind::Ident(name) if !KEYWORDS.contains(&name.as_str()) => Ok((name, token.pos)),
+            _ => Self::unexpected(&token, "name"),
+        }
+    }
+
+    /// Consume a type name.
+    fn type_name(&mut self) -> DataFusionResult<Type> {
+        let token = self.next();
+        match &token.kind {
+            TokenKind::Ident(name) => match Type::from_name(name) {
+                Some(t) => Ok(t),
+                None => Self::unexpected(&token, "type (`int`, `float`, `str`, or `bool`)"),
+            },
+            _ => Self::unexpected(&token, "type (`int`, `float`, `str`, or `bool`)"),
+        }
+    }
+
+    /// Parse function definition.
+    ///
+    /// ```text
+    /// name(param: type, ...) -> type = expr [;]
+    /// ```
+    fn function(&mut self) -> DataFusionResult<Function> {
+        let (name, _) = self.name()?;
+
+        self.expect_sym("(")?;
+        self.params.clear();
+        if !self.eat_sym(")") {
+            loop {
+                let (param, pos) = self.name()?;
+                if self.params.iter().any(|(p, _)| p == &param) {
+                    return Self::err(pos, format!("parameter `{param}` is defined twice"));
+                }
+                self.expect_sym(":")?;
+                let t = self.type_name()?;
+                self.params.push((param, t));
+
+                if self.eat_sym(")") {
+                    break;
+                }
+                self.expect_sym(",")?;
+            }
+        }
+
+        self.expect_sym("->")?;
+        let return_type = self.type_name()?;
+        self.expect_sym("=")?;
+
+        let pos = self.peek().pos;
+        let mut body = self.expr()?;
+        if return_type == Type::Float {
+            body = body.promote();
+        }
+        if body.t != return_type {
+            return Self::err(
+                pos,
+                format!(
+                    "body of `{name}` has type {} but function returns {return_type}",
+                    body.t
+                ),
+            );
+        }
+        self.eat_sym(";");
+
+        Ok(Function {
+            name,
+            params: self.params.drain(..).map(|(_, t)| t).collect(),
+            return_type,
+            body: body.expr,
+        })
+    }
+
+    /// Run a nested parser step, guarding against deep recursion.
+    fn nested<F>(&mut self, f: F) -> DataFusionResult<Typed>
+    where
+        F: FnOnce(&mut Self) -> DataFusionResult<Typed>,
+    {
+        if self.nesting >= MAX_DEPTH {
+            return Self::err(self.peek().pos, "expression nested too deeply");
+        }
+        self.nesting += 1;
+        let res = f(self);
+        self.nesting -= 1;
+        res
+    }
+
+    /// Build expression node from its children.
+    fn node(pos: Pos, expr: Expr, t: Type, children: &[usize]) -> DataFusionResult<Typed> {
+        let depth = children.iter().copied().max().unwrap_or_default() + 1;
+        if depth > MAX_DEPTH {
+            return Self::err(pos, "expression nested too deeply");
+        }
+        Ok(Typed { expr, t, depth })
+    }
+
+    /// Parse expression.
+    fn expr(&mut self) -> DataFusionResult<Typed> {
+        self.nested(Self::or)
+    }
+
+    /// Parse `or` chain.
+    fn or(&mut self) -> DataFusionResult<Typed> {
+        let mut lhs = self.and()?;
+        loop {
+            let pos = self.peek().pos;
+            if !self.eat_keyword("or") {
+                return Ok(lhs);
+            }
+            let rhs = self.and()?;
+            Self::expect_type(pos, "or", Type::Bool, &[lhs.t, rhs.t])?;
+            lhs = Self::node(
+                pos,
+                Expr::Or(Box::new(lhs.expr), Box::new(rhs.expr)),
+                Type::Bool,
+                &[lhs.depth, rhs.depth],
+            )?;
+        }
+    }
+
+    /// Parse `and` chain.
+    fn and(&mut self) -> DataFusionResult<Typed> {
+        let mut lhs = self.not()?;
+        loop {
+            let pos = self.peek().pos;
+            if !self.eat_keyword("and") {
+                return Ok(lhs);
+            }
+            let rhs = self.not()?;
+            Self::expect_type(pos, "and", Type::Bool, &[lhs.t, rhs.t])?;
+            lhs = Self::node(
+                pos,
+                Expr::And(Box::new(lhs.expr), Box::new(rhs.expr)),
+                Type::Bool,
+                &[lhs.depth, rhs.depth],
+            )?;
+        }
+    }
+
+    /// Parse `not`.
+    fn not(&mut self) -> DataFusionResult<Typed> {
+        let pos = self.peek().pos;
+        if !self.eat_keyword("not") {
+            return self.comparison();
+        }
+        let inner = self.nested(Self::not)?;
+        Self::expect_type(pos, "not", Type::Bool, &[inner.t])?;
+        Self::node(
+            pos,
+            Expr::Unary(UnaryOp::Not, Box::new(inner.expr)),
+            Type::Bool,
+            &[inner.depth],
+        )
+    }
+
+    /// Parse comparison.
+    ///
+    /// Comparisons do NOT chain, i.e. `a < b < c` is an error.
+    fn comparison(&mut self) -> DataFusionResult<Typed> {
+        let lhs = self.concat()?;
+        let token = self.peek().clone();
+        let op = match token.kind {
+            TokenKind::Sym("==") => BinaryOp::Eq,
+            TokenKind::Sym("!=") => BinaryOp::Ne,
+            TokenKind::Sym("<") => BinaryOp::Lt,
+            TokenKind::Sym("<=") => BinaryOp::Le,
+            TokenKind::Sym(">") => BinaryOp::Gt,
+            TokenKind::Sym(">=") => BinaryOp::Ge,
+            _ => {
+                return Ok(lhs);
+            }
+        };
+        self.next();
+        let rhs = self.concat()?;
+
+        let (lhs, rhs) = Self::unify(token.pos, &token.kind.to_string(), lhs, rhs)?;
+        let ordered = !matches!(op, BinaryOp::Eq | BinaryOp::Ne);
+        if ordered && lhs.t == Type::Bool {
+            return Self::err(
+                token.pos,
+                format!("{} cannot compare {}", token.kind, lhs.t),
+            );
+        }
+
+        Self::node(
+            token.pos,
+            Expr::Binary(op, Box::new(lhs.expr), Box::new(rhs.expr)),
+            Type::Bool,
+            &[lhs.depth, rhs.depth],
+        )
+    }
+
+    /// Parse string concatenation chain.
+    fn concat(&mut self) -> DataFusionResult<Typed> {
+        let mut lhs = self.additive()?;
+        loop {
+            let pos = self.peek().pos;
+            if !self.eat_sym("||") {
+                return Ok(lhs);
+            }
+            let rhs = self.additive()?;
+            Self::expect_type(pos, "`||`", Type::Str, &[lhs.t, rhs.t])?;
+            lhs = Self::node(
+                pos,
+                Expr::Binary(BinaryOp::Concat, Box::new(lhs.expr), Box::new(rhs.expr)),
+                Type::Str,
+                &[lhs.depth, rhs.depth],
+            )?;
+        }
+    }
+
+    /// Parse `+`/`-` chain.
+    fn additive(&mut self) -> DataFusionResult<Typed> {
+        let mut lhs = self.multiplicative()?;
+        loop {
+            let token = self.peek().clone();
+            let op = match token.kind {
+                TokenKind::Sym("+") => BinaryOp::Add,
+                TokenKind::Sym("-") => BinaryOp::Sub,
+                _ => {
+                    return Ok(lhs);
+                }
+            };
+            self.next();
+            let rhs = self.multiplicative()?;
+            lhs = Self::arithmetic(token, op, lhs, rhs)?;
+        }
+    }
+
+    /// Parse `*`/`/`/`%` chain.
+    fn multiplicative(&mut self) -> DataFusionResult<Typed> {
+        let mut lhs = self.unary()?;
+        loop {
+            let token = self.peek().clone();
+            let op = match token.kind {
+                TokenKind::Sym("*") => BinaryOp::Mul,
+                TokenKind::Sym("/") => BinaryOp::Div,
+                TokenKind::Sym("%") => BinaryOp::Rem,
+                _ => {
+                    return Ok(lhs);
+                }
+            };
+            self.next();
+            let rhs = self.unary()?;
+            lhs = Self::arithmetic(token, op, lhs, rhs)?;
+        }
+    }
+
+    /// Type-check arithmetic operation.
+    fn arithmetic(token: Token, op: BinaryOp, lhs: Typed, rhs: Typed) -> DataFusionResult<Typed> {
+        let (lhs, rhs) = Self::unify(token.pos, &token.kind.to_string(), lhs, rhs)?;
+        if !lhs.t.is_numeric() {
+            return Self::err(
+                token.pos,
+                format!("{} expects numbers, got {}", token.kind, lhs.t),
+            );
+        }
+        let t = lhs.t;
+        Self::node(
+            token.pos,
+            Expr::Binary(op, Box::new(lhs.expr), Box::new(rhs.expr)),
+            t,
+            &[lhs.depth, rhs.depth],
+        )
+    }
+
+    /// Parse unary minus.
+    fn unary(&mut self) -> DataFusionResult<Typed> {
+        let pos = self.peek().pos;
+        if !self.eat_sym("-") {
+            return self.primary();
+        }
+        let inner = self.nested(Self::unary)?;
+        if !inner.t.is_numeric() {
+            return Self::err(pos, format!("`-` expects a number, got {}", inner.t));
+        }
+        let t = inner.t;
+        Self::node(
+            pos,
+            Expr::Unary(UnaryOp::Neg, Box::new(inner.expr)),
+            t,
+            &[inner.depth],
+        )
+    }
+
+    /// Parse literal, parameter, call, conditional, or parenthesized expression.
+    fn primary(&mut self) -> DataFusionResult<Typed> {
+        let token = self.next();
+        match token.kind {
+            TokenKind::Int(i) => Ok(Typed::leaf(Expr::Literal(Value::Int(i)), Type::Int)),
+            TokenKind::Float(x) => Ok(Typed::leaf(Expr::Literal(Value::Float(x)), Type::Float)),
+            TokenKind::Str(s) => Ok(Typed::leaf(Expr::Literal(Value::Str(s)), Type::Str)),
+            TokenKind::Sym("(") => {
+                let inner = self.expr()?;
+                self.expect_sym(")")?;
+                Ok(inner)
+            }
+            TokenKind::Ident(ref ident) => match ident.as_str() {
+                "true" => Ok(Typed::leaf(Expr::Literal(Value::Bool(true)), Type::Bool)),
+                "false" => Ok(Typed::leaf(Expr::Literal(Value::Bool(false)), Type::Bool)),
+                "if" => self.conditional(token.pos),
+                _ if KEYWORDS.contains(&ident.as_str()) => Self::unexpected(&token, "expression"),
+                _ if self.eat_sym("(") => self.call(ident, token.pos),
+                _ => match self.params.iter().position(|(p, _)| p == ident) {
+                    Some(idx) => Ok(Typed::leaf(Expr::Param(idx), self.params[idx].1)),
+                    None => Self::err(token.pos, format!("unknown parameter `{ident}`")),
+                },
+            },
+            _ => Self::unexpected(&token, "expression"),
+        }
+    }
+
+    /// Parse `if ... then ... else ...`, after the `if` keyword.
+    fn conditional(&mut self, pos: Pos) -> DataFusionResult<Typed> {
+        let cond = self.expr()?;
+        Self::expect_type(pos, "`if` condition", Type::Bool, &[cond.t])?;
+        self.expect_keyword("then")?;
+        let then = self.expr()?;
+        self.expect_keyword("else")?;
+        let otherwise = self.expr()?;
+
+        let (then, otherwise) = Self::unify(pos, "`if` branches", then, otherwise)?;
+        let t = then.t;
+        Self::node(
+            pos,
+            Expr::If {
+                cond: Box::new(cond.expr),
+                then: Box::new(then.expr),
+                otherwise: Box::new(otherwise.expr),
+            },
+            t,
+            &[cond.depth, then.depth, otherwise.depth],
+        )
+    }
+
+    /// Parse function call, after the opening parenthesis.
+    fn call(&mut self, name: &str, pos: Pos) -> DataFusionResult<Typed> {
+        let mut args = vec![];
+        if !self.eat_sym(")") {
+            loop {
+                args.push(self.expr()?);
+                if self.eat_sym(")") {
+                    break;
+                }
+                self.expect_sym(",")?;
+            }
+        }
+
+        match name {
+            "regex_match" | "regex_replace" => return Self::regex_call(name, pos, args),
+            _ => {}
+        }
+
+        let Some(builtin) = Builtin::from_name(name) else {
+            return Self::err(pos, format!("unknown function `{name}`"));
+        };
+        let arg_types = args.iter().map(|arg| arg.t).collect::<Vec<_>>();
+        let Some((param_types, t)) = builtin.check(&arg_types) else {
+            return Self::err(
+                pos,
+                format!(
+                    "`{name}` does not accept arguments ({})",
+                    arg_types
+                        .iter()
+                        .map(|t| t.to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            );
+        };
+
+        let args = args
+            .into_iter()
+            .zip(param_types)
+            .map(|(arg, t)| if t == Type::Float { arg.promote() } else { arg })
+            .collect::<Vec<_>>();
+        let depths = args.iter().map(|arg| arg.depth).collect::<Vec<_>>();
+        Self::node(
+            pos,
+            Expr::Call(builtin, args.into_iter().map(|arg| arg.expr).collect()),
+            t,
+            &depths,
+        )
+    }
+
+    /// Type-check `regex_match(s, pattern)` and `regex_replace(s, pattern, replacement)`.
+    ///
+    /// The pattern must be a string literal, so it is compiled exactly once.
+    fn regex_call(name: &str, pos: Pos, args: Vec<Typed>) -> DataFusionResult<Typed> {
+        let expected = if name == "regex_match" { 2 } else { 3 };
+        if args.len() != expected {
+            return Self::err(
+                pos,
+                format!("`{name}` expects {expected} arguments, got {}", args.len()),
+            );
+        }
+        let arg_types = args.iter().map(|arg| arg.t).collect::<Vec<_>>();
+        Self::expect_type(pos, &format!("`{name}`"), Type::Str, &arg_types)?;
+
+        let mut args = args.into_iter();
+        let input = args.next().expect("checked length");
+        let Expr::Literal(Value::Str(pattern)) = args.next().expect("checked length").expr else {
+            return Self::err(pos, format!("pattern of `{name}` must be a string literal"));
+        };
+        let regex = RegexBuilder::new(&pattern)
+            .size_limit(REGEX_SIZE_LIMIT)
+            .build()
+            .map_err(|e| DataFusionError::Plan(format!("{pos}: invalid regex: {e}")))?;
+
+        match args.next() {
+            None => Self::node(
+                pos,
+                Expr::RegexMatch(Box::new(input.expr), regex),
+                Type::Bool,
+                &[input.depth],
+            ),
+            Some(replacement) => Self::node(
+                pos,
+                Expr::RegexReplace(Box::new(input.expr), regex, Box::new(replacement.expr)),
+                Type::Str,
+                &[input.depth, replacement.depth],
+            ),
+        }
+    }
+
+    /// Check that all operands have the expected type.
+    fn expect_type(pos: Pos, what: &str, expected: Type, actual: &[Type]) -> DataFusionResult<()> {
+        match actual.iter().find(|t| **t != expected) {
+            Some(t) => Self::err(pos, format!("{what} expects {expected}, got {t}")),
+            None => Ok(()),
+        }
+    }
+
+    /// Bring two operands to the same type, promoting integers to floats if required.
+    fn unify(pos: Pos, what: &str, lhs: Typed, rhs: Typed) -> DataFusionResult<(Typed, Typed)> {
+        match (lhs.t, rhs.t) {
+            (a, b) if a == b => Ok((lhs, rhs)),
+            (Type::Int, Type::Float) | (Type::Float, Type::Int) => {
+                Ok((lhs.promote(), rhs.promote()))
+            }
+            (a, b) => Self::err(pos, format!("{what} cannot combine {a} and {b}")),
+        }
+    }
+}
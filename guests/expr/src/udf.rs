@@ -0,0 +1,227 @@
+//! [`ScalarUDFImpl`] for functions of the expression language.
+use std::{any::Any, hash::Hash, sync::Arc};
+
+use arrow::{
+    array::{Array, ArrayRef, AsArray, BooleanArray, Float64Array, Int64Array, StringArray},
+    compute::cast,
+    datatypes::{DataType, Float64Type, Int64Type},
+};
+use datafusion_common::{Result as DataFusionResult, exec_err, plan_err};
+use datafusion_expr::{ColumnarValue, ScalarFunctionArgs, ScalarUDFImpl, Signature, Volatility};
+use uuid::Uuid;
+
+use crate::{
+    eval::{Value, eval},
+    parser::{Function, Type},
+};
+
+/// UDF that evaluates a [function](Function) row by row.
+#[derive(Debug)]
+pub(crate) struct ExprScalarUDF {
+    /// Type-checked function.
+    function: Function,
+
+    /// We treat every UDF as unique, but we need a proxy value to express that.
+    id: Uuid,
+
+    /// Signature of the UDF.
+    ///
+    /// We store this here because [`ScalarUDFImpl::signature`] requires us to return a reference.
+    signature: Signature,
+}
+
+impl ExprScalarUDF {
+    /// Create new UDF.
+    pub(crate) fn new(function: Function) -> Self {
+        let signature = Signature::exact(
+            function.params.iter().map(Type::data_type).collect(),
+            Volatility::Immutable,
+        );
+
+        Self {
+            function,
+            id: Uuid::new_v4(),
+            signature,
+        }
+    }
+}
+
+impl PartialEq<Self> for ExprScalarUDF {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for ExprScalarUDF {}
+
+impl Hash for ExprScalarUDF {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+impl ScalarUDFImpl for ExprScalarUDF {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        &self.function.name
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn return_type(&self, arg_types: &[DataType]) -> DataFusionResult<DataType> {
+        if arg_types.len() != self.function.params.len() {
+            return plan_err!(
+                "`{}` expects {} parameters but got {}",
+                self.name(),
+                self.function.params.len(),
+                arg_types.len()
+            );
+        }
+        for (pos, (actual, expected)) in arg_types.iter().zip(&self.function.params).enumerate() {
+            if actual != &expected.data_type() {
+                return plan_err!(
+                    "argument {} of `{}` should be {}, got {actual}",
+                    pos + 1,
+                    self.name(),
+                    expected.data_type()
+                );
+            }
+        }
+        Ok(self.function.return_type.data_type())
+    }
+
+    fn invoke_with_args(&self, args: ScalarFunctionArgs) -> DataFusionResult<ColumnarValue> {
+        let ScalarFunctionArgs {
+            args,
+            arg_fields: _,
+            number_rows,
+            return_field,
+            config_options: _,
+        } = args;
+
+        let return_type = self.function.return_type.data_type();
+        if return_field.data_type() != &return_type {
+            return exec_err!(
+                "`{}` returns {return_type} but was asked to produce {}",
+                self.name(),
+                return_field.data_type()
+            );
+        }
+        if args.len() != self.function.params.len() {
+            return exec_err!(
+                "`{}` expects {} arguments but got {}",
+                self.name(),
+                self.function.params.len(),
+                args.len()
+            );
+        }
+
+        let arrays = args
+            .into_iter()
+            .zip(&self.function.params)
+            .enumerate()
+            .map(|(i, (column_value, t))| {
+                let array = column_value.to_array(number_rows)?;
+                if array.len() != number_rows {
+                    return exec_err!(
+                        "array passed for argument {} should have {number_rows} rows but has {}",
+                        i + 1,
+                        array.len()
+                    );
+                }
+
+                // convert alternative layouts to the canonical one
+                let expected = t.data_type();
+                if array.data_type() == &expected {
+                    Ok(array)
+                } else {
+                    Ok(cast(&array, &expected)?)
+                }
+            })
+            .collect::<DataFusionResult<Vec<_>>>()?;
+
+        // allocate params vector once and reuse for each row
+        let mut params = Vec::with_capacity(arrays.len());
+        let mut output = Vec::with_capacity(number_rows);
+        for row in 0..number_rows {
+            params.clear();
+            for (array, t) in arrays.iter().zip(&self.function.params) {
+                if let Some(v) = value_at(array, *t, row) {
+                    params.push(v);
+                }
+            }
+
+            // NULL in, NULL out
+            if params.len() == arrays.len() {
+                output.push(Some(eval(&self.function.body, &params)?));
+            } else {
+                output.push(None);
+            }
+        }
+
+        Ok(ColumnarValue::Array(build_array(
+            self.function.return_type,
+            output,
+        )))
+    }
+}
+
+/// Read value of the given type from an array, or [`None`] if the row is NULL.
+fn value_at(array: &ArrayRef, t: Type, row: usize) -> Option<Value> {
+    if array.is_null(row) {
+        return None;
+    }
+    let v = match t {
+        Type::Int => Value::Int(array.as_primitive::<Int64Type>().value(row)),
+        Type::Float => Value::Float(array.as_primitive::<Float64Type>().value(row)),
+        Type::Str => Value::Str(array.as_string::<i32>().value(row).to_owned()),
+        Type::Bool => Value::Bool(array.as_boolean().value(row)),
+    };
+    Some(v)
+}
+
+/// Build array of the given type.
+///
+/// The type checker guarantees that all values have this type.
+fn build_array(t: Type, values: Vec<Option<Value>>) -> ArrayRef {
+    let values = values.into_iter();
+    match t {
+        Type::Int => Arc::new(
+            values
+                .map(|v| match v {
+                    Some(Value::Int(i)) => Some(i),
+                    _ => None,
+                })
+                .collect::<Int64Array>(),
+        ),
+        Type::Float => Arc::new(
+            values
+                .map(|v| match v {
+                    Some(Value::Float(x)) => Some(x),
+                    _ => None,
+                })
+                .collect::<Float64Array>(),
+        ),
+        Type::Str => Arc::new(
+            values
+                .map(|v| match v {
+                    Some(Value::Str(s)) => Some(s),
+                    _ => None,
+                })
+                .collect::<StringArray>(),
+        ),
+        Type::Bool => Arc::new(
+            values
+                .map(|v| match v {
+                    Some(Value::Bool(b)) => Some(b),
+                    _ => None,
+                })
+                .collect::<BooleanArray>(),
+        ),
+    }
+}
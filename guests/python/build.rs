@@ -2,7 +2,8 @@
 //!
 //! This ensures this:
 //! - **root file system:** If the `PYTHON_SDK_DIR` environment variable is set, we assume that we must package
-//!   the [Python Standard Library].
+//!   the [Python Standard Library]. If the `PYTHON_BYTECODE` environment variable is set to a non-empty value, the
+//!   pre-compiled bytecode is packaged as well.
 //!
 //!
 //! [CPython]: https://www.python.org/
@@ -11,7 +12,10 @@
 use std::{fs::File, io::Write, path::PathBuf};
 
 /// File endings that should be skipped when bundling the up the Python lib.
-const SKIP_ENDINGS: &[&str] = &[".a", ".pyc", ".wasm"];
+const SKIP_ENDINGS: &[&str] = &[".a", ".wasm"];
+
+/// File ending of Python bytecode, which is only bundled if requested.
+const BYTECODE_ENDING: &str = ".pyc";
 
 /// File endings that are mocked as empty files.
 const MOCK_ENDINGS: &[&str] = &[".so"];
@@ -30,12 +34,14 @@ fn main() {
 /// [Python Standard Library]: https://docs.python.org/3/library/index.html
 fn bundle_python_lib() {
     println!("cargo:rerun-if-env-changed=PYTHON_SDK_DIR");
+    println!("cargo:rerun-if-env-changed=PYTHON_BYTECODE");
     let tar_path = PathBuf::from(std::env::var("OUT_DIR").unwrap()).join("python-lib.tar");
     let Ok(lib_dir) = std::env::var("PYTHON_SDK_DIR") else {
         std::fs::write(&tar_path, b"").unwrap();
         return;
     };
     let lib_dir = PathBuf::from(lib_dir);
+    let bytecode = std::env::var("PYTHON_BYTECODE").is_ok_and(|s| !s.is_empty());
 
     let file = File::create(&tar_path).unwrap();
     let mut archive = tar::Builder::new(file);
@@ -50,6 +56,12 @@ fn bundle_python_lib() {
         if SKIP_ENDINGS.iter().any(|ending| path_str.ends_with(ending)) || path_str.is_empty() {
             continue;
         }
+        if !bytecode
+            && (path_str.ends_with(BYTECODE_ENDING)
+                || entry.file_type().is_dir() && entry.file_name() == "__pycache__")
+        {
+            continue;
+        }
 
         if MOCK_ENDINGS.iter().any(|ending| path_str.ends_with(ending)) {
             const MOCK: &[u8] = b"";
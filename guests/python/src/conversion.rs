@@ -1,29 +1,39 @@
 //! Conversion routes from [`arrow`] to/from Python.
-use std::{ops::ControlFlow, sync::Arc};
+use std::sync::Arc;
 
 use arrow::{
     array::{
-        Array, ArrayRef, BinaryBuilder, BooleanBuilder, Date32Builder, DurationMicrosecondBuilder,
-        Float64Builder, Int64Builder, NullBuilder, StringBuilder, Time64MicrosecondBuilder,
+        Array, ArrayRef, ArrowNativeTypeOp, BinaryBuilder, BooleanBuilder, Date32Builder,
+        DurationMicrosecondBuilder, FixedSizeBinaryBuilder, Float64Builder, Int64Builder,
+        IntervalMonthDayNanoBuilder, ListArray, MapArray, NullBufferBuilder, NullBuilder,
+        PrimitiveArray, PrimitiveBuilder, StringBuilder, StructArray, Time64MicrosecondBuilder,
         TimestampMicrosecondBuilder,
     },
-    datatypes::{DataType, TimeUnit},
+    buffer::{NullBuffer, OffsetBuffer},
+    compute::cast,
+    datatypes::{
+        ArrowNativeType, DECIMAL128_MAX_PRECISION, DataType, Decimal128Type, Decimal256Type,
+        DecimalType, Field, FieldRef, Fields, IntervalMonthDayNano, IntervalUnit, TimeUnit,
+    },
 };
 use chrono::{DateTime, Datelike, NaiveDate, TimeZone, Timelike, Utc};
 use datafusion_common::{
     cast::{
-        as_binary_array, as_boolean_array, as_date32_array, as_duration_microsecond_array,
-        as_float64_array, as_int64_array, as_null_array, as_string_array,
-        as_time64_microsecond_array, as_timestamp_microsecond_array,
+        as_binary_array, as_boolean_array, as_date32_array, as_decimal128_array,
+        as_decimal256_array, as_duration_microsecond_array, as_fixed_size_binary_array,
+        as_float64_array, as_int64_array, as_interval_mdn_array, as_list_array, as_map_array,
+        as_null_array, as_string_array, as_struct_array, as_time64_microsecond_array,
+        as_timestamp_microsecond_array,
     },
     error::Result as DataFusionResult,
     exec_datafusion_err, exec_err,
 };
 use pyo3::{
-    Bound, BoundObject, IntoPyObjectExt, PyAny, Python,
+    Bound, BoundObject, IntoPyObjectExt, PyAny, PyResult, Python, intern,
     types::{
-        PyAnyMethods, PyBytes, PyDate, PyDateAccess, PyDateTime, PyDelta, PyInt, PyNone,
-        PyStringMethods, PyTime, PyTimeAccess, PyTzInfoAccess,
+        PyAnyMethods, PyBytes, PyDate, PyDateAccess, PyDateTime, PyDelta, PyDeltaAccess, PyDict,
+        PyDictMethods, PyInt, PyList, PyListMethods, PyNone, PyString, PyStringMethods, PyTime,
+        PyTimeAccess, PyTuple, PyTupleMethods, PyTzInfo, PyTzInfoAccess,
     },
 };
 
@@ -38,27 +48,94 @@ use crate::{
 pub(crate) type PythonOptValueIter<'a> =
     Box<dyn Iterator<Item = DataFusionResult<Option<Bound<'a, PyAny>>>> + 'a>;
 
-/// Iterator of Python values and the information if the Python method should be called for this row
-/// ([`ControlFlow::Continue`], because we continue scanning for arguments) or not ([`ControlFlow::Break`]).
+/// Python argument for a single row.
+///
+/// This implements the NULL handling of parameters:
+///
+/// - **optional** (`T | None` or `Optional[T]`): NULLs are passed as `None`, so the value is always
+///   [passed](Self::Pass).
+/// - **non-optional** (`T`): NULLs [skip the row](Self::SkipRow), i.e. the function is NOT called and the output is
+///   NULL.
+///
+/// Return values follow the same annotations: an optional return type turns `None` into NULL, while returning `None`
+/// for a non-optional one is an error.
+#[derive(Debug)]
+pub(crate) enum PythonArg<'a> {
+    /// Pass value to the function.
+    Pass(Bound<'a, PyAny>),
+
+    /// Do NOT call the function for this row, the output is NULL.
+    SkipRow,
+}
+
+/// Iterator of [`PythonArg`]s.
 ///
 /// This is used to feed values into a Python function.
 pub(crate) type PythonValueIter<'a> =
-    Box<dyn Iterator<Item = DataFusionResult<ControlFlow<(), Bound<'a, PyAny>>>> + 'a>;
+    Box<dyn Iterator<Item = DataFusionResult<PythonArg<'a>>> + 'a>;
 
 impl PythonType {
     /// Arrow [`DataType`] for a given Python type.
     pub(crate) fn data_type(&self) -> DataType {
         match self {
             Self::Bool => DataType::Boolean,
-            Self::DateTime => DataType::Timestamp(TimeUnit::Microsecond, None),
+            Self::DateTime { tz } => DataType::Timestamp(TimeUnit::Microsecond, tz.clone()),
+            Self::Decimal { precision, scale } if *precision <= DECIMAL128_MAX_PRECISION => {
+                DataType::Decimal128(*precision, *scale)
+            }
+            Self::Decimal { precision, scale } => DataType::Decimal256(*precision, *scale),
             Self::Float => DataType::Float64,
             Self::Int => DataType::Int64,
+            Self::Interval => DataType::Interval(IntervalUnit::MonthDayNano),
+            Self::Json => DataType::Utf8,
             Self::None => DataType::Null,
+            #[cfg(feature = "pyarrow")]
+            Self::PyArrow(dt) => dt.clone(),
             Self::Str => DataType::Utf8,
-            Self::Bytes => DataType::Binary,
+            Self::Struct { fields, .. } => DataType::Struct(struct_fields(fields)),
+            Self::Bytes { size: None } => DataType::Binary,
+            Self::Bytes { size: Some(size) } => DataType::FixedSizeBinary(*size),
             Self::Date => DataType::Date32,
             Self::Time => DataType::Time64(TimeUnit::Microsecond),
             Self::Timedelta => DataType::Duration(TimeUnit::Microsecond),
+            Self::Dict(value) => DataType::Map(map_entries_field(value), false),
+            Self::List(element) => DataType::List(Arc::new(element.field(LIST_ELEMENT_NAME))),
+            Self::Tuple(elements) => DataType::Struct(tuple_fields(elements)),
+            Self::Uuid { as_str: false } => DataType::FixedSizeBinary(UUID_BYTES),
+            Self::Uuid { as_str: true } => DataType::Utf8,
+        }
+    }
+
+    /// Returns `true` if Arrow data of the given type can be passed to or produced for this Python type.
+    ///
+    /// Python does not distinguish between the different string and binary layouts of Arrow, so [`str`](Self::Str)
+    /// also accepts `LargeUtf8` and `Utf8View`, and [`bytes`](Self::Bytes) also accepts `LargeBinary` and
+    /// `BinaryView`. These are cast to/from the [canonical type](Self::data_type).
+    pub(crate) fn accepts_data_type(&self, dt: &DataType) -> bool {
+        match self {
+            Self::Json | Self::Str | Self::Uuid { as_str: true } => matches!(
+                dt,
+                DataType::Utf8 | DataType::LargeUtf8 | DataType::Utf8View
+            ),
+            Self::Bytes { size: None } => matches!(
+                dt,
+                DataType::Binary | DataType::LargeBinary | DataType::BinaryView
+            ),
+            _ => dt == &self.data_type(),
+        }
+    }
+
+    /// Returns `true` if Arrow data of the given type can be passed to this Python type.
+    ///
+    /// This is [`accepts_data_type`](Self::accepts_data_type), but [`bytes`](Self::Bytes) additionally accepts
+    /// `FixedSizeBinary` of any size and [`float`](Self::Float) additionally accepts `Float16` and `Float32`, which
+    /// are upcast losslessly. This is NOT accepted for return values, since the result values may have a different
+    /// length or may not fit into the narrower type.
+    pub(crate) fn accepts_arg_data_type(&self, dt: &DataType) -> bool {
+        match self {
+            Self::Bytes { size: None } if matches!(dt, DataType::FixedSizeBinary(_)) => true,
+            Self::Float if matches!(dt, DataType::Float16 | DataType::Float32) => true,
+            _ => self.accepts_data_type(dt),
         }
     }
 
@@ -86,18 +163,49 @@ impl PythonType {
 
                 Ok(Box::new(it))
             }
-            Self::DateTime => {
-                let array = as_timestamp_microsecond_array(array)?;
-                if let Some(tz) = array.timezone() {
-                    return exec_err!("expected no time zone but got {tz}");
-                }
-
-                let it = array.into_iter().map(move |maybe_val| {
-                    maybe_val
+            Self::DateTime { tz } => {
+                let array = match (array.data_type(), tz) {
+                    (DataType::Timestamp(_, Some(actual)), None) => {
+                        return exec_err!("expected no time zone but got {actual}");
+                    }
+                    (DataType::Timestamp(_, None), Some(tz)) => {
+                        return exec_err!("expected time zone {tz} but got none");
+                    }
+                    (DataType::Timestamp(TimeUnit::Microsecond, _), _) => {
+                        as_timestamp_microsecond_array(array)?.clone()
+                    }
+                    (DataType::Timestamp(_, actual), _) => {
+                        // timestamps are stored relative to UTC, so the time zone does not change the values
+                        let array = cast(
+                            array,
+                            &DataType::Timestamp(TimeUnit::Microsecond, actual.clone()),
+                        )?;
+                        as_timestamp_microsecond_array(&array)?.clone()
+                    }
+                    (other, _) => {
+                        return exec_err!("expected timestamp but got {other}");
+                    }
+                };
+                let tzinfo = tz
+                    .as_deref()
+                    .map(|tz| {
+                        py_tzinfo(py, tz).map_err(|e| {
+                            exec_datafusion_err!("cannot resolve time zone {tz:?}: {e}")
+                        })
+                    })
+                    .transpose()?;
+                let utc = PyTzInfo::utc(py)
+                    .map_err(|e| exec_datafusion_err!("cannot get UTC tzinfo: {e}"))?
+                    .to_owned();
+
+                let it = (0..array.len()).map(move |i| {
+                    array
+                        .is_valid(i)
+                        .then(|| array.value(i))
                         .map(|val| {
                             let dt = DateTime::from_timestamp_micros(val).ok_or_else(|| exec_datafusion_err!("cannot create DateTime object from microsecond timestamp: {val}"))?;
 
-                            PyDateTime::new(
+                            let py_dt = PyDateTime::new(
                                 py,
                                 dt.year(),
                                 dt
@@ -121,12 +229,71 @@ impl PythonType {
                                     .try_into()
                                     .map_err(|e| exec_datafusion_err!("second out of range: {e}"))?,
                                 dt.timestamp_subsec_micros(),
-                                None,
+                                tzinfo.is_some().then_some(&utc),
                             ).map_err(|e| {
                                 exec_datafusion_err!("cannot create PyDateTime: {e}")
                             })?.into_bound_py_any(py).map_err(|e| {
                                 exec_datafusion_err!("cannot convert PyDateTime to any: {e}")
-                            })
+                            })?;
+
+                            match &tzinfo {
+                                Some(tzinfo) => py_dt
+                                    .call_method1(intern!(py, "astimezone"), (tzinfo,))
+                                    .map_err(|e| {
+                                        exec_datafusion_err!("cannot convert PyDateTime to time zone: {e}")
+                                    }),
+                                None => Ok(py_dt),
+                            }
+                        })
+                        .transpose()
+                });
+
+                Ok(Box::new(it))
+            }
+            Self::Decimal { precision, scale } => {
+                let (precision, scale) = (*precision, *scale);
+
+                // https://docs.python.org/3/library/decimal.html#decimal.Decimal
+                let type_decimal = py
+                    .import(intern!(py, "decimal"))
+                    .and_then(|m| m.getattr(intern!(py, "Decimal")))
+                    .map_err(|e| exec_datafusion_err!("cannot import `decimal.Decimal`: {e}"))?;
+
+                if precision <= DECIMAL128_MAX_PRECISION {
+                    let array = as_decimal128_array(array)?;
+                    Ok(decimal_to_python(array, precision, scale, type_decimal))
+                } else {
+                    let array = as_decimal256_array(array)?;
+                    Ok(decimal_to_python(array, precision, scale, type_decimal))
+                }
+            }
+            Self::Interval => {
+                let array = as_interval_mdn_array(array)?;
+
+                let it = array.into_iter().map(move |maybe_val| {
+                    maybe_val
+                        .map(|val| {
+                            (val.months, val.days, val.nanoseconds)
+                                .into_bound_py_any(py)
+                                .map_err(|e| {
+                                    exec_datafusion_err!("cannot convert interval to Python: {e}")
+                                })
+                        })
+                        .transpose()
+                });
+
+                Ok(Box::new(it))
+            }
+            Self::Json => {
+                let array = as_string_array(array)?;
+                let json_loads = py_json_function(py, intern!(py, "loads"))?;
+
+                let it = array.into_iter().map(move |maybe_val| {
+                    maybe_val
+                        .map(|val| {
+                            json_loads
+                                .call1((val,))
+                                .map_err(|e| exec_datafusion_err!("cannot decode JSON: {e}"))
                         })
                         .transpose()
                 });
@@ -177,6 +344,10 @@ impl PythonType {
 
                 Ok(Box::new(it))
             }
+            #[cfg(feature = "pyarrow")]
+            Self::PyArrow(_) => {
+                exec_err!("`pyarrow.Array` is only supported for columnar functions")
+            }
             Self::Str => {
                 let array = as_string_array(array)?;
 
@@ -194,7 +365,50 @@ impl PythonType {
 
                 Ok(Box::new(it))
             }
-            Self::Bytes => {
+            Self::Struct { fields, dataclass } => {
+                let array = as_struct_array(array)?;
+                if array.num_columns() != fields.len() {
+                    return exec_err!(
+                        "expected struct with {} fields but got {}",
+                        fields.len(),
+                        array.num_columns()
+                    );
+                }
+                let columns = fields
+                    .iter()
+                    .zip(array.columns())
+                    .map(|((_name, field), column)| {
+                        field.arrow_to_python_values(column.as_ref(), array.nulls(), py)
+                    })
+                    .collect::<DataFusionResult<Vec<_>>>()?;
+                let names = fields
+                    .iter()
+                    .map(|(name, _field)| PyString::new(py, name))
+                    .collect::<Vec<_>>();
+                let dataclass = dataclass.as_ref().map(|class| class.0.bind(py).clone());
+
+                let it = (0..array.len()).map(move |i| {
+                    if array.is_null(i) {
+                        return Ok(None);
+                    }
+
+                    let dict = PyDict::new(py);
+                    for (name, column) in names.iter().zip(&columns) {
+                        dict.set_item(name, &column[i]).map_err(|e| {
+                            exec_datafusion_err!("cannot insert value into Python dict: {e}")
+                        })?;
+                    }
+                    match &dataclass {
+                        Some(class) => class.call((), Some(&dict)).map(Some).map_err(|e| {
+                            exec_datafusion_err!("cannot create Python dataclass: {e}")
+                        }),
+                        None => Ok(Some(dict.into_any())),
+                    }
+                });
+
+                Ok(Box::new(it))
+            }
+            Self::Bytes { size: None } => {
                 let array = as_binary_array(array)?;
 
                 let it = array.into_iter().map(move |maybe_val| {
@@ -211,6 +425,23 @@ impl PythonType {
 
                 Ok(Box::new(it))
             }
+            Self::Bytes { size: Some(_) } => {
+                let array = as_fixed_size_binary_array(array)?;
+
+                let it = array.into_iter().map(move |maybe_val| {
+                    maybe_val
+                        .map(|val| {
+                            PyBytes::new(py, val).into_bound_py_any(py).map_err(|e| {
+                                exec_datafusion_err!(
+                                    "cannot convert Rust `&[u8]` value to Python bytes: {e}"
+                                )
+                            })
+                        })
+                        .transpose()
+                });
+
+                Ok(Box::new(it))
+            }
             Self::Date => {
                 let array = as_date32_array(array)?;
 
@@ -293,30 +524,340 @@ impl PythonType {
 
                 Ok(Box::new(it))
             }
+            Self::Dict(value) => {
+                let array = as_map_array(array)?;
+                let key = PythonNullableType {
+                    t: Self::Str,
+                    nullable: false,
+                };
+                let keys = key.arrow_to_python_values(array.keys().as_ref(), None, py)?;
+                let values = value.arrow_to_python_values(array.values().as_ref(), None, py)?;
+                let offsets = array.value_offsets();
+
+                let it = (0..array.len()).map(move |i| {
+                    if array.is_null(i) {
+                        return Ok(None);
+                    }
+
+                    let dict = PyDict::new(py);
+                    for j in (offsets[i] as usize)..(offsets[i + 1] as usize) {
+                        dict.set_item(&keys[j], &values[j]).map_err(|e| {
+                            exec_datafusion_err!("cannot insert value into Python dict: {e}")
+                        })?;
+                    }
+                    Ok(Some(dict.into_any()))
+                });
+
+                Ok(Box::new(it))
+            }
+            Self::List(element) => {
+                let array = as_list_array(array)?;
+                let values = element.arrow_to_python_values(array.values().as_ref(), None, py)?;
+                let offsets = array.value_offsets();
+
+                let it = (0..array.len()).map(move |i| {
+                    if array.is_null(i) {
+                        return Ok(None);
+                    }
+
+                    let list = PyList::new(
+                        py,
+                        &values[(offsets[i] as usize)..(offsets[i + 1] as usize)],
+                    )
+                    .map_err(|e| exec_datafusion_err!("cannot create Python list: {e}"))?;
+                    Ok(Some(list.into_any()))
+                });
+
+                Ok(Box::new(it))
+            }
+            Self::Tuple(elements) => {
+                let array = as_struct_array(array)?;
+                if array.num_columns() != elements.len() {
+                    return exec_err!(
+                        "expected struct with {} fields but got {}",
+                        elements.len(),
+                        array.num_columns()
+                    );
+                }
+                let columns = elements
+                    .iter()
+                    .zip(array.columns())
+                    .map(|(element, column)| {
+                        element.arrow_to_python_values(column.as_ref(), array.nulls(), py)
+                    })
+                    .collect::<DataFusionResult<Vec<_>>>()?;
+
+                let it = (0..array.len()).map(move |i| {
+                    if array.is_null(i) {
+                        return Ok(None);
+                    }
+
+                    let tuple = PyTuple::new(py, columns.iter().map(|column| &column[i]))
+                        .map_err(|e| exec_datafusion_err!("cannot create Python tuple: {e}"))?;
+                    Ok(Some(tuple.into_any()))
+                });
+
+                Ok(Box::new(it))
+            }
+            Self::Uuid { as_str } => {
+                let type_uuid = py_uuid_type(py)?;
+
+                if *as_str {
+                    let array = as_string_array(array)?;
+
+                    let it = array.into_iter().map(move |maybe_val| {
+                        maybe_val
+                            .map(|val| {
+                                type_uuid.call1((val,)).map_err(|e| {
+                                    exec_datafusion_err!(
+                                        "cannot create Python `UUID` from {val:?}: {e}"
+                                    )
+                                })
+                            })
+                            .transpose()
+                    });
+
+                    Ok(Box::new(it))
+                } else {
+                    let array = as_fixed_size_binary_array(array)?;
+
+                    let it = array.into_iter().map(move |maybe_val| {
+                        maybe_val
+                            .map(|val| {
+                                let kwargs = PyDict::new(py);
+                                kwargs
+                                    .set_item(intern!(py, "bytes"), PyBytes::new(py, val))
+                                    .and_then(|()| type_uuid.call((), Some(&kwargs)))
+                                    .map_err(|e| {
+                                        exec_datafusion_err!("cannot create Python `UUID`: {e}")
+                                    })
+                            })
+                            .transpose()
+                    });
+
+                    Ok(Box::new(it))
+                }
+            }
         }
     }
 
     /// Get a builder for the Arrow output [`Array`].
     ///
     /// This needs an "attached" [`Python`] to create Python objects.
-    fn python_to_arrow<'py>(&self, num_rows: usize) -> Box<dyn ArrayBuilder<'py>> {
+    fn python_to_arrow<'py>(
+        &self,
+        py: Python<'py>,
+        num_rows: usize,
+    ) -> Box<dyn ArrayBuilder<'py> + 'py> {
         match self {
             Self::Bool => Box::new(BooleanBuilder::with_capacity(num_rows)),
-            Self::DateTime => Box::new(TimestampMicrosecondBuilder::with_capacity(num_rows)),
+            Self::DateTime { tz: None } => {
+                Box::new(TimestampMicrosecondBuilder::with_capacity(num_rows))
+            }
+            Self::DateTime { tz: Some(tz) } => Box::new(TimestampTzArrayBuilder {
+                builder: TimestampMicrosecondBuilder::with_capacity(num_rows)
+                    .with_timezone(Arc::clone(tz)),
+                epoch: None,
+            }),
+            Self::Decimal { precision, scale } if *precision <= DECIMAL128_MAX_PRECISION => {
+                Box::new(DecimalArrayBuilder::<Decimal128Type> {
+                    builder: PrimitiveBuilder::with_capacity(num_rows)
+                        .with_data_type(self.data_type()),
+                    precision: *precision,
+                    scale: *scale,
+                })
+            }
+            Self::Decimal { precision, scale } => Box::new(DecimalArrayBuilder::<Decimal256Type> {
+                builder: PrimitiveBuilder::with_capacity(num_rows).with_data_type(self.data_type()),
+                precision: *precision,
+                scale: *scale,
+            }),
             Self::Float => Box::new(Float64Builder::with_capacity(num_rows)),
+            Self::Json => Box::new(JsonArrayBuilder {
+                builder: StringBuilder::with_capacity(num_rows, 1024),
+                json_dumps: None,
+            }),
             Self::Int => Box::new(Int64Builder::with_capacity(num_rows)),
+            Self::Interval => Box::new(IntervalMonthDayNanoBuilder::with_capacity(num_rows)),
             Self::None => Box::new(NullBuilder::new()),
+            #[cfg(feature = "pyarrow")]
+            Self::PyArrow(_) => unreachable!("columnar functions do not use row-based builders"),
             Self::Str => Box::new(StringBuilder::with_capacity(num_rows, 1024)),
-            Self::Bytes => Box::new(BinaryBuilder::with_capacity(num_rows, 1024)),
+            Self::Struct { fields, dataclass } => Box::new(StructArrayBuilder {
+                fields: struct_fields(fields),
+                dataclass: dataclass.as_ref().map(|class| class.0.bind(py).clone()),
+                elements: fields
+                    .iter()
+                    .map(|(_name, field)| field.python_to_arrow(py, num_rows))
+                    .collect(),
+                nulls: NullBufferBuilder::new(num_rows),
+            }),
+            Self::Bytes { size: None } => Box::new(BinaryBuilder::with_capacity(num_rows, 1024)),
+            Self::Bytes { size: Some(size) } => Box::new(FixedSizeBytesArrayBuilder {
+                builder: FixedSizeBinaryBuilder::with_capacity(num_rows, *size),
+                size: *size,
+            }),
             Self::Date => Box::new(Date32Builder::with_capacity(num_rows)),
             Self::Time => Box::new(Time64MicrosecondBuilder::with_capacity(num_rows)),
             Self::Timedelta => Box::new(DurationMicrosecondBuilder::with_capacity(num_rows)),
+            Self::Dict(value) => Box::new(DictArrayBuilder {
+                entries: map_entries_field(value),
+                keys: StringBuilder::new(),
+                values: value.python_to_arrow(py, 0),
+                offsets: vec![0],
+                nulls: NullBufferBuilder::new(num_rows),
+            }),
+            Self::List(element) => Box::new(ListArrayBuilder {
+                field: Arc::new(element.field(LIST_ELEMENT_NAME)),
+                values: element.python_to_arrow(py, 0),
+                offsets: vec![0],
+                nulls: NullBufferBuilder::new(num_rows),
+            }),
+            Self::Tuple(elements) => Box::new(TupleArrayBuilder {
+                fields: tuple_fields(elements),
+                elements: elements
+                    .iter()
+                    .map(|element| element.python_to_arrow(py, num_rows))
+                    .collect(),
+                nulls: NullBufferBuilder::new(num_rows),
+            }),
+            Self::Uuid { as_str } => Box::new(UuidArrayBuilder {
+                inner: if *as_str {
+                    Box::new(StringBuilder::with_capacity(num_rows, 36 * num_rows))
+                } else {
+                    Box::new(FixedSizeBytesArrayBuilder {
+                        builder: FixedSizeBinaryBuilder::with_capacity(num_rows, UUID_BYTES),
+                        size: UUID_BYTES,
+                    })
+                },
+                as_str: *as_str,
+                type_uuid: None,
+            }),
         }
     }
 }
 
+/// Convert decimal array to Python `Decimal` values.
+fn decimal_to_python<'a, T>(
+    array: &'a PrimitiveArray<T>,
+    precision: u8,
+    scale: i8,
+    type_decimal: Bound<'a, PyAny>,
+) -> PythonOptValueIter<'a>
+where
+    T: DecimalType,
+{
+    let it = array.into_iter().map(move |maybe_val| {
+        maybe_val
+            .map(|val| {
+                // the string representation is exact and preserves the scale
+                let s = T::format_decimal(val, precision, scale);
+                type_decimal
+                    .call1((s,))
+                    .map_err(|e| exec_datafusion_err!("cannot create Python `Decimal`: {e}"))
+            })
+            .transpose()
+    });
+
+    Box::new(it)
+}
+
+/// Size of [`PythonType::Uuid`] in bytes.
+const UUID_BYTES: i32 = 16;
+
+/// Get function of the Python `json` module.
+fn py_json_function<'py>(
+    py: Python<'py>,
+    name: &Bound<'py, PyString>,
+) -> DataFusionResult<Bound<'py, PyAny>> {
+    // https://docs.python.org/3/library/json.html
+    py.import(intern!(py, "json"))
+        .and_then(|m| m.getattr(name))
+        .map_err(|e| exec_datafusion_err!("cannot import `json.{name}`: {e}"))
+}
+
+/// Get Python `uuid.UUID` type.
+fn py_uuid_type(py: Python<'_>) -> DataFusionResult<Bound<'_, PyAny>> {
+    // https://docs.python.org/3/library/uuid.html#uuid.UUID
+    py.import(intern!(py, "uuid"))
+        .and_then(|m| m.getattr(intern!(py, "UUID")))
+        .map_err(|e| exec_datafusion_err!("cannot import `uuid.UUID`: {e}"))
+}
+
+/// Name of the element field of [`PythonType::List`].
+///
+/// This is the same name that [`ListBuilder`](arrow::array::ListBuilder) uses.
+const LIST_ELEMENT_NAME: &str = "item";
+
+/// Names of the entries, key, and value fields of [`PythonType::Dict`].
+///
+/// These are the same names that the `map` function in DataFusion uses.
+const MAP_FIELD_NAMES: [&str; 3] = ["entries", "key", "value"];
+
+/// Entries field for [`PythonType::Dict`].
+fn map_entries_field(value: &PythonNullableType) -> FieldRef {
+    let [entries, key, value_name] = MAP_FIELD_NAMES;
+    Arc::new(Field::new(
+        entries,
+        DataType::Struct(Fields::from(vec![
+            Field::new(key, DataType::Utf8, false),
+            value.field(value_name),
+        ])),
+        false,
+    ))
+}
+
+/// Fields for [`PythonType::Tuple`].
+fn tuple_fields(elements: &[PythonNullableType]) -> Fields {
+    elements
+        .iter()
+        .enumerate()
+        .map(|(i, element)| element.field(&format!("c{i}")))
+        .collect()
+}
+
+/// Fields for [`PythonType::Struct`].
+fn struct_fields(fields: &[(String, PythonNullableType)]) -> Fields {
+    fields
+        .iter()
+        .map(|(name, field)| field.field(name))
+        .collect()
+}
+
 impl PythonNullableType {
-    /// Convert Arrow [`Array`] to python values.
+    /// Arrow [`Field`] for an element of a nested type.
+    fn field(&self, name: &str) -> Field {
+        Field::new(name, self.t.data_type(), self.nullable)
+    }
+
+    /// Convert all elements of a nested Arrow [`Array`] to Python values.
+    ///
+    /// NULLs are converted to `None` if the type is nullable or if the respective parent entry is NULL. Otherwise they
+    /// result in an error.
+    fn arrow_to_python_values<'a>(
+        &self,
+        array: &'a dyn Array,
+        parent_nulls: Option<&NullBuffer>,
+        py: Python<'a>,
+    ) -> DataFusionResult<Vec<Bound<'a, PyAny>>> {
+        let none = PyNone::get(py)
+            .into_bound_py_any(py)
+            .map_err(|e| exec_datafusion_err!("cannot get None object: {e}"))?;
+
+        self.t
+            .arrow_to_python(array, py)?
+            .enumerate()
+            .map(|(i, res)| match res? {
+                Some(val) => Ok(val),
+                None if self.nullable || parent_nulls.is_some_and(|nulls| nulls.is_null(i)) => {
+                    Ok(none.clone())
+                }
+                None => exec_err!("unexpected NULL in non-nullable nested value"),
+            })
+            .collect()
+    }
+
+    /// Convert Arrow [`Array`] to python arguments, see [`PythonArg`] for the NULL handling.
     pub(crate) fn arrow_to_python<'a>(
         &self,
         array: &'a dyn Array,
@@ -331,17 +872,13 @@ impl PythonNullableType {
 
             let it = it.map(move |res| {
                 let maybe_any = res?;
-                Ok(ControlFlow::Continue(
-                    maybe_any.unwrap_or_else(|| none.clone()),
-                ))
+                Ok(PythonArg::Pass(maybe_any.unwrap_or_else(|| none.clone())))
             });
             Ok(Box::new(it))
         } else {
             let it = it.map(move |res| {
                 let maybe_any = res?;
-                Ok(maybe_any
-                    .map(ControlFlow::Continue)
-                    .unwrap_or(ControlFlow::Break(())))
+                Ok(maybe_any.map_or(PythonArg::SkipRow, PythonArg::Pass))
             });
             Ok(Box::new(it))
         }
@@ -355,7 +892,7 @@ impl PythonNullableType {
         py: Python<'py>,
         num_rows: usize,
     ) -> Box<dyn ArrayBuilder<'py> + 'py> {
-        let inner = self.t.python_to_arrow(num_rows);
+        let inner = self.t.python_to_arrow(py, num_rows);
         let none = PyNone::get(py).into_bound();
         Box::new(ArrayBuilderNullChecker {
             nullable: self.nullable,
@@ -365,6 +902,56 @@ impl PythonNullableType {
     }
 }
 
+/// Resolve time zone string to a Python `tzinfo` object.
+///
+/// Supported are `UTC`, fixed offsets (`+02:00`, `+0200`, `+02`) and IANA names like `Europe/Berlin`. The latter are
+/// resolved via [`zoneinfo`] and require the time zone database to be available to the interpreter.
+///
+/// [`zoneinfo`]: https://docs.python.org/3/library/zoneinfo.html
+pub(crate) fn py_tzinfo<'py>(py: Python<'py>, tz: &str) -> PyResult<Bound<'py, PyAny>> {
+    if tz == "UTC" {
+        return Ok(PyTzInfo::utc(py)?.to_owned().into_any());
+    }
+
+    if let Some(seconds) = parse_fixed_offset(tz) {
+        let offset = PyDelta::new(py, 0, seconds, 0, true)?;
+        return Ok(PyTzInfo::fixed_offset(py, offset)?.into_any());
+    }
+
+    // https://docs.python.org/3/library/zoneinfo.html#zoneinfo.ZoneInfo
+    py.import(intern!(py, "zoneinfo"))?
+        .getattr(intern!(py, "ZoneInfo"))?
+        .call1((tz,))
+}
+
+/// Parse fixed time zone offset of the form `+HH:MM`, `+HHMM` or `+HH` into seconds.
+fn parse_fixed_offset(tz: &str) -> Option<i32> {
+    let (sign, rest) = match tz.as_bytes().first()? {
+        b'+' => (1, &tz[1..]),
+        b'-' => (-1, &tz[1..]),
+        _ => return None,
+    };
+    if !rest.is_ascii() {
+        return None;
+    }
+    let (hours, minutes) = match rest.len() {
+        2 => (rest, "00"),
+        4 => rest.split_at(2),
+        5 if rest.as_bytes()[2] == b':' => (&rest[..2], &rest[3..]),
+        _ => return None,
+    };
+    if !hours
+        .bytes()
+        .chain(minutes.bytes())
+        .all(|b| b.is_ascii_digit())
+    {
+        return None;
+    }
+    let hours: i32 = hours.parse().ok()?;
+    let minutes: i32 = minutes.parse().ok()?;
+    (hours < 24 && minutes < 60).then_some(sign * (hours * 3600 + minutes * 60))
+}
+
 /// Abstract builder for Arrow output [`Array`].
 pub(crate) trait ArrayBuilder<'py> {
     /// Push a new value.
@@ -392,14 +979,16 @@ struct ArrayBuilderNullChecker<'py> {
     none: Bound<'py, PyNone>,
 
     /// The type-specific converter that came out of [`PythonType::arrow_to_python`].
-    inner: Box<dyn ArrayBuilder<'py>>,
+    inner: Box<dyn ArrayBuilder<'py> + 'py>,
 }
 
 impl<'py> ArrayBuilder<'py> for ArrayBuilderNullChecker<'py> {
     fn push(&mut self, val: Bound<'py, PyAny>) -> DataFusionResult<()> {
         match (self.nullable, val.is(&self.none)) {
             (false, true) => {
-                exec_err!("method was not supposed to return None but did")
+                exec_err!(
+                    "method returned `None` for a non-optional type, annotate it as `T | None` to return NULLs"
+                )
             }
             (false | true, false) => self.inner.push(val),
             (true, true) => {
@@ -583,6 +1172,39 @@ impl<'py> ArrayBuilder<'py> for BinaryBuilder {
     }
 }
 
+/// Output array builder for [`PythonType::Bytes`] with a fixed size.
+struct FixedSizeBytesArrayBuilder {
+    /// Inner builder.
+    builder: FixedSizeBinaryBuilder,
+
+    /// Size of every value in bytes.
+    size: i32,
+}
+
+impl<'py> ArrayBuilder<'py> for FixedSizeBytesArrayBuilder {
+    fn push(&mut self, val: Bound<'py, PyAny>) -> DataFusionResult<()> {
+        let val = val.cast_exact::<PyBytes>().map_err(|_| {
+            exec_datafusion_err!("expected `bytes` but got {}", py_representation(&val))
+        })?;
+        let val: &[u8] = val.extract().map_err(|_| {
+            exec_datafusion_err!("cannot extract bytes from {}", py_representation(val))
+        })?;
+        if i32::try_from(val.len()).ok() != Some(self.size) {
+            return exec_err!("expected {} bytes but got {}", self.size, val.len());
+        }
+        self.builder.append_value(val)?;
+        Ok(())
+    }
+
+    fn skip(&mut self) {
+        self.builder.append_null();
+    }
+
+    fn finish(&mut self) -> ArrayRef {
+        Arc::new(self.builder.finish())
+    }
+}
+
 impl<'py> ArrayBuilder<'py> for Date32Builder {
     fn push(&mut self, val: Bound<'py, PyAny>) -> DataFusionResult<()> {
         let val = val.cast_exact::<PyDate>().map_err(|_| {
@@ -698,3 +1320,558 @@ impl<'py> ArrayBuilder<'py> for DurationMicrosecondBuilder {
         Arc::new(self.finish())
     }
 }
+
+/// Output array builder for [`PythonType::DateTime`] with a time zone.
+struct TimestampTzArrayBuilder<'py> {
+    /// Inner builder.
+    builder: TimestampMicrosecondBuilder,
+
+    /// Unix epoch as time zone aware Python `datetime`.
+    ///
+    /// This is created on first use.
+    epoch: Option<Bound<'py, PyDateTime>>,
+}
+
+impl<'py> ArrayBuilder<'py> for TimestampTzArrayBuilder<'py> {
+    fn push(&mut self, val: Bound<'py, PyAny>) -> DataFusionResult<()> {
+        let py = val.py();
+        let val = val.cast_exact::<PyDateTime>().map_err(|_| {
+            exec_datafusion_err!("expected `datetime` but got {}", py_representation(&val))
+        })?;
+        if val.get_tzinfo().is_none() {
+            return exec_err!(
+                "expected time zone aware `datetime` but got {}",
+                py_representation(val)
+            );
+        }
+
+        let epoch = match &self.epoch {
+            Some(epoch) => epoch,
+            None => {
+                let utc = PyTzInfo::utc(py)
+                    .map_err(|e| exec_datafusion_err!("cannot get UTC tzinfo: {e}"))?
+                    .to_owned();
+                let epoch = PyDateTime::new(py, 1970, 1, 1, 0, 0, 0, 0, Some(&utc))
+                    .map_err(|e| exec_datafusion_err!("cannot create Unix epoch: {e}"))?;
+                self.epoch.insert(epoch)
+            }
+        };
+
+        // the difference of two time zone aware objects is independent of their zones
+        let delta = val
+            .sub(epoch)
+            .and_then(|delta| delta.cast_into_exact::<PyDelta>().map_err(Into::into))
+            .map_err(|e| {
+                exec_datafusion_err!(
+                    "cannot compute offset of {} to Unix epoch: {e}",
+                    py_representation(val)
+                )
+            })?;
+        let val = i64::from(delta.get_days())
+            .checked_mul(86_400_000_000)
+            .and_then(|micros| {
+                micros.checked_add(
+                    i64::from(delta.get_seconds()) * 1_000_000
+                        + i64::from(delta.get_microseconds()),
+                )
+            })
+            .ok_or_else(|| exec_datafusion_err!("{} is out of range", py_representation(val)))?;
+        self.builder.append_value(val);
+        Ok(())
+    }
+
+    fn skip(&mut self) {
+        self.builder.append_null();
+    }
+
+    fn finish(&mut self) -> ArrayRef {
+        Arc::new(self.builder.finish())
+    }
+}
+
+impl<'py> ArrayBuilder<'py> for IntervalMonthDayNanoBuilder {
+    fn push(&mut self, val: Bound<'py, PyAny>) -> DataFusionResult<()> {
+        let (months, days, nanoseconds) = val
+            .cast_exact::<PyTuple>()
+            .ok()
+            .and_then(|t| {
+                t.extract::<(Bound<'py, PyInt>, Bound<'py, PyInt>, Bound<'py, PyInt>)>()
+                    .ok()
+            })
+            .ok_or_else(|| {
+                exec_datafusion_err!(
+                    "expected `(months, days, nanoseconds)` tuple of `int` but got {}",
+                    py_representation(&val)
+                )
+            })?;
+        let out_of_range = || {
+            exec_datafusion_err!(
+                "{} does not fit into an interval, months and days must fit into 32 bits, nanoseconds into 64 bits",
+                py_representation(&val)
+            )
+        };
+
+        self.append_value(IntervalMonthDayNano::new(
+            months.extract().map_err(|_| out_of_range())?,
+            days.extract().map_err(|_| out_of_range())?,
+            nanoseconds.extract().map_err(|_| out_of_range())?,
+        ));
+        Ok(())
+    }
+
+    fn skip(&mut self) {
+        self.append_null();
+    }
+
+    fn finish(&mut self) -> ArrayRef {
+        Arc::new(self.finish())
+    }
+}
+
+/// Output array builder for [`PythonType::Json`].
+struct JsonArrayBuilder<'py> {
+    /// Inner builder.
+    builder: StringBuilder,
+
+    /// Python `json.dumps` function.
+    ///
+    /// This is imported on first use.
+    json_dumps: Option<Bound<'py, PyAny>>,
+}
+
+impl<'py> ArrayBuilder<'py> for JsonArrayBuilder<'py> {
+    fn push(&mut self, val: Bound<'py, PyAny>) -> DataFusionResult<()> {
+        let py = val.py();
+        let json_dumps = match &self.json_dumps {
+            Some(json_dumps) => json_dumps,
+            None => self
+                .json_dumps
+                .insert(py_json_function(py, intern!(py, "dumps"))?),
+        };
+
+        // standard JSON does not support `NaN` and infinity
+        let kwargs = PyDict::new(py);
+        let s = kwargs
+            .set_item(intern!(py, "allow_nan"), false)
+            .and_then(|()| json_dumps.call((&val,), Some(&kwargs)))
+            .map_err(|e| {
+                exec_datafusion_err!("cannot encode {} as JSON: {e}", py_representation(&val))
+            })?;
+        let s: &str = s
+            .extract()
+            .map_err(|_| exec_datafusion_err!("`json.dumps` did not return a `str`"))?;
+        self.builder.append_value(s);
+        Ok(())
+    }
+
+    fn skip(&mut self) {
+        self.builder.append_null();
+    }
+
+    fn finish(&mut self) -> ArrayRef {
+        Arc::new(self.builder.finish())
+    }
+}
+
+/// Output array builder for [`PythonType::Uuid`].
+struct UuidArrayBuilder<'py> {
+    /// Inner builder for the bytes or the string representation.
+    inner: Box<dyn ArrayBuilder<'py> + 'py>,
+
+    /// Use the string representation.
+    as_str: bool,
+
+    /// Python `uuid.UUID` type.
+    ///
+    /// This is imported on first use.
+    type_uuid: Option<Bound<'py, PyAny>>,
+}
+
+impl<'py> ArrayBuilder<'py> for UuidArrayBuilder<'py> {
+    fn push(&mut self, val: Bound<'py, PyAny>) -> DataFusionResult<()> {
+        let py = val.py();
+        let type_uuid = match &self.type_uuid {
+            Some(type_uuid) => type_uuid,
+            None => self.type_uuid.insert(py_uuid_type(py)?),
+        };
+        if !val.is_instance(type_uuid).unwrap_or_default() {
+            return exec_err!("expected `UUID` but got {}", py_representation(&val));
+        }
+
+        let val = if self.as_str {
+            val.str()
+                .map_err(|e| exec_datafusion_err!("cannot format `UUID`: {e}"))?
+                .into_any()
+        } else {
+            val.getattr(intern!(py, "bytes"))
+                .map_err(|e| exec_datafusion_err!("cannot get bytes of `UUID`: {e}"))?
+        };
+        self.inner.push(val)
+    }
+
+    fn skip(&mut self) {
+        self.inner.skip();
+    }
+
+    fn finish(&mut self) -> ArrayRef {
+        self.inner.finish()
+    }
+}
+
+/// Output array builder for [`PythonType::Decimal`].
+struct DecimalArrayBuilder<T>
+where
+    T: DecimalType,
+{
+    /// Inner builder.
+    builder: PrimitiveBuilder<T>,
+
+    /// Decimal precision.
+    precision: u8,
+
+    /// Decimal scale.
+    scale: i8,
+}
+
+impl<'py, T> ArrayBuilder<'py> for DecimalArrayBuilder<T>
+where
+    T: DecimalType,
+{
+    fn push(&mut self, val: Bound<'py, PyAny>) -> DataFusionResult<()> {
+        let py = val.py();
+
+        // https://docs.python.org/3/library/decimal.html#decimal.Decimal
+        let type_decimal = py
+            .import(intern!(py, "decimal"))
+            .and_then(|m| m.getattr(intern!(py, "Decimal")))
+            .map_err(|e| exec_datafusion_err!("cannot import `decimal.Decimal`: {e}"))?;
+        if !val.get_type().is(&type_decimal) {
+            return exec_err!("expected `Decimal` but got {}", py_representation(&val));
+        }
+
+        // https://docs.python.org/3/library/decimal.html#decimal.Decimal.as_tuple
+        let (sign, mut digits, exponent) = val
+            .call_method0(intern!(py, "as_tuple"))
+            .and_then(|t| t.extract::<(u8, Vec<u8>, Bound<'py, PyAny>)>())
+            .map_err(|e| exec_datafusion_err!("cannot inspect `Decimal`: {e}"))?;
+        // NaN and infinity use a string exponent
+        let mut exponent: i64 = exponent.extract().map_err(|_| {
+            exec_datafusion_err!(
+                "cannot convert non-finite {} to Arrow",
+                py_representation(&val)
+            )
+        })?;
+
+        // trailing zeros do not carry any information but may push us out of the integer range
+        while digits.len() > 1 && digits.last() == Some(&0) {
+            digits.pop();
+            exponent += 1;
+        }
+
+        let out_of_range = || {
+            exec_datafusion_err!(
+                "{} does not fit into precision={} and scale={}",
+                py_representation(&val),
+                self.precision,
+                self.scale
+            )
+        };
+        let ten = T::Native::usize_as(10);
+        let mut value = digits
+            .iter()
+            .try_fold(T::Native::ZERO, |acc, d| {
+                acc.mul_checked(ten)?
+                    .add_checked(T::Native::usize_as(usize::from(*d)))
+            })
+            .map_err(|_| out_of_range())?;
+
+        let shift = exponent + i64::from(self.scale);
+        if shift >= 0 {
+            value = u32::try_from(shift)
+                .ok()
+                .and_then(|shift| ten.pow_checked(shift).ok())
+                .and_then(|factor| value.mul_checked(factor).ok())
+                .ok_or_else(out_of_range)?;
+        } else if !value.is_zero() {
+            // digits beyond the scale must NOT be silently rounded away
+            let divisor = u32::try_from(-shift)
+                .ok()
+                .and_then(|shift| ten.pow_checked(shift).ok())
+                .filter(|divisor| value.mod_wrapping(*divisor).is_zero())
+                .ok_or_else(|| {
+                    exec_datafusion_err!(
+                        "{} cannot be represented with scale={} without rounding",
+                        py_representation(&val),
+                        self.scale
+                    )
+                })?;
+            value = value.div_wrapping(divisor);
+        }
+
+        // `value` is non-negative and the maximum precision of every decimal type fits into its integer type
+        let max = ten
+            .pow_checked(u32::from(self.precision))
+            .map_err(|_| out_of_range())?;
+        if value.is_ge(max) {
+            return Err(out_of_range());
+        }
+        if sign == 1 {
+            value = value.neg_wrapping();
+        }
+
+        self.builder.append_value(value);
+        Ok(())
+    }
+
+    fn skip(&mut self) {
+        self.builder.append_null();
+    }
+
+    fn finish(&mut self) -> ArrayRef {
+        Arc::new(self.builder.finish())
+    }
+}
+
+/// Output array builder for [`PythonType::Dict`].
+struct DictArrayBuilder<'py> {
+    /// Entries field, see [`map_entries_field`].
+    entries: FieldRef,
+
+    /// Builder for the keys.
+    keys: StringBuilder,
+
+    /// Builder for the values.
+    values: Box<dyn ArrayBuilder<'py> + 'py>,
+
+    /// Offsets into the entries.
+    offsets: Vec<i32>,
+
+    /// NULL entries of the map array itself.
+    nulls: NullBufferBuilder,
+}
+
+impl<'py> ArrayBuilder<'py> for DictArrayBuilder<'py> {
+    fn push(&mut self, val: Bound<'py, PyAny>) -> DataFusionResult<()> {
+        let val = val.cast_exact::<PyDict>().map_err(|_| {
+            exec_datafusion_err!("expected `dict` but got {}", py_representation(&val))
+        })?;
+        for (k, v) in val.iter() {
+            ArrayBuilder::push(&mut self.keys, k)?;
+            self.values.push(v)?;
+        }
+        push_offset(&mut self.offsets, val.len())?;
+        self.nulls.append_non_null();
+        Ok(())
+    }
+
+    fn skip(&mut self) {
+        push_offset(&mut self.offsets, 0).expect("zero offset increment never overflows");
+        self.nulls.append_null();
+    }
+
+    fn finish(&mut self) -> ArrayRef {
+        let DataType::Struct(fields) = self.entries.data_type() else {
+            unreachable!("entries field is always a struct")
+        };
+        let entries = StructArray::new(
+            fields.clone(),
+            vec![ArrayBuilder::finish(&mut self.keys), self.values.finish()],
+            None,
+        );
+        let offsets = std::mem::replace(&mut self.offsets, vec![0]);
+        Arc::new(MapArray::new(
+            Arc::clone(&self.entries),
+            OffsetBuffer::new(offsets.into()),
+            entries,
+            self.nulls.finish(),
+            false,
+        ))
+    }
+}
+
+/// Output array builder for [`PythonType::List`].
+struct ListArrayBuilder<'py> {
+    /// Element field.
+    field: FieldRef,
+
+    /// Builder for the elements.
+    values: Box<dyn ArrayBuilder<'py> + 'py>,
+
+    /// Offsets into the elements.
+    offsets: Vec<i32>,
+
+    /// NULL entries of the list array itself.
+    nulls: NullBufferBuilder,
+}
+
+impl<'py> ArrayBuilder<'py> for ListArrayBuilder<'py> {
+    fn push(&mut self, val: Bound<'py, PyAny>) -> DataFusionResult<()> {
+        let val = val.cast_exact::<PyList>().map_err(|_| {
+            exec_datafusion_err!("expected `list` but got {}", py_representation(&val))
+        })?;
+        for element in val.iter() {
+            self.values.push(element)?;
+        }
+        push_offset(&mut self.offsets, val.len())?;
+        self.nulls.append_non_null();
+        Ok(())
+    }
+
+    fn skip(&mut self) {
+        push_offset(&mut self.offsets, 0).expect("zero offset increment never overflows");
+        self.nulls.append_null();
+    }
+
+    fn finish(&mut self) -> ArrayRef {
+        let offsets = std::mem::replace(&mut self.offsets, vec![0]);
+        Arc::new(ListArray::new(
+            Arc::clone(&self.field),
+            OffsetBuffer::new(offsets.into()),
+            self.values.finish(),
+            self.nulls.finish(),
+        ))
+    }
+}
+
+/// Output array builder for [`PythonType::Tuple`].
+struct TupleArrayBuilder<'py> {
+    /// Struct fields.
+    fields: Fields,
+
+    /// Builders for the elements, one per field.
+    elements: Vec<Box<dyn ArrayBuilder<'py> + 'py>>,
+
+    /// NULL entries of the struct array itself.
+    nulls: NullBufferBuilder,
+}
+
+impl<'py> ArrayBuilder<'py> for TupleArrayBuilder<'py> {
+    fn push(&mut self, val: Bound<'py, PyAny>) -> DataFusionResult<()> {
+        let val = val.cast_exact::<PyTuple>().map_err(|_| {
+            exec_datafusion_err!("expected `tuple` but got {}", py_representation(&val))
+        })?;
+        if val.len() != self.elements.len() {
+            return exec_err!(
+                "expected tuple with {} elements but got {}",
+                self.elements.len(),
+                py_representation(val)
+            );
+        }
+        for (builder, element) in self.elements.iter_mut().zip(val.iter()) {
+            builder.push(element)?;
+        }
+        self.nulls.append_non_null();
+        Ok(())
+    }
+
+    fn skip(&mut self) {
+        for builder in &mut self.elements {
+            builder.skip();
+        }
+        self.nulls.append_null();
+    }
+
+    fn finish(&mut self) -> ArrayRef {
+        Arc::new(StructArray::new(
+            self.fields.clone(),
+            self.elements
+                .iter_mut()
+                .map(|builder| builder.finish())
+                .collect(),
+            self.nulls.finish(),
+        ))
+    }
+}
+
+/// Output array builder for [`PythonType::Struct`].
+struct StructArrayBuilder<'py> {
+    /// Struct fields.
+    fields: Fields,
+
+    /// Dataclass, or [`None`] for a `TypedDict`.
+    dataclass: Option<Bound<'py, PyAny>>,
+
+    /// Builders for the fields.
+    elements: Vec<Box<dyn ArrayBuilder<'py> + 'py>>,
+
+    /// NULL entries of the struct array itself.
+    nulls: NullBufferBuilder,
+}
+
+impl<'py> ArrayBuilder<'py> for StructArrayBuilder<'py> {
+    fn push(&mut self, val: Bound<'py, PyAny>) -> DataFusionResult<()> {
+        match &self.dataclass {
+            Some(class) => {
+                if !val.is_instance(class).unwrap_or_default() {
+                    return exec_err!(
+                        "expected instance of {} but got {}",
+                        py_representation(class),
+                        py_representation(&val)
+                    );
+                }
+                for (builder, field) in self.elements.iter_mut().zip(&self.fields) {
+                    let element = val.getattr(field.name().as_str()).map_err(|e| {
+                        exec_datafusion_err!("cannot get dataclass field `{}`: {e}", field.name())
+                    })?;
+                    builder.push(element)?;
+                }
+            }
+            None => {
+                let dict = val.cast_exact::<PyDict>().map_err(|_| {
+                    exec_datafusion_err!("expected `dict` but got {}", py_representation(&val))
+                })?;
+                if dict.len() != self.fields.len() {
+                    return exec_err!(
+                        "expected dict with keys {:?} but got {}",
+                        self.fields.iter().map(|f| f.name()).collect::<Vec<_>>(),
+                        py_representation(&val)
+                    );
+                }
+                for (builder, field) in self.elements.iter_mut().zip(&self.fields) {
+                    let element = dict
+                        .get_item(field.name().as_str())
+                        .map_err(|e| exec_datafusion_err!("cannot get dict item: {e}"))?
+                        .ok_or_else(|| {
+                            exec_datafusion_err!(
+                                "expected dict with keys {:?} but got {}",
+                                self.fields.iter().map(|f| f.name()).collect::<Vec<_>>(),
+                                py_representation(&val)
+                            )
+                        })?;
+                    builder.push(element)?;
+                }
+            }
+        }
+        self.nulls.append_non_null();
+        Ok(())
+    }
+
+    fn skip(&mut self) {
+        for builder in &mut self.elements {
+            builder.skip();
+        }
+        self.nulls.append_null();
+    }
+
+    fn finish(&mut self) -> ArrayRef {
+        Arc::new(StructArray::new(
+            self.fields.clone(),
+            self.elements
+                .iter_mut()
+                .map(|builder| builder.finish())
+                .collect(),
+            self.nulls.finish(),
+        ))
+    }
+}
+
+/// Append end offset of an entry with `len` elements.
+fn push_offset(offsets: &mut Vec<i32>, len: usize) -> DataFusionResult<()> {
+    let last = *offsets.last().expect("offsets are never empty");
+    let next = i32::try_from(len)
+        .ok()
+        .and_then(|len| last.checked_add(len))
+        .ok_or_else(|| exec_datafusion_err!("too many nested elements"))?;
+    offsets.push(next);
+    Ok(())
+}
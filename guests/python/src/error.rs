@@ -1,4 +1,6 @@
 //! Helper for error handling.
+use datafusion_common::{DataFusionError, exec_datafusion_err};
+use datafusion_udf_wasm_guest::error::{ErrorWithTraceback, TracebackFrame};
 use pyo3::{
     PyErr, PyResult, PyTypeInfo, Python, intern,
     types::{PyAnyMethods, PyDict},
@@ -40,6 +42,46 @@ fn try_py_err_to_string(e: PyErr, py: Python<'_>) -> PyResult<String> {
     Ok(s)
 }
 
+/// Convert a Python error to a [`DataFusionError`].
+///
+/// The message is the same as [`py_err_to_string`]. If the exception has a traceback, it is attached in structured
+/// form as well, see [`ErrorWithTraceback`].
+pub(crate) fn py_err_to_datafusion(e: PyErr, py: Python<'_>) -> DataFusionError {
+    // a broken traceback should not hide the actual error
+    let frames = py_err_traceback(&e, py).unwrap_or_default();
+    let error = exec_datafusion_err!("{}", py_err_to_string(e, py));
+    if frames.is_empty() {
+        error
+    } else {
+        ErrorWithTraceback::wrap(error, frames)
+    }
+}
+
+/// Extract traceback frames of a Python error, innermost frame last.
+fn py_err_traceback(e: &PyErr, py: Python<'_>) -> PyResult<Vec<TracebackFrame>> {
+    let Some(tb) = e.traceback(py) else {
+        return Ok(vec![]);
+    };
+
+    // https://docs.python.org/3/library/traceback.html#traceback.extract_tb
+    let mod_traceback = py.import(intern!(py, "traceback"))?;
+    let fun_extract_tb = mod_traceback.getattr(intern!(py, "extract_tb"))?;
+    fun_extract_tb
+        .call1((tb,))?
+        .try_iter()?
+        .map(|frame| {
+            // https://docs.python.org/3/library/traceback.html#traceback.FrameSummary
+            let frame = frame?;
+            let line: Option<u32> = frame.getattr(intern!(py, "lineno"))?.extract()?;
+            Ok(TracebackFrame {
+                file: frame.getattr(intern!(py, "filename"))?.extract()?,
+                line: line.unwrap_or_default(),
+                function: frame.getattr(intern!(py, "name"))?.extract()?,
+            })
+        })
+        .collect()
+}
+
 /// Extension trait for [`PyErr`].
 pub(crate) trait PyErrExt {
     /// Add context to error by adding a [cause].
@@ -1,19 +1,36 @@
 //! Inspection of Python code to extract [signature](crate::signature) information.
-use std::{collections::HashSet, ffi::CString};
+use std::{cell::RefCell, collections::HashSet, ffi::CString};
 
+use arrow::datatypes::{
+    DECIMAL128_MAX_PRECISION, DECIMAL256_MAX_PRECISION, Decimal128Type, Decimal256Type,
+    validate_decimal_precision_and_scale,
+};
 use datafusion_common::{DataFusionError, error::Result as DataFusionResult};
+use datafusion_expr::Volatility;
+use datafusion_udf_wasm_guest::Diagnostic;
 use pyo3::{
-    Borrowed, Bound, FromPyObject, PyAny, PyErr, PyResult, Python,
+    Borrowed, Bound, FromPyObject, Py, PyAny, PyErr, PyResult, Python,
     exceptions::PyTypeError,
     intern,
-    types::{PyAnyMethods, PyDictMethods, PyModuleMethods, PyStringMethods, PyTypeMethods},
+    types::{
+        PyAnyMethods, PyDict, PyDictMethods, PyString, PyStringMethods, PyType, PyTypeMethods,
+    },
 };
 
 use crate::{
+    conversion::py_tzinfo,
     error::{PyErrExt, py_err_to_string},
-    signature::{PythonFn, PythonFnSignature, PythonNullableType, PythonType},
+    signature::{
+        FIELDS_PARAM, PythonClass, PythonFn, PythonFnSignature, PythonNullableType, PythonType,
+    },
 };
 
+/// Annotation that declares a `dict` or `list` as JSON, see [`PythonType::Json`].
+const JSON_ANNOTATION: &str = "json";
+
+/// Annotation that declares a `tuple[int, int, int]` as interval, see [`PythonType::Interval`].
+const INTERVAL_ANNOTATION: &str = "interval";
+
 impl<'a, 'py> FromPyObject<'a, 'py> for PythonType {
     type Error = PyErr;
 
@@ -35,18 +52,216 @@ impl<'a, 'py> FromPyObject<'a, 'py> for PythonType {
         let type_time = mod_datetime.getattr(intern!(py, "time"))?;
         let type_timedelta = mod_datetime.getattr(intern!(py, "timedelta"))?;
 
+        // https://docs.python.org/3/library/decimal.html
+        let mod_decimal = py.import(intern!(py, "decimal"))?;
+        let type_decimal = mod_decimal.getattr(intern!(py, "Decimal"))?;
+
+        // https://docs.python.org/3/library/uuid.html
+        //
+        // Only look at modules that were already imported, a `UUID` annotation requires the user code to import it.
+        let type_uuid = py
+            .import(intern!(py, "sys"))?
+            .getattr(intern!(py, "modules"))?
+            .get_item(intern!(py, "uuid"))
+            .ok()
+            .map(|m| m.getattr(intern!(py, "UUID")))
+            .transpose()?;
+        let is_uuid = |t: &Bound<'py, PyAny>| type_uuid.as_ref().is_some_and(|u| t.is(u));
+
         // https://docs.python.org/3/library/types.html
         let mod_types = py.import(intern!(py, "types"))?;
         let type_none = mod_types.getattr(intern!(py, "NoneType"))?;
 
+        // generic types like `list[int]`
+        // https://docs.python.org/3/library/typing.html#typing.get_origin
+        let mod_typing = py.import(intern!(py, "typing"))?;
+        let origin = mod_typing
+            .getattr(intern!(py, "get_origin"))?
+            .call1((&*ob,))?;
+        if !origin.is_none() {
+            // https://docs.python.org/3/library/typing.html#typing.Annotated
+            let type_annotated = mod_typing.getattr(intern!(py, "Annotated"))?;
+            let type_dict = mod_builtins.getattr(intern!(py, "dict"))?;
+            let type_list = mod_builtins.getattr(intern!(py, "list"))?;
+            let type_tuple = mod_builtins.getattr(intern!(py, "tuple"))?;
+            let ellipsis = mod_builtins.getattr(intern!(py, "Ellipsis"))?;
+
+            // https://docs.python.org/3/library/typing.html#typing.get_args
+            let args = mod_typing
+                .getattr(intern!(py, "get_args"))?
+                .call1((&*ob,))?
+                .try_iter()?
+                .collect::<PyResult<Vec<_>>>()?;
+
+            if origin.is(&type_annotated) {
+                let [t, metadata] = args.as_slice() else {
+                    return Err(PyErr::new::<PyTypeError, _>(format!(
+                        "`Annotated` requires exactly one metadata argument, got {}",
+                        args.len().saturating_sub(1)
+                    )));
+                };
+                // `dict`, `list`, or a parameterized version of these
+                let t_origin = mod_typing.getattr(intern!(py, "get_origin"))?.call1((t,))?;
+                let container = if t_origin.is_none() { t } else { &t_origin };
+                if container.is(&type_dict) || container.is(&type_list) {
+                    if metadata.extract::<String>().ok().as_deref() != Some(JSON_ANNOTATION) {
+                        return Err(PyErr::new::<PyTypeError, _>(format!(
+                            "`dict` and `list` annotations must be {JSON_ANNOTATION:?}, got {}",
+                            py_representation(metadata)
+                        )));
+                    }
+                    return Ok(Self::Json);
+                }
+                if container.is(&type_tuple) {
+                    if metadata.extract::<String>().ok().as_deref() != Some(INTERVAL_ANNOTATION) {
+                        return Err(PyErr::new::<PyTypeError, _>(format!(
+                            "`tuple` annotation must be {INTERVAL_ANNOTATION:?}, got {}",
+                            py_representation(metadata)
+                        )));
+                    }
+                    let elements = mod_typing
+                        .getattr(intern!(py, "get_args"))?
+                        .call1((t,))?
+                        .try_iter()?
+                        .collect::<PyResult<Vec<_>>>()?;
+                    if elements.len() != 3 || !elements.iter().all(|e| e.is(&type_int)) {
+                        return Err(PyErr::new::<PyTypeError, _>(format!(
+                            "{INTERVAL_ANNOTATION:?} annotation requires `tuple[int, int, int]`, got {}",
+                            py_representation(t)
+                        )));
+                    }
+                    return Ok(Self::Interval);
+                }
+                if t.is(&type_datetime) {
+                    let tz = metadata.extract::<String>().map_err(|_| {
+                        PyErr::new::<PyTypeError, _>(format!(
+                            "`datetime` annotation must be a time zone string, got {}",
+                            py_representation(metadata)
+                        ))
+                    })?;
+                    py_tzinfo(py, &tz).map_err(|e| {
+                        PyErr::new::<PyTypeError, _>(format!(
+                            "invalid time zone for `datetime`: {tz:?}: {e}"
+                        ))
+                    })?;
+                    return Ok(Self::DateTime {
+                        tz: Some(tz.into()),
+                    });
+                }
+                if t.is(&type_bytes) {
+                    let size = metadata
+                        .extract::<i32>()
+                        .ok()
+                        .filter(|size| *size > 0)
+                        .ok_or_else(|| {
+                            PyErr::new::<PyTypeError, _>(format!(
+                                "`bytes` annotation must be a positive size, got {}",
+                                py_representation(metadata)
+                            ))
+                        })?;
+                    return Ok(Self::Bytes { size: Some(size) });
+                }
+                if is_uuid(t) {
+                    if !metadata.is(&type_str) {
+                        return Err(PyErr::new::<PyTypeError, _>(format!(
+                            "`UUID` annotation must be `str`, got {}",
+                            py_representation(metadata)
+                        )));
+                    }
+                    return Ok(Self::Uuid { as_str: true });
+                }
+                if !t.is(&type_decimal) {
+                    return Err(PyErr::new::<PyTypeError, _>(format!(
+                        "`Annotated` is only supported for `Decimal`, `UUID`, `bytes`, `datetime`, `dict`, `list`, and `tuple`, got {}",
+                        py_representation(t)
+                    )));
+                }
+                let (precision, scale) = metadata.extract::<(u8, i8)>().map_err(|_| {
+                    PyErr::new::<PyTypeError, _>(format!(
+                        "`Decimal` annotation must be a `(precision, scale)` tuple, got {}",
+                        py_representation(metadata)
+                    ))
+                })?;
+                if !(1..=DECIMAL256_MAX_PRECISION).contains(&precision) {
+                    return Err(PyErr::new::<PyTypeError, _>(format!(
+                        "invalid `Decimal`: precision must be between 1 and {DECIMAL256_MAX_PRECISION}, got {precision}; precisions up to {DECIMAL128_MAX_PRECISION} map to Decimal128, higher ones to Decimal256"
+                    )));
+                }
+                if precision <= DECIMAL128_MAX_PRECISION {
+                    validate_decimal_precision_and_scale::<Decimal128Type>(precision, scale)
+                } else {
+                    validate_decimal_precision_and_scale::<Decimal256Type>(precision, scale)
+                }
+                .map_err(|e| PyErr::new::<PyTypeError, _>(format!("invalid `Decimal`: {e}")))?;
+                return Ok(Self::Decimal { precision, scale });
+            } else if origin.is(&type_list) {
+                let [t] = args.as_slice() else {
+                    return Err(PyErr::new::<PyTypeError, _>(format!(
+                        "`list` requires exactly one type argument, got {}",
+                        args.len()
+                    )));
+                };
+                let t = t
+                    .extract()
+                    .context::<PyTypeError>("inspect list element type".to_owned(), py)?;
+                return Ok(Self::List(Box::new(t)));
+            } else if origin.is(&type_dict) {
+                let [k, v] = args.as_slice() else {
+                    return Err(PyErr::new::<PyTypeError, _>(format!(
+                        "`dict` requires exactly two type arguments, got {}",
+                        args.len()
+                    )));
+                };
+                if !k.is(&type_str) {
+                    return Err(PyErr::new::<PyTypeError, _>(format!(
+                        "only `str` dictionary keys are supported, got {}",
+                        py_representation(k)
+                    )));
+                }
+                let v = v
+                    .extract()
+                    .context::<PyTypeError>("inspect dictionary value type".to_owned(), py)?;
+                return Ok(Self::Dict(Box::new(v)));
+            } else if origin.is(&type_tuple) {
+                if args.is_empty() {
+                    return Err(PyErr::new::<PyTypeError, _>(
+                        "empty tuples are not supported".to_owned(),
+                    ));
+                }
+                if args.iter().any(|arg| arg.is(&ellipsis)) {
+                    return Err(PyErr::new::<PyTypeError, _>(
+                        "variable-length tuples are not supported".to_owned(),
+                    ));
+                }
+                let elements = args
+                    .iter()
+                    .enumerate()
+                    .map(|(i, arg)| {
+                        arg.extract()
+                            .context::<PyTypeError>(format!("inspect tuple element {}", i + 1), py)
+                    })
+                    .collect::<PyResult<_>>()?;
+                return Ok(Self::Tuple(elements));
+            }
+        }
+
+        if let Some(t) = extract_struct(&ob)? {
+            return Ok(t);
+        }
+
         if ob.is(type_bool) {
             Ok(Self::Bool)
-        } else if ob.is(type_bytes) {
-            Ok(Self::Bytes)
+        } else if ob.is(&type_bytes) {
+            Ok(Self::Bytes { size: None })
         } else if ob.is(type_date) {
             Ok(Self::Date)
-        } else if ob.is(type_datetime) {
-            Ok(Self::DateTime)
+        } else if ob.is(&type_datetime) {
+            Ok(Self::DateTime { tz: None })
+        } else if ob.is(&type_decimal) {
+            Err(PyErr::new::<PyTypeError, _>(
+                "`Decimal` requires precision and scale, use `Annotated[Decimal, (precision, scale)]`"
+                    .to_owned(),
+            ))
         } else if ob.is(type_float) {
             Ok(Self::Float)
         } else if ob.is(&type_none) || ob.is_instance(&type_none).unwrap_or_default() {
@@ -59,6 +274,8 @@ impl<'a, 'py> FromPyObject<'a, 'py> for PythonType {
             Ok(Self::Time)
         } else if ob.is(type_timedelta) {
             Ok(Self::Timedelta)
+        } else if is_uuid(&ob) {
+            Ok(Self::Uuid { as_str: false })
         } else {
             Err(PyErr::new::<PyTypeError, _>(format!(
                 "unknown annotation type: {}",
@@ -68,6 +285,127 @@ impl<'a, 'py> FromPyObject<'a, 'py> for PythonType {
     }
 }
 
+thread_local! {
+    /// Pointers of the structs that are currently being inspected, used to detect recursive types.
+    static STRUCTS_IN_PROGRESS: RefCell<Vec<usize>> = const { RefCell::new(vec![]) };
+}
+
+/// Marks a struct as [in progress](STRUCTS_IN_PROGRESS) until dropped.
+struct RecursionGuard;
+
+impl RecursionGuard {
+    /// Mark struct as in progress, fails if it already is.
+    fn enter(ob: &Bound<'_, PyAny>) -> PyResult<Self> {
+        let ptr = ob.as_ptr() as usize;
+        STRUCTS_IN_PROGRESS.with_borrow_mut(|in_progress| {
+            if in_progress.contains(&ptr) {
+                return Err(PyErr::new::<PyTypeError, _>(format!(
+                    "recursive types are not supported, got {}",
+                    py_representation(ob)
+                )));
+            }
+            in_progress.push(ptr);
+            Ok(Self)
+        })
+    }
+}
+
+impl Drop for RecursionGuard {
+    fn drop(&mut self) {
+        STRUCTS_IN_PROGRESS.with_borrow_mut(|in_progress| {
+            in_progress.pop();
+        });
+    }
+}
+
+/// Extract [`PythonType::Struct`] if the annotation is a `TypedDict` or a dataclass.
+fn extract_struct(ob: &Bound<'_, PyAny>) -> PyResult<Option<PythonType>> {
+    let py = ob.py();
+
+    // https://docs.python.org/3/library/typing.html
+    let mod_typing = py.import(intern!(py, "typing"))?;
+    // https://docs.python.org/3/library/dataclasses.html
+    let mod_dataclasses = py.import(intern!(py, "dataclasses"))?;
+
+    // https://docs.python.org/3/library/typing.html#typing.is_typeddict
+    let is_typeddict = mod_typing
+        .getattr(intern!(py, "is_typeddict"))?
+        .call1((ob,))?
+        .is_truthy()?;
+    // https://docs.python.org/3/library/dataclasses.html#dataclasses.is_dataclass
+    let is_dataclass = ob.is_instance_of::<PyType>()
+        && mod_dataclasses
+            .getattr(intern!(py, "is_dataclass"))?
+            .call1((ob,))?
+            .is_truthy()?;
+    if !is_typeddict && !is_dataclass {
+        return Ok(None);
+    }
+
+    let _guard = RecursionGuard::enter(ob)?;
+
+    // resolves forward references, `include_extras` keeps `Annotated`
+    // https://docs.python.org/3/library/typing.html#typing.get_type_hints
+    let kwargs = PyDict::new(py);
+    kwargs.set_item(intern!(py, "include_extras"), true)?;
+    let hints = mod_typing
+        .getattr(intern!(py, "get_type_hints"))?
+        .call((ob,), Some(&kwargs))?;
+
+    let names = if is_dataclass {
+        // https://docs.python.org/3/library/dataclasses.html#dataclasses.fields
+        let mut names = vec![];
+        for field in mod_dataclasses
+            .getattr(intern!(py, "fields"))?
+            .call1((ob,))?
+            .try_iter()?
+        {
+            let field = field?;
+            let name = field.getattr(intern!(py, "name"))?.extract::<String>()?;
+            if !field.getattr(intern!(py, "init"))?.is_truthy()? {
+                return Err(PyErr::new::<PyTypeError, _>(format!(
+                    "dataclass field `{name}` must be part of `__init__`"
+                )));
+            }
+            names.push(name);
+        }
+        names
+    } else {
+        // https://docs.python.org/3/library/typing.html#typing.TypedDict.__optional_keys__
+        if ob.getattr(intern!(py, "__optional_keys__"))?.len()? > 0 {
+            return Err(PyErr::new::<PyTypeError, _>(
+                "`NotRequired` keys are not supported, use `T | None` instead".to_owned(),
+            ));
+        }
+        hints
+            .try_iter()?
+            .map(|name| name?.extract::<String>())
+            .collect::<PyResult<Vec<_>>>()?
+    };
+    if names.is_empty() {
+        return Err(PyErr::new::<PyTypeError, _>(format!(
+            "structs without fields are not supported, got {}",
+            py_representation(ob)
+        )));
+    }
+
+    let fields = names
+        .into_iter()
+        .map(|name| {
+            let t = hints
+                .get_item(&name)?
+                .extract::<PythonNullableType>()
+                .context::<PyTypeError>(format!("inspect field `{name}`"), py)?;
+            Ok((name, t))
+        })
+        .collect::<PyResult<_>>()?;
+
+    Ok(Some(PythonType::Struct {
+        fields,
+        dataclass: is_dataclass.then(|| PythonClass(ob.clone().unbind())),
+    }))
+}
+
 impl<'a, 'py> FromPyObject<'a, 'py> for PythonNullableType {
     type Error = PyErr;
 
@@ -139,57 +477,105 @@ impl<'a, 'py> FromPyObject<'a, 'py> for PythonFnSignature {
         // https://docs.python.org/3/library/inspect.html#inspect.Parameter.empty
         let type_parameter_empty = type_parameter.getattr(intern!(py, "empty"))?;
 
-        let parameters = ob.getattr(intern!(py, "parameters"))?;
-        let parameters_values = parameters.getattr(intern!(py, "values"))?;
-        let parameters = parameters_values
-            .call0()?
-            .try_iter()?
-            .enumerate()
-            .map(|(i, param)| {
-                let param = param?;
-                // param is now https://docs.python.org/3/library/inspect.html#inspect.Parameter
-
-                // check kind, see https://docs.python.org/3/library/inspect.html#inspect.Parameter.kind
-                let kind = param.getattr(intern!(py, "kind"))?;
-                let kind_name = kind.getattr(intern!(py, "name"))?;
-                let kind_name = kind_name.str()?;
-                let kind_name = kind_name.to_str()?;
-                if (kind_name != "POSITIONAL_OR_KEYWORD") && (kind_name != "POSITIONAL_ONLY") {
-                    return Err(PyErr::new::<PyTypeError, _>(
-                        format!("only parameters of kind `POSITIONAL_OR_KEYWORD` and `POSITIONAL_ONLY` are supported, got {kind_name}")
-                    ));
-                }
+        let parameters_values = ob
+            .getattr(intern!(py, "parameters"))?
+            .getattr(intern!(py, "values"))?;
+        let mut parameters = vec![];
+        let mut required = 0;
+        let mut fields_param = false;
+        for (i, param) in parameters_values.call0()?.try_iter()?.enumerate() {
+            let param = param?;
+            // param is now https://docs.python.org/3/library/inspect.html#inspect.Parameter
+
+            // check kind, see https://docs.python.org/3/library/inspect.html#inspect.Parameter.kind
+            let kind = param.getattr(intern!(py, "kind"))?;
+            let kind_name = kind.getattr(intern!(py, "name"))?;
+            let kind_name = kind_name.str()?;
+            let kind_name = kind_name.to_str()?;
+            let is_fields_param = (kind_name == "KEYWORD_ONLY")
+                && param
+                    .getattr(intern!(py, "name"))?
+                    .extract::<String>()
+                    .is_ok_and(|name| name == FIELDS_PARAM);
+            if !is_fields_param
+                && (kind_name != "POSITIONAL_OR_KEYWORD")
+                && (kind_name != "POSITIONAL_ONLY")
+            {
+                return Err(PyErr::new::<PyTypeError, _>(format!(
+                    "only parameters of kind `POSITIONAL_OR_KEYWORD` and `POSITIONAL_ONLY` are supported, got {kind_name}"
+                )));
+            }
 
-                // check default value
-                let default = param.getattr(intern!(py, "default"))?;
-                if !default.is(&type_parameter_empty) {
-                    return Err(PyErr::new::<PyTypeError, _>(
-                        format!("default parameter values are not supported, got {}", py_representation(&default))
-                    ));
+            // check default value
+            let default = param.getattr(intern!(py, "default"))?;
+            let has_default = !default.is(&type_parameter_empty);
+
+            // the annotation of the fields parameter is only documentation for the user
+            if is_fields_param {
+                if has_default {
+                    return Err(PyErr::new::<PyTypeError, _>(format!(
+                        "`{FIELDS_PARAM}` must not have a default value, got {}",
+                        py_representation(&default)
+                    )));
                 }
+                fields_param = true;
+                continue;
+            }
 
-                // convert annotation type
-                let annotation = param.getattr(intern!(py, "annotation"))?;
-                let param: PythonNullableType = annotation
-                    .extract()
-                    .context::<PyTypeError>(format!("inspect parameter {}", i + 1), py)?;
+            // Python guarantees that parameters with defaults follow the ones without
+            if !has_default {
+                required += 1;
+            }
 
-                PyResult::Ok(param)
-            })
-            .collect::<Result<Vec<_>, _>>()?;
+            // convert annotation type
+            let annotation = param.getattr(intern!(py, "annotation"))?;
+            let param = extract_annotation(&annotation)
+                .context::<PyTypeError>(format!("inspect parameter {}", i + 1), py)?;
+            parameters.push(param);
+        }
 
         let return_annotation = ob.getattr(intern!(py, "return_annotation"))?;
-        let return_type: PythonNullableType = return_annotation
-            .extract()
+        let return_type = extract_annotation(&return_annotation)
             .context::<PyTypeError>("inspect return type".to_owned(), py)?;
 
-        Ok(Self {
+        let this = Self {
             parameters,
+            required,
             return_type,
-        })
+            fields_param,
+        };
+
+        #[cfg(feature = "pyarrow")]
+        if this
+            .parameters
+            .iter()
+            .any(|param| matches!(param.t, PythonType::PyArrow(_)) != this.is_columnar())
+        {
+            return Err(PyErr::new::<PyTypeError, _>(
+                "`pyarrow.Array` must be used for all parameters and the return type or for none of them".to_owned(),
+            ));
+        }
+
+        Ok(this)
     }
 }
 
+/// Extract type of a parameter or return annotation.
+///
+/// In contrast to nested types, this also supports `pyarrow.Array` if the `pyarrow` feature is enabled.
+fn extract_annotation(annotation: &Bound<'_, PyAny>) -> PyResult<PythonNullableType> {
+    #[cfg(feature = "pyarrow")]
+    if let Some(dt) = crate::pyarrow::extract_array_annotation(annotation)? {
+        // arrays may always contain NULLs
+        return Ok(PythonNullableType {
+            t: PythonType::PyArrow(dt),
+            nullable: true,
+        });
+    }
+
+    annotation.extract()
+}
+
 /// Execute python code and retrieve the list of defined functions.
 pub(crate) fn inspect_python_code(code: &str) -> DataFusionResult<Vec<PythonFn>> {
     Python::attach(|py| {
@@ -198,6 +584,52 @@ pub(crate) fn inspect_python_code(code: &str) -> DataFusionResult<Vec<PythonFn>>
     })
 }
 
+/// Check Python code for syntax errors without running it.
+///
+/// Only the first error is reported, because Python stops compiling at the first error.
+pub(crate) fn validate_python_code(code: &str) -> Vec<Diagnostic> {
+    Python::attach(|py| {
+        let Err(e) = compile_python_code(code, py) else {
+            return vec![];
+        };
+
+        // https://docs.python.org/3/library/exceptions.html#SyntaxError
+        let value = e.value(py);
+        let attr_u32 = |name| {
+            value
+                .getattr(name)
+                .and_then(|v| v.extract::<Option<u32>>())
+                .ok()
+                .flatten()
+                .unwrap_or_default()
+        };
+        let line = attr_u32(intern!(py, "lineno"));
+        let column = attr_u32(intern!(py, "offset"));
+        let message = match value
+            .getattr(intern!(py, "msg"))
+            .and_then(|msg| msg.extract::<String>())
+        {
+            Ok(msg) => msg,
+            Err(_) => py_err_to_string(e, py),
+        };
+
+        vec![Diagnostic {
+            message,
+            line,
+            column,
+        }]
+    })
+}
+
+/// Compile Python code to a code object, which is then discarded.
+fn compile_python_code(code: &str, py: Python<'_>) -> PyResult<()> {
+    // https://docs.python.org/3/library/functions.html#compile
+    py.import(intern!(py, "builtins"))?
+        .getattr(intern!(py, "compile"))?
+        .call1((code, "<string>", "exec"))?;
+    Ok(())
+}
+
 /// Inner implementation of [`inspect_python_code`] which is meant to wrapped into a Python execution context.
 fn inspect_python_code_inner(code: &str, py: Python<'_>) -> PyResult<Vec<PythonFn>> {
     let code = CString::new(code).map_err(|e| PyErr::new::<PyTypeError, _>(e.to_string()))?;
@@ -211,13 +643,16 @@ fn inspect_python_code_inner(code: &str, py: Python<'_>) -> PyResult<Vec<PythonF
     let mod_builtins = py.import(intern!(py, "builtins"))?;
     let ty_type = mod_builtins.getattr(intern!(py, "type"))?;
 
-    py.run(&code, None, None)?;
+    // Every source text gets its own module namespace that is initialized exactly once. The functions keep a reference
+    // to it, so helpers, imports, and globals live as long as the UDFs and are shared between them.
+    let globals = PyDict::new(py);
+    globals.set_item(intern!(py, "__name__"), intern!(py, "__main__"))?;
+    globals.set_item(intern!(py, "__builtins__"), &mod_builtins)?;
 
-    let mod_main = py.import(intern!(py, "__main__"))?;
-    let main_content = mod_main.dict();
+    py.run(&code, Some(&globals), None)?;
 
     let mut fns = vec![];
-    for (name, val) in main_content.iter() {
+    for (name, val) in globals.iter() {
         let Ok(name) = name.str() else {
             continue;
         };
@@ -254,11 +689,21 @@ fn inspect_python_code_inner(code: &str, py: Python<'_>) -> PyResult<Vec<PythonF
             .extract()
             .context::<PyTypeError>(format!("inspect type of `{name}`"), py)?;
 
+        let volatility = extract_volatility(&val)
+            .context::<PyTypeError>(format!("inspect volatility of `{name}`"), py)?;
+        let init = extract_hook(&val, intern!(py, "init"))
+            .context::<PyTypeError>(format!("inspect init hook of `{name}`"), py)?;
+        let close = extract_hook(&val, intern!(py, "close"))
+            .context::<PyTypeError>(format!("inspect close hook of `{name}`"), py)?;
+
         let handle = val.unbind();
 
         fns.push(PythonFn {
             name,
             signature,
+            volatility,
+            init,
+            close,
             handle,
         });
     }
@@ -266,6 +711,51 @@ fn inspect_python_code_inner(code: &str, py: Python<'_>) -> PyResult<Vec<PythonF
     Ok(fns)
 }
 
+/// Extract [`Volatility`] that is declared via the `volatility` attribute of a function.
+///
+/// Functions without that attribute are [volatile](Volatility::Volatile).
+fn extract_volatility(val: &Bound<'_, PyAny>) -> PyResult<Volatility> {
+    let py = val.py();
+
+    if !val.hasattr(intern!(py, "volatility"))? {
+        return Ok(Volatility::Volatile);
+    }
+    let volatility = val.getattr(intern!(py, "volatility"))?;
+    let Ok(volatility_str) = volatility.extract::<String>() else {
+        return Err(PyErr::new::<PyTypeError, _>(format!(
+            "volatility must be a string, got {}",
+            py_representation(&volatility)
+        )));
+    };
+
+    match volatility_str.as_str() {
+        "immutable" => Ok(Volatility::Immutable),
+        "stable" => Ok(Volatility::Stable),
+        "volatile" => Ok(Volatility::Volatile),
+        other => Err(PyErr::new::<PyTypeError, _>(format!(
+            "volatility must be one of `immutable`, `stable`, or `volatile`, got `{other}`"
+        ))),
+    }
+}
+
+/// Extract setup/teardown hook that is declared via the given attribute of a function.
+///
+/// The hook must be callable without any arguments. Functions without that attribute have no hook.
+fn extract_hook(val: &Bound<'_, PyAny>, attr: &Bound<'_, PyString>) -> PyResult<Option<Py<PyAny>>> {
+    if !val.hasattr(attr)? {
+        return Ok(None);
+    }
+    let hook = val.getattr(attr)?;
+    if !hook.is_callable() {
+        return Err(PyErr::new::<PyTypeError, _>(format!(
+            "{attr} must be callable, got {}",
+            py_representation(&hook)
+        )));
+    }
+
+    Ok(Some(hook.unbind()))
+}
+
 /// Receives of human-readable representation of a given Python variable.
 pub(crate) fn py_representation(ob: &Bound<'_, PyAny>) -> String {
     let s = ob
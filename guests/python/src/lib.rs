@@ -5,39 +5,55 @@
 //! [`pyo3`]: https://pyo3.rs/
 use std::any::Any;
 use std::hash::Hash;
-use std::io::Error;
-use std::ops::{ControlFlow, Range};
+use std::ops::Range;
 use std::sync::{Arc, Once};
 
-use arrow::datatypes::DataType;
+use arrow::compute::cast;
+use arrow::datatypes::{DataType, FieldRef};
 use datafusion_common::{
     DataFusionError, Result as DataFusionResult, exec_datafusion_err, exec_err,
 };
-use datafusion_expr::{ColumnarValue, ScalarFunctionArgs, ScalarUDFImpl, Signature, Volatility};
-use datafusion_udf_wasm_guest::export;
+use datafusion_expr::{ColumnarValue, ScalarFunctionArgs, ScalarUDFImpl, Signature, TypeSignature};
+use datafusion_udf_wasm_guest::{Diagnostic, export, wrapper::UdfLifecycle};
+use pyo3::intern;
 use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyTuple};
 use uuid::Uuid;
 
-use crate::error::py_err_to_string;
-use crate::inspect::inspect_python_code;
+use crate::conversion::PythonArg;
+use crate::error::{py_err_to_datafusion, py_err_to_string};
+use crate::inspect::{inspect_python_code, validate_python_code};
 use crate::signature::PythonFn;
 
 // unused-crate-dependencies false positives
 #[cfg(test)]
 use gungraun as _;
+#[cfg(all(feature = "preinit", not(target_os = "wasi")))]
+use wit_bindgen as _;
 
 mod conversion;
 mod error;
 mod inspect;
+// only on WASI, because the pre-initialization relies on `wasi-libc`
+#[cfg(all(feature = "preinit", target_os = "wasi"))]
+mod preinit;
+#[cfg(feature = "pyarrow")]
+mod pyarrow;
 mod python_modules;
-/// Root filesystem helpers for preparing the embedded Python runtime files.
-mod root_fs;
 mod signature;
 mod wasi_symbols;
 
 /// Supported Python version range.
 const PYTHON_VERSION_RANGE: Range<(u8, u8, u8)> = (3, 14, 0)..(3, 15, 0);
 
+/// Language/runtime of this guest.
+///
+/// This is embedded as a custom section so that hosts can describe the component without running it. Keep it in sync
+/// with [`PYTHON_VERSION_RANGE`].
+#[used]
+#[unsafe(link_section = "datafusion-udf-wasm:runtime")]
+static RUNTIME: [u8; 11] = *b"python 3.14";
+
 /// A test UDF that demonstrate that we can call Python.
 #[derive(Debug)]
 struct PythonScalarUDF {
@@ -56,15 +72,27 @@ struct PythonScalarUDF {
 impl PythonScalarUDF {
     /// Create new UDF.
     fn new(python_function: PythonFn) -> Self {
-        let signature = Signature::exact(
-            python_function
-                .signature
-                .parameters
-                .iter()
-                .map(|t| t.t.data_type())
-                .collect(),
-            Volatility::Volatile,
-        );
+        // one exact signature per accepted number of arguments, omitted parameters use their default values
+        let mut signatures = python_function
+            .signature
+            .arity()
+            .map(|n| {
+                TypeSignature::Exact(
+                    python_function.signature.parameters[..n]
+                        .iter()
+                        .map(|t| t.t.data_type())
+                        .collect(),
+                )
+            })
+            .collect::<Vec<_>>();
+        let signature = if signatures.len() == 1 {
+            Signature::new(
+                signatures.pop().expect("just checked"),
+                python_function.volatility,
+            )
+        } else {
+            Signature::one_of(signatures, python_function.volatility)
+        };
 
         Self {
             python_function,
@@ -78,11 +106,16 @@ impl PythonScalarUDF {
     where
         I: ExactSizeIterator<Item = &'a DataType>,
     {
-        if arg_types.len() != self.python_function.signature.parameters.len() {
+        let arity = self.python_function.signature.arity();
+        if !arity.contains(&arg_types.len()) {
+            let expected = if arity.start() == arity.end() {
+                arity.end().to_string()
+            } else {
+                format!("{} to {}", arity.start(), arity.end())
+            };
             return Err(format!(
-                "`{}` expects {} parameters but got {}",
+                "`{}` expects {expected} parameters but got {}",
                 self.name(),
-                self.python_function.signature.parameters.len(),
                 arg_types.len(),
             ));
         }
@@ -91,13 +124,12 @@ impl PythonScalarUDF {
             .zip(&self.python_function.signature.parameters)
             .enumerate()
         {
-            let expected = expected.t.data_type();
-            if actual != &expected {
+            if !expected.t.accepts_arg_data_type(actual) {
                 return Err(format!(
                     "argument {} of `{}` should be {}, got {}",
                     pos + 1,
                     self.name(),
-                    expected,
+                    expected.t.data_type(),
                     actual
                 ));
             }
@@ -105,6 +137,95 @@ impl PythonScalarUDF {
 
         Ok(self.python_function.signature.return_type.t.data_type())
     }
+
+    /// Cast result to the [requested type](ScalarFunctionArgs::return_field), see
+    /// [`PythonType::accepts_data_type`](signature::PythonType::accepts_data_type).
+    fn cast_result(
+        output: ColumnarValue,
+        return_field: &FieldRef,
+    ) -> DataFusionResult<ColumnarValue> {
+        if output.data_type() == *return_field.data_type() {
+            return Ok(output);
+        }
+        output.cast_to(return_field.data_type(), None)
+    }
+
+    /// Build the value of the [fields parameter](signature::FIELDS_PARAM) if the function declares it.
+    ///
+    /// Every argument field is represented as a `dict` with the keys `name`, `nullable`, and `metadata`.
+    fn fields_param<'py>(
+        &self,
+        arg_fields: &[FieldRef],
+        py: Python<'py>,
+    ) -> DataFusionResult<Option<Bound<'py, PyTuple>>> {
+        if !self.python_function.signature.fields_param {
+            return Ok(None);
+        }
+
+        let fields = arg_fields
+            .iter()
+            .map(|field| {
+                let metadata = PyDict::new(py);
+                for (k, v) in field.metadata() {
+                    metadata.set_item(k, v)?;
+                }
+
+                let dict = PyDict::new(py);
+                dict.set_item(intern!(py, "name"), field.name())?;
+                dict.set_item(intern!(py, "nullable"), field.is_nullable())?;
+                dict.set_item(intern!(py, "metadata"), metadata)?;
+                Ok(dict)
+            })
+            .collect::<PyResult<Vec<_>>>()
+            .and_then(|fields| PyTuple::new(py, fields))
+            .map_err(|e| {
+                exec_datafusion_err!("{}", py_err_to_string(e, py))
+                    .context("cannot convert argument fields")
+            })?;
+        Ok(Some(fields))
+    }
+
+    /// Invoke [columnar](signature::PythonFnSignature::is_columnar) function once for the entire batch.
+    #[cfg(feature = "pyarrow")]
+    fn invoke_columnar(
+        &self,
+        arrays: &[arrow::array::ArrayRef],
+        arg_fields: &[FieldRef],
+        number_rows: usize,
+    ) -> DataFusionResult<ColumnarValue> {
+        Python::attach(|py| {
+            let params = arrays
+                .iter()
+                .map(|array| pyarrow::array_to_python(array.as_ref(), py))
+                .collect::<Result<Vec<_>, _>>()?;
+            let params = PyTuple::new(py, params)
+                .map_err(|e| exec_datafusion_err!("cannot create parameter tuple: {e}"))?;
+            let kwargs = self
+                .fields_param(arg_fields, py)?
+                .map(|fields| {
+                    let kwargs = PyDict::new(py);
+                    kwargs.set_item(signature::FIELDS_PARAM, fields)?;
+                    PyResult::Ok(kwargs)
+                })
+                .transpose()
+                .map_err(|e| exec_datafusion_err!("cannot create keyword arguments: {e}"))?;
+
+            let rval = self
+                .python_function
+                .handle
+                .bind(py)
+                .call(params, kwargs.as_ref())
+                .map_err(|e| py_err_to_datafusion(e, py).context("cannot call function"))?;
+
+            let output_array = pyarrow::array_from_python(
+                &rval,
+                &self.python_function.signature.return_type.t.data_type(),
+                number_rows,
+            )?;
+
+            Ok(ColumnarValue::Array(output_array))
+        })
+    }
 }
 
 impl PartialEq<Self> for PythonScalarUDF {
@@ -148,12 +269,12 @@ impl ScalarUDFImpl for PythonScalarUDF {
             config_options: _,
         } = args;
 
-        let return_dt = self.python_function.signature.return_type.t.data_type();
-        if return_field.data_type() != &return_dt {
+        let return_type = &self.python_function.signature.return_type.t;
+        if !return_type.accepts_data_type(return_field.data_type()) {
             return exec_err!(
                 "`{}` returns {} but was asked to produce {}",
                 self.name(),
-                return_dt,
+                return_type.data_type(),
                 return_field.data_type()
             );
         }
@@ -170,11 +291,12 @@ impl ScalarUDFImpl for PythonScalarUDF {
                 DataFusionError::Execution(format!("checking argument fields: {msg}"))
             })?;
 
-        if args.len() != self.python_function.signature.parameters.len() {
+        // missing trailing arguments are filled in by Python using their default values
+        if args.len() != arg_fields.len() {
             return exec_err!(
-                "`{}` expects {} parameters (passed as args) but got {}",
+                "`{}` got {} argument fields but {} arguments",
                 self.name(),
-                self.python_function.signature.parameters.len(),
+                arg_fields.len(),
                 args.len()
             );
         }
@@ -191,17 +313,37 @@ impl ScalarUDFImpl for PythonScalarUDF {
                         array.len()
                     );
                 }
-                Ok(array)
+
+                // convert alternative layouts to the canonical one
+                let expected = self.python_function.signature.parameters[i].t.data_type();
+                if array.data_type() == &expected {
+                    Ok(array)
+                } else {
+                    Ok(cast(&array, &expected)?)
+                }
             })
             .collect::<Result<Vec<_>, _>>()?;
 
-        Python::attach(|py| {
+        #[cfg(feature = "pyarrow")]
+        if self.python_function.signature.is_columnar() {
+            let output = self.invoke_columnar(&arrays, &arg_fields, number_rows)?;
+            return Self::cast_result(output, &return_field);
+        }
+
+        let output = Python::attach(|py| {
             let mut parameter_iters = arrays
                 .iter()
                 .zip(&self.python_function.signature.parameters)
                 .map(|(array, t)| t.arrow_to_python(array, py))
                 .collect::<Result<Vec<_>, _>>()?;
 
+            let fields = self.fields_param(&arg_fields, py)?;
+            let kwnames = fields
+                .as_ref()
+                .map(|_| PyTuple::new(py, [signature::FIELDS_PARAM]))
+                .transpose()
+                .map_err(|e| exec_datafusion_err!("cannot create keyword names: {e}"))?;
+
             let handle = self.python_function.handle.bind(py);
             let mut output_row_builder = self
                 .python_function
@@ -211,18 +353,19 @@ impl ScalarUDFImpl for PythonScalarUDF {
 
             // allocate params vector once and reuse for each row
             // NOTE: the pointer array needs one additional slot because we need to prepend a NULL ptr for the vectorcall API
+            //       and another one for the optional keyword argument
             let mut params = Vec::with_capacity(parameter_iters.len());
-            let mut params_ptrs = Vec::with_capacity(parameter_iters.len() + 1);
+            let mut params_ptrs = Vec::with_capacity(parameter_iters.len() + 2);
 
             for _ in 0..number_rows {
                 // poll ALL iterators before evaluating the controlflow
                 params.clear();
                 for it in &mut parameter_iters {
                     match it.next().expect("all iterators have n_rows")? {
-                        ControlFlow::Continue(param) => {
+                        PythonArg::Pass(param) => {
                             params.push(param);
                         }
-                        ControlFlow::Break(()) => {}
+                        PythonArg::SkipRow => {}
                     }
                 }
 
@@ -233,6 +376,10 @@ impl ScalarUDFImpl for PythonScalarUDF {
                     params_ptrs.clear();
                     params_ptrs.push(std::ptr::null_mut());
                     params_ptrs.extend(params.iter().map(|p| p.as_ptr()));
+                    // keyword arguments follow the positional ones, their names are passed separately
+                    if let Some(fields) = &fields {
+                        params_ptrs.push(fields.as_ptr());
+                    }
 
                     // SAFETY: We are holding a reference to `params` to keep the pointers alive. We also follow that `pyo3` is doing.
                     let call_res_ptr = unsafe {
@@ -240,16 +387,16 @@ impl ScalarUDFImpl for PythonScalarUDF {
                             handle.as_ptr(),
                             params_ptrs.as_mut_ptr().add(1),
                             params.len() + pyo3::ffi::PY_VECTORCALL_ARGUMENTS_OFFSET,
-                            std::ptr::null_mut(),
+                            kwnames
+                                .as_ref()
+                                .map_or(std::ptr::null_mut(), |kwnames| kwnames.as_ptr()),
                         )
                     };
                     // SAFETY: `vectorcall` returns a non-NULL pointer that we are supposed to own
                     let call_res = unsafe { Bound::from_owned_ptr_or_err(py, call_res_ptr) };
 
-                    let rval = call_res.map_err(|e| {
-                        exec_datafusion_err!("{}", py_err_to_string(e, py))
-                            .context("cannot call function")
-                    })?;
+                    let rval = call_res
+                        .map_err(|e| py_err_to_datafusion(e, py).context("cannot call function"))?;
                     output_row_builder.push(rval)?;
                 } else {
                     // NULL row
@@ -271,14 +418,16 @@ impl ScalarUDFImpl for PythonScalarUDF {
             // check invariants
             assert_eq!(output_array.len(), number_rows);
 
-            Ok(ColumnarValue::Array(output_array))
-        })
+            Ok::<_, DataFusionError>(ColumnarValue::Array(output_array))
+        })?;
+        Self::cast_result(output, &return_field)
     }
 }
 
-/// Return root file system.
+/// Return root file system as TAR archive.
 ///
-/// This will be [`Some`] if built for WASM, but [`None`] if build for non-WASM host (e.g. during `cargo check`).
+/// The host uses this to populate the root file system of the guest. This will be [`Some`] if built for WASM, but
+/// [`None`] if build for non-WASM host (e.g. during `cargo check`).
 #[allow(clippy::allow_attributes, clippy::const_is_empty)]
 fn root() -> Option<Vec<u8>> {
     // The build script will ALWAYS set this environment variable, but if we don't bundle the standard lib the file
@@ -287,22 +436,15 @@ fn root() -> Option<Vec<u8>> {
     (!ROOT_TAR.is_empty()).then(|| ROOT_TAR.to_vec())
 }
 
-/// Populate the guest root filesystem from the bundled Python standard library archive.
-fn prepare_root_fs() -> Result<(), Error> {
-    let root_fs_tar = root();
-    root_fs::populate_root_fs_from_tar(root_fs_tar.as_deref())
-        .map(|_| ())
-        .map_err(|e| DataFusionError::Execution(e.to_string()).into())
-}
-
 /// Initialize Python interpreter.
 ///
 /// This should always be called before performing any Python interaction. Calling the method more than once is fine
 /// and will result in a "no-op"
 ///
 /// # Panic
-/// This assumes that the root file system contains the [Python Standard Library] or that the Python interpreter can
-/// find it somewhere else (e.g. when executed outside of a WASM context).
+/// This assumes that the host populated the root file system from [`root`] -- which contains the
+/// [Python Standard Library] -- or that the Python interpreter can find it somewhere else (e.g. when executed outside
+/// of a WASM context).
 ///
 /// Otherwise this will likely panic and you find the following printed to stderr:
 ///
@@ -319,14 +461,15 @@ fn prepare_root_fs() -> Result<(), Error> {
 ///
 /// This also checks if the running Python version is supported.
 ///
+/// The resulting state may be pre-initialized at build time (see the `preinit` feature), so this MUST NOT depend on
+/// the runtime environment. Use [`configure_python`] for that.
+///
 ///
 /// [Python Standard Library]: https://docs.python.org/3/library/index.html
 fn init_python() {
     static INIT: Once = Once::new();
 
     INIT.call_once(|| {
-        prepare_root_fs().expect("cannot prepare root filesystem for Python");
-
         python_modules::register();
         Python::initialize();
 
@@ -341,9 +484,28 @@ fn init_python() {
     });
 }
 
+/// Apply the runtime environment to the [initialized](init_python) interpreter.
+///
+/// This syncs `os.environ` -- which was captured during pre-initialization if the `preinit` feature is enabled -- with
+/// the actual environment and -- if configured -- restricts the module search path, see the "Dependencies" section of
+/// the README. Calling the method more than once is fine and will result in a "no-op".
+fn configure_python() {
+    static CONFIGURE: Once = Once::new();
+
+    CONFIGURE.call_once(|| {
+        Python::attach(|py| {
+            #[cfg(all(feature = "preinit", target_os = "wasi"))]
+            preinit::sync_environ(py).expect("sync os.environ");
+
+            python_modules::sitecustomize(py).expect("restrict module search path");
+        });
+    });
+}
+
 /// Return UDFs defined in the provided source code.
 pub fn udfs(source: String) -> DataFusionResult<Vec<Arc<dyn ScalarUDFImpl>>> {
     init_python();
+    configure_python();
 
     let udfs = inspect_python_code(&source)?;
     Ok(udfs
@@ -352,6 +514,50 @@ pub fn udfs(source: String) -> DataFusionResult<Vec<Arc<dyn ScalarUDFImpl>>> {
         .collect())
 }
 
+/// Check Python code for syntax errors without running it.
+pub fn validate_source(source: &str) -> Vec<Diagnostic> {
+    init_python();
+    configure_python();
+
+    validate_python_code(source)
+}
+
+/// Calls the `init` and `close` hooks of [Python UDFs](PythonScalarUDF).
+const LIFECYCLE: UdfLifecycle = UdfLifecycle {
+    init: |udf| call_hook(udf, |f| f.init.as_ref()),
+    close: |udf| call_hook(udf, |f| f.close.as_ref()),
+};
+
+/// Call hook of a [Python UDF](PythonScalarUDF), if there is any.
+fn call_hook(
+    udf: &dyn ScalarUDFImpl,
+    hook: fn(&PythonFn) -> Option<&Py<PyAny>>,
+) -> DataFusionResult<()> {
+    let Some(udf) = udf.as_any().downcast_ref::<PythonScalarUDF>() else {
+        return exec_err!("`{}` is not a Python UDF", udf.name());
+    };
+    let Some(hook) = hook(&udf.python_function) else {
+        return Ok(());
+    };
+
+    Python::attach(|py| {
+        hook.call0(py)
+            .map_err(|e| exec_datafusion_err!("{}", py_err_to_string(e, py)))?;
+        Ok(())
+    })
+}
+
+/// Let the host skip NULL rows of [null-strict](signature::PythonFnSignature::is_null_strict) Python UDFs.
+fn null_strict(udf: &dyn ScalarUDFImpl) -> bool {
+    udf.as_any()
+        .downcast_ref::<PythonScalarUDF>()
+        .is_some_and(|udf| udf.python_function.signature.is_null_strict())
+}
+
 export! {
     scalar_udfs: udfs,
+    root_fs_tar: root,
+    udf_lifecycle: LIFECYCLE,
+    null_strict: null_strict,
+    validate_source: validate_source,
 }
@@ -0,0 +1,58 @@
+//! Pre-initialization of the interpreter at build time.
+//!
+//! [Wizer] calls the exported `wizer-initialize` function once when the component is built and snapshots the
+//! resulting state. Guest instances then start with a bootstrapped interpreter.
+//!
+//!
+//! [Wizer]: https://github.com/bytecodealliance/wasmtime/tree/main/crates/wizer
+use std::os::wasi::ffi::OsStrExt;
+
+use pyo3::{intern, prelude::*, types::PyBytes};
+use wit_bindgen::generate;
+
+use crate::init_python;
+
+generate!({
+    world: "preinit",
+    path: "wit",
+});
+
+unsafe extern "C" {
+    /// Reset the environment variables of `wasi-libc`, so they are read again at runtime.
+    ///
+    /// See <https://github.com/WebAssembly/wasi-libc/blob/main/libc-bottom-half/sources/environ.c>.
+    fn __wasilibc_deinitialize_environ();
+}
+
+/// Implements the `preinit` world.
+struct Preinit;
+
+impl Guest for Preinit {
+    fn wizer_initialize() {
+        init_python();
+
+        // the environment of the build MUST NOT leak into the snapshot
+        // SAFETY: no other code accesses the environment at this point
+        unsafe { __wasilibc_deinitialize_environ() };
+    }
+}
+
+export!(Preinit);
+
+/// Replace the content of `os.environ`, which was captured during pre-initialization, with the actual environment.
+pub(crate) fn sync_environ(py: Python<'_>) -> PyResult<()> {
+    // Write to the underlying storage of `os.environ` to bypass `putenv`, since the environment of `wasi-libc` is
+    // already up-to-date. This also keeps variables that `putenv` would reject, e.g. ones with an empty name.
+    let data = py
+        .import(intern!(py, "os"))?
+        .getattr(intern!(py, "environ"))?
+        .getattr(intern!(py, "_data"))?;
+    data.call_method0(intern!(py, "clear"))?;
+    for (k, v) in std::env::vars_os() {
+        data.set_item(
+            PyBytes::new(py, k.as_bytes()),
+            PyBytes::new(py, v.as_bytes()),
+        )?;
+    }
+    Ok(())
+}
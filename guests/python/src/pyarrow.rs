@@ -0,0 +1,156 @@
+//! Columnar exchange with [`pyarrow`].
+//!
+//! Arrays are passed via the [Arrow C Data Interface]. Since Python and Rust share the same linear memory, this does
+//! NOT copy the buffers.
+//!
+//!
+//! [Arrow C Data Interface]: https://arrow.apache.org/docs/format/CDataInterface.html
+//! [`pyarrow`]: https://arrow.apache.org/docs/python/
+use arrow::{
+    array::{Array, ArrayRef, make_array},
+    datatypes::DataType,
+    ffi::{FFI_ArrowArray, FFI_ArrowSchema, from_ffi, to_ffi},
+};
+use datafusion_common::{Result as DataFusionResult, exec_datafusion_err, exec_err};
+use pyo3::{
+    Bound, PyAny, PyErr, PyResult, Python, exceptions::PyTypeError, intern, types::PyAnyMethods,
+};
+
+use crate::{error::py_err_to_string, inspect::py_representation};
+
+/// Extract [`DataType`] from `Annotated[pyarrow.Array, <pyarrow.DataType>]`.
+///
+/// Returns [`None`] if the annotation does not refer to `pyarrow.Array`. This does NOT import `pyarrow`: if the user
+/// code did not import it, the annotation cannot refer to it either.
+pub(crate) fn extract_array_annotation(ob: &Bound<'_, PyAny>) -> PyResult<Option<DataType>> {
+    let py = ob.py();
+
+    // https://docs.python.org/3/library/sys.html#sys.modules
+    let modules = py
+        .import(intern!(py, "sys"))?
+        .getattr(intern!(py, "modules"))?;
+    let Some(mod_pyarrow) = modules
+        .call_method1(intern!(py, "get"), (intern!(py, "pyarrow"),))
+        .ok()
+        .filter(|m| !m.is_none())
+    else {
+        return Ok(None);
+    };
+    let type_array = mod_pyarrow.getattr(intern!(py, "Array"))?;
+    let type_data_type = mod_pyarrow.getattr(intern!(py, "DataType"))?;
+
+    if ob.is(&type_array) {
+        return Err(PyErr::new::<PyTypeError, _>(
+            "`pyarrow.Array` requires a data type, use `Annotated[pyarrow.Array, pyarrow.int64()]`"
+                .to_owned(),
+        ));
+    }
+
+    // https://docs.python.org/3/library/typing.html#typing.get_origin
+    let mod_typing = py.import(intern!(py, "typing"))?;
+    let origin = mod_typing
+        .getattr(intern!(py, "get_origin"))?
+        .call1((ob,))?;
+    if !origin.is(mod_typing.getattr(intern!(py, "Annotated"))?) {
+        return Ok(None);
+    }
+    let args = mod_typing
+        .getattr(intern!(py, "get_args"))?
+        .call1((ob,))?
+        .try_iter()?
+        .collect::<PyResult<Vec<_>>>()?;
+    let [t, metadata] = args.as_slice() else {
+        return Ok(None);
+    };
+    if !t.is(&type_array) {
+        return Ok(None);
+    }
+    if !metadata.is_instance(&type_data_type)? {
+        return Err(PyErr::new::<PyTypeError, _>(format!(
+            "`pyarrow.Array` annotation must be a `pyarrow.DataType`, got {}",
+            py_representation(metadata)
+        )));
+    }
+
+    let mut schema = FFI_ArrowSchema::empty();
+    metadata.call_method1(intern!(py, "_export_to_c"), (&raw mut schema as usize,))?;
+    let dt = DataType::try_from(&schema).map_err(|e| {
+        PyErr::new::<PyTypeError, _>(format!(
+            "cannot convert {} to Arrow: {e}",
+            py_representation(metadata)
+        ))
+    })?;
+
+    Ok(Some(dt))
+}
+
+/// Convert Arrow [`Array`] to `pyarrow.Array`.
+pub(crate) fn array_to_python<'py>(
+    array: &dyn Array,
+    py: Python<'py>,
+) -> DataFusionResult<Bound<'py, PyAny>> {
+    let (mut ffi_array, mut ffi_schema) = to_ffi(&array.to_data())?;
+
+    // `pyarrow` moves the data out of both structs, our `Drop` implementations then see the released state
+    py.import(intern!(py, "pyarrow"))
+        .and_then(|m| m.getattr(intern!(py, "Array")))
+        .and_then(|t| {
+            t.call_method1(
+                intern!(py, "_import_from_c"),
+                (&raw mut ffi_array as usize, &raw mut ffi_schema as usize),
+            )
+        })
+        .map_err(|e| {
+            exec_datafusion_err!("{}", py_err_to_string(e, py))
+                .context("cannot convert Arrow array to `pyarrow.Array`")
+        })
+}
+
+/// Convert `pyarrow.Array` to Arrow [`Array`].
+pub(crate) fn array_from_python(
+    val: &Bound<'_, PyAny>,
+    data_type: &DataType,
+    num_rows: usize,
+) -> DataFusionResult<ArrayRef> {
+    let py = val.py();
+
+    let type_array = py
+        .import(intern!(py, "pyarrow"))
+        .and_then(|m| m.getattr(intern!(py, "Array")))
+        .map_err(|e| exec_datafusion_err!("{}", py_err_to_string(e, py)))?;
+    if !val.is_instance(&type_array).unwrap_or_default() {
+        return exec_err!(
+            "expected `pyarrow.Array` but got {}",
+            py_representation(val)
+        );
+    }
+
+    let mut ffi_array = FFI_ArrowArray::empty();
+    let mut ffi_schema = FFI_ArrowSchema::empty();
+    val.call_method1(
+        intern!(py, "_export_to_c"),
+        (&raw mut ffi_array as usize, &raw mut ffi_schema as usize),
+    )
+    .map_err(|e| {
+        exec_datafusion_err!("{}", py_err_to_string(e, py)).context("cannot export `pyarrow.Array`")
+    })?;
+
+    // SAFETY: `pyarrow` just filled both structs according to the Arrow C Data Interface.
+    let data = unsafe { from_ffi(ffi_array, &ffi_schema) }?;
+    let array = make_array(data);
+
+    if array.data_type() != data_type {
+        return exec_err!(
+            "expected `pyarrow.Array` of type {data_type} but got {}",
+            array.data_type()
+        );
+    }
+    if array.len() != num_rows {
+        return exec_err!(
+            "expected `pyarrow.Array` with {num_rows} rows but got {}",
+            array.len()
+        );
+    }
+
+    Ok(array)
+}
@@ -13,6 +13,22 @@ pub(crate) fn register() {
     pyo3::append_to_inittab!(wit_world);
 }
 
+/// Run our `sitecustomize` module, which restricts the module search path.
+///
+/// See the module docs in `sitecustomize.py` for the configuration. This must be called BEFORE any UDF code runs.
+pub(crate) fn sitecustomize(py: Python<'_>) -> PyResult<()> {
+    let module = PyModule::from_code(
+        py,
+        pyo3::ffi::c_str!(include_str!("sitecustomize.py")),
+        c"sitecustomize.py",
+        c"sitecustomize",
+    )?;
+    py.import("sys")?
+        .getattr("modules")?
+        .set_item("sitecustomize", module)?;
+    Ok(())
+}
+
 /// Provide a [`componentize-py`]-compatible Python API.
 ///
 /// Note that we currently only implement the interfaces that we need.
@@ -0,0 +1,67 @@
+"""Restrict the module search path of the guest.
+
+If the `DATAFUSION_UDF_WASM_PYTHON_PATH` environment variable is set, it lists the directories -- separated by `:` --
+that modules can be imported from in addition to the Python Standard Library. All other directories, including the
+bundled `site-packages`, are removed from `sys.path`. Imports that resolve to files outside of the search path are
+rejected, even if the UDF code modifies `sys.path` later on.
+"""
+
+import os
+import site
+import sys
+
+_ENV = "DATAFUSION_UDF_WASM_PYTHON_PATH"
+
+
+def _is_allowed(origin, allowed):
+    origin = os.path.normpath(origin)
+    return any(origin.startswith(directory + os.sep) for directory in allowed)
+
+
+class _RestrictedPathFinder:
+    """Meta path finder that rejects modules outside of the allowed directories."""
+
+    def __init__(self, allowed):
+        self._allowed = allowed
+
+    def find_spec(self, fullname, path=None, target=None):
+        for finder in sys.meta_path:
+            if finder is self or not hasattr(finder, "find_spec"):
+                continue
+
+            spec = finder.find_spec(fullname, path, target)
+            if spec is None:
+                continue
+
+            if spec.has_location and not _is_allowed(spec.origin, self._allowed):
+                raise ImportError(
+                    f"module {fullname!r} is outside of the module search path",
+                    name=fullname,
+                )
+            return spec
+
+        return None
+
+
+def _restrict():
+    value = os.environ.get(_ENV)
+    if value is None:
+        return
+
+    excluded = {
+        os.path.normpath(directory)
+        for directory in [*site.getsitepackages(), site.getusersitepackages()]
+    }
+    stdlib = [
+        os.path.normpath(directory)
+        for directory in sys.path
+        if directory and os.path.normpath(directory) not in excluded
+    ]
+    extra = [os.path.normpath(directory) for directory in value.split(":") if directory]
+
+    sys.path[:] = [*stdlib, *extra]
+    sys.path_importer_cache.clear()
+    sys.meta_path.insert(0, _RestrictedPathFinder(tuple(sys.path)))
+
+
+_restrict()
@@ -1,4 +1,11 @@
 //! Types that represent Python function signatures and handles.
+use std::{
+    hash::{Hash, Hasher},
+    ops::RangeInclusive,
+    sync::Arc,
+};
+
+use datafusion_expr::Volatility;
 use pyo3::{Py, PyAny};
 
 /// Python types that we support.
@@ -32,7 +39,15 @@ pub(crate) enum PythonType {
     ///
     /// # Arrow
     /// We map this to [`Binary`](arrow::datatypes::DataType::Binary).
-    Bytes,
+    ///
+    /// # Fixed Size
+    /// Values of a fixed size -- e.g. UUIDs or hashes -- are declared via an annotation, e.g. `Annotated[bytes, 16]`,
+    /// which maps to [`FixedSizeBinary`](arrow::datatypes::DataType::FixedSizeBinary). Returned values must have
+    /// exactly that length.
+    Bytes {
+        /// Size of every value in bytes, if fixed.
+        size: Option<i32>,
+    },
 
     /// Date (year, month, day).
     ///
@@ -55,8 +70,52 @@ pub(crate) enum PythonType {
     ///
     /// # Arrow
     /// We map this to [`Timestamp`](arrow::datatypes::DataType::Timestamp) with
-    /// [`Microsecond`](arrow::datatypes::TimeUnit::Microsecond) resolution (same as Python) and no time zone.
-    DateTime,
+    /// [`Microsecond`](arrow::datatypes::TimeUnit::Microsecond) resolution (same as Python).
+    ///
+    /// # Time Zones
+    /// A plain `datetime` annotation maps to a timestamp without time zone and uses "naive" Python objects. Time zone
+    /// aware timestamps are declared via an annotation, e.g. `Annotated[datetime, "+02:00"]` or
+    /// `Annotated[datetime, "Europe/Berlin"]`. Values passed to Python carry the respective `tzinfo`, values returned
+    /// from Python must be time zone aware and are normalized to the declared zone.
+    DateTime {
+        /// Time zone, if any.
+        tz: Option<Arc<str>>,
+    },
+
+    /// Fixed-point decimal number.
+    ///
+    /// # Python
+    /// The type is called `decimal.Decimal`, documentation can be found here:
+    ///
+    /// - <https://docs.python.org/3/library/decimal.html#decimal.Decimal>
+    ///
+    /// Since Python decimals do NOT carry a fixed precision and scale, these must be provided via an annotation, e.g.
+    /// `Annotated[Decimal, (38, 9)]` for `precision = 38` and `scale = 9`.
+    ///
+    /// # Arrow
+    /// We map this to [`Decimal128`](arrow::datatypes::DataType::Decimal128) for precisions up to 38 and to
+    /// [`Decimal256`](arrow::datatypes::DataType::Decimal256) for higher precisions (up to 76).
+    Decimal {
+        /// Total number of decimal digits.
+        precision: u8,
+
+        /// Number of digits after the decimal point.
+        scale: i8,
+    },
+
+    /// Dictionary with string keys.
+    ///
+    /// # Python
+    /// The type is called `dict[str, T]`, documentation can be found here:
+    ///
+    /// - <https://docs.python.org/3/library/stdtypes.html#mapping-types-dict>
+    ///
+    /// Keys must be strings and cannot be `None`. Values may be nullable, e.g. `dict[str, int | None]`.
+    ///
+    /// # Arrow
+    /// We map this to [`Map`](arrow::datatypes::DataType::Map) with [`Utf8`](arrow::datatypes::DataType::Utf8) keys.
+    /// Keys are NOT sorted.
+    Dict(Box<PythonNullableType>),
 
     /// Float.
     ///
@@ -67,7 +126,9 @@ pub(crate) enum PythonType {
     /// - <https://docs.python.org/3/library/functions.html#float>
     ///
     /// # Arrow
-    /// We map this to [`Float64`](arrow::datatypes::DataType::Float64).
+    /// We map this to [`Float64`](arrow::datatypes::DataType::Float64). Arguments of type
+    /// [`Float16`](arrow::datatypes::DataType::Float16) and [`Float32`](arrow::datatypes::DataType::Float32) are
+    /// upcast.
     Float,
 
     /// Signed integer.
@@ -82,6 +143,47 @@ pub(crate) enum PythonType {
     /// We map this to [`Int64`](arrow::datatypes::DataType::Int64).
     Int,
 
+    /// Calendar interval.
+    ///
+    /// # Python
+    /// The type is declared as `Annotated[tuple[int, int, int], "interval"]`, i.e. a `(months, days, nanoseconds)`
+    /// tuple. The components are independent and are NOT normalized, since the length of a month or a day depends on
+    /// the point in time the interval is applied to.
+    ///
+    /// # Arrow
+    /// We map this to [`Interval`](arrow::datatypes::DataType::Interval) with
+    /// [`MonthDayNano`](arrow::datatypes::IntervalUnit::MonthDayNano) resolution.
+    Interval,
+
+    /// JSON document.
+    ///
+    /// # Python
+    /// The type is declared as `Annotated[dict, "json"]` or `Annotated[list, "json"]`, also with type arguments, e.g.
+    /// `Annotated[dict[str, int], "json"]`. Arguments are decoded with `json.loads`, return values are encoded with
+    /// `json.dumps`. Documentation can be found here:
+    ///
+    /// - <https://docs.python.org/3/library/json.html>
+    ///
+    /// Any JSON value is passed to the function, the declared container type is NOT checked. Return values must be
+    /// serializable as standard JSON, i.e. `NaN` and infinite floats are rejected.
+    ///
+    /// # Arrow
+    /// We map this to [`Utf8`](arrow::datatypes::DataType::Utf8).
+    Json,
+
+    /// List.
+    ///
+    /// # Python
+    /// The type is called `list[T]`, documentation can be found here:
+    ///
+    /// - <https://docs.python.org/3/library/stdtypes.html#lists>
+    ///
+    /// Elements may be nullable, e.g. `list[int | None]`.
+    ///
+    /// # Arrow
+    /// We map this to [`List`](arrow::datatypes::DataType::List).
+    List(Box<PythonNullableType>),
+
     /// None/Null.
     ///
     /// # Python
@@ -94,6 +196,22 @@ pub(crate) enum PythonType {
     /// We map this to [`Null`](https://docs.rs/arrow/latest/arrow/datatypes/enum.DataType.html#variant.Null).
     None,
 
+    /// Arrow array.
+    ///
+    /// # Python
+    /// The type is called `pyarrow.Array`, documentation can be found here:
+    ///
+    /// - <https://arrow.apache.org/docs/python/generated/pyarrow.Array.html>
+    ///
+    /// Since arrays do NOT carry a static type, the Arrow type must be provided via an annotation, e.g.
+    /// `Annotated[pyarrow.Array, pyarrow.int64()]`. This is only supported for the parameters and the return type of
+    /// [columnar](PythonFnSignature::is_columnar) functions and cannot be nested.
+    ///
+    /// # Arrow
+    /// We map this to the annotated type.
+    #[cfg(feature = "pyarrow")]
+    PyArrow(arrow::datatypes::DataType),
+
     /// String.
     ///
     /// # Python
@@ -105,6 +223,37 @@ pub(crate) enum PythonType {
     /// We map this to [`Utf8`](arrow::datatypes::DataType::Utf8).
     Str,
 
+    /// Record with named fields.
+    ///
+    /// # Python
+    /// The type is declared as a `TypedDict` or a dataclass, e.g.:
+    ///
+    /// ```python
+    /// class UserAgent(TypedDict):
+    ///     browser: str
+    ///     os: str | None
+    /// ```
+    ///
+    /// Documentation can be found here:
+    ///
+    /// - <https://docs.python.org/3/library/typing.html#typing.TypedDict>
+    /// - <https://docs.python.org/3/library/dataclasses.html>
+    ///
+    /// Values of a `TypedDict` are plain `dict`s with exactly the declared keys, values of a dataclass are instances of
+    /// that class. Fields may be nullable, e.g. `os: str | None`. `NotRequired` keys, dataclass fields that are excluded
+    /// from `__init__`, and recursive types are NOT supported.
+    ///
+    /// # Arrow
+    /// We map this to [`Struct`](arrow::datatypes::DataType::Struct) with the declared field names, in declaration
+    /// order.
+    Struct {
+        /// Names and types of the fields, in declaration order.
+        fields: Vec<(String, PythonNullableType)>,
+
+        /// Dataclass, or [`None`] for a `TypedDict`.
+        dataclass: Option<PythonClass>,
+    },
+
     /// Time (hour, minute, second, microsecond).
     ///
     /// # Python
@@ -128,6 +277,57 @@ pub(crate) enum PythonType {
     /// We map this to [`Duration`](arrow::datatypes::DataType::Duration) with
     /// [`Microsecond`](arrow::datatypes::TimeUnit::Microsecond) resolution (same as Python).
     Timedelta,
+
+    /// Tuple with a fixed number of elements.
+    ///
+    /// # Python
+    /// The type is called `tuple[T1, T2, ...]`, documentation can be found here:
+    ///
+    /// - <https://docs.python.org/3/library/stdtypes.html#tuples>
+    ///
+    /// Elements may be nullable, e.g. `tuple[int, str | None]`. Variable-length tuples like `tuple[int, ...]` are NOT
+    /// supported.
+    ///
+    /// # Arrow
+    /// We map this to [`Struct`](arrow::datatypes::DataType::Struct) with fields named `c0`, `c1`, etc. (same as the
+    /// `struct` function in DataFusion).
+    Tuple(Vec<PythonNullableType>),
+
+    /// Universally unique identifier.
+    ///
+    /// # Python
+    /// The type is called `uuid.UUID`, documentation can be found here:
+    ///
+    /// - <https://docs.python.org/3/library/uuid.html#uuid.UUID>
+    ///
+    /// # Arrow
+    /// We map this to [`FixedSizeBinary(16)`](arrow::datatypes::DataType::FixedSizeBinary) holding the big-endian
+    /// bytes of the UUID. `Annotated[UUID, str]` maps to [`Utf8`](arrow::datatypes::DataType::Utf8) holding the
+    /// hyphenated string representation instead.
+    Uuid {
+        /// Use the string representation.
+        as_str: bool,
+    },
+}
+
+/// Handle of a Python class.
+///
+/// Two handles are equal if they refer to the same class object.
+#[derive(Debug)]
+pub(crate) struct PythonClass(pub(crate) Py<PyAny>);
+
+impl PartialEq for PythonClass {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.as_ptr() == other.0.as_ptr()
+    }
+}
+
+impl Eq for PythonClass {}
+
+impl Hash for PythonClass {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.as_ptr().hash(state);
+    }
 }
 
 /// [`PythonType`] plus "nullable" flag.
@@ -141,7 +341,12 @@ pub(crate) enum PythonType {
 ///
 /// There used to be an older representation too: `typing.Optional[int]`. As of Python 3.14, this results in the same
 /// representation as `int | None`. See <https://docs.python.org/3.14/whatsnew/3.14.html#typing>. So we support both.
-#[derive(Debug)]
+///
+/// # Nested Types
+/// For elements of [lists](PythonType::List), [dictionaries](PythonType::Dict), [tuples](PythonType::Tuple), and
+/// [structs](PythonType::Struct), the flag controls the nullability of the respective Arrow
+/// [`Field`](arrow::datatypes::Field). So `list[int]` and `list[int | None]` are different Arrow types.
+#[derive(Debug, PartialEq, Eq, Hash)]
 pub(crate) struct PythonNullableType {
     /// Python type.
     pub(crate) t: PythonType,
@@ -158,8 +363,42 @@ pub(crate) struct PythonFnSignature {
     /// We only support unnamed arguments.
     pub(crate) parameters: Vec<PythonNullableType>,
 
+    /// Number of leading [parameters](Self::parameters) that do NOT have a default value.
+    ///
+    /// The remaining parameters may be omitted by the caller, Python then uses their default values.
+    pub(crate) required: usize,
+
     /// Return type.
     pub(crate) return_type: PythonNullableType,
+
+    /// The function declares the keyword-only [`FIELDS_PARAM`] parameter to receive the argument fields.
+    pub(crate) fields_param: bool,
+}
+
+/// Name of the optional keyword-only parameter that receives the names and metadata of the argument fields.
+pub(crate) const FIELDS_PARAM: &str = "_fields";
+
+impl PythonFnSignature {
+    /// NULL rows are skipped -- i.e. result in NULL -- if no parameter is nullable.
+    ///
+    /// This is NOT the case for functions without parameters, since they cannot observe NULLs at all. Columnar
+    /// parameters are always nullable.
+    pub(crate) fn is_null_strict(&self) -> bool {
+        !self.parameters.is_empty() && self.parameters.iter().all(|param| !param.nullable)
+    }
+
+    /// Accepted number of arguments.
+    pub(crate) fn arity(&self) -> RangeInclusive<usize> {
+        self.required..=self.parameters.len()
+    }
+
+    /// Columnar functions are called once per batch with `pyarrow.Array` arguments instead of once per row.
+    ///
+    /// If the return type is a [`PyArrow`](PythonType::PyArrow) type, all parameters are too.
+    #[cfg(feature = "pyarrow")]
+    pub(crate) fn is_columnar(&self) -> bool {
+        matches!(self.return_type.t, PythonType::PyArrow(_))
+    }
 }
 
 /// Handle of a Python function.
@@ -171,6 +410,15 @@ pub(crate) struct PythonFn {
     /// Type signature.
     pub(crate) signature: PythonFnSignature,
 
+    /// Declared volatility.
+    pub(crate) volatility: Volatility,
+
+    /// Setup hook, declared via the `init` attribute.
+    pub(crate) init: Option<Py<PyAny>>,
+
+    /// Teardown hook, declared via the `close` attribute.
+    pub(crate) close: Option<Py<PyAny>>,
+
     /// Handle of the object within the Python VM.
     pub(crate) handle: Py<PyAny>,
 }
@@ -0,0 +1,156 @@
+//! Procedural macros for the Rust guest glue code.
+//!
+//! Use these through the re-exports of `datafusion-udf-wasm-guest`, which also documents them.
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{
+    Error, FnArg, GenericArgument, Ident, ItemFn, LitStr, PathArguments, ReturnType, Type,
+    TypePath, parse_macro_input,
+};
+
+/// Turn a plain function into a scalar UDF.
+///
+/// See `datafusion_udf_wasm_guest::wasm_udf`.
+#[proc_macro_attribute]
+pub fn wasm_udf(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let mut name = None;
+    let parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("name") {
+            name = Some(meta.value()?.parse::<LitStr>()?);
+            Ok(())
+        } else {
+            Err(meta.error("unsupported `wasm_udf` argument"))
+        }
+    });
+    parse_macro_input!(attr with parser);
+    let func = parse_macro_input!(item as ItemFn);
+
+    expand(name, func)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// Generate UDF for the given function.
+fn expand(name: Option<LitStr>, func: ItemFn) -> syn::Result<TokenStream2> {
+    let krate = quote!(::datafusion_udf_wasm_guest::simple);
+    let sig = &func.sig;
+    if let Some(asyncness) = &sig.asyncness {
+        return Err(Error::new_spanned(asyncness, "UDFs cannot be async"));
+    }
+    if !sig.generics.params.is_empty() {
+        return Err(Error::new_spanned(&sig.generics, "UDFs cannot be generic"));
+    }
+    if let Some(variadic) = &sig.variadic {
+        return Err(Error::new_spanned(variadic, "UDFs cannot be variadic"));
+    }
+
+    let ident = &sig.ident;
+    let name = name.unwrap_or_else(|| LitStr::new(&ident.to_string(), ident.span()));
+
+    // the identifiers are unused for functions without arguments
+    let (args, row) = if sig.inputs.is_empty() {
+        (format_ident!("_args"), format_ident!("_row"))
+    } else {
+        (format_ident!("args"), format_ident!("row"))
+    };
+
+    let mut arg_types = vec![];
+    let mut downcasts = vec![];
+    let mut reads = vec![];
+    let mut values = vec![];
+    for (idx, input) in sig.inputs.iter().enumerate() {
+        let FnArg::Typed(pat_type) = input else {
+            return Err(Error::new_spanned(input, "UDFs cannot take `self`"));
+        };
+        let (ty, nullable) = match generic_arg(&pat_type.ty, |ident| ident == "Option") {
+            Some(ty) => (ty, true),
+            None => (pat_type.ty.as_ref(), false),
+        };
+
+        let array = format_ident!("array_{idx}");
+        let value = format_ident!("value_{idx}");
+        arg_types.push(quote! { <#ty as #krate::SimpleValue>::data_type() });
+        downcasts.push(quote! {
+            let #array = #krate::downcast::<#ty>(&#args[#idx], #idx)?;
+        });
+        reads.push(if nullable {
+            quote! {
+                let #value = #krate::get::<#ty>(#array, #row);
+            }
+        } else {
+            // NULL in, NULL out
+            quote! {
+                let ::std::option::Option::Some(#value) = #krate::get::<#ty>(#array, #row) else {
+                    out.push(::std::option::Option::None);
+                    continue;
+                };
+            }
+        });
+        values.push(value);
+    }
+
+    let ReturnType::Type(_, ty) = &sig.output else {
+        return Err(Error::new_spanned(sig, "UDFs must return a value"));
+    };
+    let (ty, fallible) = match generic_arg(ty, |ident| ident.to_string().ends_with("Result")) {
+        Some(ty) => (ty, true),
+        None => (ty.as_ref(), false),
+    };
+    let (ty, nullable) = match generic_arg(ty, |ident| ident == "Option") {
+        Some(ty) => (ty, true),
+        None => (ty, false),
+    };
+
+    let mut call = quote! { #ident(#(#values),*) };
+    if fallible {
+        call = quote! { #call? };
+    }
+    if !nullable {
+        call = quote! { ::std::option::Option::Some(#call) };
+    }
+
+    let vis = &func.vis;
+    let udf_ident = format_ident!("{ident}_udf");
+    let doc = format!("Scalar UDF that calls [`{ident}`].");
+
+    Ok(quote! {
+        #func
+
+        #[doc = #doc]
+        #vis fn #udf_ident() -> ::std::sync::Arc<dyn #krate::ScalarUDFImpl> {
+            ::std::sync::Arc::new(#krate::SimpleUdf::new(
+                #name,
+                ::std::vec![#(#arg_types),*],
+                <#ty as #krate::SimpleValue>::data_type(),
+                |#args, number_rows| {
+                    #(#downcasts)*
+                    let mut out = ::std::vec::Vec::with_capacity(number_rows);
+                    for #row in 0..number_rows {
+                        #(#reads)*
+                        out.push(#call);
+                    }
+                    ::std::result::Result::Ok(<#ty as #krate::SimpleValue>::to_array(out))
+                },
+            ))
+        }
+    })
+}
+
+/// Get first generic type argument if the last path segment of the type matches, e.g. `T` for `Option<T>`.
+fn generic_arg(ty: &Type, matches: impl Fn(&Ident) -> bool) -> Option<&Type> {
+    let Type::Path(TypePath { qself: None, path }) = ty else {
+        return None;
+    };
+    let segment = path.segments.last()?;
+    if !matches(&segment.ident) {
+        return None;
+    }
+    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    args.args.iter().find_map(|arg| match arg {
+        GenericArgument::Type(ty) => Some(ty),
+        _ => None,
+    })
+}
@@ -0,0 +1,119 @@
+//! Example Scalar UDF with a user-defined signature that lets DataFusion coerce its arguments.
+
+// unused-crate-dependencies false positives
+#![expect(unused_crate_dependencies)]
+
+use std::sync::Arc;
+
+use arrow::{array::Int64Array, datatypes::DataType};
+use datafusion_common::{
+    Result as DataFusionResult, ScalarValue, exec_datafusion_err, exec_err, plan_err,
+};
+use datafusion_expr::{ColumnarValue, ScalarFunctionArgs, ScalarUDFImpl, Signature, Volatility};
+use datafusion_udf_wasm_guest::export;
+
+/// UDF that adds two integers of any width.
+#[derive(Debug, PartialEq, Eq, Hash)]
+struct AddInts {
+    /// Signature of the UDF.
+    ///
+    /// We store this here because [`ScalarUDFImpl::signature`] requires us to return a reference.
+    signature: Signature,
+}
+
+impl Default for AddInts {
+    fn default() -> Self {
+        Self {
+            signature: Signature::user_defined(Volatility::Immutable),
+        }
+    }
+}
+
+impl ScalarUDFImpl for AddInts {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        "add_ints"
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn coerce_types(&self, arg_types: &[DataType]) -> DataFusionResult<Vec<DataType>> {
+        if arg_types.len() != 2 {
+            return plan_err!("add_ints expects exactly two arguments");
+        }
+        arg_types
+            .iter()
+            .map(|dt| {
+                if dt.is_integer() || dt.is_null() {
+                    Ok(DataType::Int64)
+                } else {
+                    plan_err!("add_ints only accepts integer arguments, got {dt}")
+                }
+            })
+            .collect()
+    }
+
+    fn return_type(&self, arg_types: &[DataType]) -> DataFusionResult<DataType> {
+        if arg_types != [DataType::Int64, DataType::Int64] {
+            return plan_err!("add_ints expects coerced Int64 arguments");
+        }
+        Ok(DataType::Int64)
+    }
+
+    fn invoke_with_args(&self, args: ScalarFunctionArgs) -> DataFusionResult<ColumnarValue> {
+        let ScalarFunctionArgs {
+            args,
+            arg_fields: _,
+            number_rows: _,
+            return_field: _,
+            config_options: _,
+        } = args;
+
+        // extract inputs
+        let [a, b] = ColumnarValue::values_to_arrays(&args)?
+            .try_into()
+            .map_err(|_| exec_datafusion_err!("add_ints expects exactly two arguments"))?;
+        let (Some(a), Some(b)) = (
+            a.as_any().downcast_ref::<Int64Array>(),
+            b.as_any().downcast_ref::<Int64Array>(),
+        ) else {
+            return exec_err!("add_ints only accepts Int64 arguments");
+        };
+
+        // perform calculation
+        let array = a
+            .iter()
+            .zip(b)
+            .map(|(a, b)| a?.checked_add(b?))
+            .collect::<Int64Array>();
+
+        // create output
+        if args
+            .iter()
+            .all(|arg| matches!(arg, ColumnarValue::Scalar(_)))
+        {
+            Ok(ColumnarValue::Scalar(ScalarValue::try_from_array(
+                &array, 0,
+            )?))
+        } else {
+            Ok(ColumnarValue::Array(Arc::new(array)))
+        }
+    }
+}
+
+/// Returns our one example UDF.
+///
+/// The passed `source` is ignored.
+#[expect(clippy::unnecessary_wraps, reason = "public API through export! macro")]
+fn udfs(_source: String) -> DataFusionResult<Vec<Arc<dyn ScalarUDFImpl>>> {
+    Ok(vec![Arc::new(AddInts::default())])
+}
+
+export! {
+    scalar_udfs: udfs,
+}
@@ -9,7 +9,10 @@ use arrow::{array::Int64Array, datatypes::DataType};
 use datafusion_common::{
     Result as DataFusionResult, ScalarValue, exec_datafusion_err, exec_err, plan_err,
 };
-use datafusion_expr::{ColumnarValue, ScalarFunctionArgs, ScalarUDFImpl, Signature, Volatility};
+use datafusion_expr::{
+    ColumnarValue, ScalarFunctionArgs, ScalarUDFImpl, Signature, Volatility,
+    sort_properties::{ExprProperties, SortProperties},
+};
 use datafusion_udf_wasm_guest::export;
 
 /// UDF that implements "add one".
@@ -52,6 +55,18 @@ impl ScalarUDFImpl for AddOne {
         Ok(DataType::Int64)
     }
 
+    fn output_ordering(&self, inputs: &[ExprProperties]) -> DataFusionResult<SortProperties> {
+        // adding a constant preserves the order
+        Ok(inputs
+            .first()
+            .map(|input| input.sort_properties)
+            .unwrap_or(SortProperties::Unordered))
+    }
+
+    fn preserves_lex_ordering(&self, _inputs: &[ExprProperties]) -> DataFusionResult<bool> {
+        Ok(true)
+    }
+
     fn invoke_with_args(&self, args: ScalarFunctionArgs) -> DataFusionResult<ColumnarValue> {
         let ScalarFunctionArgs {
             args,
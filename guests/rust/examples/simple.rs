@@ -0,0 +1,43 @@
+//! Example Scalar UDFs that are implemented as plain functions.
+
+// unused-crate-dependencies false positives
+#![expect(unused_crate_dependencies)]
+
+use std::sync::Arc;
+
+use datafusion_common::{Result as DataFusionResult, exec_err};
+use datafusion_expr::ScalarUDFImpl;
+use datafusion_udf_wasm_guest::{export, wasm_udf};
+
+/// Adds one, returns NULL on overflow.
+#[wasm_udf]
+fn add_one(x: i64) -> Option<i64> {
+    x.checked_add(1)
+}
+
+/// Greets someone, or everyone.
+#[wasm_udf(name = "hello")]
+fn greet(name: Option<String>) -> String {
+    format!("Hello, {}!", name.as_deref().unwrap_or("world"))
+}
+
+/// Divides two numbers, fails on division by zero.
+#[wasm_udf]
+fn safe_div(a: i64, b: i64) -> DataFusionResult<i64> {
+    if b == 0 {
+        return exec_err!("division by zero");
+    }
+    Ok(a / b)
+}
+
+/// Returns our example UDFs.
+///
+/// The passed `source` is ignored.
+#[expect(clippy::unnecessary_wraps, reason = "public API through export! macro")]
+fn udfs(_source: String) -> DataFusionResult<Vec<Arc<dyn ScalarUDFImpl>>> {
+    Ok(vec![add_one_udf(), greet_udf(), safe_div_udf()])
+}
+
+export! {
+    scalar_udfs: udfs,
+}
@@ -1,13 +1,17 @@
 //! Conversion routes from/to [WIT types](crate::bindings).
 use std::sync::Arc;
 
-use arrow::{array::ArrayRef, datatypes::DataType};
+use arrow::{array::ArrayRef, buffer::Buffer, datatypes::DataType};
 use datafusion_common::{error::DataFusionError, scalar::ScalarValue};
 use datafusion_expr::{ColumnarValue, ScalarFunctionArgs};
-use datafusion_udf_wasm_arrow2bytes::{array2bytes, bytes2array, bytes2datatype, datatype2bytes};
+use datafusion_udf_wasm_arrow2bytes::{
+    Compression, DecodeOptions, array2bytes, array2bytes_compressed, buffer2array, bytes2datatype,
+    checksum, datatype2bytes,
+};
 
 use crate::{
     bindings::exports::datafusion_udf_wasm::udf::types as wit_types,
+    error::{ErrorWithTraceback, TracebackFrame},
     wrapper::{ConfigOptionsWrapper, FieldWrapper},
 };
 
@@ -17,10 +21,24 @@ impl From<DataFusionError> for wit_types::DataFusionError {
 
         let mut e = e;
         let mut context_chain = Vec::new();
-        while let DataFusionError::Context(context, e2) = e {
-            e = *e2;
-            context_chain.push(context);
-        }
+        let mut traceback = None;
+        let e = loop {
+            e = match e {
+                DataFusionError::Context(context, e2) => {
+                    context_chain.push(context);
+                    *e2
+                }
+                DataFusionError::External(e2) => match e2.downcast::<ErrorWithTraceback>() {
+                    Ok(e2) => {
+                        let ErrorWithTraceback { error, frames } = *e2;
+                        traceback = Some(frames.into_iter().map(Into::into).collect());
+                        error
+                    }
+                    Err(e2) => break DataFusionError::External(e2),
+                },
+                e => break e,
+            };
+        };
 
         let kind = match e {
             DataFusionError::NotImplemented(msg) => DataFusionErrorKind::NotImplemented(msg),
@@ -34,6 +52,22 @@ impl From<DataFusionError> for wit_types::DataFusionError {
         Self {
             context: context_chain,
             kind,
+            traceback,
+        }
+    }
+}
+
+impl From<TracebackFrame> for wit_types::TracebackFrame {
+    fn from(frame: TracebackFrame) -> Self {
+        let TracebackFrame {
+            file,
+            line,
+            function,
+        } = frame;
+        Self {
+            file,
+            line,
+            function,
         }
     }
 }
@@ -98,11 +132,19 @@ impl TryFrom<datafusion_expr::TypeSignature> for wit_types::TypeSignature {
             }
             TypeSignature::Comparable(n) => Self::Comparable(n as u64),
             TypeSignature::Any(n) => Self::Any(n as u64),
-            TypeSignature::OneOf(_type_signatures) => {
-                return Err(DataFusionError::NotImplemented(
-                    "serialize TypeSignature::OneOf".to_owned(),
-                ));
-            }
+            TypeSignature::OneOf(type_signatures) => Self::OneOfExact(
+                type_signatures
+                    .into_iter()
+                    .map(|type_signature| match type_signature {
+                        TypeSignature::Exact(data_types) => {
+                            Ok(data_types.into_iter().map(From::from).collect())
+                        }
+                        _ => Err(DataFusionError::NotImplemented(
+                            "serialize TypeSignature::OneOf with non-exact signatures".to_owned(),
+                        )),
+                    })
+                    .collect::<Result<_, _>>()?,
+            ),
             TypeSignature::ArraySignature(array_function_signature) => {
                 Self::ArraySignature(array_function_signature.try_into()?)
             }
@@ -141,6 +183,46 @@ impl From<ArrayRef> for wit_types::Array {
     fn from(value: ArrayRef) -> Self {
         Self {
             arrow_ipc_batch: array2bytes(value),
+            checksum: None,
+        }
+    }
+}
+
+impl From<wit_types::IpcCompression> for Compression {
+    fn from(value: wit_types::IpcCompression) -> Self {
+        match value {
+            wit_types::IpcCompression::Lz4Frame => Self::Lz4Frame,
+            wit_types::IpcCompression::Zstd => Self::Zstd,
+        }
+    }
+}
+
+impl From<Compression> for wit_types::IpcCompression {
+    fn from(value: Compression) -> Self {
+        match value {
+            Compression::Lz4Frame => Self::Lz4Frame,
+            Compression::Zstd => Self::Zstd,
+        }
+    }
+}
+
+impl wit_types::Array {
+    /// Serialize array using the given [`Compression`].
+    pub(crate) fn compressed(
+        array: ArrayRef,
+        compression: Compression,
+    ) -> Result<Self, DataFusionError> {
+        Ok(Self {
+            arrow_ipc_batch: array2bytes_compressed(array, compression)?,
+            checksum: None,
+        })
+    }
+
+    /// Attach [checksum] of the serialized data.
+    pub(crate) fn with_checksum(self) -> Self {
+        Self {
+            checksum: Some(checksum(&self.arrow_ipc_batch)),
+            ..self
         }
     }
 }
@@ -149,7 +231,12 @@ impl TryFrom<wit_types::Array> for ArrayRef {
     type Error = DataFusionError;
 
     fn try_from(value: wit_types::Array) -> Result<Self, Self::Error> {
-        let array = bytes2array(&value.arrow_ipc_batch)?;
+        // the host only compresses data if we announced support for the codec
+        let options = DecodeOptions {
+            allow_compression: true,
+            ..Default::default()
+        };
+        let array = buffer2array(Buffer::from(value.arrow_ipc_batch), &options)?;
         Ok(array)
     }
 }
@@ -204,6 +291,33 @@ impl TryFrom<ColumnarValue> for wit_types::ColumnarValue {
     }
 }
 
+impl wit_types::ColumnarValue {
+    /// Convert value and compress arrays using the given [`Compression`].
+    ///
+    /// Scalars are not compressed since they only contain a single value.
+    pub(crate) fn compressed(
+        value: ColumnarValue,
+        compression: Compression,
+    ) -> Result<Self, DataFusionError> {
+        Ok(match value {
+            ColumnarValue::Array(array) => {
+                Self::Array(wit_types::Array::compressed(array, compression)?)
+            }
+            ColumnarValue::Scalar(scalar) => Self::Scalar(scalar.try_into()?),
+        })
+    }
+
+    /// Attach [checksum] of the serialized data.
+    pub(crate) fn with_checksum(self) -> Self {
+        match self {
+            Self::Array(array) => Self::Array(array.with_checksum()),
+            Self::Scalar(scalar) => Self::Scalar(wit_types::ScalarValue {
+                array: scalar.array.with_checksum(),
+            }),
+        }
+    }
+}
+
 impl TryFrom<wit_types::ScalarFunctionArgs<'_>> for ScalarFunctionArgs {
     type Error = DataFusionError;
 
@@ -0,0 +1,51 @@
+//! Error types that carry additional information to the host.
+use std::fmt;
+
+use datafusion_common::error::DataFusionError;
+
+/// Single frame of a [traceback](ErrorWithTraceback).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TracebackFrame {
+    /// Source file.
+    pub file: String,
+
+    /// Line number, starting at 1.
+    ///
+    /// Use 0 if the line is unknown.
+    pub line: u32,
+
+    /// Name of the function.
+    pub function: String,
+}
+
+/// Error with a structured traceback.
+///
+/// Use [`ErrorWithTraceback::wrap`] to attach the traceback to a [`DataFusionError`]. The traceback is then passed to
+/// the host alongside the error. Any [context](DataFusionError::Context) that is added on top is preserved.
+#[derive(Debug)]
+pub struct ErrorWithTraceback {
+    /// Actual error.
+    pub error: DataFusionError,
+
+    /// Traceback frames, the innermost frame last.
+    pub frames: Vec<TracebackFrame>,
+}
+
+impl ErrorWithTraceback {
+    /// Attach traceback to error.
+    pub fn wrap(error: DataFusionError, frames: Vec<TracebackFrame>) -> DataFusionError {
+        DataFusionError::External(Box::new(Self { error, frames }))
+    }
+}
+
+impl fmt::Display for ErrorWithTraceback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.error.fmt(f)
+    }
+}
+
+impl std::error::Error for ErrorWithTraceback {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
@@ -0,0 +1,34 @@
+//! Key-value store provided by the host.
+//!
+//! Access must be granted by the host, otherwise all methods fail.
+use datafusion_common::{DataFusionError, Result as DataFusionResult};
+
+use crate::bindings::datafusion_udf_wasm::udf::kv::{self, KvError};
+
+/// Get value.
+pub fn get(key: &[u8]) -> DataFusionResult<Option<Vec<u8>>> {
+    kv::get(key).map_err(convert_err)
+}
+
+/// Set value, replacing any existing one.
+pub fn set(key: &[u8], value: &[u8]) -> DataFusionResult<()> {
+    kv::set(key, value).map_err(convert_err)
+}
+
+/// Delete value, returns `true` if the key existed.
+pub fn delete(key: &[u8]) -> DataFusionResult<bool> {
+    kv::delete(key).map_err(convert_err)
+}
+
+/// Convert host error.
+fn convert_err(e: KvError) -> DataFusionError {
+    match e {
+        KvError::Denied => {
+            DataFusionError::Execution("access to key-value store denied".to_owned())
+        }
+        KvError::LimitExceeded(msg) => {
+            DataFusionError::ResourcesExhausted(format!("key-value store: {msg}"))
+        }
+        KvError::Backend(msg) => DataFusionError::External(msg.into()),
+    }
+}
@@ -5,8 +5,115 @@
 
 pub mod bindings;
 pub mod conversion;
+pub mod error;
+pub mod kv;
+pub mod secrets;
+pub mod simple;
 pub mod wrapper;
 
+/// Turn a plain function into a scalar UDF.
+///
+/// The macro keeps the function as-is and generates a second function with the suffix `_udf` that returns the
+/// [`ScalarUDFImpl`]. The signature and the conversions from and to Arrow arrays are derived from the function
+/// signature.
+///
+/// ```rust
+/// # use std::sync::Arc;
+/// #
+/// # use datafusion_common::error::DataFusionError;
+/// # use datafusion_expr::ScalarUDFImpl;
+/// #
+/// # use datafusion_udf_wasm_guest::{export, wasm_udf};
+/// #
+/// #[wasm_udf]
+/// fn add_one(x: i64) -> Option<i64> {
+///     x.checked_add(1)
+/// }
+///
+/// fn udfs(_source: String) -> Result<Vec<Arc<dyn ScalarUDFImpl>>, DataFusionError> {
+///     Ok(vec![add_one_udf()])
+/// }
+///
+/// export! {
+///     scalar_udfs: udfs,
+/// }
+/// ```
+///
+/// # Types
+/// Arguments and return values can use all types that implement [`SimpleValue`](simple::SimpleValue), e.g. `i64`,
+/// `f64`, `bool`, and `String`.
+///
+/// # NULL Handling
+/// If an argument is NULL, the UDF returns NULL without calling the function. Wrap the argument type into an
+/// [`Option`] to handle NULLs yourself. Similarly, the function may return an [`Option`] to produce NULLs.
+///
+/// # Errors
+/// The function may return a `Result<T, E>` -- or any other type that is called `...Result<T, ...>` -- where
+/// `E` can be converted into [`DataFusionError`]. `T` may be an [`Option`].
+///
+/// ```rust
+/// # use datafusion_common::{error::DataFusionError, exec_err};
+/// #
+/// # use datafusion_udf_wasm_guest::wasm_udf;
+/// #
+/// #[wasm_udf]
+/// fn safe_div(a: i64, b: Option<i64>) -> Result<Option<i64>, DataFusionError> {
+///     match b {
+///         Some(0) => exec_err!("division by zero"),
+///         Some(b) => Ok(Some(a / b)),
+///         None => Ok(None),
+///     }
+/// }
+/// ```
+///
+/// # Name
+/// The UDF is named after the function. Use `#[wasm_udf(name = "...")]` to pick another name.
+///
+/// # Volatility
+/// The UDF is [immutable](datafusion_expr::Volatility::Immutable), i.e. the function must be pure.
+///
+///
+/// [`DataFusionError`]: datafusion_common::DataFusionError
+/// [`ScalarUDFImpl`]: datafusion_expr::ScalarUDFImpl
+pub use datafusion_udf_wasm_guest_macros::wasm_udf;
+
+/// Problem in the source code, see [`ExportOptions::validate_source`].
+pub use bindings::exports::datafusion_udf_wasm::udf::types::Diagnostic;
+
+/// Optional settings of [`export!`].
+///
+/// The macro fills all settings that are NOT provided with their [defaults](Self::DEFAULT).
+#[derive(Debug, Clone, Copy)]
+pub struct ExportOptions {
+    /// Return TAR archive of the root filesystem, see [`export!`].
+    pub root_fs_tar: fn() -> Option<Vec<u8>>,
+
+    /// Setup and teardown hooks, see [`export!`].
+    pub udf_lifecycle: wrapper::UdfLifecycle,
+
+    /// Declares if a UDF is null-strict, see [`export!`].
+    pub null_strict: fn(&dyn datafusion_expr::ScalarUDFImpl) -> bool,
+
+    /// Check source code without running it, see [`export!`].
+    pub validate_source: fn(&str) -> Vec<Diagnostic>,
+}
+
+impl ExportOptions {
+    /// No root filesystem, no hooks, no null-strict UDFs, and no source validation.
+    pub const DEFAULT: Self = Self {
+        root_fs_tar: || None,
+        udf_lifecycle: wrapper::UdfLifecycle::NOOP,
+        null_strict: |_udf| false,
+        validate_source: |_source| vec![],
+    };
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
 /// Export UDFs to WebAssembly.
 ///
 /// # Example
@@ -18,7 +125,7 @@ pub mod wrapper;
 /// ```
 ///
 /// Then implement a function that takes a string -- which it may use it or not -- and returns a list of
-/// [`ScalarUDFImpl`]s.
+/// [`ScalarUDFImpl`]s. The host may call it multiple times within the same guest, once per source block.
 ///
 /// ```rust
 /// # use std::sync::Arc;
@@ -38,23 +145,193 @@ pub mod wrapper;
 /// }
 /// ```
 ///
+/// # Root Filesystem
+/// Optionally, you may provide a function that returns a TAR archive which the host uses to populate the root
+/// filesystem of the guest. The host calls this function once per component and shares the resulting filesystem
+/// between all instances, so it must always return the same data.
+///
+/// ```rust
+/// # use std::sync::Arc;
+/// #
+/// # use datafusion_common::error::DataFusionError;
+/// # use datafusion_expr::ScalarUDFImpl;
+/// #
+/// # use datafusion_udf_wasm_guest::export;
+/// #
+/// # fn udfs(source: String) -> Result<Vec<Arc<dyn ScalarUDFImpl>>, DataFusionError> {
+/// #     todo!()
+/// # }
+/// #
+/// fn root() -> Option<Vec<u8>> {
+///     None
+/// }
+///
+/// export! {
+///     scalar_udfs: udfs,
+///     root_fs_tar: root,
+/// }
+/// ```
+///
+/// # Setup and Teardown
+/// Optionally, you may provide [hooks](crate::wrapper::UdfLifecycle) for one-time setup and teardown of every UDF,
+/// e.g. to compile a regex or load a lookup table. The host calls `init` once after all UDFs were created -- failing
+/// the UDF creation if it returns an error -- and `close` when it drops the UDF.
+///
+/// ```rust
+/// # use std::sync::Arc;
+/// #
+/// # use datafusion_common::error::DataFusionError;
+/// # use datafusion_expr::ScalarUDFImpl;
+/// #
+/// # use datafusion_udf_wasm_guest::{export, wrapper::UdfLifecycle};
+/// #
+/// # fn udfs(source: String) -> Result<Vec<Arc<dyn ScalarUDFImpl>>, DataFusionError> {
+/// #     todo!()
+/// # }
+/// #
+/// const LIFECYCLE: UdfLifecycle = UdfLifecycle {
+///     init: |_udf| {
+///         // set up state for the UDF
+///         Ok(())
+///     },
+///     close: |_udf| {
+///         // release state of the UDF
+///         Ok(())
+///     },
+/// };
+///
+/// export! {
+///     scalar_udfs: udfs,
+///     udf_lifecycle: LIFECYCLE,
+/// }
+/// ```
+///
+/// # Null-Strict UDFs
+/// Most UDFs return NULL if any argument is NULL. You may declare this per UDF, so that the host filters out NULL
+/// rows before calling the guest and the UDF only ever sees non-NULL values.
+///
+/// ```rust
+/// # use std::sync::Arc;
+/// #
+/// # use datafusion_common::error::DataFusionError;
+/// # use datafusion_expr::ScalarUDFImpl;
+/// #
+/// # use datafusion_udf_wasm_guest::export;
+/// #
+/// # fn udfs(source: String) -> Result<Vec<Arc<dyn ScalarUDFImpl>>, DataFusionError> {
+/// #     todo!()
+/// # }
+/// #
+/// fn null_strict(udf: &dyn ScalarUDFImpl) -> bool {
+///     udf.name() != "coalesce_like"
+/// }
+///
+/// export! {
+///     scalar_udfs: udfs,
+///     null_strict: null_strict,
+/// }
+/// ```
+///
+/// # Source Validation
+/// Hosts may check source code -- e.g. while a user types a query -- before creating UDFs from it. Report syntax
+/// errors without running the code, because the host reuses one instance to validate many sources.
+///
+/// ```rust
+/// # use std::sync::Arc;
+/// #
+/// # use datafusion_common::error::DataFusionError;
+/// # use datafusion_expr::ScalarUDFImpl;
+/// #
+/// # use datafusion_udf_wasm_guest::{Diagnostic, export};
+/// #
+/// # fn udfs(source: String) -> Result<Vec<Arc<dyn ScalarUDFImpl>>, DataFusionError> {
+/// #     todo!()
+/// # }
+/// #
+/// fn validate_source(source: &str) -> Vec<Diagnostic> {
+///     source
+///         .lines()
+///         .enumerate()
+///         .filter(|(_idx, line)| line.contains('\t'))
+///         .map(|(idx, _line)| Diagnostic {
+///             message: "tabs are not allowed".to_owned(),
+///             line: idx as u32 + 1,
+///             column: 0,
+///         })
+///         .collect()
+/// }
+///
+/// export! {
+///     scalar_udfs: udfs,
+///     validate_source: validate_source,
+/// }
+/// ```
+///
+/// All optional settings can be combined, see [`ExportOptions`].
+///
 ///
 /// [`ScalarUDFImpl`]: datafusion_expr::ScalarUDFImpl
 #[macro_export]
 macro_rules! export {
     {
-        scalar_udfs: $scalar_udfs:ident$(,)?
+        scalar_udfs: $scalar_udfs:ident
+        $(, $key:ident: $value:expr)*
+        $(,)?
+    } => {
+        $crate::export! {
+            @impl
+            scalar_udfs: $scalar_udfs,
+            options: $crate::ExportOptions {
+                $($key: $value,)*
+                ..$crate::ExportOptions::DEFAULT
+            },
+        }
+    };
+    {
+        @impl
+        scalar_udfs: $scalar_udfs:ident,
+        options: $options:expr,
     } => {
-
         #[derive(Debug)]
         struct Implementation;
 
+        impl Implementation {
+            // Callers may set all options, so the `..DEFAULT` update is needless. Since that depends on the call site,
+            // `#[expect]` would fail for all other callers and we have to use `#[allow]` instead.
+            #[allow(clippy::allow_attributes, clippy::needless_update)]
+            const OPTIONS: $crate::ExportOptions = $options;
+        }
+
         impl $crate::bindings::exports::datafusion_udf_wasm::udf::types::Guest for Implementation {
             type ConfigOptions = $crate::wrapper::ConfigOptionsWrapper;
             type Field = $crate::wrapper::FieldWrapper;
+            type Instance = Instance;
             type ScalarUdf = $crate::wrapper::ScalarUdfWrapper;
 
-            fn scalar_udfs(
+            fn root_fs_tar() -> Option<Vec<u8>> {
+                (Self::OPTIONS.root_fs_tar)()
+            }
+
+            fn validate_source(source: String) -> Vec<$crate::Diagnostic> {
+                (Self::OPTIONS.validate_source)(&source)
+            }
+
+            fn ipc_compressions() -> Vec<$crate::bindings::exports::datafusion_udf_wasm::udf::types::IpcCompression> {
+                $crate::wrapper::ipc_compressions()
+            }
+        }
+
+        /// Guest VM state that source code is loaded into.
+        #[derive(Debug)]
+        struct Instance;
+
+        impl $crate::bindings::exports::datafusion_udf_wasm::udf::types::GuestInstance for Instance {
+            fn new() -> Self {
+                Self
+            }
+
+            fn load_source(
+                &self,
                 source: String,
             ) -> Result<
                 Vec<$crate::bindings::exports::datafusion_udf_wasm::udf::types::ScalarUdf>,
@@ -64,9 +341,14 @@ macro_rules! export {
 
                 Ok(
                     udfs.into_iter()
-                    .map(|udf| $crate::bindings::exports::datafusion_udf_wasm::udf::types::ScalarUdf::new(
-                        $crate::wrapper::ScalarUdfWrapper::new(udf)
-                    ))
+                    .map(|udf| {
+                        let null_strict = (Implementation::OPTIONS.null_strict)(udf.as_ref());
+                        $crate::bindings::exports::datafusion_udf_wasm::udf::types::ScalarUdf::new(
+                            $crate::wrapper::ScalarUdfWrapper::new(udf)
+                                .with_lifecycle(Implementation::OPTIONS.udf_lifecycle)
+                                .with_null_strict(null_strict)
+                        )
+                    })
                     .collect()
                 )
             }
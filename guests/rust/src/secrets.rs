@@ -0,0 +1,11 @@
+//! Secrets provided by the host.
+//!
+//! The host redacts all secret values that were handed to the guest from stderr output and returned errors.
+use crate::bindings::datafusion_udf_wasm::udf::secrets;
+
+/// Get secret by name.
+///
+/// Returns [`None`] if the secret does not exist or the host did not grant access to it.
+pub fn get(name: &str) -> Option<String> {
+    secrets::get(name)
+}
@@ -0,0 +1,268 @@
+//! Scalar UDFs that are backed by plain functions, see [`wasm_udf`](crate::wasm_udf).
+use std::{
+    any::Any,
+    hash::{Hash, Hasher},
+    sync::Arc,
+};
+
+use arrow::{
+    array::{Array, ArrayRef, BinaryArray, BooleanArray, PrimitiveArray, StringArray},
+    datatypes::{
+        ArrowPrimitiveType, DataType, Float32Type, Float64Type, Int8Type, Int16Type, Int32Type,
+        Int64Type, UInt8Type, UInt16Type, UInt32Type, UInt64Type,
+    },
+};
+use datafusion_common::{
+    Result as DataFusionResult, ScalarValue, exec_datafusion_err, exec_err, plan_err,
+};
+use datafusion_expr::{ColumnarValue, ScalarFunctionArgs, Signature, Volatility};
+
+#[doc(hidden)]
+pub use datafusion_expr::ScalarUDFImpl;
+
+/// Rust type that can be used as an argument or return value of a [`wasm_udf`](crate::wasm_udf).
+pub trait SimpleValue: Sized {
+    /// Arrow array that holds values of this type.
+    type Array: Array + 'static;
+
+    /// Arrow data type.
+    fn data_type() -> DataType;
+
+    /// Read non-NULL value at the given row.
+    fn value(array: &Self::Array, row: usize) -> Self;
+
+    /// Build array from values, [`None`] being NULL.
+    fn to_array(values: Vec<Option<Self>>) -> ArrayRef;
+}
+
+/// Implement [`SimpleValue`] for primitive types.
+macro_rules! impl_primitive {
+    ($($native:ty => $arrow:ty),* $(,)?) => {
+        $(
+            impl SimpleValue for $native {
+                type Array = PrimitiveArray<$arrow>;
+
+                fn data_type() -> DataType {
+                    <$arrow as ArrowPrimitiveType>::DATA_TYPE
+                }
+
+                fn value(array: &Self::Array, row: usize) -> Self {
+                    array.value(row)
+                }
+
+                fn to_array(values: Vec<Option<Self>>) -> ArrayRef {
+                    Arc::new(Self::Array::from(values))
+                }
+            }
+        )*
+    };
+}
+
+impl_primitive!(
+    i8 => Int8Type,
+    i16 => Int16Type,
+    i32 => Int32Type,
+    i64 => Int64Type,
+    u8 => UInt8Type,
+    u16 => UInt16Type,
+    u32 => UInt32Type,
+    u64 => UInt64Type,
+    f32 => Float32Type,
+    f64 => Float64Type,
+);
+
+impl SimpleValue for bool {
+    type Array = BooleanArray;
+
+    fn data_type() -> DataType {
+        DataType::Boolean
+    }
+
+    fn value(array: &Self::Array, row: usize) -> Self {
+        array.value(row)
+    }
+
+    fn to_array(values: Vec<Option<Self>>) -> ArrayRef {
+        Arc::new(BooleanArray::from(values))
+    }
+}
+
+impl SimpleValue for String {
+    type Array = StringArray;
+
+    fn data_type() -> DataType {
+        DataType::Utf8
+    }
+
+    fn value(array: &Self::Array, row: usize) -> Self {
+        array.value(row).to_owned()
+    }
+
+    fn to_array(values: Vec<Option<Self>>) -> ArrayRef {
+        Arc::new(StringArray::from_iter(values))
+    }
+}
+
+impl SimpleValue for Vec<u8> {
+    type Array = BinaryArray;
+
+    fn data_type() -> DataType {
+        DataType::Binary
+    }
+
+    fn value(array: &Self::Array, row: usize) -> Self {
+        array.value(row).to_vec()
+    }
+
+    fn to_array(values: Vec<Option<Self>>) -> ArrayRef {
+        Arc::new(BinaryArray::from_iter(values))
+    }
+}
+
+/// Downcast argument array.
+#[doc(hidden)]
+pub fn downcast<T: SimpleValue>(array: &ArrayRef, idx: usize) -> DataFusionResult<&T::Array> {
+    array.as_any().downcast_ref::<T::Array>().ok_or_else(|| {
+        exec_datafusion_err!(
+            "argument {} should be {} but is {}",
+            idx + 1,
+            T::data_type(),
+            array.data_type()
+        )
+    })
+}
+
+/// Read value at the given row, [`None`] being NULL.
+#[doc(hidden)]
+pub fn get<T: SimpleValue>(array: &T::Array, row: usize) -> Option<T> {
+    array.is_valid(row).then(|| T::value(array, row))
+}
+
+/// Evaluates the function row by row.
+///
+/// Gets the argument arrays -- which match the signature -- and the number of rows.
+type Invoke = fn(&[ArrayRef], usize) -> DataFusionResult<ArrayRef>;
+
+/// [`ScalarUDFImpl`] generated by [`wasm_udf`](crate::wasm_udf).
+#[derive(Debug)]
+pub struct SimpleUdf {
+    /// Name of the UDF.
+    name: String,
+
+    /// Argument types.
+    arg_types: Vec<DataType>,
+
+    /// Signature of the UDF.
+    ///
+    /// We store this here because [`ScalarUDFImpl::signature`] requires us to return a reference.
+    signature: Signature,
+
+    /// Return type.
+    return_type: DataType,
+
+    /// Implementation.
+    invoke: Invoke,
+}
+
+impl SimpleUdf {
+    /// Create new UDF.
+    #[doc(hidden)]
+    pub fn new(
+        name: &str,
+        arg_types: Vec<DataType>,
+        return_type: DataType,
+        invoke: Invoke,
+    ) -> Self {
+        Self {
+            name: name.to_owned(),
+            signature: Signature::exact(arg_types.clone(), Volatility::Immutable),
+            arg_types,
+            return_type,
+            invoke,
+        }
+    }
+}
+
+// `invoke` is derived from the other fields, so we can ignore it
+impl PartialEq for SimpleUdf {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+            && self.arg_types == other.arg_types
+            && self.return_type == other.return_type
+    }
+}
+
+impl Eq for SimpleUdf {}
+
+impl Hash for SimpleUdf {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.name.hash(state);
+        self.arg_types.hash(state);
+        self.return_type.hash(state);
+    }
+}
+
+impl ScalarUDFImpl for SimpleUdf {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn return_type(&self, arg_types: &[DataType]) -> DataFusionResult<DataType> {
+        if arg_types != self.arg_types {
+            return plan_err!(
+                "{} expects arguments {:?} but got {:?}",
+                self.name,
+                self.arg_types,
+                arg_types
+            );
+        }
+        Ok(self.return_type.clone())
+    }
+
+    fn invoke_with_args(&self, args: ScalarFunctionArgs) -> DataFusionResult<ColumnarValue> {
+        let ScalarFunctionArgs {
+            args,
+            arg_fields: _,
+            number_rows,
+            return_field: _,
+            config_options: _,
+        } = args;
+
+        if args.len() != self.arg_types.len() {
+            return exec_err!(
+                "{} expects {} arguments but got {}",
+                self.name,
+                self.arg_types.len(),
+                args.len()
+            );
+        }
+
+        let all_scalars = !args.is_empty()
+            && args
+                .iter()
+                .all(|arg| matches!(arg, ColumnarValue::Scalar(_)));
+        let number_rows = if all_scalars { 1 } else { number_rows };
+
+        let arrays = args
+            .iter()
+            .map(|arg| arg.to_array(number_rows))
+            .collect::<DataFusionResult<Vec<_>>>()?;
+        let array = (self.invoke)(&arrays, number_rows)?;
+
+        if all_scalars {
+            Ok(ColumnarValue::Scalar(ScalarValue::try_from_array(
+                &array, 0,
+            )?))
+        } else {
+            Ok(ColumnarValue::Array(array))
+        }
+    }
+}
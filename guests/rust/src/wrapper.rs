@@ -5,9 +5,28 @@
 use std::sync::Arc;
 
 use crate::bindings::exports::datafusion_udf_wasm::udf::types as wit_types;
-use arrow::datatypes::{DataType, Field};
-use datafusion_common::config::ConfigOptions;
-use datafusion_expr::ScalarUDFImpl;
+use arrow::{
+    compute::SortOptions,
+    datatypes::{DataType, Field},
+};
+use datafusion_common::{DataFusionError, config::ConfigOptions};
+use datafusion_expr::{
+    ScalarUDFImpl, TypeSignature,
+    sort_properties::{ExprProperties, SortProperties},
+};
+use datafusion_udf_wasm_arrow2bytes::Compression;
+
+/// Maximum number of entries in the [return type table](wit_types::GuestScalarUdf::return_type_table).
+const MAX_RETURN_TYPE_TABLE_ENTRIES: usize = 64;
+
+/// IPC compression codecs supported by this SDK, see [`Guest::ipc_compressions`](wit_types::Guest::ipc_compressions).
+pub fn ipc_compressions() -> Vec<wit_types::IpcCompression> {
+    Compression::supported()
+        .iter()
+        .copied()
+        .map(From::from)
+        .collect()
+}
 
 /// Wraps [`Field`] so that it implements the [WIT definition]
 ///
@@ -67,27 +86,79 @@ impl wit_types::GuestConfigOptions for ConfigOptionsWrapper {
     }
 }
 
+/// One-time setup and teardown hooks of a [`ScalarUDFImpl`].
+///
+/// See [`export!`](crate::export).
+#[derive(Debug, Clone, Copy)]
+pub struct UdfLifecycle {
+    /// Called once after all UDFs were created and before the UDF is invoked.
+    pub init: fn(&dyn ScalarUDFImpl) -> Result<(), DataFusionError>,
+
+    /// Called at most once when the host drops the UDF.
+    pub close: fn(&dyn ScalarUDFImpl) -> Result<(), DataFusionError>,
+}
+
+impl UdfLifecycle {
+    /// Hooks that do nothing.
+    pub const NOOP: Self = Self {
+        init: |_udf| Ok(()),
+        close: |_udf| Ok(()),
+    };
+}
+
+impl Default for UdfLifecycle {
+    fn default() -> Self {
+        Self::NOOP
+    }
+}
+
 /// Wraps a [`ScalarUDFImpl`] so that it implements the [WIT definition].
 ///
 ///
 /// [WIT definition]: wit_types::GuestScalarUdf
 #[derive(Debug)]
-pub struct ScalarUdfWrapper(Arc<dyn ScalarUDFImpl>);
+pub struct ScalarUdfWrapper {
+    /// Wrapped UDF.
+    udf: Arc<dyn ScalarUDFImpl>,
+
+    /// Setup and teardown hooks.
+    lifecycle: UdfLifecycle,
+
+    /// The UDF returns NULL if any argument is NULL.
+    null_strict: bool,
+}
 
 impl ScalarUdfWrapper {
     /// Create new wrapper from [`ScalarUDFImpl`].
     pub fn new(udf: Arc<dyn ScalarUDFImpl>) -> Self {
-        Self(udf)
+        Self {
+            udf,
+            lifecycle: UdfLifecycle::NOOP,
+            null_strict: false,
+        }
+    }
+
+    /// Set setup and teardown hooks.
+    pub fn with_lifecycle(self, lifecycle: UdfLifecycle) -> Self {
+        Self { lifecycle, ..self }
+    }
+
+    /// Declare that the UDF returns NULL if any argument is NULL.
+    pub fn with_null_strict(self, null_strict: bool) -> Self {
+        Self {
+            null_strict,
+            ..self
+        }
     }
 }
 
 impl wit_types::GuestScalarUdf for ScalarUdfWrapper {
     fn name(&self) -> String {
-        self.0.name().to_owned()
+        self.udf.name().to_owned()
     }
 
     fn signature(&self) -> wit_types::Signature {
-        self.0
+        self.udf
             .signature()
             .clone()
             .try_into()
@@ -102,17 +173,162 @@ impl wit_types::GuestScalarUdf for ScalarUdfWrapper {
             .into_iter()
             .map(DataType::try_from)
             .collect::<Result<Vec<_>, _>>()?;
-        let data_type = self.0.return_type(&arg_types)?;
+        let data_type = self.udf.return_type(&arg_types)?;
         Ok(data_type.into())
     }
 
+    fn return_type_table(
+        &self,
+    ) -> Result<Vec<wit_types::ReturnTypeEntry>, wit_types::DataFusionError> {
+        let mut table = vec![];
+        for (arg_types, strict) in signature_arg_types(&self.udf.signature().type_signature) {
+            if table.len() >= MAX_RETURN_TYPE_TABLE_ENTRIES {
+                break;
+            }
+            let return_type = match self.udf.return_type(&arg_types) {
+                Ok(return_type) => return_type,
+                Err(_) if !strict => continue,
+                Err(e) => return Err(e.into()),
+            };
+            table.push(wit_types::ReturnTypeEntry {
+                arg_types: arg_types.into_iter().map(From::from).collect(),
+                return_type: return_type.into(),
+            });
+        }
+        Ok(table)
+    }
+
     fn invoke_with_args(
         &self,
         args: wit_types::ScalarFunctionArgs<'_>,
     ) -> Result<wit_types::ColumnarValue, wit_types::DataFusionError> {
+        let result_checksum = args.result_checksum;
+        let compression = args.ipc_compression.map(Compression::from);
         let args = args.try_into()?;
-        let cval = self.0.invoke_with_args(args)?;
-        let cval = cval.try_into()?;
+        let cval = self.udf.invoke_with_args(args)?;
+        let cval = match compression {
+            Some(compression) => wit_types::ColumnarValue::compressed(cval, compression)?,
+            None => cval.try_into()?,
+        };
+        let cval = if result_checksum {
+            cval.with_checksum()
+        } else {
+            cval
+        };
         Ok(cval)
     }
+
+    fn coerce_types(
+        &self,
+        arg_types: Vec<wit_types::DataType>,
+    ) -> Result<Vec<wit_types::DataType>, wit_types::DataFusionError> {
+        let arg_types = arg_types
+            .into_iter()
+            .map(DataType::try_from)
+            .collect::<Result<Vec<_>, _>>()?;
+        let coerced = self.udf.coerce_types(&arg_types)?;
+        Ok(coerced.into_iter().map(Into::into).collect())
+    }
+
+    fn null_strict(&self) -> bool {
+        self.null_strict
+    }
+
+    fn planner_hints(&self) -> wit_types::PlannerHints {
+        // Probe the UDF with ascending arguments. This needs a fixed number of arguments, since UDFs may index into
+        // the inputs.
+        let mut arities = signature_arg_types(&self.udf.signature().type_signature)
+            .into_iter()
+            .map(|(arg_types, _strict)| arg_types.len());
+        let arity = arities
+            .next()
+            .filter(|arity| arities.all(|other| other == *arity));
+
+        let (output_ordering, preserves_lex_ordering) = match arity {
+            Some(arity) => {
+                let inputs = vec![
+                    ExprProperties::new_unknown()
+                        .with_order(SortProperties::Ordered(SortOptions::default()));
+                    arity
+                ];
+                let output_ordering = match self.udf.output_ordering(&inputs) {
+                    Ok(SortProperties::Ordered(SortOptions {
+                        descending: false, ..
+                    })) => wit_types::OutputOrdering::Ascending,
+                    Ok(SortProperties::Ordered(SortOptions {
+                        descending: true, ..
+                    })) => wit_types::OutputOrdering::Descending,
+                    Ok(SortProperties::Unordered | SortProperties::Singleton) | Err(_) => {
+                        wit_types::OutputOrdering::Unordered
+                    }
+                };
+                let preserves_lex_ordering =
+                    self.udf.preserves_lex_ordering(&inputs).unwrap_or(false);
+                (output_ordering, preserves_lex_ordering)
+            }
+            None => (wit_types::OutputOrdering::Unordered, false),
+        };
+
+        wit_types::PlannerHints {
+            short_circuits: self.udf.short_circuits(),
+            output_ordering,
+            preserves_lex_ordering,
+        }
+    }
+
+    fn init(&self) -> Result<(), wit_types::DataFusionError> {
+        (self.lifecycle.init)(self.udf.as_ref())?;
+        Ok(())
+    }
+
+    fn close(&self) -> Result<(), wit_types::DataFusionError> {
+        (self.lifecycle.close)(self.udf.as_ref())?;
+        Ok(())
+    }
+}
+
+/// Argument types that are explicitly listed by the [`TypeSignature`].
+///
+/// The flag tells if the UDF must accept the argument types. This is NOT the case for combinations of
+/// [uniform](TypeSignature::Uniform) types, since the UDF may reject some of them.
+fn signature_arg_types(type_signature: &TypeSignature) -> Vec<(Vec<DataType>, bool)> {
+    match type_signature {
+        TypeSignature::Exact(t) => vec![(t.clone(), true)],
+        TypeSignature::Nullary => vec![(vec![], true)],
+        TypeSignature::OneOf(signatures) => {
+            signatures.iter().flat_map(signature_arg_types).collect()
+        }
+        TypeSignature::Uniform(n, t) => {
+            // zero arguments or no types is not a valid uniform signature
+            if *n == 0 || t.is_empty() {
+                return vec![];
+            }
+
+            let too_many = u32::try_from(*n)
+                .ok()
+                .and_then(|n| t.len().checked_pow(n))
+                .is_none_or(|count| count > MAX_RETURN_TYPE_TABLE_ENTRIES);
+            if too_many {
+                return vec![];
+            }
+
+            (0..*n)
+                .fold(vec![vec![]], |combinations: Vec<Vec<DataType>>, _| {
+                    combinations
+                        .into_iter()
+                        .flat_map(|prefix| {
+                            t.iter().map(move |dt| {
+                                let mut combination = prefix.clone();
+                                combination.push(dt.clone());
+                                combination
+                            })
+                        })
+                        .collect()
+                })
+                .into_iter()
+                .map(|arg_types| (arg_types, false))
+                .collect()
+        }
+        _ => vec![],
+    }
 }
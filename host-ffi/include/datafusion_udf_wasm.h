@@ -0,0 +1,128 @@
+/*
+ * C API for WebAssembly-based DataFusion UDFs.
+ *
+ * See the `datafusion-udf-wasm-host-ffi` crate for the full documentation. In short:
+ *
+ * - All objects are opaque. Every constructor has a matching `*_free` function, which accepts `NULL`.
+ * - Constructors return `NULL` on failure, other fallible functions return a `DfWasmStatus`. Use `dfwasm_last_error`
+ *   to get the error message.
+ * - Arrow data is exchanged via the Arrow C Data Interface, see
+ *   <https://arrow.apache.org/docs/format/CDataInterface.html>.
+ * - UDFs MUST be freed before the runtime that was used to create them.
+ */
+
+#ifndef DATAFUSION_UDF_WASM_H
+#define DATAFUSION_UDF_WASM_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#ifndef ARROW_C_DATA_INTERFACE
+#define ARROW_C_DATA_INTERFACE
+
+#define ARROW_FLAG_DICTIONARY_ORDERED 1
+#define ARROW_FLAG_NULLABLE 2
+#define ARROW_FLAG_MAP_KEYS_SORTED 4
+
+struct ArrowSchema {
+  const char* format;
+  const char* name;
+  const char* metadata;
+  int64_t flags;
+  int64_t n_children;
+  struct ArrowSchema** children;
+  struct ArrowSchema* dictionary;
+  void (*release)(struct ArrowSchema*);
+  void* private_data;
+};
+
+struct ArrowArray {
+  int64_t length;
+  int64_t null_count;
+  int64_t offset;
+  int64_t n_buffers;
+  int64_t n_children;
+  const void** buffers;
+  struct ArrowArray** children;
+  struct ArrowArray* dictionary;
+  void (*release)(struct ArrowArray*);
+  void* private_data;
+};
+
+#endif  /* ARROW_C_DATA_INTERFACE */
+
+typedef enum DfWasmStatus {
+  DFWASM_STATUS_OK = 0,
+  DFWASM_STATUS_ERROR = 1,
+  /* objects that were passed to the call may be inconsistent and should be freed */
+  DFWASM_STATUS_PANIC = 2,
+} DfWasmStatus;
+
+typedef struct DfWasmRuntime DfWasmRuntime;
+typedef struct DfWasmPermissions DfWasmPermissions;
+typedef struct DfWasmComponent DfWasmComponent;
+typedef struct DfWasmUdfs DfWasmUdfs;
+
+/* Error message of the last failed call on this thread, or `NULL`. Valid until the next call on the same thread. */
+const char* dfwasm_last_error(void);
+
+/* Runtime. `memory_limit` is in bytes, zero means unlimited. */
+DfWasmRuntime* dfwasm_runtime_new(size_t memory_limit);
+void dfwasm_runtime_free(DfWasmRuntime* rt);
+
+/* Permissions. */
+DfWasmPermissions* dfwasm_permissions_new(void);
+DfWasmStatus dfwasm_permissions_set_max_udfs(DfWasmPermissions* permissions, size_t limit);
+DfWasmStatus dfwasm_permissions_set_stderr_bytes(DfWasmPermissions* permissions, size_t limit);
+DfWasmStatus dfwasm_permissions_set_env(DfWasmPermissions* permissions, const char* key, const char* value);
+void dfwasm_permissions_free(DfWasmPermissions* permissions);
+
+/* Components. `dfwasm_component_compile` is only available if the library was built with the `compiler` feature.
+ *
+ * `dfwasm_component_load` MUST only be used with trusted data, e.g. data produced by `dfwasm_component_store`. */
+DfWasmComponent* dfwasm_component_compile(const DfWasmRuntime* rt, const uint8_t* wasm, size_t wasm_len);
+DfWasmComponent* dfwasm_component_load(const uint8_t* data, size_t data_len);
+DfWasmStatus dfwasm_component_store(const DfWasmComponent* component, const uint8_t** data, size_t* data_len);
+void dfwasm_component_free(DfWasmComponent* component);
+
+/* UDFs created from source code. */
+DfWasmUdfs* dfwasm_udfs_new(
+    const DfWasmRuntime* rt,
+    const DfWasmComponent* component,
+    const DfWasmPermissions* permissions,
+    const char* source);
+size_t dfwasm_udfs_len(const DfWasmUdfs* udfs);
+const char* dfwasm_udfs_name(const DfWasmUdfs* udfs, size_t idx);
+
+/* `arg_types` is borrowed, `out` has to be released by the caller. */
+DfWasmStatus dfwasm_udfs_return_type(
+    const DfWasmRuntime* rt,
+    const DfWasmUdfs* udfs,
+    size_t idx,
+    const struct ArrowSchema* arg_types,
+    size_t n_args,
+    struct ArrowSchema* out);
+
+/* `args` are moved into the call (even on failure), `arg_schemas` are borrowed, `out_array` and `out_schema` have to be
+ * released by the caller. */
+DfWasmStatus dfwasm_udfs_invoke(
+    const DfWasmRuntime* rt,
+    const DfWasmUdfs* udfs,
+    size_t idx,
+    struct ArrowArray* args,
+    const struct ArrowSchema* arg_schemas,
+    size_t n_args,
+    size_t number_rows,
+    struct ArrowArray* out_array,
+    struct ArrowSchema* out_schema);
+void dfwasm_udfs_free(DfWasmUdfs* udfs);
+
+#ifdef __cplusplus
+}  /* extern "C" */
+#endif
+
+#endif  /* DATAFUSION_UDF_WASM_H */
@@ -0,0 +1,110 @@
+//! Pre-compiled WASM components.
+
+use std::sync::Arc;
+
+#[cfg(feature = "compiler")]
+use datafusion_udf_wasm_host::CompilationFlags;
+use datafusion_udf_wasm_host::WasmComponentPrecompiled;
+
+#[cfg(feature = "compiler")]
+use crate::runtime::DfWasmRuntime;
+use crate::{
+    error::{DfWasmStatus, guard, guard_new},
+    ptr::{deref, out_arg, slice_arg},
+};
+
+/// Wrapper around [`WasmComponentPrecompiled`].
+#[derive(Debug)]
+pub struct DfWasmComponent(pub(crate) Arc<WasmComponentPrecompiled>);
+
+/// Pre-compile WASM payload in binary format.
+///
+/// Returns `NULL` on failure.
+///
+/// # Safety
+/// - `rt` must be `NULL` or a valid pointer returned by [`dfwasm_runtime_new`](crate::dfwasm_runtime_new).
+/// - `wasm` must be `NULL` or point to `wasm_len` bytes.
+#[cfg(feature = "compiler")]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn dfwasm_component_compile(
+    rt: *const DfWasmRuntime,
+    wasm: *const u8,
+    wasm_len: usize,
+) -> *mut DfWasmComponent {
+    guard_new(|| {
+        // SAFETY: see function contract
+        let rt = unsafe { deref(rt, "rt") }?;
+        // SAFETY: see function contract
+        let wasm = unsafe { slice_arg(wasm, wasm_len, "wasm") }?;
+
+        let component = rt.rt.block_on(WasmComponentPrecompiled::compile(
+            wasm.into(),
+            &CompilationFlags::default(),
+        ))?;
+        Ok(DfWasmComponent(Arc::new(component)))
+    })
+}
+
+/// Load data that was produced by [`dfwasm_component_store`].
+///
+/// Returns `NULL` on failure.
+///
+/// # Safety
+/// - `data` must be `NULL` or point to `data_len` bytes.
+/// - The data MUST be trusted, see [`WasmComponentPrecompiled::load`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn dfwasm_component_load(
+    data: *const u8,
+    data_len: usize,
+) -> *mut DfWasmComponent {
+    guard_new(|| {
+        // SAFETY: see function contract
+        let data = unsafe { slice_arg(data, data_len, "data") }?;
+        // SAFETY: the caller promised that the data is trusted
+        let component = unsafe { WasmComponentPrecompiled::load(data.to_vec()) }?;
+        Ok(DfWasmComponent(Arc::new(component)))
+    })
+}
+
+/// Get pre-compiled component data, see [`WasmComponentPrecompiled::store`].
+///
+/// The data is owned by the component and stays valid until the component is freed.
+///
+/// # Safety
+/// - `component` must be `NULL` or a valid component pointer.
+/// - `data` and `data_len` must be `NULL` or valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn dfwasm_component_store(
+    component: *const DfWasmComponent,
+    data: *mut *const u8,
+    data_len: *mut usize,
+) -> DfWasmStatus {
+    guard(|| {
+        // SAFETY: see function contract
+        let component = unsafe { deref(component, "component") }?;
+        let data = out_arg(data, "data")?;
+        let data_len = out_arg(data_len, "data_len")?;
+
+        let stored = component.0.store();
+        // SAFETY: the caller promised that the pointers are valid for writes
+        unsafe {
+            data.write(stored.as_ptr());
+            data_len.write(stored.len());
+        }
+        Ok(())
+    })
+}
+
+/// Free component.
+///
+/// UDFs that were created from this component stay valid.
+///
+/// # Safety
+/// `component` must be `NULL` or a component pointer that was not freed yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn dfwasm_component_free(component: *mut DfWasmComponent) {
+    if !component.is_null() {
+        // SAFETY: see function contract
+        drop(unsafe { Box::from_raw(component) });
+    }
+}
@@ -0,0 +1,95 @@
+//! Error reporting across the FFI boundary.
+
+use std::{
+    any::Any,
+    cell::RefCell,
+    ffi::{CString, c_char},
+    panic::{AssertUnwindSafe, catch_unwind},
+    ptr,
+};
+
+use datafusion_common::Result as DataFusionResult;
+
+thread_local! {
+    /// Error of the last failed call on this thread.
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Outcome of a fallible call.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DfWasmStatus {
+    /// Call succeeded.
+    Ok = 0,
+
+    /// Call failed, see [`dfwasm_last_error`].
+    Error = 1,
+
+    /// Call panicked, see [`dfwasm_last_error`].
+    ///
+    /// The objects that were passed to the call may be in an inconsistent state and should be freed.
+    Panic = 2,
+}
+
+/// Get error message of the last failed call on the current thread.
+///
+/// Returns `NULL` if the last call succeeded. The string is owned by the library and stays valid until the next call on
+/// the same thread.
+#[unsafe(no_mangle)]
+pub extern "C" fn dfwasm_last_error() -> *const c_char {
+    LAST_ERROR.with_borrow(|e| e.as_ref().map(|e| e.as_ptr()).unwrap_or(ptr::null()))
+}
+
+/// Store error message for [`dfwasm_last_error`].
+fn set_last_error(msg: String) {
+    // C strings cannot contain NUL bytes
+    let msg = CString::new(msg.replace('\0', "\\0")).expect("NUL bytes were escaped");
+    LAST_ERROR.set(Some(msg));
+}
+
+/// Extract message from panic payload.
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    let msg = payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(|s| s.as_str()))
+        .unwrap_or("unknown panic");
+    format!("panic: {msg}")
+}
+
+/// Run a call and report its outcome as [`DfWasmStatus`].
+pub(crate) fn guard<F>(f: F) -> DfWasmStatus
+where
+    F: FnOnce() -> DataFusionResult<()>,
+{
+    LAST_ERROR.set(None);
+
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(())) => DfWasmStatus::Ok,
+        Ok(Err(e)) => {
+            set_last_error(e.to_string());
+            DfWasmStatus::Error
+        }
+        Err(payload) => {
+            set_last_error(panic_message(payload.as_ref()));
+            DfWasmStatus::Panic
+        }
+    }
+}
+
+/// Run a call that creates an object and hand the object to the caller.
+///
+/// Returns `NULL` on failure.
+pub(crate) fn guard_new<T, F>(f: F) -> *mut T
+where
+    F: FnOnce() -> DataFusionResult<T>,
+{
+    let mut out = None;
+    match guard(|| {
+        out = Some(f()?);
+        Ok(())
+    }) {
+        DfWasmStatus::Ok => Box::into_raw(Box::new(out.expect("set on success"))),
+        DfWasmStatus::Error | DfWasmStatus::Panic => ptr::null_mut(),
+    }
+}
@@ -0,0 +1,56 @@
+//! C API for [`datafusion_udf_wasm_host`].
+//!
+//! This allows engines that are NOT written in Rust to use WebAssembly-based [DataFusion] UDFs. See
+//! `include/datafusion_udf_wasm.h` for the C declarations.
+//!
+//! # Objects
+//! All objects are opaque and heap-allocated. Every `dfwasm_*_new`/`dfwasm_*_compile`/`dfwasm_*_load` function has a
+//! matching `dfwasm_*_free` function. Passing `NULL` to a `free` function is a no-op.
+//!
+//! # Errors
+//! Functions that create objects return `NULL` on failure, all other fallible functions return a [`DfWasmStatus`]. The
+//! error message can be retrieved via [`dfwasm_last_error`]. Panics are caught at the boundary and reported the same
+//! way, they never unwind into the caller.
+//!
+//! # Data
+//! Arrow data is exchanged via the [Arrow C Data Interface].
+//!
+//! # Threading
+//! All objects can be used from multiple threads. Calls block the calling thread, so they MUST NOT be issued from
+//! within the [`tokio`] runtime of a [`DfWasmRuntime`].
+//!
+//!
+//! [Arrow C Data Interface]: https://arrow.apache.org/docs/format/CDataInterface.html
+//! [DataFusion]: https://datafusion.apache.org/
+
+// unused-crate-dependencies false positives
+#[cfg(test)]
+use datafusion_udf_wasm_bundle as _;
+
+pub use crate::{
+    component::DfWasmComponent, error::DfWasmStatus, permissions::DfWasmPermissions,
+    runtime::DfWasmRuntime, udf::DfWasmUdfs,
+};
+
+#[cfg(feature = "compiler")]
+pub use crate::component::dfwasm_component_compile;
+pub use crate::{
+    component::{dfwasm_component_free, dfwasm_component_load, dfwasm_component_store},
+    error::dfwasm_last_error,
+    permissions::{
+        dfwasm_permissions_free, dfwasm_permissions_new, dfwasm_permissions_set_env,
+        dfwasm_permissions_set_max_udfs, dfwasm_permissions_set_stderr_bytes,
+    },
+    runtime::{dfwasm_runtime_free, dfwasm_runtime_new},
+    udf::{
+        dfwasm_udfs_free, dfwasm_udfs_invoke, dfwasm_udfs_len, dfwasm_udfs_name, dfwasm_udfs_new,
+        dfwasm_udfs_return_type,
+    },
+};
+
+mod component;
+mod error;
+mod permissions;
+mod ptr;
+mod runtime;
+mod udf;
@@ -0,0 +1,98 @@
+//! Permissions for the WASM guests.
+
+use std::ffi::c_char;
+
+use datafusion_udf_wasm_host::WasmPermissions;
+
+use crate::{
+    error::{DfWasmStatus, guard},
+    ptr::{deref_mut, str_arg},
+};
+
+/// Wrapper around [`WasmPermissions`].
+#[derive(Debug, Default)]
+pub struct DfWasmPermissions(pub(crate) WasmPermissions);
+
+impl DfWasmPermissions {
+    /// Apply [`WasmPermissions`] builder method.
+    fn update(&mut self, f: impl FnOnce(WasmPermissions) -> WasmPermissions) {
+        self.0 = f(std::mem::take(&mut self.0));
+    }
+}
+
+/// Create default permissions.
+#[unsafe(no_mangle)]
+pub extern "C" fn dfwasm_permissions_new() -> *mut DfWasmPermissions {
+    Box::into_raw(Box::default())
+}
+
+/// Set maximum number of UDFs that a single source may define.
+///
+/// # Safety
+/// `permissions` must be `NULL` or a valid pointer returned by [`dfwasm_permissions_new`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn dfwasm_permissions_set_max_udfs(
+    permissions: *mut DfWasmPermissions,
+    limit: usize,
+) -> DfWasmStatus {
+    guard(|| {
+        // SAFETY: see function contract
+        let permissions = unsafe { deref_mut(permissions, "permissions") }?;
+        permissions.update(|p| p.with_max_udfs(limit));
+        Ok(())
+    })
+}
+
+/// Set maximum number of stderr bytes that are captured from the guest.
+///
+/// # Safety
+/// `permissions` must be `NULL` or a valid pointer returned by [`dfwasm_permissions_new`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn dfwasm_permissions_set_stderr_bytes(
+    permissions: *mut DfWasmPermissions,
+    limit: usize,
+) -> DfWasmStatus {
+    guard(|| {
+        // SAFETY: see function contract
+        let permissions = unsafe { deref_mut(permissions, "permissions") }?;
+        permissions.update(|p| p.with_stderr_bytes(limit));
+        Ok(())
+    })
+}
+
+/// Set environment variable that is visible to the guest.
+///
+/// # Safety
+/// - `permissions` must be `NULL` or a valid pointer returned by [`dfwasm_permissions_new`].
+/// - `key` and `value` must be `NULL` or NUL-terminated strings.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn dfwasm_permissions_set_env(
+    permissions: *mut DfWasmPermissions,
+    key: *const c_char,
+    value: *const c_char,
+) -> DfWasmStatus {
+    guard(|| {
+        // SAFETY: see function contract
+        let permissions = unsafe { deref_mut(permissions, "permissions") }?;
+        // SAFETY: see function contract
+        let key = unsafe { str_arg(key, "key") }?;
+        // SAFETY: see function contract
+        let value = unsafe { str_arg(value, "value") }?;
+        permissions.update(|p| p.with_env(key.to_owned(), value.to_owned()));
+        Ok(())
+    })
+}
+
+/// Free permissions.
+///
+/// UDFs that were created with these permissions stay valid.
+///
+/// # Safety
+/// `permissions` must be `NULL` or a pointer returned by [`dfwasm_permissions_new`] that was not freed yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn dfwasm_permissions_free(permissions: *mut DfWasmPermissions) {
+    if !permissions.is_null() {
+        // SAFETY: see function contract
+        drop(unsafe { Box::from_raw(permissions) });
+    }
+}
@@ -0,0 +1,77 @@
+//! Helpers to check raw pointers that we get from the caller.
+
+use std::ffi::{CStr, c_char};
+
+use datafusion_common::{DataFusionError, Result as DataFusionResult};
+
+/// Dereference pointer.
+///
+/// # Safety
+/// The pointer must either be `NULL` or point to a valid `T` that outlives `'a`.
+pub(crate) unsafe fn deref<'a, T>(ptr: *const T, what: &str) -> DataFusionResult<&'a T> {
+    // SAFETY: see function contract
+    unsafe { ptr.as_ref() }.ok_or_else(|| null_error(what))
+}
+
+/// Dereference mutable pointer.
+///
+/// # Safety
+/// The pointer must either be `NULL` or point to a valid `T` that outlives `'a` and is not aliased.
+pub(crate) unsafe fn deref_mut<'a, T>(ptr: *mut T, what: &str) -> DataFusionResult<&'a mut T> {
+    // SAFETY: see function contract
+    unsafe { ptr.as_mut() }.ok_or_else(|| null_error(what))
+}
+
+/// Check output pointer.
+///
+/// The target may be uninitialized, so we do NOT create a reference to it.
+pub(crate) fn out_arg<T>(ptr: *mut T, what: &str) -> DataFusionResult<*mut T> {
+    if ptr.is_null() {
+        Err(null_error(what))
+    } else {
+        Ok(ptr)
+    }
+}
+
+/// Read NUL-terminated UTF-8 string.
+///
+/// # Safety
+/// The pointer must either be `NULL` or point to a NUL-terminated string that outlives `'a`.
+pub(crate) unsafe fn str_arg<'a>(ptr: *const c_char, what: &str) -> DataFusionResult<&'a str> {
+    if ptr.is_null() {
+        return Err(null_error(what));
+    }
+
+    // SAFETY: see function contract
+    let s = unsafe { CStr::from_ptr(ptr) };
+    s.to_str().map_err(|e| {
+        DataFusionError::External(Box::new(e)).context(format!("`{what}` is not UTF-8"))
+    })
+}
+
+/// Get slice from pointer and length.
+///
+/// An empty slice may be passed as `NULL`.
+///
+/// # Safety
+/// The pointer must either be `NULL` or point to `len` valid `T`s that outlive `'a`.
+pub(crate) unsafe fn slice_arg<'a, T>(
+    ptr: *const T,
+    len: usize,
+    what: &str,
+) -> DataFusionResult<&'a [T]> {
+    if len == 0 {
+        return Ok(&[]);
+    }
+    if ptr.is_null() {
+        return Err(null_error(what));
+    }
+
+    // SAFETY: see function contract
+    Ok(unsafe { std::slice::from_raw_parts(ptr, len) })
+}
+
+/// Error for unexpected `NULL` pointers.
+fn null_error(what: &str) -> DataFusionError {
+    DataFusionError::Execution(format!("`{what}` is NULL"))
+}
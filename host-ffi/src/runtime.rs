@@ -0,0 +1,59 @@
+//! Runtime that drives the WASM VMs.
+
+use std::sync::Arc;
+
+use datafusion_common::DataFusionError;
+use datafusion_execution::memory_pool::{GreedyMemoryPool, MemoryPool, UnboundedMemoryPool};
+use tokio::runtime::Runtime;
+
+use crate::error::guard_new;
+
+/// [`tokio`] runtime and memory pool that are shared by all UDFs created with it.
+#[derive(Debug)]
+pub struct DfWasmRuntime {
+    /// Runtime for async calls and I/O.
+    pub(crate) rt: Runtime,
+
+    /// Memory pool that accounts for memory used by the UDFs.
+    pub(crate) memory_pool: Arc<dyn MemoryPool>,
+}
+
+/// Create runtime.
+///
+/// `memory_limit` is the total number of bytes that all UDFs of this runtime may use. Zero means unlimited.
+///
+/// Returns `NULL` on failure.
+#[unsafe(no_mangle)]
+pub extern "C" fn dfwasm_runtime_new(memory_limit: usize) -> *mut DfWasmRuntime {
+    guard_new(|| {
+        let rt = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .thread_name("dfwasm")
+            .build()
+            .map_err(|e| DataFusionError::External(Box::new(e)).context("create tokio runtime"))?;
+
+        let memory_pool: Arc<dyn MemoryPool> = if memory_limit == 0 {
+            Arc::new(UnboundedMemoryPool::default())
+        } else {
+            Arc::new(GreedyMemoryPool::new(memory_limit))
+        };
+
+        Ok(DfWasmRuntime { rt, memory_pool })
+    })
+}
+
+/// Free runtime.
+///
+/// All UDFs created with this runtime MUST be freed first.
+///
+/// # Safety
+/// `rt` must be `NULL` or a pointer returned by [`dfwasm_runtime_new`] that was not freed yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn dfwasm_runtime_free(rt: *mut DfWasmRuntime) {
+    if !rt.is_null() {
+        // SAFETY: see function contract
+        let rt = unsafe { Box::from_raw(rt) };
+        // don't block the caller on background tasks
+        rt.rt.shutdown_background();
+    }
+}
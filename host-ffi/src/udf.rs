@@ -0,0 +1,274 @@
+//! UDFs created from source code.
+
+use std::{
+    ffi::{CString, c_char},
+    ptr,
+    sync::Arc,
+};
+
+use arrow::{
+    array::make_array,
+    datatypes::{DataType, Field},
+    ffi::{FFI_ArrowArray, FFI_ArrowSchema, from_ffi, to_ffi},
+};
+use datafusion_common::{DataFusionError, Result as DataFusionResult, config::ConfigOptions};
+use datafusion_expr::{
+    ColumnarValue, ScalarFunctionArgs, ScalarUDFImpl, async_udf::AsyncScalarUDFImpl,
+};
+use datafusion_udf_wasm_host::WasmScalarUdf;
+
+use crate::{
+    component::DfWasmComponent,
+    error::{DfWasmStatus, guard, guard_new},
+    permissions::DfWasmPermissions,
+    ptr::{deref, out_arg, slice_arg, str_arg},
+    runtime::DfWasmRuntime,
+};
+
+/// UDFs that were created from a single source.
+///
+/// The UDFs share one WASM VM.
+#[derive(Debug)]
+pub struct DfWasmUdfs {
+    /// UDFs, in the order that the guest returned them.
+    udfs: Vec<WasmScalarUdf>,
+
+    /// UDF names as C strings, so we can hand out pointers to them.
+    names: Vec<CString>,
+}
+
+impl DfWasmUdfs {
+    /// Get UDF by index.
+    fn get(&self, idx: usize) -> DataFusionResult<&WasmScalarUdf> {
+        self.udfs.get(idx).ok_or_else(|| {
+            DataFusionError::Execution(format!(
+                "UDF index {idx} out of bounds, there are {} UDFs",
+                self.udfs.len()
+            ))
+        })
+    }
+}
+
+/// Create UDFs from source code, see [`WasmScalarUdf::new`].
+///
+/// Returns `NULL` on failure.
+///
+/// # Safety
+/// - `rt`, `component`, and `permissions` must be `NULL` or valid pointers.
+/// - `source` must be `NULL` or a NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn dfwasm_udfs_new(
+    rt: *const DfWasmRuntime,
+    component: *const DfWasmComponent,
+    permissions: *const DfWasmPermissions,
+    source: *const c_char,
+) -> *mut DfWasmUdfs {
+    guard_new(|| {
+        // SAFETY: see function contract
+        let rt = unsafe { deref(rt, "rt") }?;
+        // SAFETY: see function contract
+        let component = unsafe { deref(component, "component") }?;
+        // SAFETY: see function contract
+        let permissions = unsafe { deref(permissions, "permissions") }?;
+        // SAFETY: see function contract
+        let source = unsafe { str_arg(source, "source") }?;
+
+        let udfs = rt.rt.block_on(WasmScalarUdf::new(
+            &component.0,
+            &permissions.0,
+            rt.rt.handle().clone(),
+            &rt.memory_pool,
+            source.to_owned(),
+        ))?;
+        let names = udfs
+            .iter()
+            .map(|udf| {
+                CString::new(udf.name()).map_err(|e| {
+                    DataFusionError::External(Box::new(e))
+                        .context(format!("UDF name `{}`", udf.name()))
+                })
+            })
+            .collect::<DataFusionResult<_>>()?;
+
+        Ok(DfWasmUdfs { udfs, names })
+    })
+}
+
+/// Get number of UDFs.
+///
+/// Returns zero if `udfs` is `NULL`.
+///
+/// # Safety
+/// `udfs` must be `NULL` or a valid pointer returned by [`dfwasm_udfs_new`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn dfwasm_udfs_len(udfs: *const DfWasmUdfs) -> usize {
+    // SAFETY: see function contract
+    unsafe { udfs.as_ref() }.map_or(0, |udfs| udfs.udfs.len())
+}
+
+/// Get name of the UDF at the given index.
+///
+/// The string is owned by `udfs` and stays valid until they are freed. Returns `NULL` on failure.
+///
+/// # Safety
+/// `udfs` must be `NULL` or a valid pointer returned by [`dfwasm_udfs_new`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn dfwasm_udfs_name(udfs: *const DfWasmUdfs, idx: usize) -> *const c_char {
+    let mut name = ptr::null();
+    guard(|| {
+        // SAFETY: see function contract
+        let udfs = unsafe { deref(udfs, "udfs") }?;
+        udfs.get(idx)?;
+        name = udfs.names[idx].as_ptr();
+        Ok(())
+    });
+    name
+}
+
+/// Get return type of the UDF at the given index for the given argument types.
+///
+/// The argument types are borrowed. The return type is written to `out`, which the caller has to release.
+///
+/// # Safety
+/// - `rt` and `udfs` must be `NULL` or valid pointers.
+/// - `arg_types` must be `NULL` or point to `n_args` valid schemas.
+/// - `out` must be `NULL` or valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn dfwasm_udfs_return_type(
+    rt: *const DfWasmRuntime,
+    udfs: *const DfWasmUdfs,
+    idx: usize,
+    arg_types: *const FFI_ArrowSchema,
+    n_args: usize,
+    out: *mut FFI_ArrowSchema,
+) -> DfWasmStatus {
+    guard(|| {
+        // SAFETY: see function contract
+        let rt = unsafe { deref(rt, "rt") }?;
+        // SAFETY: see function contract
+        let udf = unsafe { deref(udfs, "udfs") }?.get(idx)?;
+        // SAFETY: see function contract
+        let arg_types = unsafe { slice_arg(arg_types, n_args, "arg_types") }?
+            .iter()
+            .map(DataType::try_from)
+            .collect::<Result<Vec<_>, _>>()?;
+        let out = out_arg(out, "out")?;
+
+        let return_type = rt.rt.block_on(async { udf.return_type(&arg_types) })?;
+        let schema = FFI_ArrowSchema::try_from(&return_type)?;
+
+        // SAFETY: the caller promised that `out` is valid for writes
+        unsafe { out.write(schema) };
+        Ok(())
+    })
+}
+
+/// Invoke the UDF at the given index.
+///
+/// `args` points to `n_args` arrays with `number_rows` rows each. The arrays are moved into the call -- i.e. the
+/// caller MUST NOT release them -- even if the call fails. `arg_schemas` describes the argument fields and is
+/// borrowed.
+///
+/// The result is written to `out_array` and `out_schema`, which the caller has to release.
+///
+/// # Safety
+/// - `rt` and `udfs` must be `NULL` or valid pointers.
+/// - `args` and `arg_schemas` must be `NULL` or point to `n_args` valid arrays and schemas respectively.
+/// - `out_array` and `out_schema` must be `NULL` or valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn dfwasm_udfs_invoke(
+    rt: *const DfWasmRuntime,
+    udfs: *const DfWasmUdfs,
+    idx: usize,
+    args: *mut FFI_ArrowArray,
+    arg_schemas: *const FFI_ArrowSchema,
+    n_args: usize,
+    number_rows: usize,
+    out_array: *mut FFI_ArrowArray,
+    out_schema: *mut FFI_ArrowSchema,
+) -> DfWasmStatus {
+    guard(|| {
+        // take ownership first, so that we release the arrays on all error paths
+        let args = if n_args == 0 || args.is_null() {
+            vec![]
+        } else {
+            (0..n_args)
+                // SAFETY: the caller promised that there are `n_args` valid arrays
+                .map(|i| unsafe { FFI_ArrowArray::from_raw(args.add(i)) })
+                .collect::<Vec<_>>()
+        };
+        if args.len() != n_args {
+            return Err(DataFusionError::Execution("`args` is NULL".to_owned()));
+        }
+
+        // SAFETY: see function contract
+        let rt = unsafe { deref(rt, "rt") }?;
+        // SAFETY: see function contract
+        let udf = unsafe { deref(udfs, "udfs") }?.get(idx)?;
+        // SAFETY: see function contract
+        let arg_schemas = unsafe { slice_arg(arg_schemas, n_args, "arg_schemas") }?;
+        let out_array = out_arg(out_array, "out_array")?;
+        let out_schema = out_arg(out_schema, "out_schema")?;
+
+        let arg_fields = arg_schemas
+            .iter()
+            .map(|schema| Field::try_from(schema).map(Arc::new))
+            .collect::<Result<Vec<_>, _>>()?;
+        let args = args
+            .into_iter()
+            .zip(arg_schemas)
+            .enumerate()
+            .map(|(i, (array, schema))| {
+                // SAFETY: the caller promised that array and schema are valid and match
+                let data = unsafe { from_ffi(array, schema) }
+                    .map_err(|e| DataFusionError::from(e).context(format!("argument {i}")))?;
+                if data.len() != number_rows {
+                    return Err(DataFusionError::Execution(format!(
+                        "argument {i} has {} rows but expected {number_rows}",
+                        data.len()
+                    )));
+                }
+                Ok(ColumnarValue::Array(make_array(data)))
+            })
+            .collect::<DataFusionResult<Vec<_>>>()?;
+
+        let arg_types = arg_fields
+            .iter()
+            .map(|f| f.data_type().clone())
+            .collect::<Vec<_>>();
+        let array = rt.rt.block_on(async {
+            let return_type = udf.return_type(&arg_types)?;
+            udf.invoke_async_with_args(ScalarFunctionArgs {
+                args,
+                arg_fields,
+                number_rows,
+                return_field: Arc::new(Field::new(udf.name(), return_type, true)),
+                config_options: Arc::new(ConfigOptions::default()),
+            })
+            .await?
+            .into_array(number_rows)
+        })?;
+
+        let (array, schema) = to_ffi(&array.to_data())?;
+        // SAFETY: the caller promised that the pointers are valid for writes
+        unsafe {
+            out_array.write(array);
+            out_schema.write(schema);
+        }
+        Ok(())
+    })
+}
+
+/// Free UDFs.
+///
+/// This MUST happen before the runtime that was used to create them is freed.
+///
+/// # Safety
+/// `udfs` must be `NULL` or a pointer returned by [`dfwasm_udfs_new`] that was not freed yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn dfwasm_udfs_free(udfs: *mut DfWasmUdfs) {
+    if !udfs.is_null() {
+        // SAFETY: see function contract
+        drop(unsafe { Box::from_raw(udfs) });
+    }
+}
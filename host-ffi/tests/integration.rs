@@ -0,0 +1,155 @@
+#![expect(
+    // Docs are not strictly required for tests.
+    missing_docs,
+    // unused-crate-dependencies false positives
+    unused_crate_dependencies,
+)]
+
+use std::{
+    ffi::{CStr, CString},
+    mem::MaybeUninit,
+    ptr,
+};
+
+use arrow::{
+    array::{Array, Int64Array, make_array},
+    datatypes::{DataType, Field},
+    ffi::{FFI_ArrowArray, FFI_ArrowSchema, from_ffi, to_ffi},
+};
+use datafusion_udf_wasm_host_ffi::*;
+
+/// Get error message of the last call.
+fn last_error() -> String {
+    let err = dfwasm_last_error();
+    assert!(!err.is_null());
+    // SAFETY: non-NULL error strings are valid until the next call
+    unsafe { CStr::from_ptr(err) }.to_str().unwrap().to_owned()
+}
+
+#[test]
+fn test_lifecycle() {
+    const CODE: &str = "
+def add_one(x: int) -> int:
+    return x + 1
+";
+
+    let rt = dfwasm_runtime_new(0);
+    assert!(!rt.is_null());
+
+    let wasm = datafusion_udf_wasm_bundle::BIN_PYTHON;
+    // SAFETY: valid runtime and payload
+    let component = unsafe { dfwasm_component_compile(rt, wasm.as_ptr(), wasm.len()) };
+    assert!(!component.is_null());
+
+    let permissions = dfwasm_permissions_new();
+    let source = CString::new(CODE).unwrap();
+    // SAFETY: valid pointers
+    let udfs = unsafe { dfwasm_udfs_new(rt, component, permissions, source.as_ptr()) };
+    assert!(!udfs.is_null(), "{}", last_error());
+
+    // SAFETY: valid pointers
+    unsafe {
+        dfwasm_permissions_free(permissions);
+        dfwasm_component_free(component);
+    }
+
+    // SAFETY: valid pointer
+    assert_eq!(unsafe { dfwasm_udfs_len(udfs) }, 1);
+    // SAFETY: valid pointer, string lives as long as `udfs`
+    let name = unsafe { CStr::from_ptr(dfwasm_udfs_name(udfs, 0)) };
+    assert_eq!(name.to_str().unwrap(), "add_one");
+
+    // return type
+    let arg_type = FFI_ArrowSchema::try_from(&DataType::Int64).unwrap();
+    let mut out = MaybeUninit::<FFI_ArrowSchema>::uninit();
+    // SAFETY: valid pointers
+    let status = unsafe { dfwasm_udfs_return_type(rt, udfs, 0, &arg_type, 1, out.as_mut_ptr()) };
+    assert_eq!(status, DfWasmStatus::Ok, "{}", last_error());
+    // SAFETY: written on success
+    let out = unsafe { out.assume_init() };
+    assert_eq!(DataType::try_from(&out).unwrap(), DataType::Int64);
+
+    // invoke
+    let input = Int64Array::from_iter([Some(1), None, Some(3)]);
+    let (mut array, _) = to_ffi(&input.to_data()).unwrap();
+    let schema = FFI_ArrowSchema::try_from(Field::new("x", DataType::Int64, true)).unwrap();
+    let mut out_array = MaybeUninit::<FFI_ArrowArray>::uninit();
+    let mut out_schema = MaybeUninit::<FFI_ArrowSchema>::uninit();
+    // SAFETY: valid pointers, `array` is moved into the call
+    let status = unsafe {
+        dfwasm_udfs_invoke(
+            rt,
+            udfs,
+            0,
+            &mut array,
+            &schema,
+            1,
+            3,
+            out_array.as_mut_ptr(),
+            out_schema.as_mut_ptr(),
+        )
+    };
+    assert_eq!(status, DfWasmStatus::Ok, "{}", last_error());
+    // SAFETY: written on success
+    let (out_array, out_schema) = unsafe { (out_array.assume_init(), out_schema.assume_init()) };
+    // SAFETY: produced by the library
+    let output = make_array(unsafe { from_ffi(out_array, &out_schema) }.unwrap());
+    assert_eq!(
+        output.as_ref(),
+        &Int64Array::from_iter([Some(2), None, Some(4)]) as &dyn Array,
+    );
+
+    // SAFETY: valid pointers, UDFs are freed before the runtime
+    unsafe {
+        dfwasm_udfs_free(udfs);
+        dfwasm_runtime_free(rt);
+    }
+}
+
+#[test]
+fn test_errors() {
+    // SAFETY: NULL is handled
+    let udfs = unsafe { dfwasm_udfs_new(ptr::null(), ptr::null(), ptr::null(), ptr::null()) };
+    assert!(udfs.is_null());
+    assert_eq!(last_error(), "Execution error: `rt` is NULL");
+
+    // SAFETY: NULL is handled
+    assert_eq!(unsafe { dfwasm_udfs_len(ptr::null()) }, 0);
+    // SAFETY: NULL is handled
+    assert!(unsafe { dfwasm_udfs_name(ptr::null(), 0) }.is_null());
+
+    // SAFETY: invalid data is rejected
+    let component = unsafe { dfwasm_component_load(b"OLD".as_ptr(), 3) };
+    assert!(component.is_null());
+    assert_eq!(
+        last_error(),
+        "
+create WASM component
+caused by
+External error: failed to parse precompiled artifact as an ELF
+        "
+        .trim(),
+    );
+
+    let permissions = dfwasm_permissions_new();
+    let key = CString::new("FOO").unwrap();
+    // SAFETY: valid pointers
+    let status = unsafe { dfwasm_permissions_set_env(permissions, key.as_ptr(), ptr::null()) };
+    assert_eq!(status, DfWasmStatus::Error);
+    assert_eq!(last_error(), "Execution error: `value` is NULL");
+
+    // success clears the error
+    // SAFETY: valid pointer
+    let status = unsafe { dfwasm_permissions_set_max_udfs(permissions, 1) };
+    assert_eq!(status, DfWasmStatus::Ok);
+    assert!(dfwasm_last_error().is_null());
+
+    // SAFETY: valid pointers, NULL is a no-op
+    unsafe {
+        dfwasm_permissions_free(permissions);
+        dfwasm_permissions_free(ptr::null_mut());
+        dfwasm_udfs_free(ptr::null_mut());
+        dfwasm_component_free(ptr::null_mut());
+        dfwasm_runtime_free(ptr::null_mut());
+    }
+}
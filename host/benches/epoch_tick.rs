@@ -0,0 +1,162 @@
+//! Measure the overhead of [epoch ticks](WasmPermissions::with_epoch_tick_time).
+//!
+//! # Implementation
+//! A CPU-bound Python UDF -- i.e. one that never yields to the host -- is called with different epoch tick times.
+//! Every tick interrupts the guest, so shorter tick times allow the host to enforce timeouts more precisely at the cost
+//! of more interruptions.
+//!
+//! # What is Measured
+//! We measure the cost of [`AsyncScalarUDFImpl::invoke_async_with_args`]. The ticks are driven by a separate I/O
+//! runtime, so that they also fire while the guest is running.
+#![expect(
+    // Docs are not strictly required for tests.
+    clippy::missing_docs_in_private_items,
+    // unused-crate-dependencies false positives
+    unused_crate_dependencies,
+)]
+
+use std::{hint::black_box, sync::Arc, time::Duration};
+
+use arrow::{
+    array::Int64Array,
+    datatypes::{DataType, Field},
+};
+use datafusion_common::config::ConfigOptions;
+use datafusion_execution::memory_pool::UnboundedMemoryPool;
+use datafusion_expr::{ColumnarValue, ScalarFunctionArgs, async_udf::AsyncScalarUDFImpl};
+use datafusion_udf_wasm_host::{WasmPermissions, WasmScalarUdf};
+use gungraun::{LibraryBenchmarkConfig, library_benchmark, library_benchmark_group, main};
+use tokio::runtime::Runtime;
+
+use crate::common::build_wasm_module;
+
+mod common;
+
+const CODE: &str = "
+def spin(n: int) -> int:
+    acc = 0
+    for i in range(n):
+        acc += i
+    return acc
+";
+
+/// Number of loop iterations of the UDF.
+const ITERATIONS: i64 = 1_000_000;
+
+struct Setup {
+    udf: WasmScalarUdf,
+    args: ScalarFunctionArgs,
+    rt: Runtime,
+    io_rt: Runtime,
+}
+
+#[expect(dead_code)]
+struct SetupLeftovers {
+    udf: WasmScalarUdf,
+    rt: Runtime,
+    io_rt: Runtime,
+}
+
+impl Setup {
+    fn new(epoch_tick_time: Duration) -> Self {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+        let io_rt = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .enable_time()
+            .build()
+            .unwrap();
+
+        let component = build_wasm_module(datafusion_udf_wasm_bundle::BIN_PYTHON);
+        let udf = rt.block_on(async {
+            WasmScalarUdf::new(
+                &component,
+                &WasmPermissions::new().with_epoch_tick_time(epoch_tick_time),
+                io_rt.handle().clone(),
+                &(Arc::new(UnboundedMemoryPool::default()) as _),
+                CODE.to_owned(),
+            )
+            .await
+            .unwrap()
+            .into_iter()
+            .next()
+            .unwrap()
+        });
+
+        let args = ScalarFunctionArgs {
+            args: vec![ColumnarValue::Array(Arc::new(Int64Array::from_iter([
+                Some(ITERATIONS),
+            ])))],
+            arg_fields: vec![Arc::new(Field::new("n", DataType::Int64, true))],
+            number_rows: 1,
+            return_field: Arc::new(Field::new("r", DataType::Int64, true)),
+            config_options: Arc::new(ConfigOptions::default()),
+        };
+
+        Self {
+            udf,
+            args,
+            rt,
+            io_rt,
+        }
+    }
+
+    fn run(self) -> SetupLeftovers {
+        let Self {
+            udf,
+            args,
+            rt,
+            io_rt,
+        } = self;
+
+        black_box(rt.block_on(async { udf.invoke_async_with_args(args).await.unwrap() }));
+
+        SetupLeftovers { udf, rt, io_rt }
+    }
+}
+
+mod actual_benchmark {
+    use super::*;
+
+    #[library_benchmark(setup = Setup::new, teardown = drop)]
+    #[bench::tick_1ms(Duration::from_millis(1))]
+    #[bench::tick_10ms(Duration::from_millis(10))]
+    #[bench::tick_100ms(Duration::from_millis(100))]
+    fn bench_epoch_tick(setup: Setup) -> SetupLeftovers {
+        setup.run()
+    }
+
+    library_benchmark_group!(
+        name = epoch_tick;
+        benchmarks = bench_epoch_tick
+    );
+
+    main!(
+        config = LibraryBenchmarkConfig::default()
+            .valgrind_args([
+                // Ensure that `build_wasm_module` runs outside of valgrind for performance reasons.
+                "--trace-children=no",
+            ]);
+        ;
+        library_benchmark_groups = epoch_tick
+    );
+
+    // re-export `main`
+    pub(crate) fn pub_main() {
+        main();
+    }
+}
+
+fn main() {
+    // Running the actual benchmark under Valgrind is rather expensive, esp. in CI. Hence we just smoke tests.
+    if std::env::args().any(|arg| arg == "--test") {
+        for epoch_tick_time in [Duration::from_millis(1), Duration::from_millis(100)] {
+            println!("epoch_tick_time={epoch_tick_time:?}");
+            Setup::new(epoch_tick_time).run();
+        }
+    } else {
+        actual_benchmark::pub_main();
+    }
+}
@@ -0,0 +1,146 @@
+//! Measure the cost of creating UDFs.
+//!
+//! # Implementations
+//!
+//! ## WASM
+//! The "add one" example, written in Rust. This measures the raw overhead of creating a guest instance.
+//!
+//! ## Python
+//! The default Python guest, which bootstraps the interpreter whenever a guest instance is created.
+//!
+//! ## Python Pre-Initialized
+//! The same guest, but the interpreter was bootstrapped when the component was built and the resulting state was
+//! snapshotted, see the "Pre-Initialization" section in the README of the Python guest.
+//!
+//! # What is Measured
+//! We measure the cost of [`WasmScalarUdf::new`] for a trivial UDF, i.e. creating a guest instance and setting up the
+//! UDF. The pre-compilation of the component is NOT measured, since it is done once per process.
+#![expect(
+    // Docs are not strictly required for tests.
+    clippy::missing_docs_in_private_items,
+    // unused-crate-dependencies false positives
+    unused_crate_dependencies,
+)]
+
+use std::{hint::black_box, sync::Arc};
+
+use datafusion_execution::memory_pool::UnboundedMemoryPool;
+use datafusion_udf_wasm_host::{WasmComponentPrecompiled, WasmScalarUdf};
+use gungraun::{LibraryBenchmarkConfig, library_benchmark, library_benchmark_group, main};
+use tokio::runtime::{Handle, Runtime};
+
+use crate::common::build_wasm_module;
+
+mod common;
+
+const PYTHON_CODE: &str = "
+def add_one(a: int) -> int:
+    return a + 1
+";
+
+#[derive(Debug, Clone, Copy)]
+enum Mode {
+    Wasm,
+    Python,
+    PythonPreinit,
+}
+
+struct Setup {
+    component: WasmComponentPrecompiled,
+    code: &'static str,
+    rt: Runtime,
+}
+
+#[expect(dead_code)]
+struct SetupLeftovers {
+    udfs: Vec<WasmScalarUdf>,
+    rt: Runtime,
+}
+
+impl Setup {
+    fn new(mode: Mode) -> Self {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+
+        let (binary, code) = match mode {
+            Mode::Wasm => (datafusion_udf_wasm_bundle::BIN_EXAMPLE_ADD_ONE, ""),
+            Mode::Python => (datafusion_udf_wasm_bundle::BIN_PYTHON, PYTHON_CODE),
+            Mode::PythonPreinit => (datafusion_udf_wasm_bundle::BIN_PYTHON_PREINIT, PYTHON_CODE),
+        };
+        let component = build_wasm_module(binary);
+
+        Self {
+            component,
+            code,
+            rt,
+        }
+    }
+
+    fn run(self) -> SetupLeftovers {
+        let Self {
+            component,
+            code,
+            rt,
+        } = self;
+
+        let udfs = black_box(rt.block_on(async {
+            WasmScalarUdf::new(
+                &component,
+                &Default::default(),
+                Handle::current(),
+                &(Arc::new(UnboundedMemoryPool::default()) as _),
+                code.to_owned(),
+            )
+            .await
+            .unwrap()
+        }));
+
+        SetupLeftovers { udfs, rt }
+    }
+}
+
+mod actual_benchmark {
+    use super::*;
+
+    #[library_benchmark(setup = Setup::new, teardown = drop)]
+    #[bench::wasm(Mode::Wasm)]
+    #[bench::python(Mode::Python)]
+    #[bench::python_preinit(Mode::PythonPreinit)]
+    fn bench_instantiation(setup: Setup) -> SetupLeftovers {
+        setup.run()
+    }
+
+    library_benchmark_group!(
+        name = instantiation;
+        benchmarks = bench_instantiation
+    );
+
+    main!(
+        config = LibraryBenchmarkConfig::default()
+            .valgrind_args([
+                // Ensure that `build_wasm_module` runs outside of valgrind for performance reasons.
+                "--trace-children=no",
+            ]);
+        ;
+        library_benchmark_groups = instantiation
+    );
+
+    // re-export `main`
+    pub(crate) fn pub_main() {
+        main();
+    }
+}
+
+fn main() {
+    // Running the actual benchmark under Valgrind is rather expensive, esp. in CI. Hence we just smoke tests.
+    if std::env::args().any(|arg| arg == "--test") {
+        for mode in [Mode::Wasm, Mode::Python, Mode::PythonPreinit] {
+            println!("mode={mode:?}");
+            Setup::new(mode).run();
+        }
+    } else {
+        actual_benchmark::pub_main();
+    }
+}
@@ -76,7 +76,7 @@
     unused_crate_dependencies,
 )]
 
-use std::{hint::black_box, io::Write, sync::Arc};
+use std::{hint::black_box, sync::Arc};
 
 use arrow::{
     array::{ArrayRef, GenericStringBuilder, Int64Array, StringArray},
@@ -92,11 +92,15 @@ use datafusion_expr::{
     ColumnarValue, ScalarFunctionArgs, ScalarUDFImpl, Signature, Volatility,
     async_udf::AsyncScalarUDFImpl,
 };
-use datafusion_udf_wasm_host::{WasmComponentPrecompiled, WasmScalarUdf};
+use datafusion_udf_wasm_host::WasmScalarUdf;
 use gungraun::{LibraryBenchmarkConfig, library_benchmark, library_benchmark_group, main};
 use tokio::runtime::{Handle, Runtime};
 use wasmtime_wasi::async_trait;
 
+use crate::common::build_wasm_module;
+
+mod common;
+
 /// UDF that implements "add one".
 #[derive(Debug, PartialEq, Eq, Hash)]
 pub(crate) struct AddOne {
@@ -235,42 +239,6 @@ impl AsyncScalarUDFImpl for SubStr {
     }
 }
 
-/// Compile the WASM component outside of Valgrind, because otherwise the setup step takes like 3+min per benchmark.
-fn build_wasm_module(binary: &[u8]) -> WasmComponentPrecompiled {
-    let mut child = std::process::Command::new(env!("CARGO_BIN_EXE_compile"))
-        .arg("/dev/stdin")
-        .arg("/dev/stdout")
-        // Specify the target that is the same as the host.
-        //
-        // If we don't specify the target, this will compile for "native CPU", not for a generic CPU. And "native CPU"
-        // may include AVX&Co instructions that Valgrind doesn't support.
-        //
-        // Also in production people are likely gonna use the generic CPU pre-compiled binaries, so I feel that's a
-        // fairer comparison.
-        .arg(target_lexicon::HOST.to_string())
-        .stdin(std::process::Stdio::piped())
-        .stdout(std::process::Stdio::piped())
-        .stderr(std::process::Stdio::null())
-        .spawn()
-        .unwrap();
-
-    // Technically you also have to poll stdout/stderr while you feed data into stdin, otherwise we may deadlock.
-    // However, the `compile` binary isn't streaming and stderr is hooked up to `/dev/null`, so we can feed the entire
-    // data in before we read the output back. Just don't forget to close stdin (via `drop`) to send EOF.
-    let mut stdin = child.stdin.take().expect("Failed to open stdin");
-    stdin.write_all(binary).expect("Failed to write to stdin");
-    stdin.flush().expect("Flush stdin");
-    drop(stdin);
-
-    let output = child.wait_with_output().unwrap();
-    assert!(output.status.success());
-
-    let elf = output.stdout;
-    // SAFETY: we just compiled this data ourselves
-    let res = unsafe { WasmComponentPrecompiled::load(elf) };
-    res.unwrap()
-}
-
 #[derive(Debug, Clone, Copy)]
 enum Payload {
     AddOne,
@@ -4,5 +4,6 @@ use wasmtime::component::bindgen;
 bindgen!({
     world: "datafusion",
     path: "../wit/world.wit",
+    imports: { default: async | trappable },
     exports: { default: async },
 });
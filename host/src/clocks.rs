@@ -0,0 +1,233 @@
+//! Clocks that are exposed to the guest.
+
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant, SystemTime},
+};
+
+use wasmtime_wasi::{
+    WasiCtxBuilder,
+    clocks::{HostMonotonicClock, HostWallClock},
+};
+
+use crate::volatility::ImmutableFlag;
+
+/// Host-provided time source for guests.
+///
+/// See [`ClockPolicy::Provider`].
+pub trait ClockProvider: std::fmt::Debug + Send + Sync + 'static {
+    /// Current wall clock time, as duration since the UNIX epoch.
+    fn wall_clock_now(&self) -> Duration;
+
+    /// Resolution of [`wall_clock_now`](Self::wall_clock_now).
+    fn wall_clock_resolution(&self) -> Duration {
+        Duration::from_nanos(1)
+    }
+
+    /// Current monotonic clock value, in nanoseconds.
+    ///
+    /// The value MUST NOT decrease between calls.
+    fn monotonic_clock_now(&self) -> u64;
+
+    /// Resolution of [`monotonic_clock_now`](Self::monotonic_clock_now), in nanoseconds.
+    fn monotonic_clock_resolution(&self) -> u64 {
+        1
+    }
+}
+
+/// Defines which time the guest observes via `wasi:clocks`.
+///
+/// UDFs that are declared as [immutable](datafusion_expr::Volatility::Immutable) should not be able to observe time,
+/// otherwise caching and constant folding by DataFusion may lead to surprising results. Regardless of the policy, the
+/// clocks therefore stand still while such a UDF is invoked. Use [`Fixed`](Self::Fixed) if the guest should never
+/// observe time.
+#[derive(Debug, Clone, Default)]
+pub enum ClockPolicy {
+    /// Expose the real host clocks.
+    #[default]
+    Real,
+
+    /// Always report the same time.
+    ///
+    /// The monotonic clock is stuck at zero.
+    Fixed {
+        /// Wall clock time, as duration since the UNIX epoch.
+        wall_clock: Duration,
+    },
+
+    /// Ask a host-provided time source.
+    Provider(Arc<dyn ClockProvider>),
+}
+
+impl ClockPolicy {
+    /// Install clocks into WASI context.
+    ///
+    /// The clocks stand still while the [immutable flag](ImmutableFlag) is set.
+    pub(crate) fn apply(&self, builder: &mut WasiCtxBuilder, immutable: &ImmutableFlag) {
+        match self {
+            Self::Real => {
+                let clock = ProviderClock(Arc::new(RealClock::default()));
+                builder.wall_clock(ImmutableClock::new(clock.clone(), immutable));
+                builder.monotonic_clock(ImmutableClock::new(clock, immutable));
+            }
+            Self::Fixed { wall_clock } => {
+                builder.wall_clock(FixedWallClock(*wall_clock));
+                builder.monotonic_clock(FixedMonotonicClock);
+            }
+            Self::Provider(provider) => {
+                let clock = ProviderClock(Arc::clone(provider));
+                builder.wall_clock(ImmutableClock::new(clock.clone(), immutable));
+                builder.monotonic_clock(ImmutableClock::new(clock, immutable));
+            }
+        }
+    }
+}
+
+/// Real host clock for [`ClockPolicy::Real`].
+#[derive(Debug)]
+struct RealClock {
+    /// Start of the monotonic clock.
+    start: Instant,
+}
+
+impl Default for RealClock {
+    fn default() -> Self {
+        Self {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl ClockProvider for RealClock {
+    fn wall_clock_now(&self) -> Duration {
+        SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+    }
+
+    fn monotonic_clock_now(&self) -> u64 {
+        self.start
+            .elapsed()
+            .as_nanos()
+            .try_into()
+            .unwrap_or(u64::MAX)
+    }
+}
+
+/// Clock that stands still while the [immutable flag](ImmutableFlag) is set.
+///
+/// The wall clock reports the UNIX epoch, the monotonic clock reports the last value that was observed before the
+/// flag was set, so it never goes backwards.
+#[derive(Debug)]
+struct ImmutableClock<C> {
+    /// Underlying clock.
+    inner: C,
+
+    /// Immutable flag.
+    immutable: ImmutableFlag,
+
+    /// Last observed monotonic clock value.
+    last_monotonic: AtomicU64,
+}
+
+impl<C> ImmutableClock<C> {
+    /// Create new clock.
+    fn new(inner: C, immutable: &ImmutableFlag) -> Self {
+        Self {
+            inner,
+            immutable: immutable.clone(),
+            last_monotonic: AtomicU64::new(0),
+        }
+    }
+}
+
+impl<C> HostWallClock for ImmutableClock<C>
+where
+    C: HostWallClock,
+{
+    fn resolution(&self) -> Duration {
+        self.inner.resolution()
+    }
+
+    fn now(&self) -> Duration {
+        if self.immutable.is_set() {
+            Duration::ZERO
+        } else {
+            self.inner.now()
+        }
+    }
+}
+
+impl<C> HostMonotonicClock for ImmutableClock<C>
+where
+    C: HostMonotonicClock,
+{
+    fn resolution(&self) -> u64 {
+        self.inner.resolution()
+    }
+
+    fn now(&self) -> u64 {
+        if self.immutable.is_set() {
+            self.last_monotonic.load(Ordering::SeqCst)
+        } else {
+            let now = self.inner.now();
+            self.last_monotonic.fetch_max(now, Ordering::SeqCst);
+            now
+        }
+    }
+}
+
+/// Wall clock for [`ClockPolicy::Fixed`].
+#[derive(Debug)]
+struct FixedWallClock(Duration);
+
+impl HostWallClock for FixedWallClock {
+    fn resolution(&self) -> Duration {
+        Duration::from_nanos(1)
+    }
+
+    fn now(&self) -> Duration {
+        self.0
+    }
+}
+
+/// Monotonic clock for [`ClockPolicy::Fixed`].
+#[derive(Debug)]
+struct FixedMonotonicClock;
+
+impl HostMonotonicClock for FixedMonotonicClock {
+    fn resolution(&self) -> u64 {
+        1
+    }
+
+    fn now(&self) -> u64 {
+        0
+    }
+}
+
+/// Wall and monotonic clock for [`ClockPolicy::Real`] and [`ClockPolicy::Provider`].
+#[derive(Debug, Clone)]
+struct ProviderClock(Arc<dyn ClockProvider>);
+
+impl HostWallClock for ProviderClock {
+    fn resolution(&self) -> Duration {
+        self.0.wall_clock_resolution()
+    }
+
+    fn now(&self) -> Duration {
+        self.0.wall_clock_now()
+    }
+}
+
+impl HostMonotonicClock for ProviderClock {
+    fn resolution(&self) -> u64 {
+        self.0.monotonic_clock_resolution()
+    }
+
+    fn now(&self) -> u64 {
+        self.0.monotonic_clock_now()
+    }
+}
@@ -1,12 +1,21 @@
 //! WASM component handling.
-use std::{ops::Deref, sync::Arc, time::Duration};
+use std::{
+    ops::Deref,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
 
 use arrow::datatypes::Field;
-use datafusion_common::{config::ConfigOptions, error::Result as DataFusionResult};
-use datafusion_execution::memory_pool::MemoryPool;
+use datafusion_common::{
+    DataFusionError, config::ConfigOptions, error::Result as DataFusionResult,
+};
+use datafusion_execution::memory_pool::{MemoryConsumer, MemoryPool, MemoryReservation};
 use tokio::{
     runtime::Handle,
-    sync::{Mutex, OwnedMutexGuard},
+    sync::{Mutex, OnceCell, OwnedMutexGuard},
     task::JoinSet,
 };
 use wasmtime::{
@@ -17,19 +26,35 @@ use wasmtime_wasi::{ResourceTable, WasiCtx, p2::pipe::MemoryOutputPipe};
 use wasmtime_wasi_http::WasiHttpCtx;
 
 use crate::{
-    TrustedDataLimits, WasmPermissions, bindings,
+    ContextLabels, IpcCompression, TrustedDataLimits, WasmPermissions, WitVersion, bindings,
+    config_forwarding::ConfigForwarding,
     conversion::resource_cache::ResourceCache,
-    error::{DataFusionResultExt, WasmToDataFusionResultExt},
+    error::{
+        DataFusionResultExt, InstancePoisoned, InvocationTimeout, RuntimeShutdown,
+        WasmToDataFusionResultExt, WasmUdfError,
+    },
     http::WasiHttpHooksImpl,
     ignore_debug::IgnoreDebug,
+    kv::KvState,
     limiter::Limiter,
     linker::link,
+    metadata::WasmComponentDescription,
+    nn::nn_ctx,
+    observer::Observer,
+    random::RandomState,
+    secrets::{RevealedSecrets, SecretsState},
     state::WasmStateImpl,
-    vfs::VfsState,
+    stderr::Stderr,
+    trace::span,
+    vfs::{
+        VfsState,
+        root_fs::{RootFs, RootFsNode},
+    },
+    volatility::ImmutableFlag,
 };
 
 /// Create WASM engine.
-fn create_engine<F>(flags: &F) -> DataFusionResult<Engine>
+pub(crate) fn create_engine<F>(flags: &F) -> DataFusionResult<Engine>
 where
     F: CompilationFlagsInterface,
 {
@@ -46,13 +71,13 @@ where
 }
 
 /// Interface for different ways of conveying compilation flags.
-trait CompilationFlagsInterface {
+pub(crate) trait CompilationFlagsInterface {
     /// Apply compilation flags.
     fn apply(&self, config: &mut wasmtime::Config) -> DataFusionResult<()>;
 }
 
 /// Disable WASM bytecode -> machine code compiler.
-struct NoCompilation;
+pub(crate) struct NoCompilation;
 
 impl CompilationFlagsInterface for NoCompilation {
     #[cfg(feature = "compiler")]
@@ -100,11 +125,28 @@ impl CompilationFlagsInterface for CompilationFlags {
 /// Pre-compiled WASM component.
 ///
 /// The pre-compilation is stateless and can be used to [create](crate::WasmScalarUdf::new) multiple instances that do not share
-/// any state.
-#[derive(Debug)]
+/// any state. The only exception is the immutable root filesystem of the guest, which is retrieved once and then shared
+/// between all instances in a copy-on-write fashion.
+///
+/// Cloning is cheap, clones share the pre-compiled data and the root filesystem.
+#[derive(Debug, Clone)]
 pub struct WasmComponentPrecompiled {
-    /// Binary representation of the pre-compiled component.
-    compiled_component: Vec<u8>,
+    /// [Stored](Self::store) data: a [header](STORE_MAGIC) followed by the pre-compiled component.
+    stored: Arc<[u8]>,
+
+    /// Length of the [header](STORE_MAGIC) within [`stored`](Self::stored).
+    header_len: usize,
+
+    /// Digest of the WASM payload, see [`digest`](Self::digest).
+    digest: u128,
+
+    /// Root filesystem provided by the guest.
+    ///
+    /// This is retrieved and parsed when the first instance is created and then shared between all instances.
+    root_fs: IgnoreDebug<Arc<OnceCell<Option<RootFs>>>>,
+
+    /// Language/runtime of the guest, see [`WasmComponentDescription::runtime`].
+    runtime: Option<Arc<str>>,
 }
 
 impl WasmComponentPrecompiled {
@@ -122,23 +164,65 @@ impl WasmComponentPrecompiled {
         // Create temporary engine that we need for compilation.
         let engine = create_engine(flags)?;
 
+        let span =
+            span!("precompile", wasm_bytes = wasm_binary.len() as u64; record: compiled_bytes);
         tokio::task::spawn_blocking(move || {
-            let compiled_component = engine
-                .precompile_component(&wasm_binary)
-                .context("pre-compile component", None)?;
-
-            log::debug!(
-                "Pre-compiled {} bytes of WASM bytecode into {} bytes",
-                wasm_binary.len(),
-                compiled_component.len()
-            );
-
-            Ok(Self { compiled_component })
+            span.in_scope(|| {
+                let runtime: Option<Arc<str>> =
+                    crate::metadata::runtime_from_wasm(&wasm_binary).map(Into::into);
+                let compiled_component = engine
+                    .precompile_component(&wasm_binary)
+                    .context("pre-compile component", None)?;
+
+                log::debug!(
+                    "Pre-compiled {} bytes of WASM bytecode into {} bytes",
+                    wasm_binary.len(),
+                    compiled_component.len()
+                );
+                span.record("compiled_bytes", compiled_component.len() as u64);
+
+                let digest = digest(&wasm_binary);
+                let runtime_bytes = runtime.as_deref().unwrap_or_default().as_bytes();
+                let header_len =
+                    STORE_MAGIC.len() + size_of::<u128>() + size_of::<u64>() + runtime_bytes.len();
+                let mut stored = Vec::with_capacity(header_len + compiled_component.len());
+                stored.extend_from_slice(STORE_MAGIC);
+                stored.extend_from_slice(&digest.to_le_bytes());
+                stored.extend_from_slice(&(runtime_bytes.len() as u64).to_le_bytes());
+                stored.extend_from_slice(runtime_bytes);
+                stored.extend_from_slice(&compiled_component);
+
+                Ok(Self {
+                    digest,
+                    stored: stored.into(),
+                    header_len,
+                    root_fs: Arc::new(OnceCell::new()).into(),
+                    runtime,
+                })
+            })
         })
         .await
         .map_err(|e| datafusion_common::DataFusionError::External(Box::new(e)))?
     }
 
+    /// Verify detached signature of WASM payload and [pre-compile](Self::compile) it.
+    ///
+    /// Refuses to pre-compile unsigned or tampered payloads, see [`ComponentVerifier`](crate::ComponentVerifier).
+    #[cfg(feature = "compiler")]
+    pub async fn compile_verified(
+        wasm_binary: Arc<[u8]>,
+        signature: Option<&[u8]>,
+        verifier: &crate::ComponentVerifier,
+        flags: &CompilationFlags,
+    ) -> DataFusionResult<Self> {
+        verifier
+            .verify(&wasm_binary, signature)
+            .map_err(|e| DataFusionError::External(Box::new(e)))
+            .context("verify WASM payload")?;
+
+        Self::compile(wasm_binary, flags).await
+    }
+
     /// Get raw, pre-compiled component data.
     ///
     /// See [`load`](Self::load) too.
@@ -158,7 +242,7 @@ impl WasmComponentPrecompiled {
     ///
     /// [build script]: https://doc.rust-lang.org/cargo/reference/build-scripts.html
     pub fn store(&self) -> &[u8] {
-        &self.compiled_component
+        &self.stored
     }
 
     /// Load pre-compiled component.
@@ -185,7 +269,7 @@ impl WasmComponentPrecompiled {
     ///     "
     /// create WASM component
     /// caused by
-    /// External error: failed to parse precompiled artifact as an ELF
+    /// External error: missing datafusion-udf-wasm header
     ///     ".trim(),
     /// );
     /// ```
@@ -201,8 +285,15 @@ impl WasmComponentPrecompiled {
     ///
     /// [`dlopen`]: https://pubs.opengroup.org/onlinepubs/009696799/functions/dlopen.html
     pub unsafe fn load(data: Vec<u8>) -> DataFusionResult<Self> {
+        let (digest, runtime, header_len) = parse_store_header(&data)
+            .ok_or_else(|| wasmtime::Error::msg("missing datafusion-udf-wasm header"))
+            .context("create WASM component", None)?;
         let this = Self {
-            compiled_component: data,
+            digest,
+            stored: data.into(),
+            header_len,
+            root_fs: Arc::new(OnceCell::new()).into(),
+            runtime,
         };
 
         // test hydration
@@ -212,18 +303,92 @@ impl WasmComponentPrecompiled {
         Ok(this)
     }
 
+    /// Digest of the WASM payload that the component was [compiled](Self::compile) from.
+    ///
+    /// The digest is computed from the WASM bytecode -- NOT from the pre-compiled data, which depends on the target and
+    /// the engine configuration -- and is kept when the component is [stored](Self::store) and [loaded](Self::load).
+    /// So it identifies the same component across processes and hosts, e.g. to reconstruct UDFs from a serialized
+    /// plan. Note that the digest is NOT a cryptographic hash and MUST NOT be used to establish trust.
+    pub fn digest(&self) -> u128 {
+        self.digest
+    }
+
+    /// Describe component, e.g. the implemented WIT version and the exported interfaces.
+    pub fn describe(&self) -> DataFusionResult<WasmComponentDescription> {
+        let engine = create_engine(&NoCompilation)?;
+        let component = self.hydrate(&engine)?;
+        Ok(WasmComponentDescription::new(
+            &component,
+            &engine,
+            self.runtime(),
+            self.stored.len(),
+        ))
+    }
+
+    /// Language/runtime of the guest, see [`WasmComponentDescription::runtime`].
+    pub(crate) fn runtime(&self) -> Option<String> {
+        self.runtime.as_deref().map(ToOwned::to_owned)
+    }
+
     /// Hydrate wasmtime component from raw data.
     fn hydrate(&self, engine: &Engine) -> DataFusionResult<Component> {
-        let Self { compiled_component } = self;
+        let Self {
+            stored,
+            header_len,
+            digest: _,
+            root_fs: _,
+            runtime: _,
+        } = self;
 
         // SAFETY: Either we just produced this data ourselves within the same process (i.e. it is NOT external input)
         //         OR the API user promised us that the data is safe (see [`WasmComponentPrecompiled::load`]).
-        let component_res = unsafe { Component::deserialize(engine, compiled_component) };
+        let component_res = unsafe { Component::deserialize(engine, &stored[*header_len..]) };
         let component = component_res.context("create WASM component", None)?;
         Ok(component)
     }
 }
 
+/// Magic bytes at the start of the [stored](WasmComponentPrecompiled::store) data.
+///
+/// They are followed by
+/// - the [digest](WasmComponentPrecompiled::digest) as a 128-bit little-endian integer,
+/// - the length of the [runtime](WasmComponentDescription::runtime) as a 64-bit little-endian integer,
+/// - the runtime as UTF-8 string, empty if the guest does not declare one,
+/// - and finally the pre-compiled component.
+const STORE_MAGIC: &[u8; 8] = b"dfudfw\x00\x02";
+
+/// Parse the [header](STORE_MAGIC) of [stored](WasmComponentPrecompiled::store) data.
+///
+/// Returns the digest, the runtime, and the length of the header.
+fn parse_store_header(data: &[u8]) -> Option<(u128, Option<Arc<str>>, usize)> {
+    let rest = data.strip_prefix(STORE_MAGIC.as_slice())?;
+    let (digest, rest) = rest.split_first_chunk::<16>()?;
+    let (runtime_len, rest) = rest.split_first_chunk::<8>()?;
+    let runtime_len = usize::try_from(u64::from_le_bytes(*runtime_len)).ok()?;
+    let runtime = std::str::from_utf8(rest.get(..runtime_len)?).ok()?;
+    let header_len = data.len() - rest.len() + runtime_len;
+
+    Some((
+        u128::from_le_bytes(*digest),
+        (!runtime.is_empty()).then(|| runtime.into()),
+        header_len,
+    ))
+}
+
+/// Compute [digest](WasmComponentPrecompiled::digest) of a WASM payload.
+///
+/// SipHash with a fixed key is stable across platforms and versions.
+#[cfg(feature = "compiler")]
+fn digest(data: &[u8]) -> u128 {
+    use std::hash::Hasher;
+
+    use siphasher::sip128::{Hasher128, SipHasher24};
+
+    let mut hasher = SipHasher24::new();
+    hasher.write(data);
+    hasher.finish128().as_u128()
+}
+
 /// Stateful instance of a WASM component.
 #[derive(Debug)]
 pub(crate) struct WasmComponentInstance {
@@ -232,6 +397,19 @@ pub(crate) struct WasmComponentInstance {
     /// This mostly contains [`WasmStateImpl`].
     store: Arc<Mutex<Store<WasmStateImpl>>>,
 
+    /// Set when the guest became unusable, see [`poison`](Self::poison).
+    poisoned: AtomicBool,
+
+    /// Set by [`cancel`](Self::cancel) and reset by [`poison`](Self::poison), i.e. it reflects whether the most recent
+    /// reason for poisoning the instance was a cancelled call.
+    cancelled: AtomicBool,
+
+    /// Set by [`interrupt`](Self::interrupt), checked by the epoch deadline callback.
+    interrupted: Arc<AtomicBool>,
+
+    /// Engine, used to [interrupt](Self::interrupt) the guest without locking the [store](Self::store).
+    engine: IgnoreDebug<Engine>,
+
     /// Resource cache for [`Field`].
     ///
     /// NOTE: This is not included in [`store`](Self::store) / [`WasmStateImpl`] because creating new cache values
@@ -254,8 +432,26 @@ pub(crate) struct WasmComponentInstance {
     /// Trusted data limits.
     trusted_data_limits: TrustedDataLimits,
 
+    /// Which [`ConfigOptions`] entries are forwarded to the guest.
+    config_forwarding: ConfigForwarding,
+
+    /// DataFusion memory pool, used to account for host-side conversion buffers.
+    memory_pool: Arc<dyn MemoryPool>,
+
     /// WIT-based bindings that we resolved within the payload.
     bindings: IgnoreDebug<Arc<bindings::Datafusion>>,
+
+    /// Reports sandbox activity.
+    observer: Observer,
+
+    /// WIT version that was negotiated with the guest.
+    wit_version: WitVersion,
+
+    /// IPC compression that was negotiated with the guest, see [`WasmPermissions::with_ipc_compression`].
+    ipc_compression: Option<IpcCompression>,
+
+    /// Guest-side instance resource that source code is loaded into.
+    guest_instance: ResourceAny,
 }
 
 impl WasmComponentInstance {
@@ -266,6 +462,27 @@ impl WasmComponentInstance {
         io_rt: Handle,
         memory_pool: &Arc<dyn MemoryPool>,
     ) -> DataFusionResult<Self> {
+        span!("instantiate")
+            .instrument(Self::instantiate(
+                component,
+                permissions,
+                io_rt,
+                memory_pool,
+            ))
+            .await
+    }
+
+    /// Create new instance, see [`new`](Self::new).
+    async fn instantiate(
+        component: &WasmComponentPrecompiled,
+        permissions: &WasmPermissions,
+        io_rt: Handle,
+        memory_pool: &Arc<dyn MemoryPool>,
+    ) -> DataFusionResult<Self> {
+        if let Some(handle) = &permissions.runtime_handle {
+            handle.check()?;
+        }
+
         let engine = create_engine(&NoCompilation)?;
 
         // set up epoch timer
@@ -298,13 +515,26 @@ impl WasmComponentInstance {
             .epoch_tick_time
             .saturating_mul(permissions.inplace_blocking_max_ticks);
 
-        let component = component.hydrate(&engine)?;
+        let hydrated = component.hydrate(&engine)?;
 
         // resource/mem limiter
-        let limiter = Limiter::new(permissions.resource_limits.clone(), memory_pool);
+        let limiter = Limiter::new(
+            permissions.resource_limits.clone(),
+            permissions.dynamic_memory_limits.clone(),
+            memory_pool,
+        );
+        limiter.prereserve()?;
+
+        let observer = Observer::new(
+            permissions.observer.clone(),
+            permissions.context_labels.clone(),
+        );
 
         // Create in-memory VFS
-        let vfs_state = VfsState::new(permissions.vfs.clone(), limiter.clone());
+        let vfs_state = VfsState::new(permissions.vfs.clone(), limiter.split(), observer.clone());
+
+        // shared by all interfaces that must not be used by immutable UDFs
+        let immutable = ImmutableFlag::default();
 
         // set up WASI p2 context
         limiter.grow(permissions.stderr_bytes)?;
@@ -314,6 +544,20 @@ impl WasmComponentInstance {
         permissions.envs.iter().for_each(|(k, v)| {
             wasi_ctx_builder.env(k, v);
         });
+        permissions.clock.apply(&mut wasi_ctx_builder, &immutable);
+
+        // secrets, redacted from stderr and guest errors
+        let revealed_secrets = RevealedSecrets::default();
+        let secrets = SecretsState::new(
+            permissions.secret_provider.clone(),
+            revealed_secrets.clone(),
+        );
+
+        // key-value store, accounted separately from the guest memory like the VFS
+        let kv = KvState::new(permissions.kv.as_ref(), limiter.split());
+
+        // load models for `wasi-nn`
+        let nn_ctx = nn_ctx(&permissions.nn_models, &limiter).context("set up wasi-nn")?;
 
         // configure store
         // NOTE: Do that BEFORE linking so that memory limits are checked for the initial allocation of the WASM
@@ -321,15 +565,49 @@ impl WasmComponentInstance {
         let state = WasmStateImpl {
             vfs_state,
             limiter,
-            stderr,
+            stderr: Stderr::new(
+                stderr,
+                permissions.trusted_data_limits.sanitize_strings,
+                permissions.stderr_policy,
+                permissions.stderr_redactor.clone(),
+                revealed_secrets,
+            ),
             wasi_ctx: wasi_ctx_builder.build().into(),
             wasi_http_ctx: WasiHttpCtx::new(),
-            wasi_http_hooks: WasiHttpHooksImpl::new(permissions.http.clone(), io_rt)
-                .context("set up HTTP")?,
+            wasi_http_hooks: WasiHttpHooksImpl::new(
+                permissions.http.clone(),
+                permissions.http_rate_limit,
+                permissions.http_redirects,
+                io_rt,
+                &immutable,
+                observer.clone(),
+            )
+            .context("set up HTTP")?,
+            kv,
+            secrets,
+            nn_ctx: nn_ctx.into(),
             resource_table: ResourceTable::new(),
+            random: RandomState::new(permissions.random, &immutable),
+            immutable,
+            invocation_deadline: None,
+            epoch_ticks: 0,
         };
         let mut store = Store::new(&engine, state);
-        store.epoch_deadline_callback(|_| {
+        let interrupted = Arc::new(AtomicBool::new(false));
+        let interrupted_captured = Arc::clone(&interrupted);
+        store.epoch_deadline_callback(move |mut ctx| {
+            ctx.data_mut().epoch_ticks += 1;
+
+            if interrupted_captured.load(Ordering::SeqCst) {
+                return Err(wasmtime::Error::new(RuntimeShutdown));
+            }
+
+            if let Some(deadline) = ctx.data().invocation_deadline
+                && Instant::now() >= deadline
+            {
+                return Err(wasmtime::Error::new(InvocationTimeout));
+            }
+
             Ok(UpdateDeadline::YieldCustom(
                 // increment deadline epoch by one step
                 1,
@@ -342,14 +620,83 @@ impl WasmComponentInstance {
         });
         store.limiter(|state| &mut state.limiter);
 
-        let bindings = link(&engine, &component, &mut store)
+        let (bindings, wit_version) = span!("link")
+            .instrument(link(&engine, &hydrated, &mut store))
             .await
             .context("link WASM components", None)?;
 
+        span!("populate_root_fs")
+            .instrument(async {
+                let root_fs = component
+                    .root_fs
+                    .get_or_try_init(async || {
+                        let root_fs_tar = bindings
+                            .datafusion_udf_wasm_udf_types()
+                            .call_root_fs_tar(&mut store)
+                            .await
+                            .context(
+                                "calling root_fs_tar() method failed",
+                                Some(&store.data().stderr),
+                            )?;
+                        root_fs_tar
+                            .map(|tar| RootFsNode::from_tar(&tar, &permissions.vfs))
+                            .transpose()
+                            .context("parse root filesystem")
+                    })
+                    .await?;
+                if let Some(root_fs) = root_fs {
+                    store
+                        .data()
+                        .vfs_state
+                        .populate(root_fs)
+                        .context("populate root filesystem")?;
+                }
+                for tar in permissions.extra_root_tars.iter() {
+                    let extra_root_fs = RootFsNode::from_tar(tar, &permissions.vfs)
+                        .context("parse extra root filesystem")?;
+                    store
+                        .data()
+                        .vfs_state
+                        .populate(&extra_root_fs)
+                        .context("populate extra root filesystem")?;
+                }
+                Ok::<_, DataFusionError>(())
+            })
+            .await?;
+
+        let ipc_compression = if permissions.ipc_compression.is_empty() {
+            None
+        } else {
+            let guest_compressions = bindings
+                .datafusion_udf_wasm_udf_types()
+                .call_ipc_compressions(&mut store)
+                .await
+                .context(
+                    "calling ipc_compressions() method failed",
+                    Some(&store.data().stderr),
+                )?;
+            IpcCompression::negotiate(&permissions.ipc_compression, &guest_compressions)
+        };
+
+        let guest_instance = bindings
+            .datafusion_udf_wasm_udf_types()
+            .instance()
+            .call_constructor(&mut store)
+            .await
+            .context(
+                "calling Instance constructor failed",
+                Some(&store.data().stderr),
+            )?;
+
         let store = Arc::new(Mutex::new(store));
+        observer.instance_created();
 
         Ok(Self {
             store,
+            poisoned: AtomicBool::new(false),
+            cancelled: AtomicBool::new(false),
+            interrupted,
+            engine: engine.into(),
             cache_field: Arc::new(Mutex::new(ResourceCache::new(
                 permissions.max_cached_fields,
             ))),
@@ -359,7 +706,13 @@ impl WasmComponentInstance {
             epoch_task,
             inplace_blocking_timeout,
             trusted_data_limits: permissions.trusted_data_limits.clone(),
+            config_forwarding: permissions.config_forwarding.clone(),
+            memory_pool: Arc::clone(memory_pool),
             bindings: Arc::clone(&bindings).into(),
+            observer,
+            wit_version,
+            ipc_compression,
+            guest_instance,
         })
     }
 
@@ -369,10 +722,117 @@ impl WasmComponentInstance {
     }
 
     /// Lock inner store.
-    pub(crate) async fn lock_state(&self) -> LockedState {
+    ///
+    /// Fails if the instance was [poisoned](Self::poison).
+    pub(crate) async fn lock_state(&self) -> DataFusionResult<LockedState> {
+        let state = self.lock_state_unchecked().await;
+        if self.is_poisoned() {
+            return Err(DataFusionError::External(Box::new(
+                WasmUdfError::Poisoned {
+                    source: Box::new(InstancePoisoned {
+                        cancelled: self.cancelled.load(Ordering::SeqCst),
+                    }),
+                },
+            )));
+        }
+        Ok(state)
+    }
+
+    /// Lock inner store, even if the instance was [poisoned](Self::poison).
+    ///
+    /// Only use this to inspect the state, never to call into the guest.
+    pub(crate) async fn lock_state_unchecked(&self) -> LockedState {
         LockedState(Arc::clone(&self.store).lock_owned().await)
     }
 
+    /// Mark instance as unusable.
+    ///
+    /// This must be called when a call into the guest was aborted in the middle, e.g. due to a timeout, or when the
+    /// guest trapped. The guest may be in an inconsistent state afterwards, so all further calls fail.
+    pub(crate) fn poison(&self) {
+        self.cancelled.store(false, Ordering::SeqCst);
+        self.poisoned.store(true, Ordering::SeqCst);
+    }
+
+    /// [Poison](Self::poison) instance if the error is [fatal](WasmUdfError::is_fatal).
+    ///
+    /// The error is also reported to the [`SandboxObserver`](crate::SandboxObserver).
+    pub(crate) fn poison_if_fatal(&self, e: DataFusionError) -> DataFusionError {
+        self.poison_if_fatal_with_labels(e, None)
+    }
+
+    /// [Poison](Self::poison) instance if the error is [fatal](WasmUdfError::is_fatal).
+    ///
+    /// The error is also reported to the [`SandboxObserver`](crate::SandboxObserver), along with the given labels of
+    /// the call.
+    pub(crate) fn poison_if_fatal_with_labels(
+        &self,
+        e: DataFusionError,
+        labels: Option<&ContextLabels>,
+    ) -> DataFusionError {
+        self.observer.error(&e, labels);
+        if WasmUdfError::is_fatal(&e) {
+            self.poison();
+        }
+        e
+    }
+
+    /// Report finished invocation to the [`SandboxObserver`](crate::SandboxObserver).
+    pub(crate) fn invocation_finished(
+        &self,
+        labels: &ContextLabels,
+        rows: usize,
+        duration: Duration,
+    ) {
+        self.observer.invocation_finished(labels, rows, duration);
+    }
+
+    /// [Poison](Self::poison) instance and abort running calls.
+    ///
+    /// Bumping the epoch makes the guest run into its epoch deadline right away, even if it never yields.
+    pub(crate) fn interrupt(&self) {
+        self.poison();
+        self.interrupted.store(true, Ordering::SeqCst);
+        self.engine.increment_epoch();
+    }
+
+    /// [Interrupt](Self::interrupt) instance because the caller cancelled a running call.
+    ///
+    /// Timeouts also drop the running call, but [poison](Self::poison) the instance afterwards, which takes
+    /// precedence.
+    fn cancel(&self) {
+        self.interrupt();
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// [Poison](Self::poison) instance if a call was [cancelled](Self::cancel).
+    ///
+    /// This is used for timeouts: the timed-out call is dropped and hence cancelled, but the instance should be
+    /// reported as timed out.
+    pub(crate) fn poison_if_cancelled(&self) {
+        if self.cancelled.load(Ordering::SeqCst) {
+            self.poison();
+        }
+    }
+
+    /// Create guard that [cancels](Self::cancel) the instance if it is dropped before it is
+    /// [disarmed](CancelGuard::disarm).
+    ///
+    /// Hold it during calls into the guest: if the caller drops the call future, the guest is left in the middle of
+    /// the call. Interrupting it right away makes it trap at the next epoch check instead of running until its next
+    /// yield, and poisons the instance so that it is [recycled](WasmPermissions::with_max_recycles).
+    pub(crate) fn cancel_guard(&self) -> CancelGuard<'_> {
+        CancelGuard {
+            instance: self,
+            armed: true,
+        }
+    }
+
+    /// Returns `true` if the instance was [poisoned](Self::poison).
+    pub(crate) fn is_poisoned(&self) -> bool {
+        self.poisoned.load(Ordering::SeqCst)
+    }
+
     /// Resource cache for [`Field`].
     pub(crate) async fn cache_field(&self) -> OwnedMutexGuard<ResourceCache<Field, ResourceAny>> {
         Arc::clone(&self.cache_field).lock_owned().await
@@ -385,6 +845,21 @@ impl WasmComponentInstance {
         Arc::clone(&self.cache_config_options).lock_owned().await
     }
 
+    /// WIT version that was negotiated with the guest.
+    pub(crate) fn wit_version(&self) -> WitVersion {
+        self.wit_version
+    }
+
+    /// IPC compression that was negotiated with the guest.
+    pub(crate) fn ipc_compression(&self) -> Option<IpcCompression> {
+        self.ipc_compression
+    }
+
+    /// Guest-side instance resource that source code is loaded into.
+    pub(crate) fn guest_instance(&self) -> ResourceAny {
+        self.guest_instance
+    }
+
     /// Timeout for blocking tasks.
     pub(crate) fn inplace_blocking_timeout(&self) -> Duration {
         self.inplace_blocking_timeout
@@ -394,6 +869,51 @@ impl WasmComponentInstance {
     pub(crate) fn trusted_data_limits(&self) -> &TrustedDataLimits {
         &self.trusted_data_limits
     }
+
+    /// Which [`ConfigOptions`] entries are forwarded to the guest.
+    pub(crate) fn config_forwarding(&self) -> &ConfigForwarding {
+        &self.config_forwarding
+    }
+
+    /// Reserve memory for host-side buffers, e.g. the serialized data that is exchanged with the guest.
+    pub(crate) fn reserve_buffers(&self, bytes: usize) -> DataFusionResult<MemoryReservation> {
+        let mut reservation =
+            MemoryConsumer::new("WASM UDF conversion buffers").register(&self.memory_pool);
+        reservation.try_grow(bytes)?;
+        Ok(reservation)
+    }
+}
+
+/// Interrupts the guest if a call is cancelled, see [`WasmComponentInstance::cancel_guard`].
+#[derive(Debug)]
+pub(crate) struct CancelGuard<'a> {
+    /// Instance that is called.
+    instance: &'a WasmComponentInstance,
+
+    /// Interrupt on drop.
+    armed: bool,
+}
+
+impl CancelGuard<'_> {
+    /// Call finished, do NOT interrupt the instance.
+    pub(crate) fn disarm(mut self) {
+        self.armed = false;
+    }
+}
+
+impl Drop for CancelGuard<'_> {
+    fn drop(&mut self) {
+        if self.armed {
+            log::debug!("call into guest was cancelled, interrupt guest");
+            self.instance.cancel();
+        }
+    }
+}
+
+impl Drop for WasmComponentInstance {
+    fn drop(&mut self) {
+        self.observer.instance_destroyed();
+    }
 }
 
 /// Locked state.
@@ -0,0 +1,74 @@
+//! Session-level settings for WASM UDFs.
+
+use datafusion_common::{config::ConfigExtension, extensions_options};
+
+extensions_options! {
+    /// Settings for WASM UDFs that can be changed per session.
+    ///
+    /// Register this via [`SessionConfig::with_option_extension`] to change the settings via SQL, e.g.:
+    ///
+    /// ```sql
+    /// SET udf_wasm.max_batch_rows = 1000;
+    /// ```
+    ///
+    /// Note that DataFusion reserves the `datafusion` prefix for its built-in options, hence these settings live
+    /// under [`udf_wasm`](Self::PREFIX).
+    ///
+    /// If the extension is NOT registered, the [defaults](Default) are used.
+    ///
+    ///
+    /// [`SessionConfig::with_option_extension`]: https://docs.rs/datafusion/latest/datafusion/prelude/struct.SessionConfig.html#method.with_option_extension
+    pub struct WasmUdfConfig {
+        /// Allow queries to define WASM UDFs.
+        pub enabled: bool, default = true
+
+        /// Timeout of a single guest invocation in milliseconds, `0` disables the timeout.
+        ///
+        /// The guest can only be interrupted on an epoch tick, so the actual timeout may be slightly longer.
+        pub invocation_timeout_ms: u64, default = 0
+
+        /// Maximum number of rows that are passed to the guest in a single invocation, `0` disables the cap.
+        ///
+        /// Larger batches are split and the results are concatenated.
+        pub max_batch_rows: usize, default = 0
+
+        /// Evict cached resources that are no longer referenced after every invocation instead of only when the
+        /// cache is full.
+        pub eager_cache_cleanup: bool, default = false
+
+        /// Comma-separated `key=value` labels -- e.g. `query_id=42,tenant=acme` -- that are attached to errors and
+        /// observer events of invocations, see [`ContextLabels`](crate::ContextLabels).
+        pub context_labels: String, default = String::new()
+    }
+}
+
+impl ConfigExtension for WasmUdfConfig {
+    const PREFIX: &'static str = "udf_wasm";
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion_common::config::ConfigOptions;
+
+    use super::*;
+
+    #[test]
+    fn test_set() {
+        let mut options = ConfigOptions::new();
+        options.extensions.insert(WasmUdfConfig::default());
+        options.set("udf_wasm.max_batch_rows", "10").unwrap();
+        options.set("udf_wasm.enabled", "false").unwrap();
+
+        let config = options.extensions.get::<WasmUdfConfig>().unwrap();
+        assert!(!config.enabled);
+        assert_eq!(config.invocation_timeout_ms, 0);
+        assert_eq!(config.max_batch_rows, 10);
+        assert!(!config.eager_cache_cleanup);
+        assert_eq!(config.context_labels, "");
+
+        insta::assert_snapshot!(
+            options.set("udf_wasm.foo", "1").unwrap_err(),
+            @"Invalid or Unsupported Configuration: Config value \"foo\" not found on WasmUdfConfig",
+        );
+    }
+}
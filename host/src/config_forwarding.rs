@@ -0,0 +1,203 @@
+//! Forwarding of [`ConfigOptions`] to the guest.
+
+use datafusion_common::{
+    DataFusionError, config::ConfigOptions, error::Result as DataFusionResult,
+};
+
+/// Limits for [`ConfigOptions`] that are forwarded to the guest.
+///
+/// These are checked after the [allowlist](crate::WasmPermissions::with_config_allowlist) was applied. Exceeding them
+/// results in an error instead of silently dropping entries, since the guest would otherwise observe different
+/// settings than the user configured.
+#[derive(Debug, Clone)]
+#[expect(missing_copy_implementations, reason = "allow later extensions")]
+pub struct ConfigLimits {
+    /// Maximum number of forwarded entries.
+    pub max_entries: usize,
+
+    /// Maximum size of a single value, in bytes.
+    pub max_value_bytes: usize,
+
+    /// Maximum total size of all forwarded keys and values, in bytes.
+    pub max_total_bytes: usize,
+}
+
+impl Default for ConfigLimits {
+    fn default() -> Self {
+        Self {
+            max_entries: 1_000,
+            max_value_bytes: 1_024,
+            max_total_bytes: 100_000,
+        }
+    }
+}
+
+/// Decides which [`ConfigOptions`] entries are forwarded to the guest.
+#[derive(Debug, Clone)]
+pub(crate) struct ConfigForwarding {
+    /// Allowed keys, see [`WasmPermissions::with_config_allowlist`](crate::WasmPermissions::with_config_allowlist).
+    pub(crate) allowlist: Vec<String>,
+
+    /// Limits.
+    pub(crate) limits: ConfigLimits,
+}
+
+impl Default for ConfigForwarding {
+    fn default() -> Self {
+        Self {
+            allowlist: vec!["datafusion.*".to_owned()],
+            limits: ConfigLimits::default(),
+        }
+    }
+}
+
+impl ConfigForwarding {
+    /// Returns `true` if the key matches any pattern of the allowlist.
+    fn is_allowed(&self, key: &str) -> bool {
+        self.allowlist
+            .iter()
+            .any(|pattern| match pattern.strip_suffix('*') {
+                Some(prefix) => key.starts_with(prefix),
+                None => key == pattern,
+            })
+    }
+
+    /// Select entries that are forwarded to the guest.
+    ///
+    /// Entries without a value are skipped.
+    pub(crate) fn filter(
+        &self,
+        options: &ConfigOptions,
+    ) -> DataFusionResult<Vec<(String, String)>> {
+        let mut total_bytes = 0;
+        let mut settings = vec![];
+
+        for e in options.entries() {
+            // the guest does not know about config extensions like `WasmUdfConfig`
+            if !e.key.starts_with("datafusion.") || !self.is_allowed(&e.key) {
+                continue;
+            }
+            let Some(v) = e.value else {
+                continue;
+            };
+
+            if v.len() > self.limits.max_value_bytes {
+                return Err(DataFusionError::ResourcesExhausted(format!(
+                    "config value of `{}` too large: got={}, limit={}",
+                    e.key,
+                    v.len(),
+                    self.limits.max_value_bytes,
+                )));
+            }
+            total_bytes += e.key.len() + v.len();
+            settings.push((e.key, v));
+        }
+
+        if settings.len() > self.limits.max_entries {
+            return Err(DataFusionError::ResourcesExhausted(format!(
+                "too many config entries: got={}, limit={}",
+                settings.len(),
+                self.limits.max_entries,
+            )));
+        }
+        if total_bytes > self.limits.max_total_bytes {
+            return Err(DataFusionError::ResourcesExhausted(format!(
+                "config entries too large: got={total_bytes}, limit={}",
+                self.limits.max_total_bytes,
+            )));
+        }
+
+        Ok(settings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::WasmUdfConfig;
+
+    use super::*;
+
+    #[test]
+    fn test_default_skips_extensions() {
+        let mut options = ConfigOptions::new();
+        options.extensions.insert(WasmUdfConfig::default());
+
+        let settings = ConfigForwarding::default().filter(&options).unwrap();
+        assert!(!settings.is_empty());
+        assert!(settings.iter().all(|(k, _v)| k.starts_with("datafusion.")));
+    }
+
+    #[test]
+    fn test_allowlist() {
+        let mut options = ConfigOptions::new();
+        options
+            .set("datafusion.execution.time_zone", "+01:00")
+            .unwrap();
+
+        let forwarding = ConfigForwarding {
+            allowlist: vec![
+                "datafusion.execution.time_zone".to_owned(),
+                "datafusion.sql_parser.*".to_owned(),
+                // extensions are never forwarded
+                "udf_wasm.*".to_owned(),
+            ],
+            limits: ConfigLimits::default(),
+        };
+        let settings = forwarding.filter(&options).unwrap();
+        assert!(settings.contains(&(
+            "datafusion.execution.time_zone".to_owned(),
+            "+01:00".to_owned()
+        )));
+        assert!(settings.iter().all(|(k, _v)| {
+            k == "datafusion.execution.time_zone" || k.starts_with("datafusion.sql_parser.")
+        }));
+
+        let forwarding = ConfigForwarding {
+            allowlist: vec![],
+            limits: ConfigLimits::default(),
+        };
+        assert_eq!(forwarding.filter(&options).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn test_limits() {
+        let mut options = ConfigOptions::new();
+        options
+            .set("datafusion.execution.time_zone", "+01:00")
+            .unwrap();
+
+        let forwarding = ConfigForwarding {
+            allowlist: vec!["datafusion.execution.time_zone".to_owned()],
+            limits: ConfigLimits {
+                max_value_bytes: 5,
+                ..Default::default()
+            },
+        };
+        insta::assert_snapshot!(
+            forwarding.filter(&options).unwrap_err(),
+            @"Resources exhausted: config value of `datafusion.execution.time_zone` too large: got=6, limit=5",
+        );
+
+        let forwarding = ConfigForwarding {
+            allowlist: vec!["datafusion.execution.*".to_owned()],
+            limits: ConfigLimits {
+                max_entries: 1,
+                ..Default::default()
+            },
+        };
+        let err = forwarding.filter(&options).unwrap_err().to_string();
+        assert!(err.contains("too many config entries"), "{err}");
+
+        let forwarding = ConfigForwarding {
+            allowlist: vec!["datafusion.execution.time_zone".to_owned()],
+            limits: ConfigLimits {
+                max_total_bytes: 10,
+                ..Default::default()
+            },
+        };
+        insta::assert_snapshot!(
+            forwarding.filter(&options).unwrap_err(),
+            @"Resources exhausted: config entries too large: got=36, limit=10",
+        );
+    }
+}
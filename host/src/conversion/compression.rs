@@ -0,0 +1,79 @@
+//! Compression of IPC payloads that cross the sandbox boundary.
+use datafusion_udf_wasm_arrow2bytes::Compression;
+
+use crate::bindings::exports::datafusion_udf_wasm::udf::types as wit_types;
+
+/// Compression codec for the arrow IPC payloads that are exchanged with the guest.
+///
+/// See [`WasmPermissions::with_ipc_compression`](crate::WasmPermissions::with_ipc_compression).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IpcCompression {
+    /// [LZ4 frame format](https://github.com/lz4/lz4/blob/dev/doc/lz4_Frame_format.md).
+    ///
+    /// Fast with a moderate compression ratio.
+    Lz4Frame,
+
+    /// [Zstandard](https://facebook.github.io/zstd/).
+    ///
+    /// Slower than [LZ4](Self::Lz4Frame) but with a better compression ratio.
+    Zstd,
+}
+
+impl IpcCompression {
+    /// Pick the first of the `preferred` codecs that the guest supports.
+    pub(crate) fn negotiate(
+        preferred: &[Self],
+        guest: &[wit_types::IpcCompression],
+    ) -> Option<Self> {
+        preferred
+            .iter()
+            .copied()
+            .find(|codec| guest.contains(&(*codec).into()))
+    }
+}
+
+impl From<IpcCompression> for Compression {
+    fn from(value: IpcCompression) -> Self {
+        match value {
+            IpcCompression::Lz4Frame => Self::Lz4Frame,
+            IpcCompression::Zstd => Self::Zstd,
+        }
+    }
+}
+
+impl From<IpcCompression> for wit_types::IpcCompression {
+    fn from(value: IpcCompression) -> Self {
+        match value {
+            IpcCompression::Lz4Frame => Self::Lz4Frame,
+            IpcCompression::Zstd => Self::Zstd,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_negotiate() {
+        use IpcCompression::*;
+
+        let guest = [wit_types::IpcCompression::Lz4Frame];
+        assert_eq!(IpcCompression::negotiate(&[], &guest), None);
+        assert_eq!(IpcCompression::negotiate(&[Zstd], &guest), None);
+        assert_eq!(
+            IpcCompression::negotiate(&[Zstd, Lz4Frame], &guest),
+            Some(Lz4Frame),
+        );
+        assert_eq!(IpcCompression::negotiate(&[Lz4Frame], &[]), None);
+
+        let guest = [
+            wit_types::IpcCompression::Lz4Frame,
+            wit_types::IpcCompression::Zstd,
+        ];
+        assert_eq!(
+            IpcCompression::negotiate(&[Zstd, Lz4Frame], &guest),
+            Some(Zstd),
+        );
+    }
+}
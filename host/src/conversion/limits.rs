@@ -3,6 +3,8 @@ use std::{cell::RefCell, rc::Rc};
 
 use datafusion_common::{DataFusionError, error::Result as DataFusionResult};
 
+use crate::conversion::sanitize::{contains_unsafe_chars, sanitize_owned};
+
 /// Limits that should be applied during conversion from untrusted to trusted data.
 #[derive(Debug, Clone)]
 #[expect(missing_copy_implementations, reason = "allow later extensions")]
@@ -18,6 +20,18 @@ pub struct TrustedDataLimits {
     /// like string arrays.
     pub max_aux_string_length: usize,
 
+    /// Maximum size of a serialized array returned by the guest, in bytes.
+    ///
+    /// This is checked BEFORE the array is decoded. If an [`IpcCompression`](crate::IpcCompression) was negotiated,
+    /// this also applies to the uncompressed size, so this always limits the memory required by the decoded array.
+    pub max_array_bytes: usize,
+
+    /// Maximum length of individual string and binary values in arrays returned by the guest, in bytes.
+    ///
+    /// This applies to all string and binary layouts, also when nested in lists, structs, maps, or dictionaries. The
+    /// decoder checks the offsets and views of the array; string values are always validated to be UTF-8.
+    pub max_array_value_length: usize,
+
     /// Maximum data structure depth.
     ///
     /// # Data Structures
@@ -101,6 +115,35 @@ pub struct TrustedDataLimits {
     ///           o
     /// ```
     pub max_complexity: u64,
+
+    /// Maximum number of context entries of an error returned by the guest.
+    ///
+    /// The outermost entries are kept, the remaining ones are replaced by a single marker entry.
+    pub max_error_context_depth: usize,
+
+    /// Maximum accumulated size of an error returned by the guest, in bytes.
+    ///
+    /// This covers the error message and all context entries. The message itself is always kept (it is limited by
+    /// [`max_aux_string_length`](Self::max_aux_string_length)), but context entries that would exceed this size are
+    /// replaced by a single marker entry.
+    pub max_error_size: usize,
+
+    /// Maximum number of traceback frames of an error returned by the guest.
+    ///
+    /// The innermost frames are kept. Frames also count towards [`max_error_size`](Self::max_error_size).
+    pub max_traceback_frames: usize,
+
+    /// Sanitize strings that may end up in logs or may be shown to SQL clients.
+    ///
+    /// If enabled:
+    ///
+    /// - identifiers (like UDF names) that contain control characters or Unicode bidirectional formatting characters
+    ///   are rejected
+    /// - ANSI escape sequences, control characters (except for line feeds and tabs), and Unicode bidirectional
+    ///   formatting characters are removed from error messages and stderr output
+    ///
+    /// This prevents log injection and terminal spoofing by malicious guests.
+    pub sanitize_strings: bool,
 }
 
 impl Default for TrustedDataLimits {
@@ -108,8 +151,14 @@ impl Default for TrustedDataLimits {
         Self {
             max_identifier_length: 50,
             max_aux_string_length: 10_000,
+            max_array_bytes: 256 * 1024 * 1024,
+            max_array_value_length: 16 * 1024 * 1024,
             max_depth: 10,
             max_complexity: 100,
+            max_error_context_depth: 8,
+            max_error_size: 20_000,
+            max_traceback_frames: 50,
+            sanitize_strings: true,
         }
     }
 }
@@ -121,6 +170,9 @@ struct ComplexityCounter {
 
     /// Current complexity.
     current_complexity: u64,
+
+    /// Accept compressed arrays.
+    allow_compression: bool,
 }
 
 /// A token to count complexity of untrusted data.
@@ -172,6 +224,7 @@ impl ComplexityToken {
         let counter = Rc::new(RefCell::new(ComplexityCounter {
             limits,
             current_complexity: 0,
+            allow_compression: false,
         }));
         Self::new_inner(counter, 0)
     }
@@ -186,14 +239,42 @@ impl ComplexityToken {
         drop(self);
     }
 
-    /// Check identifier using [`TrustedDataLimits::max_identifier_length`].
+    /// Accept compressed arrays, i.e. an [`IpcCompression`] was negotiated with the guest.
+    ///
+    /// This applies to the entire data structure, not only to this token. The decoded size of compressed arrays is
+    /// limited by [`TrustedDataLimits::max_array_bytes`].
+    ///
+    ///
+    /// [`IpcCompression`]: crate::IpcCompression
+    pub(crate) fn with_compression(self, allow: bool) -> Self {
+        self.counter.borrow_mut().allow_compression = allow;
+        self
+    }
+
+    /// Compressed arrays are accepted, see [`with_compression`](Self::with_compression).
+    pub(crate) fn allow_compression(&self) -> bool {
+        self.counter.borrow().allow_compression
+    }
+
+    /// Limits that this token checks.
+    pub(crate) fn limits(&self) -> TrustedDataLimits {
+        self.counter.borrow().limits.clone()
+    }
+
+    /// Check identifier using [`TrustedDataLimits::max_identifier_length`] and
+    /// [`TrustedDataLimits::sanitize_strings`].
     pub(crate) fn check_identifier(&self, id: &str) -> DataFusionResult<()> {
         let len = id.len();
-        let limit = self.counter.borrow().limits.max_identifier_length;
+        let counter = self.counter.borrow();
+        let limit = counter.limits.max_identifier_length;
         if len > limit {
             Err(DataFusionError::ResourcesExhausted(format!(
                 "identifier length: got={len}, limit={limit}"
             )))
+        } else if counter.limits.sanitize_strings && contains_unsafe_chars(id) {
+            Err(DataFusionError::External(
+                format!("identifier contains unsafe characters: {id:?}").into(),
+            ))
         } else {
             Ok(())
         }
@@ -213,6 +294,41 @@ impl ComplexityToken {
             Ok(())
         }
     }
+
+    /// Check size of serialized array using [`TrustedDataLimits::max_array_bytes`].
+    pub(crate) fn check_array_bytes(&self, len: usize) -> DataFusionResult<()> {
+        let limit = self.counter.borrow().limits.max_array_bytes;
+        if len > limit {
+            Err(DataFusionError::ResourcesExhausted(format!(
+                "array size: got={len}, limit={limit}"
+            )))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Check length of a string or binary value in an array using [`TrustedDataLimits::max_array_value_length`].
+    pub(crate) fn check_array_value_length(&self, len: usize) -> DataFusionResult<()> {
+        let limit = self.counter.borrow().limits.max_array_value_length;
+        if len > limit {
+            Err(DataFusionError::ResourcesExhausted(format!(
+                "array value length: got={len}, limit={limit}"
+            )))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Sanitize string in error messages using [`TrustedDataLimits::sanitize_strings`].
+    ///
+    /// Use [`check_aux_string`](Self::check_aux_string) to check the length first.
+    pub(crate) fn sanitize_aux_string(&self, s: String) -> String {
+        if self.counter.borrow().limits.sanitize_strings {
+            sanitize_owned(s)
+        } else {
+            s
+        }
+    }
 }
 
 /// A conversion from untrusted to trusted data.
@@ -1,15 +1,25 @@
 //! Conversion routes from/to [WIT types](crate::bindings).
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
 
 use arrow::{
-    array::ArrayRef,
-    datatypes::{DataType, Field, IntervalUnit, TimeUnit, UnionFields, UnionMode},
+    array::{ArrayData, ArrayRef},
+    buffer::Buffer,
+    datatypes::{
+        DECIMAL32_MAX_PRECISION, DECIMAL64_MAX_PRECISION, DECIMAL128_MAX_PRECISION,
+        DECIMAL256_MAX_PRECISION, DataType, Field, IntervalUnit, TimeUnit, UnionFields, UnionMode,
+    },
 };
 use datafusion_common::{
     DataFusionError, ScalarValue, config::ConfigOptions, error::Result as DataFusionResult,
 };
 use datafusion_expr::{ColumnarValue, ScalarFunctionArgs};
-use datafusion_udf_wasm_arrow2bytes::{array2bytes, bytes2array, bytes2datatype, datatype2bytes};
+use datafusion_udf_wasm_arrow2bytes::{
+    DecodeOptions, array2bytes, array2bytes_compressed, buffer2array, bytes2datatype, checksum,
+    datatype2bytes,
+};
 use wasmtime::component::ResourceAny;
 
 use crate::{
@@ -17,51 +27,117 @@ use crate::{
     component::WasmComponentInstance,
     conversion::{
         async_from::AsyncTryFrom,
-        limits::{CheckedFrom, CheckedInto},
+        compression::IpcCompression,
+        limits::{CheckedFrom, CheckedInto, TrustedDataLimits},
         resource_cache::ResourceCacheValue,
     },
-    error::{DataFusionResultExt, WasmToDataFusionResultExt, WitDataFusionResultExt},
+    error::{
+        DataFusionResultExt, GuestTraceback, TracebackFrame, WasmToDataFusionResultExt,
+        WitDataFusionResultExt,
+    },
+    validate::SourceDiagnostic,
 };
 
 pub(crate) mod async_from;
+pub(crate) mod compression;
 pub(crate) mod limits;
 pub(crate) mod resource_cache;
+pub(crate) mod sanitize;
 
 impl CheckedFrom<wit_types::DataFusionError> for DataFusionError {
     fn checked_from(
         value: wit_types::DataFusionError,
-        mut token: limits::ComplexityToken,
+        token: limits::ComplexityToken,
     ) -> datafusion_common::Result<Self> {
         use wit_types::DataFusionErrorKind;
 
         let mut e = match value.kind {
             DataFusionErrorKind::NotImplemented(msg) => {
                 token.check_aux_string(&msg)?;
-                Self::NotImplemented(msg)
+                Self::NotImplemented(token.sanitize_aux_string(msg))
             }
             DataFusionErrorKind::Internal(msg) => {
                 token.check_aux_string(&msg)?;
-                Self::Internal(msg)
+                Self::Internal(token.sanitize_aux_string(msg))
             }
             DataFusionErrorKind::Plan(msg) => {
                 token.check_aux_string(&msg)?;
-                Self::Plan(msg)
+                Self::Plan(token.sanitize_aux_string(msg))
             }
             DataFusionErrorKind::Configuration(msg) => {
                 token.check_aux_string(&msg)?;
-                Self::Configuration(msg)
+                Self::Configuration(token.sanitize_aux_string(msg))
             }
             DataFusionErrorKind::Execution(msg) => {
                 token.check_aux_string(&msg)?;
-                Self::Execution(msg)
+                Self::Execution(token.sanitize_aux_string(msg))
             }
         };
 
-        // context chain is stored "top-level to inner-level", but we assemble the types inner-to-outer
-        for context in value.context.into_iter().rev() {
-            token = token.sub()?;
+        // context chain is stored "top-level to inner-level", so we keep the top-level entries and truncate the
+        // inner ones
+        let TrustedDataLimits {
+            max_error_context_depth,
+            max_error_size,
+            max_traceback_frames,
+            ..
+        } = token.limits();
+        let mut contexts = value.context;
+        let mut size = e.message().len();
+        let keep = contexts
+            .iter()
+            .take(max_error_context_depth)
+            .take_while(|context| {
+                size = size.saturating_add(context.len());
+                size <= max_error_size
+            })
+            .count();
+        let truncated = contexts.len() - keep;
+        contexts.truncate(keep);
+
+        // frames are stored "outer-level to inner-level", so we keep the inner frames and drop the outer ones
+        if let Some(mut frames) = value.traceback {
+            let keep = frames
+                .iter()
+                .rev()
+                .take(max_traceback_frames)
+                .take_while(|frame| {
+                    size = size
+                        .saturating_add(frame.file.len())
+                        .saturating_add(frame.function.len());
+                    size <= max_error_size
+                })
+                .count();
+            let truncated_frames = frames.len() - keep;
+            let frames = frames
+                .drain(truncated_frames..)
+                .map(|frame| {
+                    let wit_types::TracebackFrame {
+                        file,
+                        line,
+                        function,
+                    } = frame;
+                    token.check_aux_string(&file)?;
+                    token.check_aux_string(&function)?;
+                    Ok(TracebackFrame {
+                        file: token.sanitize_aux_string(file),
+                        line,
+                        function: token.sanitize_aux_string(function),
+                    })
+                })
+                .collect::<DataFusionResult<_>>()?;
+            e = GuestTraceback::wrap(e, frames, truncated_frames);
+        }
+
+        if truncated > 0 {
+            e = e.context(format!("[error context truncated: entries={truncated}]"));
+        }
+
+        // we assemble the types inner-to-outer
+        for context in contexts.into_iter().rev() {
+            let token = token.sub()?;
             token.check_aux_string(&context)?;
-            e = e.context(context);
+            e = e.context(token.sanitize_aux_string(context));
         }
 
         Ok(e)
@@ -98,6 +174,57 @@ fn check_time_unit(
     }
 }
 
+/// Check precision and scale of a decimal type.
+///
+/// Arrow rejects invalid decimal types only when data is processed, so we check them early to produce a
+/// helpful error.
+fn check_decimal(
+    dt: &DataType,
+    precision: u8,
+    scale: i8,
+    max_precision: u8,
+    token: limits::ComplexityToken,
+) -> datafusion_common::Result<()> {
+    token.no_recursion();
+
+    if precision == 0 || precision > max_precision {
+        let hint = if max_precision < DECIMAL256_MAX_PRECISION {
+            ", use a wider decimal type for higher precisions"
+        } else {
+            ""
+        };
+        return Err(DataFusionError::External(
+            format!("precision of {dt} must be between 1 and {max_precision}{hint}").into(),
+        ));
+    }
+    if scale > 0 && scale.unsigned_abs() > precision {
+        return Err(DataFusionError::External(
+            format!("scale of {dt} must not exceed the precision").into(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Check time zone string of a [`Timestamp`](DataType::Timestamp) for complexity.
+///
+/// Time zones are either IANA names like `Europe/Berlin` or fixed offsets like `+02:00`, so we only allow the
+/// characters used by these forms.
+fn check_time_zone(tz: &str, token: &limits::ComplexityToken) -> datafusion_common::Result<()> {
+    token.check_identifier(tz)?;
+    let valid = !tz.is_empty()
+        && tz
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '/' | '_' | '+' | '-' | ':'));
+    if valid {
+        Ok(())
+    } else {
+        Err(DataFusionError::External(
+            format!("invalid time zone: {tz:?}").into(),
+        ))
+    }
+}
+
 /// Check [`UnionFields`] complexity.
 fn check_union_fields(
     ufields: &UnionFields,
@@ -126,6 +253,43 @@ fn check_union_mode(
     }
 }
 
+/// Check lengths of string and binary values within [`ArrayData`], including all children.
+///
+/// This only inspects offsets and views, the values themselves are NOT touched. The depth is bounded by
+/// [`check_data_type`].
+fn check_array_values(
+    data: &ArrayData,
+    token: &limits::ComplexityToken,
+) -> datafusion_common::Result<()> {
+    if !data.is_empty() {
+        let max_len = match data.data_type() {
+            DataType::Utf8 | DataType::Binary => data.buffer::<i32>(0)[..=data.len()]
+                .windows(2)
+                .map(|w| usize::try_from(w[1].saturating_sub(w[0])).unwrap_or_default())
+                .max(),
+            DataType::LargeUtf8 | DataType::LargeBinary => data.buffer::<i64>(0)[..=data.len()]
+                .windows(2)
+                .map(|w| usize::try_from(w[1].saturating_sub(w[0])).unwrap_or_default())
+                .max(),
+            // the length is stored in the lower 32 bits of every view
+            DataType::Utf8View | DataType::BinaryView => data.buffer::<u128>(0)[..data.len()]
+                .iter()
+                .map(|view| *view as u32 as usize)
+                .max(),
+            DataType::FixedSizeBinary(size) => usize::try_from(*size).ok(),
+            _ => None,
+        };
+        if let Some(max_len) = max_len {
+            token.check_array_value_length(max_len)?;
+        }
+    }
+
+    for child in data.child_data() {
+        check_array_values(child, token)?;
+    }
+    Ok(())
+}
+
 /// Check [`DataType`] complexity.
 fn check_data_type(
     dt: &DataType,
@@ -155,18 +319,26 @@ fn check_data_type(
         | DataType::BinaryView
         | DataType::Utf8
         | DataType::LargeUtf8
-        | DataType::Utf8View
-        | DataType::Decimal32(_, _)
-        | DataType::Decimal64(_, _)
-        | DataType::Decimal128(_, _)
-        | DataType::Decimal256(_, _) => {
+        | DataType::Utf8View => {
             token.no_recursion();
             Ok(())
         }
+        DataType::Decimal32(precision, scale) => {
+            check_decimal(dt, *precision, *scale, DECIMAL32_MAX_PRECISION, token)
+        }
+        DataType::Decimal64(precision, scale) => {
+            check_decimal(dt, *precision, *scale, DECIMAL64_MAX_PRECISION, token)
+        }
+        DataType::Decimal128(precision, scale) => {
+            check_decimal(dt, *precision, *scale, DECIMAL128_MAX_PRECISION, token)
+        }
+        DataType::Decimal256(precision, scale) => {
+            check_decimal(dt, *precision, *scale, DECIMAL256_MAX_PRECISION, token)
+        }
         DataType::Timestamp(tu, tz) => {
             check_time_unit(tu, &token)?;
             if let Some(tz) = tz {
-                token.check_identifier(tz.as_ref())?;
+                check_time_zone(tz, &token).context("time zone")?;
             }
             Ok(())
         }
@@ -178,11 +350,29 @@ fn check_data_type(
         | DataType::ListView(field)
         | DataType::FixedSizeList(field, _)
         | DataType::LargeList(field)
-        | DataType::LargeListView(field)
-        | DataType::Map(field, _) => check_field(field, &token).context("field"),
+        | DataType::LargeListView(field) => check_field(field, &token).context("field"),
+        DataType::Map(field, _sorted) => {
+            let valid_entries = match field.data_type() {
+                DataType::Struct(entries) => entries.len() == 2 && !entries[0].is_nullable(),
+                _ => false,
+            };
+            if !valid_entries || field.is_nullable() {
+                return Err(DataFusionError::External(
+                    "map entries must be a non-nullable struct of a non-nullable key and a value"
+                        .into(),
+                ));
+            }
+            check_field(field, &token).context("field")
+        }
         DataType::Struct(fields) => {
+            let mut names = HashSet::with_capacity(fields.len());
             for (idx, field) in fields.iter().enumerate() {
                 check_field(field, &token).with_context(|| format!("field {idx}"))?;
+                if !names.insert(field.name()) {
+                    return Err(DataFusionError::External(
+                        format!("duplicate struct field name: {:?}", field.name()).into(),
+                    ));
+                }
             }
             Ok(())
         }
@@ -266,25 +456,104 @@ impl ResourceCacheValue<Field> for ResourceAny {
                 .collect(),
         };
 
-        let mut state = ctx.lock_state().await;
+        let mut state = ctx.lock_state().await?;
         ctx.bindings()
             .datafusion_udf_wasm_udf_types()
             .field()
             .call_new(&mut state, &args)
             .await
-            .context(
-                "cannot create Field resource",
-                Some(&state.stderr.contents()),
-            )?
-            .convert_err(ctx.trusted_data_limits().clone())
+            .context("cannot create Field resource", Some(&state.stderr))?
+            .convert_err(ctx.trusted_data_limits().clone(), &state.stderr)
     }
 
     async fn clean(self, ctx: &Self::Context) -> DataFusionResult<()> {
-        let mut state = ctx.lock_state().await;
+        let mut state = ctx.lock_state().await?;
 
         self.resource_drop_async(&mut state)
             .await
-            .context("cannot free Field resource", Some(&state.stderr.contents()))
+            .context("cannot free Field resource", Some(&state.stderr))
+    }
+}
+
+impl CheckedFrom<Vec<wit_types::Diagnostic>> for Vec<SourceDiagnostic> {
+    fn checked_from(
+        value: Vec<wit_types::Diagnostic>,
+        token: limits::ComplexityToken,
+    ) -> datafusion_common::Result<Self> {
+        value
+            .into_iter()
+            .enumerate()
+            .map(|(idx, diagnostic)| {
+                diagnostic
+                    .checked_into(&token)
+                    .with_context(|| format!("diagnostic {idx}"))
+            })
+            .collect()
+    }
+}
+
+impl CheckedFrom<wit_types::Diagnostic> for SourceDiagnostic {
+    fn checked_from(
+        value: wit_types::Diagnostic,
+        token: limits::ComplexityToken,
+    ) -> datafusion_common::Result<Self> {
+        let wit_types::Diagnostic {
+            message,
+            line,
+            column,
+        } = value;
+        token.check_aux_string(&message)?;
+        let message = token.sanitize_aux_string(message);
+        token.no_recursion();
+        Ok(Self {
+            message,
+            line,
+            column,
+        })
+    }
+}
+
+/// Return types for specific argument types.
+pub(crate) type ReturnTypeTable = Vec<(Vec<DataType>, DataType)>;
+
+impl CheckedFrom<Vec<wit_types::ReturnTypeEntry>> for ReturnTypeTable {
+    fn checked_from(
+        value: Vec<wit_types::ReturnTypeEntry>,
+        token: limits::ComplexityToken,
+    ) -> datafusion_common::Result<Self> {
+        value
+            .into_iter()
+            .enumerate()
+            .map(|(idx, entry)| {
+                entry
+                    .checked_into(&token)
+                    .with_context(|| format!("entry {idx}"))
+            })
+            .collect()
+    }
+}
+
+impl CheckedFrom<wit_types::ReturnTypeEntry> for (Vec<DataType>, DataType) {
+    fn checked_from(
+        value: wit_types::ReturnTypeEntry,
+        token: limits::ComplexityToken,
+    ) -> datafusion_common::Result<Self> {
+        let wit_types::ReturnTypeEntry {
+            arg_types,
+            return_type,
+        } = value;
+
+        let arg_types = arg_types
+            .into_iter()
+            .enumerate()
+            .map(|(idx, dt)| {
+                dt.checked_into(&token)
+                    .with_context(|| format!("argument type {idx}"))
+            })
+            .collect::<Result<_, _>>()?;
+        let return_type = return_type.checked_into(&token).context("return type")?;
+
+        Ok((arg_types, return_type))
     }
 }
 
@@ -358,6 +627,25 @@ impl CheckedFrom<wit_types::TypeSignature> for datafusion_expr::TypeSignature {
             TypeSignature::Numeric(n) => Self::Numeric(n as usize),
             TypeSignature::String(n) => Self::String(n as usize),
             TypeSignature::Nullary => Self::Nullary,
+            TypeSignature::OneOfExact(signatures) => Self::OneOf(
+                signatures
+                    .into_iter()
+                    .enumerate()
+                    .map(|(idx, data_types)| {
+                        let data_types = data_types
+                            .into_iter()
+                            .enumerate()
+                            .map(|(idx, dt)| {
+                                dt.checked_into(&token)
+                                    .with_context(|| format!("child {idx}"))
+                            })
+                            .collect::<Result<_, _>>()
+                            .with_context(|| format!("signature {idx}"))?;
+                        Ok(Self::Exact(data_types))
+                    })
+                    .collect::<Result<_, DataFusionError>>()
+                    .context("one-of signature")?,
+            ),
         })
     }
 }
@@ -417,6 +705,37 @@ impl From<ArrayRef> for wit_types::Array {
     fn from(value: ArrayRef) -> Self {
         Self {
             arrow_ipc_batch: array2bytes(value),
+            checksum: None,
+        }
+    }
+}
+
+impl wit_types::Array {
+    /// Serialize array using the given [`IpcCompression`].
+    fn compressed(array: ArrayRef, compression: IpcCompression) -> DataFusionResult<Self> {
+        Ok(Self {
+            arrow_ipc_batch: array2bytes_compressed(array, compression.into())?,
+            checksum: None,
+        })
+    }
+
+    /// Verify [checksum] of the serialized data that was attached by the guest.
+    pub(crate) fn verify_checksum(&self) -> DataFusionResult<()> {
+        let Some(expected) = self.checksum else {
+            return Err(DataFusionError::External(
+                "guest did not provide a result checksum".into(),
+            ));
+        };
+        let actual = checksum(&self.arrow_ipc_batch);
+        if actual == expected {
+            Ok(())
+        } else {
+            Err(DataFusionError::External(
+                format!(
+                    "result checksum mismatch: expected={expected:#018x}, actual={actual:#018x}"
+                )
+                .into(),
+            ))
         }
     }
 }
@@ -426,9 +745,17 @@ impl CheckedFrom<wit_types::Array> for ArrayRef {
         value: wit_types::Array,
         token: limits::ComplexityToken,
     ) -> datafusion_common::Result<Self> {
-        let array = bytes2array(&value.arrow_ipc_batch)?;
+        token.check_array_bytes(value.arrow_ipc_batch.len())?;
+        // the array references the serialized data instead of copying it, unless it is compressed
+        let options = DecodeOptions {
+            max_decoded_bytes: Some(token.limits().max_array_bytes),
+            allow_compression: token.allow_compression(),
+            ..Default::default()
+        };
+        let array = buffer2array(Buffer::from(value.arrow_ipc_batch), &options)?;
         // we assume that the array data and the attached data type are in-sync, so we only gonna check the data type
         check_data_type(array.data_type(), &token)?;
+        check_array_values(&array.to_data(), &token)?;
         Ok(array)
     }
 }
@@ -471,6 +798,38 @@ impl TryFrom<ColumnarValue> for wit_types::ColumnarValue {
     }
 }
 
+impl wit_types::ColumnarValue {
+    /// Convert value and compress arrays using the given [`IpcCompression`].
+    ///
+    /// Scalars are not compressed since they only contain a single value.
+    fn compressed(value: ColumnarValue, compression: IpcCompression) -> DataFusionResult<Self> {
+        Ok(match value {
+            ColumnarValue::Array(array) => {
+                Self::Array(wit_types::Array::compressed(array, compression)?)
+            }
+            ColumnarValue::Scalar(scalar) => Self::Scalar(scalar.try_into()?),
+        })
+    }
+
+    /// Verify [checksum] of the serialized data that was attached by the guest.
+    pub(crate) fn verify_checksum(&self) -> DataFusionResult<()> {
+        match self {
+            Self::Array(array) => array.verify_checksum(),
+            Self::Scalar(scalar) => scalar.array.verify_checksum(),
+        }
+    }
+}
+
+impl wit_types::ColumnarValue {
+    /// Size of the serialized data, in bytes.
+    pub(crate) fn ipc_bytes(&self) -> usize {
+        match self {
+            Self::Array(array) => array.arrow_ipc_batch.len(),
+            Self::Scalar(scalar) => scalar.array.arrow_ipc_batch.len(),
+        }
+    }
+}
+
 impl CheckedFrom<wit_types::ColumnarValue> for ColumnarValue {
     fn checked_from(
         value: wit_types::ColumnarValue,
@@ -493,36 +852,27 @@ impl ResourceCacheValue<ConfigOptions> for ResourceAny {
     type Context = Arc<WasmComponentInstance>;
 
     async fn new(k: &Arc<ConfigOptions>, ctx: &Self::Context) -> DataFusionResult<Self> {
-        let settings = k
-            .entries()
-            .into_iter()
-            .filter_map(|e| {
-                let k = e.key;
-                let v = e.value?;
-                Some((k, v))
-            })
-            .collect::<Vec<_>>();
+        let settings = ctx
+            .config_forwarding()
+            .filter(k)
+            .context("forward ConfigOptions")?;
 
-        let mut state = ctx.lock_state().await;
+        let mut state = ctx.lock_state().await?;
         ctx.bindings()
             .datafusion_udf_wasm_udf_types()
             .config_options()
             .call_from_string_hash_map(&mut state, &settings)
             .await
-            .context(
-                "cannot create ConfigOptions resource",
-                Some(&state.stderr.contents()),
-            )?
-            .convert_err(ctx.trusted_data_limits().clone())
+            .context("cannot create ConfigOptions resource", Some(&state.stderr))?
+            .convert_err(ctx.trusted_data_limits().clone(), &state.stderr)
     }
 
     async fn clean(self, ctx: &Self::Context) -> DataFusionResult<()> {
-        let mut state = ctx.lock_state().await;
+        let mut state = ctx.lock_state().await?;
 
-        self.resource_drop_async(&mut state).await.context(
-            "cannot free ConfigOptions resource",
-            Some(&state.stderr.contents()),
-        )
+        self.resource_drop_async(&mut state)
+            .await
+            .context("cannot free ConfigOptions resource", Some(&state.stderr))
     }
 }
 
@@ -543,11 +893,16 @@ impl AsyncTryFrom<(ScalarFunctionArgs, &Arc<WasmComponentInstance>)>
             arg_fields.push(cache_field.cache(&f, instance).await?);
         }
 
+        let ipc_compression = instance.ipc_compression();
+
         Ok(Self {
             args: value
                 .args
                 .into_iter()
-                .map(TryFrom::try_from)
+                .map(|arg| match ipc_compression {
+                    Some(compression) => wit_types::ColumnarValue::compressed(arg, compression),
+                    None => arg.try_into(),
+                })
                 .collect::<Result<_, _>>()?,
             arg_fields,
             number_rows: value.number_rows as u64,
@@ -555,6 +910,183 @@ impl AsyncTryFrom<(ScalarFunctionArgs, &Arc<WasmComponentInstance>)>
             config_options: cache_config_options
                 .cache(&value.config_options, instance)
                 .await?,
+            result_checksum: false,
+            ipc_compression: ipc_compression.map(From::from),
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use arrow::{
+        array::{
+            DictionaryArray, FixedSizeBinaryArray, Int64Array, LargeBinaryArray, StringArray,
+            StringViewArray, StructArray,
+        },
+        datatypes::{Fields, Int32Type},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_check_struct() {
+        let limits = TrustedDataLimits::default();
+        let check = |dt: &DataType| {
+            let token = limits::ComplexityToken::new(limits.clone()).unwrap();
+            check_data_type(dt, &token)
+        };
+
+        let ok = DataType::Struct(Fields::from(vec![
+            Field::new("browser", DataType::Utf8, false),
+            Field::new("os", DataType::Utf8, true),
+        ]));
+        check(&ok).unwrap();
+
+        let duplicate = DataType::Struct(Fields::from(vec![
+            Field::new("os", DataType::Utf8, false),
+            Field::new("os", DataType::Utf8, true),
+        ]));
+        insta::assert_snapshot!(
+            check(&duplicate).unwrap_err(),
+            @r#"External error: duplicate struct field name: "os""#,
+        );
+
+        let mut nested = DataType::Int64;
+        for _ in 0..limits.max_depth {
+            nested = DataType::Struct(Fields::from(vec![Field::new("x", nested, false)]));
+        }
+        assert!(matches!(
+            check(&nested).unwrap_err().find_root(),
+            DataFusionError::ResourcesExhausted(_),
+        ));
+    }
+
+    #[test]
+    fn test_check_map() {
+        let check = |dt: &DataType| {
+            let token = limits::ComplexityToken::new(TrustedDataLimits::default()).unwrap();
+            check_data_type(dt, &token)
+        };
+        let map = |entries: DataType, nullable: bool| {
+            DataType::Map(Arc::new(Field::new("entries", entries, nullable)), false)
+        };
+        let entries = |key_nullable: bool| {
+            DataType::Struct(Fields::from(vec![
+                Field::new("key", DataType::Utf8, key_nullable),
+                Field::new("value", DataType::Int64, true),
+            ]))
+        };
+
+        check(&map(entries(false), false)).unwrap();
+
+        for invalid in [
+            map(entries(true), false),
+            map(entries(false), true),
+            map(DataType::Int64, false),
+            map(
+                DataType::Struct(Fields::from(vec![Field::new("key", DataType::Utf8, false)])),
+                false,
+            ),
+        ] {
+            insta::assert_snapshot!(
+                check(&invalid).unwrap_err(),
+                @"External error: map entries must be a non-nullable struct of a non-nullable key and a value",
+            );
+        }
+    }
+
+    #[test]
+    fn test_check_array_values() {
+        let check = |array: ArrayRef| {
+            let token = limits::ComplexityToken::new(TrustedDataLimits {
+                max_array_value_length: 3,
+                ..Default::default()
+            })
+            .unwrap();
+            check_array_values(&array.to_data(), &token)
+        };
+
+        check(Arc::new(StringArray::from(vec![
+            Some("foo"),
+            None,
+            Some(""),
+        ])))
+        .unwrap();
+        check(Arc::new(StringArray::from(vec!["foobar"]).slice(1, 0))).unwrap();
+        check(Arc::new(Int64Array::from(vec![1, 2, 3]))).unwrap();
+
+        for array in [
+            Arc::new(StringArray::from(vec!["foo", "barz"])) as ArrayRef,
+            Arc::new(LargeBinaryArray::from(vec![b"barz".as_slice()])),
+            Arc::new(StringViewArray::from(vec!["a", "barz"])),
+            Arc::new(FixedSizeBinaryArray::from(vec![b"barz".as_slice()])),
+            Arc::new(
+                ["foo", "barz"]
+                    .into_iter()
+                    .collect::<DictionaryArray<Int32Type>>(),
+            ),
+            Arc::new(StructArray::from(vec![(
+                Arc::new(Field::new("s", DataType::Utf8, false)),
+                Arc::new(StringArray::from(vec!["barz"])) as ArrayRef,
+            )])),
+        ] {
+            insta::assert_snapshot!(
+                check(array).unwrap_err(),
+                @"Resources exhausted: array value length: got=4, limit=3",
+            );
+        }
+    }
+
+    #[test]
+    fn test_check_decimal() {
+        let check = |dt: &DataType| {
+            let token = limits::ComplexityToken::new(TrustedDataLimits::default()).unwrap();
+            check_data_type(dt, &token)
+        };
+
+        check(&DataType::Decimal128(38, 10)).unwrap();
+        check(&DataType::Decimal128(10, -2)).unwrap();
+        check(&DataType::Decimal256(76, 76)).unwrap();
+
+        insta::assert_snapshot!(
+            check(&DataType::Decimal128(39, 2)).unwrap_err(),
+            @"External error: precision of Decimal128(39, 2) must be between 1 and 38, use a wider decimal type for higher precisions",
+        );
+        insta::assert_snapshot!(
+            check(&DataType::Decimal256(77, 2)).unwrap_err(),
+            @"External error: precision of Decimal256(77, 2) must be between 1 and 76",
+        );
+        insta::assert_snapshot!(
+            check(&DataType::Decimal32(0, 0)).unwrap_err(),
+            @"External error: precision of Decimal32(0, 0) must be between 1 and 9, use a wider decimal type for higher precisions",
+        );
+        insta::assert_snapshot!(
+            check(&DataType::Decimal64(5, 6)).unwrap_err(),
+            @"External error: scale of Decimal64(5, 6) must not exceed the precision",
+        );
+    }
+
+    #[test]
+    fn test_verify_checksum() {
+        let array: wit_types::Array =
+            (Arc::new(Int64Array::from(vec![1, 2, 3])) as ArrayRef).into();
+        insta::assert_snapshot!(
+            array.verify_checksum().unwrap_err(),
+            @"External error: guest did not provide a result checksum",
+        );
+
+        let mut array = wit_types::Array {
+            checksum: Some(checksum(&array.arrow_ipc_batch)),
+            ..array
+        };
+        array.verify_checksum().unwrap();
+
+        let idx = array.arrow_ipc_batch.len() / 2;
+        array.arrow_ipc_batch[idx] ^= 1;
+        let err = array.verify_checksum().unwrap_err().to_string();
+        assert!(
+            err.starts_with("External error: result checksum mismatch: expected="),
+            "{err}",
+        );
+    }
+}
@@ -0,0 +1,117 @@
+//! Sanitization of untrusted strings.
+//!
+//! Strings that are provided by the guest may end up in logs or may be shown to SQL clients. Guests could use that to
+//! inject fake log lines or to spoof terminal output, e.g. via ANSI escape sequences or Unicode bidirectional
+//! overrides.
+use std::{borrow::Cow, iter::Peekable, str::Chars};
+
+/// Sanitize string.
+///
+/// This removes:
+///
+/// - ANSI escape sequences
+/// - control characters, except for line feeds (`\n`) and tabs (`\t`)
+/// - Unicode bidirectional formatting characters
+///
+/// Returns [`Cow::Borrowed`] if the string did not need any sanitization.
+pub(crate) fn sanitize(s: &str) -> Cow<'_, str> {
+    if !contains_unsafe_chars(s) {
+        return Cow::Borrowed(s);
+    }
+
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\x1b' => skip_escape_sequence(&mut chars),
+            c if is_unsafe(c) => {}
+            c => out.push(c),
+        }
+    }
+    Cow::Owned(out)
+}
+
+/// Sanitize owned string, see [`sanitize`].
+pub(crate) fn sanitize_owned(s: String) -> String {
+    let sanitized = match sanitize(&s) {
+        Cow::Borrowed(_) => None,
+        Cow::Owned(sanitized) => Some(sanitized),
+    };
+    sanitized.unwrap_or(s)
+}
+
+/// Checks if the string contains any characters that would be removed by [`sanitize`].
+pub(crate) fn contains_unsafe_chars(s: &str) -> bool {
+    s.chars().any(is_unsafe)
+}
+
+/// Checks if the character is unsafe.
+///
+/// The escape character is included, since it is covered by [`char::is_control`].
+fn is_unsafe(c: char) -> bool {
+    (c.is_control() && c != '\n' && c != '\t') || is_bidi_control(c)
+}
+
+/// Unicode bidirectional formatting characters.
+///
+/// See <https://www.unicode.org/reports/tr9/#Directional_Formatting_Characters>.
+fn is_bidi_control(c: char) -> bool {
+    matches!(
+        c,
+        '\u{061C}' | '\u{200E}' | '\u{200F}' | '\u{202A}'..='\u{202E}' | '\u{2066}'..='\u{2069}'
+    )
+}
+
+/// Skip ANSI escape sequence.
+///
+/// The leading escape character was already consumed.
+fn skip_escape_sequence(chars: &mut Peekable<Chars<'_>>) {
+    match chars.next() {
+        // Control Sequence Introducer: parameter and intermediate bytes, terminated by a final byte
+        Some('[') => {
+            for c in chars.by_ref() {
+                if !('\x20'..='\x3f').contains(&c) {
+                    break;
+                }
+            }
+        }
+        // string sequences (OSC, DCS, SOS, PM, APC): terminated by BEL or String Terminator
+        Some(']' | 'P' | 'X' | '^' | '_') => {
+            while let Some(c) = chars.next() {
+                if c == '\x07' || (c == '\x1b' && chars.next_if_eq(&'\\').is_some()) {
+                    break;
+                }
+            }
+        }
+        // two-character sequence
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize() {
+        assert!(matches!(
+            sanitize("foo\n\tbar"),
+            Cow::Borrowed("foo\n\tbar")
+        ));
+        assert_eq!(sanitize("\x1b[1;31mred\x1b[0m"), "red");
+        assert_eq!(sanitize("\x1b]0;title\x07foo"), "foo");
+        assert_eq!(sanitize("\x1b]8;;https://example.com\x1b\\foo"), "foo");
+        assert_eq!(sanitize("\x1bcfoo"), "foo");
+        assert_eq!(sanitize("foo\x1b"), "foo");
+        assert_eq!(sanitize("foo\rbar\x08\x00"), "foobar");
+        assert_eq!(sanitize("foo\u{009b}bar"), "foobar");
+        assert_eq!(sanitize("foo\u{202e}rab\u{202c}"), "foorab");
+        assert_eq!(sanitize("ünïcödé ✓"), "ünïcödé ✓");
+    }
+
+    #[test]
+    fn test_sanitize_owned() {
+        assert_eq!(sanitize_owned("foo".to_owned()), "foo");
+        assert_eq!(sanitize_owned("\x1b[0mfoo".to_owned()), "foo");
+    }
+}
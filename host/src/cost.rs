@@ -0,0 +1,72 @@
+//! Cost estimation for UDF invocations.
+
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+/// Cost estimate of a [`WasmScalarUdf`](crate::WasmScalarUdf), learned from previous invocations.
+///
+/// This is a cheap handle that can be cloned and kept around after the UDF was registered with DataFusion. It
+/// observes all future invocations of the UDF.
+#[derive(Debug, Clone, Default)]
+pub struct WasmUdfCostEstimate {
+    /// Shared history.
+    history: Arc<CostHistory>,
+}
+
+/// Invocation history.
+#[derive(Debug, Default)]
+struct CostHistory {
+    /// Total number of rows processed.
+    rows: AtomicU64,
+
+    /// Total time spent, in nanoseconds.
+    nanos: AtomicU64,
+}
+
+impl WasmUdfCostEstimate {
+    /// Record an invocation.
+    pub(crate) fn record(&self, rows: u64, elapsed: Duration) {
+        let nanos = u64::try_from(elapsed.as_nanos()).unwrap_or(u64::MAX);
+        self.history.rows.fetch_add(rows, Ordering::Relaxed);
+        self.history.nanos.fetch_add(nanos, Ordering::Relaxed);
+    }
+
+    /// Total number of rows that were observed so far.
+    pub fn rows(&self) -> u64 {
+        self.history.rows.load(Ordering::Relaxed)
+    }
+
+    /// Average cost per row.
+    ///
+    /// Returns [`None`] if no rows were observed so far.
+    pub fn per_row(&self) -> Option<Duration> {
+        let rows = self.rows();
+        if rows == 0 {
+            return None;
+        }
+        let nanos = self.history.nanos.load(Ordering::Relaxed);
+        Some(Duration::from_nanos(nanos / rows))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_per_row() {
+        let cost = WasmUdfCostEstimate::default();
+        assert_eq!(cost.per_row(), None);
+
+        let cost2 = cost.clone();
+        cost2.record(10, Duration::from_micros(10));
+        cost2.record(0, Duration::from_micros(10));
+        assert_eq!(cost.rows(), 10);
+        assert_eq!(cost.per_row(), Some(Duration::from_micros(2)));
+    }
+}
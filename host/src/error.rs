@@ -5,6 +5,7 @@ use wasmtime_wasi::p2::FsError;
 use crate::{
     bindings::exports::datafusion_udf_wasm::udf::types::{self as wit_types},
     conversion::limits::{CheckedFrom, ComplexityToken, TrustedDataLimits},
+    stderr::Stderr,
 };
 
 /// Extension for [`wasmtime::Error`].
@@ -13,20 +14,22 @@ pub(crate) trait WasmToDataFusionErrorExt {
     ///
     /// The context has:
     /// - `msg`: a human-readable context description
-    /// - `stderr`: stderr output of the WASM payload if available
-    fn context(self, msg: &str, stderr: Option<&[u8]>) -> DataFusionError;
+    /// - `stderr`: stderr output of the WASM payload if available, subject to its [policy](crate::StderrPolicy)
+    fn context(self, msg: &str, stderr: Option<&Stderr>) -> DataFusionError;
 }
 
 impl WasmToDataFusionErrorExt for wasmtime::Error {
-    fn context(self, msg: &str, stderr: Option<&[u8]>) -> DataFusionError {
+    fn context(self, msg: &str, stderr: Option<&Stderr>) -> DataFusionError {
         let mut context = msg.to_owned();
 
-        if let Some(stderr) = stderr
-            && !stderr.is_empty()
-        {
-            context.push_str(&format!("\n\nstderr:\n{}", String::from_utf8_lossy(stderr)));
+        if let Some(stderr) = stderr.and_then(Stderr::attachment) {
+            context.push_str(&format!("\n\nstderr:\n{stderr}"));
         }
 
+        let timeout = self.downcast_ref::<InvocationTimeout>().is_some();
+        let shutdown = self.downcast_ref::<RuntimeShutdown>().is_some();
+        let trap = self.downcast_ref::<wasmtime::Trap>().is_some();
+
         let this = match self.to_string().as_str() {
             // that's somewhat a hack but there isn't a better API for this yet, see
             // https://github.com/bytecodealliance/wasmtime/issues/12465
@@ -37,6 +40,15 @@ impl WasmToDataFusionErrorExt for wasmtime::Error {
                 self.into_boxed_dyn_error()
             }
         };
+        let this = if timeout {
+            Box::new(WasmUdfError::Timeout { source: this })
+        } else if shutdown {
+            Box::new(WasmUdfError::Shutdown { source: this })
+        } else if trap {
+            Box::new(WasmUdfError::Trap { source: this })
+        } else {
+            this
+        };
 
         DataFusionError::External(this).context(context)
     }
@@ -54,16 +66,16 @@ pub(crate) trait WasmToDataFusionResultExt {
     ///
     /// The context has:
     /// - `msg`: a human-readable context description
-    /// - `stderr`: stderr output of the WASM payload if available
-    fn context(self, msg: &str, stderr: Option<&[u8]>) -> Result<Self::T, DataFusionError>;
+    /// - `stderr`: stderr output of the WASM payload if available, subject to its [policy](crate::StderrPolicy)
+    fn context(self, msg: &str, stderr: Option<&Stderr>) -> Result<Self::T, DataFusionError>;
 
     /// Add context to error.
     ///
     /// The context has:
     /// - `msg`: a closure that generates a human-readable context description based on the error
-    /// - `stderr`: stderr output of the WASM payload if available
+    /// - `stderr`: stderr output of the WASM payload if available, subject to its [policy](crate::StderrPolicy)
     #[cfg(feature = "compiler")]
-    fn with_context<F>(self, msg: F, stderr: Option<&[u8]>) -> Result<Self::T, DataFusionError>
+    fn with_context<F>(self, msg: F, stderr: Option<&Stderr>) -> Result<Self::T, DataFusionError>
     where
         F: for<'a> FnOnce(&'a Self::E) -> String;
 }
@@ -72,12 +84,12 @@ impl<T> WasmToDataFusionResultExt for Result<T, wasmtime::Error> {
     type T = T;
     type E = wasmtime::Error;
 
-    fn context(self, msg: &str, stderr: Option<&[u8]>) -> Result<Self::T, DataFusionError> {
+    fn context(self, msg: &str, stderr: Option<&Stderr>) -> Result<Self::T, DataFusionError> {
         self.map_err(|err| WasmToDataFusionErrorExt::context(err, msg, stderr))
     }
 
     #[cfg(feature = "compiler")]
-    fn with_context<F>(self, msg: F, stderr: Option<&[u8]>) -> Result<Self::T, DataFusionError>
+    fn with_context<F>(self, msg: F, stderr: Option<&Stderr>) -> Result<Self::T, DataFusionError>
     where
         F: for<'a> FnOnce(&'a Self::E) -> String,
     {
@@ -130,23 +142,338 @@ pub(crate) trait WitDataFusionResultExt {
     type T;
 
     /// Convert error to [`DataFusionError`]
-    fn convert_err(self, limits: TrustedDataLimits) -> Result<Self::T, DataFusionError>;
+    ///
+    /// Like for [traps](WasmToDataFusionErrorExt::context), the stderr output of the WASM payload is attached -- if
+    /// available and allowed by its [policy](crate::StderrPolicy). Secrets that were handed to the guest are redacted.
+    fn convert_err(
+        self,
+        limits: TrustedDataLimits,
+        stderr: &Stderr,
+    ) -> Result<Self::T, DataFusionError>;
 }
 
 impl<T> WitDataFusionResultExt for Result<T, wit_types::DataFusionError> {
     type T = T;
 
-    fn convert_err(self, limits: TrustedDataLimits) -> Result<Self::T, DataFusionError> {
+    fn convert_err(
+        self,
+        limits: TrustedDataLimits,
+        stderr: &Stderr,
+    ) -> Result<Self::T, DataFusionError> {
         self.map_err(|e| DataFusionError::checked_from(e, ComplexityToken::new(limits)?))
-            .map_err(|e| match e {
-                // this is the error that we've got from the WASM guest
-                Ok(e) => e,
-                // the conversion failed, also with a DataFusionError
-                Err(e) => e.context("convert error from WASI"),
+            .map_err(|e| {
+                let e = match e {
+                    // this is the error that we've got from the WASM guest
+                    Ok(e) => e,
+                    // the conversion failed, also with a DataFusionError
+                    Err(e) => e.context("convert error from WASI"),
+                };
+                let e = stderr.secrets().redact_error(e);
+                match stderr.attachment() {
+                    Some(stderr) => e.context(format!("stderr:\n{stderr}")),
+                    None => e,
+                }
             })
     }
 }
 
+/// Error that originates from the WASM sandbox itself.
+///
+/// The host wraps these errors into [`DataFusionError::External`]. Use [`find`](Self::find) to get them back from an
+/// error returned by a [`WasmScalarUdf`](crate::WasmScalarUdf). The display output is the one of the underlying source,
+/// so wrapping does NOT change error messages.
+///
+/// Errors that the guest reports itself -- e.g. a Python exception -- keep their respective [`DataFusionError`]
+/// variant (e.g. [`DataFusionError::Execution`]). This also covers rejected HTTP requests and exceeded
+/// [VFS limits](crate::VfsLimits), since these are handed to the guest as ordinary I/O errors and the guest decides how
+/// to react. Host-side memory accounting fails with [`DataFusionError::ResourcesExhausted`].
+///
+/// # Stability
+/// This type is part of the stable API. New variants may be added in the future.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum WasmUdfError {
+    /// The guest trapped, e.g. because it executed an `unreachable` instruction or overflowed its stack.
+    ///
+    /// A trapped guest is [poisoned](Self::Poisoned), i.e. all further calls to UDFs of the same source will fail as
+    /// well, unless the host [recycles](crate::WasmPermissions::with_max_recycles) the guest.
+    Trap {
+        /// Underlying error.
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+
+    /// The guest trapped after it hit a resource limit.
+    ///
+    /// Like for [`Trap`](Self::Trap), the guest is [poisoned](Self::Poisoned). Retrying with the same limits will most
+    /// likely fail again.
+    ResourceLimit {
+        /// Limit that was hit.
+        kind: ResourceLimitKind,
+
+        /// Underlying error.
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+
+    /// A call did not finish within its time budget.
+    ///
+    /// See [`WasmUdfConfig::invocation_timeout_ms`](crate::WasmUdfConfig::invocation_timeout_ms),
+    /// [`WasmPermissions::with_invoke_timeout`](crate::WasmPermissions::with_invoke_timeout),
+    /// [`WasmPermissions::with_init_timeout`](crate::WasmPermissions::with_init_timeout), and
+    /// [`WasmPermissions::with_inplace_blocking_max_ticks`](crate::WasmPermissions::with_inplace_blocking_max_ticks).
+    Timeout {
+        /// Underlying error.
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+
+    /// An earlier call left the guest in an unusable state, e.g. because it [trapped](Self::Trap),
+    /// [timed out](Self::Timeout), or was cancelled by the caller.
+    ///
+    /// The guest may be in an inconsistent state, so all further calls to UDFs of the same source fail. Retrying
+    /// requires new UDF instances, unless the host [recycles](crate::WasmPermissions::with_max_recycles) the guest.
+    Poisoned {
+        /// Underlying error.
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+
+    /// The [runtime](crate::WasmRuntimeHandle) was [shut down](crate::WasmRuntimeHandle::shutdown).
+    ///
+    /// Running calls are interrupted and all further calls to UDFs that were created with this runtime fail.
+    Shutdown {
+        /// Underlying error.
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+}
+
+impl WasmUdfError {
+    /// Find sandbox error in the given error chain.
+    ///
+    /// This looks through [context](DataFusionError::context) and other wrappers.
+    pub fn find(e: &DataFusionError) -> Option<&Self> {
+        match e {
+            DataFusionError::External(e) => e.downcast_ref::<Self>(),
+            DataFusionError::Context(_, e) | DataFusionError::Diagnostic(_, e) => Self::find(e),
+            DataFusionError::Shared(e) => Self::find(e),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if the error left the guest in an unusable state.
+    ///
+    /// These are [traps](Self::Trap), [resource limits](Self::ResourceLimit), and [timeouts](Self::Timeout). Errors
+    /// that the guest reports itself are NOT fatal.
+    pub(crate) fn is_fatal(e: &DataFusionError) -> bool {
+        matches!(
+            Self::find(e),
+            Some(Self::Trap { .. } | Self::ResourceLimit { .. } | Self::Timeout { .. })
+        )
+    }
+
+    /// Underlying error.
+    fn inner(&self) -> &(dyn std::error::Error + Send + Sync + 'static) {
+        match self {
+            Self::Trap { source }
+            | Self::ResourceLimit { source, .. }
+            | Self::Timeout { source }
+            | Self::Poisoned { source }
+            | Self::Shutdown { source } => source.as_ref(),
+        }
+    }
+
+    /// Turn [trap](Self::Trap) into [resource limit](Self::ResourceLimit) error.
+    ///
+    /// This is used when the limiter rejected an allocation before the guest trapped, because the guest usually does
+    /// NOT handle failed allocations gracefully.
+    pub(crate) fn reclassify_trap(e: DataFusionError, kind: ResourceLimitKind) -> DataFusionError {
+        match e {
+            DataFusionError::External(e) => match e.downcast::<Self>() {
+                Ok(e) => match *e {
+                    Self::Trap { source } => {
+                        DataFusionError::External(Box::new(Self::ResourceLimit { kind, source }))
+                    }
+                    e => DataFusionError::External(Box::new(e)),
+                },
+                Err(e) => DataFusionError::External(e),
+            },
+            DataFusionError::Context(ctx, e) => {
+                DataFusionError::Context(ctx, Box::new(Self::reclassify_trap(*e, kind)))
+            }
+            e => e,
+        }
+    }
+}
+
+impl std::fmt::Display for WasmUdfError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(self.inner(), f)
+    }
+}
+
+impl std::error::Error for WasmUdfError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(self.inner())
+    }
+}
+
+/// Structured traceback that the guest attached to an error, e.g. for a Python exception.
+///
+/// The host wraps the guest error into [`DataFusionError::External`]. Use [`find`](Self::find) to get the traceback
+/// back from an error returned by a [`WasmScalarUdf`](crate::WasmScalarUdf), e.g. to render a proper stack trace in a
+/// UI. The display output is the one of the guest error.
+///
+/// The number of frames is bounded by [`TrustedDataLimits::max_traceback_frames`] and
+/// [`TrustedDataLimits::max_error_size`]. If the guest sends more, the outermost frames are dropped.
+///
+///
+/// [`TrustedDataLimits::max_traceback_frames`]: crate::TrustedDataLimits::max_traceback_frames
+/// [`TrustedDataLimits::max_error_size`]: crate::TrustedDataLimits::max_error_size
+#[derive(Debug)]
+pub struct GuestTraceback {
+    /// Guest error.
+    error: DataFusionError,
+
+    /// Frames, innermost frame last.
+    frames: Vec<TracebackFrame>,
+
+    /// Number of outermost frames that were dropped.
+    truncated_frames: usize,
+}
+
+impl GuestTraceback {
+    /// Attach traceback to guest error.
+    pub(crate) fn wrap(
+        error: DataFusionError,
+        frames: Vec<TracebackFrame>,
+        truncated_frames: usize,
+    ) -> DataFusionError {
+        DataFusionError::External(Box::new(Self {
+            error,
+            frames,
+            truncated_frames,
+        }))
+    }
+
+    /// Find traceback in the given error chain.
+    ///
+    /// This looks through [context](DataFusionError::context) and other wrappers.
+    pub fn find(e: &DataFusionError) -> Option<&Self> {
+        match e {
+            DataFusionError::External(e) => e.downcast_ref::<Self>(),
+            DataFusionError::Context(_, e) | DataFusionError::Diagnostic(_, e) => Self::find(e),
+            DataFusionError::Shared(e) => Self::find(e),
+            _ => None,
+        }
+    }
+
+    /// Guest error that the traceback belongs to.
+    pub fn error(&self) -> &DataFusionError {
+        &self.error
+    }
+
+    /// Frames, innermost frame last.
+    pub fn frames(&self) -> &[TracebackFrame] {
+        &self.frames
+    }
+
+    /// Number of outermost frames that were dropped due to limits.
+    pub fn truncated_frames(&self) -> usize {
+        self.truncated_frames
+    }
+}
+
+impl std::fmt::Display for GuestTraceback {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.error.fmt(f)
+    }
+}
+
+impl std::error::Error for GuestTraceback {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// Single frame of a [`GuestTraceback`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TracebackFrame {
+    /// Source file, as reported by the guest.
+    pub file: String,
+
+    /// Line number, starting at 1.
+    ///
+    /// This is 0 if the guest does not know the line.
+    pub line: u32,
+
+    /// Name of the function.
+    pub function: String,
+}
+
+/// Kind of resource limit, see [`WasmUdfError::ResourceLimit`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ResourceLimitKind {
+    /// Linear memory, accounted by the DataFusion memory pool.
+    Memory,
+
+    /// Table elements, see [`StaticResourceLimits::n_elements_per_table`](crate::StaticResourceLimits::n_elements_per_table).
+    TableElements,
+}
+
+/// Marker error for invocations that exceeded their deadline.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct InvocationTimeout;
+
+impl std::fmt::Display for InvocationTimeout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "UDF invocation timed out")
+    }
+}
+
+impl std::error::Error for InvocationTimeout {}
+
+/// Marker error for calls to a poisoned instance, see [`WasmUdfError::Poisoned`].
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct InstancePoisoned {
+    /// The instance was poisoned because a call was cancelled by the caller, not because the guest failed.
+    pub(crate) cancelled: bool,
+}
+
+impl std::fmt::Display for InstancePoisoned {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let reason = if self.cancelled {
+            "was cancelled"
+        } else {
+            "trapped or timed out"
+        };
+        write!(
+            f,
+            "UDF instance is poisoned because an earlier call {reason}"
+        )
+    }
+}
+
+impl std::error::Error for InstancePoisoned {}
+
+/// Marker error for guests that were interrupted or refused because the runtime was shut down, see
+/// [`WasmUdfError::Shutdown`].
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct RuntimeShutdown;
+
+impl std::fmt::Display for RuntimeShutdown {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "WASM runtime was shut down")
+    }
+}
+
+impl std::error::Error for RuntimeShutdown {}
+
+impl From<RuntimeShutdown> for DataFusionError {
+    fn from(e: RuntimeShutdown) -> Self {
+        Self::External(Box::new(WasmUdfError::Shutdown {
+            source: Box::new(e),
+        }))
+    }
+}
+
 /// Failed allocation error.
 #[derive(Debug, Clone)]
 #[expect(missing_copy_implementations, reason = "allow later extensions")]
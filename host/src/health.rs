@@ -0,0 +1,100 @@
+//! Component-level health check.
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use datafusion_common::Result as DataFusionResult;
+use datafusion_execution::memory_pool::{MemoryPool, UnboundedMemoryPool};
+use tokio::runtime::Handle;
+use wasmtime::AsContextMut;
+
+use crate::{
+    WasmComponentPrecompiled, WasmPermissions, WitVersion, component::WasmComponentInstance,
+    error::DataFusionResultExt, recycle::call_load_source, trace::span,
+};
+
+/// Source code that is passed to the guest during the [health check](WasmComponentPrecompiled::health_check).
+const PROBE_SOURCE: &str = "";
+
+/// Outcome of a successful [health check](WasmComponentPrecompiled::health_check).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WasmComponentHealth {
+    /// Time it took to create a guest instance, incl. populating its root filesystem.
+    pub startup_time: Duration,
+
+    /// Time it took the guest to process the probe, i.e. to create UDFs from empty source code.
+    pub probe_time: Duration,
+
+    /// WIT version that was negotiated with the guest.
+    pub wit_version: WitVersion,
+
+    /// Language/runtime of the guest, see [`WasmComponentDescription::runtime`](crate::WasmComponentDescription::runtime).
+    pub runtime: Option<String>,
+
+    /// Number of UDFs that the guest created from the probe.
+    pub probe_udfs: usize,
+}
+
+impl std::fmt::Display for WasmComponentHealth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Self {
+            startup_time,
+            probe_time,
+            wit_version,
+            runtime,
+            probe_udfs,
+        } = self;
+        write!(
+            f,
+            "wit={wit_version}, runtime={}, startup={startup_time:?}, probe={probe_time:?}, udfs={probe_udfs}",
+            runtime.as_deref().unwrap_or("<unknown>"),
+        )
+    }
+}
+
+impl WasmComponentPrecompiled {
+    /// Check that the component can be instantiated and serves requests.
+    ///
+    /// This creates a throw-away guest instance -- which also retrieves the root filesystem via `root_fs_tar` -- and
+    /// asks it to create UDFs from empty source code, limited by [`WasmPermissions::with_init_timeout`]. Services can
+    /// call this at boot to fail fast if a bundled component is broken.
+    ///
+    /// The root filesystem is cached and shared between all clones of this component, so this also warms up the
+    /// component for later [UDFs](crate::WasmScalarUdf::new). The guest memory is NOT accounted in a
+    /// [memory pool](MemoryPool), but it is still limited by the [permissions](WasmPermissions::with_resource_limits).
+    pub async fn health_check(
+        &self,
+        permissions: &WasmPermissions,
+        io_rt: Handle,
+    ) -> DataFusionResult<WasmComponentHealth> {
+        span!("health_check")
+            .instrument(async move {
+                let memory_pool: Arc<dyn MemoryPool> = Arc::new(UnboundedMemoryPool::default());
+
+                let start = Instant::now();
+                let instance =
+                    WasmComponentInstance::new(self, permissions, io_rt, &memory_pool).await?;
+                let startup_time = start.elapsed();
+
+                let start = Instant::now();
+                let mut state = instance.lock_state().await?;
+                state.as_context_mut().data_mut().invocation_deadline =
+                    Some(Instant::now() + permissions.init_timeout);
+                drop(state);
+                let udfs = call_load_source(&instance, PROBE_SOURCE, permissions)
+                    .await
+                    .context("probe")?;
+                let probe_time = start.elapsed();
+
+                Ok(WasmComponentHealth {
+                    startup_time,
+                    probe_time,
+                    wit_version: instance.wit_version(),
+                    runtime: self.runtime(),
+                    probe_udfs: udfs.len(),
+                })
+            })
+            .await
+    }
+}
@@ -1,37 +1,105 @@
 //! Config for HTTP integration.
 
-use std::sync::Arc;
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
+use datafusion_common::Result as DataFusionResult;
 use reqwest::dns::Resolve;
 
 use crate::{
-    HttpRequestValidator, RejectAllHttpRequests, TlsClientConfig, http::dns::ShuffleResolver,
+    HttpRequestValidator, IpPolicy, RejectAllHttpRequests, TlsClientConfig,
+    http::{build_client, dns::ShuffleResolver},
 };
 
+/// Defines which guest instances share HTTP connections.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum HttpPoolSharing {
+    /// Every guest instance has its own connection pool.
+    ///
+    /// Connections are still reused across invocations of the same instance, but NOT across instances -- e.g. the ones
+    /// created for [`InstanceSharing::PerUdf`](crate::InstanceSharing::PerUdf) or after
+    /// [recycling](crate::WasmPermissions::with_max_recycles).
+    PerInstance,
+
+    /// All guest instances that use the same config -- or a clone of it -- share one connection pool and TLS session
+    /// cache.
+    ///
+    /// Note that changing the config -- e.g. via [`HttpConfig::with_validator`] -- creates a new pool.
+    #[default]
+    Shared,
+}
+
 /// HTTP-related configs.
 #[derive(Clone)]
 pub struct HttpConfig {
     /// Maximum idle connection per host allowed in the pool.
     pub(crate) pool_max_idle_per_host: usize,
 
+    /// Timeout for idle connections in the pool.
+    pub(crate) pool_idle_timeout: Option<Duration>,
+
+    /// Which guest instances share the connection pool.
+    pub(crate) pool_sharing: HttpPoolSharing,
+
     /// DNS resolver.
     pub(crate) resolver: Arc<dyn Resolve>,
 
     /// Validator.
     pub(crate) validator: Arc<dyn HttpRequestValidator>,
 
+    /// Allowed IP addresses.
+    pub(crate) ip_policy: Arc<IpPolicy>,
+
     /// TLS config.
     pub(crate) tls_config: TlsClientConfig,
+
+    /// Client -- and with it the connection pool -- that is shared by all users of this config.
+    ///
+    /// Created on first use. Every builder method resets it, so a changed config never uses a stale client.
+    pub(crate) shared_client: Arc<Mutex<Option<reqwest::Client>>>,
 }
 
 impl HttpConfig {
     /// Sets the maximum idle connection per host allowed in the pool.
     ///
+    /// Idle connections keep their socket -- and for HTTPS their TLS session -- open, so this bounds the resources that
+    /// a guest can pin by talking to a single host. Use `0` to disable pooling.
+    ///
     /// # Default
-    /// Default is `usize::MAX` (no limit).
+    /// Default is 32.
     pub fn with_pool_max_idle_per_host(self, max: usize) -> Self {
         Self {
             pool_max_idle_per_host: max,
+            shared_client: Default::default(),
+            ..self
+        }
+    }
+
+    /// Sets the timeout for idle connections in the pool. [`None`] keeps idle connections forever.
+    ///
+    /// # Default
+    /// Default is 90 seconds.
+    pub fn with_pool_idle_timeout(self, timeout: Option<Duration>) -> Self {
+        Self {
+            pool_idle_timeout: timeout,
+            shared_client: Default::default(),
+            ..self
+        }
+    }
+
+    /// Sets which guest instances share the connection pool.
+    ///
+    /// Establishing connections -- especially TLS ones -- is expensive. Sharing the pool allows guests to reuse
+    /// connections across instances.
+    ///
+    /// # Default
+    /// Default is [`HttpPoolSharing::Shared`].
+    pub fn with_pool_sharing(self, sharing: HttpPoolSharing) -> Self {
+        Self {
+            pool_sharing: sharing,
+            shared_client: Default::default(),
             ..self
         }
     }
@@ -52,6 +120,7 @@ impl HttpConfig {
     {
         Self {
             resolver: Arc::new(resolver),
+            shared_client: Default::default(),
             ..self
         }
     }
@@ -66,6 +135,19 @@ impl HttpConfig {
     {
         Self {
             validator: Arc::new(validator),
+            shared_client: Default::default(),
+            ..self
+        }
+    }
+
+    /// Set policy for the IP addresses that the guest may connect to.
+    ///
+    /// # Default
+    /// See [`IpPolicy`].
+    pub fn with_ip_policy(self, policy: IpPolicy) -> Self {
+        Self {
+            ip_policy: Arc::new(policy),
+            shared_client: Default::default(),
             ..self
         }
     }
@@ -74,18 +156,44 @@ impl HttpConfig {
     pub fn with_tls_config(self, config: TlsClientConfig) -> Self {
         Self {
             tls_config: config,
+            shared_client: Default::default(),
             ..self
         }
     }
+
+    /// Get HTTP client for a new guest instance.
+    pub(crate) fn client(&self) -> DataFusionResult<reqwest::Client> {
+        match self.pool_sharing {
+            HttpPoolSharing::PerInstance => build_client(self),
+            HttpPoolSharing::Shared => {
+                let mut guard = self
+                    .shared_client
+                    .lock()
+                    .expect("shared HTTP client lock poisoned");
+                match guard.as_ref() {
+                    Some(client) => Ok(client.clone()),
+                    None => {
+                        let client = build_client(self)?;
+                        *guard = Some(client.clone());
+                        Ok(client)
+                    }
+                }
+            }
+        }
+    }
 }
 
 impl Default for HttpConfig {
     fn default() -> Self {
         Self {
             resolver: Arc::new(ShuffleResolver),
-            pool_max_idle_per_host: usize::MAX,
+            pool_max_idle_per_host: 32,
+            pool_idle_timeout: Some(Duration::from_secs(90)),
+            pool_sharing: HttpPoolSharing::default(),
             validator: Arc::new(RejectAllHttpRequests),
+            ip_policy: Arc::new(IpPolicy::default()),
             tls_config: TlsClientConfig::default(),
+            shared_client: Default::default(),
         }
     }
 }
@@ -94,16 +202,24 @@ impl std::fmt::Debug for HttpConfig {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let Self {
             pool_max_idle_per_host,
+            pool_idle_timeout,
+            pool_sharing,
             // doesn't implement Debug
             resolver: _,
             validator,
+            ip_policy,
             tls_config,
+            // cache
+            shared_client: _,
         } = self;
 
         f.debug_struct("HttpConfig")
             .field("pool_max_idle_per_host", pool_max_idle_per_host)
+            .field("pool_idle_timeout", pool_idle_timeout)
+            .field("pool_sharing", pool_sharing)
             .field("resolver", &"<RESOLVER>")
             .field("validator", validator)
+            .field("ip_policy", ip_policy)
             .field("tls_config", tls_config)
             .finish()
     }
@@ -1,6 +1,8 @@
 //! DNS-related tools.
 use std::{net::ToSocketAddrs, sync::Arc};
 
+use crate::http::ip_policy::IpPolicy;
+
 use rand::prelude::SliceRandom;
 use reqwest::dns::{Addrs, Name, Resolve, Resolving};
 use tokio::task::JoinSet;
@@ -39,18 +41,22 @@ impl Resolve for ShuffleResolver {
 pub(crate) struct ResolverWrapper {
     /// User-provided resolver.
     inner: Arc<dyn Resolve>,
+
+    /// Allowed IP addresses.
+    ip_policy: Arc<IpPolicy>,
 }
 
 impl ResolverWrapper {
     /// Create new wrapper.
-    pub(crate) fn new(inner: Arc<dyn Resolve>) -> Self {
-        Self { inner }
+    pub(crate) fn new(inner: Arc<dyn Resolve>, ip_policy: Arc<IpPolicy>) -> Self {
+        Self { inner, ip_policy }
     }
 }
 
 impl Resolve for ResolverWrapper {
     fn resolve(&self, name: Name) -> Resolving {
         let inner = Arc::clone(&self.inner);
+        let ip_policy = Arc::clone(&self.ip_policy);
 
         Box::pin(async move {
             let name_string = name.as_str().to_owned();
@@ -65,11 +71,36 @@ impl Resolve for ResolverWrapper {
                 }
             }
 
+            let n_resolved = addrs.len();
+            let addrs = addrs
+                .into_iter()
+                .filter(|addr| ip_policy.is_allowed(addr.ip()))
+                .collect::<Vec<_>>();
+            if addrs.is_empty() && n_resolved > 0 {
+                return Err(Box::new(ResolvedIpDenied { name: name_string }) as DynErr);
+            }
+
             Ok(Box::new(addrs.into_iter()) as Addrs)
         })
     }
 }
 
+/// All addresses that a DNS name resolved to are denied by the [`IpPolicy`].
+#[derive(Debug, Default)]
+pub(crate) struct ResolvedIpDenied {
+    /// DNS name.
+    name: String,
+}
+
+impl std::fmt::Display for ResolvedIpDenied {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Self { name } = self;
+        write!(f, "all resolved addresses for `{name}` are denied")
+    }
+}
+
+impl std::error::Error for ResolvedIpDenied {}
+
 /// A user-provided DNS resolver acquired an [`SocketAddr`](std::net::SocketAddr) with a non-zero port.
 #[derive(Debug, Default)]
 pub(crate) struct ResolvedPortNotZero {
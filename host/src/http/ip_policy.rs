@@ -0,0 +1,303 @@
+//! Policies for the IP addresses that the guest may connect to.
+
+use std::{
+    fmt,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    str::FromStr,
+};
+
+/// IP address range in [CIDR notation], e.g. `10.0.0.0/8`.
+///
+///
+/// [CIDR notation]: https://en.wikipedia.org/wiki/Classless_Inter-Domain_Routing#CIDR_notation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct IpCidr {
+    /// Network address.
+    addr: IpAddr,
+
+    /// Number of leading bits that are fixed.
+    prefix_len: u8,
+}
+
+impl IpCidr {
+    /// Create new range.
+    ///
+    /// Returns [`None`] if the prefix length exceeds the address length. Host bits of the address are ignored.
+    pub const fn new(addr: IpAddr, prefix_len: u8) -> Option<Self> {
+        let max = match addr {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+        if prefix_len > max {
+            None
+        } else {
+            Some(Self { addr, prefix_len })
+        }
+    }
+
+    /// Returns `true` if the given address is within this range.
+    ///
+    /// IPv4 ranges never contain IPv6 addresses and vice versa. Use [`IpPolicy`] to handle IPv4-mapped IPv6 addresses.
+    pub fn contains(&self, addr: IpAddr) -> bool {
+        match (self.addr, addr) {
+            (IpAddr::V4(net), IpAddr::V4(addr)) => prefix_matches(
+                net.to_bits().into(),
+                addr.to_bits().into(),
+                32,
+                self.prefix_len,
+            ),
+            (IpAddr::V6(net), IpAddr::V6(addr)) => {
+                prefix_matches(net.to_bits(), addr.to_bits(), 128, self.prefix_len)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Compare the leading `prefix_len` bits of two addresses that are `bits` long.
+fn prefix_matches(net: u128, addr: u128, bits: u8, prefix_len: u8) -> bool {
+    let shift = bits - prefix_len;
+    if shift >= 128 {
+        true
+    } else {
+        (net >> shift) == (addr >> shift)
+    }
+}
+
+impl fmt::Display for IpCidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix_len)
+    }
+}
+
+/// Invalid [`IpCidr`] string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidIpCidr(String);
+
+impl fmt::Display for InvalidIpCidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid CIDR: {}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidIpCidr {}
+
+impl FromStr for IpCidr {
+    type Err = InvalidIpCidr;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (addr, prefix_len) = s
+            .split_once('/')
+            .ok_or_else(|| InvalidIpCidr(s.to_owned()))?;
+        let addr = addr
+            .parse::<IpAddr>()
+            .map_err(|_| InvalidIpCidr(s.to_owned()))?;
+        let prefix_len = prefix_len
+            .parse::<u8>()
+            .map_err(|_| InvalidIpCidr(s.to_owned()))?;
+        Self::new(addr, prefix_len).ok_or_else(|| InvalidIpCidr(s.to_owned()))
+    }
+}
+
+/// Private ranges (RFC 1918, and unique local addresses from RFC 4193 for IPv6), shared address space, "this network",
+/// and link-local ranges.
+///
+/// These are denied by the [default policy](IpPolicy::default).
+const DEFAULT_DENY: &[IpCidr] = &[
+    // RFC 1918
+    cidr_v4(Ipv4Addr::new(10, 0, 0, 0), 8),
+    cidr_v4(Ipv4Addr::new(172, 16, 0, 0), 12),
+    cidr_v4(Ipv4Addr::new(192, 168, 0, 0), 16),
+    // "this network" (RFC 1122), `0.0.0.0` reaches the local host on many systems
+    cidr_v4(Ipv4Addr::new(0, 0, 0, 0), 8),
+    // shared address space for carrier-grade NAT (RFC 6598), often used for internal cloud networks
+    cidr_v4(Ipv4Addr::new(100, 64, 0, 0), 10),
+    // link-local, includes cloud metadata endpoints like `169.254.169.254`
+    cidr_v4(Ipv4Addr::new(169, 254, 0, 0), 16),
+    // unique local addresses
+    cidr_v6(Ipv6Addr::new(0xfc00, 0, 0, 0, 0, 0, 0, 0), 7),
+    // link-local
+    cidr_v6(Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 0), 10),
+];
+
+/// Create IPv4 range in const context.
+const fn cidr_v4(addr: Ipv4Addr, prefix_len: u8) -> IpCidr {
+    IpCidr {
+        addr: IpAddr::V4(addr),
+        prefix_len,
+    }
+}
+
+/// Create IPv6 range in const context.
+const fn cidr_v6(addr: Ipv6Addr, prefix_len: u8) -> IpCidr {
+    IpCidr {
+        addr: IpAddr::V6(addr),
+        prefix_len,
+    }
+}
+
+/// Defines which IP addresses the guest may connect to.
+///
+/// This is checked after DNS resolution, so it also covers host names that resolve to internal addresses (e.g. to
+/// prevent [SSRF]). Resolved addresses that are denied are dropped. If no address is left -- or if the request uses
+/// a denied IP address directly -- the request fails with `ErrorCode::HttpRequestDenied`.
+///
+/// An address is allowed if it is within an [allowed](Self::allow) range. Otherwise it is denied if it is within a
+/// [denied](Self::deny) range. All other addresses are allowed. IPv4-mapped IPv6 addresses (e.g. `::ffff:10.0.0.1`) are
+/// treated as IPv4 addresses.
+///
+/// This complements the [validator](crate::HttpConfig::with_validator), which only sees the request URL.
+///
+/// # Default
+/// The default denies private (RFC 1918, and unique local IPv6 addresses), shared (RFC 6598), "this network"
+/// (`0.0.0.0/8`), and link-local ranges. Loopback addresses are NOT denied by default.
+///
+///
+/// [SSRF]: https://owasp.org/www-community/attacks/Server_Side_Request_Forgery
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IpPolicy {
+    /// Allowed ranges, take precedence over [`deny`](Self::deny).
+    allow: Vec<IpCidr>,
+
+    /// Denied ranges.
+    deny: Vec<IpCidr>,
+}
+
+impl IpPolicy {
+    /// Policy that allows all addresses.
+    pub fn allow_all() -> Self {
+        Self {
+            allow: vec![],
+            deny: vec![],
+        }
+    }
+
+    /// Allow range, even if it is also denied.
+    pub fn allow(mut self, cidr: IpCidr) -> Self {
+        self.allow.push(cidr);
+        self
+    }
+
+    /// Deny range.
+    pub fn deny(mut self, cidr: IpCidr) -> Self {
+        self.deny.push(cidr);
+        self
+    }
+
+    /// Returns `true` if the guest may connect to the given address.
+    pub fn is_allowed(&self, addr: IpAddr) -> bool {
+        let addr = match addr {
+            IpAddr::V6(v6) => v6
+                .to_ipv4_mapped()
+                .map(IpAddr::V4)
+                .unwrap_or(IpAddr::V6(v6)),
+            IpAddr::V4(_) => addr,
+        };
+
+        self.allow.iter().any(|cidr| cidr.contains(addr))
+            || !self.deny.iter().any(|cidr| cidr.contains(addr))
+    }
+}
+
+impl Default for IpPolicy {
+    fn default() -> Self {
+        Self {
+            allow: vec![],
+            deny: DEFAULT_DENY.to_vec(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_cidr_parse() {
+        assert_eq!(
+            "10.0.0.0/8".parse::<IpCidr>().unwrap().to_string(),
+            "10.0.0.0/8",
+        );
+        assert_eq!("::/0".parse::<IpCidr>().unwrap().to_string(), "::/0");
+        "10.0.0.0".parse::<IpCidr>().unwrap_err();
+        "10.0.0.0/33".parse::<IpCidr>().unwrap_err();
+        "::/129".parse::<IpCidr>().unwrap_err();
+        "foo/8".parse::<IpCidr>().unwrap_err();
+    }
+
+    #[test]
+    fn test_cidr_contains() {
+        let cidr = "172.16.0.0/12".parse::<IpCidr>().unwrap();
+        assert!(cidr.contains(ip("172.16.0.1")));
+        assert!(cidr.contains(ip("172.31.255.255")));
+        assert!(!cidr.contains(ip("172.32.0.0")));
+        assert!(!cidr.contains(ip("::ffff:172.16.0.1")));
+
+        let cidr = "0.0.0.0/0".parse::<IpCidr>().unwrap();
+        assert!(cidr.contains(ip("1.2.3.4")));
+        assert!(!cidr.contains(ip("::1")));
+
+        let cidr = "fe80::/10".parse::<IpCidr>().unwrap();
+        assert!(cidr.contains(ip("fe80::1")));
+        assert!(!cidr.contains(ip("fec0::1")));
+
+        let cidr = "1.2.3.4/32".parse::<IpCidr>().unwrap();
+        assert!(cidr.contains(ip("1.2.3.4")));
+        assert!(!cidr.contains(ip("1.2.3.5")));
+    }
+
+    #[test]
+    fn test_default_policy() {
+        let policy = IpPolicy::default();
+
+        for denied in [
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "0.0.0.0",
+            "0.1.2.3",
+            "100.64.0.1",
+            "100.127.255.255",
+            "169.254.169.254",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:10.0.0.1",
+            "::ffff:169.254.169.254",
+            "::ffff:0.0.0.0",
+            "::ffff:100.64.0.1",
+        ] {
+            assert!(!policy.is_allowed(ip(denied)), "{denied}");
+        }
+
+        for allowed in [
+            "1.1.1.1",
+            "1.0.0.0",
+            "100.63.255.255",
+            "100.128.0.0",
+            "127.0.0.1",
+            "::1",
+            "2001:db8::1",
+            "::ffff:1.1.1.1",
+        ] {
+            assert!(policy.is_allowed(ip(allowed)), "{allowed}");
+        }
+    }
+
+    #[test]
+    fn test_allow_overrides_deny() {
+        let policy = IpPolicy::default()
+            .allow("10.1.0.0/16".parse().unwrap())
+            .deny("1.1.1.0/24".parse().unwrap());
+
+        assert!(policy.is_allowed(ip("10.1.2.3")));
+        assert!(!policy.is_allowed(ip("10.2.0.1")));
+        assert!(!policy.is_allowed(ip("1.1.1.1")));
+
+        assert!(IpPolicy::allow_all().is_allowed(ip("10.0.0.1")));
+    }
+
+    /// Parse IP address.
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+}
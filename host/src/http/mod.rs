@@ -3,6 +3,7 @@
 use std::{io::ErrorKind, sync::Arc};
 
 use datafusion_common::{DataFusionError, error::Result as DataFusionResult};
+use futures_util::TryStreamExt;
 use http::HeaderName;
 use http_body_util::BodyExt;
 use hyper::body::Frame;
@@ -17,7 +18,9 @@ use wasmtime_wasi_http::{
     },
 };
 
-pub use config::HttpConfig;
+pub use config::{HttpConfig, HttpPoolSharing};
+pub use ip_policy::{InvalidIpCidr, IpCidr, IpPolicy};
+pub use redirect::HttpRedirectPolicy;
 pub use tls::TlsClientConfig;
 pub use types::{HttpConnectionMode, HttpMethod, HttpPort};
 pub use validator::{
@@ -26,12 +29,21 @@ pub use validator::{
 };
 
 use crate::{
-    http::dns::{ResolvedPortNotZero, ResolverWrapper},
+    http::{
+        dns::{ResolvedIpDenied, ResolvedPortNotZero, ResolverWrapper},
+        rate_limit::{HttpRateLimit, HttpRateLimiter},
+        redirect::RequestHead,
+    },
+    observer::{DeniedIo, Observer},
     state::WasmStateImpl,
+    volatility::ImmutableFlag,
 };
 
 mod config;
 mod dns;
+mod ip_policy;
+pub(crate) mod rate_limit;
+mod redirect;
 mod tls;
 mod types;
 mod validator;
@@ -49,8 +61,11 @@ impl WasiHttpView for WasmStateImpl {
 /// Implements [`WasiHttpHooks`].
 #[derive(Debug)]
 pub(crate) struct WasiHttpHooksImpl {
-    /// HTTP request validator.
-    http_validator: Arc<dyn HttpRequestValidator>,
+    /// Checks for every outgoing request.
+    checks: RequestChecks,
+
+    /// Redirect policy.
+    redirects: HttpRedirectPolicy,
 
     /// Handle to tokio I/O runtime.
     io_rt: Handle,
@@ -59,67 +74,100 @@ pub(crate) struct WasiHttpHooksImpl {
     ///
     /// This may cache connections and TLS state.
     client: reqwest::Client,
+
+    /// Deny requests while the guest executes an immutable UDF.
+    immutable: ImmutableFlag,
+
+    /// Reports sent bytes.
+    observer: Observer,
 }
 
 impl WasiHttpHooksImpl {
     /// Set up data structures.
-    pub(crate) fn new(config: HttpConfig, io_rt: Handle) -> DataFusionResult<Self> {
-        let HttpConfig {
-            pool_max_idle_per_host,
-            resolver,
-            validator,
-            tls_config,
-        } = config;
-
-        // https://github.com/seanmonstar/reqwest/issues/2924
-        if rustls::crypto::CryptoProvider::get_default().is_none() {
-            let _ = rustls::crypto::ring::default_provider().install_default();
-        }
-
-        let client_builder = reqwest::Client::builder()
-            // disable response body compression (the guest shall do that)
-            .no_brotli()
-            .no_deflate()
-            .no_gzip()
-            .no_zstd()
-            // disable redirect handling (the guest shall do that)
-            .redirect(reqwest::redirect::Policy::none())
-            // disable proxy
-            // TODO: allow overrides
-            .no_proxy()
-            // set up DNS
-            .dns_resolver(ResolverWrapper::new(resolver))
-            // connection pool setup
-            .pool_max_idle_per_host(pool_max_idle_per_host);
-
-        // TLS setup
-        let TlsClientConfig {
-            ca_certs,
-            exclude_bundled_ca_certs,
-            version_min,
-        } = tls_config;
-        let client_builder = client_builder
-            .tls_backend_rustls()
-            .tls_version_min(version_min);
-        let client_builder = if exclude_bundled_ca_certs {
-            client_builder.tls_certs_only(ca_certs.iter().cloned())
-        } else {
-            client_builder.tls_certs_merge(ca_certs.iter().cloned())
-        };
-
-        // done
-        let client = client_builder
-            .build()
-            .map_err(|e| DataFusionError::External(Box::new(e)))?;
+    pub(crate) fn new(
+        config: HttpConfig,
+        rate_limit: Option<HttpRateLimit>,
+        redirects: HttpRedirectPolicy,
+        io_rt: Handle,
+        immutable: &ImmutableFlag,
+        observer: Observer,
+    ) -> DataFusionResult<Self> {
+        let client = config.client()?;
 
         Ok(Self {
-            http_validator: validator,
+            checks: RequestChecks {
+                validator: config.validator,
+                ip_policy: config.ip_policy,
+                rate_limiter: rate_limit.map(HttpRateLimiter::new),
+            },
+            redirects,
             io_rt,
             client,
+            immutable: immutable.clone(),
+            observer,
         })
     }
 }
 
+/// Build HTTP client for the given config.
+pub(crate) fn build_client(config: &HttpConfig) -> DataFusionResult<reqwest::Client> {
+    let HttpConfig {
+        pool_max_idle_per_host,
+        pool_idle_timeout,
+        pool_sharing: _,
+        resolver,
+        validator: _,
+        ip_policy,
+        tls_config,
+        shared_client: _,
+    } = config;
+
+    // https://github.com/seanmonstar/reqwest/issues/2924
+    if rustls::crypto::CryptoProvider::get_default().is_none() {
+        let _ = rustls::crypto::ring::default_provider().install_default();
+    }
+
+    let client_builder = reqwest::Client::builder()
+        // disable response body compression (the guest shall do that)
+        .no_brotli()
+        .no_deflate()
+        .no_gzip()
+        .no_zstd()
+        // disable redirect handling (the guest or `send_request` shall do that)
+        .redirect(reqwest::redirect::Policy::none())
+        // disable proxy
+        // TODO: allow overrides
+        .no_proxy()
+        // set up DNS
+        .dns_resolver(ResolverWrapper::new(
+            Arc::clone(resolver),
+            Arc::clone(ip_policy),
+        ))
+        // connection pool setup
+        .pool_max_idle_per_host(*pool_max_idle_per_host)
+        .pool_idle_timeout(*pool_idle_timeout);
+
+    // TLS setup
+    let TlsClientConfig {
+        ca_certs,
+        exclude_bundled_ca_certs,
+        version_min,
+    } = tls_config;
+    let client_builder = client_builder
+        .tls_backend_rustls()
+        .tls_version_min(*version_min);
+    let client_builder = if *exclude_bundled_ca_certs {
+        client_builder.tls_certs_only(ca_certs.iter().cloned())
+    } else {
+        client_builder.tls_certs_merge(ca_certs.iter().cloned())
+    };
+
+    // done
+    client_builder
+        .build()
+        .map_err(|e| DataFusionError::External(Box::new(e)))
+}
+
 impl WasiHttpHooks for WasiHttpHooksImpl {
     fn send_request(
         &mut self,
@@ -134,26 +182,40 @@ impl WasiHttpHooks for WasiHttpHooksImpl {
         // technically we could return an error straight away, but `urllib3` doesn't handle that super well, so we
         // create a future and validate the error in there (before actually starting the request of course)
 
-        let validator = Arc::clone(&self.http_validator);
+        let checks = self.checks.clone();
+        let redirects = self.redirects;
         let client = self.client.clone();
+        let immutable = self.immutable.is_set();
+        let observer = self.observer.clone();
         let handle = wasmtime_wasi::runtime::spawn(async move {
             // yes, that's another layer of futures. The WASI interface is somewhat nested.
+            let denied = DeniedIo::Http {
+                method: request.method().to_string(),
+                uri: request.uri().to_string(),
+            };
             let fut = async {
-                let mode = HttpConnectionMode::from_use_tls(config.use_tls);
-                validator
-                    .validate(&request, mode)
-                    .map_err(|_| HttpErrorCode::HttpRequestDenied)?;
-
-                log::debug!(
-                    "UDF HTTP request: {} {} ({mode:?})",
-                    request.method().as_str(),
-                    request.uri(),
-                );
-
-                send_request(&client, request, config).await
+                if immutable {
+                    return Err(HttpErrorCode::HttpRequestDenied);
+                }
+
+                checks.check(&request, config.use_tls)?;
+
+                send_request(
+                    &client,
+                    request,
+                    config,
+                    redirects,
+                    &checks,
+                    observer.clone(),
+                )
+                .await
             };
 
-            Ok(fut.await)
+            let res = fut.await;
+            if matches!(res, Err(HttpErrorCode::HttpRequestDenied)) {
+                observer.io_denied(denied);
+            }
+            Ok(res)
         });
 
         Ok(HostFutureIncomingResponse::pending(handle))
@@ -169,14 +231,70 @@ impl WasiHttpHooks for WasiHttpHooksImpl {
     }
 }
 
-/// Send HTTP request.
+/// Checks that every outgoing request -- including redirect hops -- must pass.
+#[derive(Debug, Clone)]
+struct RequestChecks {
+    /// HTTP request validator.
+    validator: Arc<dyn HttpRequestValidator>,
+
+    /// Allowed IP addresses, for requests that use IP addresses instead of DNS names.
+    ip_policy: Arc<IpPolicy>,
+
+    /// Rate limiter, shared by all requests of this guest instance.
+    rate_limiter: Option<HttpRateLimiter>,
+}
+
+impl RequestChecks {
+    /// Check request.
+    fn check(
+        &self,
+        request: &hyper::Request<HyperOutgoingBody>,
+        use_tls: bool,
+    ) -> Result<(), HttpErrorCode> {
+        let mode = HttpConnectionMode::from_use_tls(use_tls);
+        self.validator
+            .validate(request, mode)
+            .map_err(|_| HttpErrorCode::HttpRequestDenied)?;
+
+        // DNS names are checked by the resolver, but IP addresses are never resolved
+        if let Some(ip) = request.uri().host().and_then(|host| {
+            host.trim_start_matches('[')
+                .trim_end_matches(']')
+                .parse()
+                .ok()
+        }) && !self.ip_policy.is_allowed(ip)
+        {
+            return Err(HttpErrorCode::HttpRequestDenied);
+        }
+
+        if let Some(rate_limiter) = &self.rate_limiter
+            && !rate_limiter.try_acquire(request.uri().host().unwrap_or_default())
+        {
+            log::debug!("UDF HTTP request rate limited: {}", request.uri());
+            return Err(HttpErrorCode::ConnectionLimitReached);
+        }
+
+        log::debug!(
+            "UDF HTTP request: {} {} ({mode:?})",
+            request.method().as_str(),
+            request.uri(),
+        );
+
+        Ok(())
+    }
+}
+
+/// Send HTTP request, following redirects according to the given policy.
 async fn send_request(
     client: &reqwest::Client,
-    request: hyper::Request<HyperOutgoingBody>,
+    mut request: hyper::Request<HyperOutgoingBody>,
     config: OutgoingRequestConfig,
+    redirects: HttpRedirectPolicy,
+    checks: &RequestChecks,
+    observer: Observer,
 ) -> Result<IncomingResponse, HttpErrorCode> {
     let OutgoingRequestConfig {
-        use_tls,
+        mut use_tls,
         connect_timeout,
         first_byte_timeout,
         between_bytes_timeout,
@@ -187,19 +305,39 @@ async fn send_request(
     // connections.
     let first_byte_timeout = first_byte_timeout.min(connect_timeout);
 
-    let resp = tokio::time::timeout(
-        first_byte_timeout,
-        assemble_request(client, request, use_tls)?.send(),
-    )
-    .await
-    .map_err(|_| HttpErrorCode::ConnectionReadTimeout)?
-    .map_err(map_reqwest_err)?;
-
-    Ok(IncomingResponse {
-        resp: assemble_response(resp)?,
-        worker: None,
-        between_bytes_timeout,
-    })
+    let max_hops = redirects.max_hops();
+    let mut hops = 0;
+    loop {
+        let head = (max_hops > 0).then(|| RequestHead::new(&request, use_tls));
+
+        let resp = tokio::time::timeout(
+            first_byte_timeout,
+            assemble_request(client, request, use_tls, observer.clone())?.send(),
+        )
+        .await
+        .map_err(|_| HttpErrorCode::ConnectionReadTimeout)?
+        .map_err(map_reqwest_err)?;
+
+        if let Some(head) = head
+            && let Some((next_request, next_use_tls)) = head.next_hop(&resp)
+        {
+            if hops == max_hops {
+                return Err(HttpErrorCode::LoopDetected);
+            }
+            hops += 1;
+
+            checks.check(&next_request, next_use_tls)?;
+            request = next_request;
+            use_tls = next_use_tls;
+            continue;
+        }
+
+        return Ok(IncomingResponse {
+            resp: assemble_response(resp)?,
+            worker: None,
+            between_bytes_timeout,
+        });
+    }
 }
 
 /// Build outgoing request object.
@@ -207,6 +345,7 @@ fn assemble_request(
     client: &reqwest::Client,
     request: hyper::Request<HyperOutgoingBody>,
     use_tls: bool,
+    observer: Observer,
 ) -> Result<reqwest::RequestBuilder, HttpErrorCode> {
     let (parts, body) = request.into_parts();
     let http::request::Parts {
@@ -231,7 +370,10 @@ fn assemble_request(
         .request(method, uri.to_string())
         .version(version)
         .headers(headers)
-        .body(reqwest::Body::wrap_stream(body.into_data_stream())))
+        .body(reqwest::Body::wrap_stream(
+            body.into_data_stream()
+                .inspect_ok(move |chunk| observer.http_egress(chunk.len() as u64)),
+        )))
 }
 
 /// Build incoming response object.
@@ -262,6 +404,11 @@ fn assemble_response(
 
 /// Map [`reqwest::Error`] to [`HttpErrorCode`].
 fn map_reqwest_err(e: reqwest::Error) -> HttpErrorCode {
+    // denied by IP policy
+    if extract_error_type::<ResolvedIpDenied>(&e).is_some() {
+        return HttpErrorCode::HttpRequestDenied;
+    }
+
     // known "internal" case
     if let Some(e) = extract_error_type::<ResolvedPortNotZero>(&e) {
         return HttpErrorCode::InternalError(Some(e.to_string()));
@@ -0,0 +1,207 @@
+//! Rate limiting of outbound HTTP requests.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+/// Maximum number of hosts that are tracked for [per-host](HttpRateLimit::per_host) limits.
+///
+/// Once reached, buckets that are full again are dropped, since they behave like new ones.
+const MAX_TRACKED_HOSTS: usize = 1024;
+
+/// Token-bucket rate limit for outbound HTTP requests.
+///
+/// See [`WasmPermissions::with_http_rate_limit`](crate::WasmPermissions::with_http_rate_limit).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct HttpRateLimit {
+    /// Sustained number of requests per second.
+    pub(crate) requests_per_sec: f64,
+
+    /// Maximum number of requests that can be issued at once.
+    pub(crate) burst: u32,
+
+    /// Track every destination host separately.
+    pub(crate) per_host: bool,
+}
+
+/// Token bucket.
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    /// Available tokens.
+    tokens: f64,
+
+    /// Last refill.
+    last: Instant,
+}
+
+impl Bucket {
+    /// Create full bucket.
+    fn new(limit: &HttpRateLimit, now: Instant) -> Self {
+        Self {
+            tokens: f64::from(limit.burst),
+            last: now,
+        }
+    }
+
+    /// Refill bucket.
+    fn refill(&mut self, limit: &HttpRateLimit, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * limit.requests_per_sec).min(f64::from(limit.burst));
+        self.last = now;
+    }
+
+    /// Refill bucket and try to take a token.
+    fn try_acquire(&mut self, limit: &HttpRateLimit, now: Instant) -> bool {
+        self.refill(limit, now);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Bucket is full, i.e. it behaves like a new one.
+    fn is_full(&self, limit: &HttpRateLimit) -> bool {
+        self.tokens >= f64::from(limit.burst)
+    }
+}
+
+/// State of the rate limiter.
+#[derive(Debug)]
+struct State {
+    /// Bucket for all requests.
+    global: Bucket,
+
+    /// Buckets by host, only used for [per-host](HttpRateLimit::per_host) limits.
+    hosts: HashMap<String, Bucket>,
+}
+
+/// Rate limiter of a guest instance.
+#[derive(Debug, Clone)]
+pub(crate) struct HttpRateLimiter {
+    /// Limit.
+    limit: HttpRateLimit,
+
+    /// State, shared with in-flight requests.
+    state: Arc<Mutex<State>>,
+}
+
+impl HttpRateLimiter {
+    /// Create new limiter.
+    pub(crate) fn new(limit: HttpRateLimit) -> Self {
+        let now = Instant::now();
+        Self {
+            limit,
+            state: Arc::new(Mutex::new(State {
+                global: Bucket::new(&limit, now),
+                hosts: HashMap::new(),
+            })),
+        }
+    }
+
+    /// Try to issue a request to the given host.
+    ///
+    /// Returns `false` if the rate limit is exceeded.
+    pub(crate) fn try_acquire(&self, host: &str) -> bool {
+        self.try_acquire_at(host, Instant::now())
+    }
+
+    /// Try to issue a request to the given host at the given time.
+    fn try_acquire_at(&self, host: &str, now: Instant) -> bool {
+        let Self { limit, state } = self;
+        let mut guard = state.lock().expect("HTTP rate limiter lock poisoned");
+
+        if !limit.per_host {
+            return guard.global.try_acquire(limit, now);
+        }
+
+        if !guard.hosts.contains_key(host) && guard.hosts.len() >= MAX_TRACKED_HOSTS {
+            guard.hosts.retain(|_host, bucket| {
+                bucket.refill(limit, now);
+                !bucket.is_full(limit)
+            });
+        }
+        guard
+            .hosts
+            .entry(host.to_owned())
+            .or_insert_with(|| Bucket::new(limit, now))
+            .try_acquire(limit, now)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_burst_and_refill() {
+        let limiter = HttpRateLimiter::new(HttpRateLimit {
+            requests_per_sec: 2.0,
+            burst: 3,
+            per_host: false,
+        });
+        let t0 = Instant::now();
+
+        assert!(limiter.try_acquire_at("a", t0));
+        assert!(limiter.try_acquire_at("b", t0));
+        assert!(limiter.try_acquire_at("a", t0));
+        assert!(!limiter.try_acquire_at("c", t0));
+
+        // half a second refills one token
+        let t1 = t0 + Duration::from_millis(500);
+        assert!(limiter.try_acquire_at("a", t1));
+        assert!(!limiter.try_acquire_at("a", t1));
+
+        // refill is capped by the burst
+        let t2 = t1 + Duration::from_secs(60);
+        for _ in 0..3 {
+            assert!(limiter.try_acquire_at("a", t2));
+        }
+        assert!(!limiter.try_acquire_at("a", t2));
+    }
+
+    #[test]
+    fn test_per_host() {
+        let limiter = HttpRateLimiter::new(HttpRateLimit {
+            requests_per_sec: 1.0,
+            burst: 1,
+            per_host: true,
+        });
+        let t0 = Instant::now();
+
+        assert!(limiter.try_acquire_at("a", t0));
+        assert!(!limiter.try_acquire_at("a", t0));
+        assert!(limiter.try_acquire_at("b", t0));
+        assert!(!limiter.try_acquire_at("b", t0));
+
+        let t1 = t0 + Duration::from_secs(1);
+        assert!(limiter.try_acquire_at("a", t1));
+    }
+
+    #[test]
+    fn test_tracked_hosts_are_bounded() {
+        let limiter = HttpRateLimiter::new(HttpRateLimit {
+            requests_per_sec: 1.0,
+            burst: 1,
+            per_host: true,
+        });
+        let t0 = Instant::now();
+
+        for i in 0..MAX_TRACKED_HOSTS {
+            assert!(limiter.try_acquire_at(&i.to_string(), t0));
+        }
+
+        // all buckets are full again, so they are dropped
+        let t1 = t0 + Duration::from_secs(1);
+        assert!(limiter.try_acquire_at("new", t1));
+        assert_eq!(limiter.state.lock().unwrap().hosts.len(), 1);
+
+        // the dropped hosts start with a full bucket
+        assert!(limiter.try_acquire_at("0", t1));
+    }
+}
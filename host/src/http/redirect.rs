@@ -0,0 +1,147 @@
+//! Redirect handling.
+
+use http::{HeaderMap, Method, StatusCode, Uri, Version, header};
+use wasmtime_wasi_http::p2::body::HyperOutgoingBody;
+
+/// Defines if the host follows HTTP redirects on behalf of the guest.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum HttpRedirectPolicy {
+    /// Return redirect responses to the guest.
+    ///
+    /// The guest may still follow redirects itself. Every request that it issues is checked like any other request.
+    #[default]
+    None,
+
+    /// Follow up to `max_hops` redirects.
+    ///
+    /// Every hop is checked like a request of the guest, i.e. it must pass the
+    /// [`HttpRequestValidator`](crate::HttpRequestValidator) and the [`IpPolicy`](crate::IpPolicy) and counts
+    /// towards the [rate limit](crate::WasmPermissions::with_http_rate_limit). A rejected hop fails the entire request
+    /// with `HTTP-request-denied`, exceeding `max_hops` fails it with `loop-detected`.
+    ///
+    /// Only redirects that do not need to resend the request body are followed, i.e. `301`, `302` and `303` (which
+    /// switch to `GET`) as well as `307` and `308` for `GET`, `HEAD` and `OPTIONS` requests. Other redirects are
+    /// returned to the guest. Credentials are dropped when a hop leaves the origin.
+    Follow {
+        /// Maximum number of redirects per request.
+        max_hops: usize,
+    },
+}
+
+impl HttpRedirectPolicy {
+    /// Maximum number of redirects per request.
+    pub(crate) fn max_hops(&self) -> usize {
+        match self {
+            Self::None => 0,
+            Self::Follow { max_hops } => *max_hops,
+        }
+    }
+}
+
+/// Request data that is required to follow a redirect.
+#[derive(Debug)]
+pub(crate) struct RequestHead {
+    /// Method.
+    method: Method,
+
+    /// URI.
+    uri: Uri,
+
+    /// HTTP version.
+    version: Version,
+
+    /// Headers.
+    headers: HeaderMap,
+
+    /// Use TLS.
+    use_tls: bool,
+}
+
+impl RequestHead {
+    /// Capture head of the given request.
+    pub(crate) fn new(request: &hyper::Request<HyperOutgoingBody>, use_tls: bool) -> Self {
+        Self {
+            method: request.method().clone(),
+            uri: request.uri().clone(),
+            version: request.version(),
+            headers: request.headers().clone(),
+            use_tls,
+        }
+    }
+
+    /// Request for the next hop, or [`None`] if the response shall be returned to the guest.
+    ///
+    /// Returns the request and if it uses TLS.
+    pub(crate) fn next_hop(
+        &self,
+        resp: &reqwest::Response,
+    ) -> Option<(hyper::Request<HyperOutgoingBody>, bool)> {
+        let status = resp.status();
+        let method = match status {
+            StatusCode::MOVED_PERMANENTLY | StatusCode::FOUND | StatusCode::SEE_OTHER => {
+                if self.method == Method::HEAD {
+                    Method::HEAD
+                } else {
+                    Method::GET
+                }
+            }
+            StatusCode::TEMPORARY_REDIRECT | StatusCode::PERMANENT_REDIRECT
+                if [Method::GET, Method::HEAD, Method::OPTIONS].contains(&self.method) =>
+            {
+                self.method.clone()
+            }
+            // other methods would need to resend the body
+            _ => {
+                return None;
+            }
+        };
+
+        let location = resp.headers().get(header::LOCATION)?.to_str().ok()?;
+        let scheme = if self.use_tls { "https" } else { "http" };
+        let authority = self.uri.authority()?;
+        let path = self.uri.path_and_query().map(|p| p.as_str()).unwrap_or("/");
+        let base = reqwest::Url::parse(&format!("{scheme}://{authority}{path}")).ok()?;
+        let next = base.join(location).ok()?;
+        let use_tls = match next.scheme() {
+            "https" => true,
+            "http" => false,
+            _ => {
+                return None;
+            }
+        };
+        let uri: Uri = next.as_str().parse().ok()?;
+
+        let mut headers = self.headers.clone();
+        headers.remove(header::HOST);
+        // the body is never resent
+        for name in [
+            header::CONTENT_ENCODING,
+            header::CONTENT_LENGTH,
+            header::CONTENT_TYPE,
+            header::TRANSFER_ENCODING,
+        ] {
+            headers.remove(name);
+        }
+        let same_origin = next.scheme() == base.scheme()
+            && next.host_str() == base.host_str()
+            && next.port_or_known_default() == base.port_or_known_default();
+        if !same_origin {
+            for name in [
+                header::AUTHORIZATION,
+                header::COOKIE,
+                header::PROXY_AUTHORIZATION,
+            ] {
+                headers.remove(name);
+            }
+        }
+
+        let mut builder = hyper::Request::builder()
+            .method(method)
+            .uri(uri)
+            .version(self.version);
+        *builder.headers_mut()? = headers;
+        let request = builder.body(HyperOutgoingBody::default()).ok()?;
+
+        Some((request, use_tls))
+    }
+}
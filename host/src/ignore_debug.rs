@@ -3,6 +3,7 @@
 use std::ops::{Deref, DerefMut};
 
 /// Helper to simplify [`Debug`] implementation by ignoring it.
+#[derive(Clone)]
 pub(crate) struct IgnoreDebug<T>(T);
 
 impl<T> std::fmt::Debug for IgnoreDebug<T> {
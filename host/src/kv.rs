@@ -0,0 +1,265 @@
+//! Key-value store that is exposed to the guest.
+
+use std::{collections::HashMap, sync::Arc};
+
+use datafusion_common::{DataFusionError, Result as DataFusionResult};
+use wasmtime::component::HasData;
+use wasmtime_wasi::async_trait;
+
+use crate::{
+    bindings::datafusion_udf_wasm::udf::kv::{self, KvError},
+    limiter::Limiter,
+};
+
+/// Limits of the key-value store.
+#[derive(Debug, Clone)]
+#[expect(missing_copy_implementations, reason = "allow later extensions")]
+pub struct KvLimits {
+    /// Maximum size of a key in bytes.
+    pub max_key_bytes: usize,
+
+    /// Maximum size of a value in bytes.
+    pub max_value_bytes: usize,
+
+    /// Maximum number of entries.
+    ///
+    /// Only enforced for the built-in in-memory store, see [`KvConfig::with_backend`].
+    pub max_entries: usize,
+
+    /// Maximum size of all keys and values in bytes.
+    ///
+    /// Only enforced for the built-in in-memory store, see [`KvConfig::with_backend`].
+    pub max_total_bytes: usize,
+}
+
+impl Default for KvLimits {
+    fn default() -> Self {
+        Self {
+            max_key_bytes: 256,
+            max_value_bytes: 64 * 1024, // 64KB
+            max_entries: 1_000,
+            max_total_bytes: 1024 * 1024, // 1MB
+        }
+    }
+}
+
+/// Storage for the key-value store of guests.
+///
+/// Use this to share data between guest instances or hosts, e.g. via Redis.
+#[async_trait]
+pub trait KvBackend: std::fmt::Debug + Send + Sync + 'static {
+    /// Get value.
+    async fn get(&self, key: &[u8]) -> DataFusionResult<Option<Vec<u8>>>;
+
+    /// Set value, replacing any existing one.
+    async fn set(&self, key: Vec<u8>, value: Vec<u8>) -> DataFusionResult<()>;
+
+    /// Delete value, returns `true` if the key existed.
+    async fn delete(&self, key: &[u8]) -> DataFusionResult<bool>;
+}
+
+/// Configuration of the key-value store, see [`WasmPermissions::with_kv`](crate::WasmPermissions::with_kv).
+#[derive(Debug, Clone, Default)]
+pub struct KvConfig {
+    /// Limits.
+    pub(crate) limits: KvLimits,
+
+    /// Storage, or [`None`] for the built-in in-memory store.
+    pub(crate) backend: Option<Arc<dyn KvBackend>>,
+}
+
+impl KvConfig {
+    /// Create config that uses the built-in in-memory store.
+    ///
+    /// Every guest instance gets its own store, which is accounted against the memory pool and lives as long as the
+    /// instance.
+    pub fn new(limits: KvLimits) -> Self {
+        Self {
+            limits,
+            backend: None,
+        }
+    }
+
+    /// Use given storage instead of the built-in in-memory store.
+    ///
+    /// Key and value sizes are still checked by the host, but the backend is responsible for limiting the number of
+    /// entries and the total size.
+    pub fn with_backend(self, backend: Arc<dyn KvBackend>) -> Self {
+        Self {
+            backend: Some(backend),
+            ..self
+        }
+    }
+}
+
+/// Key-value state of a guest.
+#[derive(Debug)]
+pub(crate) struct KvState {
+    /// Limits.
+    limits: KvLimits,
+
+    /// Storage.
+    store: KvStore,
+}
+
+/// Storage of [`KvState`].
+#[derive(Debug)]
+enum KvStore {
+    /// Access denied.
+    Denied,
+
+    /// Built-in in-memory store.
+    Memory {
+        /// Entries.
+        entries: HashMap<Vec<u8>, Vec<u8>>,
+
+        /// Size of all keys and values in bytes.
+        total_bytes: usize,
+
+        /// Accounts the entries against the memory pool.
+        limiter: Limiter,
+    },
+
+    /// User-provided backend.
+    Backend(Arc<dyn KvBackend>),
+}
+
+impl KvState {
+    /// Create new state.
+    pub(crate) fn new(config: Option<&KvConfig>, limiter: Limiter) -> Self {
+        let Some(KvConfig { limits, backend }) = config else {
+            return Self {
+                limits: KvLimits::default(),
+                store: KvStore::Denied,
+            };
+        };
+
+        let store = match backend {
+            Some(backend) => KvStore::Backend(Arc::clone(backend)),
+            None => KvStore::Memory {
+                entries: HashMap::new(),
+                total_bytes: 0,
+                limiter,
+            },
+        };
+        Self {
+            limits: limits.clone(),
+            store,
+        }
+    }
+
+    /// Check key size.
+    fn check_key(&self, key: &[u8]) -> Result<(), KvError> {
+        if key.len() > self.limits.max_key_bytes {
+            return Err(KvError::LimitExceeded(format!(
+                "key too large: got={}, limit={}",
+                key.len(),
+                self.limits.max_key_bytes
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Convert backend error.
+fn backend_err(e: DataFusionError) -> KvError {
+    KvError::Backend(e.to_string())
+}
+
+impl kv::Host for KvState {
+    async fn get(&mut self, key: Vec<u8>) -> wasmtime::Result<Result<Option<Vec<u8>>, KvError>> {
+        if let Err(e) = self.check_key(&key) {
+            return Ok(Err(e));
+        }
+
+        let res = match &self.store {
+            KvStore::Denied => Err(KvError::Denied),
+            KvStore::Memory { entries, .. } => Ok(entries.get(&key).cloned()),
+            KvStore::Backend(backend) => backend.get(&key).await.map_err(backend_err),
+        };
+        Ok(res)
+    }
+
+    async fn set(&mut self, key: Vec<u8>, value: Vec<u8>) -> wasmtime::Result<Result<(), KvError>> {
+        if let Err(e) = self.check_key(&key) {
+            return Ok(Err(e));
+        }
+        if value.len() > self.limits.max_value_bytes {
+            return Ok(Err(KvError::LimitExceeded(format!(
+                "value too large: got={}, limit={}",
+                value.len(),
+                self.limits.max_value_bytes
+            ))));
+        }
+
+        match &mut self.store {
+            KvStore::Denied => Ok(Err(KvError::Denied)),
+            KvStore::Memory {
+                entries,
+                total_bytes,
+                limiter,
+            } => {
+                let new_bytes = key.len() + value.len();
+                let old_bytes = entries.get(&key).map(|old| key.len() + old.len());
+
+                if old_bytes.is_none() && entries.len() >= self.limits.max_entries {
+                    return Ok(Err(KvError::LimitExceeded(format!(
+                        "too many entries: limit={}",
+                        self.limits.max_entries
+                    ))));
+                }
+                let total = *total_bytes - old_bytes.unwrap_or_default() + new_bytes;
+                if total > self.limits.max_total_bytes {
+                    return Ok(Err(KvError::LimitExceeded(format!(
+                        "store too large: got={total}, limit={}",
+                        self.limits.max_total_bytes
+                    ))));
+                }
+
+                if let Err(e) = limiter.grow(new_bytes) {
+                    return Ok(Err(KvError::LimitExceeded(
+                        DataFusionError::from(e).to_string(),
+                    )));
+                }
+                if let Some(old_bytes) = old_bytes {
+                    limiter.shrink(old_bytes)?;
+                }
+                entries.insert(key, value);
+                *total_bytes = total;
+                Ok(Ok(()))
+            }
+            KvStore::Backend(backend) => Ok(backend.set(key, value).await.map_err(backend_err)),
+        }
+    }
+
+    async fn delete(&mut self, key: Vec<u8>) -> wasmtime::Result<Result<bool, KvError>> {
+        if let Err(e) = self.check_key(&key) {
+            return Ok(Err(e));
+        }
+
+        match &mut self.store {
+            KvStore::Denied => Ok(Err(KvError::Denied)),
+            KvStore::Memory {
+                entries,
+                total_bytes,
+                limiter,
+            } => match entries.remove(&key) {
+                Some(old) => {
+                    let old_bytes = key.len() + old.len();
+                    limiter.shrink(old_bytes)?;
+                    *total_bytes -= old_bytes;
+                    Ok(Ok(true))
+                }
+                None => Ok(Ok(false)),
+            },
+            KvStore::Backend(backend) => Ok(backend.delete(&key).await.map_err(backend_err)),
+        }
+    }
+}
+
+/// Marker struct to tell linker that we provide a key-value store.
+pub(crate) struct HasKv;
+
+impl HasData for HasKv {
+    type Data<'a> = &'a mut KvState;
+}
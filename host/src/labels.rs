@@ -0,0 +1,113 @@
+//! Labels that identify the origin of errors and observer events.
+
+use std::collections::BTreeMap;
+
+use datafusion_common::{DataFusionError, Result as DataFusionResult};
+
+/// Key-value labels -- e.g. query ID or tenant -- that are attached to errors and [observer](crate::SandboxObserver)
+/// events.
+///
+/// Labels are collected from two sources:
+///
+/// 1. **creation:** [`WasmPermissions::with_context_labels`](crate::WasmPermissions::with_context_labels), e.g. the
+///    tenant that defined the UDF
+/// 2. **invocation:** [`WasmUdfConfig::context_labels`](crate::WasmUdfConfig::context_labels), e.g. the query ID
+///
+/// Invocation labels override creation labels with the same key.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ContextLabels(BTreeMap<String, String>);
+
+impl ContextLabels {
+    /// Create empty labels.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add label, replacing an existing label with the same key.
+    pub fn with_label(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.0.insert(key.into(), value.into());
+        self
+    }
+
+    /// Get label value.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).map(String::as_str)
+    }
+
+    /// Iterate over all labels, ordered by key.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    /// Returns `true` if there are no labels.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Parse comma-separated `key=value` pairs, e.g. `query_id=42,tenant=acme`.
+    ///
+    /// Whitespace around keys and values is trimmed, empty entries are ignored.
+    pub(crate) fn parse(s: &str) -> DataFusionResult<Self> {
+        s.split(',')
+            .filter(|entry| !entry.trim().is_empty())
+            .try_fold(Self::new(), |labels, entry| {
+                let (key, value) = entry.split_once('=').ok_or_else(|| {
+                    DataFusionError::Configuration(format!(
+                        "invalid context label `{}`, expected `key=value`",
+                        entry.trim()
+                    ))
+                })?;
+                Ok(labels.with_label(key.trim(), value.trim()))
+            })
+    }
+
+    /// Combine labels, `other` takes precedence.
+    pub(crate) fn merge(&self, other: &Self) -> Self {
+        let mut merged = self.clone();
+        merged
+            .0
+            .extend(other.0.iter().map(|(k, v)| (k.clone(), v.clone())));
+        merged
+    }
+}
+
+impl std::fmt::Display for ContextLabels {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (idx, (key, value)) in self.iter().enumerate() {
+            if idx > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{key}={value}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let labels = ContextLabels::parse(" query_id = 42 ,, tenant=acme,").unwrap();
+        assert_eq!(labels.get("query_id"), Some("42"));
+        assert_eq!(labels.get("tenant"), Some("acme"));
+        assert_eq!(labels.to_string(), "query_id=42, tenant=acme");
+
+        assert!(ContextLabels::parse("").unwrap().is_empty());
+
+        insta::assert_snapshot!(
+            ContextLabels::parse("a=1,b").unwrap_err(),
+            @"Invalid or Unsupported Configuration: invalid context label `b`, expected `key=value`",
+        );
+    }
+
+    #[test]
+    fn test_merge() {
+        let a = ContextLabels::new()
+            .with_label("tenant", "acme")
+            .with_label("query_id", "1");
+        let b = ContextLabels::new().with_label("query_id", "2");
+        assert_eq!(a.merge(&b).to_string(), "query_id=2, tenant=acme");
+    }
+}
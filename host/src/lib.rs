@@ -4,21 +4,53 @@
 //! [DataFusion]: https://datafusion.apache.org/
 
 pub use crate::{
+    clocks::{ClockPolicy, ClockProvider},
     component::WasmComponentPrecompiled,
-    conversion::limits::TrustedDataLimits,
+    config::WasmUdfConfig,
+    config_forwarding::ConfigLimits,
+    conversion::{compression::IpcCompression, limits::TrustedDataLimits},
+    cost::WasmUdfCostEstimate,
+    error::{GuestTraceback, ResourceLimitKind, TracebackFrame, WasmUdfError},
+    health::WasmComponentHealth,
     http::{
         AllowCertainHttpRequests, AllowHttpEndpoint, AllowHttpHost, HttpConfig, HttpConnectionMode,
-        HttpMethod, HttpPort, HttpRequestRejected, HttpRequestValidator, RejectAllHttpRequests,
+        HttpMethod, HttpPoolSharing, HttpPort, HttpRedirectPolicy, HttpRequestRejected,
+        HttpRequestValidator, InvalidIpCidr, IpCidr, IpPolicy, RejectAllHttpRequests,
         TlsClientConfig,
     },
-    limiter::StaticResourceLimits,
+    kv::{KvBackend, KvConfig, KvLimits},
+    labels::ContextLabels,
+    limiter::{DynamicMemoryLimits, StaticResourceLimits},
+    metadata::{WasmComponentDescription, WitVersion},
+    names::UdfNameCollisionPolicy,
+    nn::NnModel,
+    observer::{DeniedIo, IoViolationLog, SandboxInstanceId, SandboxObserver},
     permissions::WasmPermissions,
+    random::RandomPolicy,
+    registry::WasmUdfRegistry,
+    secrets::SecretProvider,
+    sharing::InstanceSharing,
+    shutdown::WasmRuntimeHandle,
+    signature::{ComponentVerificationError, ComponentVerifier},
+    stderr::{StderrPolicy, StderrRedactor},
     udf::WasmScalarUdf,
+    usage::WasmResourceUsage,
+    validate::{SourceDiagnostic, WasmSourceValidator},
     vfs::limits::VfsLimits,
+    warm_pool::WarmPool,
+};
+
+#[cfg(feature = "metrics-export")]
+pub use crate::metrics_export::{
+    HistogramSnapshot, INVOCATION_DURATION_BUCKETS, MetricCounter, SandboxMetrics,
+    SandboxMetricsSnapshot,
 };
 
 #[cfg(feature = "compiler")]
-pub use crate::component::CompilationFlags;
+pub use crate::{
+    component::CompilationFlags,
+    self_check::{SelfCheckReport, SelfCheckStep, SelfCheckStepReport, self_check},
+};
 
 // unused-crate-dependencies false positives
 #[cfg(test)]
@@ -43,15 +75,44 @@ use time as _;
 use tokio_rustls as _;
 
 mod bindings;
+mod clocks;
 mod component;
+mod config;
+mod config_forwarding;
 mod conversion;
+mod cost;
 mod error;
+mod health;
 mod http;
 mod ignore_debug;
+mod kv;
+mod labels;
 mod limiter;
 mod linker;
+mod metadata;
+#[cfg(feature = "metrics-export")]
+mod metrics_export;
+mod names;
+mod nn;
+mod observer;
 mod permissions;
+mod random;
+mod recycle;
+mod registered;
+mod registry;
+mod secrets;
+#[cfg(feature = "compiler")]
+mod self_check;
+mod sharing;
+mod shutdown;
+mod signature;
 mod state;
+mod stderr;
 mod tokio_helpers;
+mod trace;
 mod udf;
+mod usage;
+mod validate;
 mod vfs;
+mod volatility;
+mod warm_pool;
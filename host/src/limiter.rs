@@ -1,12 +1,17 @@
 //! Resource limiter.
 
-use std::sync::{Arc, Mutex};
+use std::sync::{
+    Arc, Mutex,
+    atomic::{AtomicUsize, Ordering},
+};
 
 use datafusion_common::DataFusionError;
-use datafusion_execution::memory_pool::{MemoryConsumer, MemoryPool, MemoryReservation};
+use datafusion_execution::memory_pool::{
+    MemoryConsumer, MemoryLimit, MemoryPool, MemoryReservation,
+};
 use wasmtime::{ResourceLimiter, error::Context};
 
-use crate::error::LimitExceeded;
+use crate::error::{LimitExceeded, ResourceLimitKind};
 
 /// Static resource limits.
 #[derive(Debug, Clone)]
@@ -45,16 +50,128 @@ impl Default for StaticResourceLimits {
     }
 }
 
+/// Memory limits that adapt to the state of the DataFusion [`MemoryPool`].
+///
+/// The guest memory is always accounted against the pool. These limits additionally control how the guest interacts
+/// with other consumers of the same pool.
+#[derive(Debug, Clone, Default)]
+#[expect(missing_copy_implementations, reason = "allow later extensions")]
+pub struct DynamicMemoryLimits {
+    /// Bytes that are reserved from the pool when the guest is created.
+    ///
+    /// Creation fails if the pool cannot provide them. The guest can then grow up to this size without asking the
+    /// pool again, so it is not affected by pool pressure for that part. The reservation is kept even if the guest
+    /// uses less.
+    pub prereserve_bytes: usize,
+
+    /// Bytes that must stay available in the pool after the guest grew beyond its
+    /// [pre-reservation](Self::prereserve_bytes).
+    ///
+    /// If the pool is under pressure, the next allocation of the guest fails -- which the guest may handle
+    /// gracefully, e.g. Python raises a `MemoryError` -- instead of the pool rejecting allocations of other operators
+    /// of the same query. Only applies to pools with a [finite limit](MemoryLimit::Finite).
+    ///
+    /// Zero disables this check.
+    pub pool_headroom_bytes: usize,
+}
+
+/// Memory reservation that is shared by a [`Limiter`] and its clones.
+#[derive(Debug)]
+struct SharedReservation {
+    /// DataFusion memory reservation.
+    reservation: MemoryReservation,
+
+    /// Pool that [`reservation`](Self::reservation) belongs to.
+    pool: Arc<dyn MemoryPool>,
+
+    /// Bytes that are actually used.
+    ///
+    /// This may be less than the size of the reservation, see [`DynamicMemoryLimits::prereserve_bytes`].
+    used: usize,
+
+    /// Dynamic limits.
+    limits: DynamicMemoryLimits,
+}
+
+impl SharedReservation {
+    /// Grow usage, growing the reservation if required.
+    fn grow(&mut self, bytes: usize) -> Result<(), DataFusionError> {
+        let used = self.used.checked_add(bytes).ok_or_else(|| {
+            DataFusionError::ResourcesExhausted("memory usage overflow".to_owned())
+        })?;
+        let additional = used.saturating_sub(self.reservation.size());
+        if additional > 0 {
+            self.check_pressure(additional)?;
+            self.reservation.try_grow(additional)?;
+        }
+        self.used = used;
+        Ok(())
+    }
+
+    /// Shrink usage, shrinking the reservation down to the pre-reservation.
+    ///
+    /// Returns the remaining usage.
+    fn shrink(&mut self, bytes: usize) -> Result<usize, DataFusionError> {
+        let used = self.used.checked_sub(bytes).ok_or_else(|| {
+            DataFusionError::Internal(format!(
+                "cannot free {bytes} bytes, only {} bytes are used",
+                self.used
+            ))
+        })?;
+        self.used = used;
+        let keep = used.max(self.limits.prereserve_bytes);
+        self.reservation
+            .shrink(self.reservation.size().saturating_sub(keep));
+        Ok(used)
+    }
+
+    /// Check that the pool has enough headroom for additional bytes.
+    fn check_pressure(&self, additional: usize) -> Result<(), DataFusionError> {
+        let headroom = self.limits.pool_headroom_bytes;
+        if headroom == 0 {
+            return Ok(());
+        }
+        let MemoryLimit::Finite(limit) = self.pool.memory_limit() else {
+            return Ok(());
+        };
+
+        let available = limit.saturating_sub(self.pool.reserved());
+        if available < additional.saturating_add(headroom) {
+            return Err(DataFusionError::ResourcesExhausted(format!(
+                "memory pool under pressure: available={available}, requested={additional}, headroom={headroom}"
+            )));
+        }
+        Ok(())
+    }
+}
+
 /// Resource limiter.
 #[derive(Debug)]
 pub(crate) struct Limiter {
     /// DataFusion memory reservation.
     ///
     /// This is ONLY used for bytes, not for any other resources.
-    memory_reservation: Arc<Mutex<MemoryReservation>>,
+    memory_reservation: Arc<Mutex<SharedReservation>>,
 
     /// Limits.
     limits: StaticResourceLimits,
+
+    /// Limit that rejected the last failed growth, see [`take_exceeded`](Self::take_exceeded).
+    exceeded: Option<ResourceLimitKind>,
+
+    /// Peak size of the memory reservation, in bytes.
+    ///
+    /// This is shared with all clones AND [split](Self::split) limiters.
+    peak_reserved_bytes: Arc<AtomicUsize>,
+
+    /// Bytes that were accounted via this limiter and its clones, but NOT via [split](Self::split) limiters.
+    accounted_bytes: Arc<AtomicUsize>,
+
+    /// Size of all linear memories of the guest, in bytes.
+    linear_memory_bytes: usize,
+
+    /// Number of elements of all tables of the guest.
+    table_elements: usize,
 }
 
 impl Clone for Limiter {
@@ -62,30 +179,117 @@ impl Clone for Limiter {
         Self {
             memory_reservation: Arc::clone(&self.memory_reservation),
             limits: self.limits.clone(),
+            exceeded: self.exceeded,
+            peak_reserved_bytes: Arc::clone(&self.peak_reserved_bytes),
+            accounted_bytes: Arc::clone(&self.accounted_bytes),
+            linear_memory_bytes: self.linear_memory_bytes,
+            table_elements: self.table_elements,
         }
     }
 }
 
 impl Limiter {
     /// Create new limiter.
-    pub(crate) fn new(limits: StaticResourceLimits, pool: &Arc<dyn MemoryPool>) -> Self {
-        let memory_reservation = MemoryConsumer::new("WASM UDF resources").register(pool);
+    pub(crate) fn new(
+        limits: StaticResourceLimits,
+        dynamic_limits: DynamicMemoryLimits,
+        pool: &Arc<dyn MemoryPool>,
+    ) -> Self {
+        let memory_reservation = SharedReservation {
+            reservation: MemoryConsumer::new("WASM UDF resources").register(pool),
+            pool: Arc::clone(pool),
+            used: 0,
+            limits: dynamic_limits,
+        };
         Self {
             memory_reservation: Arc::new(Mutex::new(memory_reservation)),
             limits,
+            exceeded: None,
+            peak_reserved_bytes: Arc::default(),
+            accounted_bytes: Arc::default(),
+            linear_memory_bytes: 0,
+            table_elements: 0,
+        }
+    }
+
+    /// Create limiter that shares the memory reservation but tracks its [accounted bytes](Self::accounted_bytes)
+    /// separately.
+    pub(crate) fn split(&self) -> Self {
+        Self {
+            accounted_bytes: Arc::default(),
+            linear_memory_bytes: 0,
+            table_elements: 0,
+            ..self.clone()
         }
     }
 
+    /// Reserve [`DynamicMemoryLimits::prereserve_bytes`] from the pool.
+    pub(crate) fn prereserve(&self) -> Result<(), GrowthError> {
+        let mut self_guard = self
+            .memory_reservation
+            .lock()
+            .expect("memory reservation lock poisoned");
+        let additional = self_guard
+            .limits
+            .prereserve_bytes
+            .saturating_sub(self_guard.reservation.size());
+        self_guard
+            .reservation
+            .try_grow(additional)
+            .map_err(|e| GrowthError(e.context("pre-reserve memory")))?;
+        self.peak_reserved_bytes
+            .fetch_max(self_guard.reservation.size(), Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Get and reset the limit that rejected the last failed growth.
+    pub(crate) fn take_exceeded(&mut self) -> Option<ResourceLimitKind> {
+        self.exceeded.take()
+    }
+
+    /// Bytes that are currently accounted via this limiter and its clones.
+    pub(crate) fn accounted_bytes(&self) -> usize {
+        self.accounted_bytes.load(Ordering::SeqCst)
+    }
+
+    /// Current size of the memory reservation, in bytes.
+    pub(crate) fn reserved_bytes(&self) -> usize {
+        self.memory_reservation
+            .lock()
+            .expect("memory reservation lock poisoned")
+            .reservation
+            .size()
+    }
+
+    /// Peak size of the memory reservation, in bytes.
+    pub(crate) fn peak_reserved_bytes(&self) -> usize {
+        self.peak_reserved_bytes.load(Ordering::SeqCst)
+    }
+
+    /// Size of all linear memories of the guest, in bytes.
+    pub(crate) fn linear_memory_bytes(&self) -> usize {
+        self.linear_memory_bytes
+    }
+
+    /// Number of elements of all tables of the guest.
+    pub(crate) fn table_elements(&self) -> usize {
+        self.table_elements
+    }
+
     /// Grow memory usage.
     pub(crate) fn grow(&self, bytes: usize) -> Result<(), GrowthError> {
         let mut self_guard = self
             .memory_reservation
             .lock()
             .expect("memory reservation lock poisoned");
-        self_guard.try_grow(bytes).map_err(|e| {
+        self_guard.grow(bytes).map_err(|e| {
             log::debug!("failed to grow memory: {e}");
             GrowthError(e)
-        })
+        })?;
+        self.peak_reserved_bytes
+            .fetch_max(self_guard.reservation.size(), Ordering::SeqCst);
+        self.accounted_bytes.fetch_add(bytes, Ordering::SeqCst);
+        Ok(())
     }
 
     /// Shrink memory usage.
@@ -94,10 +298,12 @@ impl Limiter {
             .memory_reservation
             .lock()
             .expect("memory reservation lock poisoned");
-        self_guard.try_shrink(bytes).map_err(|e| {
+        let remaining = self_guard.shrink(bytes).map_err(|e| {
             log::debug!("failed to shrink memory: {e}");
             GrowthError(e)
-        })
+        })?;
+        self.accounted_bytes.fetch_sub(bytes, Ordering::SeqCst);
+        Ok(remaining)
     }
 
     /// Inner implementation of [`ResourceLimiter::table_growing`]
@@ -141,9 +347,13 @@ impl ResourceLimiter for Limiter {
             .expect("wasmtime accounting correct");
 
         match self.grow(growth) {
-            Ok(()) => Ok(true),
+            Ok(()) => {
+                self.linear_memory_bytes += growth;
+                Ok(true)
+            }
             Err(e) => {
                 log::debug!("memory growth failed: {}", e.0);
+                self.exceeded = Some(ResourceLimitKind::Memory);
                 // reject allocation but do NOT trap
                 Ok(false)
             }
@@ -158,9 +368,17 @@ impl ResourceLimiter for Limiter {
         _maximum: Option<usize>,
     ) -> wasmtime::Result<bool> {
         match self.table_growing_inner(current, desired) {
-            Ok(()) => Ok(true),
+            Ok(()) => {
+                self.table_elements += desired - current;
+                Ok(true)
+            }
             Err(e) => {
                 log::debug!("table growth failed: {e}");
+                self.exceeded = Some(if e.downcast_ref::<LimitExceeded>().is_some() {
+                    ResourceLimitKind::TableElements
+                } else {
+                    ResourceLimitKind::Memory
+                });
                 // reject allocation but do NOT trap
                 Ok(false)
             }
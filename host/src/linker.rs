@@ -10,28 +10,87 @@ use wasmtime::{
 use wasmtime_wasi::{ResourceTable, WasiView};
 
 use crate::{
+    WitVersion,
     bindings::Datafusion,
+    kv::HasKv,
+    metadata::{WIT_PACKAGE, exported_wit_version},
+    random::HasRandom,
+    secrets::HasSecrets,
     state::WasmStateImpl,
     vfs::{HasFs, VfsView},
 };
 
 /// Link everything.
+///
+/// Returns the bindings and the [negotiated](negotiate) WIT version.
 pub(crate) async fn link(
     engine: &Engine,
     component: &Component,
     store: &mut Store<WasmStateImpl>,
-) -> Result<Arc<Datafusion>> {
+) -> Result<(Arc<Datafusion>, WitVersion)> {
+    let wit_version = negotiate(engine, component).context("negotiate WIT version")?;
+
     let mut linker = Linker::new(engine);
     link_wasi_p2(&mut linker).context("link WASI p2")?;
     wasmtime_wasi_http::p2::add_only_http_to_linker_async(&mut linker)
         .context("link WASI p2 HTTP")?;
+    crate::bindings::datafusion_udf_wasm::udf::kv::add_to_linker::<WasmStateImpl, HasKv>(
+        &mut linker,
+        |t| &mut t.kv,
+    )
+    .context("link key-value store")?;
+    crate::bindings::datafusion_udf_wasm::udf::secrets::add_to_linker::<WasmStateImpl, HasSecrets>(
+        &mut linker,
+        |t| &mut t.secrets,
+    )
+    .context("link secrets")?;
+    #[cfg(feature = "nn")]
+    wasmtime_wasi_nn::wit::add_to_linker(&mut linker, |state: &mut WasmStateImpl| {
+        wasmtime_wasi_nn::wit::WasiNnView::new(&mut state.resource_table, &mut state.nn_ctx)
+    })
+    .context("link WASI NN")?;
 
     let bindings = Arc::new(
         Datafusion::instantiate_async(store, component, &linker)
             .await
             .context("initialize bindings")?,
     );
-    Ok(bindings)
+    Ok((bindings, wit_version))
+}
+
+/// Detect which WIT version the component exports and check that the host supports it.
+///
+/// This gives a clearer error than the type mismatch that instantiation would report. Hosts can use the negotiated
+/// version to adapt to older components, see [`WitVersion::SUPPORTED`].
+fn negotiate(engine: &Engine, component: &Component) -> Result<WitVersion> {
+    let component_type = component.component_type();
+    let exported = exported_wit_version(component_type.exports(engine).map(|(name, _)| name))
+        .ok_or_else(|| {
+            wasmtime::Error::msg(format!(
+                "component does not export WIT package `{WIT_PACKAGE}`"
+            ))
+        })?;
+    let version = WitVersion::parse(exported).ok_or_else(|| {
+        wasmtime::Error::msg(format!(
+            "component exports invalid WIT version `{WIT_PACKAGE}@{exported}`"
+        ))
+    })?;
+
+    if WitVersion::SUPPORTED
+        .iter()
+        .any(|supported| supported.is_compatible_with(&version))
+    {
+        Ok(version)
+    } else {
+        let supported = WitVersion::SUPPORTED
+            .iter()
+            .map(|v| format!("`{WIT_PACKAGE}@{v}`"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        Err(wasmtime::Error::msg(format!(
+            "component implements WIT package `{WIT_PACKAGE}@{version}` but host supports {supported}"
+        )))
+    }
 }
 
 /// Link WASIp2 interfaces.
@@ -40,7 +99,6 @@ fn link_wasi_p2(linker: &mut Linker<WasmStateImpl>) -> Result<()> {
         cli::{WasiCli, WasiCliView},
         clocks::{WasiClocks, WasiClocksView},
         p2::bindings,
-        random::WasiRandom,
         sockets::{WasiSockets, WasiSocketsView},
     };
 
@@ -102,14 +160,12 @@ fn link_wasi_p2(linker: &mut Linker<WasmStateImpl>) -> Result<()> {
         linker,
         WasmStateImpl::vfs,
     )?;
-    bindings::random::random::add_to_linker::<WasmStateImpl, WasiRandom>(linker, |t| {
-        t.ctx().ctx.random()
-    })?;
-    bindings::random::insecure::add_to_linker::<WasmStateImpl, WasiRandom>(linker, |t| {
-        t.ctx().ctx.random()
+    bindings::random::random::add_to_linker::<WasmStateImpl, HasRandom>(linker, |t| &mut t.random)?;
+    bindings::random::insecure::add_to_linker::<WasmStateImpl, HasRandom>(linker, |t| {
+        &mut t.random
     })?;
-    bindings::random::insecure_seed::add_to_linker::<WasmStateImpl, WasiRandom>(linker, |t| {
-        t.ctx().ctx.random()
+    bindings::random::insecure_seed::add_to_linker::<WasmStateImpl, HasRandom>(linker, |t| {
+        &mut t.random
     })?;
     bindings::sockets::instance_network::add_to_linker::<WasmStateImpl, WasiSockets>(
         linker,
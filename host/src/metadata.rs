@@ -0,0 +1,349 @@
+//! Metadata of WASM components.
+
+use wasmtime::{Engine, component::Component};
+
+/// WIT package that guests implement.
+pub(crate) const WIT_PACKAGE: &str = "datafusion-udf-wasm:udf";
+
+/// Version of the `datafusion-udf-wasm:udf` WIT package.
+///
+/// Versions follow [semver](https://semver.org/). For `0.x` versions, the minor version is treated like a major one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct WitVersion {
+    /// Major version.
+    pub major: u32,
+
+    /// Minor version.
+    pub minor: u32,
+
+    /// Patch version.
+    pub patch: u32,
+}
+
+impl WitVersion {
+    /// Version that this host was built against.
+    ///
+    /// This is always the newest [supported version](Self::SUPPORTED).
+    pub const HOST: Self = Self::new(0, 9, 0);
+
+    /// Versions that the host can link, oldest first.
+    ///
+    /// Components that export any version that is [compatible](Self::is_compatible_with) with one of these are
+    /// accepted. Components built against newer versions of a compatible release work as long as they do not rely on
+    /// additions that this host does not know.
+    pub const SUPPORTED: &[Self] = &[Self::HOST];
+
+    /// Create new version.
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    /// Parse version, e.g. `0.8.0`.
+    ///
+    /// Pre-release and build metadata are NOT supported.
+    pub(crate) fn parse(s: &str) -> Option<Self> {
+        let mut parts = s.split('.').map(|part| part.parse().ok());
+        let version = Self::new(parts.next()??, parts.next()??, parts.next()??);
+        parts.next().is_none().then_some(version)
+    }
+
+    /// Returns `true` if both versions belong to the same semver-compatible release.
+    pub fn is_compatible_with(&self, other: &Self) -> bool {
+        if self.major == 0 {
+            other.major == 0 && self.minor == other.minor
+        } else {
+            self.major == other.major
+        }
+    }
+}
+
+impl std::fmt::Display for WitVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Self {
+            major,
+            minor,
+            patch,
+        } = self;
+        write!(f, "{major}.{minor}.{patch}")
+    }
+}
+
+/// Name of the custom section that contains the language/runtime of the guest, e.g. `python 3.14`.
+#[cfg(feature = "compiler")]
+const RUNTIME_SECTION: &str = "datafusion-udf-wasm:runtime";
+
+/// Description of a [pre-compiled component](crate::WasmComponentPrecompiled::describe).
+///
+/// Registries can use this to display what a component supports.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WasmComponentDescription {
+    /// Version of the `datafusion-udf-wasm:udf` WIT package that the component implements.
+    ///
+    /// This is [`None`] if the component does not export the package at all.
+    pub wit_version: Option<String>,
+
+    /// Names of the exported interfaces, e.g. `datafusion-udf-wasm:udf/types@0.9.0`.
+    pub exports: Vec<String>,
+
+    /// Names of the imported interfaces, e.g. `wasi:http/outgoing-handler@0.2.0`.
+    pub imports: Vec<String>,
+
+    /// Language/runtime of the guest, e.g. `python 3.14`.
+    ///
+    /// Guests embed this as a custom section named `datafusion-udf-wasm:runtime`. Pre-compiled data does not contain
+    /// custom sections, so this is only known for components that were [compiled](crate::WasmComponentPrecompiled::compile)
+    /// in this process, NOT for [loaded](crate::WasmComponentPrecompiled::load) ones.
+    pub runtime: Option<String>,
+
+    /// Size of the [pre-compiled data](crate::WasmComponentPrecompiled::store) in bytes.
+    pub precompiled_bytes: usize,
+}
+
+impl WasmComponentDescription {
+    /// Describe component.
+    pub(crate) fn new(
+        component: &Component,
+        engine: &Engine,
+        runtime: Option<String>,
+        precompiled_bytes: usize,
+    ) -> Self {
+        let component_type = component.component_type();
+        let exports = component_type
+            .exports(engine)
+            .map(|(name, _)| name.to_owned())
+            .collect::<Vec<_>>();
+        let imports = component_type
+            .imports(engine)
+            .map(|(name, _)| name.to_owned())
+            .collect();
+        let wit_version =
+            exported_wit_version(exports.iter().map(String::as_str)).map(ToOwned::to_owned);
+
+        Self {
+            wit_version,
+            exports,
+            imports,
+            runtime,
+            precompiled_bytes,
+        }
+    }
+}
+
+/// Extract version of the `datafusion-udf-wasm:udf` WIT package from the names of the exported interfaces.
+pub(crate) fn exported_wit_version<'a>(
+    exports: impl IntoIterator<Item = &'a str>,
+) -> Option<&'a str> {
+    exports
+        .into_iter()
+        .filter_map(|name| name.strip_prefix(WIT_PACKAGE)?.strip_prefix('/'))
+        .find_map(|name| name.split_once('@').map(|(_, version)| version))
+}
+
+/// Extract language/runtime from the custom section of a WASM payload.
+///
+/// Searches the component itself as well as nested components and core modules.
+#[cfg(feature = "compiler")]
+pub(crate) fn runtime_from_wasm(wasm_binary: &[u8]) -> Option<String> {
+    // magic + version/layer
+    let mut data = wasm_binary.strip_prefix(b"\0asm")?.get(4..)?;
+
+    while !data.is_empty() {
+        let (&id, rest) = data.split_first()?;
+        let (size, rest) = read_leb128(rest)?;
+        let section = rest.get(..size)?;
+        data = &rest[size..];
+
+        match id {
+            // custom section
+            0 => {
+                let (name_len, section) = read_leb128(section)?;
+                let name = section.get(..name_len)?;
+                if name == RUNTIME_SECTION.as_bytes() {
+                    return std::str::from_utf8(&section[name_len..])
+                        .ok()
+                        .map(ToOwned::to_owned);
+                }
+            }
+            // core module / component, only valid within components
+            1 | 4 if wasm_binary[6..8] == [1, 0] => {
+                if let Some(runtime) = runtime_from_wasm(section) {
+                    return Some(runtime);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// Read unsigned LEB128-encoded 32-bit integer.
+#[cfg(feature = "compiler")]
+fn read_leb128(data: &[u8]) -> Option<(usize, &[u8])> {
+    let mut value = 0u32;
+    for (i, &byte) in data.iter().enumerate().take(5) {
+        value |= u32::from(byte & 0x7f) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Some((value as usize, &data[i + 1..]));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use std::hash::Hasher;
+
+    use siphasher::sip::SipHasher24;
+
+    use super::*;
+
+    #[test]
+    fn test_wit_version_parse() {
+        assert_eq!(WitVersion::parse("0.8.0"), Some(WitVersion::new(0, 8, 0)));
+        assert_eq!(WitVersion::parse("10.0.3"), Some(WitVersion::new(10, 0, 3)));
+        assert_eq!(WitVersion::parse(""), None);
+        assert_eq!(WitVersion::parse("0.8"), None);
+        assert_eq!(WitVersion::parse("0.8.0.1"), None);
+        assert_eq!(WitVersion::parse("0.8.0-rc1"), None);
+        assert_eq!(WitVersion::parse("x.8.0"), None);
+    }
+
+    #[test]
+    fn test_wit_version_display() {
+        let version = WitVersion::new(0, 8, 1);
+        assert_eq!(version.to_string(), "0.8.1");
+        assert_eq!(WitVersion::parse(&version.to_string()), Some(version));
+    }
+
+    #[test]
+    fn test_wit_version_compatible() {
+        let compatible = |a: &str, b: &str| {
+            WitVersion::parse(a)
+                .unwrap()
+                .is_compatible_with(&WitVersion::parse(b).unwrap())
+        };
+        assert!(compatible("0.8.0", "0.8.0"));
+        assert!(compatible("0.8.1", "0.8.0"));
+        assert!(!compatible("0.7.0", "0.8.0"));
+        assert!(!compatible("1.8.0", "0.8.0"));
+        assert!(compatible("1.2.0", "1.0.0"));
+        assert!(!compatible("2.0.0", "1.0.0"));
+    }
+
+    /// Content of the WIT world that the host was built against.
+    const WIT: &str = include_str!("../../wit/world.wit");
+
+    /// [`WitVersion::HOST`] and digest of [`WIT`] at the time the version was last reviewed.
+    ///
+    /// When the WIT world changes, bump [`WitVersion::HOST`] if the change breaks guests that were built against the
+    /// previous version. Then update this entry.
+    const REVIEWED_WIT: (WitVersion, u64) = (WitVersion::new(0, 9, 0), 0xddbdec8f85f525f9);
+
+    #[test]
+    fn test_host_wit_version_matches_wit_file() {
+        let version = WIT
+            .lines()
+            .find_map(|line| {
+                line.trim()
+                    .strip_prefix("package ")?
+                    .strip_suffix(';')?
+                    .strip_prefix(WIT_PACKAGE)?
+                    .strip_prefix('@')
+            })
+            .expect("WIT file has package declaration");
+        assert_eq!(
+            WitVersion::parse(version),
+            Some(WitVersion::HOST),
+            "update WitVersion::HOST",
+        );
+        assert_eq!(WitVersion::SUPPORTED.last(), Some(&WitVersion::HOST));
+    }
+
+    #[test]
+    fn test_wit_changes_are_versioned() {
+        let mut hasher = SipHasher24::new();
+        hasher.write(WIT.replace("\r\n", "\n").as_bytes());
+        let digest = hasher.finish();
+
+        assert_eq!(
+            (WitVersion::HOST, digest),
+            REVIEWED_WIT,
+            "wit/world.wit changed: bump WitVersion::HOST if the change is breaking, then update REVIEWED_WIT",
+        );
+    }
+
+    #[cfg(feature = "compiler")]
+    #[test]
+    fn test_runtime_from_wasm() {
+        let module = wasm(
+            &[1, 0, 0, 0],
+            &[custom_section(RUNTIME_SECTION, b"python 3.14")],
+        );
+        assert_eq!(runtime_from_wasm(&module).as_deref(), Some("python 3.14"));
+
+        // nested in component
+        let mut module_section = vec![1];
+        module_section.extend(leb128(module.len()));
+        module_section.extend(module);
+        let component = wasm(
+            &[0x0d, 0, 1, 0],
+            &[custom_section("other", b"foo"), module_section],
+        );
+        assert_eq!(
+            runtime_from_wasm(&component).as_deref(),
+            Some("python 3.14"),
+        );
+
+        // missing
+        let module = wasm(&[1, 0, 0, 0], &[custom_section("other", b"foo")]);
+        assert_eq!(runtime_from_wasm(&module), None);
+
+        // garbage
+        assert_eq!(runtime_from_wasm(b"foo"), None);
+        assert_eq!(runtime_from_wasm(b"\0asm\x01\0\0\0\0\x7f"), None);
+    }
+
+    /// Assemble WASM binary from version/layer and sections.
+    #[cfg(feature = "compiler")]
+    fn wasm(version: &[u8], sections: &[Vec<u8>]) -> Vec<u8> {
+        let mut out = b"\0asm".to_vec();
+        out.extend(version);
+        for section in sections {
+            out.extend(section);
+        }
+        out
+    }
+
+    /// Encode custom section.
+    #[cfg(feature = "compiler")]
+    fn custom_section(name: &str, data: &[u8]) -> Vec<u8> {
+        let mut content = leb128(name.len());
+        content.extend(name.as_bytes());
+        content.extend(data);
+
+        let mut out = vec![0];
+        out.extend(leb128(content.len()));
+        out.extend(content);
+        out
+    }
+
+    /// Encode unsigned LEB128.
+    #[cfg(feature = "compiler")]
+    fn leb128(mut value: usize) -> Vec<u8> {
+        let mut out = vec![];
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if value == 0 {
+                out.push(byte);
+                return out;
+            }
+            out.push(byte | 0x80);
+        }
+    }
+}
@@ -0,0 +1,483 @@
+//! Aggregated sandbox metrics for dashboards, see the `metrics-export` feature.
+
+use std::{
+    fmt::Write,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use datafusion_common::DataFusionError;
+
+use crate::{ContextLabels, ResourceLimitKind, SandboxInstanceId, SandboxObserver};
+
+/// Upper bounds of the [invocation latency](SandboxMetricsSnapshot::invocation_duration) buckets, in seconds.
+pub const INVOCATION_DURATION_BUCKETS: [f64; 12] = [
+    0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 5.0,
+];
+
+/// [`SandboxObserver`] that aggregates events into counters and histograms.
+///
+/// Register it via [`WasmPermissions::with_observer`](crate::WasmPermissions::with_observer) and export a
+/// [snapshot](Self::snapshot) periodically or on scrape, either as
+/// [Prometheus text](SandboxMetricsSnapshot::encode_prometheus) or via [counters](SandboxMetricsSnapshot::counters),
+/// e.g. into the [`metrics`](https://docs.rs/metrics) facade:
+///
+/// ```ignore
+/// for c in snapshot.counters() {
+///     metrics::counter!(c.name, &c.labels).absolute(c.value);
+/// }
+/// ```
+///
+/// [Context labels](ContextLabels) are NOT exported since they usually have a high cardinality, e.g. query IDs. Use a
+/// dedicated observer for per-query accounting.
+#[derive(Debug, Default)]
+pub struct SandboxMetrics {
+    /// See [`SandboxMetricsSnapshot::instances_created`].
+    instances_created: AtomicU64,
+
+    /// See [`SandboxMetricsSnapshot::instances_destroyed`].
+    instances_destroyed: AtomicU64,
+
+    /// See [`SandboxMetricsSnapshot::invocation_rows`].
+    invocation_rows: AtomicU64,
+
+    /// See [`SandboxMetricsSnapshot::invocation_duration`].
+    invocation_duration: Histogram,
+
+    /// See [`SandboxMetricsSnapshot::memory_limit_exceeded`].
+    memory_limit_exceeded: AtomicU64,
+
+    /// See [`SandboxMetricsSnapshot::table_elements_limit_exceeded`].
+    table_elements_limit_exceeded: AtomicU64,
+
+    /// See [`SandboxMetricsSnapshot::timeouts`].
+    timeouts: AtomicU64,
+
+    /// See [`SandboxMetricsSnapshot::guest_errors`].
+    guest_errors: AtomicU64,
+
+    /// See [`SandboxMetricsSnapshot::http_egress_bytes`].
+    http_egress_bytes: AtomicU64,
+
+    /// See [`SandboxMetricsSnapshot::vfs_write_bytes`].
+    vfs_write_bytes: AtomicU64,
+}
+
+impl SandboxMetrics {
+    /// Create metrics with all counters set to zero.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Current values.
+    ///
+    /// Counters are read one by one, so the snapshot is NOT atomic across counters.
+    pub fn snapshot(&self) -> SandboxMetricsSnapshot {
+        let Self {
+            instances_created,
+            instances_destroyed,
+            invocation_rows,
+            invocation_duration,
+            memory_limit_exceeded,
+            table_elements_limit_exceeded,
+            timeouts,
+            guest_errors,
+            http_egress_bytes,
+            vfs_write_bytes,
+        } = self;
+
+        SandboxMetricsSnapshot {
+            instances_created: instances_created.load(Ordering::Relaxed),
+            instances_destroyed: instances_destroyed.load(Ordering::Relaxed),
+            invocation_rows: invocation_rows.load(Ordering::Relaxed),
+            invocation_duration: invocation_duration.snapshot(),
+            memory_limit_exceeded: memory_limit_exceeded.load(Ordering::Relaxed),
+            table_elements_limit_exceeded: table_elements_limit_exceeded.load(Ordering::Relaxed),
+            timeouts: timeouts.load(Ordering::Relaxed),
+            guest_errors: guest_errors.load(Ordering::Relaxed),
+            http_egress_bytes: http_egress_bytes.load(Ordering::Relaxed),
+            vfs_write_bytes: vfs_write_bytes.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl SandboxObserver for SandboxMetrics {
+    fn instance_created(&self, _instance: SandboxInstanceId, _labels: &ContextLabels) {
+        self.instances_created.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn instance_destroyed(&self, _instance: SandboxInstanceId, _labels: &ContextLabels) {
+        self.instances_destroyed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn vfs_write(&self, _instance: SandboxInstanceId, _labels: &ContextLabels, bytes: u64) {
+        self.vfs_write_bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    fn http_egress(&self, _instance: SandboxInstanceId, _labels: &ContextLabels, bytes: u64) {
+        self.http_egress_bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    fn invocation_finished(
+        &self,
+        _instance: SandboxInstanceId,
+        _labels: &ContextLabels,
+        rows: usize,
+        duration: Duration,
+    ) {
+        self.invocation_rows
+            .fetch_add(rows as u64, Ordering::Relaxed);
+        self.invocation_duration.observe(duration);
+    }
+
+    fn limit_exceeded(
+        &self,
+        _instance: SandboxInstanceId,
+        _labels: &ContextLabels,
+        kind: ResourceLimitKind,
+    ) {
+        let counter = match kind {
+            ResourceLimitKind::Memory => &self.memory_limit_exceeded,
+            ResourceLimitKind::TableElements => &self.table_elements_limit_exceeded,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn timeout(&self, _instance: SandboxInstanceId, _labels: &ContextLabels) {
+        self.timeouts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn guest_error(
+        &self,
+        _instance: SandboxInstanceId,
+        _labels: &ContextLabels,
+        _error: &DataFusionError,
+    ) {
+        self.guest_errors.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Histogram with fixed [buckets](INVOCATION_DURATION_BUCKETS).
+#[derive(Debug, Default)]
+struct Histogram {
+    /// Number of observations per bucket, NOT cumulative.
+    ///
+    /// The last entry counts observations above the largest bucket.
+    buckets: [AtomicU64; INVOCATION_DURATION_BUCKETS.len() + 1],
+
+    /// Sum of all observations, in nanoseconds.
+    sum_nanos: AtomicU64,
+}
+
+impl Histogram {
+    /// Record observation.
+    fn observe(&self, duration: Duration) {
+        let secs = duration.as_secs_f64();
+        let idx = INVOCATION_DURATION_BUCKETS
+            .iter()
+            .position(|bound| secs <= *bound)
+            .unwrap_or(INVOCATION_DURATION_BUCKETS.len());
+        self.buckets[idx].fetch_add(1, Ordering::Relaxed);
+        self.sum_nanos.fetch_add(
+            duration.as_nanos().try_into().unwrap_or(u64::MAX),
+            Ordering::Relaxed,
+        );
+    }
+
+    /// Current values.
+    fn snapshot(&self) -> HistogramSnapshot {
+        let mut cumulative = 0;
+        let buckets = INVOCATION_DURATION_BUCKETS
+            .iter()
+            .zip(&self.buckets)
+            .map(|(bound, count)| {
+                cumulative += count.load(Ordering::Relaxed);
+                (*bound, cumulative)
+            })
+            .collect();
+        let count =
+            cumulative + self.buckets[INVOCATION_DURATION_BUCKETS.len()].load(Ordering::Relaxed);
+
+        HistogramSnapshot {
+            buckets,
+            count,
+            sum: Duration::from_nanos(self.sum_nanos.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+/// Values of a histogram.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistogramSnapshot {
+    /// Upper bound in seconds and cumulative number of observations that are less or equal to that bound.
+    pub buckets: Vec<(f64, u64)>,
+
+    /// Total number of observations.
+    pub count: u64,
+
+    /// Sum of all observations.
+    pub sum: Duration,
+}
+
+/// Values of [`SandboxMetrics`].
+///
+/// All counters are monotonic.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SandboxMetricsSnapshot {
+    /// Guest instances that were created.
+    pub instances_created: u64,
+
+    /// Guest instances that were destroyed.
+    pub instances_destroyed: u64,
+
+    /// Rows that were passed to guests.
+    pub invocation_rows: u64,
+
+    /// Latency of batches passed to guests, see [`SandboxObserver::invocation_finished`].
+    pub invocation_duration: HistogramSnapshot,
+
+    /// Calls that failed due to [`ResourceLimitKind::Memory`].
+    pub memory_limit_exceeded: u64,
+
+    /// Calls that failed due to [`ResourceLimitKind::TableElements`].
+    pub table_elements_limit_exceeded: u64,
+
+    /// Calls that timed out.
+    pub timeouts: u64,
+
+    /// Calls that failed for other reasons.
+    pub guest_errors: u64,
+
+    /// Bytes of HTTP request bodies sent by guests.
+    pub http_egress_bytes: u64,
+
+    /// Bytes written to the VFS by guests.
+    pub vfs_write_bytes: u64,
+}
+
+impl SandboxMetricsSnapshot {
+    /// All counters, including the count of the [invocation histogram](Self::invocation_duration).
+    pub fn counters(&self) -> Vec<MetricCounter> {
+        let Self {
+            instances_created,
+            instances_destroyed,
+            invocation_rows,
+            invocation_duration,
+            memory_limit_exceeded,
+            table_elements_limit_exceeded,
+            timeouts,
+            guest_errors,
+            http_egress_bytes,
+            vfs_write_bytes,
+        } = self;
+
+        let counter = |name, help, labels, value| MetricCounter {
+            name,
+            help,
+            labels,
+            value,
+        };
+        vec![
+            counter(
+                "datafusion_udf_wasm_instances_created_total",
+                "Guest instances that were created.",
+                vec![],
+                *instances_created,
+            ),
+            counter(
+                "datafusion_udf_wasm_instances_destroyed_total",
+                "Guest instances that were destroyed.",
+                vec![],
+                *instances_destroyed,
+            ),
+            counter(
+                "datafusion_udf_wasm_invocations_total",
+                "Batches that were passed to guests.",
+                vec![],
+                invocation_duration.count,
+            ),
+            counter(
+                "datafusion_udf_wasm_invocation_rows_total",
+                "Rows that were passed to guests.",
+                vec![],
+                *invocation_rows,
+            ),
+            counter(
+                "datafusion_udf_wasm_limit_exceeded_total",
+                "Calls that failed because the guest hit a resource limit.",
+                vec![("kind", "memory")],
+                *memory_limit_exceeded,
+            ),
+            counter(
+                "datafusion_udf_wasm_limit_exceeded_total",
+                "Calls that failed because the guest hit a resource limit.",
+                vec![("kind", "table_elements")],
+                *table_elements_limit_exceeded,
+            ),
+            counter(
+                "datafusion_udf_wasm_timeouts_total",
+                "Calls that did not finish within their time budget.",
+                vec![],
+                *timeouts,
+            ),
+            counter(
+                "datafusion_udf_wasm_guest_errors_total",
+                "Calls that failed for other reasons.",
+                vec![],
+                *guest_errors,
+            ),
+            counter(
+                "datafusion_udf_wasm_http_egress_bytes_total",
+                "Bytes of HTTP request bodies sent by guests.",
+                vec![],
+                *http_egress_bytes,
+            ),
+            counter(
+                "datafusion_udf_wasm_vfs_write_bytes_total",
+                "Bytes written to the VFS by guests.",
+                vec![],
+                *vfs_write_bytes,
+            ),
+        ]
+    }
+
+    /// Encode in the [Prometheus text format], e.g. to serve it on a `/metrics` endpoint or to append it to the
+    /// output of a `prometheus::Registry`.
+    ///
+    ///
+    /// [Prometheus text format]: https://prometheus.io/docs/instrumenting/exposition_formats/#text-based-format
+    pub fn encode_prometheus(&self) -> String {
+        let mut out = String::new();
+
+        let mut last_name = None;
+        for MetricCounter {
+            name,
+            help,
+            labels,
+            value,
+        } in self.counters()
+        {
+            if last_name != Some(name) {
+                writeln!(out, "# HELP {name} {help}").expect("write to string");
+                writeln!(out, "# TYPE {name} counter").expect("write to string");
+                last_name = Some(name);
+            }
+            let labels = labels
+                .iter()
+                .map(|(k, v)| format!("{k}=\"{v}\""))
+                .collect::<Vec<_>>()
+                .join(",");
+            if labels.is_empty() {
+                writeln!(out, "{name} {value}").expect("write to string");
+            } else {
+                writeln!(out, "{name}{{{labels}}} {value}").expect("write to string");
+            }
+        }
+
+        let HistogramSnapshot {
+            buckets,
+            count,
+            sum,
+        } = &self.invocation_duration;
+        let name = "datafusion_udf_wasm_invocation_duration_seconds";
+        writeln!(
+            out,
+            "# HELP {name} Latency of batches passed to guests, including data conversion."
+        )
+        .expect("write to string");
+        writeln!(out, "# TYPE {name} histogram").expect("write to string");
+        for (bound, cumulative) in buckets {
+            writeln!(out, "{name}_bucket{{le=\"{bound}\"}} {cumulative}").expect("write to string");
+        }
+        writeln!(out, "{name}_bucket{{le=\"+Inf\"}} {count}").expect("write to string");
+        writeln!(out, "{name}_sum {}", sum.as_secs_f64()).expect("write to string");
+        writeln!(out, "{name}_count {count}").expect("write to string");
+
+        out
+    }
+}
+
+/// Counter of a [`SandboxMetricsSnapshot`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetricCounter {
+    /// Metric name, e.g. `datafusion_udf_wasm_timeouts_total`.
+    ///
+    /// Counters that only differ in their [labels](Self::labels) share the name.
+    pub name: &'static str,
+
+    /// Human-readable description.
+    pub help: &'static str,
+
+    /// Labels that tell counters of the same name apart.
+    pub labels: Vec<(&'static str, &'static str)>,
+
+    /// Current value.
+    pub value: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_prometheus() {
+        let metrics = SandboxMetrics::new();
+        let instance = SandboxInstanceId::next();
+        let labels = ContextLabels::new().with_label("query_id", "1");
+
+        metrics.instance_created(instance, &labels);
+        metrics.invocation_finished(instance, &labels, 10, Duration::from_millis(3));
+        metrics.invocation_finished(instance, &labels, 5, Duration::from_secs(10));
+        metrics.limit_exceeded(instance, &labels, ResourceLimitKind::Memory);
+        metrics.http_egress(instance, &labels, 100);
+        metrics.http_egress(instance, &labels, 23);
+
+        insta::assert_snapshot!(metrics.snapshot().encode_prometheus(), @r#"
+        # HELP datafusion_udf_wasm_instances_created_total Guest instances that were created.
+        # TYPE datafusion_udf_wasm_instances_created_total counter
+        datafusion_udf_wasm_instances_created_total 1
+        # HELP datafusion_udf_wasm_instances_destroyed_total Guest instances that were destroyed.
+        # TYPE datafusion_udf_wasm_instances_destroyed_total counter
+        datafusion_udf_wasm_instances_destroyed_total 0
+        # HELP datafusion_udf_wasm_invocations_total Batches that were passed to guests.
+        # TYPE datafusion_udf_wasm_invocations_total counter
+        datafusion_udf_wasm_invocations_total 2
+        # HELP datafusion_udf_wasm_invocation_rows_total Rows that were passed to guests.
+        # TYPE datafusion_udf_wasm_invocation_rows_total counter
+        datafusion_udf_wasm_invocation_rows_total 15
+        # HELP datafusion_udf_wasm_limit_exceeded_total Calls that failed because the guest hit a resource limit.
+        # TYPE datafusion_udf_wasm_limit_exceeded_total counter
+        datafusion_udf_wasm_limit_exceeded_total{kind="memory"} 1
+        datafusion_udf_wasm_limit_exceeded_total{kind="table_elements"} 0
+        # HELP datafusion_udf_wasm_timeouts_total Calls that did not finish within their time budget.
+        # TYPE datafusion_udf_wasm_timeouts_total counter
+        datafusion_udf_wasm_timeouts_total 0
+        # HELP datafusion_udf_wasm_guest_errors_total Calls that failed for other reasons.
+        # TYPE datafusion_udf_wasm_guest_errors_total counter
+        datafusion_udf_wasm_guest_errors_total 0
+        # HELP datafusion_udf_wasm_http_egress_bytes_total Bytes of HTTP request bodies sent by guests.
+        # TYPE datafusion_udf_wasm_http_egress_bytes_total counter
+        datafusion_udf_wasm_http_egress_bytes_total 123
+        # HELP datafusion_udf_wasm_vfs_write_bytes_total Bytes written to the VFS by guests.
+        # TYPE datafusion_udf_wasm_vfs_write_bytes_total counter
+        datafusion_udf_wasm_vfs_write_bytes_total 0
+        # HELP datafusion_udf_wasm_invocation_duration_seconds Latency of batches passed to guests, including data conversion.
+        # TYPE datafusion_udf_wasm_invocation_duration_seconds histogram
+        datafusion_udf_wasm_invocation_duration_seconds_bucket{le="0.0005"} 0
+        datafusion_udf_wasm_invocation_duration_seconds_bucket{le="0.001"} 0
+        datafusion_udf_wasm_invocation_duration_seconds_bucket{le="0.0025"} 0
+        datafusion_udf_wasm_invocation_duration_seconds_bucket{le="0.005"} 1
+        datafusion_udf_wasm_invocation_duration_seconds_bucket{le="0.01"} 1
+        datafusion_udf_wasm_invocation_duration_seconds_bucket{le="0.025"} 1
+        datafusion_udf_wasm_invocation_duration_seconds_bucket{le="0.05"} 1
+        datafusion_udf_wasm_invocation_duration_seconds_bucket{le="0.1"} 1
+        datafusion_udf_wasm_invocation_duration_seconds_bucket{le="0.25"} 1
+        datafusion_udf_wasm_invocation_duration_seconds_bucket{le="0.5"} 1
+        datafusion_udf_wasm_invocation_duration_seconds_bucket{le="1"} 1
+        datafusion_udf_wasm_invocation_duration_seconds_bucket{le="5"} 1
+        datafusion_udf_wasm_invocation_duration_seconds_bucket{le="+Inf"} 2
+        datafusion_udf_wasm_invocation_duration_seconds_sum 10.003
+        datafusion_udf_wasm_invocation_duration_seconds_count 2
+        "#);
+    }
+}
@@ -0,0 +1,66 @@
+//! Collision detection for UDF names.
+
+use std::borrow::Cow;
+
+use icu_normalizer::ComposingNormalizerBorrowed;
+
+/// Defines when two UDF names that are produced by the same guest are considered to collide.
+///
+/// SQL resolves unquoted identifiers case-insensitively, so two UDFs that only differ in case -- or that only differ
+/// in their Unicode representation -- are confusing at best and may lead to the wrong UDF being called.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UdfNameCollisionPolicy {
+    /// Names only collide if they are byte-wise identical.
+    Exact,
+
+    /// Names collide if they are identical after lowercasing them.
+    CaseInsensitive,
+
+    /// Names collide if they are identical after [NFKC] normalization and lowercasing them.
+    ///
+    /// This also catches names that look the same but use different Unicode code points, e.g. `ﬀ` and `ff`.
+    ///
+    ///
+    /// [NFKC]: https://www.unicode.org/reports/tr15/
+    #[default]
+    Normalized,
+}
+
+impl UdfNameCollisionPolicy {
+    /// Canonical form of the name that is used to detect collisions.
+    pub(crate) fn canonicalize<'a>(&self, name: &'a str) -> Cow<'a, str> {
+        match self {
+            Self::Exact => Cow::Borrowed(name),
+            Self::CaseInsensitive => Cow::Owned(name.to_lowercase()),
+            Self::Normalized => {
+                let nfkc = ComposingNormalizerBorrowed::new_nfkc();
+                // lowercasing may produce non-normalized output, so normalize again
+                let lower = nfkc.normalize(name).to_lowercase();
+                Cow::Owned(nfkc.normalize(&lower).into_owned())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_canonicalize() {
+        let policy = UdfNameCollisionPolicy::Exact;
+        assert_eq!(policy.canonicalize("Foo"), "Foo");
+        assert_eq!(policy.canonicalize("ﬀ"), "ﬀ");
+
+        let policy = UdfNameCollisionPolicy::CaseInsensitive;
+        assert_eq!(policy.canonicalize("Foo"), "foo");
+        assert_eq!(policy.canonicalize("ÄÖÜ"), "äöü");
+        assert_eq!(policy.canonicalize("ﬀ"), "ﬀ");
+
+        let policy = UdfNameCollisionPolicy::Normalized;
+        assert_eq!(policy.canonicalize("Foo"), "foo");
+        assert_eq!(policy.canonicalize("ﬀ"), "ff");
+        assert_eq!(policy.canonicalize("A\u{0308}"), "ä");
+        assert_eq!(policy.canonicalize("ＦＯＯ"), "foo");
+    }
+}
@@ -0,0 +1,131 @@
+//! Model inference via [`wasi-nn`].
+//!
+//!
+//! [`wasi-nn`]: https://github.com/WebAssembly/wasi-nn
+
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "nn")]
+use std::collections::HashMap;
+
+use datafusion_common::{DataFusionError, Result as DataFusionResult};
+
+use crate::limiter::Limiter;
+
+/// Name of the model file within the [model directory](NnModel::onnx).
+#[cfg(feature = "nn")]
+const ONNX_MODEL_FILE: &str = "model.onnx";
+
+/// Model that guests may load via [`wasi-nn`].
+///
+/// Guests load models by name (`load-by-name`). Loading models from guest-provided bytes is NOT supported, so the
+/// host stays in control of which models run.
+///
+///
+/// [`wasi-nn`]: https://github.com/WebAssembly/wasi-nn
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NnModel {
+    /// Name under which the guest can load the model.
+    name: String,
+
+    /// Directory that contains the model.
+    dir: PathBuf,
+}
+
+impl NnModel {
+    /// [ONNX] model, stored as `model.onnx` within the given directory.
+    ///
+    ///
+    /// [ONNX]: https://onnx.ai/
+    pub fn onnx(name: impl Into<String>, dir: impl Into<PathBuf>) -> Self {
+        Self {
+            name: name.into(),
+            dir: dir.into(),
+        }
+    }
+
+    /// Name under which the guest can load the model.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Directory that contains the model.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+}
+
+/// `wasi-nn` state of a guest.
+#[cfg(feature = "nn")]
+pub(crate) type NnCtx = wasmtime_wasi_nn::wit::WasiNnCtx;
+
+/// `wasi-nn` state of a guest.
+///
+/// `wasi-nn` support was not compiled in, so this is empty.
+#[cfg(not(feature = "nn"))]
+#[derive(Debug, Default)]
+pub(crate) struct NnCtx;
+
+/// Load allowed models and set up the `wasi-nn` state.
+///
+/// The size of every model file is accounted against the memory pool, for as long as the guest lives.
+#[cfg(feature = "nn")]
+pub(crate) fn nn_ctx(models: &[NnModel], limiter: &Limiter) -> DataFusionResult<NnCtx> {
+    use wasmtime_wasi_nn::{
+        Registry,
+        backend::{BackendFromDir, onnx::OnnxBackend},
+        wit::ExecutionTarget,
+    };
+
+    let mut backend = OnnxBackend::default();
+    let mut graphs = HashMap::with_capacity(models.len());
+    for NnModel { name, dir } in models {
+        let size = std::fs::metadata(dir.join(ONNX_MODEL_FILE))
+            .map_err(|e| {
+                DataFusionError::IoError(e)
+                    .context(format!("load model `{name}` from `{}`", dir.display()))
+            })?
+            .len();
+        limiter.grow(usize::try_from(size).unwrap_or(usize::MAX))?;
+
+        let graph = backend
+            .load_from_dir(dir, ExecutionTarget::Cpu)
+            .map_err(|e| {
+                DataFusionError::External(e.into())
+                    .context(format!("load model `{name}` from `{}`", dir.display()))
+            })?;
+        graphs.insert(name.clone(), graph);
+    }
+
+    // No backends: guests can only use the pre-loaded graphs, not load their own.
+    Ok(NnCtx::new([], Registry::from(NnRegistry(graphs))))
+}
+
+/// Load allowed models and set up the `wasi-nn` state.
+///
+/// `wasi-nn` support was not compiled in, so this fails if any models are allowed.
+#[cfg(not(feature = "nn"))]
+pub(crate) fn nn_ctx(models: &[NnModel], _limiter: &Limiter) -> DataFusionResult<NnCtx> {
+    if models.is_empty() {
+        Ok(NnCtx)
+    } else {
+        Err(DataFusionError::NotImplemented(
+            "wasi-nn models require the `nn` feature".to_owned(),
+        ))
+    }
+}
+
+/// Graphs that guests may load by name.
+#[cfg(feature = "nn")]
+struct NnRegistry(HashMap<String, wasmtime_wasi_nn::Graph>);
+
+#[cfg(feature = "nn")]
+impl wasmtime_wasi_nn::GraphRegistry for NnRegistry {
+    fn get(&self, name: &str) -> Option<&wasmtime_wasi_nn::Graph> {
+        self.0.get(name)
+    }
+
+    fn get_mut(&mut self, name: &str) -> Option<&mut wasmtime_wasi_nn::Graph> {
+        self.0.get_mut(name)
+    }
+}
@@ -0,0 +1,299 @@
+//! Observation of sandbox activity.
+
+use std::{
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use datafusion_common::DataFusionError;
+
+use crate::{ContextLabels, ResourceLimitKind, WasmUdfError};
+
+/// Identifies a guest instance in [`SandboxObserver`] events.
+///
+/// IDs are unique within the process. Note that a single [`WasmScalarUdf`](crate::WasmScalarUdf) may use multiple
+/// instances over its lifetime, e.g. due to [recycling](crate::WasmPermissions::with_max_recycles).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SandboxInstanceId(u64);
+
+impl SandboxInstanceId {
+    /// Allocate new, unique ID.
+    pub(crate) fn next() -> Self {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        Self(COUNTER.fetch_add(1, Ordering::Relaxed))
+    }
+
+    /// Numeric representation.
+    pub fn get(&self) -> u64 {
+        self.0
+    }
+}
+
+impl std::fmt::Display for SandboxInstanceId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// I/O attempt that the sandbox denied, see [`SandboxObserver::io_denied`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum DeniedIo {
+    /// Outgoing HTTP request that was rejected, e.g. by the [HTTP validator](crate::HttpRequestValidator).
+    Http {
+        /// Request method.
+        method: String,
+
+        /// Request URI.
+        uri: String,
+    },
+
+    /// Modification of a [read-only](crate::VfsLimits::read_only) VFS.
+    VfsWrite {
+        /// Path, relative to the descriptor that the guest used.
+        path: String,
+    },
+}
+
+impl std::fmt::Display for DeniedIo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Http { method, uri } => write!(f, "HTTP {method} {uri}"),
+            Self::VfsWrite { path } => write!(f, "VFS write {path:?}"),
+        }
+    }
+}
+
+/// Receives events about the activity of guests, e.g. to feed them into a security monitoring pipeline.
+///
+/// See [`WasmPermissions::with_observer`](crate::WasmPermissions::with_observer). All methods default to no-ops.
+///
+/// Every event carries the [context labels](ContextLabels) of the guest. Events of calls into the guest additionally
+/// carry the labels of the invocation, if any.
+///
+/// # Implementation
+/// Methods are called synchronously -- often while the guest is paused -- so they should be cheap and MUST NOT block,
+/// e.g. push the event into a channel and process it elsewhere.
+pub trait SandboxObserver: std::fmt::Debug + Send + Sync + 'static {
+    /// A guest instance was created.
+    fn instance_created(&self, instance: SandboxInstanceId, labels: &ContextLabels) {
+        let _ = (instance, labels);
+    }
+
+    /// A guest instance was destroyed.
+    fn instance_destroyed(&self, instance: SandboxInstanceId, labels: &ContextLabels) {
+        let _ = (instance, labels);
+    }
+
+    /// The guest wrote data to the [VFS](crate::VfsLimits).
+    fn vfs_write(&self, instance: SandboxInstanceId, labels: &ContextLabels, bytes: u64) {
+        let _ = (instance, labels, bytes);
+    }
+
+    /// The sandbox denied an I/O attempt of the guest.
+    ///
+    /// The guest only sees a regular error -- which it may swallow -- so this is the way to tell if a UDF tried to
+    /// escape a [hermetic](crate::WasmPermissions::hermetic) sandbox, see [`IoViolationLog`].
+    fn io_denied(&self, instance: SandboxInstanceId, labels: &ContextLabels, io: &DeniedIo) {
+        let _ = (instance, labels, io);
+    }
+
+    /// The guest sent HTTP request body data.
+    ///
+    /// This is reported as the data is streamed to the remote server, so a single request may result in multiple
+    /// events. Headers are NOT counted.
+    fn http_egress(&self, instance: SandboxInstanceId, labels: &ContextLabels, bytes: u64) {
+        let _ = (instance, labels, bytes);
+    }
+
+    /// The guest finished processing a batch, successfully or not.
+    ///
+    /// The `duration` covers the conversion of the arguments and of the result.
+    fn invocation_finished(
+        &self,
+        instance: SandboxInstanceId,
+        labels: &ContextLabels,
+        rows: usize,
+        duration: Duration,
+    ) {
+        let _ = (instance, labels, rows, duration);
+    }
+
+    /// A call into the guest failed because the guest hit a resource limit, see [`WasmUdfError::ResourceLimit`].
+    fn limit_exceeded(
+        &self,
+        instance: SandboxInstanceId,
+        labels: &ContextLabels,
+        kind: ResourceLimitKind,
+    ) {
+        let _ = (instance, labels, kind);
+    }
+
+    /// A call into the guest did not finish within its time budget, see [`WasmUdfError::Timeout`].
+    fn timeout(&self, instance: SandboxInstanceId, labels: &ContextLabels) {
+        let _ = (instance, labels);
+    }
+
+    /// A call into the guest failed for any other reason, e.g. the guest returned an error or trapped.
+    fn guest_error(
+        &self,
+        instance: SandboxInstanceId,
+        labels: &ContextLabels,
+        error: &DataFusionError,
+    ) {
+        let _ = (instance, labels, error);
+    }
+}
+
+/// Reports events of a single guest instance to the [`SandboxObserver`], if there is one.
+#[derive(Debug, Clone)]
+pub(crate) struct Observer {
+    /// User-provided observer.
+    inner: Option<Arc<dyn SandboxObserver>>,
+
+    /// Instance that the events belong to.
+    instance: SandboxInstanceId,
+
+    /// Labels of the instance, see [`WasmPermissions::with_context_labels`](crate::WasmPermissions::with_context_labels).
+    labels: ContextLabels,
+}
+
+impl Observer {
+    /// Create observer for a new instance.
+    pub(crate) fn new(inner: Option<Arc<dyn SandboxObserver>>, labels: ContextLabels) -> Self {
+        Self {
+            inner,
+            instance: SandboxInstanceId::next(),
+            labels,
+        }
+    }
+
+    /// See [`SandboxObserver::instance_created`].
+    pub(crate) fn instance_created(&self) {
+        if let Some(inner) = &self.inner {
+            inner.instance_created(self.instance, &self.labels);
+        }
+    }
+
+    /// See [`SandboxObserver::instance_destroyed`].
+    pub(crate) fn instance_destroyed(&self) {
+        if let Some(inner) = &self.inner {
+            inner.instance_destroyed(self.instance, &self.labels);
+        }
+    }
+
+    /// See [`SandboxObserver::vfs_write`].
+    pub(crate) fn vfs_write(&self, bytes: u64) {
+        if let Some(inner) = &self.inner {
+            inner.vfs_write(self.instance, &self.labels, bytes);
+        }
+    }
+
+    /// See [`SandboxObserver::io_denied`].
+    pub(crate) fn io_denied(&self, io: DeniedIo) {
+        if let Some(inner) = &self.inner {
+            inner.io_denied(self.instance, &self.labels, &io);
+        }
+    }
+
+    /// See [`SandboxObserver::http_egress`].
+    pub(crate) fn http_egress(&self, bytes: u64) {
+        if let Some(inner) = &self.inner {
+            inner.http_egress(self.instance, &self.labels, bytes);
+        }
+    }
+
+    /// See [`SandboxObserver::invocation_finished`].
+    ///
+    /// The `labels` of the call are merged into the labels of the instance.
+    pub(crate) fn invocation_finished(
+        &self,
+        labels: &ContextLabels,
+        rows: usize,
+        duration: Duration,
+    ) {
+        if let Some(inner) = &self.inner {
+            inner.invocation_finished(self.instance, &self.labels.merge(labels), rows, duration);
+        }
+    }
+
+    /// Report failed call into the guest.
+    ///
+    /// The `labels` of the call -- if any -- are merged into the labels of the instance.
+    pub(crate) fn error(&self, e: &DataFusionError, labels: Option<&ContextLabels>) {
+        let Some(inner) = &self.inner else {
+            return;
+        };
+        let merged;
+        let labels = match labels {
+            Some(labels) => {
+                merged = self.labels.merge(labels);
+                &merged
+            }
+            None => &self.labels,
+        };
+
+        match WasmUdfError::find(e) {
+            Some(WasmUdfError::ResourceLimit { kind, .. }) => {
+                inner.limit_exceeded(self.instance, labels, *kind);
+            }
+            Some(WasmUdfError::Timeout { .. }) => {
+                inner.timeout(self.instance, labels);
+            }
+            _ => {
+                inner.guest_error(self.instance, labels, e);
+            }
+        }
+    }
+}
+
+/// [`SandboxObserver`] that records [denied I/O](SandboxObserver::io_denied).
+///
+/// Use it to assert that a UDF is hermetic -- e.g. before caching its results or evaluating it at planning time --
+/// since guests may swallow the errors that they see:
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use datafusion_udf_wasm_host::{IoViolationLog, WasmPermissions};
+/// let log = Arc::new(IoViolationLog::new());
+/// let permissions = WasmPermissions::hermetic().with_observer(Arc::clone(&log) as _);
+///
+/// // ... create and invoke UDFs ...
+///
+/// assert!(log.take().is_empty());
+/// ```
+#[derive(Debug, Default)]
+pub struct IoViolationLog {
+    /// Recorded violations.
+    violations: Mutex<Vec<(SandboxInstanceId, ContextLabels, DeniedIo)>>,
+}
+
+impl IoViolationLog {
+    /// Create empty log.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Take all violations that were recorded so far, along with the instance and the labels of the guest.
+    pub fn take(&self) -> Vec<(SandboxInstanceId, ContextLabels, DeniedIo)> {
+        std::mem::take(&mut self.violations.lock().expect("not poisoned"))
+    }
+}
+
+impl SandboxObserver for IoViolationLog {
+    fn io_denied(&self, instance: SandboxInstanceId, labels: &ContextLabels, io: &DeniedIo) {
+        self.violations
+            .lock()
+            .expect("not poisoned")
+            .push((instance, labels.clone(), io.clone()));
+    }
+}
+
+impl Default for Observer {
+    fn default() -> Self {
+        Self::new(None, ContextLabels::default())
+    }
+}
@@ -1,11 +1,19 @@
 //! Permission for guests.
 
-use std::{collections::BTreeMap, num::NonZeroUsize, time::Duration};
+use std::{collections::BTreeMap, hash::Hasher, num::NonZeroUsize, sync::Arc, time::Duration};
 
-use crate::{HttpConfig, StaticResourceLimits, TrustedDataLimits, VfsLimits};
+use siphasher::sip::SipHasher24;
+
+use crate::{
+    ClockPolicy, ConfigLimits, ContextLabels, DynamicMemoryLimits, HttpConfig, HttpRedirectPolicy,
+    InstanceSharing, IpcCompression, KvConfig, NnModel, RandomPolicy, SandboxObserver,
+    SecretProvider, StaticResourceLimits, StderrPolicy, StderrRedactor, TrustedDataLimits,
+    UdfNameCollisionPolicy, VfsLimits, WasmRuntimeHandle, config_forwarding::ConfigForwarding,
+    http::rate_limit::HttpRateLimit, ignore_debug::IgnoreDebug,
+};
 
 /// Permissions for a WASM component.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct WasmPermissions {
     /// Epoch tick time.
     pub(crate) epoch_tick_time: Duration,
@@ -17,24 +25,63 @@ pub struct WasmPermissions {
     /// increasing the timeout.
     pub(crate) inplace_blocking_max_ticks: u32,
 
+    /// Timeout for the one-time setup of every UDF.
+    pub(crate) init_timeout: Duration,
+
+    /// Wall-clock timeout for every invocation.
+    pub(crate) invoke_timeout: Option<Duration>,
+
+    /// How often a poisoned guest is re-instantiated.
+    pub(crate) max_recycles: usize,
+
+    /// Idle time after which a guest is suspended.
+    pub(crate) idle_ttl: Option<Duration>,
+
+    /// How UDFs share guest instances.
+    pub(crate) instance_sharing: InstanceSharing,
+
+    /// Number of guest instances that a single batch is split across.
+    pub(crate) intra_batch_parallelism: NonZeroUsize,
+
     /// HTTP configs.
     pub(crate) http: HttpConfig,
 
     /// Virtual file system limits.
     pub(crate) vfs: VfsLimits,
 
+    /// Additional TAR archives that are overlaid onto the root filesystem of the guest.
+    pub(crate) extra_root_tars: IgnoreDebug<Vec<Arc<[u8]>>>,
+
     /// Limit of the stored stderr data.
     pub(crate) stderr_bytes: usize,
 
+    /// When stderr data is attached to errors.
+    pub(crate) stderr_policy: StderrPolicy,
+
+    /// Redacts stderr data before it is attached to errors.
+    pub(crate) stderr_redactor: Option<Arc<dyn StderrRedactor>>,
+
     /// Static resource limits.
     pub(crate) resource_limits: StaticResourceLimits,
 
+    /// Dynamic memory limits.
+    pub(crate) dynamic_memory_limits: DynamicMemoryLimits,
+
     /// Trusted data limits.
     pub(crate) trusted_data_limits: TrustedDataLimits,
 
     /// Maximum number of UDFs.
     pub(crate) max_udfs: usize,
 
+    /// When UDF names collide.
+    pub(crate) udf_name_collisions: UdfNameCollisionPolicy,
+
+    /// Request and verify checksums of UDF results.
+    pub(crate) result_checksums: bool,
+
+    /// Preferred compression codecs for IPC payloads, in order of preference.
+    pub(crate) ipc_compression: Vec<IpcCompression>,
+
     /// Maximum number of cached [`Field`]s.
     ///
     ///
@@ -47,8 +94,44 @@ pub struct WasmPermissions {
     /// [`ConfigOptions`]: datafusion_common::config::ConfigOptions
     pub(crate) max_cached_config_options: NonZeroUsize,
 
+    /// Which [`ConfigOptions`] entries are forwarded to the guest.
+    ///
+    ///
+    /// [`ConfigOptions`]: datafusion_common::config::ConfigOptions
+    pub(crate) config_forwarding: ConfigForwarding,
+
     /// Environment variables.
     pub(crate) envs: BTreeMap<String, String>,
+
+    /// Clocks exposed to the guest.
+    pub(crate) clock: ClockPolicy,
+
+    /// Randomness exposed to the guest.
+    pub(crate) random: RandomPolicy,
+
+    /// Rate limit for outbound HTTP requests.
+    pub(crate) http_rate_limit: Option<HttpRateLimit>,
+
+    /// Redirect policy for outbound HTTP requests.
+    pub(crate) http_redirects: HttpRedirectPolicy,
+
+    /// Secrets, or [`None`] if access is denied.
+    pub(crate) secret_provider: Option<Arc<dyn SecretProvider>>,
+
+    /// Key-value store, or [`None`] if access is denied.
+    pub(crate) kv: Option<KvConfig>,
+
+    /// Models that the guest may load via `wasi-nn`.
+    pub(crate) nn_models: Vec<NnModel>,
+
+    /// Observer for sandbox activity.
+    pub(crate) observer: Option<Arc<dyn SandboxObserver>>,
+
+    /// Handle to shut down all guests.
+    pub(crate) runtime_handle: Option<WasmRuntimeHandle>,
+
+    /// Labels that are attached to errors and observer events.
+    pub(crate) context_labels: ContextLabels,
 }
 
 impl WasmPermissions {
@@ -56,6 +139,104 @@ impl WasmPermissions {
     pub fn new() -> Self {
         Self::default()
     }
+
+    /// Create permissions that deny all I/O, e.g. for UDFs that are evaluated at planning time or whose results are
+    /// cached.
+    ///
+    /// Compared to [the defaults](Self::new), this:
+    ///
+    /// - pins the [clocks](ClockPolicy::Fixed) to the UNIX epoch
+    /// - [seeds](RandomPolicy::Seeded) randomness with `0`
+    /// - makes the [VFS read-only](VfsLimits::read_only)
+    ///
+    /// HTTP requests, environment variables, secrets, the key-value store, and models are denied, same as for the
+    /// defaults. Use an [`IoViolationLog`](crate::IoViolationLog) to detect attempts to escape the sandbox, since the
+    /// guest may swallow the errors that it sees.
+    pub fn hermetic() -> Self {
+        Self {
+            http: HttpConfig::default(),
+            vfs: VfsLimits {
+                read_only: true,
+                ..Default::default()
+            },
+            envs: BTreeMap::default(),
+            clock: ClockPolicy::Fixed {
+                wall_clock: Duration::ZERO,
+            },
+            random: RandomPolicy::Seeded(0),
+            http_rate_limit: None,
+            secret_provider: None,
+            kv: None,
+            nn_models: vec![],
+            ..Self::default()
+        }
+    }
+
+    /// Fingerprint of these permissions.
+    ///
+    /// This can be used to detect that two nodes use different permissions, e.g. when shipping plans. The fingerprint
+    /// is stable across processes. Settings that contain user-provided callbacks -- i.e. the
+    /// [HTTP config](Self::with_http), the [clock policy](Self::with_clock_policy), the
+    /// [stderr redactor](Self::with_stderr_redactor), the [secret provider](Self::with_secret_provider), the
+    /// [key-value backend](KvConfig::with_backend), the [observer](Self::with_observer), and the
+    /// [runtime handle](Self::with_runtime_handle) -- are NOT included. Neither are the
+    /// [context labels](Self::with_context_labels), since they do not change how guests behave.
+    pub fn fingerprint(&self) -> u64 {
+        let Self {
+            epoch_tick_time,
+            inplace_blocking_max_ticks,
+            init_timeout,
+            invoke_timeout,
+            max_recycles,
+            idle_ttl,
+            instance_sharing,
+            intra_batch_parallelism,
+            http: _,
+            vfs,
+            extra_root_tars,
+            stderr_bytes,
+            stderr_policy,
+            stderr_redactor: _,
+            resource_limits,
+            dynamic_memory_limits,
+            trusted_data_limits,
+            max_udfs,
+            udf_name_collisions,
+            result_checksums,
+            ipc_compression,
+            max_cached_fields,
+            max_cached_config_options,
+            config_forwarding,
+            envs,
+            clock: _,
+            random,
+            http_rate_limit,
+            http_redirects,
+            secret_provider: _,
+            kv,
+            nn_models,
+            observer: _,
+            runtime_handle: _,
+            context_labels: _,
+        } = self;
+
+        // the backend is a user-provided callback
+        let kv_limits = kv.as_ref().map(|kv| &kv.limits);
+
+        // all included types are plain data, so their debug representation is deterministic
+        let repr = format!(
+            "{epoch_tick_time:?}|{inplace_blocking_max_ticks:?}|{init_timeout:?}|{invoke_timeout:?}|{max_recycles:?}|{idle_ttl:?}|{instance_sharing:?}|{intra_batch_parallelism:?}|{vfs:?}|{stderr_bytes:?}|\
+            {stderr_policy:?}|{resource_limits:?}|{dynamic_memory_limits:?}|{trusted_data_limits:?}|{max_udfs:?}|{udf_name_collisions:?}|{result_checksums:?}|\
+            {ipc_compression:?}|{max_cached_fields:?}|{max_cached_config_options:?}|{config_forwarding:?}|{envs:?}|{random:?}|{http_rate_limit:?}|{http_redirects:?}|{kv_limits:?}|{nn_models:?}"
+        );
+        let mut hasher = SipHasher24::new();
+        hasher.write(repr.as_bytes());
+        for tar in extra_root_tars.iter() {
+            hasher.write_usize(tar.len());
+            hasher.write(tar);
+        }
+        hasher.finish()
+    }
 }
 
 impl Default for WasmPermissions {
@@ -68,15 +249,39 @@ impl Default for WasmPermissions {
             inplace_blocking_max_ticks: inplace_blocking_timeout
                 .div_duration_f32(epoch_tick_time)
                 .floor() as _,
+            init_timeout: Duration::from_secs(10),
+            invoke_timeout: None,
+            max_recycles: 0,
+            idle_ttl: None,
+            instance_sharing: InstanceSharing::default(),
+            intra_batch_parallelism: NonZeroUsize::MIN,
             http: HttpConfig::default(),
             vfs: VfsLimits::default(),
+            extra_root_tars: vec![].into(),
             stderr_bytes: 1024, // 1KB
+            stderr_policy: StderrPolicy::default(),
+            stderr_redactor: None,
             resource_limits: StaticResourceLimits::default(),
+            dynamic_memory_limits: DynamicMemoryLimits::default(),
             trusted_data_limits: TrustedDataLimits::default(),
             max_udfs: 23,
+            udf_name_collisions: UdfNameCollisionPolicy::default(),
+            result_checksums: false,
+            ipc_compression: vec![],
             max_cached_fields: NonZeroUsize::new(1_000).expect("valid value"),
             max_cached_config_options: NonZeroUsize::new(1).expect("valid value"),
+            config_forwarding: ConfigForwarding::default(),
             envs: BTreeMap::default(),
+            clock: ClockPolicy::default(),
+            random: RandomPolicy::default(),
+            http_rate_limit: None,
+            http_redirects: HttpRedirectPolicy::default(),
+            secret_provider: None,
+            kv: None,
+            nn_models: vec![],
+            observer: None,
+            runtime_handle: None,
+            context_labels: ContextLabels::default(),
         }
     }
 }
@@ -109,11 +314,138 @@ impl WasmPermissions {
         }
     }
 
+    /// Set timeout for the one-time setup (`init`) of every UDF.
+    ///
+    /// The guest can only be interrupted on an [epoch tick](Self::with_epoch_tick_time), so the actual timeout may be
+    /// slightly longer.
+    pub fn with_init_timeout(self, t: Duration) -> Self {
+        Self {
+            init_timeout: t,
+            ..self
+        }
+    }
+
+    /// Set wall-clock timeout for every invocation of a UDF.
+    ///
+    /// A call that exceeds the timeout fails with [`WasmUdfError::Timeout`]. Since the guest was aborted in the middle
+    /// of the call, the UDF instance is poisoned and all further calls to UDFs of the same
+    /// [source](crate::WasmScalarUdf::new) fail with [`WasmUdfError::Poisoned`].
+    ///
+    /// Defaults to no timeout.
+    ///
+    ///
+    /// [`WasmUdfError::Timeout`]: crate::WasmUdfError::Timeout
+    /// [`WasmUdfError::Poisoned`]: crate::WasmUdfError::Poisoned
+    pub fn with_invoke_timeout(self, t: Duration) -> Self {
+        Self {
+            invoke_timeout: Some(t),
+            ..self
+        }
+    }
+
+    /// Set how often a poisoned guest is re-instantiated.
+    ///
+    /// A guest that [trapped](crate::WasmUdfError::Trap), hit a [resource limit](crate::WasmUdfError::ResourceLimit),
+    /// [timed out](crate::WasmUdfError::Timeout), or whose call was cancelled -- i.e. the call future was dropped -- is
+    /// poisoned. The call that caused this still fails, but the next call to any UDF of the same
+    /// [source](crate::WasmScalarUdf::new) re-instantiates the component and sets up all UDFs again. This discards all
+    /// guest state, e.g. global variables and files. Once the budget is used up, further calls fail with [`WasmUdfError::Poisoned`](crate::WasmUdfError::Poisoned).
+    ///
+    /// Defaults to `0`, i.e. poisoned guests are never re-instantiated.
+    pub fn with_max_recycles(self, n: usize) -> Self {
+        Self {
+            max_recycles: n,
+            ..self
+        }
+    }
+
+    /// Suspend guests that were not called for the given time.
+    ///
+    /// A [suspended](crate::WasmScalarUdf::suspend) guest is dropped, which frees its memory. The next call to any UDF
+    /// of the same [source](crate::WasmScalarUdf::new) re-instantiates the component and sets up all UDFs again. This
+    /// discards all guest state, e.g. global variables and files. The check runs on the I/O runtime, so a guest is
+    /// suspended between `ttl` and twice `ttl` after its last call.
+    ///
+    /// Defaults to no suspension.
+    pub fn with_idle_ttl(self, ttl: Duration) -> Self {
+        Self {
+            idle_ttl: Some(ttl),
+            ..self
+        }
+    }
+
+    /// Set how UDFs that are created from the same source share guest instances.
+    ///
+    /// Note that [resource limits](Self::with_resource_limits) apply to every instance.
+    pub fn with_instance_sharing(self, sharing: InstanceSharing) -> Self {
+        Self {
+            instance_sharing: sharing,
+            ..self
+        }
+    }
+
+    /// Split every batch across `k` guest instances that run in parallel.
+    ///
+    /// A single invocation only uses one WASM thread, so CPU-bound UDFs -- e.g. written in Python -- cannot use more
+    /// than one core per batch. With `k > 1`, every UDF gets `k - 1` additional instances. Batches are split into up
+    /// to `k` contiguous slices, which are invoked concurrently, and the results are reassembled in the original
+    /// order. Batches with constant arguments only are not split.
+    ///
+    /// The additional instances only run the UDF itself, i.e. guest state like global variables is NOT shared between
+    /// the slices. [Resource limits](Self::with_resource_limits) apply to every instance.
+    ///
+    /// Defaults to `1`, i.e. batches are not split.
+    pub fn with_intra_batch_parallelism(self, k: NonZeroUsize) -> Self {
+        Self {
+            intra_batch_parallelism: k,
+            ..self
+        }
+    }
+
     /// Set HTTP config.
     pub fn with_http(self, http: HttpConfig) -> Self {
         Self { http, ..self }
     }
 
+    /// Limit outbound HTTP requests of every guest instance.
+    ///
+    /// This is a token bucket that allows `burst` requests at once and refills with `requests_per_sec`. Requests that
+    /// exceed the limit fail with `ErrorCode::ConnectionLimitReached`. The limit complements the
+    /// [validator](HttpConfig::with_validator), i.e. rejected requests do not count towards it.
+    pub fn with_http_rate_limit(self, requests_per_sec: f64, burst: u32) -> Self {
+        Self {
+            http_rate_limit: Some(HttpRateLimit {
+                requests_per_sec,
+                burst,
+                per_host: false,
+            }),
+            ..self
+        }
+    }
+
+    /// Like [`with_http_rate_limit`](Self::with_http_rate_limit), but tracks every destination host separately.
+    pub fn with_http_rate_limit_per_host(self, requests_per_sec: f64, burst: u32) -> Self {
+        Self {
+            http_rate_limit: Some(HttpRateLimit {
+                requests_per_sec,
+                burst,
+                per_host: true,
+            }),
+            ..self
+        }
+    }
+
+    /// Set if the host follows HTTP redirects on behalf of the guest.
+    ///
+    /// # Default
+    /// Default is [`HttpRedirectPolicy::None`].
+    pub fn with_http_redirect_policy(self, policy: HttpRedirectPolicy) -> Self {
+        Self {
+            http_redirects: policy,
+            ..self
+        }
+    }
+
     /// Limit of the stored stderr data.
     pub fn with_stderr_bytes(self, limit: usize) -> Self {
         Self {
@@ -122,6 +454,22 @@ impl WasmPermissions {
         }
     }
 
+    /// Set when stderr data is attached to errors.
+    pub fn with_stderr_policy(self, policy: StderrPolicy) -> Self {
+        Self {
+            stderr_policy: policy,
+            ..self
+        }
+    }
+
+    /// Redact stderr data before it is attached to errors, e.g. to remove secrets.
+    pub fn with_stderr_redactor(self, redactor: Arc<dyn StderrRedactor>) -> Self {
+        Self {
+            stderr_redactor: Some(redactor),
+            ..self
+        }
+    }
+
     /// Set static resource limits.
     ///
     /// Note that this does NOT limit the overall memory consumption of the payload. This will be done via [`MemoryPool`].
@@ -135,6 +483,17 @@ impl WasmPermissions {
         }
     }
 
+    /// Set memory limits that adapt to the state of the DataFusion [`MemoryPool`].
+    ///
+    ///
+    /// [`MemoryPool`]: datafusion_execution::memory_pool::MemoryPool
+    pub fn with_dynamic_memory_limits(self, limits: DynamicMemoryLimits) -> Self {
+        Self {
+            dynamic_memory_limits: limits,
+            ..self
+        }
+    }
+
     /// Set trusted data limits.
     pub fn with_trusted_data_limits(self, limits: TrustedDataLimits) -> Self {
         Self {
@@ -151,6 +510,20 @@ impl WasmPermissions {
         }
     }
 
+    /// Overlay TAR archive onto the root filesystem of the guest.
+    ///
+    /// The archive is applied after the root filesystem that is shipped with the component and after
+    /// archives that were added earlier. This can be used to inject dependencies -- e.g. pure-Python packages into
+    /// `lib/python3.14/site-packages` -- at instantiation time without rebuilding the component. Directories are
+    /// merged, but existing files can NOT be replaced. Every archive must contain entries for all its directories.
+    ///
+    /// The archive is parsed for every guest instance and accounted for by the [VFS limits](Self::with_vfs_limits).
+    /// Invalid archives result in an error when the guest is created.
+    pub fn with_extra_root_tar(mut self, tar: impl Into<Arc<[u8]>>) -> Self {
+        self.extra_root_tars.push(tar.into());
+        self
+    }
+
     /// Get the maximum number of UDFs that a payload/guest can produce.
     pub fn max_udfs(&self) -> usize {
         self.max_udfs
@@ -164,6 +537,39 @@ impl WasmPermissions {
         }
     }
 
+    /// Set when two UDF names of the same payload/guest collide.
+    pub fn with_udf_name_collision_policy(self, policy: UdfNameCollisionPolicy) -> Self {
+        Self {
+            udf_name_collisions: policy,
+            ..self
+        }
+    }
+
+    /// Request checksums of UDF results from the guest and verify them after they were transferred to the host.
+    ///
+    /// This catches memory corruption -- e.g. caused by bugs in the guest toolchain -- and turns it into an error
+    /// instead of silently producing bad data. It comes at the cost of hashing every result on both sides.
+    pub fn with_result_checksums(self, enabled: bool) -> Self {
+        Self {
+            result_checksums: enabled,
+            ..self
+        }
+    }
+
+    /// Compress arrow IPC payloads -- i.e. UDF arguments and results -- that cross the sandbox boundary.
+    ///
+    /// The codecs are listed in order of preference. The first codec that the guest supports is used, if the guest
+    /// supports none of them the payloads are NOT compressed. This trades CPU time for less data that is copied into
+    /// and out of the guest memory, which pays off for large, well-compressible batches. Defaults to no compression.
+    ///
+    /// The decoded size of the guest results is still limited by [`TrustedDataLimits::max_array_bytes`].
+    pub fn with_ipc_compression(self, preferred: Vec<IpcCompression>) -> Self {
+        Self {
+            ipc_compression: preferred,
+            ..self
+        }
+    }
+
     /// Maximum number of cached [`Field`]s.
     ///
     ///
@@ -186,9 +592,127 @@ impl WasmPermissions {
         }
     }
 
+    /// Set which [`ConfigOptions`] entries are forwarded to the guest.
+    ///
+    /// A pattern either matches a key exactly or -- if it ends with `*` -- matches all keys with the given prefix,
+    /// e.g. `datafusion.execution.*`. Config extensions are never forwarded, since the guest does not know about them.
+    ///
+    /// Defaults to `["datafusion.*"]`, i.e. all built-in DataFusion options.
+    ///
+    ///
+    /// [`ConfigOptions`]: datafusion_common::config::ConfigOptions
+    pub fn with_config_allowlist(self, patterns: Vec<String>) -> Self {
+        Self {
+            config_forwarding: ConfigForwarding {
+                allowlist: patterns,
+                ..self.config_forwarding
+            },
+            ..self
+        }
+    }
+
+    /// Set limits for [`ConfigOptions`] that are forwarded to the guest.
+    ///
+    ///
+    /// [`ConfigOptions`]: datafusion_common::config::ConfigOptions
+    pub fn with_config_limits(self, limits: ConfigLimits) -> Self {
+        Self {
+            config_forwarding: ConfigForwarding {
+                limits,
+                ..self.config_forwarding
+            },
+            ..self
+        }
+    }
+
     /// Add environment variable.
     pub fn with_env(mut self, key: String, value: String) -> Self {
         self.envs.insert(key, value);
         self
     }
+
+    /// Set which time the guest observes.
+    pub fn with_clock_policy(self, policy: ClockPolicy) -> Self {
+        Self {
+            clock: policy,
+            ..self
+        }
+    }
+
+    /// Set where the guest gets randomness from.
+    pub fn with_random_policy(self, policy: RandomPolicy) -> Self {
+        Self {
+            random: policy,
+            ..self
+        }
+    }
+
+    /// Report sandbox activity -- e.g. instance creation, VFS writes, timeouts, and errors -- to the given observer.
+    pub fn with_observer(self, observer: Arc<dyn SandboxObserver>) -> Self {
+        Self {
+            observer: Some(observer),
+            ..self
+        }
+    }
+
+    /// Register guests with the given handle, so that they can be torn down via [`WasmRuntimeHandle::shutdown`].
+    pub fn with_runtime_handle(self, handle: WasmRuntimeHandle) -> Self {
+        Self {
+            runtime_handle: Some(handle),
+            ..self
+        }
+    }
+
+    /// Attach labels -- e.g. the tenant -- to all errors and [observer](Self::with_observer) events of UDFs created with
+    /// these permissions.
+    ///
+    /// Labels that change per query -- e.g. the query ID -- are better passed via
+    /// [`WasmUdfConfig::context_labels`](crate::WasmUdfConfig::context_labels), since UDFs may be shared between queries.
+    pub fn with_context_labels(self, labels: ContextLabels) -> Self {
+        Self {
+            context_labels: labels,
+            ..self
+        }
+    }
+
+    /// Provide secrets -- e.g. API tokens -- to the guest.
+    ///
+    /// Guests request secrets by name. Every secret value that was handed to the guest is redacted from the stderr
+    /// output and from errors returned by the guest. Note that redaction is best-effort: a guest that transforms a secret
+    /// -- e.g. by encoding it -- can still leak it. Defaults to no access.
+    pub fn with_secret_provider(self, provider: Arc<dyn SecretProvider>) -> Self {
+        Self {
+            secret_provider: Some(provider),
+            ..self
+        }
+    }
+
+    /// Grant access to a key-value store.
+    ///
+    /// Guests can use this for small caches that persist across invocations, e.g. to memoize lookups. Defaults to no
+    /// access.
+    pub fn with_kv(self, config: KvConfig) -> Self {
+        Self {
+            kv: Some(config),
+            ..self
+        }
+    }
+
+    /// Set models that the guest may load via [`wasi-nn`].
+    ///
+    /// Models are loaded by the host whenever a guest instance is created, and their file sizes are accounted
+    /// against the memory pool. Guests can only load these models by name, they can neither load models from
+    /// their own bytes nor access the model files. Defaults to no models.
+    ///
+    /// Inference runs on the host and cannot be interrupted, so [timeouts](Self::with_invoke_timeout) only apply
+    /// once it returns. Requires the `nn` feature, otherwise creating the guest fails if any models are set.
+    ///
+    ///
+    /// [`wasi-nn`]: https://github.com/WebAssembly/wasi-nn
+    pub fn with_nn(self, models: impl IntoIterator<Item = NnModel>) -> Self {
+        Self {
+            nn_models: models.into_iter().collect(),
+            ..self
+        }
+    }
 }
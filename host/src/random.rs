@@ -0,0 +1,174 @@
+//! Randomness that is exposed to the guest.
+
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use wasmtime::component::HasData;
+use wasmtime_wasi::p2::bindings::random::{insecure, insecure_seed, random};
+
+use crate::volatility::ImmutableFlag;
+
+/// Defines where the guest gets randomness from via `wasi:random`.
+///
+/// Note that many guests -- including the Python guest -- already require some randomness during startup, e.g. to
+/// seed hash functions.
+#[derive(Debug, Clone, Copy, Default)]
+pub enum RandomPolicy {
+    /// Deny access to randomness.
+    ///
+    /// Any attempt of the guest to get random data will trap.
+    Deny,
+
+    /// Use a deterministic pseudo-random number generator with the given seed.
+    ///
+    /// Every WASM VM starts with the same state, so UDFs that are evaluated in the same order produce the same results.
+    /// This is useful for testing and for [stable](datafusion_expr::Volatility::Stable) UDFs that should produce
+    /// reproducible results. The generator is NOT cryptographically secure.
+    Seeded(u64),
+
+    /// Use entropy provided by the host.
+    #[default]
+    HostEntropy,
+}
+
+/// Maximum number of random bytes that the guest can request per call.
+///
+/// The buffer is allocated on the host BEFORE it is copied into the guest memory, so it is NOT accounted by the
+/// [`Limiter`](crate::limiter::Limiter). Guests that need more randomness have to call multiple times.
+const MAX_RANDOM_BYTES: u64 = 64 * 1024;
+
+/// Random number generator state of a guest.
+#[derive(Debug)]
+pub(crate) struct RandomState {
+    /// Source of randomness.
+    source: RandomSource,
+
+    /// Deny access while the guest executes an immutable UDF.
+    immutable: ImmutableFlag,
+}
+
+/// Source of randomness, see [`RandomPolicy`].
+#[derive(Debug)]
+enum RandomSource {
+    /// See [`RandomPolicy::Deny`].
+    Deny,
+
+    /// See [`RandomPolicy::Seeded`].
+    Seeded(Box<StdRng>),
+
+    /// See [`RandomPolicy::HostEntropy`].
+    HostEntropy,
+}
+
+impl RandomState {
+    /// Create new state.
+    pub(crate) fn new(policy: RandomPolicy, immutable: &ImmutableFlag) -> Self {
+        let source = match policy {
+            RandomPolicy::Deny => RandomSource::Deny,
+            RandomPolicy::Seeded(seed) => {
+                RandomSource::Seeded(Box::new(StdRng::seed_from_u64(seed)))
+            }
+            RandomPolicy::HostEntropy => RandomSource::HostEntropy,
+        };
+        Self {
+            source,
+            immutable: immutable.clone(),
+        }
+    }
+
+    /// Get source, checking that access is allowed.
+    fn source(&mut self) -> wasmtime::Result<&mut RandomSource> {
+        if self.immutable.is_set() {
+            return Err(wasmtime::Error::msg(
+                "access to randomness denied for immutable UDF",
+            ));
+        }
+        match &mut self.source {
+            RandomSource::Deny => Err(denied()),
+            source => Ok(source),
+        }
+    }
+
+    /// Get random bytes.
+    fn bytes(&mut self, len: u64) -> wasmtime::Result<Vec<u8>> {
+        let source = self.source()?;
+
+        if len > MAX_RANDOM_BYTES {
+            return Err(wasmtime::Error::msg(format!(
+                "too many random bytes requested: got={len}, limit={MAX_RANDOM_BYTES}"
+            )));
+        }
+        let len = usize::try_from(len).map_err(wasmtime::Error::new)?;
+        let mut buf = vec![0; len];
+        match source {
+            RandomSource::Deny => unreachable!("checked by source()"),
+            RandomSource::Seeded(rng) => rng.fill_bytes(&mut buf),
+            RandomSource::HostEntropy => rand::rng().fill_bytes(&mut buf),
+        }
+        Ok(buf)
+    }
+
+    /// Get random `u64`.
+    fn u64(&mut self) -> wasmtime::Result<u64> {
+        match self.source()? {
+            RandomSource::Deny => unreachable!("checked by source()"),
+            RandomSource::Seeded(rng) => Ok(rng.next_u64()),
+            RandomSource::HostEntropy => Ok(rand::rng().next_u64()),
+        }
+    }
+}
+
+/// Error returned for [`RandomPolicy::Deny`].
+fn denied() -> wasmtime::Error {
+    wasmtime::Error::msg("access to randomness denied")
+}
+
+impl random::Host for RandomState {
+    fn get_random_bytes(&mut self, len: u64) -> wasmtime::Result<Vec<u8>> {
+        self.bytes(len)
+    }
+
+    fn get_random_u64(&mut self) -> wasmtime::Result<u64> {
+        self.u64()
+    }
+}
+
+impl insecure::Host for RandomState {
+    fn get_insecure_random_bytes(&mut self, len: u64) -> wasmtime::Result<Vec<u8>> {
+        self.bytes(len)
+    }
+
+    fn get_insecure_random_u64(&mut self) -> wasmtime::Result<u64> {
+        self.u64()
+    }
+}
+
+impl insecure_seed::Host for RandomState {
+    fn insecure_seed(&mut self) -> wasmtime::Result<(u64, u64)> {
+        Ok((self.u64()?, self.u64()?))
+    }
+}
+
+/// Marker struct to tell linker that we provide randomness.
+pub(crate) struct HasRandom;
+
+impl HasData for HasRandom {
+    type Data<'a> = &'a mut RandomState;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bytes_limit() {
+        let mut state = RandomState::new(RandomPolicy::Seeded(42), &ImmutableFlag::default());
+
+        assert_eq!(state.bytes(MAX_RANDOM_BYTES).unwrap().len(), 64 * 1024);
+
+        let err = state.bytes(MAX_RANDOM_BYTES + 1).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "too many random bytes requested: got=65537, limit=65536",
+        );
+        state.bytes(u64::MAX).unwrap_err();
+    }
+}
@@ -0,0 +1,426 @@
+//! Re-instantiation of poisoned and suspended guests.
+
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use datafusion_common::{DataFusionError, Result as DataFusionResult};
+use datafusion_execution::memory_pool::MemoryPool;
+use tokio::{runtime::Handle, sync::Mutex};
+use wasmtime::{AsContextMut, component::ResourceAny};
+
+use crate::{
+    WasmComponentPrecompiled, WasmPermissions,
+    component::WasmComponentInstance,
+    error::{DataFusionResultExt, WasmToDataFusionResultExt, WasmUdfError, WitDataFusionResultExt},
+    trace::span,
+};
+
+/// Guest that is shared by all UDFs that were created from the same source.
+///
+/// A guest that hits a [fatal](WasmUdfError::is_fatal) error is [poisoned](WasmComponentInstance::poison). Up to
+/// [`max_recycles`](WasmPermissions::with_max_recycles) times, the next call then re-instantiates the component and
+/// sets up all UDFs again.
+///
+/// A guest can also be [suspended](Self::suspend) -- either explicitly or after being
+/// [idle](WasmPermissions::with_idle_ttl) for too long -- which drops it. The next call then re-instantiates the
+/// component in the same way, but without using up the recycle budget.
+#[derive(Debug)]
+pub(crate) struct RecyclableInstance {
+    /// Current generation and the remaining recycle budget.
+    state: Mutex<RecycleState>,
+
+    /// Component that is re-instantiated.
+    component: WasmComponentPrecompiled,
+
+    /// Permissions for new instances.
+    permissions: WasmPermissions,
+
+    /// I/O runtime for new instances.
+    io_rt: Handle,
+
+    /// Memory pool for new instances.
+    memory_pool: Arc<dyn MemoryPool>,
+
+    /// Source code that is passed to new instances.
+    source: Arc<str>,
+
+    /// Timeout for blocking tasks.
+    ///
+    /// This only depends on the permissions and is therefore the same for all instances.
+    inplace_blocking_timeout: Duration,
+}
+
+/// Mutable state of [`RecyclableInstance`].
+#[derive(Debug)]
+struct RecycleState {
+    /// Current instance.
+    ///
+    /// This is `None` if the guest is [suspended](RecyclableInstance::suspend).
+    instance: Option<Arc<WasmComponentInstance>>,
+
+    /// UDF resources within [`instance`](Self::instance), indexed by UDF name.
+    resources: HashMap<String, ResourceAny>,
+
+    /// Remaining number of re-instantiations.
+    recycles_left: usize,
+
+    /// Start of the last call that used the instance.
+    last_used: Instant,
+}
+
+impl RecyclableInstance {
+    /// Wrap an instance that was already set up.
+    pub(crate) fn new(
+        instance: Arc<WasmComponentInstance>,
+        resources: HashMap<String, ResourceAny>,
+        component: &WasmComponentPrecompiled,
+        permissions: &WasmPermissions,
+        io_rt: Handle,
+        memory_pool: &Arc<dyn MemoryPool>,
+        source: Arc<str>,
+    ) -> Self {
+        let inplace_blocking_timeout = instance.inplace_blocking_timeout();
+
+        Self {
+            state: Mutex::new(RecycleState {
+                instance: Some(instance),
+                resources,
+                recycles_left: permissions.max_recycles,
+                last_used: Instant::now(),
+            }),
+            component: component.clone(),
+            permissions: permissions.clone(),
+            io_rt,
+            memory_pool: Arc::clone(memory_pool),
+            source,
+            inplace_blocking_timeout,
+        }
+    }
+
+    /// Timeout for blocking tasks.
+    pub(crate) fn inplace_blocking_timeout(&self) -> Duration {
+        self.inplace_blocking_timeout
+    }
+
+    /// Create new instance and set up the given UDF.
+    ///
+    /// This is used to give a UDF its own instance, see [`InstanceSharing::PerUdf`](crate::InstanceSharing::PerUdf).
+    pub(crate) async fn create(
+        component: &WasmComponentPrecompiled,
+        permissions: &WasmPermissions,
+        io_rt: Handle,
+        memory_pool: &Arc<dyn MemoryPool>,
+        source: Arc<str>,
+        name: &str,
+    ) -> DataFusionResult<Self> {
+        let (instance, resources) = instantiate(
+            component,
+            permissions,
+            io_rt.clone(),
+            memory_pool,
+            &source,
+            &[name.to_owned()],
+        )
+        .await?;
+        Ok(Self::new(
+            instance,
+            resources,
+            component,
+            permissions,
+            io_rt,
+            memory_pool,
+            source,
+        ))
+    }
+
+    /// Get current instance and the resource of the given UDF.
+    ///
+    /// Re-instantiates the guest if it was suspended, or if it was poisoned and the recycle budget is not used up yet.
+    pub(crate) async fn current(
+        &self,
+        name: &str,
+    ) -> DataFusionResult<(Arc<WasmComponentInstance>, ResourceAny)> {
+        if let Some(handle) = &self.permissions.runtime_handle {
+            handle.check()?;
+        }
+
+        let mut state = self.state.lock().await;
+        state.last_used = Instant::now();
+
+        let poisoned = state
+            .instance
+            .as_ref()
+            .map(|instance| instance.is_poisoned());
+        match poisoned {
+            None => {
+                log::debug!("re-instantiate suspended guest");
+                self.reinstantiate(&mut state)
+                    .await
+                    .context("re-instantiate suspended guest")?;
+            }
+            Some(true) if state.recycles_left > 0 => {
+                state.recycles_left -= 1;
+                log::info!(
+                    "re-instantiate poisoned guest, recycles_left={}",
+                    state.recycles_left
+                );
+                self.reinstantiate(&mut state)
+                    .await
+                    .context("re-instantiate poisoned guest")?;
+            }
+            Some(_) => {}
+        }
+
+        Self::lookup(&state, name)
+    }
+
+    /// Replace current instance with a new one and set up all UDFs again.
+    async fn reinstantiate(&self, state: &mut RecycleState) -> DataFusionResult<()> {
+        let names = state.resources.keys().cloned().collect::<Vec<_>>();
+        let (instance, resources) = instantiate(
+            &self.component,
+            &self.permissions,
+            self.io_rt.clone(),
+            &self.memory_pool,
+            &self.source,
+            &names,
+        )
+        .await?;
+        state.instance = Some(instance);
+        state.resources = resources;
+        Ok(())
+    }
+
+    /// Get current instance and the resource of the given UDF, without re-instantiating the guest.
+    pub(crate) async fn current_unchecked(
+        &self,
+        name: &str,
+    ) -> DataFusionResult<(Arc<WasmComponentInstance>, ResourceAny)> {
+        let state = self.state.lock().await;
+        Self::lookup(&state, name)
+    }
+
+    /// Get current instance, without re-instantiating the guest.
+    ///
+    /// Returns `None` if the guest is [suspended](Self::suspend).
+    pub(crate) async fn current_instance(&self) -> Option<Arc<WasmComponentInstance>> {
+        self.state.lock().await.instance.clone()
+    }
+
+    /// [Poison](WasmComponentInstance::poison_if_cancelled) current instance if a call was cancelled.
+    pub(crate) async fn poison_if_cancelled(&self) {
+        if let Some(instance) = &self.state.lock().await.instance {
+            instance.poison_if_cancelled();
+        }
+    }
+
+    /// Drop current instance.
+    ///
+    /// The `close` hooks of all UDFs are called first. The next call [re-instantiates](Self::current) the guest.
+    pub(crate) async fn suspend(&self) {
+        let mut state = self.state.lock().await;
+        Self::suspend_locked(&mut state).await;
+    }
+
+    /// Drop current instance and [interrupt](WasmComponentInstance::interrupt) running calls.
+    ///
+    /// Returns the interrupted instance, which may still be referenced by running calls. This is used for
+    /// [shutdown](crate::WasmRuntimeHandle::shutdown).
+    pub(crate) async fn shutdown(&self) -> Option<Arc<WasmComponentInstance>> {
+        let instance = self.state.lock().await.instance.take()?;
+        instance.interrupt();
+        Some(instance)
+    }
+
+    /// Spawn background task that [suspends](Self::suspend) the guest once it was idle for
+    /// [`idle_ttl`](WasmPermissions::with_idle_ttl).
+    ///
+    /// The task stops once this object is dropped.
+    pub(crate) fn start_idle_teardown(self: &Arc<Self>) {
+        let Some(idle_ttl) = self.permissions.idle_ttl else {
+            return;
+        };
+
+        let this = Arc::downgrade(self);
+        self.io_rt.spawn(async move {
+            let mut ticker = tokio::time::interval(idle_ttl);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+            loop {
+                ticker.tick().await;
+
+                let Some(this) = this.upgrade() else {
+                    return;
+                };
+                let mut state = this.state.lock().await;
+                let idle = state.last_used.elapsed() >= idle_ttl
+                    // no call holds on to the instance
+                    && state
+                        .instance
+                        .as_ref()
+                        .is_some_and(|instance| Arc::strong_count(instance) == 1);
+                if idle {
+                    log::debug!("suspend idle guest");
+                    Self::suspend_locked(&mut state).await;
+                }
+            }
+        });
+    }
+
+    /// Drop instance of the given state, see [`suspend`](Self::suspend).
+    async fn suspend_locked(state: &mut RecycleState) {
+        let Some(instance) = state.instance.take() else {
+            return;
+        };
+
+        // a poisoned guest cannot be called anymore
+        if instance.is_poisoned() {
+            return;
+        }
+
+        for (name, resource) in &state.resources {
+            if let Err(e) = call_close(&instance, *resource).await {
+                log::warn!("cannot close UDF `{name}`: {e}");
+            }
+        }
+    }
+
+    /// Find resource of the given UDF.
+    fn lookup(
+        state: &RecycleState,
+        name: &str,
+    ) -> DataFusionResult<(Arc<WasmComponentInstance>, ResourceAny)> {
+        let instance = state
+            .instance
+            .as_ref()
+            .ok_or_else(|| DataFusionError::Execution("guest instance is suspended".to_owned()))?;
+        let resource = state.resources.get(name).copied().ok_or_else(|| {
+            DataFusionError::Internal(format!("unknown UDF `{name}` in guest instance"))
+        })?;
+        Ok((Arc::clone(instance), resource))
+    }
+}
+
+/// Create new instance and set up the given UDFs.
+///
+/// Other UDFs that the guest returns are NOT set up.
+async fn instantiate(
+    component: &WasmComponentPrecompiled,
+    permissions: &WasmPermissions,
+    io_rt: Handle,
+    memory_pool: &Arc<dyn MemoryPool>,
+    source: &str,
+    names: &[String],
+) -> DataFusionResult<(Arc<WasmComponentInstance>, HashMap<String, ResourceAny>)> {
+    let instance =
+        Arc::new(WasmComponentInstance::new(component, permissions, io_rt, memory_pool).await?);
+
+    let mut udfs = vec![];
+    for resource in call_load_source(&instance, source, permissions).await? {
+        let mut state = instance.lock_state().await?;
+        let name = instance
+            .bindings()
+            .datafusion_udf_wasm_udf_types()
+            .scalar_udf()
+            .call_name(&mut state, resource)
+            .await
+            .context("call ScalarUdf::name", Some(&state.stderr))?;
+        if names.contains(&name) {
+            udfs.push((name, resource));
+        }
+    }
+
+    // The UDF objects already exist, so the guest must provide the same UDFs. Signatures are NOT checked again.
+    let resources = udfs.iter().cloned().collect::<HashMap<_, _>>();
+    if let Some(missing) = names.iter().find(|name| !resources.contains_key(*name)) {
+        return Err(DataFusionError::External(
+            format!("guest did not return UDF `{missing}` again").into(),
+        ));
+    }
+
+    for (name, resource) in &udfs {
+        call_init(&instance, *resource, name, permissions).await?;
+    }
+
+    Ok((instance, resources))
+}
+
+/// Load source code into the guest and get the UDF resources that it defines.
+///
+/// This may be called multiple times for the same instance, the resources of earlier calls stay valid.
+pub(crate) async fn call_load_source(
+    instance: &WasmComponentInstance,
+    source: &str,
+    permissions: &WasmPermissions,
+) -> DataFusionResult<Vec<ResourceAny>> {
+    let udf_resources = {
+        let mut state = instance.lock_state().await?;
+        let res = span!("load_source", source_bytes = source.len() as u64)
+            .instrument(
+                instance
+                    .bindings()
+                    .datafusion_udf_wasm_udf_types()
+                    .instance()
+                    .call_load_source(&mut state, instance.guest_instance(), source),
+            )
+            .await;
+        res.context("calling load_source() method failed", Some(&state.stderr))?
+            .convert_err(permissions.trusted_data_limits.clone(), &state.stderr)
+            .context("load_source")?
+    };
+    if udf_resources.len() > permissions.max_udfs {
+        return Err(DataFusionError::ResourcesExhausted(format!(
+            "guest returned too many UDFs: got={}, limit={}",
+            udf_resources.len(),
+            permissions.max_udfs,
+        )));
+    }
+    Ok(udf_resources)
+}
+
+/// Run one-time setup of a UDF.
+pub(crate) async fn call_init(
+    instance: &WasmComponentInstance,
+    resource: ResourceAny,
+    name: &str,
+    permissions: &WasmPermissions,
+) -> DataFusionResult<()> {
+    let mut state = instance.lock_state().await?;
+    state.as_context_mut().data_mut().invocation_deadline =
+        Some(Instant::now() + permissions.init_timeout);
+    state.as_context_mut().data_mut().limiter.take_exceeded();
+    let res = instance
+        .bindings()
+        .datafusion_udf_wasm_udf_types()
+        .scalar_udf()
+        .call_init(&mut state, resource)
+        .await;
+    state.as_context_mut().data_mut().invocation_deadline = None;
+    let exceeded = state.as_context_mut().data_mut().limiter.take_exceeded();
+    res.context("call ScalarUdf::init", Some(&state.stderr))
+        .map_err(|e| match exceeded {
+            Some(kind) => WasmUdfError::reclassify_trap(e, kind),
+            None => e,
+        })?
+        .convert_err(permissions.trusted_data_limits.clone(), &state.stderr)
+        .with_context(|| format!("init `{name}`"))
+}
+
+/// Run teardown of a UDF.
+async fn call_close(
+    instance: &WasmComponentInstance,
+    resource: ResourceAny,
+) -> DataFusionResult<()> {
+    let mut state = instance.lock_state().await?;
+    instance
+        .bindings()
+        .datafusion_udf_wasm_udf_types()
+        .scalar_udf()
+        .call_close(&mut state, resource)
+        .await
+        .context("call ScalarUdf::close", Some(&state.stderr))?
+        .convert_err(instance.trusted_data_limits().clone(), &state.stderr)
+}
@@ -0,0 +1,134 @@
+//! Registration of [`WasmScalarUdf`]s with DataFusion.
+
+use std::{any::Any, cell::Cell, hash::Hash, sync::Arc};
+
+use arrow::datatypes::{DataType, FieldRef};
+use datafusion_common::Result as DataFusionResult;
+use datafusion_expr::{
+    ColumnarValue, Expr, ReturnFieldArgs, ScalarFunctionArgs, ScalarUDF, ScalarUDFImpl, Signature,
+    async_udf::AsyncScalarUDF,
+    simplify::{ExprSimplifyResult, SimplifyInfo},
+    sort_properties::{ExprProperties, SortProperties},
+};
+
+use crate::WasmScalarUdf;
+
+thread_local! {
+    /// Let [`RegisteredUdf::as_any`] return the [`RegisteredUdf`] itself, see [`WasmScalarUdf::from_scalar_udf`].
+    static UNWRAP: Cell<bool> = const { Cell::new(false) };
+}
+
+/// [`ScalarUDFImpl`] that DataFusion sees when a [`WasmScalarUdf`] is [registered](WasmScalarUdf::into_scalar_udf).
+///
+/// DataFusion only evaluates a UDF asynchronously if [`ScalarUDFImpl::as_any`] returns an [`AsyncScalarUDF`]. That
+/// type does not give access to the UDF that it wraps and only forwards some methods. So this wrapper returns an
+/// [`AsyncScalarUDF`] from [`as_any`](ScalarUDFImpl::as_any) but implements all other methods itself.
+#[derive(Debug)]
+struct RegisteredUdf {
+    /// The actual UDF.
+    udf: Arc<WasmScalarUdf>,
+
+    /// Async view of [`udf`](Self::udf).
+    async_udf: AsyncScalarUDF,
+}
+
+impl PartialEq<Self> for RegisteredUdf {
+    fn eq(&self, other: &Self) -> bool {
+        self.udf == other.udf
+    }
+}
+
+impl Eq for RegisteredUdf {}
+
+impl Hash for RegisteredUdf {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.udf.hash(state);
+    }
+}
+
+impl ScalarUDFImpl for RegisteredUdf {
+    fn as_any(&self) -> &dyn Any {
+        if UNWRAP.get() { self } else { &self.async_udf }
+    }
+
+    fn name(&self) -> &str {
+        self.udf.name()
+    }
+
+    fn signature(&self) -> &Signature {
+        self.udf.signature()
+    }
+
+    fn return_type(&self, arg_types: &[DataType]) -> DataFusionResult<DataType> {
+        self.udf.return_type(arg_types)
+    }
+
+    fn return_field_from_args(&self, args: ReturnFieldArgs<'_>) -> DataFusionResult<FieldRef> {
+        self.udf.return_field_from_args(args)
+    }
+
+    fn coerce_types(&self, arg_types: &[DataType]) -> DataFusionResult<Vec<DataType>> {
+        self.udf.coerce_types(arg_types)
+    }
+
+    fn short_circuits(&self) -> bool {
+        self.udf.short_circuits()
+    }
+
+    fn output_ordering(&self, inputs: &[ExprProperties]) -> DataFusionResult<SortProperties> {
+        self.udf.output_ordering(inputs)
+    }
+
+    fn preserves_lex_ordering(&self, inputs: &[ExprProperties]) -> DataFusionResult<bool> {
+        self.udf.preserves_lex_ordering(inputs)
+    }
+
+    fn simplify(
+        &self,
+        args: Vec<Expr>,
+        info: &dyn SimplifyInfo,
+    ) -> DataFusionResult<ExprSimplifyResult> {
+        self.udf.simplify(args, info)
+    }
+
+    fn invoke_with_args(&self, args: ScalarFunctionArgs) -> DataFusionResult<ColumnarValue> {
+        self.async_udf.invoke_with_args(args)
+    }
+}
+
+impl WasmScalarUdf {
+    /// Convert this [WasmScalarUdf] into a [ScalarUDF] that can be registered with DataFusion.
+    ///
+    /// The result is evaluated asynchronously like an [`AsyncScalarUDF`], but -- unlike
+    /// [`as_async_udf`](Self::as_async_udf) -- it can be mapped back to this UDF, see
+    /// [`from_scalar_udf`](Self::from_scalar_udf).
+    pub fn into_scalar_udf(self: Arc<Self>) -> ScalarUDF {
+        let async_udf = AsyncScalarUDF::new(Arc::clone(&self) as _);
+        ScalarUDF::new_from_impl(RegisteredUdf {
+            udf: self,
+            async_udf,
+        })
+    }
+
+    /// Get the [`WasmScalarUdf`] behind a [`ScalarUDF`] that was created via
+    /// [`into_scalar_udf`](Self::into_scalar_udf).
+    ///
+    /// Returns [`None`] for all other UDFs.
+    pub fn from_scalar_udf(udf: &ScalarUDF) -> Option<Arc<Self>> {
+        /// Resets [`UNWRAP`] even if [`ScalarUDFImpl::as_any`] panics.
+        struct Reset;
+
+        impl Drop for Reset {
+            fn drop(&mut self) {
+                UNWRAP.set(false);
+            }
+        }
+
+        UNWRAP.set(true);
+        let _reset = Reset;
+        udf.inner()
+            .as_any()
+            .downcast_ref::<RegisteredUdf>()
+            .map(|registered| Arc::clone(&registered.udf))
+    }
+}
@@ -0,0 +1,403 @@
+//! Registry of UDF sources.
+
+use std::{
+    any::Any,
+    collections::{BTreeMap, HashMap},
+    hash::Hash,
+    sync::{Arc, Mutex, RwLock},
+};
+
+use arrow::datatypes::DataType;
+use datafusion_common::{DataFusionError, Result as DataFusionResult};
+use datafusion_execution::memory_pool::MemoryPool;
+use datafusion_expr::{
+    ColumnarValue, ScalarFunctionArgs, ScalarUDFImpl, Signature, async_udf::AsyncScalarUDFImpl,
+};
+use tokio::runtime::Handle;
+use uuid::Uuid;
+use wasmtime_wasi::async_trait;
+
+use crate::{WasmComponentHealth, WasmComponentPrecompiled, WasmPermissions, WasmScalarUdf};
+
+/// A language, i.e. a guest that turns source code into UDFs.
+#[derive(Debug)]
+struct Language {
+    /// Pre-compiled guest.
+    component: Arc<WasmComponentPrecompiled>,
+
+    /// Permissions for the guest.
+    permissions: WasmPermissions,
+}
+
+/// A registered source.
+#[derive(Debug)]
+struct Source {
+    /// Language name.
+    lang: String,
+
+    /// Source code.
+    code: Arc<str>,
+
+    /// UDFs created from the source.
+    udfs: Vec<Arc<RegistryUdf>>,
+}
+
+/// Registry that owns UDFs created from named sources.
+///
+/// Every registered source gets its own WASM VM, i.e. UDFs of different sources do NOT share any state. Handles that
+/// were handed out follow [reloads](Self::reload) and [refreshes](Self::refresh) of their source. They stay valid
+/// after a source was [unregistered](Self::unregister) or replaced via [`register_source`](Self::register_source), they
+/// just no longer show up in the registry.
+#[derive(Debug)]
+pub struct WasmUdfRegistry {
+    /// I/O runtime, see [`WasmScalarUdf::new`].
+    io_rt: Handle,
+
+    /// Memory pool, see [`WasmScalarUdf::new`].
+    memory_pool: Arc<dyn MemoryPool>,
+
+    /// Known languages, by name.
+    languages: HashMap<String, Language>,
+
+    /// Registered sources, by name.
+    sources: Mutex<BTreeMap<String, Source>>,
+}
+
+impl WasmUdfRegistry {
+    /// Create empty registry without any languages.
+    pub fn new(io_rt: Handle, memory_pool: Arc<dyn MemoryPool>) -> Self {
+        Self {
+            io_rt,
+            memory_pool,
+            languages: HashMap::default(),
+            sources: Mutex::default(),
+        }
+    }
+
+    /// Add language, i.e. a guest that turns source code into UDFs.
+    ///
+    /// An existing language with the same name is replaced. This does NOT affect sources that are already registered
+    /// until they are [refreshed](Self::refresh).
+    pub fn with_language(
+        mut self,
+        lang: impl Into<String>,
+        component: Arc<WasmComponentPrecompiled>,
+        permissions: WasmPermissions,
+    ) -> Self {
+        self.languages.insert(
+            lang.into(),
+            Language {
+                component,
+                permissions,
+            },
+        );
+        self
+    }
+
+    /// Create UDFs from source code and register them under the given name.
+    ///
+    /// An existing source with the same name is replaced.
+    pub async fn register_source(
+        &self,
+        name: impl Into<String>,
+        lang: impl Into<String>,
+        code: impl Into<String>,
+    ) -> DataFusionResult<Vec<Arc<dyn AsyncScalarUDFImpl>>> {
+        let name = name.into();
+        let lang = lang.into();
+        let code: Arc<str> = Arc::from(code.into());
+
+        let udfs = self
+            .create(&lang, &code)
+            .await?
+            .into_iter()
+            .map(|udf| Arc::new(RegistryUdf::new(udf)))
+            .collect::<Vec<_>>();
+        let handles = handles(&udfs);
+        self.sources_guard()
+            .insert(name, Source { lang, code, udfs });
+        Ok(handles)
+    }
+
+    /// Remove source.
+    ///
+    /// Returns `true` if the source was registered.
+    pub fn unregister(&self, name: &str) -> bool {
+        self.sources_guard().remove(name).is_some()
+    }
+
+    /// Get UDFs of all registered sources, by source name.
+    pub fn list(&self) -> BTreeMap<String, Vec<Arc<dyn AsyncScalarUDFImpl>>> {
+        self.sources_guard()
+            .iter()
+            .map(|(name, source)| (name.clone(), handles(&source.udfs)))
+            .collect()
+    }
+
+    /// Replace source code of a registered source.
+    ///
+    /// The new WASM VM is created while the old one keeps serving calls. Then all handles are switched over at once.
+    /// The old VM is torn down when all in-flight calls finished.
+    ///
+    /// The new source code must produce UDFs with the same names and signatures, since these were already used to plan
+    /// queries. Use [`register_source`](Self::register_source) otherwise.
+    pub async fn reload(&self, name: &str, code: impl Into<String>) -> DataFusionResult<()> {
+        let code: Arc<str> = Arc::from(code.into());
+        let Some((lang, udfs)) = self
+            .sources_guard()
+            .get(name)
+            .map(|source| (source.lang.clone(), source.udfs.clone()))
+        else {
+            return Err(DataFusionError::Plan(format!("unknown source: `{name}`")));
+        };
+
+        let new_udfs = self
+            .create(&lang, &code)
+            .await
+            .map_err(|e| e.context(format!("reload `{name}`")))?;
+        let new_udfs =
+            match_udfs(&udfs, new_udfs).map_err(|e| e.context(format!("reload `{name}`")))?;
+
+        // the source may have been replaced or removed in the meantime
+        let mut sources = self.sources_guard();
+        let Some(source) = sources.get_mut(name) else {
+            return Err(DataFusionError::Plan(format!("unknown source: `{name}`")));
+        };
+        let unchanged = source.udfs.len() == udfs.len()
+            && source
+                .udfs
+                .iter()
+                .zip(&udfs)
+                .all(|(a, b)| Arc::ptr_eq(a, b));
+        if !unchanged {
+            return Err(DataFusionError::Plan(format!(
+                "source `{name}` was replaced concurrently"
+            )));
+        }
+        for (udf, new_udf) in udfs.iter().zip(new_udfs) {
+            udf.swap(new_udf);
+        }
+        source.code = code;
+
+        Ok(())
+    }
+
+    /// Re-create the UDFs of all registered sources.
+    ///
+    /// This uses fresh WASM VMs -- which resets the guest state -- and the current [languages](Self::with_language).
+    /// Like for [`reload`](Self::reload), the UDF names and signatures must not change. If any source fails, the
+    /// registry is NOT modified.
+    pub async fn refresh(&self) -> DataFusionResult<()> {
+        let snapshot = self
+            .sources_guard()
+            .iter()
+            .map(|(name, source)| {
+                (
+                    name.clone(),
+                    source.lang.clone(),
+                    Arc::clone(&source.code),
+                    source.udfs.clone(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let mut refreshed = Vec::with_capacity(snapshot.len());
+        for (name, lang, code, udfs) in snapshot {
+            let new_udfs = self
+                .create(&lang, &code)
+                .await
+                .and_then(|new_udfs| match_udfs(&udfs, new_udfs))
+                .map_err(|e| e.context(format!("refresh `{name}`")))?;
+            refreshed.push((udfs, new_udfs));
+        }
+
+        // sources that were replaced or removed in the meantime still own their handles, so swapping is harmless
+        for (udfs, new_udfs) in refreshed {
+            for (udf, new_udf) in udfs.iter().zip(new_udfs) {
+                udf.swap(new_udf);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// [Health check](WasmComponentPrecompiled::health_check) the guests of all languages, by language name.
+    ///
+    /// This also warms up the guests, so that the first [registration](Self::register_source) of a source is faster.
+    /// Call this at boot to fail fast if a guest is broken.
+    pub async fn warmup(&self) -> BTreeMap<String, DataFusionResult<WasmComponentHealth>> {
+        let mut outcome = BTreeMap::new();
+        for (lang, language) in &self.languages {
+            let health = language
+                .component
+                .health_check(&language.permissions, self.io_rt.clone())
+                .await
+                .map_err(|e| e.context(format!("warm up `{lang}`")));
+            outcome.insert(lang.clone(), health);
+        }
+        outcome
+    }
+
+    /// Create UDFs from source code.
+    async fn create(&self, lang: &str, code: &str) -> DataFusionResult<Vec<WasmScalarUdf>> {
+        let Some(language) = self.languages.get(lang) else {
+            return Err(DataFusionError::Plan(format!("unknown language: `{lang}`")));
+        };
+
+        WasmScalarUdf::new(
+            &language.component,
+            &language.permissions,
+            self.io_rt.clone(),
+            &self.memory_pool,
+            code.to_owned(),
+        )
+        .await
+    }
+
+    /// Lock sources.
+    fn sources_guard(&self) -> std::sync::MutexGuard<'_, BTreeMap<String, Source>> {
+        self.sources.lock().expect("sources lock poisoned")
+    }
+}
+
+/// Convert UDFs to handles.
+fn handles(udfs: &[Arc<RegistryUdf>]) -> Vec<Arc<dyn AsyncScalarUDFImpl>> {
+    udfs.iter()
+        .map(|udf| Arc::clone(udf) as Arc<dyn AsyncScalarUDFImpl>)
+        .collect()
+}
+
+/// Check that new UDFs can replace the existing ones and return them in the same order.
+fn match_udfs(
+    udfs: &[Arc<RegistryUdf>],
+    new_udfs: Vec<WasmScalarUdf>,
+) -> DataFusionResult<Vec<WasmScalarUdf>> {
+    if udfs.len() != new_udfs.len() {
+        return Err(DataFusionError::Plan(format!(
+            "expected {} UDFs but got {}",
+            udfs.len(),
+            new_udfs.len()
+        )));
+    }
+
+    let mut new_udfs = new_udfs
+        .into_iter()
+        .map(|udf| (udf.name().to_owned(), udf))
+        .collect::<HashMap<_, _>>();
+    udfs.iter()
+        .map(|udf| {
+            let new_udf = new_udfs
+                .remove(&udf.name)
+                .ok_or_else(|| DataFusionError::Plan(format!("UDF `{}` is missing", udf.name)))?;
+            if new_udf.signature() != &udf.signature {
+                return Err(DataFusionError::Plan(format!(
+                    "signature of UDF `{}` changed",
+                    udf.name
+                )));
+            }
+            Ok(new_udf)
+        })
+        .collect()
+}
+
+/// UDF handed out by the [`WasmUdfRegistry`].
+///
+/// This forwards to the current [`WasmScalarUdf`] which is swapped on [reload](WasmUdfRegistry::reload).
+#[derive(Debug)]
+struct RegistryUdf {
+    /// Name, fixed over all reloads.
+    name: String,
+
+    /// Signature, fixed over all reloads.
+    signature: Signature,
+
+    /// Current implementation.
+    ///
+    /// Calls clone the [`Arc`] so that in-flight calls keep the old one alive.
+    current: RwLock<Arc<WasmScalarUdf>>,
+
+    /// We treat every UDF as unique, but we need a proxy value to express that.
+    id: Uuid,
+}
+
+impl RegistryUdf {
+    /// Wrap UDF.
+    fn new(udf: WasmScalarUdf) -> Self {
+        Self {
+            name: udf.name().to_owned(),
+            signature: udf.signature().clone(),
+            current: RwLock::new(Arc::new(udf)),
+            id: Uuid::new_v4(),
+        }
+    }
+
+    /// Get current implementation.
+    fn current(&self) -> Arc<WasmScalarUdf> {
+        Arc::clone(&self.current.read().expect("UDF lock poisoned"))
+    }
+
+    /// Replace current implementation.
+    fn swap(&self, udf: WasmScalarUdf) {
+        let old = std::mem::replace(
+            &mut *self.current.write().expect("UDF lock poisoned"),
+            Arc::new(udf),
+        );
+        // the old UDF is torn down when the last in-flight call finished
+        drop(old);
+    }
+}
+
+impl PartialEq<Self> for RegistryUdf {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for RegistryUdf {}
+
+impl Hash for RegistryUdf {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+impl ScalarUDFImpl for RegistryUdf {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn return_type(&self, arg_types: &[DataType]) -> DataFusionResult<DataType> {
+        self.current().return_type(arg_types)
+    }
+
+    fn coerce_types(&self, arg_types: &[DataType]) -> DataFusionResult<Vec<DataType>> {
+        self.current().coerce_types(arg_types)
+    }
+
+    fn invoke_with_args(&self, args: ScalarFunctionArgs) -> DataFusionResult<ColumnarValue> {
+        self.current().invoke_with_args(args)
+    }
+}
+
+#[async_trait]
+impl AsyncScalarUDFImpl for RegistryUdf {
+    fn ideal_batch_size(&self) -> Option<usize> {
+        self.current().ideal_batch_size()
+    }
+
+    async fn invoke_async_with_args(
+        &self,
+        args: ScalarFunctionArgs,
+    ) -> DataFusionResult<ColumnarValue> {
+        let udf = self.current();
+        udf.invoke_async_with_args(args).await
+    }
+}
@@ -0,0 +1,140 @@
+//! Secrets that are exposed to the guest.
+
+use std::sync::{Arc, Mutex};
+
+use datafusion_common::DataFusionError;
+use wasmtime::component::HasData;
+
+use crate::bindings::datafusion_udf_wasm::udf::secrets;
+
+/// Replacement for redacted secrets.
+const REDACTED: &str = "[REDACTED]";
+
+/// Provides secrets -- e.g. API tokens -- to the guest.
+///
+/// See [`WasmPermissions::with_secret_provider`](crate::WasmPermissions::with_secret_provider).
+pub trait SecretProvider: std::fmt::Debug + Send + Sync + 'static {
+    /// Get secret by name.
+    ///
+    /// Return [`None`] if the secret does not exist or if the guest must not access it.
+    fn get(&self, name: &str) -> Option<String>;
+}
+
+/// Secret values that were handed to the guest.
+///
+/// These are redacted from stderr output and guest errors. The set is shared between the [`SecretsState`] and the
+/// [`Stderr`](crate::stderr::Stderr) buffer of the same guest.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct RevealedSecrets(Arc<Mutex<Vec<String>>>);
+
+impl RevealedSecrets {
+    /// Remember secret value.
+    fn add(&self, secret: &str) {
+        if secret.is_empty() {
+            return;
+        }
+
+        let mut guard = self.0.lock().expect("revealed secrets lock poisoned");
+        if !guard.iter().any(|s| s == secret) {
+            guard.push(secret.to_owned());
+            // redact longer secrets first, in case one secret contains another one
+            guard.sort_by_key(|s| std::cmp::Reverse(s.len()));
+        }
+    }
+
+    /// Redact all revealed secrets from string.
+    pub(crate) fn redact(&self, s: String) -> String {
+        let guard = self.0.lock().expect("revealed secrets lock poisoned");
+        redact_str(s, &guard)
+    }
+
+    /// Redact all revealed secrets from error.
+    ///
+    /// Messages and contexts are redacted in place. Other errors -- e.g. [external](DataFusionError::External) ones --
+    /// are replaced by their redacted message if they contain a secret.
+    pub(crate) fn redact_error(&self, e: DataFusionError) -> DataFusionError {
+        let guard = self.0.lock().expect("revealed secrets lock poisoned");
+        if guard.is_empty() {
+            return e;
+        }
+        redact_error(e, &guard)
+    }
+}
+
+/// Redact secrets from string.
+fn redact_str(mut s: String, secrets: &[String]) -> String {
+    for secret in secrets {
+        if s.contains(secret.as_str()) {
+            s = s.replace(secret.as_str(), REDACTED);
+        }
+    }
+    s
+}
+
+/// Redact secrets from error.
+fn redact_error(e: DataFusionError, secrets: &[String]) -> DataFusionError {
+    match e {
+        DataFusionError::Context(ctx, e) => DataFusionError::Context(
+            redact_str(ctx, secrets),
+            Box::new(redact_error(*e, secrets)),
+        ),
+        DataFusionError::Configuration(s) => DataFusionError::Configuration(redact_str(s, secrets)),
+        DataFusionError::Execution(s) => DataFusionError::Execution(redact_str(s, secrets)),
+        DataFusionError::Internal(s) => DataFusionError::Internal(redact_str(s, secrets)),
+        DataFusionError::NotImplemented(s) => {
+            DataFusionError::NotImplemented(redact_str(s, secrets))
+        }
+        DataFusionError::ResourcesExhausted(s) => {
+            DataFusionError::ResourcesExhausted(redact_str(s, secrets))
+        }
+        e => {
+            let s = e.to_string();
+            if secrets.iter().any(|secret| s.contains(secret.as_str())) {
+                DataFusionError::External(redact_str(s, secrets).into())
+            } else {
+                e
+            }
+        }
+    }
+}
+
+/// Secrets state of a guest.
+#[derive(Debug)]
+pub(crate) struct SecretsState {
+    /// Provider, or [`None`] if access is denied.
+    provider: Option<Arc<dyn SecretProvider>>,
+
+    /// Secrets that were handed to the guest.
+    revealed: RevealedSecrets,
+}
+
+impl SecretsState {
+    /// Create new state.
+    pub(crate) fn new(
+        provider: Option<Arc<dyn SecretProvider>>,
+        revealed: RevealedSecrets,
+    ) -> Self {
+        Self { provider, revealed }
+    }
+}
+
+impl secrets::Host for SecretsState {
+    async fn get(&mut self, name: String) -> wasmtime::Result<Option<String>> {
+        let Some(provider) = &self.provider else {
+            return Ok(None);
+        };
+
+        let secret = provider.get(&name);
+        if let Some(secret) = &secret {
+            self.revealed.add(secret);
+        }
+        Ok(secret)
+    }
+}
+
+/// Marker struct to tell linker that we provide secrets.
+pub(crate) struct HasSecrets;
+
+impl HasData for HasSecrets {
+    type Data<'a> = &'a mut SecretsState;
+}
@@ -0,0 +1,330 @@
+//! Startup self-check.
+use std::{
+    future::Future,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use datafusion_common::{DataFusionError, error::Result as DataFusionResult};
+use datafusion_execution::memory_pool::{GreedyMemoryPool, MemoryPool};
+use wasmtime::{Engine, Instance, Module, Store, Trap};
+
+use crate::{
+    CompilationFlags, DynamicMemoryLimits, StaticResourceLimits,
+    component::{NoCompilation, create_engine},
+    error::WasmToDataFusionResultExt,
+    limiter::Limiter,
+};
+
+/// Tiny built-in WASM module used for the self-check.
+///
+/// This is the binary representation of:
+///
+/// ```wat
+/// (module
+///   (memory 1)
+///   (func (export "add") (param i32 i32) (result i32)
+///     local.get 0
+///     local.get 1
+///     i32.add)
+///   (func (export "spin")
+///     (loop br 0))
+///   (func (export "grow") (param i32) (result i32)
+///     local.get 0
+///     memory.grow))
+/// ```
+const SELF_CHECK_MODULE: &[u8] = &[
+    // magic + version
+    0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, //
+    // type section: (i32, i32) -> i32, () -> (), (i32) -> i32
+    0x01, 0x0f, 0x03, //
+    0x60, 0x02, 0x7f, 0x7f, 0x01, 0x7f, //
+    0x60, 0x00, 0x00, //
+    0x60, 0x01, 0x7f, 0x01, 0x7f, //
+    // function section
+    0x03, 0x04, 0x03, 0x00, 0x01, 0x02, //
+    // memory section: one memory with a minimum of one page
+    0x05, 0x03, 0x01, 0x00, 0x01, //
+    // export section
+    0x07, 0x15, 0x03, //
+    0x03, b'a', b'd', b'd', 0x00, 0x00, //
+    0x04, b's', b'p', b'i', b'n', 0x00, 0x01, //
+    0x04, b'g', b'r', b'o', b'w', 0x00, 0x02, //
+    // code section
+    0x0a, 0x18, 0x03, //
+    0x07, 0x00, 0x20, 0x00, 0x20, 0x01, 0x6a, 0x0b, //
+    0x07, 0x00, 0x03, 0x40, 0x0c, 0x00, 0x0b, 0x0b, //
+    0x06, 0x00, 0x20, 0x00, 0x40, 0x00, 0x0b, //
+];
+
+/// Size of a WASM page.
+const WASM_PAGE_SIZE: usize = 65_536;
+
+/// Memory limit used for [`SelfCheckStep::MemoryLimit`].
+///
+/// This allows the initial page and one additional page.
+const SELF_CHECK_MEMORY_LIMIT: usize = 2 * WASM_PAGE_SIZE;
+
+/// Epoch tick time used for [`SelfCheckStep::EpochInterruption`].
+const SELF_CHECK_EPOCH_TICK: Duration = Duration::from_millis(10);
+
+/// Step of the [self-check](self_check).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelfCheckStep {
+    /// Compile WASM bytecode to machine code for the current host.
+    Compile,
+
+    /// Load the pre-compiled machine code into an engine without a compiler.
+    Load,
+
+    /// Instantiate the module and round-trip a simple invocation.
+    Invoke,
+
+    /// Ensure that linear memory growth is bounded by the [DataFusion memory system](datafusion_execution::memory_pool).
+    MemoryLimit,
+
+    /// Ensure that an endless loop is interrupted by an epoch tick.
+    EpochInterruption,
+}
+
+impl std::fmt::Display for SelfCheckStep {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Compile => "compile",
+            Self::Load => "load",
+            Self::Invoke => "invoke",
+            Self::MemoryLimit => "memory limit",
+            Self::EpochInterruption => "epoch interruption",
+        };
+        f.write_str(s)
+    }
+}
+
+/// Outcome of a single [`SelfCheckStep`].
+#[derive(Debug)]
+pub struct SelfCheckStepReport {
+    /// Step.
+    pub step: SelfCheckStep,
+
+    /// Time it took to run the step.
+    pub duration: Duration,
+
+    /// Result.
+    pub result: DataFusionResult<()>,
+}
+
+/// Report of the [self-check](self_check).
+///
+/// Steps are executed in order. If a step fails, later steps that depend on it are NOT executed and are hence
+/// missing from the report.
+#[derive(Debug, Default)]
+pub struct SelfCheckReport {
+    /// Executed steps.
+    pub steps: Vec<SelfCheckStepReport>,
+}
+
+impl SelfCheckReport {
+    /// Returns `true` if all steps were executed and succeeded.
+    pub fn is_ok(&self) -> bool {
+        self.steps.len() == N_STEPS && self.steps.iter().all(|step| step.result.is_ok())
+    }
+
+    /// Run step and record its outcome.
+    ///
+    /// Returns [`None`] if the step failed.
+    async fn run<T, Fut>(&mut self, step: SelfCheckStep, fut: Fut) -> Option<T>
+    where
+        Fut: Future<Output = DataFusionResult<T>>,
+    {
+        let start = Instant::now();
+        let res = fut.await;
+        let duration = start.elapsed();
+
+        let (result, out) = match res {
+            Ok(out) => (Ok(()), Some(out)),
+            Err(e) => {
+                log::warn!("self-check step failed: {step}: {e}");
+                (Err(e), None)
+            }
+        };
+        self.steps.push(SelfCheckStepReport {
+            step,
+            duration,
+            result,
+        });
+        out
+    }
+}
+
+impl std::fmt::Display for SelfCheckReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for SelfCheckStepReport {
+            step,
+            duration,
+            result,
+        } in &self.steps
+        {
+            match result {
+                Ok(()) => writeln!(f, "{step}: ok ({duration:?})")?,
+                Err(e) => writeln!(f, "{step}: FAILED ({duration:?}): {e}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Number of [steps](SelfCheckStep).
+const N_STEPS: usize = 5;
+
+/// Validate that the current platform and runtime layout support WASM UDFs.
+///
+/// This compiles a tiny built-in WASM module, loads it the same way [`WasmComponentPrecompiled`] does, runs a
+/// round-trip invocation, and verifies that memory limiting and epoch interruption work. Use this at startup to
+/// catch misconfiguration (e.g. unsupported CPU features or missing signal handling) before production traffic.
+///
+/// The check is independent of any guest and does NOT need network or filesystem access.
+///
+///
+/// [`WasmComponentPrecompiled`]: crate::WasmComponentPrecompiled
+pub async fn self_check() -> SelfCheckReport {
+    let mut report = SelfCheckReport::default();
+
+    let Some(compiled) = report
+        .run(SelfCheckStep::Compile, async {
+            let engine = create_engine(&CompilationFlags::default())?;
+            tokio::task::spawn_blocking(move || {
+                engine
+                    .precompile_module(SELF_CHECK_MODULE)
+                    .context("pre-compile module", None)
+            })
+            .await
+            .map_err(|e| DataFusionError::External(Box::new(e)))?
+        })
+        .await
+    else {
+        return report;
+    };
+
+    let Some((engine, module)) = report
+        .run(SelfCheckStep::Load, async {
+            let engine = create_engine(&NoCompilation)?;
+
+            // SAFETY: We just produced this data ourselves within the same process.
+            let module =
+                unsafe { Module::deserialize(&engine, &compiled) }.context("load module", None)?;
+
+            Ok((engine, module))
+        })
+        .await
+    else {
+        return report;
+    };
+
+    let Some((mut store, instance)) = report
+        .run(SelfCheckStep::Invoke, async {
+            let pool: Arc<dyn MemoryPool> =
+                Arc::new(GreedyMemoryPool::new(SELF_CHECK_MEMORY_LIMIT));
+            let mut store = Store::new(
+                &engine,
+                Limiter::new(
+                    StaticResourceLimits::default(),
+                    DynamicMemoryLimits::default(),
+                    &pool,
+                ),
+            );
+            store.limiter(|limiter| limiter);
+            store.epoch_deadline_trap();
+            // The epoch only advances once the interruption step starts the ticker.
+            store.set_epoch_deadline(1);
+
+            let instance = Instance::new_async(&mut store, &module, &[])
+                .await
+                .context("instantiate module", None)?;
+            let add = instance
+                .get_typed_func::<(i32, i32), i32>(&mut store, "add")
+                .context("get `add` function", None)?;
+            let res = add
+                .call_async(&mut store, (1, 2))
+                .await
+                .context("call `add` function", None)?;
+            if res != 3 {
+                return Err(DataFusionError::External(
+                    format!("`add(1, 2)` returned {res}").into(),
+                ));
+            }
+
+            Ok((store, instance))
+        })
+        .await
+    else {
+        return report;
+    };
+
+    report
+        .run(SelfCheckStep::MemoryLimit, async {
+            let grow = instance
+                .get_typed_func::<i32, i32>(&mut store, "grow")
+                .context("get `grow` function", None)?;
+
+            let res = grow
+                .call_async(&mut store, 1)
+                .await
+                .context("call `grow` function", None)?;
+            if res != 1 {
+                return Err(DataFusionError::External(
+                    format!("growing memory within the limit failed: {res}").into(),
+                ));
+            }
+
+            let res = grow
+                .call_async(&mut store, 1)
+                .await
+                .context("call `grow` function", None)?;
+            if res != -1 {
+                return Err(DataFusionError::External(
+                    "growing memory beyond the limit was NOT rejected".into(),
+                ));
+            }
+
+            Ok(())
+        })
+        .await;
+
+    report
+        .run(SelfCheckStep::EpochInterruption, async {
+            let spin = instance
+                .get_typed_func::<(), ()>(&mut store, "spin")
+                .context("get `spin` function", None)?;
+
+            // Use an OS thread instead of a tokio task because the endless loop blocks the current runtime thread.
+            start_epoch_ticker(&engine);
+
+            match spin.call_async(&mut store, ()).await {
+                Ok(()) => Err(DataFusionError::External("endless loop returned".into())),
+                Err(e) if matches!(e.downcast_ref::<Trap>(), Some(Trap::Interrupt)) => Ok(()),
+                Err(e) => Err(e).context("call `spin` function", None),
+            }
+        })
+        .await;
+
+    report
+}
+
+/// Start OS thread that increments the epoch of the given engine until the engine is dropped.
+fn start_epoch_ticker(engine: &Engine) {
+    let engine_weak = engine.weak();
+    std::thread::spawn(move || {
+        loop {
+            std::thread::sleep(SELF_CHECK_EPOCH_TICK);
+
+            match engine_weak.upgrade() {
+                Some(engine) => {
+                    engine.increment_epoch();
+                }
+                None => {
+                    return;
+                }
+            }
+        }
+    });
+}
@@ -0,0 +1,18 @@
+//! Sharing of guest instances between UDFs.
+
+/// How UDFs that are [created](crate::WasmScalarUdf::new) from the same source share guest instances.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InstanceSharing {
+    /// All UDFs share a single instance.
+    ///
+    /// UDFs can share state, e.g. global variables. A UDF that [poisons](crate::WasmUdfError::Poisoned) the guest or
+    /// keeps it busy affects all its siblings.
+    #[default]
+    Shared,
+
+    /// Every UDF gets its own instance.
+    ///
+    /// This isolates failures and slow calls, but every instance needs its own memory. UDFs can NOT share state, e.g.
+    /// the one-time setup (`init`) of a UDF can not rely on its siblings.
+    PerUdf,
+}
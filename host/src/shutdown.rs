@@ -0,0 +1,137 @@
+//! Graceful shutdown of guests.
+
+use std::{
+    sync::{
+        Arc, Mutex, Weak,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use datafusion_common::{DataFusionError, Result as DataFusionResult};
+use tokio::time::Instant;
+
+use crate::{
+    component::WasmComponentInstance, error::RuntimeShutdown, recycle::RecyclableInstance,
+};
+
+/// Handle to shut down all guests that were created with it.
+///
+/// Pass a handle to [`WasmPermissions::with_runtime_handle`](crate::WasmPermissions::with_runtime_handle). All guests
+/// that are created with these permissions -- including guests that are later re-instantiated due to
+/// [recycling](crate::WasmPermissions::with_max_recycles) or [suspension](crate::WasmScalarUdf::suspend) -- can then be
+/// torn down at once using [`shutdown`](Self::shutdown), e.g. when a server stops.
+///
+/// Cloning the handle is cheap, all clones refer to the same set of guests.
+#[derive(Debug, Clone, Default)]
+pub struct WasmRuntimeHandle {
+    /// Shared state.
+    inner: Arc<Inner>,
+}
+
+/// Shared state of [`WasmRuntimeHandle`].
+#[derive(Debug, Default)]
+struct Inner {
+    /// Set once [`shutdown`](WasmRuntimeHandle::shutdown) was called.
+    shut_down: AtomicBool,
+
+    /// Guests that were created with this handle.
+    ///
+    /// Dropped guests are pruned on registration.
+    instances: Mutex<Vec<Weak<RecyclableInstance>>>,
+
+    /// Guest instances that were interrupted but may still be referenced by running calls.
+    interrupted: Mutex<Vec<Weak<WasmComponentInstance>>>,
+}
+
+impl WasmRuntimeHandle {
+    /// Create new handle.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns `true` if [`shutdown`](Self::shutdown) was called.
+    pub fn is_shut_down(&self) -> bool {
+        self.inner.shut_down.load(Ordering::SeqCst)
+    }
+
+    /// Shut down all guests.
+    ///
+    /// This:
+    ///
+    /// 1. refuses to create new guests,
+    /// 2. interrupts running calls -- even if the guest spins and never yields -- which then fail with
+    ///    [`WasmUdfError::Shutdown`](crate::WasmUdfError::Shutdown),
+    /// 3. drops all guests, which also stops their epoch timers.
+    ///
+    /// Calls that are currently in host code -- e.g. waiting for an HTTP response -- hold on to their guest until they
+    /// return to it. This method waits for them up to the given `deadline` and fails if some guests are still alive
+    /// afterwards. Calling it again waits for the remaining guests.
+    ///
+    /// UDFs that were created with this handle stay valid, but all further calls fail. Their `close` hooks are NOT
+    /// called.
+    pub async fn shutdown(&self, deadline: Duration) -> DataFusionResult<()> {
+        let deadline = Instant::now() + deadline;
+
+        let instances = {
+            let instances = self.inner.instances.lock().expect("not poisoned");
+            self.inner.shut_down.store(true, Ordering::SeqCst);
+            instances.clone()
+        };
+
+        // interrupt all guests first, so that they shut down concurrently
+        let mut unreachable = 0;
+        for instance in instances.iter().filter_map(Weak::upgrade) {
+            match tokio::time::timeout_at(deadline, instance.shutdown()).await {
+                Ok(Some(instance)) => {
+                    self.inner
+                        .interrupted
+                        .lock()
+                        .expect("not poisoned")
+                        .push(Arc::downgrade(&instance));
+                }
+                Ok(None) => {}
+                Err(_) => {
+                    unreachable += 1;
+                }
+            }
+        }
+
+        loop {
+            let alive = {
+                let mut interrupted = self.inner.interrupted.lock().expect("not poisoned");
+                interrupted.retain(|instance| instance.strong_count() > 0);
+                interrupted.len()
+            } + unreachable;
+            if alive == 0 {
+                return Ok(());
+            }
+            if Instant::now() >= deadline {
+                return Err(DataFusionError::Execution(format!(
+                    "{alive} guest(s) did not shut down within deadline"
+                )));
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+
+    /// Fail if the runtime was [shut down](Self::shutdown).
+    pub(crate) fn check(&self) -> Result<(), RuntimeShutdown> {
+        if self.is_shut_down() {
+            Err(RuntimeShutdown)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Track guest, so that it is torn down on [shutdown](Self::shutdown).
+    ///
+    /// Fails if the runtime was already shut down.
+    pub(crate) fn register(&self, instance: &Arc<RecyclableInstance>) -> DataFusionResult<()> {
+        let mut instances = self.inner.instances.lock().expect("not poisoned");
+        self.check()?;
+        instances.retain(|instance| instance.strong_count() > 0);
+        instances.push(Arc::downgrade(instance));
+        Ok(())
+    }
+}
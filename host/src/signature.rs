@@ -0,0 +1,162 @@
+//! Signature verification of WASM payloads.
+
+use ring::signature::{ED25519, UnparsedPublicKey};
+
+/// Verifies detached signatures of WASM payloads before they are
+/// [pre-compiled](crate::WasmComponentPrecompiled::compile_verified).
+///
+/// This allows operators to ensure that only blessed payloads -- e.g. the language runtimes that they ship -- are
+/// deployed. A payload is accepted if its signature was made by any of the trusted keys. A verifier without keys
+/// rejects all payloads.
+///
+/// # Signature Format
+/// Signatures are raw [Ed25519] signatures (64 bytes) over the entire WASM payload in [binary format].
+///
+///
+/// [binary format]: https://webassembly.github.io/spec/core/binary/index.html
+/// [Ed25519]: https://www.rfc-editor.org/rfc/rfc8032
+#[derive(Debug, Clone, Default)]
+pub struct ComponentVerifier {
+    /// Trusted Ed25519 public keys.
+    ed25519_keys: Vec<[u8; 32]>,
+}
+
+impl ComponentVerifier {
+    /// Create verifier without any trusted keys.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Trust given Ed25519 public key.
+    pub fn with_ed25519_key(mut self, key: [u8; 32]) -> Self {
+        self.ed25519_keys.push(key);
+        self
+    }
+
+    /// Verify payload against the given detached signature.
+    pub fn verify(
+        &self,
+        wasm_binary: &[u8],
+        signature: Option<&[u8]>,
+    ) -> Result<(), ComponentVerificationError> {
+        let signature = signature.ok_or(ComponentVerificationError::Unsigned)?;
+
+        let trusted = self.ed25519_keys.iter().any(|key| {
+            UnparsedPublicKey::new(&ED25519, key)
+                .verify(wasm_binary, signature)
+                .is_ok()
+        });
+        if trusted {
+            Ok(())
+        } else {
+            Err(ComponentVerificationError::InvalidSignature)
+        }
+    }
+}
+
+/// Error returned by [`ComponentVerifier::verify`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ComponentVerificationError {
+    /// The payload has no signature.
+    Unsigned,
+
+    /// The signature does not match the payload or was not made by a trusted key.
+    InvalidSignature,
+}
+
+impl std::fmt::Display for ComponentVerificationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unsigned => write!(f, "WASM payload is not signed"),
+            Self::InvalidSignature => write!(
+                f,
+                "WASM payload signature is invalid or not made by a trusted key"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ComponentVerificationError {}
+
+#[cfg(test)]
+mod tests {
+    use ring::signature::{Ed25519KeyPair, KeyPair};
+
+    use super::*;
+
+    #[test]
+    fn test_valid_signature() {
+        let (key, signature) = sign(1, b"payload");
+        let verifier = ComponentVerifier::new().with_ed25519_key(key);
+        assert_eq!(verifier.verify(b"payload", Some(&signature)), Ok(()));
+    }
+
+    #[test]
+    fn test_any_trusted_key() {
+        let (key1, _) = sign(1, b"payload");
+        let (key2, signature) = sign(2, b"payload");
+        let verifier = ComponentVerifier::new()
+            .with_ed25519_key(key1)
+            .with_ed25519_key(key2);
+        assert_eq!(verifier.verify(b"payload", Some(&signature)), Ok(()));
+    }
+
+    #[test]
+    fn test_unsigned() {
+        let (key, _) = sign(1, b"payload");
+        let verifier = ComponentVerifier::new().with_ed25519_key(key);
+        assert_eq!(
+            verifier.verify(b"payload", None),
+            Err(ComponentVerificationError::Unsigned),
+        );
+    }
+
+    #[test]
+    fn test_tampered_payload() {
+        let (key, signature) = sign(1, b"payload");
+        let verifier = ComponentVerifier::new().with_ed25519_key(key);
+        assert_eq!(
+            verifier.verify(b"pAyload", Some(&signature)),
+            Err(ComponentVerificationError::InvalidSignature),
+        );
+    }
+
+    #[test]
+    fn test_untrusted_key() {
+        let (key, _) = sign(1, b"payload");
+        let (_, signature) = sign(2, b"payload");
+        let verifier = ComponentVerifier::new().with_ed25519_key(key);
+        assert_eq!(
+            verifier.verify(b"payload", Some(&signature)),
+            Err(ComponentVerificationError::InvalidSignature),
+        );
+    }
+
+    #[test]
+    fn test_no_keys() {
+        let (_, signature) = sign(1, b"payload");
+        assert_eq!(
+            ComponentVerifier::new().verify(b"payload", Some(&signature)),
+            Err(ComponentVerificationError::InvalidSignature),
+        );
+    }
+
+    #[test]
+    fn test_malformed_signature() {
+        let (key, _) = sign(1, b"payload");
+        let verifier = ComponentVerifier::new().with_ed25519_key(key);
+        assert_eq!(
+            verifier.verify(b"payload", Some(b"foo")),
+            Err(ComponentVerificationError::InvalidSignature),
+        );
+    }
+
+    /// Sign payload with a key derived from the given seed, returns public key and signature.
+    fn sign(seed: u8, payload: &[u8]) -> ([u8; 32], Vec<u8>) {
+        let pair = Ed25519KeyPair::from_seed_unchecked(&[seed; 32]).unwrap();
+        let key = pair.public_key().as_ref().try_into().unwrap();
+        let signature = pair.sign(payload).as_ref().to_vec();
+        (key, signature)
+    }
+}
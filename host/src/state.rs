@@ -1,9 +1,15 @@
 //! State handling of guests.
 
-use wasmtime_wasi::{ResourceTable, WasiCtx, WasiCtxView, WasiView, p2::pipe::MemoryOutputPipe};
+use std::time::Instant;
+
+use wasmtime_wasi::{ResourceTable, WasiCtx, WasiCtxView, WasiView};
 use wasmtime_wasi_http::WasiHttpCtx;
 
-use crate::{http::WasiHttpHooksImpl, ignore_debug::IgnoreDebug, limiter::Limiter, vfs::VfsState};
+use crate::{
+    http::WasiHttpHooksImpl, ignore_debug::IgnoreDebug, kv::KvState, limiter::Limiter, nn::NnCtx,
+    random::RandomState, secrets::SecretsState, stderr::Stderr, vfs::VfsState,
+    volatility::ImmutableFlag,
+};
 
 /// State of the WASM payload.
 #[derive(Debug)]
@@ -19,7 +25,7 @@ pub(crate) struct WasmStateImpl {
     /// A limited buffer for stderr.
     ///
     /// This is especially useful for when the payload crashes.
-    pub(crate) stderr: MemoryOutputPipe,
+    pub(crate) stderr: Stderr,
 
     /// WASI context.
     pub(crate) wasi_ctx: IgnoreDebug<WasiCtx>,
@@ -30,8 +36,33 @@ pub(crate) struct WasmStateImpl {
     /// HTTP hooks.
     pub(crate) wasi_http_hooks: WasiHttpHooksImpl,
 
+    /// Key-value store.
+    pub(crate) kv: KvState,
+
+    /// Secrets.
+    pub(crate) secrets: SecretsState,
+
+    /// `wasi-nn` state.
+    #[cfg_attr(not(feature = "nn"), expect(dead_code))]
+    pub(crate) nn_ctx: IgnoreDebug<NnCtx>,
+
     /// Resource tables.
     pub(crate) resource_table: ResourceTable,
+
+    /// Source of randomness.
+    pub(crate) random: RandomState,
+
+    /// Set while the guest executes an immutable UDF.
+    pub(crate) immutable: ImmutableFlag,
+
+    /// Deadline of the current invocation, see [`WasmUdfConfig::invocation_timeout_ms`].
+    ///
+    ///
+    /// [`WasmUdfConfig::invocation_timeout_ms`]: crate::WasmUdfConfig::invocation_timeout_ms
+    pub(crate) invocation_deadline: Option<Instant>,
+
+    /// Number of epoch ticks that the guest was running for.
+    pub(crate) epoch_ticks: u64,
 }
 
 impl WasiView for WasmStateImpl {
@@ -0,0 +1,115 @@
+//! Stderr output of guests.
+
+use std::sync::Arc;
+
+use wasmtime_wasi::p2::pipe::MemoryOutputPipe;
+
+use crate::{conversion::sanitize::sanitize, secrets::RevealedSecrets};
+
+/// Redacts sensitive data from stderr output before it is attached to errors.
+///
+/// See [`WasmPermissions::with_stderr_redactor`](crate::WasmPermissions::with_stderr_redactor).
+pub trait StderrRedactor: std::fmt::Debug + Send + Sync + 'static {
+    /// Redact stderr output.
+    ///
+    /// The output is already [sanitized](crate::TrustedDataLimits::sanitize_strings) if requested.
+    fn redact(&self, stderr: String) -> String;
+}
+
+/// Defines if and how stderr output of the guest is attached to errors.
+///
+/// The output is attached to every error that results from a call into the guest, regardless of whether the guest
+/// trapped or returned an error. Note that the output accumulates over the lifetime of the guest, i.e. it may contain
+/// output of earlier calls.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum StderrPolicy {
+    /// Attach all output that was captured, see
+    /// [`WasmPermissions::with_stderr_bytes`](crate::WasmPermissions::with_stderr_bytes).
+    #[default]
+    Always,
+
+    /// Attach only the last bytes of the captured output.
+    ///
+    /// The most recent output -- e.g. a panic message -- is usually the most helpful one.
+    Truncated {
+        /// Maximum number of bytes.
+        bytes: usize,
+    },
+
+    /// Never attach output.
+    Never,
+}
+
+/// Limited buffer for stderr output of the WASM payload.
+#[derive(Debug)]
+pub(crate) struct Stderr {
+    /// Underlying buffer that is passed to the guest.
+    pipe: MemoryOutputPipe,
+
+    /// Sanitize output, see [`TrustedDataLimits::sanitize_strings`](crate::TrustedDataLimits::sanitize_strings).
+    sanitize: bool,
+
+    /// Attachment policy.
+    policy: StderrPolicy,
+
+    /// Redaction hook.
+    redactor: Option<Arc<dyn StderrRedactor>>,
+
+    /// Secrets that were handed to the guest and that are always redacted.
+    secrets: RevealedSecrets,
+}
+
+impl Stderr {
+    /// Create new buffer.
+    pub(crate) fn new(
+        pipe: MemoryOutputPipe,
+        sanitize: bool,
+        policy: StderrPolicy,
+        redactor: Option<Arc<dyn StderrRedactor>>,
+        secrets: RevealedSecrets,
+    ) -> Self {
+        Self {
+            pipe,
+            sanitize,
+            policy,
+            redactor,
+            secrets,
+        }
+    }
+
+    /// Secrets that were handed to the guest.
+    pub(crate) fn secrets(&self) -> &RevealedSecrets {
+        &self.secrets
+    }
+
+    /// Output that should be attached to an error.
+    ///
+    /// Returns [`None`] if there is no output or if the [policy](StderrPolicy) does not allow attaching it.
+    pub(crate) fn attachment(&self) -> Option<String> {
+        if self.policy == StderrPolicy::Never {
+            return None;
+        }
+
+        let contents = self.pipe.contents();
+        let mut s = String::from_utf8_lossy(&contents).into_owned();
+        if self.sanitize {
+            s = sanitize(&s).into_owned();
+        }
+        if let Some(redactor) = &self.redactor {
+            s = redactor.redact(s);
+        }
+        s = self.secrets.redact(s);
+
+        if let StderrPolicy::Truncated { bytes } = self.policy
+            && s.len() > bytes
+        {
+            let mut start = s.len() - bytes;
+            while !s.is_char_boundary(start) {
+                start += 1;
+            }
+            s = format!("[stderr truncated: bytes={start}]\n{}", &s[start..]);
+        }
+
+        (!s.is_empty()).then_some(s)
+    }
+}
@@ -5,6 +5,8 @@ use std::time::Duration;
 use datafusion_common::DataFusionError;
 use tokio::runtime::RuntimeFlavor;
 
+use crate::error::WasmUdfError;
+
 /// Run an async method in a sync context.
 ///
 /// **This is a hack that is required because the respective DataFusion interfaces aren't fully async.**
@@ -26,9 +28,11 @@ where
     }
 
     let fut = async move {
-        tokio::time::timeout(timeout, fut)
-            .await
-            .map_err(|e| DataFusionError::External(Box::new(e)))
+        tokio::time::timeout(timeout, fut).await.map_err(|e| {
+            DataFusionError::External(Box::new(WasmUdfError::Timeout {
+                source: Box::new(e),
+            }))
+        })
     };
 
     tokio::task::block_in_place(move || handle.block_on(fut)).flatten()
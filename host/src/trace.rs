@@ -0,0 +1,101 @@
+//! Optional [tracing](https://docs.rs/tracing) instrumentation, see the `tracing` feature.
+//!
+//! Without the feature, spans are zero-sized and all methods are no-ops.
+
+/// Span of a host operation, see [`span!`].
+#[derive(Debug)]
+pub(crate) struct Span {
+    /// Actual span.
+    #[cfg(feature = "tracing")]
+    pub(crate) inner: tracing::Span,
+}
+
+impl Span {
+    /// Disabled span.
+    #[cfg(not(feature = "tracing"))]
+    pub(crate) fn disabled() -> Self {
+        Self {}
+    }
+
+    /// Record value of a field that was declared via `record` in [`span!`].
+    #[cfg_attr(not(feature = "tracing"), expect(clippy::unused_self))]
+    pub(crate) fn record(&self, field: &'static str, value: u64) {
+        #[cfg(feature = "tracing")]
+        self.inner.record(field, value);
+
+        #[cfg(not(feature = "tracing"))]
+        let _ = (field, value);
+    }
+
+    /// Run closure within the span.
+    #[cfg(all(feature = "compiler", feature = "tracing"))]
+    pub(crate) fn in_scope<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce() -> R,
+    {
+        self.inner.in_scope(f)
+    }
+
+    /// Run closure within the span.
+    #[cfg(all(feature = "compiler", not(feature = "tracing")))]
+    #[expect(clippy::unused_self)]
+    pub(crate) fn in_scope<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce() -> R,
+    {
+        f()
+    }
+
+    /// Run future within the span.
+    #[cfg(feature = "tracing")]
+    pub(crate) async fn instrument<F>(&self, fut: F) -> F::Output
+    where
+        F: Future,
+    {
+        tracing::Instrument::instrument(fut, self.inner.clone()).await
+    }
+
+    /// Run future within the span.
+    #[cfg(not(feature = "tracing"))]
+    pub(crate) async fn instrument<F>(&self, fut: F) -> F::Output
+    where
+        F: Future,
+    {
+        fut.await
+    }
+}
+
+/// Create [`Span`] at INFO level.
+///
+/// Fields are passed as `name = value`. Fields that are only known later are listed after `record:` and set via
+/// [`Span::record`].
+///
+/// ```ignore
+/// let span = span!("invoke", udf = name, rows = n; record: duration_us);
+/// ```
+macro_rules! span {
+    ($name:literal $(, $field:ident = $value:expr)* $(; record: $($recorded:ident),+)? $(,)?) => {{
+        #[cfg(feature = "tracing")]
+        let span = $crate::trace::Span {
+            inner: tracing::info_span!(
+                target: "datafusion_udf_wasm_host",
+                $name
+                $(, $field = $value)*
+                $($(, $recorded = tracing::field::Empty)+)?
+            ),
+        };
+
+        #[cfg(not(feature = "tracing"))]
+        let span = {
+            // don't evaluate fields, but keep their inputs "used"
+            $(let _ = || {
+                let _ = &$value;
+            };)*
+            $crate::trace::Span::disabled()
+        };
+
+        span
+    }};
+}
+
+pub(crate) use span;
@@ -1,36 +1,55 @@
 //! DataFusion UDF types.
 
-use std::{any::Any, collections::HashSet, hash::Hash, sync::Arc};
+use std::{
+    any::Any,
+    collections::HashMap,
+    hash::Hash,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
-use arrow::datatypes::DataType;
-use datafusion_common::{DataFusionError, Result as DataFusionResult};
+use arrow::{
+    array::{Array, AsArray, BooleanArray, UInt64Array, new_null_array},
+    buffer::NullBuffer,
+    compute::{SortOptions, concat, filter, take},
+    datatypes::{DataType, Field},
+};
+use datafusion_common::{DataFusionError, Result as DataFusionResult, ScalarValue};
 use datafusion_execution::memory_pool::MemoryPool;
 use datafusion_expr::{
-    ColumnarValue, ScalarFunctionArgs, ScalarUDFImpl, Signature, TypeSignature,
+    ColumnarValue, Expr, ReturnFieldArgs, ScalarFunctionArgs, ScalarUDFImpl, Signature,
+    TypeSignature, Volatility,
     async_udf::{AsyncScalarUDF, AsyncScalarUDFImpl},
+    simplify::{ExprSimplifyResult, SimplifyInfo},
+    sort_properties::{ExprProperties, SortProperties},
 };
 use tokio::runtime::Handle;
 use uuid::Uuid;
-use wasmtime::component::ResourceAny;
+use wasmtime::{AsContextMut, component::ResourceAny};
 use wasmtime_wasi::async_trait;
 
 use crate::{
-    WasmComponentPrecompiled, WasmPermissions,
+    ContextLabels, InstanceSharing, WasmComponentPrecompiled, WasmPermissions, WasmResourceUsage,
+    WasmUdfConfig, WasmUdfCostEstimate, WitVersion,
     bindings::exports::datafusion_udf_wasm::udf::types as wit_types,
     component::WasmComponentInstance,
     conversion::{
+        ReturnTypeTable,
         async_from::AsyncTryInto,
-        limits::{CheckedInto, ComplexityToken},
+        limits::{CheckedFrom, CheckedInto, ComplexityToken},
     },
-    error::{DataFusionResultExt, WasmToDataFusionResultExt, WitDataFusionResultExt},
+    error::{DataFusionResultExt, WasmToDataFusionResultExt, WasmUdfError, WitDataFusionResultExt},
+    recycle::{RecyclableInstance, call_init, call_load_source},
     tokio_helpers::async_in_sync_context,
+    trace::span,
 };
 
 /// A [`ScalarUDFImpl`] that wraps a WebAssembly payload.
 ///
 /// # Async, Blocking, Cancellation
 /// Async methods will yield back to the runtime in periodical intervals. The caller should implement some form of
-/// timeout, e.g. using [`tokio::time::timeout`]. It is safe to cancel async methods.
+/// timeout, e.g. using [`tokio::time::timeout`] or [`WasmPermissions::with_invoke_timeout`]. It is safe to cancel
+/// async methods.
 ///
 /// For the async interruption to work it is important that the I/O [runtime] passed to [`WasmScalarUdf::new`] is
 /// different from the runtime used to call UDF methods, since the I/O runtime is also used to schedule an
@@ -45,17 +64,43 @@ use crate::{
 /// [timeout](WasmPermissions::with_inplace_blocking_max_ticks). See
 /// <https://github.com/influxdata/datafusion-udf-wasm/issues/169> for a potential future improvement on that front.
 ///
+/// # Constant Folding
+/// [Immutable](Volatility::Immutable) UDFs fold calls with constant arguments -- e.g. `my_udf('constant')` -- into a
+/// literal while planning, see [`ScalarUDFImpl::simplify`]. This blocks in place, with the same restrictions as above.
+/// If the call cannot be evaluated at planning time, it is left as is and evaluated during execution. UDFs with other
+/// volatilities could observe that they are called at planning time, so they are never folded. The synchronous
+/// [`ScalarUDFImpl::invoke_with_args`] is NOT supported.
+///
 ///
 /// [runtime]: tokio::runtime::Runtime
 #[derive(Debug)]
 pub struct WasmScalarUdf {
     /// WASM component instance.
-    instance: Arc<WasmComponentInstance>,
+    ///
+    /// This also holds the resource handle -- somewhat an "object reference" -- for the Scalar UDF within the VM,
+    /// since it changes when the instance is recycled.
+    instance: Arc<RecyclableInstance>,
+
+    /// Additional instances that batches are split across, see [`WasmPermissions::with_intra_batch_parallelism`].
+    ///
+    /// Every worker only sets up this UDF.
+    workers: Vec<Arc<RecyclableInstance>>,
 
-    /// Resource handle for the Scalar UDF within the VM.
+    /// Source code that was used to create the UDF.
     ///
-    /// This is somewhat an "object reference".
-    resource: ResourceAny,
+    /// This is shared between all UDFs that were created from the same VM.
+    source: Arc<str>,
+
+    /// [Digest](WasmComponentPrecompiled::digest) of the component that the UDF was created from.
+    component_digest: u128,
+
+    /// [Fingerprint](WasmPermissions::fingerprint) of the permissions that the UDF was created with.
+    permissions_fingerprint: u64,
+
+    /// WIT version that was negotiated with the guest.
+    ///
+    /// All instances of the same component use the same version.
+    wit_version: WitVersion,
 
     /// Name of the UDF.
     ///
@@ -73,20 +118,43 @@ pub struct WasmScalarUdf {
     /// reference.
     signature: Signature,
 
-    /// Return type of the UDF.
+    /// Return types of the UDF for the argument types that the guest listed.
     ///
-    /// This was pre-fetched during UDF generation because
-    /// [`ScalarUDFImpl::return_type`] is sync and requires us to return a
-    /// reference. We can only compute the return type if the underlying
-    /// [TypeSignature] is [Exact](TypeSignature::Exact).
-    return_type: Option<DataType>,
+    /// These were pre-fetched during UDF generation because
+    /// [`ScalarUDFImpl::return_type`] is sync and calling the guest requires us to block in place. The guest lists
+    /// the argument types of [exact](TypeSignature::Exact) signatures and -- if there are not too many combinations
+    /// -- [uniform](TypeSignature::Uniform) signatures.
+    return_types: ReturnTypeTable,
+
+    /// Cost estimate, learned from invocations.
+    cost: WasmUdfCostEstimate,
+
+    /// Request and verify result checksums, see [`WasmPermissions::with_result_checksums`].
+    result_checksums: bool,
+
+    /// Wall-clock timeout for every invocation, see [`WasmPermissions::with_invoke_timeout`].
+    invoke_timeout: Option<Duration>,
+
+    /// The UDF returns NULL if any argument is NULL.
+    ///
+    /// This was pre-fetched during UDF generation. NULL rows are filtered out before the guest is invoked.
+    null_strict: bool,
+
+    /// Hints for the query planner.
+    ///
+    /// This was pre-fetched during UDF generation.
+    planner_hints: wit_types::PlannerHints,
+
+    /// Labels from creation, see [`WasmPermissions::with_context_labels`].
+    context_labels: ContextLabels,
 }
 
 impl WasmScalarUdf {
     /// Create multiple UDFs from a single WASM VM.
     ///
     /// UDFs bound to the same VM share state, however calling this method
-    /// multiple times will yield independent WASM VMs.
+    /// multiple times will yield independent WASM VMs. Use
+    /// [`WasmPermissions::with_instance_sharing`] to give every UDF its own VM.
     pub async fn new(
         component: &WasmComponentPrecompiled,
         permissions: &WasmPermissions,
@@ -95,48 +163,55 @@ impl WasmScalarUdf {
         source: String,
     ) -> DataFusionResult<Vec<Self>> {
         let instance =
-            Arc::new(WasmComponentInstance::new(component, permissions, io_rt, memory_pool).await?);
+            WasmComponentInstance::new(component, permissions, io_rt.clone(), memory_pool).await?;
+        Self::from_instance(instance, component, permissions, io_rt, memory_pool, source).await
+    }
 
-        let udf_resources = {
-            let mut state = instance.lock_state().await;
-            instance
-                .bindings()
-                .datafusion_udf_wasm_udf_types()
-                .call_scalar_udfs(&mut state, &source)
-                .await
-                .context(
-                    "calling scalar_udfs() method failed",
-                    Some(&state.stderr.contents()),
-                )?
-                .convert_err(permissions.trusted_data_limits.clone())
-                .context("scalar_udfs")?
-        };
-        if udf_resources.len() > permissions.max_udfs {
-            return Err(DataFusionError::ResourcesExhausted(format!(
-                "guest returned too many UDFs: got={}, limit={}",
-                udf_resources.len(),
-                permissions.max_udfs,
-            )));
-        }
+    /// Create multiple UDFs from a fresh WASM VM.
+    ///
+    /// The instance MUST have been created from the same component and permissions and MUST NOT have been used
+    /// before, see [`WarmPool`](crate::WarmPool).
+    pub(crate) async fn from_instance(
+        instance: WasmComponentInstance,
+        component: &WasmComponentPrecompiled,
+        permissions: &WasmPermissions,
+        io_rt: Handle,
+        memory_pool: &Arc<dyn MemoryPool>,
+        source: String,
+    ) -> DataFusionResult<Vec<Self>> {
+        let instance = Arc::new(instance);
+        let component_digest = component.digest();
+        let permissions_fingerprint = permissions.fingerprint();
+        let wit_version = instance.wit_version();
 
+        let udf_resources = call_load_source(&instance, &source, permissions).await?;
+
+        let source: Arc<str> = source.into();
         let mut udfs = Vec::with_capacity(udf_resources.len());
-        let mut names_seen = HashSet::with_capacity(udf_resources.len());
+        let mut names_seen = HashMap::with_capacity(udf_resources.len());
         for resource in udf_resources {
-            let mut state = instance.lock_state().await;
+            let mut state = instance.lock_state().await?;
             let name = instance
                 .bindings()
                 .datafusion_udf_wasm_udf_types()
                 .scalar_udf()
                 .call_name(&mut state, resource)
                 .await
-                .context("call ScalarUdf::name", Some(&state.stderr.contents()))?;
+                .context("call ScalarUdf::name", Some(&state.stderr))?;
             ComplexityToken::new(permissions.trusted_data_limits.clone())?
                 .check_identifier(&name)
                 .context("UDF name")?;
-            if !names_seen.insert(name.clone()) {
-                return Err(DataFusionError::External(
-                    format!("non-unique UDF name: '{name}'").into(),
-                ));
+            let canonical = permissions
+                .udf_name_collisions
+                .canonicalize(&name)
+                .into_owned();
+            if let Some(other) = names_seen.insert(canonical, name.clone()) {
+                let msg = if other == name {
+                    format!("non-unique UDF name: '{name}'")
+                } else {
+                    format!("UDF name '{name}' collides with '{other}'")
+                };
+                return Err(DataFusionError::External(msg.into()));
             }
 
             let signature: Signature = instance
@@ -145,79 +220,914 @@ impl WasmScalarUdf {
                 .scalar_udf()
                 .call_signature(&mut state, resource)
                 .await
-                .context("call ScalarUdf::signature", Some(&state.stderr.contents()))?
+                .context("call ScalarUdf::signature", Some(&state.stderr))?
                 .checked_into_root(&permissions.trusted_data_limits)
                 .context("signature")?;
 
-            let return_type = match &signature.type_signature {
-                TypeSignature::Exact(t) => {
-                    let r = instance
-                        .bindings()
-                        .datafusion_udf_wasm_udf_types()
-                        .scalar_udf()
-                        .call_return_type(
-                            &mut state,
-                            resource,
-                            &t.iter()
-                                .map(|dt| wit_types::DataType::from(dt.clone()))
-                                .collect::<Vec<_>>(),
-                        )
-                        .await
-                        .context(
-                            "call ScalarUdf::return_type",
-                            Some(&state.stderr.contents()),
-                        )?
-                        .convert_err(permissions.trusted_data_limits.clone())?;
-                    Some(r.checked_into_root(&permissions.trusted_data_limits)?)
-                }
-                _ => None,
-            };
+            let return_types: ReturnTypeTable = instance
+                .bindings()
+                .datafusion_udf_wasm_udf_types()
+                .scalar_udf()
+                .call_return_type_table(&mut state, resource)
+                .await
+                .context("call ScalarUdf::return_type_table", Some(&state.stderr))?
+                .convert_err(permissions.trusted_data_limits.clone(), &state.stderr)?
+                .checked_into_root(&permissions.trusted_data_limits)
+                .context("return type table")?;
 
-            udfs.push(Self {
-                instance: Arc::clone(&instance),
-                resource,
+            let null_strict = instance
+                .bindings()
+                .datafusion_udf_wasm_udf_types()
+                .scalar_udf()
+                .call_null_strict(&mut state, resource)
+                .await
+                .context("call ScalarUdf::null_strict", Some(&state.stderr))?;
+
+            let planner_hints = instance
+                .bindings()
+                .datafusion_udf_wasm_udf_types()
+                .scalar_udf()
+                .call_planner_hints(&mut state, resource)
+                .await
+                .context("call ScalarUdf::planner_hints", Some(&state.stderr))?;
+
+            udfs.push((
                 name,
-                id: Uuid::new_v4(),
+                resource,
                 signature,
-                return_type,
-            });
+                return_types,
+                null_strict,
+                planner_hints,
+            ));
+        }
+
+        let make_udf = |instance, name, signature, return_types, null_strict, planner_hints| Self {
+            instance,
+            workers: vec![],
+            source: Arc::clone(&source),
+            component_digest,
+            permissions_fingerprint,
+            wit_version,
+            name,
+            id: Uuid::new_v4(),
+            signature,
+            return_types,
+            cost: WasmUdfCostEstimate::default(),
+            result_checksums: permissions.result_checksums,
+            invoke_timeout: permissions.invoke_timeout,
+            null_strict,
+            planner_hints,
+            context_labels: permissions.context_labels.clone(),
+        };
+
+        let mut udfs = match permissions.instance_sharing {
+            InstanceSharing::Shared => {
+                let order = udfs
+                    .iter()
+                    .map(|(name, resource, ..)| (name.clone(), *resource))
+                    .collect::<Vec<_>>();
+                let recyclable = Arc::new(RecyclableInstance::new(
+                    Arc::clone(&instance),
+                    order.iter().cloned().collect(),
+                    component,
+                    permissions,
+                    io_rt.clone(),
+                    memory_pool,
+                    Arc::clone(&source),
+                ));
+                let udfs = udfs
+                    .into_iter()
+                    .map(
+                        |(name, _resource, signature, return_types, null_strict, planner_hints)| {
+                            make_udf(
+                                Arc::clone(&recyclable),
+                                name,
+                                signature,
+                                return_types,
+                                null_strict,
+                                planner_hints,
+                            )
+                        },
+                    )
+                    .collect::<Vec<_>>();
+
+                // set up UDFs only after all of them were created, so that `init` can rely on its siblings
+                for (name, resource) in &order {
+                    call_init(&instance, *resource, name, permissions).await?;
+                }
+                recyclable.start_idle_teardown();
+                if let Some(handle) = &permissions.runtime_handle {
+                    handle.register(&recyclable)?;
+                }
+
+                udfs
+            }
+            InstanceSharing::PerUdf => {
+                // the first instance was only used to discover the UDFs
+                drop(instance);
+
+                let mut out = Vec::with_capacity(udfs.len());
+                for (name, _resource, signature, return_types, null_strict, planner_hints) in udfs {
+                    let recyclable = RecyclableInstance::create(
+                        component,
+                        permissions,
+                        io_rt.clone(),
+                        memory_pool,
+                        Arc::clone(&source),
+                        &name,
+                    )
+                    .await
+                    .with_context(|| format!("instantiate `{name}`"))?;
+                    let recyclable = Arc::new(recyclable);
+                    recyclable.start_idle_teardown();
+                    if let Some(handle) = &permissions.runtime_handle {
+                        handle.register(&recyclable)?;
+                    }
+                    out.push(make_udf(
+                        recyclable,
+                        name,
+                        signature,
+                        return_types,
+                        null_strict,
+                        planner_hints,
+                    ));
+                }
+                out
+            }
+        };
+
+        for udf in &mut udfs {
+            for _ in 1..permissions.intra_batch_parallelism.get() {
+                let worker = RecyclableInstance::create(
+                    component,
+                    permissions,
+                    io_rt.clone(),
+                    memory_pool,
+                    Arc::clone(&source),
+                    &udf.name,
+                )
+                .await
+                .with_context(|| format!("instantiate worker for `{}`", udf.name))?;
+                let worker = Arc::new(worker);
+                worker.start_idle_teardown();
+                if let Some(handle) = &permissions.runtime_handle {
+                    handle.register(&worker)?;
+                }
+                udf.workers.push(worker);
+            }
         }
 
         Ok(udfs)
     }
 
+    /// Source code that was passed to [`new`](Self::new) when this UDF was created.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// [Digest](WasmComponentPrecompiled::digest) of the component that this UDF was created from.
+    pub fn component_digest(&self) -> u128 {
+        self.component_digest
+    }
+
+    /// [Fingerprint](WasmPermissions::fingerprint) of the permissions that this UDF was created with.
+    pub fn permissions_fingerprint(&self) -> u64 {
+        self.permissions_fingerprint
+    }
+
+    /// Override the [volatility](Volatility) that the guest declared for this UDF.
+    ///
+    /// Guests may not know or declare the volatility of their UDFs, but DataFusion can only apply optimizations like
+    /// constant folding to UDFs that are not [volatile](Volatility::Volatile).
+    ///
+    /// The host enforces [immutable](Volatility::Immutable) UDFs -- regardless of whether this was declared by the
+    /// guest or by this override: while such a UDF is invoked, the clocks of the guest stand still and access to
+    /// randomness and HTTP is denied.
+    pub fn with_volatility(mut self, volatility: Volatility) -> Self {
+        self.signature.volatility = volatility;
+        self
+    }
+
+    /// WIT version that the guest implements.
+    ///
+    /// Features that were added in later versions are not available for guests that implement older versions.
+    pub fn wit_version(&self) -> WitVersion {
+        self.wit_version
+    }
+
+    /// Cost estimate of this UDF.
+    ///
+    /// The returned handle stays connected to this UDF, i.e. it can be used to observe the cost after the UDF was
+    /// [converted](Self::as_async_udf) and registered with DataFusion.
+    pub fn cost_estimate(&self) -> WasmUdfCostEstimate {
+        self.cost.clone()
+    }
+
+    /// Current resource usage of the guest.
+    ///
+    /// This waits for running invocations of UDFs that share the same guest. A [suspended](Self::suspend) guest does
+    /// not use any resources.
+    pub async fn resource_usage(&self) -> WasmResourceUsage {
+        let Some(instance) = self.instance.current_instance().await else {
+            return WasmResourceUsage::default();
+        };
+        let state = instance.lock_state_unchecked().await;
+        WasmResourceUsage {
+            reserved_bytes: state.limiter.reserved_bytes(),
+            peak_reserved_bytes: state.limiter.peak_reserved_bytes(),
+            linear_memory_bytes: state.limiter.linear_memory_bytes(),
+            table_elements: state.limiter.table_elements(),
+            vfs_inodes: state.vfs_state.inodes(),
+            vfs_bytes: state.vfs_state.accounted_bytes(),
+            epoch_ticks: state.epoch_ticks,
+        }
+    }
+
+    /// Drop the guest to free its resources.
+    ///
+    /// This affects all UDFs that share the same guest. The `close` hooks of these UDFs are called and the guest is
+    /// dropped once running invocations finish. The next invocation re-instantiates the component and sets up all UDFs
+    /// again, which discards all guest state, e.g. global variables and files. See
+    /// [`WasmPermissions::with_idle_ttl`] to suspend idle guests automatically.
+    pub async fn suspend(&self) {
+        self.instance.suspend().await;
+        for worker in &self.workers {
+            worker.suspend().await;
+        }
+    }
+
+    /// Returns `true` if the guest is [suspended](Self::suspend).
+    pub async fn is_suspended(&self) -> bool {
+        self.instance.current_instance().await.is_none()
+    }
+
     /// Convert this [WasmScalarUdf] into an [AsyncScalarUDF].
     pub fn as_async_udf(self) -> AsyncScalarUDF {
         AsyncScalarUDF::new(Arc::new(self))
     }
 
+    /// Invoke UDF for a single batch.
+    ///
+    /// The batch is split across the [workers](WasmPermissions::with_intra_batch_parallelism) if there are any and
+    /// at least one argument is an array.
+    async fn invoke_batch(
+        &self,
+        args: ScalarFunctionArgs,
+        config: &WasmUdfConfig,
+    ) -> DataFusionResult<ColumnarValue> {
+        let n_slices = (self.workers.len() + 1).min(args.number_rows);
+        if n_slices <= 1
+            || args
+                .args
+                .iter()
+                .all(|arg| matches!(arg, ColumnarValue::Scalar(_)))
+        {
+            return self.invoke_batch_with(&self.instance, args, config).await;
+        }
+
+        let slice_rows = args.number_rows.div_ceil(n_slices);
+        let futures = std::iter::once(&self.instance)
+            .chain(&self.workers)
+            .zip((0..args.number_rows).step_by(slice_rows))
+            .map(|(recyclable, offset)| {
+                let number_rows = slice_rows.min(args.number_rows - offset);
+                let slice_args = ScalarFunctionArgs {
+                    args: args
+                        .args
+                        .iter()
+                        .map(|arg| match arg {
+                            ColumnarValue::Array(array) => {
+                                ColumnarValue::Array(array.slice(offset, number_rows))
+                            }
+                            ColumnarValue::Scalar(scalar) => ColumnarValue::Scalar(scalar.clone()),
+                        })
+                        .collect(),
+                    arg_fields: args.arg_fields.clone(),
+                    number_rows,
+                    return_field: Arc::clone(&args.return_field),
+                    config_options: Arc::clone(&args.config_options),
+                };
+                async move {
+                    self.invoke_batch_with(recyclable, slice_args, config)
+                        .await
+                        .with_context(|| format!("slice rows {offset}..{}", offset + number_rows))?
+                        .into_array(number_rows)
+                }
+            });
+        // Drive ALL slices to completion before reporting the first error. Dropping the other slices would cancel
+        // their guest calls and hence poison all other workers, even though only one slice failed.
+        let results = futures_util::future::join_all(futures)
+            .await
+            .into_iter()
+            .collect::<DataFusionResult<Vec<_>>>()?;
+
+        let results = results.iter().map(|a| a.as_ref()).collect::<Vec<_>>();
+        Ok(ColumnarValue::Array(concat(&results)?))
+    }
+
+    /// Invoke UDF for a single batch that is passed to the given guest as a whole.
+    ///
+    /// [Poisons](WasmComponentInstance::poison) the instance on [fatal](WasmUdfError::is_fatal) errors.
+    async fn invoke_batch_with(
+        &self,
+        recyclable: &RecyclableInstance,
+        args: ScalarFunctionArgs,
+        config: &WasmUdfConfig,
+    ) -> DataFusionResult<ColumnarValue> {
+        let labels = self.labels(config)?;
+        let (instance, resource) = recyclable.current(&self.name).await?;
+        let rows = args.number_rows;
+        let start = Instant::now();
+        let res = self
+            .invoke_batch_on(&instance, resource, args, config)
+            .await;
+        instance.invocation_finished(&labels, rows, start.elapsed());
+        res.map_err(|e| instance.poison_if_fatal_with_labels(e, Some(&labels)))
+    }
+
+    /// Labels of an invocation, i.e. the labels from creation merged with [`WasmUdfConfig::context_labels`].
+    fn labels(&self, config: &WasmUdfConfig) -> DataFusionResult<ContextLabels> {
+        Ok(self
+            .context_labels
+            .merge(&ContextLabels::parse(&config.context_labels)?))
+    }
+
+    /// Invoke UDF for a single batch on the given instance.
+    async fn invoke_batch_on(
+        &self,
+        instance: &Arc<WasmComponentInstance>,
+        resource: ResourceAny,
+        args: ScalarFunctionArgs,
+        config: &WasmUdfConfig,
+    ) -> DataFusionResult<ColumnarValue> {
+        let start = Instant::now();
+
+        // cast right before serializing, since the values of dictionaries are only unpacked on the way here
+        let args = self.cast_args(args)?;
+
+        // The serialized data lives on the host until it is copied into the guest memory, which in turn is covered by
+        // the limiter. Same goes for the result in the other direction. Reserve BEFORE serializing, based on the input
+        // size, and settle for the actual size afterwards.
+        let mut reservation = instance.reserve_buffers(estimate_ipc_bytes(&args.args)?)?;
+        let args_converted = wit_types::ScalarFunctionArgs {
+            result_checksum: self.result_checksums,
+            ..(args.clone(), instance).async_try_into().await?
+        };
+        reservation.try_resize(args_converted.args.iter().map(|arg| arg.ipc_bytes()).sum())?;
+        let mut state = instance.lock_state().await?;
+        let immutable =
+            (self.signature.volatility == Volatility::Immutable).then(|| state.immutable.set());
+        let deadline = (config.invocation_timeout_ms > 0)
+            .then(|| Instant::now() + Duration::from_millis(config.invocation_timeout_ms));
+        state.as_context_mut().data_mut().invocation_deadline = deadline;
+        state.as_context_mut().data_mut().limiter.take_exceeded();
+        let cancel_guard = instance.cancel_guard();
+        let res = instance
+            .bindings()
+            .datafusion_udf_wasm_udf_types()
+            .scalar_udf()
+            .call_invoke_with_args(&mut state, resource, &args_converted)
+            .await;
+        cancel_guard.disarm();
+        state.as_context_mut().data_mut().invocation_deadline = None;
+        let exceeded = state.as_context_mut().data_mut().limiter.take_exceeded();
+        let return_type = res
+            .context("call ScalarUdf::invoke_with_args", Some(&state.stderr))
+            .map_err(|e| match exceeded {
+                Some(kind) => WasmUdfError::reclassify_trap(e, kind),
+                None => e,
+            })?
+            .convert_err(instance.trusted_data_limits().clone(), &state.stderr)?;
+        // account for the serialized result BEFORE decoding it
+        reservation.try_grow(return_type.ipc_bytes())?;
+        self.cost
+            .record(args_converted.number_rows, start.elapsed());
+
+        // clean resources AFTER the actual function call
+        drop(immutable);
+        drop(args);
+        drop(state);
+        instance
+            .cache_config_options()
+            .await
+            .clean(instance)
+            .await?;
+        if config.eager_cache_cleanup {
+            instance.cache_field().await.clean(instance).await?;
+        }
+
+        if self.result_checksums {
+            return_type.verify_checksum()?;
+        }
+
+        // the guest may compress the result if we negotiated a codec
+        let token = ComplexityToken::new(instance.trusted_data_limits().clone())?
+            .with_compression(instance.ipc_compression().is_some());
+        match ColumnarValue::checked_from(return_type, token) {
+            Ok(ColumnarValue::Scalar(scalar)) => Ok(ColumnarValue::Scalar(scalar)),
+            Ok(ColumnarValue::Array(array)) if array.len() as u64 != args_converted.number_rows => {
+                Err(DataFusionError::External(
+                    format!(
+                        "UDF returned array of length {} but should produce {} rows",
+                        array.len(),
+                        args_converted.number_rows
+                    )
+                    .into(),
+                ))
+            }
+            Ok(ColumnarValue::Array(array)) => Ok(ColumnarValue::Array(array)),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Evaluate a call with constant arguments at planning time, see [`simplify`](ScalarUDFImpl::simplify).
+    fn fold(
+        &self,
+        args: Vec<ScalarValue>,
+        info: &dyn SimplifyInfo,
+    ) -> DataFusionResult<ScalarValue> {
+        let arg_fields = args
+            .iter()
+            .enumerate()
+            .map(|(i, arg)| {
+                Arc::new(Field::new(
+                    format!("arg_{i}"),
+                    arg.data_type(),
+                    arg.is_null(),
+                ))
+            })
+            .collect::<Vec<_>>();
+        let return_field = self.return_field_from_args(ReturnFieldArgs {
+            arg_fields: &arg_fields,
+            scalar_arguments: &args.iter().map(Some).collect::<Vec<_>>(),
+        })?;
+        let args = ScalarFunctionArgs {
+            args: args.into_iter().map(ColumnarValue::Scalar).collect(),
+            arg_fields,
+            number_rows: 1,
+            return_field,
+            config_options: info
+                .execution_props()
+                .config_options
+                .clone()
+                .unwrap_or_default(),
+        };
+
+        let result = async_in_sync_context(
+            AsyncScalarUDFImpl::invoke_async_with_args(self, args),
+            self.instance.inplace_blocking_timeout(),
+        )?;
+        match result {
+            ColumnarValue::Scalar(scalar) => Ok(scalar),
+            ColumnarValue::Array(array) => ScalarValue::try_from_array(&array, 0),
+        }
+    }
+
+    /// Invoke UDF, taking care of [NULL rows](Self::null_strict) and batching.
+    async fn invoke_values(
+        &self,
+        args: ScalarFunctionArgs,
+        config: &WasmUdfConfig,
+    ) -> DataFusionResult<ColumnarValue> {
+        if self.null_strict {
+            self.invoke_null_strict(args, config).await
+        } else {
+            self.invoke_split(args, config).await
+        }
+    }
+
+    /// Invoke UDF only for the values of a dictionary-encoded argument and expand the result using the dictionary keys.
+    ///
+    /// Returns [`None`] if this optimization does not apply. It requires that the UDF is NOT
+    /// [volatile](Volatility::Volatile) -- otherwise every row may produce a different result --, that exactly one
+    /// argument is a dictionary-encoded array while all others are scalars, and that the dictionary has fewer values
+    /// than there are rows. Only dictionary values that are referenced by a key are evaluated.
+    ///
+    /// NULL keys result in NULL rows for [null-strict](Self::null_strict) UDFs. All other UDFs are evaluated for an
+    /// additional NULL value that all NULL keys refer to.
+    async fn invoke_dictionary(
+        &self,
+        args: &ScalarFunctionArgs,
+        config: &WasmUdfConfig,
+    ) -> DataFusionResult<Option<ColumnarValue>> {
+        if self.signature.volatility == Volatility::Volatile {
+            return Ok(None);
+        }
+
+        let mut dictionary = None;
+        for (pos, arg) in args.args.iter().enumerate() {
+            match arg {
+                ColumnarValue::Array(array) => {
+                    let Some(array) = array.as_any_dictionary_opt() else {
+                        return Ok(None);
+                    };
+                    if dictionary.replace((pos, array)).is_some() {
+                        return Ok(None);
+                    }
+                }
+                ColumnarValue::Scalar(_) => {}
+            }
+        }
+        let Some((pos, array)) = dictionary else {
+            return Ok(None);
+        };
+        // Only evaluate values that are referenced by a key. Dictionaries may carry values that are no longer used --
+        // e.g. after filtering --, and the UDF may not even succeed for them.
+        let keys = array.keys();
+        let mut slots = vec![None; array.values().len()];
+        let mut referenced = vec![];
+        let mut indices = array
+            .normalized_keys()
+            .into_iter()
+            .enumerate()
+            .map(|(row, key)| {
+                (!keys.is_null(row)).then(|| {
+                    *slots[key].get_or_insert_with(|| {
+                        referenced.push(key as u64);
+                        referenced.len() as u64 - 1
+                    })
+                })
+            })
+            .collect::<Vec<_>>();
+        let mut values = take(
+            array.values().as_ref(),
+            &UInt64Array::from_iter_values(referenced),
+            None,
+        )?;
+
+        if !self.null_strict && keys.null_count() > 0 {
+            // NULL keys refer to an appended NULL value
+            let null_slot = values.len() as u64;
+            values = concat(&[
+                values.as_ref(),
+                new_null_array(values.data_type(), 1).as_ref(),
+            ])?;
+            for index in &mut indices {
+                index.get_or_insert(null_slot);
+            }
+        } else if values.is_empty() {
+            // all keys are NULL, which result in NULL rows
+            return Ok(Some(ColumnarValue::Array(new_null_array(
+                args.return_field.data_type(),
+                args.number_rows,
+            ))));
+        }
+        if values.len() >= args.number_rows {
+            return Ok(None);
+        }
+
+        let mut values_args = args.args.clone();
+        values_args[pos] = ColumnarValue::Array(Arc::clone(&values));
+        let mut arg_fields = args.arg_fields.clone();
+        if let Some(field) = arg_fields.get_mut(pos) {
+            *field = Arc::new(
+                field
+                    .as_ref()
+                    .clone()
+                    .with_data_type(values.data_type().clone()),
+            );
+        }
+        let result = self
+            .invoke_values(
+                ScalarFunctionArgs {
+                    args: values_args,
+                    arg_fields,
+                    number_rows: values.len(),
+                    return_field: Arc::clone(&args.return_field),
+                    config_options: Arc::clone(&args.config_options),
+                },
+                config,
+            )
+            .await?
+            .into_array(values.len())?;
+
+        // remaining NULL indices result in NULL rows
+        let result = take(result.as_ref(), &UInt64Array::from(indices), None)?;
+        Ok(Some(ColumnarValue::Array(result)))
+    }
+
+    /// Invoke UDF, splitting the input into batches of at most [`max_batch_rows`](WasmUdfConfig::max_batch_rows).
+    async fn invoke_split(
+        &self,
+        args: ScalarFunctionArgs,
+        config: &WasmUdfConfig,
+    ) -> DataFusionResult<ColumnarValue> {
+        // Constant inputs produce a constant output, so evaluate the guest for a single row only. This does NOT apply
+        // to volatile UDFs, since they may return different results for every row.
+        if args.number_rows > 1
+            && self.signature.volatility != Volatility::Volatile
+            && args
+                .args
+                .iter()
+                .all(|arg| matches!(arg, ColumnarValue::Scalar(_)))
+        {
+            let result = self
+                .invoke_batch(
+                    ScalarFunctionArgs {
+                        number_rows: 1,
+                        ..args
+                    },
+                    config,
+                )
+                .await?;
+            let scalar = match result {
+                ColumnarValue::Array(array) => ScalarValue::try_from_array(&array, 0)?,
+                ColumnarValue::Scalar(scalar) => scalar,
+            };
+            return Ok(ColumnarValue::Scalar(scalar));
+        }
+
+        let max_batch_rows = config.max_batch_rows;
+        if max_batch_rows == 0 || args.number_rows <= max_batch_rows {
+            return self.invoke_batch(args, config).await;
+        }
+
+        let n_batches = args.number_rows.div_ceil(max_batch_rows);
+        let mut results = Vec::with_capacity(n_batches);
+        for (batch, offset) in (0..args.number_rows).step_by(max_batch_rows).enumerate() {
+            let number_rows = max_batch_rows.min(args.number_rows - offset);
+            let batch_args = ScalarFunctionArgs {
+                args: args
+                    .args
+                    .iter()
+                    .map(|arg| match arg {
+                        ColumnarValue::Array(array) => {
+                            ColumnarValue::Array(array.slice(offset, number_rows))
+                        }
+                        ColumnarValue::Scalar(scalar) => ColumnarValue::Scalar(scalar.clone()),
+                    })
+                    .collect(),
+                arg_fields: args.arg_fields.clone(),
+                number_rows,
+                return_field: Arc::clone(&args.return_field),
+                config_options: Arc::clone(&args.config_options),
+            };
+            let result = self
+                .invoke_batch(batch_args, config)
+                .await
+                .with_context(|| {
+                    format!(
+                        "batch {} of {n_batches}, rows {offset}..{}",
+                        batch + 1,
+                        offset + number_rows
+                    )
+                })?;
+            results.push(result.into_array(number_rows)?);
+        }
+
+        let results = results.iter().map(|a| a.as_ref()).collect::<Vec<_>>();
+        Ok(ColumnarValue::Array(concat(&results)?))
+    }
+
+    /// Invoke [null-strict](Self::null_strict) UDF only for rows where all arguments are non-NULL.
+    ///
+    /// The results are scattered back into the original row positions, all other rows are NULL.
+    async fn invoke_null_strict(
+        &self,
+        args: ScalarFunctionArgs,
+        config: &WasmUdfConfig,
+    ) -> DataFusionResult<ColumnarValue> {
+        let return_type = args.return_field.data_type().clone();
+
+        let mut nulls: Option<NullBuffer> = None;
+        for arg in &args.args {
+            match arg {
+                ColumnarValue::Array(array) => {
+                    nulls = NullBuffer::union(nulls.as_ref(), array.logical_nulls().as_ref());
+                }
+                ColumnarValue::Scalar(scalar) if scalar.is_null() => {
+                    return Ok(ColumnarValue::Scalar(ScalarValue::try_from(&return_type)?));
+                }
+                ColumnarValue::Scalar(_) => {}
+            }
+        }
+        let Some(nulls) = nulls.filter(|nulls| nulls.null_count() > 0) else {
+            return self.invoke_split(args, config).await;
+        };
+        if nulls.null_count() == args.number_rows {
+            return Ok(ColumnarValue::Array(new_null_array(
+                &return_type,
+                args.number_rows,
+            )));
+        }
+
+        let predicate = BooleanArray::new(nulls.inner().clone(), None);
+        let number_rows = nulls.len() - nulls.null_count();
+        let filtered_args = ScalarFunctionArgs {
+            args: args
+                .args
+                .iter()
+                .map(|arg| match arg {
+                    ColumnarValue::Array(array) => {
+                        Ok(ColumnarValue::Array(filter(array.as_ref(), &predicate)?))
+                    }
+                    ColumnarValue::Scalar(scalar) => Ok(ColumnarValue::Scalar(scalar.clone())),
+                })
+                .collect::<DataFusionResult<_>>()?,
+            number_rows,
+            ..args
+        };
+        let result = self
+            .invoke_split(filtered_args, config)
+            .await?
+            .into_array(number_rows)?;
+
+        let mut next = 0;
+        let indices = nulls
+            .iter()
+            .map(|valid| {
+                valid.then(|| {
+                    let idx = next;
+                    next += 1;
+                    idx
+                })
+            })
+            .collect::<UInt64Array>();
+        Ok(ColumnarValue::Array(take(result.as_ref(), &indices, None)?))
+    }
+
+    /// Parameter types of the [exact](TypeSignature::Exact) signature that takes the given number of arguments.
+    fn exact_arg_types(&self, n_args: usize) -> Option<&[DataType]> {
+        let signatures = match &self.signature.type_signature {
+            TypeSignature::OneOf(signatures) => signatures.as_slice(),
+            signature => std::slice::from_ref(signature),
+        };
+        signatures.iter().find_map(|signature| match signature {
+            TypeSignature::Exact(expected_types) if expected_types.len() == n_args => {
+                Some(expected_types.as_slice())
+            }
+            _ => None,
+        })
+    }
+
+    /// Cast arguments that are [compatible](is_compatible_type) with -- but NOT equal to -- the declared parameter
+    /// types to the declared types.
+    ///
+    /// So guests only ever see the declared types, e.g. the Rust guest SDK downcasts arrays to the declared layout.
+    fn cast_args(&self, mut args: ScalarFunctionArgs) -> DataFusionResult<ScalarFunctionArgs> {
+        let Some(expected_types) = self.exact_arg_types(args.args.len()) else {
+            return Ok(args);
+        };
+
+        for (i, expected) in expected_types.iter().enumerate() {
+            let provided = args.args[i].data_type();
+            if &provided == expected || !is_compatible_type(&provided, expected) {
+                continue;
+            }
+
+            args.args[i] = args.args[i].cast_to(expected, None)?;
+            if let Some(field) = args.arg_fields.get_mut(i) {
+                *field = Arc::new(field.as_ref().clone().with_data_type(expected.clone()));
+            }
+        }
+        Ok(args)
+    }
+
     /// Check that the provided argument types match the UDF signature.
     fn check_arg_types(&self, arg_types: &[DataType]) -> DataFusionResult<()> {
-        if let TypeSignature::Exact(expected_types) = &self.signature.type_signature {
-            if arg_types.len() != expected_types.len() {
-                return Err(DataFusionError::Plan(format!(
+        match &self.signature.type_signature {
+            TypeSignature::Exact(expected_types) => {
+                check_exact_arg_types(&self.name, arg_types, expected_types)
+            }
+            TypeSignature::OneOf(signatures) => {
+                // guests can only express `OneOf` with exact signatures
+                let mut lengths = vec![];
+                for signature in signatures {
+                    let TypeSignature::Exact(expected_types) = signature else {
+                        return Ok(());
+                    };
+                    if expected_types.len() == arg_types.len() {
+                        return check_exact_arg_types(&self.name, arg_types, expected_types);
+                    }
+                    lengths.push(expected_types.len().to_string());
+                }
+
+                Err(DataFusionError::Plan(format!(
                     "`{}` expects {} parameters but got {}",
                     self.name,
-                    expected_types.len(),
+                    lengths.join(" or "),
                     arg_types.len()
-                )));
+                )))
             }
+            _ => Ok(()),
+        }
+    }
+}
 
-            for (i, (provided, expected)) in arg_types.iter().zip(expected_types.iter()).enumerate()
-            {
-                if provided != expected {
-                    return Err(DataFusionError::Plan(format!(
-                        "argument {} of `{}` should be {:?}, got {:?}",
-                        i + 1,
-                        self.name,
-                        expected,
-                        provided
-                    )));
-                }
-            }
+/// Check that the provided argument types match an [exact](TypeSignature::Exact) signature.
+fn check_exact_arg_types(
+    name: &str,
+    arg_types: &[DataType],
+    expected_types: &[DataType],
+) -> DataFusionResult<()> {
+    if arg_types.len() != expected_types.len() {
+        return Err(DataFusionError::Plan(format!(
+            "`{}` expects {} parameters but got {}",
+            name,
+            expected_types.len(),
+            arg_types.len()
+        )));
+    }
+
+    for (i, (provided, expected)) in arg_types.iter().zip(expected_types.iter()).enumerate() {
+        if !is_compatible_type(provided, expected) {
+            return Err(DataFusionError::Plan(format!(
+                "argument {} of `{}` should be {:?}, got {:?}",
+                i + 1,
+                name,
+                expected,
+                provided
+            )));
         }
+    }
+
+    Ok(())
+}
+
+/// Returns `true` if an argument of type `provided` can be passed to a parameter declared as `expected`.
+///
+/// Besides exact matches, this accepts the large and view layouts of strings and binary data, fixed-size binary data,
+/// and narrower floats. These are [cast](WasmScalarUdf::cast_args) to the declared type before they are passed to the
+/// guest.
+fn is_compatible_type(provided: &DataType, expected: &DataType) -> bool {
+    match expected {
+        DataType::Utf8 => matches!(
+            provided,
+            DataType::Utf8 | DataType::LargeUtf8 | DataType::Utf8View
+        ),
+        DataType::Binary => matches!(
+            provided,
+            DataType::Binary
+                | DataType::LargeBinary
+                | DataType::BinaryView
+                | DataType::FixedSizeBinary(_)
+        ),
+        DataType::Float64 => matches!(
+            provided,
+            DataType::Float16 | DataType::Float32 | DataType::Float64
+        ),
+        _ => provided == expected,
+    }
+}
+
+impl Drop for WasmScalarUdf {
+    fn drop(&mut self) {
+        let Ok(handle) = Handle::try_current() else {
+            log::debug!("no tokio runtime, skip closing UDF `{}`", self.name);
+            return;
+        };
+
+        let recyclables = std::iter::once(Arc::clone(&self.instance))
+            .chain(std::mem::take(&mut self.workers))
+            .collect::<Vec<_>>();
+        let name = std::mem::take(&mut self.name);
+        handle.spawn(async move {
+            for recyclable in recyclables {
+                close_udf(&recyclable, &name).await;
+            }
+        });
+    }
+}
 
-        Ok(())
+/// Estimate the serialized size of the given arguments, in bytes.
+///
+/// Only the referenced part of sliced arrays is counted, e.g. for the slices of [intra-batch
+/// parallelism](WasmPermissions::with_intra_batch_parallelism).
+fn estimate_ipc_bytes(args: &[ColumnarValue]) -> DataFusionResult<usize> {
+    args.iter()
+        .map(|arg| match arg {
+            ColumnarValue::Array(array) => Ok(array.to_data().get_slice_memory_size()?),
+            ColumnarValue::Scalar(scalar) => Ok(scalar.size()),
+        })
+        .sum()
+}
+
+/// Call the `close` hook of the UDF within the current instance, if there is one.
+async fn close_udf(recyclable: &RecyclableInstance, name: &str) {
+    let (instance, resource) = match recyclable.current_unchecked(name).await {
+        Ok(current) => current,
+        Err(e) => {
+            log::debug!("skip closing UDF `{name}`: {e}");
+            return;
+        }
+    };
+    let mut state = match instance.lock_state().await {
+        Ok(state) => state,
+        Err(e) => {
+            log::debug!("skip closing UDF `{name}`: {e}");
+            return;
+        }
+    };
+    let res = instance
+        .bindings()
+        .datafusion_udf_wasm_udf_types()
+        .scalar_udf()
+        .call_close(&mut state, resource)
+        .await
+        .context("call ScalarUdf::close", Some(&state.stderr))
+        .and_then(|res| res.convert_err(instance.trusted_data_limits().clone(), &state.stderr));
+    if let Err(e) = res {
+        log::warn!("cannot close UDF `{name}`: {e}");
     }
 }
 
@@ -251,7 +1161,13 @@ impl ScalarUDFImpl for WasmScalarUdf {
     fn return_type(&self, arg_types: &[DataType]) -> DataFusionResult<DataType> {
         self.check_arg_types(arg_types)?;
 
-        if let Some(return_type) = &self.return_type {
+        if let Some((_, return_type)) = self.return_types.iter().find(|(expected_types, _)| {
+            expected_types.len() == arg_types.len()
+                && arg_types
+                    .iter()
+                    .zip(expected_types)
+                    .all(|(provided, expected)| is_compatible_type(provided, expected))
+        }) {
             return Ok(return_type.clone());
         }
 
@@ -261,25 +1177,131 @@ impl ScalarUDFImpl for WasmScalarUdf {
                     .iter()
                     .map(|t| wit_types::DataType::from(t.clone()))
                     .collect::<Vec<_>>();
-                let mut state = self.instance.lock_state().await;
-                let return_type = self
-                    .instance
+                let (instance, resource) = self.instance.current(&self.name).await?;
+                let mut state = instance.lock_state().await?;
+                let return_type = instance
+                    .bindings()
+                    .datafusion_udf_wasm_udf_types()
+                    .scalar_udf()
+                    .call_return_type(&mut state, resource, &arg_types)
+                    .await
+                    .context("call ScalarUdf::return_type", Some(&state.stderr))
+                    .map_err(|e| instance.poison_if_fatal(e))?
+                    .convert_err(instance.trusted_data_limits().clone(), &state.stderr)?;
+                return_type.checked_into_root(instance.trusted_data_limits())
+            },
+            self.instance.inplace_blocking_timeout(),
+        )
+    }
+
+    fn coerce_types(&self, arg_types: &[DataType]) -> DataFusionResult<Vec<DataType>> {
+        async_in_sync_context(
+            async {
+                let wit_arg_types = arg_types
+                    .iter()
+                    .map(|t| wit_types::DataType::from(t.clone()))
+                    .collect::<Vec<_>>();
+                let (instance, resource) = self.instance.current(&self.name).await?;
+                let mut state = instance.lock_state().await?;
+                let coerced = instance
                     .bindings()
                     .datafusion_udf_wasm_udf_types()
                     .scalar_udf()
-                    .call_return_type(&mut state, self.resource, &arg_types)
+                    .call_coerce_types(&mut state, resource, &wit_arg_types)
                     .await
-                    .context(
-                        "call ScalarUdf::return_type",
-                        Some(&state.stderr.contents()),
-                    )?
-                    .convert_err(self.instance.trusted_data_limits().clone())?;
-                return_type.checked_into_root(self.instance.trusted_data_limits())
+                    .context("call ScalarUdf::coerce_types", Some(&state.stderr))
+                    .map_err(|e| instance.poison_if_fatal(e))?
+                    .convert_err(instance.trusted_data_limits().clone(), &state.stderr)?;
+
+                if coerced.len() != arg_types.len() {
+                    return Err(DataFusionError::Plan(format!(
+                        "`{}` coerced {} argument types to {}",
+                        self.name,
+                        arg_types.len(),
+                        coerced.len()
+                    )));
+                }
+
+                coerced
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, t)| {
+                        t.checked_into_root(instance.trusted_data_limits())
+                            .with_context(|| format!("coerced type {i}"))
+                    })
+                    .collect()
             },
             self.instance.inplace_blocking_timeout(),
         )
     }
 
+    fn short_circuits(&self) -> bool {
+        self.planner_hints.short_circuits
+    }
+
+    fn output_ordering(&self, inputs: &[ExprProperties]) -> DataFusionResult<SortProperties> {
+        // the guest hint assumes that all non-constant arguments are sorted in the same direction
+        let mut input_order = SortProperties::Singleton;
+        for input in inputs {
+            input_order = match (input_order, input.sort_properties) {
+                (order, SortProperties::Singleton) | (SortProperties::Singleton, order) => order,
+                (SortProperties::Ordered(a), SortProperties::Ordered(b)) if a == b => {
+                    SortProperties::Ordered(a)
+                }
+                _ => SortProperties::Unordered,
+            };
+        }
+
+        let order = match (self.planner_hints.output_ordering, input_order) {
+            (wit_types::OutputOrdering::Unordered, _) => SortProperties::Unordered,
+            (_, SortProperties::Singleton) => SortProperties::Singleton,
+            (_, SortProperties::Unordered) => SortProperties::Unordered,
+            (wit_types::OutputOrdering::Ascending, SortProperties::Ordered(options)) => {
+                SortProperties::Ordered(options)
+            }
+            (wit_types::OutputOrdering::Descending, SortProperties::Ordered(options)) => {
+                SortProperties::Ordered(SortOptions {
+                    descending: !options.descending,
+                    nulls_first: options.nulls_first,
+                })
+            }
+        };
+        Ok(order)
+    }
+
+    fn preserves_lex_ordering(&self, _inputs: &[ExprProperties]) -> DataFusionResult<bool> {
+        Ok(self.planner_hints.preserves_lex_ordering)
+    }
+
+    fn simplify(
+        &self,
+        args: Vec<Expr>,
+        info: &dyn SimplifyInfo,
+    ) -> DataFusionResult<ExprSimplifyResult> {
+        if self.signature.volatility != Volatility::Immutable {
+            return Ok(ExprSimplifyResult::Original(args));
+        }
+        let Some(scalars) = args
+            .iter()
+            .map(|arg| match arg {
+                Expr::Literal(scalar, _) => Some(scalar.clone()),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()
+        else {
+            return Ok(ExprSimplifyResult::Original(args));
+        };
+
+        // errors surface again during execution, with the usual error handling
+        match self.fold(scalars, info) {
+            Ok(scalar) => Ok(ExprSimplifyResult::Simplified(Expr::Literal(scalar, None))),
+            Err(e) => {
+                log::debug!("cannot fold `{}` at planning time: {e}", self.name);
+                Ok(ExprSimplifyResult::Original(args))
+            }
+        }
+    }
+
     fn invoke_with_args(&self, _args: ScalarFunctionArgs) -> DataFusionResult<ColumnarValue> {
         Err(DataFusionError::NotImplemented(
             "synchronous invocation of WasmScalarUdf is not supported, use invoke_async_with_args instead".to_string(),
@@ -297,44 +1319,61 @@ impl AsyncScalarUDFImpl for WasmScalarUdf {
         &self,
         args: ScalarFunctionArgs,
     ) -> DataFusionResult<ColumnarValue> {
-        let args_converted = (args.clone(), &self.instance).async_try_into().await?;
-        let mut state = self.instance.lock_state().await;
-        let return_type = self
-            .instance
-            .bindings()
-            .datafusion_udf_wasm_udf_types()
-            .scalar_udf()
-            .call_invoke_with_args(&mut state, self.resource, &args_converted)
-            .await
-            .context(
-                "call ScalarUdf::invoke_with_args",
-                Some(&state.stderr.contents()),
-            )?
-            .convert_err(self.instance.trusted_data_limits().clone())?;
+        let config = args
+            .config_options
+            .extensions
+            .get::<WasmUdfConfig>()
+            .cloned()
+            .unwrap_or_default();
+        let labels = self.labels(&config)?;
 
-        // clean resources AFTER the actual function call
-        drop(args);
-        drop(state);
-        self.instance
-            .cache_config_options()
-            .await
-            .clean(&self.instance)
-            .await?;
+        let span = span!(
+            "invoke",
+            udf = self.name.as_str(),
+            rows = args.number_rows as u64,
+            bytes = args
+                .args
+                .iter()
+                .map(|arg| match arg {
+                    ColumnarValue::Array(array) => array.get_array_memory_size(),
+                    ColumnarValue::Scalar(scalar) => scalar.size(),
+                })
+                .sum::<usize>() as u64;
+            record: duration_us
+        );
+        let start = Instant::now();
 
-        match return_type.checked_into_root(self.instance.trusted_data_limits()) {
-            Ok(ColumnarValue::Scalar(scalar)) => Ok(ColumnarValue::Scalar(scalar)),
-            Ok(ColumnarValue::Array(array)) if array.len() as u64 != args_converted.number_rows => {
-                Err(DataFusionError::External(
-                    format!(
-                        "UDF returned array of length {} but should produce {} rows",
-                        array.len(),
-                        args_converted.number_rows
-                    )
-                    .into(),
-                ))
+        let fut = async {
+            if let Some(result) = self.invoke_dictionary(&args, &config).await? {
+                return Ok(result);
             }
-            Ok(ColumnarValue::Array(array)) => Ok(ColumnarValue::Array(array)),
-            Err(e) => Err(e),
+            self.invoke_values(args, &config).await
+        };
+
+        let fut = span.instrument(fut);
+        let res = match self.invoke_timeout {
+            None => fut.await,
+            Some(invoke_timeout) => match tokio::time::timeout(invoke_timeout, fut).await {
+                Ok(res) => res,
+                Err(e) => {
+                    // Only the guests that were interrupted in the middle of the call may be in an inconsistent state.
+                    // Dropping the call cancelled them, but they should be reported as timed out. Guests that already
+                    // finished their slice stay usable.
+                    for recyclable in std::iter::once(&self.instance).chain(&self.workers) {
+                        recyclable.poison_if_cancelled().await;
+                    }
+                    Err(DataFusionError::External(Box::new(WasmUdfError::Timeout {
+                        source: Box::new(e),
+                    })))
+                }
+            },
+        };
+        span.record("duration_us", start.elapsed().as_micros() as u64);
+
+        if labels.is_empty() {
+            res
+        } else {
+            res.with_context(|| format!("invoke `{}` [{labels}]", self.name))
         }
     }
 }
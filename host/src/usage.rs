@@ -0,0 +1,48 @@
+//! Resource usage of guests.
+
+/// Resource usage of the guest that backs a [`WasmScalarUdf`](crate::WasmScalarUdf).
+///
+/// All UDFs that were created by the same [`WasmScalarUdf::new`](crate::WasmScalarUdf::new) call share one guest, so
+/// they report the same usage.
+///
+/// Compare these numbers with the [static resource limits](crate::StaticResourceLimits), the
+/// [VFS limits](crate::VfsLimits), the [DataFusion memory pool](datafusion_execution::memory_pool), and the
+/// [epoch tick time](crate::WasmPermissions::with_epoch_tick_time) to right-size them.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[expect(missing_copy_implementations, reason = "allow later extensions")]
+pub struct WasmResourceUsage {
+    /// Bytes that are currently accounted against the DataFusion memory pool.
+    ///
+    /// This covers the linear memory of the guest, its tables, the VFS, the stderr buffer, and unused parts of the
+    /// [pre-reservation](crate::DynamicMemoryLimits::prereserve_bytes). It does NOT cover host-side conversion
+    /// buffers, since these only live for the duration of a single call.
+    pub reserved_bytes: usize,
+
+    /// Peak of [`reserved_bytes`](Self::reserved_bytes).
+    pub peak_reserved_bytes: usize,
+
+    /// Size of all linear memories of the guest, in bytes.
+    ///
+    /// WASM memories never shrink, so this is also the peak.
+    pub linear_memory_bytes: usize,
+
+    /// Number of elements of all tables of the guest.
+    ///
+    /// Tables never shrink, so this is also the peak. Each table is limited by
+    /// [`StaticResourceLimits::n_elements_per_table`](crate::StaticResourceLimits::n_elements_per_table).
+    pub table_elements: usize,
+
+    /// Number of VFS inodes, see [`VfsLimits::inodes`](crate::VfsLimits::inodes).
+    pub vfs_inodes: u64,
+
+    /// Bytes that are used by the VFS, i.e. file content and directory entries.
+    ///
+    /// File content of the root filesystem is shared between guests and only counted once it is modified.
+    pub vfs_bytes: usize,
+
+    /// Number of epoch ticks that the guest was running for.
+    ///
+    /// Multiply with the [epoch tick time](crate::WasmPermissions::with_epoch_tick_time) to get a rough estimate of
+    /// the CPU time.
+    pub epoch_ticks: u64,
+}
@@ -0,0 +1,145 @@
+//! Validation of UDF source code.
+
+use std::{sync::Arc, time::Instant};
+
+use datafusion_common::Result as DataFusionResult;
+use datafusion_execution::memory_pool::MemoryPool;
+use tokio::{runtime::Handle, sync::Mutex};
+use wasmtime::AsContextMut;
+
+use crate::{
+    WasmComponentPrecompiled, WasmPermissions,
+    component::WasmComponentInstance,
+    conversion::limits::CheckedInto,
+    error::{WasmToDataFusionResultExt, WasmUdfError},
+};
+
+/// Problem in UDF source code, see [`WasmSourceValidator`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SourceDiagnostic {
+    /// Description of the problem.
+    pub message: String,
+
+    /// Line number, starting at 1.
+    ///
+    /// This is 0 if the guest does not know the line.
+    pub line: u32,
+
+    /// Column, starting at 1.
+    ///
+    /// This is 0 if the guest does not know the column.
+    pub column: u32,
+}
+
+impl std::fmt::Display for SourceDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Self {
+            message,
+            line,
+            column,
+        } = self;
+        match (line, column) {
+            (0, _) => write!(f, "{message}"),
+            (line, 0) => write!(f, "{line}: {message}"),
+            (line, column) => write!(f, "{line}:{column}: {message}"),
+        }
+    }
+}
+
+/// Checks UDF source code for syntax errors without creating UDFs.
+///
+/// Creating [UDFs](crate::WasmScalarUdf) spins up a fresh guest and runs the source code, which is slow -- e.g. for
+/// Python -- and has side effects. Instead, the validator keeps a single guest and asks it to check the code without
+/// running it. The guest is created on first use and replaced if it gets [poisoned](WasmUdfError::Poisoned).
+///
+/// Guests that do not support validation report no problems, so an empty result does NOT guarantee that creating
+/// UDFs succeeds.
+#[derive(Debug)]
+pub struct WasmSourceValidator {
+    /// Component that is instantiated.
+    component: WasmComponentPrecompiled,
+
+    /// Permissions of the guest.
+    permissions: WasmPermissions,
+
+    /// I/O runtime of the guest.
+    io_rt: Handle,
+
+    /// Memory pool of the guest.
+    memory_pool: Arc<dyn MemoryPool>,
+
+    /// Guest instance, created on first use.
+    instance: Mutex<Option<Arc<WasmComponentInstance>>>,
+}
+
+impl WasmSourceValidator {
+    /// Create new validator.
+    ///
+    /// The guest is NOT created until the first [validation](Self::validate). Each validation may run up to
+    /// [`WasmPermissions::with_init_timeout`].
+    pub fn new(
+        component: &WasmComponentPrecompiled,
+        permissions: &WasmPermissions,
+        io_rt: Handle,
+        memory_pool: &Arc<dyn MemoryPool>,
+    ) -> Self {
+        Self {
+            component: component.clone(),
+            permissions: permissions.clone(),
+            io_rt,
+            memory_pool: Arc::clone(memory_pool),
+            instance: Mutex::default(),
+        }
+    }
+
+    /// Check source code.
+    ///
+    /// Returns the problems that the guest found, which is empty if the code is fine. Errors are reserved for failures
+    /// of the guest itself.
+    pub async fn validate(&self, source: &str) -> DataFusionResult<Vec<SourceDiagnostic>> {
+        let instance = self.instance().await?;
+
+        let mut state = instance.lock_state().await?;
+        state.as_context_mut().data_mut().invocation_deadline =
+            Some(Instant::now() + self.permissions.init_timeout);
+        state.as_context_mut().data_mut().limiter.take_exceeded();
+        let res = instance
+            .bindings()
+            .datafusion_udf_wasm_udf_types()
+            .call_validate_source(&mut state, source)
+            .await;
+        state.as_context_mut().data_mut().invocation_deadline = None;
+        let exceeded = state.as_context_mut().data_mut().limiter.take_exceeded();
+        let diagnostics = res
+            .context("call validate_source", Some(&state.stderr))
+            .map_err(|e| match exceeded {
+                Some(kind) => WasmUdfError::reclassify_trap(e, kind),
+                None => e,
+            })
+            .map_err(|e| instance.poison_if_fatal(e))?;
+
+        diagnostics.checked_into_root(instance.trusted_data_limits())
+    }
+
+    /// Get current guest instance, creating a new one if required.
+    async fn instance(&self) -> DataFusionResult<Arc<WasmComponentInstance>> {
+        let mut guard = self.instance.lock().await;
+        if let Some(instance) = guard.as_ref()
+            && !instance.is_poisoned()
+        {
+            return Ok(Arc::clone(instance));
+        }
+
+        let instance = Arc::new(
+            WasmComponentInstance::new(
+                &self.component,
+                &self.permissions,
+                self.io_rt.clone(),
+                &self.memory_pool,
+            )
+            .await?,
+        );
+        *guard = Some(Arc::clone(&instance));
+        Ok(instance)
+    }
+}
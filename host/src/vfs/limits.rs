@@ -19,6 +19,17 @@ pub struct VfsLimits {
     ///
     /// Keep this to a rather small size to prevent super-linear complexity due to string hashing.
     pub max_path_segment_size: u64,
+
+    /// Allow the guest to remove files and directories.
+    ///
+    /// Only nodes that were created at runtime can be removed, the content of the root filesystem is always
+    /// preserved.
+    pub allow_remove: bool,
+
+    /// Deny all modifications, i.e. the guest can only read the root filesystem.
+    ///
+    /// Denied attempts are reported to the [observer](crate::SandboxObserver::io_denied).
+    pub read_only: bool,
 }
 
 impl Default for VfsLimits {
@@ -27,6 +38,8 @@ impl Default for VfsLimits {
             inodes: 10_000,
             max_path_length: 255,
             max_path_segment_size: 50,
+            allow_remove: false,
+            read_only: false,
         }
     }
 }
@@ -8,6 +8,7 @@
 use std::{
     collections::{HashMap, hash_map::Entry},
     hash::Hash,
+    ops::Deref,
     sync::{
         Arc, RwLock, Weak,
         atomic::{AtomicU64, Ordering},
@@ -39,15 +40,18 @@ use wasmtime_wasi_io::bytes;
 use crate::{
     error::LimitExceeded,
     limiter::Limiter,
+    observer::{DeniedIo, Observer},
     state::WasmStateImpl,
     vfs::{
         limits::VfsLimits,
         path::{PathSegment, PathTraversal},
+        root_fs::{RootFs, RootFsDir, RootFsNode},
     },
 };
 
 pub(crate) mod limits;
 mod path;
+pub(crate) mod root_fs;
 
 impl VfsView for WasmStateImpl {
     fn vfs(&mut self) -> VfsCtxView<'_> {
@@ -69,6 +73,49 @@ struct VfsNode {
 
     /// Pointer to parent node.
     parent: Option<Weak<RwLock<Self>>>,
+
+    /// Node was populated from the [root filesystem](RootFsNode) and hence cannot be removed.
+    from_root_fs: bool,
+
+    /// Resources of a removed node that are released once the last reference -- e.g. an open descriptor -- is gone.
+    ///
+    /// See [`VfsState::unlink`].
+    unlinked: Option<UnlinkedCharge>,
+}
+
+impl Drop for VfsNode {
+    fn drop(&mut self) {
+        if let Some(charge) = self.unlinked.take() {
+            let content_bytes = match &self.kind {
+                VfsNodeKind::File { content } => content.accounted_bytes(),
+                VfsNodeKind::Directory { .. } => 0,
+            };
+            charge.release(content_bytes);
+        }
+    }
+}
+
+/// Resources that a [removed](VfsState::unlink) node still holds.
+#[derive(Debug)]
+struct UnlinkedCharge {
+    /// Bytes for the name and the node itself, NOT including the file content.
+    bytes: usize,
+
+    /// Allocation that the inode is returned to.
+    inodes_allocation: Arc<Allocation>,
+
+    /// Limiter that the bytes are returned to.
+    limiter: Limiter,
+}
+
+impl UnlinkedCharge {
+    /// Release inode and memory, including the given content bytes.
+    fn release(self, content_bytes: usize) {
+        self.inodes_allocation.dec(1);
+        if let Err(e) = self.limiter.shrink(self.bytes + content_bytes) {
+            log::warn!("cannot release memory of removed VFS node: {e:?}");
+        }
+    }
 }
 
 /// A kind node in the virtual filesystem tree.
@@ -77,15 +124,85 @@ enum VfsNodeKind {
     /// A regular file with its content.
     File {
         /// File content stored in memory.
-        content: Vec<u8>,
+        content: FileContent,
     },
     /// A directory containing child nodes.
     Directory {
         /// Child nodes indexed by name.
+        ///
+        /// If [`shared`](Self::Directory::shared) is set, this is empty until the directory is
+        /// [materialized](VfsState::materialize).
         children: HashMap<PathSegment, SharedVfsNode>,
+
+        /// Content of the [root filesystem](RootFsNode) that was not materialized yet.
+        shared: Option<RootFsDir>,
     },
 }
 
+/// Content of a regular file.
+#[derive(Debug)]
+enum FileContent {
+    /// Content shared with the [root filesystem](RootFsNode) of other instances.
+    ///
+    /// This is NOT accounted for by the [`Limiter`] and is copied on the first write.
+    Shared(Arc<[u8]>),
+
+    /// Content owned by this VFS.
+    Owned(Vec<u8>),
+}
+
+impl FileContent {
+    /// Get mutable access to the content.
+    ///
+    /// Shared content is copied first, which is accounted for by the [`Limiter`].
+    fn make_mut(&mut self, limiter: &Limiter) -> FsResult<&mut Vec<u8>> {
+        if let Self::Shared(data) = self {
+            limiter
+                .grow(data.len())
+                .map_err(|_| FsError::trap(ErrorCode::InsufficientMemory))?;
+            *self = Self::Owned(data.to_vec());
+        }
+
+        match self {
+            Self::Owned(data) => Ok(data),
+            Self::Shared(_) => unreachable!("just converted to owned data"),
+        }
+    }
+
+    /// Number of bytes that are accounted for by the [`Limiter`].
+    ///
+    /// Writes only account for the length of the content, not for the capacity of the underlying buffer.
+    fn accounted_bytes(&self) -> usize {
+        match self {
+            Self::Shared(_) => 0,
+            Self::Owned(data) => data.len(),
+        }
+    }
+}
+
+impl Default for FileContent {
+    fn default() -> Self {
+        Self::Owned(Vec::new())
+    }
+}
+
+impl From<Vec<u8>> for FileContent {
+    fn from(data: Vec<u8>) -> Self {
+        Self::Owned(data)
+    }
+}
+
+impl Deref for FileContent {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            Self::Shared(data) => &data[..],
+            Self::Owned(data) => &data[..],
+        }
+    }
+}
+
 impl VfsNode {
     /// Convert a VfsNode to DescriptorStat.
     fn stat(&self) -> DescriptorStat {
@@ -161,43 +278,6 @@ impl VfsNode {
         let (lower, upper) = hasher.finish128().as_u64();
         MetadataHashValue { lower, upper }
     }
-
-    /// Resolve a path from a starting node to a target node.
-    fn traverse(
-        start: SharedVfsNode,
-        directions: impl Iterator<Item = Result<PathTraversal, LimitExceeded>>,
-    ) -> FsResult<SharedVfsNode> {
-        let mut current = start;
-
-        for direction in directions {
-            let direction = direction?;
-
-            let current_guard = current.read().unwrap();
-            let next = match &current_guard.kind {
-                VfsNodeKind::Directory { children, .. } => match direction {
-                    PathTraversal::Stay => Arc::clone(&current),
-                    PathTraversal::Up => current_guard
-                        .parent
-                        .as_ref()
-                        .map(|parent| parent.upgrade().expect("parent still valid"))
-                        // note: `/..` = `/`, i.e. overshooting is allowed
-                        .unwrap_or_else(|| Arc::clone(&current)),
-                    PathTraversal::Down(segment) => Arc::clone(
-                        children
-                            .get(&segment)
-                            .ok_or_else(|| FsError::trap(ErrorCode::NoEntry))?,
-                    ),
-                },
-                VfsNodeKind::File { .. } => {
-                    return Err(FsError::trap(ErrorCode::NotDirectory));
-                }
-            };
-            drop(current_guard);
-            current = next;
-        }
-
-        Ok(current)
-    }
 }
 
 /// Tracked allocation of some resource.
@@ -258,29 +338,193 @@ pub(crate) struct VfsState {
     limits: VfsLimits,
 
     /// Current allocation of inodes.
-    inodes_allocation: Allocation,
+    inodes_allocation: Arc<Allocation>,
 
     /// Storage limiter.
     limiter: Limiter,
+
+    /// Reports writes.
+    observer: Observer,
 }
 
 impl VfsState {
     /// Create a new empty VFS.
-    pub(crate) fn new(limits: VfsLimits, limiter: Limiter) -> Self {
-        let inodes_allocation = Allocation::new("inodes", limits.inodes);
+    pub(crate) fn new(limits: VfsLimits, limiter: Limiter, observer: Observer) -> Self {
+        let inodes_allocation = Arc::new(Allocation::new("inodes", limits.inodes));
 
         Self {
             root: Arc::new(RwLock::new(VfsNode {
                 kind: VfsNodeKind::Directory {
                     children: HashMap::new(),
+                    shared: None,
                 },
                 parent: None,
+                from_root_fs: true,
+                unlinked: None,
             })),
             metadata_hash_key: rand::rng().random(),
             limits,
             inodes_allocation,
             limiter,
+            observer,
+        }
+    }
+
+    /// Number of inodes.
+    pub(crate) fn inodes(&self) -> u64 {
+        self.inodes_allocation.n.load(Ordering::SeqCst)
+    }
+
+    /// Bytes that are accounted for by the [`Limiter`].
+    pub(crate) fn accounted_bytes(&self) -> usize {
+        self.limiter.accounted_bytes()
+    }
+
+    /// Populate VFS from the shared, immutable root filesystem.
+    ///
+    /// Nodes are NOT allocated here. Instead, the VFS points to the shared tree and [materializes](Self::materialize)
+    /// directories when they are accessed. File content is shared with `root_fs` until it is modified. Inodes and the
+    /// directory structure are accounted for upfront as if the nodes were created by the guest.
+    ///
+    /// This can be called multiple times to overlay several filesystems, but MUST be called before the VFS is used.
+    /// Directories that already exist are merged, while files that already exist -- or that collide with a directory --
+    /// result in an error.
+    pub(crate) fn populate(&self, root_fs: &RootFs) -> std::io::Result<()> {
+        let mut root_guard = self.root.write().unwrap();
+        let VfsNodeKind::Directory { children, shared } = &mut root_guard.kind else {
+            unreachable!("root is always a directory");
+        };
+        if !children.is_empty() {
+            return Err(std::io::Error::other(
+                "root filesystem must be populated before the VFS is used",
+            ));
+        }
+
+        let (merged, added) = match shared {
+            Some(root) => RootFs {
+                root: Arc::clone(root),
+                usage: Default::default(),
+            }
+            .overlay(root_fs)
+            .map(|(merged, added)| (merged.root, added))?,
+            None => (Arc::clone(&root_fs.root), root_fs.usage),
+        };
+
+        self.inodes_allocation.inc(added.inodes)?;
+        if let Err(e) = self.limiter.grow(added.bytes) {
+            self.inodes_allocation.dec(added.inodes);
+            return Err(e.into());
+        }
+        *shared = Some(merged);
+
+        Ok(())
+    }
+
+    /// Allocate nodes for the [root filesystem](RootFsNode) content of the given directory.
+    ///
+    /// Only the direct children are allocated, sub-directories are materialized when they are accessed themselves.
+    /// This is a no-op for files and for directories that were already materialized.
+    fn materialize(&self, node: &SharedVfsNode) -> FsResult<()> {
+        if !matches!(
+            node.read().unwrap().kind,
+            VfsNodeKind::Directory {
+                shared: Some(_),
+                ..
+            }
+        ) {
+            return Ok(());
+        }
+
+        let mut guard = node.write().unwrap();
+        let VfsNodeKind::Directory { children, shared } = &mut guard.kind else {
+            unreachable!("checked above");
+        };
+        // another thread may have been faster
+        let Some(dir) = shared.as_ref() else {
+            return Ok(());
+        };
+
+        let materialized = dir
+            .iter()
+            .map(|(name, child)| {
+                let name = PathSegment::new(name, &self.limits)?;
+                let kind = match child {
+                    RootFsNode::File { content } => VfsNodeKind::File {
+                        content: FileContent::Shared(Arc::clone(content)),
+                    },
+                    RootFsNode::Directory { children } => VfsNodeKind::Directory {
+                        children: HashMap::new(),
+                        shared: Some(Arc::clone(children)),
+                    },
+                };
+                let child = Arc::new(RwLock::new(VfsNode {
+                    kind,
+                    parent: Some(Arc::downgrade(node)),
+                    from_root_fs: true,
+                    unlinked: None,
+                }));
+                Ok((name, child))
+            })
+            .collect::<FsResult<Vec<_>>>()?;
+        children.extend(materialized);
+        *shared = None;
+
+        Ok(())
+    }
+    /// Mark node that was removed from its parent as unlinked.
+    ///
+    /// Its inode and memory -- the name of the given length, the node itself, and its content -- stay accounted for
+    /// until the last reference to the node is dropped. Otherwise, a guest could keep writing to a removed file via an
+    /// open descriptor without being limited.
+    fn unlink(&self, node: &SharedVfsNode, name_len: usize) {
+        node.write().unwrap().unlinked = Some(UnlinkedCharge {
+            bytes: name_len + std::mem::size_of::<SharedVfsNode>(),
+            inodes_allocation: Arc::clone(&self.inodes_allocation),
+            limiter: self.limiter.clone(),
+        });
+    }
+
+    /// Resolve a path from a starting node to a target node.
+    ///
+    /// Directories are [materialized](Self::materialize) when they are traversed.
+    fn traverse(
+        &self,
+        start: SharedVfsNode,
+        directions: impl Iterator<Item = Result<PathTraversal, LimitExceeded>>,
+    ) -> FsResult<SharedVfsNode> {
+        let mut current = start;
+
+        for direction in directions {
+            let direction = direction?;
+
+            self.materialize(&current)?;
+            let current_guard = current.read().unwrap();
+            let next = match &current_guard.kind {
+                VfsNodeKind::Directory { children, .. } => match direction {
+                    PathTraversal::Stay => Arc::clone(&current),
+                    PathTraversal::Up => match &current_guard.parent {
+                        // note: `/..` = `/`, i.e. overshooting is allowed
+                        None => Arc::clone(&current),
+                        // the parent may have been removed while a descriptor still points to this node
+                        Some(parent) => parent
+                            .upgrade()
+                            .ok_or_else(|| FsError::trap(ErrorCode::NoEntry))?,
+                    },
+                    PathTraversal::Down(segment) => Arc::clone(
+                        children
+                            .get(&segment)
+                            .ok_or_else(|| FsError::trap(ErrorCode::NoEntry))?,
+                    ),
+                },
+                VfsNodeKind::File { .. } => {
+                    return Err(FsError::trap(ErrorCode::NotDirectory));
+                }
+            };
+            drop(current_guard);
+            current = next;
         }
+
+        Ok(current)
     }
 }
 
@@ -308,6 +552,8 @@ struct VfsOutputStream {
     offset: u64,
     /// Resource limiter for memory accounting.
     limiter: Limiter,
+    /// Reports writes.
+    observer: Observer,
 }
 
 impl std::fmt::Debug for VfsOutputStream {
@@ -335,6 +581,7 @@ impl WasiOutputStream for VfsOutputStream {
 
         match perform_write(&self.node, self.offset as usize, &buf, &self.limiter) {
             Ok(nbyte) => {
+                self.observer.vfs_write(nbyte);
                 self.offset += nbyte;
                 Ok(())
             }
@@ -405,7 +652,7 @@ impl<'a> VfsCtxView<'a> {
             node
         };
 
-        match VfsNode::traverse(start, directions) {
+        match self.vfs_state.traverse(start, directions) {
             Ok(node) => Ok(Some(node)),
             Err(e) => match e.downcast_ref() {
                 Some(ErrorCode::NoEntry) => Ok(None),
@@ -441,10 +688,196 @@ impl<'a> VfsCtxView<'a> {
             }
         };
 
-        let parent = VfsNode::traverse(start, directions.into_iter())?;
+        let parent = self.vfs_state.traverse(start, directions.into_iter())?;
+        if parent.read().unwrap().unlinked.is_some() {
+            // Per POSIX, a removed directory cannot contain entries.
+            return Err(FsError::trap(ErrorCode::NoEntry));
+        }
+        self.vfs_state.materialize(&parent)?;
 
         Ok((parent, name))
     }
+
+    /// Deny modification at given path if the VFS is [read-only](VfsLimits::read_only).
+    fn check_writable(&self, path: &str) -> FsResult<()> {
+        if self.vfs_state.limits.read_only {
+            self.vfs_state.observer.io_denied(DeniedIo::VfsWrite {
+                path: path.to_owned(),
+            });
+            return Err(FsError::trap(ErrorCode::ReadOnly));
+        }
+        Ok(())
+    }
+
+    /// Remove file or empty directory at given path.
+    ///
+    /// Descriptors that still point to the removed node stay valid. The inode as well as the memory that was accounted
+    /// for the node are released once the last of them is dropped, see [`VfsState::unlink`].
+    fn remove_at(&self, res: Resource<Descriptor>, path: &str, directory: bool) -> FsResult<()> {
+        self.check_writable(path)?;
+        let desc = self.get_descriptor(res)?;
+        if !self.vfs_state.limits.allow_remove
+            || !desc.flags.contains(DescriptorFlags::MUTATE_DIRECTORY)
+        {
+            return Err(FsError::trap(ErrorCode::ReadOnly));
+        }
+
+        let (parent_node, name) = self.parent_node_and_name(Arc::clone(&desc.node), path)?;
+        if matches!(name.as_ref(), "." | "..") {
+            // Per POSIX: "If the path argument refers to a path whose final component is either dot or dot-dot,
+            // rmdir() shall fail."
+            return Err(FsError::trap(ErrorCode::Invalid));
+        }
+
+        let mut parent_guard = parent_node.write().unwrap();
+        let children = match &mut parent_guard.kind {
+            VfsNodeKind::Directory { children, .. } => children,
+            VfsNodeKind::File { .. } => {
+                return Err(FsError::trap(ErrorCode::NotDirectory));
+            }
+        };
+        let node = children
+            .get(&name)
+            .ok_or_else(|| FsError::trap(ErrorCode::NoEntry))?;
+
+        let node_guard = node.read().unwrap();
+        if node_guard.from_root_fs {
+            return Err(FsError::trap(ErrorCode::NotPermitted));
+        }
+        match (&node_guard.kind, directory) {
+            (VfsNodeKind::File { .. }, false) => {}
+            (VfsNodeKind::File { .. }, true) => {
+                return Err(FsError::trap(ErrorCode::NotDirectory));
+            }
+            (VfsNodeKind::Directory { children, .. }, true) => {
+                if !children.is_empty() {
+                    return Err(FsError::trap(ErrorCode::NotEmpty));
+                }
+            }
+            (VfsNodeKind::Directory { .. }, false) => {
+                return Err(FsError::trap(ErrorCode::IsDirectory));
+            }
+        }
+        drop(node_guard);
+
+        let removed = children.remove(&name).expect("checked above");
+        drop(parent_guard);
+        self.vfs_state.unlink(&removed, name.len());
+
+        Ok(())
+    }
+
+    /// Move node from one path to another.
+    ///
+    /// Only nodes that were created at runtime can be moved. An existing destination is replaced, which counts as a
+    /// [removal](VfsLimits::allow_remove).
+    fn rename(
+        &self,
+        old_base: SharedVfsNode,
+        old_path: &str,
+        new_base: SharedVfsNode,
+        new_path: &str,
+    ) -> FsResult<()> {
+        let (old_parent, old_name) = self.parent_node_and_name(old_base, old_path)?;
+        let (new_parent, new_name) = self.parent_node_and_name(new_base, new_path)?;
+        if matches!(old_name.as_ref(), "." | "..") || matches!(new_name.as_ref(), "." | "..") {
+            // Per POSIX: "If either pathname argument refers to a path whose final component is either dot or
+            // dot-dot, rename() shall fail."
+            return Err(FsError::trap(ErrorCode::Invalid));
+        }
+
+        let node =
+            child(&old_parent, &old_name)?.ok_or_else(|| FsError::trap(ErrorCode::NoEntry))?;
+        let node_guard = node.read().unwrap();
+        if node_guard.from_root_fs {
+            return Err(FsError::trap(ErrorCode::ReadOnly));
+        }
+        let is_dir = matches!(node_guard.kind, VfsNodeKind::Directory { .. });
+        drop(node_guard);
+
+        if is_dir {
+            // Per POSIX: "The new directory pathname contains a path prefix that names the old directory." -> EINVAL
+            let mut current = Some(Arc::clone(&new_parent));
+            while let Some(ancestor) = current {
+                if Arc::ptr_eq(&ancestor, &node) {
+                    return Err(FsError::trap(ErrorCode::Invalid));
+                }
+                current = ancestor
+                    .read()
+                    .unwrap()
+                    .parent
+                    .as_ref()
+                    .and_then(Weak::upgrade);
+            }
+        }
+
+        match child(&new_parent, &new_name)? {
+            None => {}
+            Some(existing) if Arc::ptr_eq(&existing, &node) => {
+                // Per POSIX: "If the old argument and the new argument resolve to [...] the same existing file,
+                // rename() shall return successfully and perform no other action."
+                return Ok(());
+            }
+            Some(existing) => {
+                let existing_guard = existing.read().unwrap();
+                if existing_guard.from_root_fs || !self.vfs_state.limits.allow_remove {
+                    return Err(FsError::trap(ErrorCode::ReadOnly));
+                }
+                match (&existing_guard.kind, is_dir) {
+                    (VfsNodeKind::File { .. }, false) => {}
+                    (VfsNodeKind::File { .. }, true) => {
+                        return Err(FsError::trap(ErrorCode::NotDirectory));
+                    }
+                    (VfsNodeKind::Directory { children, .. }, true) => {
+                        if !children.is_empty() {
+                            return Err(FsError::trap(ErrorCode::NotEmpty));
+                        }
+                    }
+                    (VfsNodeKind::Directory { .. }, false) => {
+                        return Err(FsError::trap(ErrorCode::IsDirectory));
+                    }
+                }
+            }
+        }
+
+        // Account for the new name BEFORE modifying anything, so that we can still bail out.
+        self.vfs_state
+            .limiter
+            .grow(new_name.len())
+            .map_err(|_| FsError::trap(ErrorCode::InsufficientMemory))?;
+
+        match &mut old_parent.write().unwrap().kind {
+            VfsNodeKind::Directory { children, .. } => {
+                children.remove(&old_name);
+            }
+            VfsNodeKind::File { .. } => unreachable!("checked above"),
+        }
+        let new_name_len = new_name.len();
+        let replaced = match &mut new_parent.write().unwrap().kind {
+            VfsNodeKind::Directory { children, .. } => children.insert(new_name, Arc::clone(&node)),
+            VfsNodeKind::File { .. } => unreachable!("checked above"),
+        };
+        node.write().unwrap().parent = Some(Arc::downgrade(&new_parent));
+
+        // the replaced node may still be open, see `VfsState::unlink`
+        if let Some(replaced) = replaced {
+            self.vfs_state.unlink(&replaced, new_name_len);
+        }
+        self.vfs_state
+            .limiter
+            .shrink(old_name.len())
+            .map_err(|_| FsError::trap(ErrorCode::InsufficientMemory))?;
+
+        Ok(())
+    }
+}
+
+/// Get child of a directory node.
+fn child(parent: &SharedVfsNode, name: &PathSegment) -> FsResult<Option<SharedVfsNode>> {
+    match &parent.read().unwrap().kind {
+        VfsNodeKind::Directory { children, .. } => Ok(children.get(name).map(Arc::clone)),
+        VfsNodeKind::File { .. } => Err(FsError::trap(ErrorCode::NotDirectory)),
+    }
 }
 
 impl<'a> filesystem::types::HostDescriptor for VfsCtxView<'a> {
@@ -489,6 +922,7 @@ impl<'a> filesystem::types::HostDescriptor for VfsCtxView<'a> {
 
         let node = Arc::clone(&desc.node);
         let limiter = self.vfs_state.limiter.clone();
+        let observer = self.vfs_state.observer.clone();
 
         match &node.read().unwrap().kind {
             VfsNodeKind::File { .. } => {
@@ -496,6 +930,7 @@ impl<'a> filesystem::types::HostDescriptor for VfsCtxView<'a> {
                     node: Arc::clone(&node),
                     offset,
                     limiter,
+                    observer,
                 };
                 let stream: Box<dyn WasiOutputStream> = Box::new(stream);
                 let res = self
@@ -609,14 +1044,18 @@ impl<'a> filesystem::types::HostDescriptor for VfsCtxView<'a> {
             };
         }
 
-        perform_write(&node, offset as usize, &buffer, &self.vfs_state.limiter)
+        let nbyte = perform_write(&node, offset as usize, &buffer, &self.vfs_state.limiter)?;
+        self.vfs_state.observer.vfs_write(nbyte);
+        Ok(nbyte)
     }
 
     async fn read_directory(
         &mut self,
         self_: Resource<Descriptor>,
     ) -> FsResult<Resource<DirectoryEntryStream>> {
-        match &self.node(self_)?.read().unwrap().kind {
+        let node = self.node(self_)?;
+        self.vfs_state.materialize(&node)?;
+        match &node.read().unwrap().kind {
             VfsNodeKind::Directory { children, .. } => {
                 let mut entries = children
                     .iter()
@@ -658,6 +1097,7 @@ impl<'a> filesystem::types::HostDescriptor for VfsCtxView<'a> {
         self_: Resource<Descriptor>,
         path: String,
     ) -> FsResult<()> {
+        self.check_writable(&path)?;
         let desc = self.get_descriptor(self_)?;
         if !desc.flags.contains(DescriptorFlags::MUTATE_DIRECTORY) {
             return Err(FsError::trap(ErrorCode::ReadOnly));
@@ -668,8 +1108,11 @@ impl<'a> filesystem::types::HostDescriptor for VfsCtxView<'a> {
         let new_dir = Arc::new(RwLock::new(VfsNode {
             kind: VfsNodeKind::Directory {
                 children: HashMap::new(),
+                shared: None,
             },
             parent: Some(Arc::downgrade(&parent_node)),
+            from_root_fs: false,
+            unlinked: None,
         }));
 
         self.vfs_state
@@ -689,7 +1132,7 @@ impl<'a> filesystem::types::HostDescriptor for VfsCtxView<'a> {
             VfsNodeKind::File { .. } => {
                 return Err(FsError::trap(ErrorCode::NotDirectory));
             }
-            VfsNodeKind::Directory { children } => match children.entry(name) {
+            VfsNodeKind::Directory { children, .. } => match children.entry(name) {
                 Entry::Vacant(entry) => {
                     entry.insert(new_dir);
                 }
@@ -703,7 +1146,9 @@ impl<'a> filesystem::types::HostDescriptor for VfsCtxView<'a> {
     }
 
     async fn stat(&mut self, self_: Resource<Descriptor>) -> FsResult<DescriptorStat> {
-        Ok(self.node(self_)?.read().unwrap().stat())
+        let node = self.node(self_)?;
+        self.vfs_state.materialize(&node)?;
+        Ok(node.read().unwrap().stat())
     }
 
     async fn stat_at(
@@ -716,6 +1161,7 @@ impl<'a> filesystem::types::HostDescriptor for VfsCtxView<'a> {
             Some(node) => node,
             None => return Err(FsError::trap(ErrorCode::NoEntry)),
         };
+        self.vfs_state.materialize(&node)?;
 
         Ok(node.read().unwrap().stat())
     }
@@ -758,6 +1204,9 @@ impl<'a> filesystem::types::HostDescriptor for VfsCtxView<'a> {
         let directory = open_flags.contains(OpenFlags::DIRECTORY);
         let exclusive = open_flags.contains(OpenFlags::EXCLUSIVE);
         let truncate = open_flags.contains(OpenFlags::TRUNCATE);
+        if create || truncate || flags.contains(DescriptorFlags::WRITE) {
+            self.check_writable(&path)?;
+        }
 
         // Try to resolve the path to an existing node
         let existing = self
@@ -809,9 +1258,9 @@ impl<'a> filesystem::types::HostDescriptor for VfsCtxView<'a> {
                         if flags.contains(DescriptorFlags::WRITE) {
                             self.vfs_state
                                 .limiter
-                                .shrink(content.capacity())
+                                .shrink(content.accounted_bytes())
                                 .map_err(|_| FsError::trap(ErrorCode::InsufficientMemory))?;
-                            *content = Vec::new();
+                            *content = FileContent::default();
                         }
                     }
                     VfsNodeKind::Directory { .. } => {
@@ -843,9 +1292,11 @@ impl<'a> filesystem::types::HostDescriptor for VfsCtxView<'a> {
 
                 let new_file = Arc::new(RwLock::new(VfsNode {
                     kind: VfsNodeKind::File {
-                        content: Vec::new(),
+                        content: FileContent::default(),
                     },
                     parent: Some(Arc::downgrade(&parent_node)),
+                    from_root_fs: false,
+                    unlinked: None,
                 }));
 
                 // Insert the new file into the parent directory
@@ -856,7 +1307,7 @@ impl<'a> filesystem::types::HostDescriptor for VfsCtxView<'a> {
                         // existing file that is neither a directory nor a symbolic link to a directory"
                         return Err(FsError::trap(ErrorCode::NotDirectory));
                     }
-                    VfsNodeKind::Directory { children } => {
+                    VfsNodeKind::Directory { children, .. } => {
                         let growth = name.len() + std::mem::size_of_val(&new_file);
                         match children.entry(name) {
                             Entry::Vacant(entry) => {
@@ -906,20 +1357,33 @@ impl<'a> filesystem::types::HostDescriptor for VfsCtxView<'a> {
 
     async fn remove_directory_at(
         &mut self,
-        _self_: Resource<Descriptor>,
-        _path: String,
+        self_: Resource<Descriptor>,
+        path: String,
     ) -> FsResult<()> {
-        Err(FsError::trap(ErrorCode::ReadOnly))
+        self.remove_at(self_, &path, true)
     }
 
     async fn rename_at(
         &mut self,
-        _self_: Resource<Descriptor>,
-        _old_path: String,
-        _new_descriptor: Resource<Descriptor>,
-        _new_path: String,
+        self_: Resource<Descriptor>,
+        old_path: String,
+        new_descriptor: Resource<Descriptor>,
+        new_path: String,
     ) -> FsResult<()> {
-        Err(FsError::trap(ErrorCode::ReadOnly))
+        self.check_writable(&old_path)?;
+        let old_desc = self.get_descriptor(self_)?;
+        let old_base = Arc::clone(&old_desc.node);
+        let old_flags = old_desc.flags;
+        let new_desc = self.get_descriptor(new_descriptor)?;
+        let new_base = Arc::clone(&new_desc.node);
+        let new_flags = new_desc.flags;
+        if !old_flags.contains(DescriptorFlags::MUTATE_DIRECTORY)
+            || !new_flags.contains(DescriptorFlags::MUTATE_DIRECTORY)
+        {
+            return Err(FsError::trap(ErrorCode::ReadOnly));
+        }
+
+        self.rename(old_base, &old_path, new_base, &new_path)
     }
 
     async fn symlink_at(
@@ -931,12 +1395,8 @@ impl<'a> filesystem::types::HostDescriptor for VfsCtxView<'a> {
         Err(FsError::trap(ErrorCode::ReadOnly))
     }
 
-    async fn unlink_file_at(
-        &mut self,
-        _self_: Resource<Descriptor>,
-        _path: String,
-    ) -> FsResult<()> {
-        Err(FsError::trap(ErrorCode::ReadOnly))
+    async fn unlink_file_at(&mut self, self_: Resource<Descriptor>, path: String) -> FsResult<()> {
+        self.remove_at(self_, &path, false)
     }
 
     async fn is_same_object(
@@ -950,8 +1410,9 @@ impl<'a> filesystem::types::HostDescriptor for VfsCtxView<'a> {
     }
 
     async fn metadata_hash(&mut self, self_: Resource<Descriptor>) -> FsResult<MetadataHashValue> {
-        Ok(self
-            .node(self_)?
+        let node = self.node(self_)?;
+        self.vfs_state.materialize(&node)?;
+        Ok(node
             .read()
             .unwrap()
             .metadata_hash(&self.vfs_state.metadata_hash_key))
@@ -967,6 +1428,7 @@ impl<'a> filesystem::types::HostDescriptor for VfsCtxView<'a> {
             Some(node) => node,
             None => return Err(FsError::trap(ErrorCode::NoEntry)),
         };
+        self.vfs_state.materialize(&node)?;
 
         Ok(node
             .read()
@@ -1045,6 +1507,7 @@ fn perform_write(
     let mut guard = node.write().unwrap();
     match &mut guard.kind {
         VfsNodeKind::File { content } => {
+            let content = content.make_mut(limiter)?;
             let nbyte = buffer.len();
             let new_end = offset.saturating_add(nbyte);
             let old_len = content.len();
@@ -1109,7 +1572,7 @@ mod tests {
     use wasmtime_wasi::p2::bindings::filesystem::types::HostDescriptor;
 
     use super::*;
-    use crate::limiter::{Limiter, StaticResourceLimits};
+    use crate::limiter::{DynamicMemoryLimits, Limiter, StaticResourceLimits};
 
     /// Parameters for creating a test VFS context.
     struct VfsTestParams {
@@ -1123,6 +1586,10 @@ mod tests {
         memory_pool_bytes: Option<usize>,
         /// Static resource limits for the limiter.
         static_limits: StaticResourceLimits,
+        /// Allow removal of files and directories.
+        allow_remove: bool,
+        /// Deny all modifications.
+        read_only: bool,
     }
 
     impl Default for VfsTestParams {
@@ -1133,6 +1600,8 @@ mod tests {
                 max_path_segment_size: 100,
                 memory_pool_bytes: None,
                 static_limits: StaticResourceLimits::default(),
+                allow_remove: false,
+                read_only: false,
             }
         }
     }
@@ -1144,7 +1613,19 @@ mod tests {
             self
         }
 
-        /// Create params with a specific memory pool size.
+        /// Create params that allow removal of files and directories.
+        fn with_allow_remove(mut self) -> Self {
+            self.allow_remove = true;
+            self
+        }
+
+        /// Create params that deny all modifications.
+        fn with_read_only(mut self) -> Self {
+            self.read_only = true;
+            self
+        }
+
+        /// Create params with a specific memory pool size.
         fn with_memory_pool_bytes(mut self, bytes: usize) -> Self {
             self.memory_pool_bytes = Some(bytes);
             self
@@ -1168,6 +1649,8 @@ mod tests {
                 inodes: self.inodes,
                 max_path_length: self.max_path_length,
                 max_path_segment_size: self.max_path_segment_size,
+                allow_remove: self.allow_remove,
+                read_only: self.read_only,
             };
 
             let pool: Arc<dyn MemoryPool> = match self.memory_pool_bytes {
@@ -1175,8 +1658,8 @@ mod tests {
                 None => Arc::new(UnboundedMemoryPool::default()),
             };
 
-            let limiter = Limiter::new(self.static_limits, &pool);
-            let vfs_state = VfsState::new(limits, limiter);
+            let limiter = Limiter::new(self.static_limits, DynamicMemoryLimits::default(), &pool);
+            let vfs_state = VfsState::new(limits, limiter, Observer::default());
             let table = ResourceTable::new();
             (table, vfs_state)
         }
@@ -1197,7 +1680,7 @@ mod tests {
         let guard = node.read().unwrap();
         match &guard.kind {
             VfsNodeKind::File { content } => {
-                assert_eq!(content.as_slice(), expected, "File content mismatch");
+                assert_eq!(&content[..], expected, "File content mismatch");
             }
             VfsNodeKind::Directory { .. } => {
                 panic!("Expected file, got directory");
@@ -1242,7 +1725,7 @@ mod tests {
             ctx,
             DescriptorFlags::READ | DescriptorFlags::MUTATE_DIRECTORY,
         );
-        let _ = ctx
+        let file_desc = ctx
             .open_at(
                 desc,
                 PathFlags::empty(),
@@ -1252,6 +1735,27 @@ mod tests {
             )
             .await
             .expect("file creation should succeed");
+        HostDescriptor::drop(ctx, file_desc).unwrap();
+    }
+
+    /// Helper to open -- and create if needed -- a file for writing.
+    async fn open_test_file_for_write(
+        ctx: &mut VfsCtxView<'_>,
+        name: &str,
+    ) -> Resource<Descriptor> {
+        let desc = create_test_descriptor(
+            ctx,
+            DescriptorFlags::READ | DescriptorFlags::WRITE | DescriptorFlags::MUTATE_DIRECTORY,
+        );
+        ctx.open_at(
+            desc,
+            PathFlags::empty(),
+            name.to_string(),
+            OpenFlags::CREATE,
+            DescriptorFlags::READ | DescriptorFlags::WRITE,
+        )
+        .await
+        .expect("opening file should succeed")
     }
 
     /// Helper to create a directory in the VFS for testing.
@@ -1292,7 +1796,7 @@ mod tests {
             let node = node.unwrap();
             let mut guard = node.write().unwrap();
             if let VfsNodeKind::File { content: c } = &mut guard.kind {
-                *c = content;
+                *c = content.into();
             }
             drop(guard);
             node
@@ -1301,6 +1805,45 @@ mod tests {
 
     // ==================== create_directory_at tests ====================
 
+    #[tokio::test]
+    async fn test_read_only_denies_modifications() {
+        let (mut table, mut vfs_state) = VfsTestParams::default().with_read_only().build();
+        let mut ctx = VfsCtxView {
+            table: &mut table,
+            vfs_state: &mut vfs_state,
+        };
+        let flags =
+            DescriptorFlags::READ | DescriptorFlags::WRITE | DescriptorFlags::MUTATE_DIRECTORY;
+
+        let desc = create_test_descriptor(&mut ctx, flags);
+        let result = ctx.create_directory_at(desc, "testdir".to_string()).await;
+        assert_error_code(result, ErrorCode::ReadOnly);
+
+        let desc = create_test_descriptor(&mut ctx, flags);
+        let result = ctx
+            .open_at(
+                desc,
+                PathFlags::empty(),
+                "testfile".to_string(),
+                OpenFlags::CREATE,
+                flags,
+            )
+            .await;
+        assert_error_code(result, ErrorCode::ReadOnly);
+
+        // reading is fine
+        let desc = create_test_descriptor(&mut ctx, flags);
+        ctx.open_at(
+            desc,
+            PathFlags::empty(),
+            ".".to_string(),
+            OpenFlags::DIRECTORY,
+            DescriptorFlags::READ,
+        )
+        .await
+        .unwrap();
+    }
+
     #[tokio::test]
     async fn test_create_directory_readonly_descriptor_fails() {
         let (mut table, mut vfs_state) = VfsTestParams::default().build();
@@ -2932,4 +3475,721 @@ mod tests {
         let node = ctx.node_at(desc, "testfile").unwrap().unwrap();
         assert_file_content(&node, &[1, 2, 10, 11, 12]);
     }
+
+    // ==================== unlink_file_at / remove_directory_at tests ====================
+
+    #[tokio::test]
+    async fn test_unlink_file_disabled_fails() {
+        let (mut table, mut vfs_state) = VfsTestParams::default().build();
+        let mut ctx = VfsCtxView {
+            table: &mut table,
+            vfs_state: &mut vfs_state,
+        };
+
+        create_test_file_via_open(&mut ctx, "afile").await;
+
+        let desc = create_test_descriptor(
+            &mut ctx,
+            DescriptorFlags::READ | DescriptorFlags::MUTATE_DIRECTORY,
+        );
+        let result = ctx.unlink_file_at(desc, "afile".to_string()).await;
+        assert_error_code(result, ErrorCode::ReadOnly);
+    }
+
+    #[tokio::test]
+    async fn test_unlink_file_readonly_descriptor_fails() {
+        let (mut table, mut vfs_state) = VfsTestParams::default().with_allow_remove().build();
+        let mut ctx = VfsCtxView {
+            table: &mut table,
+            vfs_state: &mut vfs_state,
+        };
+
+        create_test_file_via_open(&mut ctx, "afile").await;
+
+        let desc = create_test_descriptor(&mut ctx, DescriptorFlags::READ);
+        let result = ctx.unlink_file_at(desc, "afile".to_string()).await;
+        assert_error_code(result, ErrorCode::ReadOnly);
+    }
+
+    #[tokio::test]
+    async fn test_unlink_file_releases_inode() {
+        let (mut table, mut vfs_state) = VfsTestParams::default()
+            .with_inodes(1)
+            .with_allow_remove()
+            .build();
+        let mut ctx = VfsCtxView {
+            table: &mut table,
+            vfs_state: &mut vfs_state,
+        };
+
+        let desc = create_test_descriptor(
+            &mut ctx,
+            DescriptorFlags::READ | DescriptorFlags::WRITE | DescriptorFlags::MUTATE_DIRECTORY,
+        );
+        let file_desc = ctx
+            .open_at(
+                desc,
+                PathFlags::empty(),
+                "afile".to_string(),
+                OpenFlags::CREATE,
+                DescriptorFlags::READ | DescriptorFlags::WRITE,
+            )
+            .await
+            .unwrap();
+        ctx.write(Resource::new_borrow(file_desc.rep()), vec![1, 2, 3], 0)
+            .await
+            .unwrap();
+
+        let desc = create_test_descriptor(
+            &mut ctx,
+            DescriptorFlags::READ | DescriptorFlags::MUTATE_DIRECTORY,
+        );
+        ctx.unlink_file_at(desc, "afile".to_string()).await.unwrap();
+
+        let desc = create_test_descriptor(&mut ctx, DescriptorFlags::READ);
+        assert!(ctx.node_at(desc, "afile").unwrap().is_none());
+
+        // inode is released once the last descriptor is closed
+        assert_eq!(ctx.vfs_state.inodes_allocation.n.load(Ordering::SeqCst), 1);
+        HostDescriptor::drop(&mut ctx, file_desc).unwrap();
+        assert_eq!(ctx.vfs_state.inodes_allocation.n.load(Ordering::SeqCst), 0);
+
+        // inode can be reused
+        create_test_file_via_open(&mut ctx, "bfile").await;
+    }
+
+    #[tokio::test]
+    async fn test_write_after_unlink_is_limited() {
+        let (mut table, mut vfs_state) = VfsTestParams::default()
+            .with_memory_pool_bytes(1_000)
+            .with_allow_remove()
+            .build();
+        let mut ctx = VfsCtxView {
+            table: &mut table,
+            vfs_state: &mut vfs_state,
+        };
+
+        // create, write, and unlink the same file over and over again, but keep the descriptors open
+        let mut open = vec![];
+        let result = loop {
+            assert!(open.len() < 10, "writes to removed files are not limited");
+
+            let file_desc = open_test_file_for_write(&mut ctx, "afile").await;
+            let result = ctx
+                .write(Resource::new_borrow(file_desc.rep()), vec![0; 300], 0)
+                .await;
+            open.push(file_desc);
+            if result.is_err() {
+                break result;
+            }
+
+            let desc = create_test_descriptor(
+                &mut ctx,
+                DescriptorFlags::READ | DescriptorFlags::MUTATE_DIRECTORY,
+            );
+            ctx.unlink_file_at(desc, "afile".to_string()).await.unwrap();
+        };
+        assert_error_code(result, ErrorCode::InsufficientMemory);
+        assert_eq!(ctx.vfs_state.inodes(), open.len() as u64);
+
+        // closing the descriptors releases the removed files
+        for file_desc in open {
+            HostDescriptor::drop(&mut ctx, file_desc).unwrap();
+        }
+        assert_eq!(ctx.vfs_state.inodes(), 1);
+        let file_desc = open_test_file_for_write(&mut ctx, "afile").await;
+        ctx.write(file_desc, vec![0; 300], 0).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_unlink_file_on_directory_fails() {
+        let (mut table, mut vfs_state) = VfsTestParams::default().with_allow_remove().build();
+        let mut ctx = VfsCtxView {
+            table: &mut table,
+            vfs_state: &mut vfs_state,
+        };
+
+        create_test_directory(&mut ctx, "adir").await;
+
+        let desc = create_test_descriptor(
+            &mut ctx,
+            DescriptorFlags::READ | DescriptorFlags::MUTATE_DIRECTORY,
+        );
+        let result = ctx.unlink_file_at(desc, "adir".to_string()).await;
+        assert_error_code(result, ErrorCode::IsDirectory);
+    }
+
+    #[tokio::test]
+    async fn test_unlink_file_nonexistent_fails() {
+        let (mut table, mut vfs_state) = VfsTestParams::default().with_allow_remove().build();
+        let mut ctx = VfsCtxView {
+            table: &mut table,
+            vfs_state: &mut vfs_state,
+        };
+
+        let desc = create_test_descriptor(
+            &mut ctx,
+            DescriptorFlags::READ | DescriptorFlags::MUTATE_DIRECTORY,
+        );
+        let result = ctx.unlink_file_at(desc, "nonexistent".to_string()).await;
+        assert_error_code(result, ErrorCode::NoEntry);
+    }
+
+    #[tokio::test]
+    async fn test_remove_directory_success() {
+        let (mut table, mut vfs_state) = VfsTestParams::default().with_allow_remove().build();
+        let mut ctx = VfsCtxView {
+            table: &mut table,
+            vfs_state: &mut vfs_state,
+        };
+
+        create_test_directory(&mut ctx, "adir").await;
+
+        let desc = create_test_descriptor(
+            &mut ctx,
+            DescriptorFlags::READ | DescriptorFlags::MUTATE_DIRECTORY,
+        );
+        ctx.remove_directory_at(desc, "adir".to_string())
+            .await
+            .unwrap();
+        assert_eq!(ctx.vfs_state.inodes_allocation.n.load(Ordering::SeqCst), 0);
+
+        let desc = create_test_descriptor(&mut ctx, DescriptorFlags::READ);
+        assert!(ctx.node_at(desc, "adir").unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_remove_directory_not_empty_fails() {
+        let (mut table, mut vfs_state) = VfsTestParams::default().with_allow_remove().build();
+        let mut ctx = VfsCtxView {
+            table: &mut table,
+            vfs_state: &mut vfs_state,
+        };
+
+        create_test_directory(&mut ctx, "adir").await;
+        create_test_file_via_open(&mut ctx, "adir/afile").await;
+
+        let desc = create_test_descriptor(
+            &mut ctx,
+            DescriptorFlags::READ | DescriptorFlags::MUTATE_DIRECTORY,
+        );
+        let result = ctx.remove_directory_at(desc, "adir".to_string()).await;
+        assert_error_code(result, ErrorCode::NotEmpty);
+    }
+
+    #[tokio::test]
+    async fn test_remove_directory_on_file_fails() {
+        let (mut table, mut vfs_state) = VfsTestParams::default().with_allow_remove().build();
+        let mut ctx = VfsCtxView {
+            table: &mut table,
+            vfs_state: &mut vfs_state,
+        };
+
+        create_test_file_via_open(&mut ctx, "afile").await;
+
+        let desc = create_test_descriptor(
+            &mut ctx,
+            DescriptorFlags::READ | DescriptorFlags::MUTATE_DIRECTORY,
+        );
+        let result = ctx.remove_directory_at(desc, "afile".to_string()).await;
+        assert_error_code(result, ErrorCode::NotDirectory);
+    }
+
+    #[tokio::test]
+    async fn test_remove_directory_dot_fails() {
+        let (mut table, mut vfs_state) = VfsTestParams::default().with_allow_remove().build();
+        let mut ctx = VfsCtxView {
+            table: &mut table,
+            vfs_state: &mut vfs_state,
+        };
+
+        create_test_directory(&mut ctx, "adir").await;
+
+        let desc = create_test_descriptor(
+            &mut ctx,
+            DescriptorFlags::READ | DescriptorFlags::MUTATE_DIRECTORY,
+        );
+        let result = ctx.remove_directory_at(desc, "adir/.".to_string()).await;
+        assert_error_code(result, ErrorCode::Invalid);
+    }
+
+    #[tokio::test]
+    async fn test_traverse_up_from_removed_directory_fails() {
+        let (mut table, mut vfs_state) = VfsTestParams::default().with_allow_remove().build();
+        let mut ctx = VfsCtxView {
+            table: &mut table,
+            vfs_state: &mut vfs_state,
+        };
+
+        create_test_directory(&mut ctx, "a").await;
+        create_test_directory(&mut ctx, "a/b").await;
+
+        let desc = create_test_descriptor(&mut ctx, DescriptorFlags::READ);
+        let dir_desc = ctx
+            .open_at(
+                desc,
+                PathFlags::empty(),
+                "a/b".to_string(),
+                OpenFlags::DIRECTORY,
+                DescriptorFlags::READ,
+            )
+            .await
+            .unwrap();
+
+        for path in ["a/b", "a"] {
+            let desc = create_test_descriptor(
+                &mut ctx,
+                DescriptorFlags::READ | DescriptorFlags::MUTATE_DIRECTORY,
+            );
+            ctx.remove_directory_at(desc, path.to_string())
+                .await
+                .unwrap();
+        }
+
+        let result = ctx
+            .stat_at(dir_desc, PathFlags::empty(), "..".to_string())
+            .await;
+        assert_error_code(result, ErrorCode::NoEntry);
+    }
+
+    // ==================== rename_at tests ====================
+
+    /// Rename relative to the root directory.
+    async fn rename(ctx: &mut VfsCtxView<'_>, old_path: &str, new_path: &str) -> FsResult<()> {
+        let flags = DescriptorFlags::READ | DescriptorFlags::MUTATE_DIRECTORY;
+        let old_desc = create_test_descriptor(ctx, flags);
+        let new_desc = create_test_descriptor(ctx, flags);
+        ctx.rename_at(
+            old_desc,
+            old_path.to_string(),
+            new_desc,
+            new_path.to_string(),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_rename_file_success() {
+        let (mut table, mut vfs_state) = VfsTestParams::default().build();
+        let mut ctx = VfsCtxView {
+            table: &mut table,
+            vfs_state: &mut vfs_state,
+        };
+
+        create_test_file_via_open(&mut ctx, "foo.tmp").await;
+        rename(&mut ctx, "foo.tmp", "foo").await.unwrap();
+
+        let desc = create_test_descriptor(&mut ctx, DescriptorFlags::READ);
+        assert!(ctx.node_at(desc, "foo.tmp").unwrap().is_none());
+        let desc = create_test_descriptor(&mut ctx, DescriptorFlags::READ);
+        assert_empty_file(&ctx.node_at(desc, "foo").unwrap().unwrap());
+        assert_eq!(ctx.vfs_state.inodes_allocation.n.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_rename_readonly_descriptor_fails() {
+        let (mut table, mut vfs_state) = VfsTestParams::default().build();
+        let mut ctx = VfsCtxView {
+            table: &mut table,
+            vfs_state: &mut vfs_state,
+        };
+
+        create_test_file_via_open(&mut ctx, "foo.tmp").await;
+
+        let old_desc = create_test_descriptor(&mut ctx, DescriptorFlags::READ);
+        let new_desc = create_test_descriptor(&mut ctx, DescriptorFlags::READ);
+        let result = ctx
+            .rename_at(old_desc, "foo.tmp".to_string(), new_desc, "foo".to_string())
+            .await;
+        assert_error_code(result, ErrorCode::ReadOnly);
+    }
+
+    #[tokio::test]
+    async fn test_rename_nonexistent_fails() {
+        let (mut table, mut vfs_state) = VfsTestParams::default().build();
+        let mut ctx = VfsCtxView {
+            table: &mut table,
+            vfs_state: &mut vfs_state,
+        };
+
+        let result = rename(&mut ctx, "nonexistent", "foo").await;
+        assert_error_code(result, ErrorCode::NoEntry);
+    }
+
+    #[tokio::test]
+    async fn test_rename_directory_across_directories_updates_parent() {
+        let (mut table, mut vfs_state) = VfsTestParams::default().build();
+        let mut ctx = VfsCtxView {
+            table: &mut table,
+            vfs_state: &mut vfs_state,
+        };
+
+        create_test_directory(&mut ctx, "a").await;
+        create_test_directory(&mut ctx, "b").await;
+        create_test_directory(&mut ctx, "a/dir").await;
+        create_test_file_via_open(&mut ctx, "a/dir/file").await;
+
+        rename(&mut ctx, "a/dir", "b/moved").await.unwrap();
+
+        let desc = create_test_descriptor(&mut ctx, DescriptorFlags::READ);
+        assert!(ctx.node_at(desc, "a/dir").unwrap().is_none());
+        let desc = create_test_descriptor(&mut ctx, DescriptorFlags::READ);
+        assert_empty_file(&ctx.node_at(desc, "b/moved/file").unwrap().unwrap());
+
+        // `..` follows the new parent
+        let desc = create_test_descriptor(&mut ctx, DescriptorFlags::READ);
+        let b = ctx.node_at(desc, "b").unwrap().unwrap();
+        let desc = create_test_descriptor(&mut ctx, DescriptorFlags::READ);
+        let parent = ctx.node_at(desc, "b/moved/..").unwrap().unwrap();
+        assert!(Arc::ptr_eq(&b, &parent));
+    }
+
+    #[tokio::test]
+    async fn test_rename_directory_into_itself_fails() {
+        let (mut table, mut vfs_state) = VfsTestParams::default().build();
+        let mut ctx = VfsCtxView {
+            table: &mut table,
+            vfs_state: &mut vfs_state,
+        };
+
+        create_test_directory(&mut ctx, "a").await;
+        create_test_directory(&mut ctx, "a/b").await;
+
+        let result = rename(&mut ctx, "a", "a/b/c").await;
+        assert_error_code(result, ErrorCode::Invalid);
+    }
+
+    #[tokio::test]
+    async fn test_rename_replace_without_allow_remove_fails() {
+        let (mut table, mut vfs_state) = VfsTestParams::default().build();
+        let mut ctx = VfsCtxView {
+            table: &mut table,
+            vfs_state: &mut vfs_state,
+        };
+
+        create_test_file_via_open(&mut ctx, "foo.tmp").await;
+        create_test_file_via_open(&mut ctx, "foo").await;
+
+        let result = rename(&mut ctx, "foo.tmp", "foo").await;
+        assert_error_code(result, ErrorCode::ReadOnly);
+    }
+
+    #[tokio::test]
+    async fn test_rename_replace_file_releases_inode() {
+        let (mut table, mut vfs_state) = VfsTestParams::default().with_allow_remove().build();
+        let mut ctx = VfsCtxView {
+            table: &mut table,
+            vfs_state: &mut vfs_state,
+        };
+
+        create_test_file_via_open(&mut ctx, "foo.tmp").await;
+        create_test_file_via_open(&mut ctx, "foo").await;
+        assert_eq!(ctx.vfs_state.inodes_allocation.n.load(Ordering::SeqCst), 2);
+
+        rename(&mut ctx, "foo.tmp", "foo").await.unwrap();
+        assert_eq!(ctx.vfs_state.inodes_allocation.n.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_rename_replace_open_file_is_limited() {
+        let (mut table, mut vfs_state) = VfsTestParams::default()
+            .with_memory_pool_bytes(1_000)
+            .with_allow_remove()
+            .build();
+        let mut ctx = VfsCtxView {
+            table: &mut table,
+            vfs_state: &mut vfs_state,
+        };
+
+        let file_desc = open_test_file_for_write(&mut ctx, "foo").await;
+        ctx.write(Resource::new_borrow(file_desc.rep()), vec![0; 600], 0)
+            .await
+            .unwrap();
+
+        create_test_file_via_open(&mut ctx, "foo.tmp").await;
+        rename(&mut ctx, "foo.tmp", "foo").await.unwrap();
+        assert_eq!(ctx.vfs_state.inodes(), 2);
+
+        // the replaced file is still open and its content is still accounted
+        let result = ctx
+            .write(Resource::new_borrow(file_desc.rep()), vec![0; 600], 600)
+            .await;
+        assert_error_code(result, ErrorCode::InsufficientMemory);
+
+        HostDescriptor::drop(&mut ctx, file_desc).unwrap();
+        assert_eq!(ctx.vfs_state.inodes(), 1);
+        let file_desc = open_test_file_for_write(&mut ctx, "foo").await;
+        ctx.write(file_desc, vec![0; 600], 0).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_rename_file_over_directory_fails() {
+        let (mut table, mut vfs_state) = VfsTestParams::default().with_allow_remove().build();
+        let mut ctx = VfsCtxView {
+            table: &mut table,
+            vfs_state: &mut vfs_state,
+        };
+
+        create_test_file_via_open(&mut ctx, "foo").await;
+        create_test_directory(&mut ctx, "adir").await;
+
+        let result = rename(&mut ctx, "foo", "adir").await;
+        assert_error_code(result, ErrorCode::IsDirectory);
+    }
+
+    // ==================== shared root filesystem tests ====================
+
+    /// Build a TAR archive with a `lib` directory that contains a single file.
+    fn test_root_fs(limits: &VfsLimits) -> RootFs {
+        let mut builder = tar::Builder::new(Vec::new());
+
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Directory);
+        header.set_size(0);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "lib", std::io::empty())
+            .unwrap();
+
+        let mut header = tar::Header::new_gnu();
+        header.set_size(3);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "lib/file", &[1u8, 2, 3][..])
+            .unwrap();
+
+        let tar = builder.into_inner().unwrap();
+        RootFsNode::from_tar(&tar, limits).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_populate_shares_content_until_write() {
+        let (mut table, mut vfs_state) = VfsTestParams::default().build();
+        let root_fs = test_root_fs(&vfs_state.limits);
+        vfs_state.populate(&root_fs).unwrap();
+        let mut ctx = VfsCtxView {
+            table: &mut table,
+            vfs_state: &mut vfs_state,
+        };
+
+        let desc = create_test_descriptor(&mut ctx, DescriptorFlags::READ);
+        let node = ctx.node_at(desc, "/lib/file").unwrap().unwrap();
+        assert_file_content(&node, &[1, 2, 3]);
+        assert!(matches!(
+            &node.read().unwrap().kind,
+            VfsNodeKind::File {
+                content: FileContent::Shared(_)
+            },
+        ));
+
+        let desc = create_test_descriptor(
+            &mut ctx,
+            DescriptorFlags::READ | DescriptorFlags::WRITE | DescriptorFlags::MUTATE_DIRECTORY,
+        );
+        let file_desc = ctx
+            .open_at(
+                desc,
+                PathFlags::empty(),
+                "lib/file".to_string(),
+                OpenFlags::empty(),
+                DescriptorFlags::READ | DescriptorFlags::WRITE,
+            )
+            .await
+            .unwrap();
+        ctx.write(file_desc, vec![10], 1).await.unwrap();
+
+        assert_file_content(&node, &[1, 10, 3]);
+        assert!(matches!(
+            &node.read().unwrap().kind,
+            VfsNodeKind::File {
+                content: FileContent::Owned(_)
+            },
+        ));
+
+        // other instances are NOT affected
+        let (_table, vfs_state) = VfsTestParams::default().build();
+        vfs_state.populate(&root_fs).unwrap();
+        let RootFsNode::Directory { children } = &root_fs.root["lib"] else {
+            panic!("lib is a directory");
+        };
+        let RootFsNode::File { content } = &children["file"] else {
+            panic!("file is a file");
+        };
+        assert_eq!(&content[..], &[1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_remove_root_fs_node_fails() {
+        let (mut table, mut vfs_state) = VfsTestParams::default().with_allow_remove().build();
+        let root_fs = test_root_fs(&vfs_state.limits);
+        vfs_state.populate(&root_fs).unwrap();
+        let mut ctx = VfsCtxView {
+            table: &mut table,
+            vfs_state: &mut vfs_state,
+        };
+
+        let desc = create_test_descriptor(
+            &mut ctx,
+            DescriptorFlags::READ | DescriptorFlags::MUTATE_DIRECTORY,
+        );
+        let result = ctx.unlink_file_at(desc, "lib/file".to_string()).await;
+        assert_error_code(result, ErrorCode::NotPermitted);
+
+        // files created at runtime within root FS directories can be removed
+        create_test_file_via_open(&mut ctx, "lib/tmp").await;
+        let desc = create_test_descriptor(
+            &mut ctx,
+            DescriptorFlags::READ | DescriptorFlags::MUTATE_DIRECTORY,
+        );
+        ctx.unlink_file_at(desc, "lib/tmp".to_string())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_rename_root_fs_node_fails() {
+        let (mut table, mut vfs_state) = VfsTestParams::default().build();
+        let root_fs = test_root_fs(&vfs_state.limits);
+        vfs_state.populate(&root_fs).unwrap();
+        let mut ctx = VfsCtxView {
+            table: &mut table,
+            vfs_state: &mut vfs_state,
+        };
+
+        let result = rename(&mut ctx, "lib/file", "lib/other").await;
+        assert_error_code(result, ErrorCode::ReadOnly);
+
+        // files created at runtime can be moved into root FS directories
+        create_test_file_via_open(&mut ctx, "tmp").await;
+        rename(&mut ctx, "tmp", "lib/tmp").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_populate_overlay() {
+        let (mut table, mut vfs_state) = VfsTestParams::default().build();
+        let root_fs = test_root_fs(&vfs_state.limits);
+        vfs_state.populate(&root_fs).unwrap();
+
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Directory);
+        header.set_size(0);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "lib", std::io::empty())
+            .unwrap();
+        let mut header = tar::Header::new_gnu();
+        header.set_size(1);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "lib/other", &[4u8][..])
+            .unwrap();
+        let tar = builder.into_inner().unwrap();
+        let overlay = RootFsNode::from_tar(&tar, &vfs_state.limits).unwrap();
+        vfs_state.populate(&overlay).unwrap();
+        assert_eq!(vfs_state.inodes(), 3);
+
+        // files cannot be replaced
+        let err = vfs_state.populate(&root_fs).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::AlreadyExists);
+
+        let mut ctx = VfsCtxView {
+            table: &mut table,
+            vfs_state: &mut vfs_state,
+        };
+        let desc = create_test_descriptor(&mut ctx, DescriptorFlags::READ);
+        let node = ctx.node_at(desc, "/lib/file").unwrap().unwrap();
+        assert_file_content(&node, &[1, 2, 3]);
+        let desc = create_test_descriptor(&mut ctx, DescriptorFlags::READ);
+        let node = ctx.node_at(desc, "/lib/other").unwrap().unwrap();
+        assert_file_content(&node, &[4]);
+    }
+
+    #[tokio::test]
+    async fn test_populate_insufficient_inodes_fails() {
+        let (_table, vfs_state) = VfsTestParams::default().with_inodes(1).build();
+        let root_fs = test_root_fs(&VfsLimits::default());
+        let err = vfs_state.populate(&root_fs).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::QuotaExceeded);
+    }
+
+    #[test]
+    fn test_root_fs_from_tar_too_many_inodes_fails() {
+        let limits = VfsLimits {
+            inodes: 1,
+            ..Default::default()
+        };
+        let mut builder = tar::Builder::new(Vec::new());
+        for name in ["a", "b"] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(0);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, name, std::io::empty())
+                .unwrap();
+        }
+        let tar = builder.into_inner().unwrap();
+
+        let err = RootFsNode::from_tar(&tar, &limits).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::QuotaExceeded);
+    }
+
+    /// Count nodes that are allocated within the VFS, including the given one.
+    fn allocated_nodes(node: &SharedVfsNode) -> usize {
+        match &node.read().unwrap().kind {
+            VfsNodeKind::File { .. } => 1,
+            VfsNodeKind::Directory { children, .. } => {
+                1 + children.values().map(allocated_nodes).sum::<usize>()
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_populate_allocates_nodes_lazily() {
+        let mut builder = tar::Builder::new(Vec::new());
+        for d in 0..100 {
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(tar::EntryType::Directory);
+            header.set_size(0);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, format!("d{d}"), std::io::empty())
+                .unwrap();
+            for f in 0..10 {
+                let mut header = tar::Header::new_gnu();
+                header.set_size(1);
+                header.set_cksum();
+                builder
+                    .append_data(&mut header, format!("d{d}/f{f}"), &[f][..])
+                    .unwrap();
+            }
+        }
+        let tar = builder.into_inner().unwrap();
+
+        let (mut table, mut vfs_state) = VfsTestParams::default().with_inodes(10_000).build();
+        let root_fs = RootFsNode::from_tar(&tar, &vfs_state.limits).unwrap();
+        vfs_state.populate(&root_fs).unwrap();
+
+        // the whole tree is accounted for, but nothing is allocated yet
+        assert_eq!(vfs_state.inodes(), 1_100);
+        assert_eq!(allocated_nodes(&vfs_state.root), 1);
+
+        let mut ctx = VfsCtxView {
+            table: &mut table,
+            vfs_state: &mut vfs_state,
+        };
+        let desc = create_test_descriptor(&mut ctx, DescriptorFlags::READ);
+        let node = ctx.node_at(desc, "/d42/f7").unwrap().unwrap();
+        assert_file_content(&node, &[7]);
+
+        // only the accessed directories are allocated: root + 100 directories + 10 files in `d42`
+        assert_eq!(allocated_nodes(&ctx.vfs_state.root), 111);
+        assert_eq!(ctx.vfs_state.inodes(), 1_100);
+
+        // the tree itself is shared, not copied
+        let RootFsNode::Directory { children } = &root_fs.root["d0"] else {
+            panic!("d0 is a directory");
+        };
+        assert_eq!(Arc::strong_count(children), 2);
+    }
 }
@@ -0,0 +1,289 @@
+//! Immutable root filesystem that is shared between VFS instances.
+
+use std::{
+    collections::{BTreeMap, btree_map::Entry},
+    io::{Error, ErrorKind, Read},
+    ops::{Add, AddAssign},
+    path::Component,
+    sync::Arc,
+};
+
+use crate::{
+    error::LimitExceeded,
+    vfs::{SharedVfsNode, limits::VfsLimits, path::PathSegment},
+};
+
+/// Children of a [directory](RootFsNode::Directory), indexed by name.
+///
+/// This is reference-counted so that VFS instances can point to a directory without copying it, see
+/// [`VfsState::populate`](super::VfsState::populate).
+pub(crate) type RootFsDir = Arc<BTreeMap<Box<str>, RootFsNode>>;
+
+/// Node of the immutable root filesystem tree.
+///
+/// This is parsed once per component and then shared with every [`VfsState`](super::VfsState). Nodes are only
+/// allocated within a VFS instance when the guest accesses the directory that contains them, and file content is
+/// shared between all instances until it is modified.
+#[derive(Debug, Clone)]
+pub(crate) enum RootFsNode {
+    /// A regular file.
+    File {
+        /// File content.
+        content: Arc<[u8]>,
+    },
+
+    /// A directory.
+    Directory {
+        /// Child nodes indexed by name.
+        children: RootFsDir,
+    },
+}
+
+/// Immutable root filesystem.
+#[derive(Debug, Clone)]
+pub(crate) struct RootFs {
+    /// Content of the root directory.
+    pub(crate) root: RootFsDir,
+
+    /// Resources that a VFS instance accounts for the whole tree.
+    pub(crate) usage: RootFsUsage,
+}
+
+/// Resources that are accounted for root filesystem nodes.
+///
+/// This is accounted for when a VFS is [populated](super::VfsState::populate), independently of whether the nodes were
+/// accessed yet, so that the limits of an instance do not depend on the access pattern of the guest.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct RootFsUsage {
+    /// Number of inodes.
+    pub(crate) inodes: u64,
+
+    /// Bytes for names and nodes.
+    pub(crate) bytes: usize,
+}
+
+impl RootFsUsage {
+    /// Usage of a single node, including its children.
+    fn node(name: &str, node: &RootFsNode) -> Self {
+        let usage = Self {
+            inodes: 1,
+            bytes: name.len() + std::mem::size_of::<SharedVfsNode>(),
+        };
+        match node {
+            RootFsNode::File { .. } => usage,
+            RootFsNode::Directory { children } => usage + Self::dir(children),
+        }
+    }
+
+    /// Usage of the given directory content.
+    fn dir(children: &BTreeMap<Box<str>, RootFsNode>) -> Self {
+        children
+            .iter()
+            .map(|(name, node)| Self::node(name, node))
+            .fold(Self::default(), Add::add)
+    }
+}
+
+impl Add for RootFsUsage {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self {
+            inodes: self.inodes + rhs.inodes,
+            bytes: self.bytes + rhs.bytes,
+        }
+    }
+}
+
+impl AddAssign for RootFsUsage {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl RootFs {
+    /// Overlay `other` on top of `self`.
+    ///
+    /// Directories that exist in both filesystems are merged, while files that exist in both -- or that collide with a
+    /// directory -- result in an error. Only the directories along the merged paths are copied, everything else is
+    /// shared with the inputs.
+    ///
+    /// Returns the merged filesystem as well as the usage that `other` added.
+    pub(crate) fn overlay(&self, other: &Self) -> std::io::Result<(Self, RootFsUsage)> {
+        let (root, added) = merge(&self.root, &other.root)?;
+        let merged = Self {
+            root,
+            usage: self.usage + added,
+        };
+        Ok((merged, added))
+    }
+}
+
+/// Merge directory content, see [`RootFs::overlay`].
+fn merge(base: &RootFsDir, other: &RootFsDir) -> std::io::Result<(RootFsDir, RootFsUsage)> {
+    let mut merged = BTreeMap::clone(base);
+    let mut added = RootFsUsage::default();
+
+    for (name, node) in other.iter() {
+        match merged.entry(name.clone()) {
+            Entry::Vacant(entry) => {
+                added += RootFsUsage::node(name, node);
+                entry.insert(node.clone());
+            }
+            Entry::Occupied(mut entry) => match (entry.get_mut(), node) {
+                (
+                    RootFsNode::Directory { children },
+                    RootFsNode::Directory {
+                        children: other_children,
+                    },
+                ) => {
+                    let (children_merged, children_added) = merge(children, other_children)?;
+                    *children = children_merged;
+                    added += children_added;
+                }
+                _ => {
+                    return Err(Error::new(
+                        ErrorKind::AlreadyExists,
+                        format!("root filesystem entry already exists: {name}"),
+                    ));
+                }
+            },
+        }
+    }
+
+    Ok((Arc::new(merged), added))
+}
+
+impl RootFsNode {
+    /// Parse TAR archive.
+    ///
+    /// The number of entries is limited by [`VfsLimits::inodes`] and path segments are limited by
+    /// [`VfsLimits::max_path_segment_size`].
+    pub(crate) fn from_tar(data: &[u8], limits: &VfsLimits) -> std::io::Result<RootFs> {
+        let mut root = Self::Directory {
+            children: RootFsDir::default(),
+        };
+        let mut n_inodes = 0u64;
+
+        let mut archive = tar::Archive::new(data);
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let path = entry.path()?.into_owned();
+            let path_display = path.display().to_string();
+
+            n_inodes += 1;
+            if n_inodes > limits.inodes {
+                return Err(LimitExceeded {
+                    name: "inodes",
+                    limit: limits.inodes,
+                    current: n_inodes - 1,
+                    requested: 1,
+                }
+                .into());
+            }
+
+            let mut segments = vec![];
+            for component in path.components() {
+                match component {
+                    Component::Normal(s) => {
+                        let s = s.to_str().ok_or_else(|| {
+                            Error::new(
+                                ErrorKind::InvalidFilename,
+                                format!("TAR path is not valid UTF-8: {path_display}"),
+                            )
+                        })?;
+                        if s.contains('\0') {
+                            return Err(Error::new(
+                                ErrorKind::InvalidFilename,
+                                format!("TAR path contains NULL byte: {path_display}"),
+                            ));
+                        }
+                        PathSegment::new(s, limits)?;
+                        segments.push(s);
+                    }
+                    Component::RootDir | Component::CurDir => {}
+                    Component::ParentDir | Component::Prefix(_) => {
+                        return Err(Error::new(
+                            ErrorKind::InvalidFilename,
+                            format!("TAR path MUST be normalized: {path_display}"),
+                        ));
+                    }
+                }
+            }
+            let Some((name, parents)) = segments.split_last() else {
+                return Err(Error::new(
+                    ErrorKind::InvalidFilename,
+                    format!("TAR target MUST end in a valid filename: {path_display}"),
+                ));
+            };
+
+            let node = match entry.header().entry_type() {
+                tar::EntryType::Directory => Self::Directory {
+                    children: RootFsDir::default(),
+                },
+                tar::EntryType::Regular => {
+                    let mut content = Vec::with_capacity(entry.size().try_into().unwrap_or(0));
+                    entry.read_to_end(&mut content)?;
+                    Self::File {
+                        content: content.into(),
+                    }
+                }
+                other => {
+                    return Err(Error::new(
+                        ErrorKind::Unsupported,
+                        format!("Unsupported TAR content: {other:?} @ {path_display}"),
+                    ));
+                }
+            };
+
+            match root.directory_mut(parents)?.entry((*name).into()) {
+                Entry::Vacant(entry) => {
+                    entry.insert(node);
+                }
+                Entry::Occupied(_) => {
+                    return Err(Error::new(
+                        ErrorKind::AlreadyExists,
+                        format!("duplicate TAR entry: {path_display}"),
+                    ));
+                }
+            }
+        }
+
+        let Self::Directory { children: root } = root else {
+            unreachable!("root is a directory");
+        };
+        let usage = RootFsUsage::dir(&root);
+        Ok(RootFs { root, usage })
+    }
+
+    /// Get children of the directory at the given path.
+    fn directory_mut(&mut self, path: &[&str]) -> std::io::Result<&mut BTreeMap<Box<str>, Self>> {
+        let mut current = self;
+        for segment in path {
+            current = match current {
+                Self::Directory { children } => {
+                    Arc::make_mut(children).get_mut(*segment).ok_or_else(|| {
+                        Error::new(
+                            ErrorKind::NotFound,
+                            format!("parent directory missing in TAR: {}", path.join("/")),
+                        )
+                    })?
+                }
+                Self::File { .. } => {
+                    return Err(Error::new(
+                        ErrorKind::NotADirectory,
+                        format!("TAR parent is not a directory: {}", path.join("/")),
+                    ));
+                }
+            };
+        }
+
+        match current {
+            Self::Directory { children } => Ok(Arc::make_mut(children)),
+            Self::File { .. } => Err(Error::new(
+                ErrorKind::NotADirectory,
+                format!("TAR parent is not a directory: {}", path.join("/")),
+            )),
+        }
+    }
+}
@@ -0,0 +1,41 @@
+//! Enforcement of UDF [volatility](datafusion_expr::Volatility).
+
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
+
+/// Flag that is set while the guest executes an [immutable](datafusion_expr::Volatility::Immutable) UDF.
+///
+/// While set, the guest cannot observe anything that may change between two invocations with the same input:
+///
+/// - **clocks:** time stands still
+/// - **randomness:** access is denied
+/// - **HTTP:** requests are denied
+///
+/// This is shared between the different host interfaces of a single WASM VM.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ImmutableFlag(Arc<AtomicBool>);
+
+impl ImmutableFlag {
+    /// Check if the flag is set.
+    pub(crate) fn is_set(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    /// Set flag until the returned guard is dropped.
+    pub(crate) fn set(&self) -> ImmutableFlagGuard {
+        self.0.store(true, Ordering::SeqCst);
+        ImmutableFlagGuard(self.clone())
+    }
+}
+
+/// Guard returned by [`ImmutableFlag::set`].
+#[derive(Debug)]
+pub(crate) struct ImmutableFlagGuard(ImmutableFlag);
+
+impl Drop for ImmutableFlagGuard {
+    fn drop(&mut self) {
+        (self.0).0.store(false, Ordering::SeqCst);
+    }
+}
@@ -0,0 +1,197 @@
+//! Pool of pre-instantiated guests.
+
+use std::sync::{Arc, Mutex};
+
+use datafusion_common::Result as DataFusionResult;
+use datafusion_execution::memory_pool::MemoryPool;
+use tokio::runtime::Handle;
+
+use crate::{
+    WasmComponentPrecompiled, WasmPermissions, WasmScalarUdf, component::WasmComponentInstance,
+};
+
+/// Mutable state of [`WarmPool`].
+#[derive(Debug, Default)]
+struct State {
+    /// Blank guests that are ready to be used.
+    instances: Vec<WasmComponentInstance>,
+
+    /// Number of guests that are currently being created.
+    pending: usize,
+}
+
+/// Shared data of [`WarmPool`].
+#[derive(Debug)]
+struct Inner {
+    /// Component that is instantiated.
+    component: WasmComponentPrecompiled,
+
+    /// Permissions of the guests.
+    permissions: WasmPermissions,
+
+    /// I/O runtime of the guests.
+    io_rt: Handle,
+
+    /// Memory pool of the guests.
+    memory_pool: Arc<dyn MemoryPool>,
+
+    /// Number of guests that are kept warm.
+    size: usize,
+
+    /// Mutable state.
+    state: Mutex<State>,
+}
+
+impl Inner {
+    /// Lock state.
+    fn state_guard(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().expect("warm pool lock poisoned")
+    }
+
+    /// Reserve slot for a new guest.
+    ///
+    /// Returns [`None`] if the pool is already full, counting the guests that are currently being created.
+    fn reserve(&self) -> Option<Reservation<'_>> {
+        let mut state = self.state_guard();
+        if state.instances.len() + state.pending < self.size {
+            state.pending += 1;
+            Some(Reservation { inner: self })
+        } else {
+            None
+        }
+    }
+
+    /// Create guests until the pool is full.
+    async fn fill(&self) -> DataFusionResult<()> {
+        while let Some(reservation) = self.reserve() {
+            let instance = WasmComponentInstance::new(
+                &self.component,
+                &self.permissions,
+                self.io_rt.clone(),
+                &self.memory_pool,
+            )
+            .await?;
+            reservation.fulfill(instance);
+        }
+        Ok(())
+    }
+}
+
+/// Slot for a guest that is currently being created.
+///
+/// The slot is released when this is dropped, also if the creation failed or was cancelled.
+#[derive(Debug)]
+struct Reservation<'a> {
+    /// Pool.
+    inner: &'a Inner,
+}
+
+impl Reservation<'_> {
+    /// Add created guest to the pool.
+    fn fulfill(self, instance: WasmComponentInstance) {
+        self.inner.state_guard().instances.push(instance);
+    }
+}
+
+impl Drop for Reservation<'_> {
+    fn drop(&mut self) {
+        self.inner.state_guard().pending -= 1;
+    }
+}
+
+/// Keeps blank guests around, so that creating UDFs does not have to wait for the instantiation.
+///
+/// Instantiating a guest dominates the time it takes to create UDFs, especially for Python. The pool creates guests in
+/// advance -- with their root filesystem populated and, if the guest was pre-initialized, their interpreter ready --
+/// and binds the source code to one of them when [UDFs are created](Self::create_udfs). Guests that were handed out are
+/// replaced in the background using the I/O runtime.
+///
+/// Every guest is only used once, so UDFs created from the pool do NOT share state with each other, same as for
+/// [`WasmScalarUdf::new`]. Note that guests in the pool hold their memory -- which is accounted in the memory pool --
+/// and keep an [epoch timer](WasmPermissions::with_epoch_tick_time) running.
+///
+/// Cloning is cheap, clones share the guests.
+#[derive(Debug, Clone)]
+pub struct WarmPool {
+    /// Shared data.
+    inner: Arc<Inner>,
+}
+
+impl WarmPool {
+    /// Create new pool that keeps `size` guests warm.
+    ///
+    /// The pool starts empty, use [`fill`](Self::fill) to create the guests.
+    pub fn new(
+        component: &WasmComponentPrecompiled,
+        permissions: &WasmPermissions,
+        io_rt: Handle,
+        memory_pool: &Arc<dyn MemoryPool>,
+        size: usize,
+    ) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                component: component.clone(),
+                permissions: permissions.clone(),
+                io_rt,
+                memory_pool: Arc::clone(memory_pool),
+                size,
+                state: Mutex::default(),
+            }),
+        }
+    }
+
+    /// Create guests until the pool is full.
+    ///
+    /// Call this at startup. Guests that are created concurrently -- e.g. by a background refill -- are not awaited.
+    pub async fn fill(&self) -> DataFusionResult<()> {
+        self.inner.fill().await
+    }
+
+    /// Number of guests that are ready to be used.
+    pub fn available(&self) -> usize {
+        self.inner.state_guard().instances.len()
+    }
+
+    /// Create UDFs from source code, see [`WasmScalarUdf::new`].
+    ///
+    /// This uses a guest from the pool. If the pool is empty, a new guest is created, same as for
+    /// [`WasmScalarUdf::new`]. Either way, the pool is refilled in the background.
+    pub async fn create_udfs(&self, source: String) -> DataFusionResult<Vec<WasmScalarUdf>> {
+        let instance = self.inner.state_guard().instances.pop();
+        self.refill();
+
+        let Inner {
+            component,
+            permissions,
+            io_rt,
+            memory_pool,
+            ..
+        } = self.inner.as_ref();
+        let instance = match instance {
+            Some(instance) => instance,
+            None => {
+                WasmComponentInstance::new(component, permissions, io_rt.clone(), memory_pool)
+                    .await?
+            }
+        };
+        WasmScalarUdf::from_instance(
+            instance,
+            component,
+            permissions,
+            io_rt.clone(),
+            memory_pool,
+            source,
+        )
+        .await
+    }
+
+    /// Refill pool in the background.
+    fn refill(&self) {
+        let inner = Arc::clone(&self.inner);
+        self.inner.io_rt.spawn(async move {
+            if let Err(e) = inner.fill().await {
+                log::warn!("cannot refill warm pool: {e}");
+            }
+        });
+    }
+}
@@ -3,13 +3,55 @@ use std::sync::Arc;
 use arrow::datatypes::{DataType, Field};
 use datafusion_common::config::ConfigOptions;
 use datafusion_expr::{ScalarFunctionArgs, ScalarUDFImpl, async_udf::AsyncScalarUDFImpl};
-use datafusion_udf_wasm_host::{TrustedDataLimits, WasmPermissions};
+use datafusion_udf_wasm_host::{TrustedDataLimits, UdfNameCollisionPolicy, WasmPermissions};
 
 use crate::integration_tests::{
-    evil::test_utils::{try_scalar_udfs, try_scalar_udfs_with_env},
+    evil::test_utils::{
+        try_scalar_udfs, try_scalar_udfs_with_env, try_scalar_udfs_with_permissions,
+    },
     test_utils::FullError,
 };
 
+#[tokio::test(flavor = "multi_thread")]
+async fn test_coerce_types_dt_depth() {
+    let err = run_coerce_types_udf("dt_depth").await;
+
+    insta::assert_snapshot!(
+        err,
+        @r"
+    coerced type 0
+    caused by
+    field
+    caused by
+    field data type
+    caused by
+    field
+    caused by
+    field data type
+    caused by
+    field
+    caused by
+    field data type
+    caused by
+    field
+    caused by
+    field data type
+    caused by
+    Resources exhausted: data structure depth: limit=10
+    ",
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_coerce_types_too_many() {
+    let err = run_coerce_types_udf("too_many").await;
+
+    insta::assert_snapshot!(
+        err,
+        @"Error during planning: `too_many` coerced 1 argument types to 2",
+    );
+}
+
 #[tokio::test]
 async fn test_err_long_ctx() {
     let err = run_err_udf(
@@ -46,16 +88,72 @@ async fn test_err_long_msg() {
     );
 }
 
+#[tokio::test]
+async fn test_err_ansi() {
+    let err = run_err_udf("ansi", 0).await;
+
+    insta::assert_snapshot!(
+        err,
+        @r"
+    ctx
+    caused by
+    Execution error: redtxet
+    fake log line
+    ",
+    );
+}
+
+#[tokio::test]
+async fn test_err_large_ctx() {
+    let limit = TrustedDataLimits::default().max_error_size;
+    let err = run_err_udf("large_ctx", limit).await;
+
+    insta::assert_snapshot!(
+        err.to_string().replace(&"x".repeat(limit / 4), "<chunk>"),
+        @r"
+    <chunk>
+    caused by
+    <chunk>
+    caused by
+    <chunk>
+    caused by
+    [error context truncated: entries=5]
+    caused by
+    Execution error: foo
+    ",
+    );
+}
+
 #[tokio::test]
 async fn test_err_nested_ctx() {
-    let err = run_err_udf("nested_ctx", TrustedDataLimits::default().max_depth as _).await;
+    let err = run_err_udf(
+        "nested_ctx",
+        TrustedDataLimits::default().max_error_context_depth,
+    )
+    .await;
 
     insta::assert_snapshot!(
         err,
         @r"
-    convert error from WASI
+    bar
     caused by
-    Resources exhausted: data structure depth: limit=10
+    bar
+    caused by
+    bar
+    caused by
+    bar
+    caused by
+    bar
+    caused by
+    bar
+    caused by
+    bar
+    caused by
+    bar
+    caused by
+    [error context truncated: entries=1]
+    caused by
+    Execution error: foo
     ",
     );
 }
@@ -231,6 +329,34 @@ async fn test_return_type_field_md_items() {
     );
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn test_return_type_timestamp_tz_length() {
+    let err = run_return_type_udf("timestamp_tz_length").await;
+
+    insta::assert_snapshot!(
+        err,
+        @r"
+    time zone
+    caused by
+    Resources exhausted: identifier length: got=51, limit=50
+    ",
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_return_type_timestamp_tz_chars() {
+    let err = run_return_type_udf("timestamp_tz_chars").await;
+
+    insta::assert_snapshot!(
+        err,
+        @r#"
+    time zone
+    caused by
+    External error: invalid time zone: "Europe/Berlin\nUTC"
+    "#,
+    );
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn test_return_value_dt_depth_array() {
     let err = run_return_value_udf("dt_depth_array").await;
@@ -310,6 +436,59 @@ async fn test_udf_long_name() {
     ");
 }
 
+#[tokio::test]
+async fn test_udf_unsafe_name() {
+    let err = try_scalar_udfs("complex::udf_unsafe_name")
+        .await
+        .unwrap_err();
+
+    insta::assert_snapshot!(
+        err,
+        @r#"
+    UDF name
+    caused by
+    External error: identifier contains unsafe characters: "\u{1b}[2Kudf"
+    "#);
+}
+
+#[tokio::test]
+async fn test_udfs_colliding_names() {
+    let err = try_scalar_udfs_with_env("complex::udfs_colliding_names", &[("names", "foo,Foo")])
+        .await
+        .unwrap_err();
+    insta::assert_snapshot!(
+        err,
+        @"External error: UDF name 'Foo' collides with 'foo'");
+
+    let err =
+        try_scalar_udfs_with_env("complex::udfs_colliding_names", &[("names", "ff,\u{fb00}")])
+            .await
+            .unwrap_err();
+    insta::assert_snapshot!(
+        err,
+        @"External error: UDF name 'ﬀ' collides with 'ff'");
+
+    let udfs = try_scalar_udfs_with_permissions(
+        "complex::udfs_colliding_names",
+        WasmPermissions::new()
+            .with_env("names".to_owned(), "ff,\u{fb00}".to_owned())
+            .with_udf_name_collision_policy(UdfNameCollisionPolicy::CaseInsensitive),
+    )
+    .await
+    .unwrap();
+    assert_eq!(udfs.len(), 2);
+
+    let udfs = try_scalar_udfs_with_permissions(
+        "complex::udfs_colliding_names",
+        WasmPermissions::new()
+            .with_env("names".to_owned(), "foo,Foo".to_owned())
+            .with_udf_name_collision_policy(UdfNameCollisionPolicy::Exact),
+    )
+    .await
+    .unwrap();
+    assert_eq!(udfs.len(), 2);
+}
+
 #[tokio::test]
 async fn test_udfs_duplicate_names() {
     let err = try_scalar_udfs("complex::udfs_duplicate_names")
@@ -364,6 +543,7 @@ async fn run_return_type_udf(name: &'static str) -> FullError {
         max_aux_string_length,
         max_depth,
         max_complexity,
+        ..
     } = TrustedDataLimits::default();
 
     let udf = try_scalar_udfs_with_env(
@@ -385,6 +565,25 @@ async fn run_return_type_udf(name: &'static str) -> FullError {
     FullError::new(err)
 }
 
+/// Test UDF related to coerced argument types.
+async fn run_coerce_types_udf(name: &'static str) -> FullError {
+    let udf = try_scalar_udfs_with_env(
+        "complex::coerce_types",
+        &[(
+            "max_depth",
+            &TrustedDataLimits::default().max_depth.to_string(),
+        )],
+    )
+    .await
+    .unwrap()
+    .into_iter()
+    .find(|udf| udf.name() == name)
+    .unwrap();
+
+    let err = udf.coerce_types(&[DataType::Int32]).unwrap_err();
+    FullError::new(err)
+}
+
 /// Test UDF related to return values.
 async fn run_return_value_udf(name: &'static str) -> FullError {
     let TrustedDataLimits {
@@ -392,6 +591,7 @@ async fn run_return_value_udf(name: &'static str) -> FullError {
         max_aux_string_length,
         max_depth,
         max_complexity,
+        ..
     } = TrustedDataLimits::default();
 
     let udf = try_scalar_udfs_with_env(
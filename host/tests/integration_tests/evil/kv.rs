@@ -0,0 +1,185 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use arrow::{
+    array::AsArray,
+    datatypes::{DataType, Field},
+};
+use datafusion_common::{Result as DataFusionResult, ScalarValue, config::ConfigOptions, exec_err};
+use datafusion_expr::{
+    ColumnarValue, ScalarFunctionArgs, ScalarUDFImpl, async_udf::AsyncScalarUDFImpl,
+};
+use datafusion_udf_wasm_host::{KvBackend, KvConfig, KvLimits, WasmPermissions, WasmScalarUdf};
+use wasmtime_wasi::async_trait;
+
+use crate::integration_tests::{
+    evil::test_utils::try_scalar_udfs_with_permissions, test_utils::ColumnarValueExt,
+};
+
+#[tokio::test]
+async fn test_denied_by_default() {
+    let udfs = udfs(WasmPermissions::new()).await;
+
+    insta::assert_snapshot!(
+        call(&udfs, "kv_get", "foo").await,
+        @"ERR: Execution error: access to key-value store denied",
+    );
+    insta::assert_snapshot!(
+        call(&udfs, "kv_set", "foo=bar").await,
+        @"ERR: Execution error: access to key-value store denied",
+    );
+    insta::assert_snapshot!(
+        call(&udfs, "kv_delete", "foo").await,
+        @"ERR: Execution error: access to key-value store denied",
+    );
+}
+
+#[tokio::test]
+async fn test_roundtrip() {
+    let udfs = udfs_with_kv(KvConfig::default()).await;
+
+    assert_eq!(call(&udfs, "kv_get", "foo").await, "OK: <missing>");
+    assert_eq!(call(&udfs, "kv_set", "foo=bar").await, "OK: set");
+    assert_eq!(call(&udfs, "kv_get", "foo").await, "OK: bar");
+    assert_eq!(call(&udfs, "kv_set", "foo=baz").await, "OK: set");
+    assert_eq!(call(&udfs, "kv_get", "foo").await, "OK: baz");
+    assert_eq!(call(&udfs, "kv_delete", "foo").await, "OK: true");
+    assert_eq!(call(&udfs, "kv_delete", "foo").await, "OK: false");
+    assert_eq!(call(&udfs, "kv_get", "foo").await, "OK: <missing>");
+}
+
+#[tokio::test]
+async fn test_persists_across_invocations() {
+    let udfs = udfs_with_kv(KvConfig::default()).await;
+
+    assert_eq!(call(&udfs, "kv_incr", "n").await, "OK: 1");
+    assert_eq!(call(&udfs, "kv_incr", "n").await, "OK: 2");
+    assert_eq!(call(&udfs, "kv_incr", "n").await, "OK: 3");
+    assert_eq!(call(&udfs, "kv_incr", "m").await, "OK: 1");
+
+    // stores are NOT shared between instances
+    let other = udfs_with_kv(KvConfig::default()).await;
+    assert_eq!(call(&other, "kv_get", "n").await, "OK: <missing>");
+}
+
+#[tokio::test]
+async fn test_limits() {
+    let udfs = udfs_with_kv(KvConfig::new(KvLimits {
+        max_key_bytes: 3,
+        max_value_bytes: 4,
+        max_entries: 2,
+        max_total_bytes: 9,
+    }))
+    .await;
+
+    insta::assert_snapshot!(
+        call(&udfs, "kv_get", "long").await,
+        @"ERR: Resources exhausted: key-value store: key too large: got=4, limit=3",
+    );
+    insta::assert_snapshot!(
+        call(&udfs, "kv_set", "a=large").await,
+        @"ERR: Resources exhausted: key-value store: value too large: got=5, limit=4",
+    );
+
+    assert_eq!(call(&udfs, "kv_set", "a=1").await, "OK: set");
+    assert_eq!(call(&udfs, "kv_set", "b=2").await, "OK: set");
+    insta::assert_snapshot!(
+        call(&udfs, "kv_set", "c=3").await,
+        @"ERR: Resources exhausted: key-value store: too many entries: limit=2",
+    );
+
+    // replacing existing entries is fine as long as the total size fits
+    assert_eq!(call(&udfs, "kv_set", "a=1234").await, "OK: set");
+    insta::assert_snapshot!(
+        call(&udfs, "kv_set", "b=1234").await,
+        @"ERR: Resources exhausted: key-value store: store too large: got=10, limit=9",
+    );
+
+    // deleting frees up space
+    assert_eq!(call(&udfs, "kv_delete", "a").await, "OK: true");
+    assert_eq!(call(&udfs, "kv_set", "c=3").await, "OK: set");
+}
+
+#[tokio::test]
+async fn test_backend() {
+    let backend = Arc::new(MemoryBackend::default());
+    let config = KvConfig::default().with_backend(Arc::clone(&backend) as _);
+
+    let udfs_1 = udfs_with_kv(config.clone()).await;
+    let udfs_2 = udfs_with_kv(config).await;
+
+    assert_eq!(call(&udfs_1, "kv_set", "foo=bar").await, "OK: set");
+    assert_eq!(call(&udfs_2, "kv_get", "foo").await, "OK: bar");
+    assert_eq!(
+        backend.entries.lock().unwrap().get(b"foo".as_slice()),
+        Some(&b"bar".to_vec()),
+    );
+
+    insta::assert_snapshot!(
+        call(&udfs_1, "kv_set", "fail=1").await,
+        @"ERR: External error: Execution error: backend failure",
+    );
+}
+
+/// Backend that stores entries in memory and fails for the `fail` key.
+#[derive(Debug, Default)]
+struct MemoryBackend {
+    /// Entries.
+    entries: Mutex<HashMap<Vec<u8>, Vec<u8>>>,
+}
+
+#[async_trait]
+impl KvBackend for MemoryBackend {
+    async fn get(&self, key: &[u8]) -> DataFusionResult<Option<Vec<u8>>> {
+        Ok(self.entries.lock().unwrap().get(key).cloned())
+    }
+
+    async fn set(&self, key: Vec<u8>, value: Vec<u8>) -> DataFusionResult<()> {
+        if key == b"fail" {
+            return exec_err!("backend failure");
+        }
+        self.entries.lock().unwrap().insert(key, value);
+        Ok(())
+    }
+
+    async fn delete(&self, key: &[u8]) -> DataFusionResult<bool> {
+        Ok(self.entries.lock().unwrap().remove(key).is_some())
+    }
+}
+
+/// Get evil UDFs.
+async fn udfs(permissions: WasmPermissions) -> Vec<WasmScalarUdf> {
+    try_scalar_udfs_with_permissions("kv", permissions)
+        .await
+        .unwrap()
+}
+
+/// Get evil UDFs with access to the key-value store.
+async fn udfs_with_kv(config: KvConfig) -> Vec<WasmScalarUdf> {
+    udfs(WasmPermissions::new().with_kv(config)).await
+}
+
+/// Call UDF that expects one string input.
+async fn call(udfs: &[WasmScalarUdf], name: &str, input: &str) -> String {
+    let udf = udfs
+        .iter()
+        .find(|udf| udf.name() == name)
+        .expect("UDF exists");
+
+    let array = udf
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![ColumnarValue::Scalar(ScalarValue::Utf8(Some(
+                input.to_owned(),
+            )))],
+            arg_fields: vec![Arc::new(Field::new("a", DataType::Utf8, true))],
+            number_rows: 1,
+            return_field: Arc::new(Field::new("r", DataType::Utf8, true)),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+        .unwrap()
+        .unwrap_array();
+    array.as_string::<i32>().value(0).to_owned()
+}
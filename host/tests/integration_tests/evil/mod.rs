@@ -1,8 +1,10 @@
 mod complex;
 mod env;
 mod fs;
+mod kv;
 mod net;
 mod return_data;
 mod runtime;
+mod secrets;
 mod spin;
 mod test_utils;
@@ -2,31 +2,98 @@ use std::sync::Arc;
 
 use arrow::datatypes::{DataType, Field};
 use datafusion_common::config::ConfigOptions;
-use datafusion_expr::{ScalarFunctionArgs, async_udf::AsyncScalarUDFImpl};
+use datafusion_expr::{ScalarFunctionArgs, ScalarUDFImpl, async_udf::AsyncScalarUDFImpl};
+use datafusion_udf_wasm_host::{TrustedDataLimits, WasmPermissions, WasmScalarUdf};
 
-use crate::integration_tests::evil::test_utils::try_scalar_udfs;
+use crate::integration_tests::evil::test_utils::{
+    try_scalar_udfs, try_scalar_udfs_with_permissions,
+};
 
 #[tokio::test]
 async fn test_wrong_number_of_rows() {
-    let [udf] = try_scalar_udfs("return_data")
-        .await
-        .unwrap()
-        .try_into()
-        .unwrap();
-
-    let err = udf
-        .invoke_async_with_args(ScalarFunctionArgs {
-            args: vec![],
-            arg_fields: vec![],
-            number_rows: 42,
-            return_field: Arc::new(Field::new("r", DataType::Utf8, true)),
-            config_options: Arc::new(ConfigOptions::default()),
-        })
-        .await
-        .unwrap_err();
+    let udf = find_udf(
+        try_scalar_udfs("return_data").await.unwrap(),
+        "wrong-number-of-rows",
+    );
+
+    let err = invoke(&udf, 42).await.unwrap_err();
 
     insta::assert_snapshot!(
         err,
         @"External error: UDF returned array of length 43 but should produce 42 rows",
     );
 }
+
+#[tokio::test]
+async fn test_long_value() {
+    // default limits are fine
+    let udf = find_udf(try_scalar_udfs("return_data").await.unwrap(), "long-value");
+    invoke(&udf, 3).await.unwrap();
+
+    let udf = udf_with_limits(TrustedDataLimits {
+        max_array_value_length: 999,
+        ..Default::default()
+    })
+    .await;
+    let err = invoke(&udf, 3).await.unwrap_err();
+
+    insta::assert_snapshot!(
+        err,
+        @r"
+    array
+    caused by
+    Resources exhausted: array value length: got=1000, limit=999
+    ",
+    );
+}
+
+#[tokio::test]
+async fn test_array_bytes() {
+    let udf = udf_with_limits(TrustedDataLimits {
+        max_array_bytes: 2_000,
+        ..Default::default()
+    })
+    .await;
+
+    invoke(&udf, 1).await.unwrap();
+    let err = invoke(&udf, 3).await.unwrap_err().to_string();
+    // the exact size depends on the IPC encoding
+    assert!(
+        err.starts_with("array\ncaused by\nResources exhausted: array size: got="),
+        "unexpected error: {err}",
+    );
+    assert!(err.ends_with(", limit=2000"), "unexpected error: {err}");
+}
+
+/// Get `long-value` UDF with the given limits.
+async fn udf_with_limits(limits: TrustedDataLimits) -> WasmScalarUdf {
+    find_udf(
+        try_scalar_udfs_with_permissions(
+            "return_data",
+            WasmPermissions::default().with_trusted_data_limits(limits),
+        )
+        .await
+        .unwrap(),
+        "long-value",
+    )
+}
+
+/// Find UDF by name.
+fn find_udf(udfs: Vec<WasmScalarUdf>, name: &str) -> WasmScalarUdf {
+    udfs.into_iter().find(|udf| udf.name() == name).unwrap()
+}
+
+/// Invoke UDF without arguments.
+async fn invoke(
+    udf: &WasmScalarUdf,
+    number_rows: usize,
+) -> datafusion_common::Result<datafusion_expr::ColumnarValue> {
+    udf.invoke_async_with_args(ScalarFunctionArgs {
+        args: vec![],
+        arg_fields: vec![],
+        number_rows,
+        return_field: Arc::new(Field::new("r", DataType::Utf8, true)),
+        config_options: Arc::new(ConfigOptions::default()),
+    })
+    .await
+}
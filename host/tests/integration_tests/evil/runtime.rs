@@ -1,14 +1,19 @@
 use std::sync::{Arc, LazyLock};
 
 use arrow::datatypes::{DataType, Field};
-use datafusion_common::config::ConfigOptions;
+use datafusion_common::{DataFusionError, config::ConfigOptions};
 use datafusion_expr::{ScalarFunctionArgs, ScalarUDFImpl, async_udf::AsyncScalarUDFImpl};
-use datafusion_udf_wasm_host::WasmScalarUdf;
+use datafusion_udf_wasm_host::{
+    InstanceSharing, ResourceLimitKind, StderrPolicy, StderrRedactor, WasmPermissions,
+    WasmScalarUdf, WasmUdfError,
+};
 use regex::Regex;
 use wasmtime::Trap;
 
 use crate::integration_tests::{
-    evil::test_utils::{normalize_panic_location, try_scalar_udfs},
+    evil::test_utils::{
+        normalize_panic_location, try_scalar_udfs, try_scalar_udfs_with_permissions,
+    },
     test_utils::FullError,
 };
 
@@ -256,12 +261,186 @@ async fn test_trap() {
     assert!(chain.any(|e| e.is::<Trap>()));
 
     // try to re-enter component, this should fail with a reasonable error message
+    let err = try_call_no_params_raw(udf_good).await.unwrap_err();
+    assert!(
+        matches!(
+            WasmUdfError::find(&err),
+            Some(WasmUdfError::Poisoned { .. })
+        ),
+        "{err}",
+    );
+    insta::assert_snapshot!(
+        FullError::new(err),
+        @"External error: UDF instance is poisoned because an earlier call trapped or timed out",
+    );
+}
+
+/// Tests that a poisoned guest is re-instantiated.
+#[tokio::test]
+async fn test_trap_recycle() {
+    let udfs =
+        try_scalar_udfs_with_permissions("runtime", WasmPermissions::new().with_max_recycles(1))
+            .await
+            .unwrap();
+    let udf_bad = udfs
+        .iter()
+        .find(|udf| udf.name() == "stackoverflow")
+        .unwrap();
+    let udf_good = udfs.iter().find(|udf| udf.name() == "pass").unwrap();
+
+    // first trap uses up the budget
+    err_call_no_params(udf_bad).await;
+    try_call_no_params(udf_good).await.unwrap();
+    try_call_no_params(udf_good).await.unwrap();
+
+    // second trap poisons the guest for good
+    err_call_no_params(udf_bad).await;
+    let err = try_call_no_params_raw(udf_good).await.unwrap_err();
+    assert!(
+        matches!(
+            WasmUdfError::find(&err),
+            Some(WasmUdfError::Poisoned { .. })
+        ),
+        "{err}",
+    );
+}
+
+/// Tests that a trap does NOT affect siblings that have their own instance.
+#[tokio::test]
+async fn test_trap_per_udf() {
+    let udfs = try_scalar_udfs_with_permissions(
+        "runtime",
+        WasmPermissions::new().with_instance_sharing(InstanceSharing::PerUdf),
+    )
+    .await
+    .unwrap();
+    let udf_bad = udfs
+        .iter()
+        .find(|udf| udf.name() == "stackoverflow")
+        .unwrap();
+    let udf_good = udfs.iter().find(|udf| udf.name() == "pass").unwrap();
+
+    err_call_no_params(udf_bad).await;
+    try_call_no_params(udf_good).await.unwrap();
+
+    // the bad one stays poisoned
+    let err = try_call_no_params_raw(udf_bad).await.unwrap_err();
+    assert!(
+        matches!(
+            WasmUdfError::find(&err),
+            Some(WasmUdfError::Poisoned { .. })
+        ),
+        "{err}",
+    );
+}
+
+#[tokio::test]
+async fn test_error_classification() {
+    let err = try_call_no_params_raw(&udf("abort").await)
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(WasmUdfError::find(&err), Some(WasmUdfError::Trap { .. })),
+        "{err}",
+    );
+
+    let err = try_call_no_params_raw(&udf("alloc").await)
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(
+            WasmUdfError::find(&err),
+            Some(WasmUdfError::ResourceLimit {
+                kind: ResourceLimitKind::Memory,
+                ..
+            })
+        ),
+        "{err}",
+    );
+
+    // classification does NOT change the error message
+    insta::assert_snapshot!(
+        err,
+        @r"
+    call ScalarUdf::invoke_with_args
+
+    stderr:
+    memory allocation of 1000000000 bytes failed
+    note: run with `RUST_BACKTRACE=1` environment variable to display a backtrace
+
+    caused by
+    External error: wasm trap: wasm `unreachable` instruction executed
+    ",
+    );
+
+    // a clean exit is NOT a trap
+    let err = try_call_no_params_raw(&udf("exit").await)
+        .await
+        .unwrap_err();
+    assert!(WasmUdfError::find(&err).is_none(), "{err}");
+}
+
+#[tokio::test]
+async fn test_stderr_policy() {
+    /// Redacts the allocation size.
+    #[derive(Debug)]
+    struct Redactor;
+
+    impl StderrRedactor for Redactor {
+        fn redact(&self, stderr: String) -> String {
+            stderr.replace("1000000000", "<REDACTED>")
+        }
+    }
+
+    let udf = udf_with_permissions(
+        "alloc",
+        WasmPermissions::new().with_stderr_policy(StderrPolicy::Never),
+    )
+    .await;
     insta::assert_snapshot!(
-        err_call_no_params(udf_good).await,
+        err_call_no_params(&udf).await,
         @r"
     call ScalarUdf::invoke_with_args
     caused by
-    External error: wasm trap: cannot enter component instance
+    External error: wasm trap: wasm `unreachable` instruction executed
+    ",
+    );
+
+    let udf = udf_with_permissions(
+        "alloc",
+        WasmPermissions::new().with_stderr_policy(StderrPolicy::Truncated { bytes: 20 }),
+    )
+    .await;
+    insta::assert_snapshot!(
+        err_call_no_params(&udf).await,
+        @r"
+    call ScalarUdf::invoke_with_args
+
+    stderr:
+    [stderr truncated: bytes=103]
+    display a backtrace
+
+    caused by
+    External error: wasm trap: wasm `unreachable` instruction executed
+    ",
+    );
+
+    let udf = udf_with_permissions(
+        "alloc",
+        WasmPermissions::new().with_stderr_redactor(Arc::new(Redactor)),
+    )
+    .await;
+    insta::assert_snapshot!(
+        err_call_no_params(&udf).await,
+        @r"
+    call ScalarUdf::invoke_with_args
+
+    stderr:
+    memory allocation of <REDACTED> bytes failed
+    note: run with `RUST_BACKTRACE=1` environment variable to display a backtrace
+
+    caused by
+    External error: wasm trap: wasm `unreachable` instruction executed
     ",
     );
 }
@@ -273,14 +452,24 @@ async fn udfs() -> Vec<WasmScalarUdf> {
 
 /// Get evil UDF.
 async fn udf(name: &'static str) -> WasmScalarUdf {
-    udfs()
+    udf_with_permissions(name, WasmPermissions::new()).await
+}
+
+/// Get evil UDF with permissions.
+async fn udf_with_permissions(name: &'static str, permissions: WasmPermissions) -> WasmScalarUdf {
+    try_scalar_udfs_with_permissions("runtime", permissions)
         .await
+        .unwrap()
         .into_iter()
         .find(|udf| udf.name() == name)
         .unwrap()
 }
 
 async fn try_call_no_params(udf: &WasmScalarUdf) -> Result<(), FullError> {
+    try_call_no_params_raw(udf).await.map_err(FullError::new)
+}
+
+async fn try_call_no_params_raw(udf: &WasmScalarUdf) -> Result<(), DataFusionError> {
     static RETURN_FIELD: LazyLock<Arc<Field>> =
         LazyLock::new(|| Arc::new(Field::new("r", DataType::Null, true)));
     static CONFIG_OPTIONS: LazyLock<Arc<ConfigOptions>> =
@@ -295,7 +484,6 @@ async fn try_call_no_params(udf: &WasmScalarUdf) -> Result<(), FullError> {
     })
     .await
     .map(|_| ())
-    .map_err(FullError::new)
 }
 
 async fn err_call_no_params(udf: &WasmScalarUdf) -> FullError {
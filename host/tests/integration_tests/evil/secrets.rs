@@ -0,0 +1,123 @@
+use std::sync::Arc;
+
+use arrow::{
+    array::AsArray,
+    datatypes::{DataType, Field},
+};
+use datafusion_common::{ScalarValue, config::ConfigOptions};
+use datafusion_expr::{
+    ColumnarValue, ScalarFunctionArgs, ScalarUDFImpl, async_udf::AsyncScalarUDFImpl,
+};
+use datafusion_udf_wasm_host::{SecretProvider, WasmPermissions, WasmScalarUdf};
+
+use crate::integration_tests::{
+    evil::test_utils::try_scalar_udfs_with_permissions,
+    test_utils::{ColumnarValueExt, FullError},
+};
+
+#[tokio::test]
+async fn test_denied_by_default() {
+    let udf = udf("secret_get", WasmPermissions::new()).await;
+    assert_eq!(call_get(&udf, "token").await, "ERR: missing");
+}
+
+#[tokio::test]
+async fn test_get() {
+    let udf = udf("secret_get", permissions()).await;
+    assert_eq!(call_get(&udf, "token").await, "OK: s3cr3t");
+    assert_eq!(call_get(&udf, "other").await, "ERR: missing");
+}
+
+#[tokio::test]
+async fn test_redaction() {
+    let udf = udf("leak", permissions()).await;
+
+    insta::assert_snapshot!(
+        call_leak(&udf).await,
+        @r"
+    stderr:
+    using token=[REDACTED]
+
+    caused by
+    authenticate as [REDACTED]
+    caused by
+    Execution error: request with token `[REDACTED]` failed
+    ",
+    );
+}
+
+#[tokio::test]
+async fn test_no_redaction_without_access() {
+    let udf = udf("leak", WasmPermissions::new()).await;
+
+    insta::assert_snapshot!(
+        call_leak(&udf).await,
+        @r"
+    stderr:
+    using token=
+
+    caused by
+    authenticate as 
+    caused by
+    Execution error: request with token `` failed
+    ",
+    );
+}
+
+/// Provides a single secret called `token`.
+#[derive(Debug)]
+struct Provider;
+
+impl SecretProvider for Provider {
+    fn get(&self, name: &str) -> Option<String> {
+        (name == "token").then(|| "s3cr3t".to_owned())
+    }
+}
+
+/// Permissions with access to secrets.
+fn permissions() -> WasmPermissions {
+    WasmPermissions::new().with_secret_provider(Arc::new(Provider))
+}
+
+/// Get evil UDF.
+async fn udf(name: &'static str, permissions: WasmPermissions) -> WasmScalarUdf {
+    try_scalar_udfs_with_permissions("secrets", permissions)
+        .await
+        .unwrap()
+        .into_iter()
+        .find(|udf| udf.name() == name)
+        .unwrap()
+}
+
+/// Call `secret_get` UDF.
+async fn call_get(udf: &WasmScalarUdf, name: &str) -> String {
+    let array = udf
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![ColumnarValue::Scalar(ScalarValue::Utf8(Some(
+                name.to_owned(),
+            )))],
+            arg_fields: vec![Arc::new(Field::new("a", DataType::Utf8, true))],
+            number_rows: 1,
+            return_field: Arc::new(Field::new("r", DataType::Utf8, true)),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+        .unwrap()
+        .unwrap_array();
+    array.as_string::<i32>().value(0).to_owned()
+}
+
+/// Call `leak` UDF.
+async fn call_leak(udf: &WasmScalarUdf) -> FullError {
+    let err = udf
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![],
+            arg_fields: vec![],
+            number_rows: 1,
+            return_field: Arc::new(Field::new("r", DataType::Null, true)),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+        .unwrap_err();
+    FullError::new(err)
+}
@@ -3,8 +3,11 @@ use std::{sync::Arc, time::Duration};
 use arrow::datatypes::{DataType, Field};
 use datafusion_common::config::ConfigOptions;
 use datafusion_expr::{ScalarFunctionArgs, ScalarUDFImpl, async_udf::AsyncScalarUDFImpl};
+use datafusion_udf_wasm_host::{WasmPermissions, WasmRuntimeHandle, WasmUdfError};
 
-use crate::integration_tests::evil::test_utils::try_scalar_udfs;
+use crate::integration_tests::evil::test_utils::{
+    try_scalar_udfs, try_scalar_udfs_with_permissions,
+};
 
 #[tokio::test]
 async fn test_udf_invoke() {
@@ -22,6 +25,173 @@ async fn test_udf_invoke() {
     assert_timeout(fut).await;
 }
 
+#[tokio::test]
+async fn test_udf_invoke_timeout() {
+    let udfs = try_scalar_udfs_with_permissions(
+        "spin::udf_invoke",
+        WasmPermissions::new().with_invoke_timeout(Duration::from_millis(100)),
+    )
+    .await
+    .unwrap();
+    assert_eq!(udfs.len(), 1);
+    let udf = udfs.into_iter().next().unwrap();
+
+    let args = ScalarFunctionArgs {
+        args: vec![],
+        arg_fields: vec![],
+        number_rows: 1,
+        return_field: Arc::new(Field::new("r", DataType::Null, true)),
+        config_options: Arc::new(ConfigOptions::default()),
+    };
+
+    let err = udf.invoke_async_with_args(args.clone()).await.unwrap_err();
+    insta::assert_snapshot!(
+        err,
+        @"External error: deadline has elapsed",
+    );
+    assert!(matches!(
+        WasmUdfError::find(&err),
+        Some(WasmUdfError::Timeout { .. })
+    ));
+
+    // instance is poisoned now
+    let err = udf.invoke_async_with_args(args).await.unwrap_err();
+    insta::assert_snapshot!(
+        err,
+        @"External error: UDF instance is poisoned because an earlier call trapped or timed out",
+    );
+    assert!(matches!(
+        WasmUdfError::find(&err),
+        Some(WasmUdfError::Poisoned { .. })
+    ));
+}
+
+#[tokio::test]
+async fn test_udf_invoke_cancel() {
+    let udfs = try_scalar_udfs_with_permissions(
+        "spin::udf_invoke",
+        WasmPermissions::new().with_max_recycles(1),
+    )
+    .await
+    .unwrap();
+    assert_eq!(udfs.len(), 1);
+    let udf = Arc::new(udfs.into_iter().next().unwrap());
+
+    let args = ScalarFunctionArgs {
+        args: vec![],
+        arg_fields: vec![],
+        number_rows: 1,
+        return_field: Arc::new(Field::new("r", DataType::Null, true)),
+        config_options: Arc::new(ConfigOptions::default()),
+    };
+
+    let task = tokio::spawn({
+        let udf = Arc::clone(&udf);
+        let args = args.clone();
+        async move { udf.invoke_async_with_args(args).await }
+    });
+
+    // let the guest spin for a bit
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(!task.is_finished());
+
+    // sibling call waits for the spinning one
+    let sibling = tokio::spawn({
+        let udf = Arc::clone(&udf);
+        let args = args.clone();
+        async move { udf.invoke_async_with_args(args).await }
+    });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(!sibling.is_finished());
+
+    // cancel call
+    task.abort();
+    assert!(task.await.unwrap_err().is_cancelled());
+
+    // sibling is NOT delayed by the cancelled call, it fails right away because it still uses the poisoned instance
+    let err = tokio::time::timeout(Duration::from_secs(1), sibling)
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap_err();
+    insta::assert_snapshot!(
+        err,
+        @"External error: UDF instance is poisoned because an earlier call was cancelled",
+    );
+
+    // cancelled instance is recycled, so the next call spins in a fresh guest instead of failing
+    let fut = udf.invoke_async_with_args(args.clone());
+    assert_timeout(fut).await;
+
+    // ... and that one was cancelled as well, so the recycle budget is used up now
+    let err = tokio::time::timeout(Duration::from_secs(5), udf.invoke_async_with_args(args))
+        .await
+        .unwrap()
+        .unwrap_err();
+    insta::assert_snapshot!(
+        err,
+        @"External error: UDF instance is poisoned because an earlier call was cancelled",
+    );
+}
+
+#[tokio::test]
+async fn test_udf_invoke_shutdown() {
+    let handle = WasmRuntimeHandle::new();
+    let permissions = WasmPermissions::new().with_runtime_handle(handle.clone());
+    let udfs = try_scalar_udfs_with_permissions("spin::udf_invoke", permissions.clone())
+        .await
+        .unwrap();
+    assert_eq!(udfs.len(), 1);
+    let udf = Arc::new(udfs.into_iter().next().unwrap());
+
+    let args = ScalarFunctionArgs {
+        args: vec![],
+        arg_fields: vec![],
+        number_rows: 1,
+        return_field: Arc::new(Field::new("r", DataType::Null, true)),
+        config_options: Arc::new(ConfigOptions::default()),
+    };
+
+    let task = tokio::spawn({
+        let udf = Arc::clone(&udf);
+        let args = args.clone();
+        async move { udf.invoke_async_with_args(args).await }
+    });
+
+    // let the guest spin for a bit
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(!task.is_finished());
+
+    handle.shutdown(Duration::from_secs(5)).await.unwrap();
+    assert!(handle.is_shut_down());
+
+    // running call was interrupted
+    let err = task.await.unwrap().unwrap_err();
+    assert!(
+        matches!(
+            WasmUdfError::find(&err),
+            Some(WasmUdfError::Shutdown { .. })
+        ),
+        "{err}",
+    );
+
+    // further calls fail
+    let err = udf.invoke_async_with_args(args).await.unwrap_err();
+    insta::assert_snapshot!(
+        err,
+        @"External error: WASM runtime was shut down",
+    );
+
+    // no new guests
+    let err = try_scalar_udfs_with_permissions("spin::udf_invoke", permissions)
+        .await
+        .unwrap_err();
+    insta::assert_snapshot!(
+        err,
+        @"External error: WASM runtime was shut down",
+    );
+}
+
 #[tokio::test]
 async fn test_udf_name() {
     let fut = try_scalar_udfs("spin::udf_name");
@@ -53,6 +223,10 @@ async fn test_udf_return_type_other() {
         err,
         @"External error: deadline has elapsed",
     );
+    assert!(matches!(
+        WasmUdfError::find(&err),
+        Some(WasmUdfError::Timeout { .. })
+    ));
 }
 
 #[tokio::test]
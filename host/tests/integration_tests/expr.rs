@@ -0,0 +1,304 @@
+use std::sync::Arc;
+
+use arrow::{
+    array::{Array, ArrayRef, BooleanArray, Float64Array, Int64Array, StringArray},
+    datatypes::{DataType, Field},
+};
+use datafusion_common::config::ConfigOptions;
+use datafusion_execution::memory_pool::GreedyMemoryPool;
+use datafusion_expr::{
+    ColumnarValue, ScalarFunctionArgs, ScalarUDFImpl, Signature, Volatility,
+    async_udf::AsyncScalarUDFImpl,
+};
+use datafusion_udf_wasm_host::{
+    CompilationFlags, SourceDiagnostic, WasmComponentPrecompiled, WasmScalarUdf,
+    WasmSourceValidator,
+};
+use tokio::{runtime::Handle, sync::OnceCell};
+
+use crate::integration_tests::test_utils::{ColumnarValueExt, FullError};
+
+/// Static precompiled expression language WASM component for tests.
+static COMPONENT: OnceCell<Arc<WasmComponentPrecompiled>> = OnceCell::const_new();
+
+/// Returns a static reference to the precompiled expression language WASM component.
+async fn expr_component() -> &'static Arc<WasmComponentPrecompiled> {
+    COMPONENT
+        .get_or_init(async || {
+            Arc::new(
+                WasmComponentPrecompiled::compile(
+                    datafusion_udf_wasm_bundle::BIN_EXPR.into(),
+                    &CompilationFlags::default(),
+                )
+                .await
+                .unwrap(),
+            )
+        })
+        .await
+}
+
+/// Compiles the provided source code into a list of UDFs.
+async fn expr_scalar_udfs(code: &str) -> Result<Vec<WasmScalarUdf>, FullError> {
+    WasmScalarUdf::new(
+        expr_component().await,
+        &Default::default(),
+        Handle::current(),
+        &(Arc::new(GreedyMemoryPool::new(10_000_000)) as _),
+        code.to_owned(),
+    )
+    .await
+    .map_err(FullError::new)
+}
+
+/// Compiles the provided source code into a single UDF.
+async fn expr_scalar_udf(code: &str) -> WasmScalarUdf {
+    let udfs = expr_scalar_udfs(code).await.unwrap();
+    assert_eq!(udfs.len(), 1);
+    udfs.into_iter().next().expect("just checked len")
+}
+
+/// Invoke UDF with arrays.
+async fn invoke(
+    udf: &WasmScalarUdf,
+    args: Vec<ArrayRef>,
+    return_type: DataType,
+) -> Result<ArrayRef, FullError> {
+    let number_rows = args.first().map(|a| a.len()).unwrap_or(1);
+    let arg_fields = args
+        .iter()
+        .enumerate()
+        .map(|(i, a)| Arc::new(Field::new(format!("a{i}"), a.data_type().clone(), true)))
+        .collect();
+    udf.invoke_async_with_args(ScalarFunctionArgs {
+        args: args.into_iter().map(ColumnarValue::Array).collect(),
+        arg_fields,
+        number_rows,
+        return_field: Arc::new(Field::new("r", return_type, true)),
+        config_options: Arc::new(ConfigOptions::default()),
+    })
+    .await
+    .map(|res| res.unwrap_array())
+    .map_err(FullError::new)
+}
+
+#[tokio::test]
+async fn test_add_one() {
+    let udf = expr_scalar_udf("add_one(x: int) -> int = x + 1").await;
+
+    assert_eq!(udf.name(), "add_one");
+    assert_eq!(
+        udf.signature(),
+        &Signature::exact(vec![DataType::Int64], Volatility::Immutable),
+    );
+
+    let array = invoke(
+        &udf,
+        vec![Arc::new(Int64Array::from_iter([Some(3), None, Some(1)]))],
+        DataType::Int64,
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        array.as_ref(),
+        &Int64Array::from_iter([Some(4), None, Some(2)]) as &dyn Array,
+    );
+}
+
+#[tokio::test]
+async fn test_multiple_functions() {
+    let udfs = expr_scalar_udfs(
+        "
+# numbers
+hypot(a: float, b: float) -> float = sqrt(a * a + b * b)
+sign(x: int) -> int = if x < 0 then -1 else if x == 0 then 0 else 1
+
+# strings
+shout(s: str) -> str = upper(trim(s)) || \"!\"
+",
+    )
+    .await
+    .unwrap();
+    let names = udfs.iter().map(|udf| udf.name()).collect::<Vec<_>>();
+    assert_eq!(names, ["hypot", "sign", "shout"]);
+
+    let array = invoke(
+        &udfs[0],
+        vec![
+            Arc::new(Float64Array::from_iter([Some(3.0)])),
+            Arc::new(Float64Array::from_iter([Some(4.0)])),
+        ],
+        DataType::Float64,
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        array.as_ref(),
+        &Float64Array::from_iter([Some(5.0)]) as &dyn Array,
+    );
+
+    let array = invoke(
+        &udfs[1],
+        vec![Arc::new(Int64Array::from_iter([
+            Some(-7),
+            Some(0),
+            Some(7),
+        ]))],
+        DataType::Int64,
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        array.as_ref(),
+        &Int64Array::from_iter([Some(-1), Some(0), Some(1)]) as &dyn Array,
+    );
+
+    let array = invoke(
+        &udfs[2],
+        vec![Arc::new(StringArray::from_iter([Some(" hi "), None]))],
+        DataType::Utf8,
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        array.as_ref(),
+        &StringArray::from_iter([Some("HI!"), None]) as &dyn Array,
+    );
+}
+
+#[tokio::test]
+async fn test_regex() {
+    let udfs = expr_scalar_udfs(
+        r##"
+is_id(s: str) -> bool = regex_match(s, "^[a-z]+-[0-9]+$")
+mask(s: str) -> str = regex_replace(s, "[0-9]", "#")
+"##,
+    )
+    .await
+    .unwrap();
+
+    let array = invoke(
+        &udfs[0],
+        vec![Arc::new(StringArray::from_iter([
+            Some("abc-123"),
+            Some("abc"),
+        ]))],
+        DataType::Boolean,
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        array.as_ref(),
+        &BooleanArray::from_iter([Some(true), Some(false)]) as &dyn Array,
+    );
+
+    let array = invoke(
+        &udfs[1],
+        vec![Arc::new(StringArray::from_iter([Some("a1b22")]))],
+        DataType::Utf8,
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        array.as_ref(),
+        &StringArray::from_iter([Some("a#b##")]) as &dyn Array,
+    );
+}
+
+#[tokio::test]
+async fn test_parse_errors() {
+    insta::assert_snapshot!(
+        expr_scalar_udfs("f(x: int) -> int = y").await.unwrap_err(),
+        @r"
+    load_source
+    caused by
+    Error during planning: 1:20: unknown parameter `y`
+    ",
+    );
+
+    insta::assert_snapshot!(
+        expr_scalar_udfs("f(x: int) -> str = x + 1").await.unwrap_err(),
+        @r"
+    load_source
+    caused by
+    Error during planning: 1:20: body of `f` has type int but function returns str
+    ",
+    );
+
+    insta::assert_snapshot!(
+        expr_scalar_udfs("f(s: str) -> bool = regex_match(s, s)").await.unwrap_err(),
+        @r"
+    load_source
+    caused by
+    Error during planning: 1:21: pattern of `regex_match` must be a string literal
+    ",
+    );
+
+    let deep = format!("f(x: int) -> int = {}x{}", "(".repeat(100), ")".repeat(100));
+    insta::assert_snapshot!(
+        expr_scalar_udfs(&deep).await.unwrap_err(),
+        @r"
+    load_source
+    caused by
+    Error during planning: 1:84: expression nested too deeply
+    ",
+    );
+}
+
+#[tokio::test]
+async fn test_validate_source() {
+    let validator = WasmSourceValidator::new(
+        expr_component().await,
+        &Default::default(),
+        Handle::current(),
+        &(Arc::new(GreedyMemoryPool::new(10_000_000)) as _),
+    );
+
+    assert_eq!(
+        validator
+            .validate("f(x: int) -> int = x + 1")
+            .await
+            .unwrap(),
+        vec![],
+    );
+    assert_eq!(
+        validator.validate("f(x: int) -> int = y").await.unwrap(),
+        vec![SourceDiagnostic {
+            message: "unknown parameter `y`".to_owned(),
+            line: 1,
+            column: 20,
+        }],
+    );
+}
+
+#[tokio::test]
+async fn test_runtime_errors() {
+    let udf = expr_scalar_udf("div(a: int, b: int) -> int = a / b").await;
+
+    insta::assert_snapshot!(
+        invoke(
+            &udf,
+            vec![
+                Arc::new(Int64Array::from_iter([Some(1)])),
+                Arc::new(Int64Array::from_iter([Some(0)])),
+            ],
+            DataType::Int64,
+        )
+        .await
+        .unwrap_err(),
+        @"Execution error: division by zero",
+    );
+
+    insta::assert_snapshot!(
+        invoke(
+            &udf,
+            vec![
+                Arc::new(Int64Array::from_iter([Some(i64::MIN)])),
+                Arc::new(Int64Array::from_iter([Some(-1)])),
+            ],
+            DataType::Int64,
+        )
+        .await
+        .unwrap_err(),
+        @"Execution error: integer overflow in `-9223372036854775808 / -1`",
+    );
+}
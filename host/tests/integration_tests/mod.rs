@@ -1,5 +1,8 @@
 mod evil;
+mod expr;
 mod python;
 mod rust;
+mod self_check;
+mod stress;
 
 mod test_utils;
@@ -4,12 +4,12 @@
 use std::sync::Arc;
 
 use arrow::{
-    array::{Array, Int64Array},
+    array::{Array, Int64Array, StringArray},
     datatypes::{DataType, Field},
 };
 use datafusion_common::config::ConfigOptions;
 use datafusion_expr::{
-    ColumnarValue, ScalarFunctionArgs, ScalarUDFImpl, Signature, Volatility,
+    ColumnarValue, ScalarFunctionArgs, ScalarUDFImpl, Signature, TypeSignature, Volatility,
     async_udf::AsyncScalarUDFImpl,
 };
 
@@ -62,19 +62,59 @@ async fn test_positional_or_keyword_default() {
 def foo(x: int = 1) -> int:
     return x + 1
 ";
-    let err = python_scalar_udf(CODE).await.unwrap_err();
+    let udf = python_scalar_udf(CODE).await.unwrap();
 
-    insta::assert_snapshot!(
-        err,
-        @r"
-    scalar_udfs
-    caused by
-    Error during planning: TypeError: default parameter values are not supported, got `1` of type `int`
+    assert_eq!(
+        udf.signature(),
+        &Signature::one_of(
+            vec![
+                TypeSignature::Exact(vec![]),
+                TypeSignature::Exact(vec![DataType::Int64]),
+            ],
+            Volatility::Volatile,
+        ),
+    );
 
-    The above exception was the direct cause of the following exception:
+    // return types of all overloads are cached, so this does NOT block
+    assert_eq!(udf.return_type(&[]).unwrap(), DataType::Int64);
+    assert_eq!(
+        udf.return_type(&[DataType::Int64]).unwrap(),
+        DataType::Int64,
+    );
 
-    TypeError: inspect type of `foo`
-    ",
+    let array = udf
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![],
+            arg_fields: vec![],
+            number_rows: 2,
+            return_field: Arc::new(Field::new("r", DataType::Int64, true)),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+        .unwrap()
+        .unwrap_array();
+    assert_eq!(
+        array.as_ref(),
+        &Int64Array::from_iter([Some(2), Some(2)]) as &dyn Array,
+    );
+
+    let array = udf
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![ColumnarValue::Array(Arc::new(Int64Array::from_iter([
+                Some(3),
+                None,
+            ])))],
+            arg_fields: vec![Arc::new(Field::new("a1", DataType::Int64, true))],
+            number_rows: 2,
+            return_field: Arc::new(Field::new("r", DataType::Int64, true)),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+        .unwrap()
+        .unwrap_array();
+    assert_eq!(
+        array.as_ref(),
+        &Int64Array::from_iter([Some(4), None]) as &dyn Array,
     );
 }
 
@@ -120,22 +160,44 @@ def foo(x: int, /) -> int:
 #[tokio::test]
 async fn test_positional_only_default() {
     const CODE: &str = "
-def foo(x: int = 1, /) -> int:
-    return x + 1
+def foo(x: int, y: int = 10, /) -> int:
+    return x + y
 ";
-    let err = python_scalar_udf(CODE).await.unwrap_err();
+    let udf = python_scalar_udf(CODE).await.unwrap();
 
-    insta::assert_snapshot!(
-        err,
-        @r"
-    scalar_udfs
-    caused by
-    Error during planning: TypeError: default parameter values are not supported, got `1` of type `int`
+    assert_eq!(
+        udf.signature(),
+        &Signature::one_of(
+            vec![
+                TypeSignature::Exact(vec![DataType::Int64]),
+                TypeSignature::Exact(vec![DataType::Int64, DataType::Int64]),
+            ],
+            Volatility::Volatile,
+        ),
+    );
 
-    The above exception was the direct cause of the following exception:
+    insta::assert_snapshot!(
+        udf.return_type(&[]).unwrap_err(),
+        @"Error during planning: `foo` expects 1 or 2 parameters but got 0",
+    );
 
-    TypeError: inspect type of `foo`
-    ",
+    let array = udf
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![ColumnarValue::Array(Arc::new(Int64Array::from_iter([
+                Some(3),
+                None,
+            ])))],
+            arg_fields: vec![Arc::new(Field::new("a1", DataType::Int64, true))],
+            number_rows: 2,
+            return_field: Arc::new(Field::new("r", DataType::Int64, true)),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+        .unwrap()
+        .unwrap_array();
+    assert_eq!(
+        array.as_ref(),
+        &Int64Array::from_iter([Some(13), None]) as &dyn Array,
     );
 }
 
@@ -192,7 +254,7 @@ def foo(*x: int) -> int:
     insta::assert_snapshot!(
         err,
         @r"
-    scalar_udfs
+    load_source
     caused by
     Error during planning: TypeError: only parameters of kind `POSITIONAL_OR_KEYWORD` and `POSITIONAL_ONLY` are supported, got VAR_POSITIONAL
 
@@ -214,7 +276,7 @@ def foo(*, x: int) -> int:
     insta::assert_snapshot!(
         err,
         @r"
-    scalar_udfs
+    load_source
     caused by
     Error during planning: TypeError: only parameters of kind `POSITIONAL_OR_KEYWORD` and `POSITIONAL_ONLY` are supported, got KEYWORD_ONLY
 
@@ -236,7 +298,7 @@ def foo(**x: int) -> int:
     insta::assert_snapshot!(
         err,
         @r"
-    scalar_udfs
+    load_source
     caused by
     Error during planning: TypeError: only parameters of kind `POSITIONAL_OR_KEYWORD` and `POSITIONAL_ONLY` are supported, got VAR_KEYWORD
 
@@ -246,3 +308,42 @@ def foo(**x: int) -> int:
     ",
     );
 }
+
+#[tokio::test]
+async fn test_fields() {
+    const CODE: &str = "
+def foo(x: int, *, _fields: tuple[dict, ...]) -> str:
+    [field] = _fields
+    unit = field['metadata'].get('unit', '-')
+    return f\"{field['name']} {field['nullable']} {x}{unit}\"
+";
+    let udf = python_scalar_udf(CODE).await.unwrap();
+
+    assert_eq!(
+        udf.signature(),
+        &Signature::exact(vec![DataType::Int64], Volatility::Volatile),
+    );
+
+    let array = udf
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![ColumnarValue::Array(Arc::new(Int64Array::from_iter([
+                Some(3),
+                None,
+                Some(-10),
+            ])))],
+            arg_fields: vec![Arc::new(
+                Field::new("a1", DataType::Int64, true)
+                    .with_metadata([("unit".to_owned(), "km".to_owned())].into_iter().collect()),
+            )],
+            number_rows: 3,
+            return_field: Arc::new(Field::new("r", DataType::Utf8, true)),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+        .unwrap()
+        .unwrap_array();
+    assert_eq!(
+        array.as_ref(),
+        &StringArray::from_iter([Some("a1 True 3km"), None, Some("a1 True -10km")]) as &dyn Array,
+    );
+}
@@ -7,7 +7,7 @@ async fn test_invalid_syntax() {
     insta::assert_snapshot!(
         err(CODE).await,
         @r#"
-    scalar_udfs
+    load_source
     caused by
     Error during planning:   File "<string>", line 1
         )
@@ -27,7 +27,7 @@ def add_one(x: int):
     insta::assert_snapshot!(
         err(CODE).await,
         @r"
-    scalar_udfs
+    load_source
     caused by
     Error during planning: TypeError: type missing
 
@@ -52,7 +52,7 @@ def add_one(x) -> int:
     insta::assert_snapshot!(
         err(CODE).await,
         @r"
-    scalar_udfs
+    load_source
     caused by
     Error during planning: TypeError: type missing
 
@@ -77,7 +77,7 @@ def add_one(x: int | str) -> int:
     insta::assert_snapshot!(
         err(CODE).await,
         @r"
-    scalar_udfs
+    load_source
     caused by
     Error during planning: TypeError: only unions of form `T | None` are supported, but got a union of 2 distinct none-NULL types
 
@@ -102,7 +102,7 @@ def add_one(x: int | str | None) -> int:
     insta::assert_snapshot!(
         err(CODE).await,
         @r"
-    scalar_udfs
+    load_source
     caused by
     Error during planning: TypeError: only unions of form `T | None` are supported, but got a union of 2 distinct none-NULL types
 
@@ -127,7 +127,7 @@ def add_one(x: int | str | int) -> int:
     insta::assert_snapshot!(
         err(CODE).await,
         @r"
-    scalar_udfs
+    load_source
     caused by
     Error during planning: TypeError: only unions of form `T | None` are supported, but got a union of 2 distinct none-NULL types
 
@@ -152,7 +152,7 @@ def add_one(x: int | None | str | int) -> int:
     insta::assert_snapshot!(
         err(CODE).await,
         @r"
-    scalar_udfs
+    load_source
     caused by
     Error during planning: TypeError: only unions of form `T | None` are supported, but got a union of 2 distinct none-NULL types
 
@@ -177,7 +177,7 @@ def add_one(x: int | str | float) -> int:
     insta::assert_snapshot!(
         err(CODE).await,
         @r"
-    scalar_udfs
+    load_source
     caused by
     Error during planning: TypeError: only unions of form `T | None` are supported, but got a union of 3 distinct none-NULL types
 
@@ -202,7 +202,7 @@ def add_one(x: 1337) -> int:
     insta::assert_snapshot!(
         err(CODE).await,
         @r"
-    scalar_udfs
+    load_source
     caused by
     Error during planning: TypeError: unknown annotation type: `1337` of type `int`
 
@@ -220,16 +220,16 @@ def add_one(x: 1337) -> int:
 #[tokio::test]
 async fn test_unsupported_type() {
     const CODE: &str = "
-def add_one(x: list[int]) -> int:
+def add_one(x: set[int]) -> int:
     return x + 1
 ";
 
     insta::assert_snapshot!(
         err(CODE).await,
         @r"
-    scalar_udfs
+    load_source
     caused by
-    Error during planning: TypeError: unknown annotation type: `list[int]` of type `GenericAlias`
+    Error during planning: TypeError: unknown annotation type: `set[int]` of type `GenericAlias`
 
     The above exception was the direct cause of the following exception:
 
@@ -242,6 +242,196 @@ def add_one(x: list[int]) -> int:
     );
 }
 
+#[tokio::test]
+async fn test_decimal_without_precision_and_scale() {
+    const CODE: &str = "
+from decimal import Decimal
+
+def add_one(x: Decimal) -> Decimal:
+    return x + 1
+";
+
+    insta::assert_snapshot!(
+        err(CODE).await,
+        @r"
+    load_source
+    caused by
+    Error during planning: TypeError: `Decimal` requires precision and scale, use `Annotated[Decimal, (precision, scale)]`
+
+    The above exception was the direct cause of the following exception:
+
+    TypeError: inspect parameter 1
+
+    The above exception was the direct cause of the following exception:
+
+    TypeError: inspect type of `add_one`
+    ",
+    );
+}
+
+#[tokio::test]
+async fn test_decimal_invalid_annotation() {
+    const CODE: &str = "
+from decimal import Decimal
+from typing import Annotated
+
+def add_one(x: Annotated[Decimal, 38]) -> int:
+    return 1
+";
+
+    insta::assert_snapshot!(
+        err(CODE).await,
+        @r"
+    load_source
+    caused by
+    Error during planning: TypeError: `Decimal` annotation must be a `(precision, scale)` tuple, got `38` of type `int`
+
+    The above exception was the direct cause of the following exception:
+
+    TypeError: inspect parameter 1
+
+    The above exception was the direct cause of the following exception:
+
+    TypeError: inspect type of `add_one`
+    ",
+    );
+}
+
+#[tokio::test]
+async fn test_decimal_invalid_precision() {
+    const CODE: &str = "
+from decimal import Decimal
+from typing import Annotated
+
+def add_one(x: Annotated[Decimal, (80, 2)]) -> int:
+    return 1
+";
+
+    insta::assert_snapshot!(
+        err(CODE).await,
+        @r"
+    load_source
+    caused by
+    Error during planning: TypeError: invalid `Decimal`: precision must be between 1 and 76, got 80; precisions up to 38 map to Decimal128, higher ones to Decimal256
+
+    The above exception was the direct cause of the following exception:
+
+    TypeError: inspect parameter 1
+
+    The above exception was the direct cause of the following exception:
+
+    TypeError: inspect type of `add_one`
+    ",
+    );
+}
+
+#[tokio::test]
+async fn test_bytes_invalid_annotation() {
+    const CODE: &str = "
+from typing import Annotated
+
+def foo(x: Annotated[bytes, 0]) -> int:
+    return 1
+";
+
+    insta::assert_snapshot!(
+        err(CODE).await,
+        @r"
+    load_source
+    caused by
+    Error during planning: TypeError: `bytes` annotation must be a positive size, got `0` of type `int`
+
+    The above exception was the direct cause of the following exception:
+
+    TypeError: inspect parameter 1
+
+    The above exception was the direct cause of the following exception:
+
+    TypeError: inspect type of `foo`
+    ",
+    );
+}
+
+#[tokio::test]
+async fn test_json_invalid_annotation() {
+    const CODE: &str = r#"
+from typing import Annotated
+
+def foo(x: Annotated[dict, "yaml"]) -> int:
+    return 1
+"#;
+    insta::assert_snapshot!(
+        err(CODE).await,
+        @r#"
+    load_source
+    caused by
+    Error during planning: TypeError: `dict` and `list` annotations must be "json", got `yaml` of type `str`
+
+    The above exception was the direct cause of the following exception:
+
+    TypeError: inspect parameter 1
+
+    The above exception was the direct cause of the following exception:
+
+    TypeError: inspect type of `foo`
+    "#,
+    );
+}
+
+#[tokio::test]
+async fn test_interval_invalid_annotation() {
+    const CODE: &str = r#"
+from typing import Annotated
+
+def foo(x: Annotated[tuple[int, str, int], "interval"]) -> int:
+    return 1
+"#;
+    insta::assert_snapshot!(
+        err(CODE).await,
+        @r#"
+    load_source
+    caused by
+    Error during planning: TypeError: "interval" annotation requires `tuple[int, int, int]`, got `tuple[int, str, int]` of type `GenericAlias`
+
+    The above exception was the direct cause of the following exception:
+
+    TypeError: inspect parameter 1
+
+    The above exception was the direct cause of the following exception:
+
+    TypeError: inspect type of `foo`
+    "#,
+    );
+}
+
+#[tokio::test]
+async fn test_datetime_invalid_annotation() {
+    const CODE: &str = "
+from datetime import datetime
+from typing import Annotated
+
+def foo(x: Annotated[datetime, 1]) -> int:
+    return 1
+";
+
+    insta::assert_snapshot!(
+        err(CODE).await,
+        @r"
+    load_source
+    caused by
+    Error during planning: TypeError: `datetime` annotation must be a time zone string, got `1` of type `int`
+
+    The above exception was the direct cause of the following exception:
+
+    TypeError: inspect parameter 1
+
+    The above exception was the direct cause of the following exception:
+
+    TypeError: inspect type of `foo`
+    ",
+    );
+}
+
 #[tokio::test]
 async fn test_custom_type() {
     const CODE: &str = "
@@ -255,7 +445,7 @@ def add_one(x: C) -> int:
     insta::assert_snapshot!(
         err(CODE).await,
         @r"
-    scalar_udfs
+    load_source
     caused by
     Error during planning: TypeError: unknown annotation type: `<class '__main__.C'>` of type `type`
 
@@ -270,6 +460,73 @@ def add_one(x: C) -> int:
     );
 }
 
+#[tokio::test]
+async fn test_typed_dict_not_required() {
+    const CODE: &str = "
+from typing import NotRequired, TypedDict
+
+class UserAgent(TypedDict):
+    browser: str
+    os: NotRequired[str]
+
+def parse_ua(ua: str) -> UserAgent:
+    return {'browser': ua}
+";
+
+    insta::assert_snapshot!(
+        err(CODE).await,
+        @r"
+    load_source
+    caused by
+    Error during planning: TypeError: `NotRequired` keys are not supported, use `T | None` instead
+
+    The above exception was the direct cause of the following exception:
+
+    TypeError: inspect return type
+
+    The above exception was the direct cause of the following exception:
+
+    TypeError: inspect type of `parse_ua`
+    ",
+    );
+}
+
+#[tokio::test]
+async fn test_recursive_dataclass() {
+    const CODE: &str = "
+from dataclasses import dataclass
+
+@dataclass
+class Node:
+    value: int
+    child: Node | None
+
+def leaf(x: int) -> Node:
+    return Node(x, None)
+";
+
+    insta::assert_snapshot!(
+        err(CODE).await,
+        @r"
+    load_source
+    caused by
+    Error during planning: TypeError: recursive types are not supported, got `<class '__main__.Node'>` of type `type`
+
+    The above exception was the direct cause of the following exception:
+
+    TypeError: inspect field `child`
+
+    The above exception was the direct cause of the following exception:
+
+    TypeError: inspect return type
+
+    The above exception was the direct cause of the following exception:
+
+    TypeError: inspect type of `leaf`
+    ",
+    );
+}
+
 #[tokio::test]
 async fn test_exception() {
     const CODE: &str = "
@@ -279,7 +536,7 @@ raise Exception('foo')
     insta::assert_snapshot!(
         err(CODE).await,
         @r#"
-    scalar_udfs
+    load_source
     caused by
     Error during planning: Traceback (most recent call last):
       File "<string>", line 2, in <module>
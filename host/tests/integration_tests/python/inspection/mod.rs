@@ -1,2 +1,28 @@
+use datafusion_udf_wasm_host::WasmComponentPrecompiled;
+
+use crate::integration_tests::python::test_utils::python_component;
+
 mod errors;
 mod filter;
+mod validate;
+
+#[tokio::test]
+async fn test_describe() {
+    let component = python_component().await;
+    let description = component.describe().unwrap();
+
+    assert_eq!(description.wit_version.as_deref(), Some("0.9.0"));
+    assert_eq!(description.runtime.as_deref(), Some("python 3.14"));
+    assert!(
+        description
+            .imports
+            .iter()
+            .any(|name| name.starts_with("wasi:http/")),
+        "{description:?}",
+    );
+
+    // the runtime survives a store->load round trip
+    // SAFETY: we just compiled that
+    let loaded = unsafe { WasmComponentPrecompiled::load(component.store().to_vec()) }.unwrap();
+    assert_eq!(loaded.describe().unwrap(), description);
+}
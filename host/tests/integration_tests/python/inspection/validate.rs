@@ -0,0 +1,58 @@
+use std::sync::Arc;
+
+use datafusion_execution::memory_pool::UnboundedMemoryPool;
+use datafusion_udf_wasm_host::{SourceDiagnostic, WasmSourceValidator};
+use tokio::runtime::Handle;
+
+use crate::integration_tests::python::test_utils::python_component;
+
+async fn validator() -> WasmSourceValidator {
+    WasmSourceValidator::new(
+        python_component().await,
+        &Default::default(),
+        Handle::current(),
+        &(Arc::new(UnboundedMemoryPool::default()) as _),
+    )
+}
+
+#[tokio::test]
+async fn test_valid() {
+    let validator = validator().await;
+
+    // the code is NOT executed, so neither the exception nor the missing type annotations matter
+    const CODE: &str = "
+raise Exception('must not run')
+
+def add_one(x):
+    return x + 1
+";
+    assert_eq!(validator.validate(CODE).await.unwrap(), vec![]);
+}
+
+#[tokio::test]
+async fn test_invalid() {
+    let validator = validator().await;
+
+    assert_eq!(
+        validator.validate(")").await.unwrap(),
+        vec![SourceDiagnostic {
+            message: "unmatched ')'".to_owned(),
+            line: 1,
+            column: 1,
+        }],
+    );
+
+    const CODE: &str = "
+def add_one(x: int) -> int:
+return x + 1
+";
+    let diagnostics = validator.validate(CODE).await.unwrap();
+    insta::assert_snapshot!(
+        diagnostics[0],
+        @"3:1: expected an indented block after function definition on line 2",
+    );
+    assert_eq!(diagnostics.len(), 1);
+
+    // validator is reusable
+    assert_eq!(validator.validate("x = 1").await.unwrap(), vec![]);
+}
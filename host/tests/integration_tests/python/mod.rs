@@ -1,7 +1,9 @@
 mod argument_forms;
 mod examples;
 mod inspection;
+mod registry;
 mod runtime;
 mod state;
 mod test_utils;
 mod types;
+mod warm_pool;
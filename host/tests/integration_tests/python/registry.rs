@@ -0,0 +1,176 @@
+use std::sync::Arc;
+
+use arrow::{
+    array::{Array, Int64Array},
+    datatypes::{DataType, Field},
+};
+use datafusion_common::config::ConfigOptions;
+use datafusion_execution::memory_pool::UnboundedMemoryPool;
+use datafusion_expr::{ScalarFunctionArgs, async_udf::AsyncScalarUDFImpl};
+use datafusion_udf_wasm_host::{WasmPermissions, WasmUdfRegistry};
+use tokio::runtime::Handle;
+
+use crate::integration_tests::{
+    python::test_utils::python_component,
+    test_utils::{ColumnarValueExt, FullError},
+};
+
+#[tokio::test]
+async fn test_lifecycle() {
+    let registry = registry().await;
+    assert!(registry.list().is_empty());
+
+    let udfs = registry
+        .register_source(
+            "counter",
+            "python",
+            "
+count = 0
+
+def inc() -> int:
+    global count
+    count += 1
+    return count
+",
+        )
+        .await
+        .unwrap();
+    assert_eq!(udfs.len(), 1);
+    assert_eq!(call(&udfs[0]).await, 1);
+    assert_eq!(call(&udfs[0]).await, 2);
+
+    let list = registry.list();
+    assert_eq!(list.keys().collect::<Vec<_>>(), ["counter"]);
+    assert_eq!(call(&list["counter"][0]).await, 3);
+
+    // refreshing resets the state, handles follow
+    registry.refresh().await.unwrap();
+    let list = registry.list();
+    assert_eq!(call(&list["counter"][0]).await, 1);
+    assert_eq!(call(&udfs[0]).await, 2);
+
+    // reloading swaps the code, handles follow
+    registry
+        .reload(
+            "counter",
+            "
+def inc() -> int:
+    return 42
+",
+        )
+        .await
+        .unwrap();
+    assert_eq!(call(&udfs[0]).await, 42);
+
+    // UDF names must not change
+    let err = registry
+        .reload(
+            "counter",
+            "
+def dec() -> int:
+    return -1
+",
+        )
+        .await
+        .unwrap_err();
+    insta::assert_snapshot!(
+        FullError::new(err),
+        @r"
+    reload `counter`
+    caused by
+    Error during planning: UDF `inc` is missing
+    ",
+    );
+    assert_eq!(call(&udfs[0]).await, 42);
+
+    // signatures must not change
+    let err = registry
+        .reload(
+            "counter",
+            "
+def inc(x: int) -> int:
+    return x
+",
+        )
+        .await
+        .unwrap_err();
+    insta::assert_snapshot!(
+        FullError::new(err),
+        @r"
+    reload `counter`
+    caused by
+    Error during planning: signature of UDF `inc` changed
+    ",
+    );
+
+    // unregistered source keeps working
+
+    assert!(registry.unregister("counter"));
+    assert!(!registry.unregister("counter"));
+    assert!(registry.list().is_empty());
+    assert_eq!(call(&udfs[0]).await, 42);
+
+    let err = registry.reload("counter", "").await.unwrap_err();
+    insta::assert_snapshot!(
+        FullError::new(err),
+        @"Error during planning: unknown source: `counter`",
+    );
+}
+
+#[tokio::test]
+async fn test_unknown_language() {
+    let registry = registry().await;
+
+    let err = registry
+        .register_source("foo", "cobol", "")
+        .await
+        .unwrap_err();
+    insta::assert_snapshot!(
+        FullError::new(err),
+        @"Error during planning: unknown language: `cobol`",
+    );
+    assert!(registry.list().is_empty());
+}
+
+/// Create registry that knows Python.
+#[tokio::test]
+async fn test_warmup() {
+    let registry = registry().await;
+
+    let outcome = registry.warmup().await;
+    assert_eq!(outcome.keys().collect::<Vec<_>>(), ["python"]);
+    let health = outcome["python"].as_ref().unwrap();
+    assert_eq!(health.runtime.as_deref(), Some("python 3.14"));
+    assert_eq!(health.probe_udfs, 0);
+
+    // warming up does not register anything
+    assert!(registry.list().is_empty());
+}
+
+async fn registry() -> WasmUdfRegistry {
+    WasmUdfRegistry::new(Handle::current(), Arc::new(UnboundedMemoryPool::default())).with_language(
+        "python",
+        Arc::clone(python_component().await),
+        WasmPermissions::new(),
+    )
+}
+
+/// Call UDF without arguments.
+async fn call(udf: &Arc<dyn AsyncScalarUDFImpl>) -> i64 {
+    let array = udf
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![],
+            arg_fields: vec![],
+            number_rows: 1,
+            return_field: Arc::new(Field::new("r", DataType::Int64, true)),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+        .unwrap()
+        .unwrap_array();
+    array
+        .as_any()
+        .downcast_ref::<Int64Array>()
+        .unwrap()
+        .value(0)
+}
@@ -0,0 +1,77 @@
+use std::{sync::Arc, time::Duration};
+
+use arrow::datatypes::{DataType, Field};
+use datafusion_common::{cast::as_string_array, config::ConfigOptions};
+use datafusion_execution::memory_pool::UnboundedMemoryPool;
+use datafusion_expr::{ScalarFunctionArgs, async_udf::AsyncScalarUDFImpl};
+use datafusion_udf_wasm_host::{ClockPolicy, ClockProvider, WasmPermissions, WasmScalarUdf};
+use tokio::runtime::Handle;
+
+use crate::integration_tests::{
+    python::test_utils::python_component, test_utils::ColumnarValueExt,
+};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_fixed_clock() {
+    let actual = observe_time(ClockPolicy::Fixed {
+        wall_clock: Duration::from_secs(1_000_000_000),
+    })
+    .await;
+    assert_eq!(actual, "1000000000.0,0.0");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_clock_provider() {
+    #[derive(Debug)]
+    struct Provider;
+
+    impl ClockProvider for Provider {
+        fn wall_clock_now(&self) -> Duration {
+            Duration::from_millis(1_500)
+        }
+
+        fn monotonic_clock_now(&self) -> u64 {
+            2_000_000_000
+        }
+    }
+
+    let actual = observe_time(ClockPolicy::Provider(Arc::new(Provider))).await;
+    assert_eq!(actual, "1.5,2.0");
+}
+
+async fn observe_time(policy: ClockPolicy) -> String {
+    const CODE: &str = r#"
+import time
+
+def now() -> str:
+    return f"{time.time()},{time.monotonic()}"
+"#;
+
+    let component = python_component().await;
+
+    let udfs = WasmScalarUdf::new(
+        component,
+        &WasmPermissions::default().with_clock_policy(policy),
+        Handle::current(),
+        &(Arc::new(UnboundedMemoryPool::default()) as _),
+        CODE.to_owned(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(udfs.len(), 1);
+    let udf = udfs.into_iter().next().unwrap();
+
+    let array = udf
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![],
+            arg_fields: vec![],
+            number_rows: 1,
+            return_field: Arc::new(Field::new("r", DataType::Utf8, true)),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+        .unwrap()
+        .unwrap_array();
+
+    as_string_array(&array).unwrap().value(0).to_owned()
+}
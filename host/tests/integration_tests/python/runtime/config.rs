@@ -0,0 +1,93 @@
+use std::sync::Arc;
+
+use arrow::{
+    array::{Array, Int64Array},
+    datatypes::{DataType, Field},
+};
+use datafusion_common::{ScalarValue, config::ConfigOptions};
+use datafusion_expr::{ColumnarValue, ScalarFunctionArgs, async_udf::AsyncScalarUDFImpl};
+use datafusion_udf_wasm_host::{WasmUdfConfig, WasmUdfError};
+
+use crate::integration_tests::{
+    python::test_utils::python_scalar_udf,
+    test_utils::{ColumnarValueExt, FullError},
+};
+
+#[tokio::test]
+async fn test_max_batch_rows() {
+    const CODE: &str = "
+def add(x: int, y: int) -> int:
+    return x + y
+";
+
+    let udf = python_scalar_udf(CODE).await.unwrap();
+    let array = udf
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![
+                ColumnarValue::Array(Arc::new(Int64Array::from_iter([
+                    Some(1),
+                    None,
+                    Some(3),
+                    Some(4),
+                    Some(5),
+                ]))),
+                ColumnarValue::Scalar(ScalarValue::Int64(Some(10))),
+            ],
+            arg_fields: vec![
+                Arc::new(Field::new("x", DataType::Int64, true)),
+                Arc::new(Field::new("y", DataType::Int64, true)),
+            ],
+            number_rows: 5,
+            return_field: Arc::new(Field::new("r", DataType::Int64, true)),
+            config_options: config_options(&[("udf_wasm.max_batch_rows", "2")]),
+        })
+        .await
+        .unwrap()
+        .unwrap_array();
+
+    assert_eq!(
+        array.as_ref(),
+        &Int64Array::from_iter([Some(11), None, Some(13), Some(14), Some(15)]) as &dyn Array,
+    );
+}
+
+#[tokio::test]
+async fn test_invocation_timeout() {
+    const CODE: &str = "
+def spin(x: int) -> int:
+    while True:
+        pass
+";
+
+    let udf = python_scalar_udf(CODE).await.unwrap();
+    let err = udf
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![ColumnarValue::Array(Arc::new(Int64Array::from_iter([
+                Some(1),
+            ])))],
+            arg_fields: vec![Arc::new(Field::new("x", DataType::Int64, true))],
+            number_rows: 1,
+            return_field: Arc::new(Field::new("r", DataType::Int64, true)),
+            config_options: config_options(&[("udf_wasm.invocation_timeout_ms", "100")]),
+        })
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        WasmUdfError::find(&err),
+        Some(WasmUdfError::Timeout { .. })
+    ));
+
+    let err = FullError::new(err).to_string();
+    assert!(err.contains("call ScalarUdf::invoke_with_args"), "{err}");
+    assert!(err.contains("UDF invocation timed out"), "{err}");
+}
+
+/// Create [`ConfigOptions`] with [`WasmUdfConfig`] and the given settings.
+fn config_options(settings: &[(&str, &str)]) -> Arc<ConfigOptions> {
+    let mut options = ConfigOptions::new();
+    options.extensions.insert(WasmUdfConfig::default());
+    for (k, v) in settings {
+        options.set(k, v).unwrap();
+    }
+    Arc::new(options)
+}
@@ -1,14 +1,18 @@
-use std::sync::Arc;
+use std::{collections::BTreeSet, sync::Arc};
 
 use arrow::{
-    array::{Array, Int64Array},
+    array::{Array, Int64Array, StringArray},
     datatypes::{DataType, Field},
 };
 use datafusion_common::config::ConfigOptions;
+use datafusion_execution::memory_pool::UnboundedMemoryPool;
 use datafusion_expr::{ColumnarValue, ScalarFunctionArgs, async_udf::AsyncScalarUDFImpl};
+use datafusion_udf_wasm_host::{WasmPermissions, WasmScalarUdf};
+use tokio::runtime::Handle;
 
 use crate::integration_tests::{
-    python::test_utils::python_scalar_udf, test_utils::ColumnarValueExt,
+    python::test_utils::{python_component, python_scalar_udf},
+    test_utils::ColumnarValueExt,
 };
 
 #[tokio::test]
@@ -114,3 +118,148 @@ def foo(x: int) -> int:
         &Int64Array::from_iter([Some(1), Some(2), Some(3)]) as &dyn Array,
     );
 }
+
+#[tokio::test]
+async fn extra_root_tar() {
+    const CODE: &str = "
+import tenant_lib
+
+def foo(x: int) -> int:
+    return tenant_lib.double(x)
+";
+
+    let tar = tar_with_files(&[(
+        "lib/python3.14/site-packages/tenant_lib.py",
+        "def double(x):\n    return 2 * x\n",
+    )]);
+
+    let udf = WasmScalarUdf::new(
+        python_component().await,
+        &WasmPermissions::new().with_extra_root_tar(tar),
+        Handle::current(),
+        &(Arc::new(UnboundedMemoryPool::default()) as _),
+        CODE.to_owned(),
+    )
+    .await
+    .unwrap()
+    .into_iter()
+    .next()
+    .unwrap();
+    let array = udf
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![ColumnarValue::Array(Arc::new(Int64Array::from_iter([
+                Some(1),
+                Some(2),
+            ])))],
+            arg_fields: vec![Arc::new(Field::new("a1", DataType::Int64, true))],
+            number_rows: 2,
+            return_field: Arc::new(Field::new("r", DataType::Int64, true)),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+        .unwrap()
+        .unwrap_array();
+    assert_eq!(
+        array.as_ref(),
+        &Int64Array::from_iter([Some(2), Some(4)]) as &dyn Array,
+    );
+
+    // the module is NOT available without the overlay
+    let err = python_scalar_udf(CODE).await.unwrap_err();
+    assert!(
+        err.to_string().contains("No module named 'tenant_lib'"),
+        "{err}"
+    );
+}
+
+#[tokio::test]
+async fn restricted_module_search_path() {
+    const CODE: &str = "
+import sys
+
+def foo(name: str) -> str:
+    sys.path.append('/lib/tenant_b')
+    try:
+        __import__(name)
+        return 'ok'
+    except ImportError as e:
+        return str(e)
+";
+
+    let tar = tar_with_files(&[
+        ("lib/tenant_a/a_lib.py", "X = 1\n"),
+        ("lib/tenant_b/b_lib.py", "X = 2\n"),
+    ]);
+    let udf = WasmScalarUdf::new(
+        python_component().await,
+        &WasmPermissions::new().with_extra_root_tar(tar).with_env(
+            "DATAFUSION_UDF_WASM_PYTHON_PATH".to_owned(),
+            "/lib/tenant_a".to_owned(),
+        ),
+        Handle::current(),
+        &(Arc::new(UnboundedMemoryPool::default()) as _),
+        CODE.to_owned(),
+    )
+    .await
+    .unwrap()
+    .into_iter()
+    .next()
+    .unwrap();
+    let array = udf
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![ColumnarValue::Array(Arc::new(StringArray::from_iter([
+                Some("json"),
+                Some("a_lib"),
+                Some("b_lib"),
+                Some("requests"),
+            ])))],
+            arg_fields: vec![Arc::new(Field::new("a1", DataType::Utf8, true))],
+            number_rows: 4,
+            return_field: Arc::new(Field::new("r", DataType::Utf8, true)),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+        .unwrap()
+        .unwrap_array();
+    assert_eq!(
+        array.as_ref(),
+        &StringArray::from_iter([
+            Some("ok"),
+            Some("ok"),
+            Some("module 'b_lib' is outside of the module search path"),
+            Some("No module named 'requests'"),
+        ]) as &dyn Array,
+    );
+}
+
+/// Build TAR archive that contains the given files and all their parent directories.
+fn tar_with_files(files: &[(&str, &str)]) -> Vec<u8> {
+    let dirs = files
+        .iter()
+        .flat_map(|(path, _)| {
+            path.match_indices('/')
+                .map(|(idx, _)| &path[..idx])
+                .collect::<Vec<_>>()
+        })
+        .collect::<BTreeSet<_>>();
+
+    let mut builder = tar::Builder::new(Vec::new());
+    for dir in dirs {
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Directory);
+        header.set_size(0);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, dir, std::io::empty())
+            .unwrap();
+    }
+    for (path, content) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, path, content.as_bytes())
+            .unwrap();
+    }
+    builder.into_inner().unwrap()
+}
@@ -4,10 +4,11 @@ use arrow::{
     array::{Float64Array, Int64Array},
     datatypes::{DataType, Field},
 };
-use datafusion_common::config::ConfigOptions;
+use datafusion_common::{DataFusionError, config::ConfigOptions};
 use datafusion_expr::{
     ColumnarValue, ScalarFunctionArgs, ScalarUDFImpl, async_udf::AsyncScalarUDFImpl,
 };
+use datafusion_udf_wasm_host::{GuestTraceback, TracebackFrame};
 
 use crate::integration_tests::{python::test_utils::python_scalar_udf, test_utils::FullError};
 
@@ -54,7 +55,7 @@ def foo(x: int) -> int:
             config_options: Arc::new(ConfigOptions::default()),
         })
         .await.unwrap_err(),
-        @"Execution error: `foo` expects 1 parameters (passed as args) but got 0",
+        @"Execution error: `foo` got 1 argument fields but 0 arguments",
     );
 
     insta::assert_snapshot!(
@@ -76,7 +77,7 @@ def foo(x: int) -> int:
         })
         .await
         .unwrap_err(),
-        @"Execution error: `foo` expects 1 parameters (passed as args) but got 2",
+        @"Execution error: `foo` got 1 argument fields but 2 arguments",
     );
 
     insta::assert_snapshot!(
@@ -244,7 +245,7 @@ def foo(x: int) -> int:
 
     insta::assert_snapshot!(
         err(CODE).await,
-        @"Execution error: method was not supposed to return None but did",
+        @"Execution error: method returned `None` for a non-optional type, annotate it as `T | None` to return NULLs",
     );
 }
 
@@ -260,7 +261,7 @@ def foo(x: int) -> int:
         @r#"
     cannot call function
     caused by
-    Execution error: Traceback (most recent call last):
+    External error: Execution error: Traceback (most recent call last):
       File "<string>", line 3, in foo
     Exception: bar
     "#,
@@ -295,7 +296,7 @@ def foo(x: int) -> int:
         @r#"
     cannot call function
     caused by
-    Execution error: Traceback (most recent call last):
+    External error: Execution error: Traceback (most recent call last):
       File "<string>", line 4, in _inner1
       File "<string>", line 9, in _inner2
       File "<string>", line 12, in _inner3
@@ -317,6 +318,45 @@ def foo(x: int) -> int:
     );
 }
 
+#[tokio::test]
+async fn test_exception_traceback() {
+    const CODE: &str = "
+def _inner() -> None:
+    raise ValueError('bar')
+
+def foo(x: int) -> int:
+    _inner()
+";
+
+    let err = raw_err(CODE).await;
+    let traceback = GuestTraceback::find(&err).unwrap();
+    assert_eq!(
+        traceback.frames(),
+        [
+            TracebackFrame {
+                file: "<string>".to_owned(),
+                line: 6,
+                function: "foo".to_owned(),
+            },
+            TracebackFrame {
+                file: "<string>".to_owned(),
+                line: 3,
+                function: "_inner".to_owned(),
+            },
+        ],
+    );
+    assert_eq!(traceback.truncated_frames(), 0);
+    insta::assert_snapshot!(
+        traceback.error(),
+        @r#"
+    Execution error: Traceback (most recent call last):
+      File "<string>", line 6, in foo
+      File "<string>", line 3, in _inner
+    ValueError: bar
+    "#,
+    );
+}
+
 #[tokio::test]
 async fn test_stack_overflow() {
     // borrowed from https://wiki.python.org/moin/CrashingPython
@@ -350,26 +390,30 @@ def foo(x: int) -> int:
         @r#"
     cannot call function
     caused by
-    Execution error: Traceback (most recent call last):
+    External error: Execution error: Traceback (most recent call last):
       File "<string>", line 3, in foo
     MemoryError
     "#,
     );
 }
 
+/// Invoke `foo` and return error chain.
 async fn err(code: &str) -> FullError {
+    FullError::new(raw_err(code).await)
+}
+
+/// Invoke `foo` and return error.
+async fn raw_err(code: &str) -> DataFusionError {
     let udf = python_scalar_udf(code).await.unwrap();
-    let err = udf
-        .invoke_async_with_args(ScalarFunctionArgs {
-            args: vec![ColumnarValue::Array(Arc::new(Int64Array::from_iter([
-                Some(1),
-            ])))],
-            arg_fields: vec![Arc::new(Field::new("x", DataType::Int64, true))],
-            number_rows: 1,
-            return_field: Arc::new(Field::new("r", DataType::Int64, true)),
-            config_options: Arc::new(ConfigOptions::default()),
-        })
-        .await
-        .unwrap_err();
-    FullError::new(err)
+    udf.invoke_async_with_args(ScalarFunctionArgs {
+        args: vec![ColumnarValue::Array(Arc::new(Int64Array::from_iter([
+            Some(1),
+        ])))],
+        arg_fields: vec![Arc::new(Field::new("x", DataType::Int64, true))],
+        number_rows: 1,
+        return_field: Arc::new(Field::new("r", DataType::Int64, true)),
+        config_options: Arc::new(ConfigOptions::default()),
+    })
+    .await
+    .unwrap_err()
 }
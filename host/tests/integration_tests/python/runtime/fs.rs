@@ -4,10 +4,10 @@ use arrow::{
     array::{Array, StringArray},
     datatypes::{DataType, Field},
 };
-use datafusion_common::config::ConfigOptions;
+use datafusion_common::{ScalarValue, config::ConfigOptions};
 use datafusion_execution::memory_pool::{GreedyMemoryPool, UnboundedMemoryPool};
 use datafusion_expr::{ColumnarValue, ScalarFunctionArgs, async_udf::AsyncScalarUDFImpl};
-use datafusion_udf_wasm_host::{VfsLimits, WasmPermissions, WasmScalarUdf};
+use datafusion_udf_wasm_host::{IoViolationLog, VfsLimits, WasmPermissions, WasmScalarUdf};
 use regex::Regex;
 use tokio::runtime::Handle;
 
@@ -25,7 +25,8 @@ def listdir(cwd: str | None, dir: str) -> str:
     if cwd:
         os.chdir(cwd)
 
-    return ", ".join(os.listdir(dir))
+    # bytecode is only bundled for some builds
+    return ", ".join(entry for entry in os.listdir(dir) if entry != "__pycache__")
 "#;
 
     let udf = python_scalar_udf(CODE).await.unwrap();
@@ -184,6 +185,47 @@ if __name__ == '__main__':
     );
 }
 
+#[tokio::test]
+async fn test_resource_usage() {
+    const CODE: &str = r#"
+def write(path: str, size: int) -> int:
+    with open(path, "w") as fp:
+        fp.write("x" * size)
+    return size
+"#;
+
+    let udf = python_scalar_udf(CODE).await.unwrap();
+    let before = udf.resource_usage().await;
+    assert!(before.linear_memory_bytes > 0);
+    assert!(before.table_elements > 0);
+    assert!(before.vfs_inodes > 0);
+    assert!(before.reserved_bytes >= before.linear_memory_bytes + before.vfs_bytes);
+    assert!(before.peak_reserved_bytes >= before.reserved_bytes);
+
+    udf.invoke_async_with_args(ScalarFunctionArgs {
+        args: vec![
+            ColumnarValue::Scalar(ScalarValue::Utf8(Some("/test".to_owned()))),
+            ColumnarValue::Scalar(ScalarValue::Int64(Some(10_000))),
+        ],
+        arg_fields: vec![
+            Arc::new(Field::new("path", DataType::Utf8, true)),
+            Arc::new(Field::new("size", DataType::Int64, true)),
+        ],
+        number_rows: 1,
+        return_field: Arc::new(Field::new("r", DataType::Int64, true)),
+        config_options: Arc::new(ConfigOptions::default()),
+    })
+    .await
+    .unwrap();
+
+    let after = udf.resource_usage().await;
+    assert_eq!(after.vfs_inodes, before.vfs_inodes + 1);
+    assert!(after.vfs_bytes >= before.vfs_bytes + 10_000);
+    assert!(after.linear_memory_bytes >= before.linear_memory_bytes);
+    assert!(after.peak_reserved_bytes >= after.reserved_bytes);
+    assert!(after.epoch_ticks >= before.epoch_ticks);
+}
+
 #[tokio::test]
 async fn test_write() {
     const CODE: &str = r#"
@@ -264,6 +306,64 @@ def write(path: str, content: str) -> str:
     );
 }
 
+#[tokio::test]
+async fn test_write_hermetic() {
+    const CODE: &str = r#"
+def write(path: str) -> str:
+    try:
+        with open(path, "w") as fp:
+            fp.write("data")
+        return "OK"
+    except Exception as e:
+        return f"ERR: {e}"
+"#;
+
+    let log = Arc::new(IoViolationLog::new());
+    let udf = WasmScalarUdf::new(
+        python_component().await,
+        &WasmPermissions::hermetic().with_observer(Arc::clone(&log) as _),
+        Handle::current(),
+        &(Arc::new(UnboundedMemoryPool::default()) as _),
+        CODE.to_owned(),
+    )
+    .await
+    .unwrap()
+    .pop()
+    .unwrap();
+    assert_eq!(log.take(), vec![]);
+
+    let array = udf
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![ColumnarValue::Scalar(ScalarValue::Utf8(Some(
+                "/test".to_owned(),
+            )))],
+            arg_fields: vec![Arc::new(Field::new("path", DataType::Utf8, true))],
+            number_rows: 1,
+            return_field: Arc::new(Field::new("r", DataType::Utf8, true)),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+        .unwrap()
+        .unwrap_array();
+    assert_eq!(
+        array.as_ref(),
+        &StringArray::from_iter([Some("ERR: [Errno 69] Read-only file system: '/test'")])
+            as &dyn Array,
+    );
+
+    // the guest swallowed the error, but the violation was recorded
+    let violations = log
+        .take()
+        .into_iter()
+        .map(|(_instance, _labels, io)| io.to_string())
+        .collect::<Vec<_>>();
+    insta::assert_debug_snapshot!(violations, @r#"
+    [
+        "VFS write \"test\"",
+    ]
+    "#);
+}
+
 #[tokio::test]
 async fn test_limit_inodes() {
     let component = python_component().await;
@@ -285,7 +385,7 @@ async fn test_limit_inodes() {
     insta::assert_snapshot!(
         err,
         @r#"
-    calling scalar_udfs() method failed
+    calling load_source() method failed
 
     stderr:
 
@@ -340,7 +440,7 @@ async fn test_limit_bytes() {
     insta::assert_snapshot!(
         err,
         @r"
-    calling scalar_udfs() method failed
+    calling load_source() method failed
     caused by
     External error: insufficient-memory (error 22)
     ");
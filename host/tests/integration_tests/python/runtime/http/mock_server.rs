@@ -179,6 +179,7 @@ impl Default for ServerMock {
 struct State {
     mocks: Vec<(ServerMock, u64)>,
     errors: Vec<String>,
+    connections: u64,
     ignore_errors: Vec<&'static str>,
 }
 
@@ -292,6 +293,7 @@ impl MockServer {
         let state = Arc::new(Mutex::new(State {
             mocks: vec![],
             errors: vec![],
+            connections: 0,
             ignore_errors,
         }));
 
@@ -314,6 +316,8 @@ impl MockServer {
                         }
                     };
 
+                    state_captured.lock().unwrap().connections += 1;
+
                     if failure == Some(Failure::CloseWithoutAnswer) {
                         continue;
                     }
@@ -376,6 +380,11 @@ impl MockServer {
         format!("{scheme}://{hostname}:{port}", port = self.port())
     }
 
+    /// Number of accepted TCP connections.
+    pub(crate) fn connections(&self) -> u64 {
+        self.state.lock().unwrap().connections
+    }
+
     pub(crate) fn mock(&self, mock: ServerMock) {
         self.state.lock().unwrap().mocks.push((mock, 0));
     }
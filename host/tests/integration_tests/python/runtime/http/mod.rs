@@ -16,8 +16,8 @@ use datafusion_expr::{
     ColumnarValue, ScalarFunctionArgs, ScalarUDFImpl, async_udf::AsyncScalarUDFImpl,
 };
 use datafusion_udf_wasm_host::{
-    AllowCertainHttpRequests, HttpConfig, HttpConnectionMode, HttpPort, TlsClientConfig,
-    WasmPermissions, WasmScalarUdf,
+    AllowCertainHttpRequests, HttpConfig, HttpConnectionMode, HttpPoolSharing, HttpPort,
+    HttpRedirectPolicy, TlsClientConfig, WasmPermissions, WasmScalarUdf,
 };
 use http::{
     HeaderName, HeaderValue, Method,
@@ -92,6 +92,370 @@ def perform_request(url: str) -> str:
     );
 }
 
+#[tokio::test]
+async fn test_connection_pool_sharing() {
+    const CODE: &str = r#"
+import requests
+
+def perform_request(url: str) -> str:
+    return requests.get(url).text
+"#;
+
+    for (sharing, expected_connections) in [
+        (HttpPoolSharing::Shared, 1),
+        (HttpPoolSharing::PerInstance, 2),
+    ] {
+        let server = MockServer::start().await;
+        server.mock(ServerMock {
+            response: Box::new(SimpleResponseGen {
+                body: "hello world!".to_owned(),
+                ..Default::default()
+            }),
+            hits: Some(4),
+            ..Default::default()
+        });
+
+        let mut validator = AllowCertainHttpRequests::new();
+        let endpoint = validator
+            .allow_host(server.hostname())
+            .allow_port(HttpPort::new(server.port()).unwrap());
+        endpoint.allow_mode(HttpConnectionMode::PlainText);
+        endpoint.allow_method(http::Method::GET);
+        let cfg = HttpConfig::default()
+            .with_validator(validator)
+            .with_pool_sharing(sharing);
+
+        // two independent instances, two requests each
+        for _ in 0..2 {
+            let udf = python_udf_with_http_config(CODE, cfg.clone()).await;
+            for _ in 0..2 {
+                let array = udf
+                    .invoke_async_with_args(ScalarFunctionArgs {
+                        args: vec![ColumnarValue::Scalar(ScalarValue::Utf8(Some(server.uri())))],
+                        arg_fields: vec![Arc::new(Field::new("uri", DataType::Utf8, true))],
+                        number_rows: 1,
+                        return_field: Arc::new(Field::new("r", DataType::Utf8, true)),
+                        config_options: Arc::new(ConfigOptions::default()),
+                    })
+                    .await
+                    .unwrap()
+                    .unwrap_array();
+                assert_eq!(
+                    array.as_ref(),
+                    &StringArray::from_iter([Some("hello world!".to_owned())]) as &dyn Array,
+                );
+            }
+        }
+
+        assert_eq!(server.connections(), expected_connections, "{sharing:?}");
+    }
+}
+
+#[tokio::test]
+async fn test_rate_limit() {
+    const CODE: &str = r#"
+import requests
+
+def perform_request(url: str) -> str:
+    try:
+        return requests.get(url).text
+    except Exception as e:
+        if "ConnectionLimitReached" in str(e):
+            return "rate limited"
+        raise
+"#;
+
+    let server = MockServer::start().await;
+    server.mock(ServerMock {
+        response: Box::new(SimpleResponseGen {
+            body: "hello world!".to_owned(),
+            ..Default::default()
+        }),
+        hits: Some(2),
+        ..Default::default()
+    });
+
+    let mut validator = AllowCertainHttpRequests::new();
+    let endpoint = validator
+        .allow_host(server.hostname())
+        .allow_port(HttpPort::new(server.port()).unwrap());
+    endpoint.allow_mode(HttpConnectionMode::PlainText);
+    endpoint.allow_method(http::Method::GET);
+    let permissions = WasmPermissions::new()
+        .with_http(HttpConfig::default().with_validator(validator))
+        .with_http_rate_limit(0.001, 2);
+    let udfs = WasmScalarUdf::new(
+        python_component().await,
+        &permissions,
+        Handle::current(),
+        &(Arc::new(UnboundedMemoryPool::default()) as _),
+        CODE.to_owned(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(udfs.len(), 1);
+
+    let array = udfs[0]
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![ColumnarValue::Array(Arc::new(StringArray::from_iter(
+                std::iter::repeat_n(Some(server.uri()), 3),
+            )))],
+            arg_fields: vec![Arc::new(Field::new("uri", DataType::Utf8, true))],
+            number_rows: 3,
+            return_field: Arc::new(Field::new("r", DataType::Utf8, true)),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+        .unwrap()
+        .unwrap_array();
+
+    assert_eq!(
+        array.as_ref(),
+        &StringArray::from_iter([
+            Some("hello world!".to_owned()),
+            Some("hello world!".to_owned()),
+            Some("rate limited".to_owned()),
+        ]) as &dyn Array,
+    );
+}
+
+#[tokio::test]
+async fn test_ip_policy() {
+    const CODE: &str = r#"
+import requests
+
+def perform_request(url: str) -> str:
+    try:
+        resp = requests.get(url)
+        return f"{resp.status_code}: {resp.text}"
+    except Exception as e:
+        if "HttpRequestDenied" in str(e):
+            return "denied"
+        raise
+"#;
+
+    let server = MockServer::with_options(MockServerOptions {
+        hostname: Some("public.test".to_owned()),
+        ..Default::default()
+    })
+    .await;
+    let port = server.port();
+    server.mock(ServerMock {
+        matcher: Matcher {
+            path: Some("/hello".to_owned()),
+            ..Default::default()
+        },
+        response: Box::new(SimpleResponseGen {
+            body: "hello world!".to_owned(),
+            ..Default::default()
+        }),
+        ..Default::default()
+    });
+    // redirect chain: public -> public -> internal
+    server.mock(ServerMock {
+        matcher: Matcher {
+            path: Some("/redirect1".to_owned()),
+            ..Default::default()
+        },
+        response: Box::new(SimpleResponseGen {
+            status: http::StatusCode::FOUND,
+            headers: Some(
+                [(
+                    http::header::LOCATION,
+                    HeaderValue::from_str(&format!("http://public.test:{port}/redirect2")).unwrap(),
+                )]
+                .into_iter()
+                .collect(),
+            ),
+            ..Default::default()
+        }),
+        ..Default::default()
+    });
+    server.mock(ServerMock {
+        matcher: Matcher {
+            path: Some("/redirect2".to_owned()),
+            ..Default::default()
+        },
+        response: Box::new(SimpleResponseGen {
+            status: http::StatusCode::FOUND,
+            headers: Some(
+                [(
+                    http::header::LOCATION,
+                    HeaderValue::from_str(&format!("http://internal.test:{port}/secret")).unwrap(),
+                )]
+                .into_iter()
+                .collect(),
+            ),
+            ..Default::default()
+        }),
+        ..Default::default()
+    });
+
+    let resolver = MockResolver::default();
+    // mixed answers only keep the allowed addresses
+    resolver.mock_ok(
+        "public.test",
+        vec![
+            "10.0.0.1:0".parse().unwrap(),
+            "127.0.0.1:0".parse().unwrap(),
+        ],
+        3,
+    );
+    resolver.mock_ok(
+        "internal.test",
+        vec![
+            "192.168.0.1:0".parse().unwrap(),
+            "[::ffff:169.254.169.254]:0".parse().unwrap(),
+        ],
+        1,
+    );
+
+    let mut validator = AllowCertainHttpRequests::new();
+    for host in ["public.test", "internal.test", "10.0.0.1"] {
+        let endpoint = validator
+            .allow_host(host.to_owned())
+            .allow_port(HttpPort::new(port).unwrap());
+        endpoint.allow_mode(HttpConnectionMode::PlainText);
+        endpoint.allow_method(http::Method::GET);
+    }
+    let udf = python_udf_with_http_config(
+        CODE,
+        HttpConfig::default()
+            // avoid connection caching, so that every request resolves the name
+            .with_pool_max_idle_per_host(0)
+            .with_resolver(resolver.clone())
+            .with_validator(validator),
+    )
+    .await;
+
+    let urls = [
+        format!("http://public.test:{port}/hello"),
+        format!("http://public.test:{port}/redirect1"),
+        format!("http://10.0.0.1:{port}/"),
+    ];
+    let array = udf
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![ColumnarValue::Array(Arc::new(
+                StringArray::from_iter_values(urls),
+            ))],
+            arg_fields: vec![Arc::new(Field::new("uri", DataType::Utf8, true))],
+            number_rows: 3,
+            return_field: Arc::new(Field::new("r", DataType::Utf8, true)),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+        .unwrap()
+        .unwrap_array();
+
+    assert_eq!(
+        array.as_ref(),
+        &StringArray::from_iter_values(["200: hello world!", "denied", "denied"]) as &dyn Array,
+    );
+}
+
+#[tokio::test]
+async fn test_redirect_policy() {
+    const CODE: &str = r#"
+import requests
+
+def perform_request(url: str) -> str:
+    try:
+        # let the host follow redirects
+        resp = requests.get(url, allow_redirects=False)
+        return f"{resp.status_code}: {resp.text}"
+    except Exception as e:
+        if "HttpRequestDenied" in str(e):
+            return "denied"
+        if "LoopDetected" in str(e):
+            return "loop"
+        raise
+"#;
+
+    let server = MockServer::start().await;
+    let port = server.port();
+    let redirect = |path: &str, location: String, hits: u64| ServerMock {
+        matcher: Matcher {
+            path: Some(path.to_owned()),
+            ..Default::default()
+        },
+        response: Box::new(SimpleResponseGen {
+            status: http::StatusCode::FOUND,
+            headers: Some(
+                [(
+                    http::header::LOCATION,
+                    HeaderValue::from_str(&location).unwrap(),
+                )]
+                .into_iter()
+                .collect(),
+            ),
+            ..Default::default()
+        }),
+        hits: Some(hits),
+    };
+    server.mock(ServerMock {
+        matcher: Matcher {
+            path: Some("/hello".to_owned()),
+            ..Default::default()
+        },
+        response: Box::new(SimpleResponseGen {
+            body: "hello world!".to_owned(),
+            ..Default::default()
+        }),
+        hits: Some(1),
+    });
+    // permitted chain: redirect1 -> redirect2 -> hello
+    server.mock(redirect("/redirect1", "/redirect2".to_owned(), 1));
+    server.mock(redirect("/redirect2", "/hello".to_owned(), 1));
+    // hop to a host that is not permitted
+    server.mock(redirect(
+        "/escape",
+        format!("http://evil.test:{port}/hello"),
+        1,
+    ));
+    // initial request + 2 hops
+    server.mock(redirect("/loop", "/loop".to_owned(), 3));
+
+    let mut validator = AllowCertainHttpRequests::new();
+    let endpoint = validator
+        .allow_host(server.hostname())
+        .allow_port(HttpPort::new(port).unwrap());
+    endpoint.allow_mode(HttpConnectionMode::PlainText);
+    endpoint.allow_method(http::Method::GET);
+    let permissions = WasmPermissions::new()
+        .with_http(HttpConfig::default().with_validator(validator))
+        .with_http_redirect_policy(HttpRedirectPolicy::Follow { max_hops: 2 });
+    let udfs = WasmScalarUdf::new(
+        python_component().await,
+        &permissions,
+        Handle::current(),
+        &(Arc::new(UnboundedMemoryPool::default()) as _),
+        CODE.to_owned(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(udfs.len(), 1);
+
+    let urls = ["/redirect1", "/escape", "/loop"].map(|path| format!("{}{path}", server.uri()));
+    let array = udfs[0]
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![ColumnarValue::Array(Arc::new(
+                StringArray::from_iter_values(urls),
+            ))],
+            arg_fields: vec![Arc::new(Field::new("uri", DataType::Utf8, true))],
+            number_rows: 3,
+            return_field: Arc::new(Field::new("r", DataType::Utf8, true)),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+        .unwrap()
+        .unwrap_array();
+
+    assert_eq!(
+        array.as_ref(),
+        &StringArray::from_iter_values(["200: hello world!", "denied", "loop"]) as &dyn Array,
+    );
+}
+
 #[tokio::test]
 async fn test_urllib3_unguarded_fail() {
     const CODE: &str = r#"
@@ -130,7 +494,7 @@ def perform_request(url: str) -> str:
         @r#"
     cannot call function
     caused by
-    Execution error: urllib3.exceptions.ProtocolError: ('Connection aborted.', WasiErrorCode('Request failed with wasi http error ErrorCode_HttpRequestDenied'))
+    External error: Execution error: urllib3.exceptions.ProtocolError: ('Connection aborted.', WasiErrorCode('Request failed with wasi http error ErrorCode_HttpRequestDenied'))
 
     The above exception was the direct cause of the following exception:
 
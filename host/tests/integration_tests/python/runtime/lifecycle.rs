@@ -0,0 +1,491 @@
+use std::{
+    num::NonZeroUsize,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use arrow::{
+    array::{Array, Int64Array},
+    datatypes::{DataType, Field},
+};
+use datafusion_common::{DataFusionError, ScalarValue, config::ConfigOptions};
+use datafusion_execution::memory_pool::UnboundedMemoryPool;
+use datafusion_expr::{ColumnarValue, ScalarFunctionArgs, async_udf::AsyncScalarUDFImpl};
+use datafusion_udf_wasm_host::{
+    ContextLabels, ResourceLimitKind, SandboxInstanceId, SandboxObserver, WasmPermissions,
+    WasmScalarUdf, WasmUdfConfig,
+};
+use tokio::runtime::Handle;
+
+use crate::integration_tests::{
+    python::test_utils::{python_component, python_scalar_udf, python_scalar_udfs},
+    test_utils::ColumnarValueExt,
+};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_init() {
+    const CODE: &str = "
+_lookup = None
+
+def _init():
+    global _lookup
+    _lookup = 42
+
+def foo() -> int:
+    return _lookup
+
+foo.init = _init
+";
+
+    let udf = python_scalar_udf(CODE).await.unwrap();
+    assert_eq!(
+        call(&udf).await.as_ref(),
+        &Int64Array::from_iter([Some(42), Some(42), Some(42)]) as &dyn Array,
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_init_error() {
+    const CODE: &str = "
+def _init():
+    raise ValueError('boom')
+
+def foo() -> int:
+    return 1
+
+foo.init = _init
+";
+
+    let err = python_scalar_udf(CODE).await.unwrap_err().to_string();
+    assert!(err.contains("init `foo`"), "{err}");
+    assert!(err.contains("ValueError: boom"), "{err}");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_hook_not_callable() {
+    const CODE: &str = "
+def foo() -> int:
+    return 1
+
+foo.close = 1
+";
+
+    let err = python_scalar_udf(CODE).await.unwrap_err().to_string();
+    assert!(err.contains("inspect close hook of `foo`"), "{err}");
+    assert!(err.contains("close must be callable"), "{err}");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_close() {
+    const CODE: &str = "
+_closed = 0
+
+def _close():
+    global _closed
+    _closed += 1
+
+def closed() -> int:
+    return _closed
+
+def foo() -> int:
+    return 1
+
+foo.close = _close
+";
+
+    let [closed, foo]: [_; 2] = python_scalar_udfs(CODE).await.unwrap().try_into().unwrap();
+    assert_eq!(
+        call(&closed).await.as_ref(),
+        &Int64Array::from_iter([Some(0), Some(0), Some(0)]) as &dyn Array,
+    );
+
+    // closing happens in the background
+    drop(foo);
+    for _ in 0..100 {
+        let res = call(&closed).await;
+        if res.as_ref() == &Int64Array::from_iter([Some(1), Some(1), Some(1)]) as &dyn Array {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("UDF was not closed");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_observer() {
+    const CODE: &str = r#"
+def write(size: int) -> int:
+    if size == 0:
+        raise ValueError("boom")
+    with open("/test", "w") as fp:
+        fp.write("x" * size)
+    return size
+"#;
+
+    let observer = Arc::new(RecordingObserver::default());
+    let udf = WasmScalarUdf::new(
+        python_component().await,
+        &WasmPermissions::new().with_observer(Arc::clone(&observer) as _),
+        Handle::current(),
+        &(Arc::new(UnboundedMemoryPool::default()) as _),
+        CODE.to_owned(),
+    )
+    .await
+    .unwrap()
+    .pop()
+    .unwrap();
+
+    let write = async |size: i64| {
+        udf.invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![ColumnarValue::Scalar(ScalarValue::Int64(Some(size)))],
+            arg_fields: vec![Arc::new(Field::new("size", DataType::Int64, true))],
+            number_rows: 1,
+            return_field: Arc::new(Field::new("r", DataType::Int64, true)),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+    };
+    write(10_000).await.unwrap();
+    write(0).await.unwrap_err();
+
+    let events = observer.events();
+    assert_eq!(events[0], "created");
+    assert_eq!(events.last().unwrap(), "error");
+    let written = events
+        .iter()
+        .filter_map(|e| e.strip_prefix("vfs_write: "))
+        .map(|bytes| bytes.parse::<u64>().unwrap())
+        .sum::<u64>();
+    assert!(written >= 10_000, "{written}");
+
+    // closing happens in the background
+    drop(write);
+    drop(udf);
+    for _ in 0..100 {
+        if observer.events().last().unwrap() == "destroyed" {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("instance was not destroyed");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_intra_batch_parallelism_error() {
+    const CODE: &str = r#"
+def slow(x: int) -> int:
+    if x == 0:
+        raise ValueError("boom")
+    for _ in range(1_000_000):
+        pass
+    return x
+"#;
+
+    let observer = Arc::new(RecordingObserver::default());
+    let udf = WasmScalarUdf::new(
+        python_component().await,
+        &WasmPermissions::new()
+            .with_intra_batch_parallelism(NonZeroUsize::new(3).unwrap())
+            .with_observer(Arc::clone(&observer) as _),
+        Handle::current(),
+        &(Arc::new(UnboundedMemoryPool::default()) as _),
+        CODE.to_owned(),
+    )
+    .await
+    .unwrap()
+    .pop()
+    .unwrap();
+
+    let slow = async |values: [i64; 3]| {
+        udf.invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![ColumnarValue::Array(Arc::new(
+                Int64Array::from_iter_values(values),
+            ))],
+            arg_fields: vec![Arc::new(Field::new("x", DataType::Int64, true))],
+            number_rows: 3,
+            return_field: Arc::new(Field::new("r", DataType::Int64, true)),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+    };
+
+    let created = || {
+        observer
+            .events()
+            .iter()
+            .filter(|e| e.as_str() == "created")
+            .count()
+    };
+    let created_before = created();
+
+    // the first slice fails right away, while the other ones are still running
+    let err = slow([0, 1, 2]).await.unwrap_err().to_string();
+    assert!(err.contains("boom"), "{err}");
+
+    // the other workers were NOT cancelled, so they are neither poisoned nor re-instantiated
+    let array = slow([1, 2, 3]).await.unwrap().unwrap_array();
+    assert_eq!(
+        array.as_ref(),
+        &Int64Array::from_iter_values([1, 2, 3]) as &dyn Array,
+    );
+    assert_eq!(created(), created_before);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_intra_batch_parallelism_timeout() {
+    const CODE: &str = r#"
+def spin(x: int) -> int:
+    while x == 0:
+        pass
+    return x
+"#;
+
+    let udf = WasmScalarUdf::new(
+        python_component().await,
+        &WasmPermissions::new()
+            .with_intra_batch_parallelism(NonZeroUsize::new(3).unwrap())
+            .with_invoke_timeout(Duration::from_secs(1)),
+        Handle::current(),
+        &(Arc::new(UnboundedMemoryPool::default()) as _),
+        CODE.to_owned(),
+    )
+    .await
+    .unwrap()
+    .pop()
+    .unwrap();
+
+    let spin = async |values: [i64; 3]| {
+        udf.invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![ColumnarValue::Array(Arc::new(
+                Int64Array::from_iter_values(values),
+            ))],
+            arg_fields: vec![Arc::new(Field::new("x", DataType::Int64, true))],
+            number_rows: 3,
+            return_field: Arc::new(Field::new("r", DataType::Int64, true)),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+    };
+
+    // only the last slice spins
+    let err = spin([1, 2, 0]).await.unwrap_err().to_string();
+    assert!(err.contains("deadline has elapsed"), "{err}");
+
+    // only the worker that timed out is poisoned
+    let err = spin([1, 2, 3]).await.unwrap_err().to_string();
+    assert!(err.contains("slice rows 2..3"), "{err}");
+    assert!(err.contains("poisoned"), "{err}");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_suspend() {
+    const CODE: &str = "
+_calls = 0
+
+def _init():
+    global _calls
+    _calls = 100
+
+def count() -> int:
+    global _calls
+    _calls += 1
+    return _calls
+
+count.init = _init
+";
+
+    let udf = python_scalar_udf(CODE).await.unwrap();
+    assert_eq!(
+        call(&udf).await.as_ref(),
+        &Int64Array::from_iter([Some(101), Some(101), Some(101)]) as &dyn Array,
+    );
+    assert!(!udf.is_suspended().await);
+    assert_ne!(udf.resource_usage().await.linear_memory_bytes, 0);
+
+    udf.suspend().await;
+    assert!(udf.is_suspended().await);
+    assert_eq!(udf.resource_usage().await.linear_memory_bytes, 0);
+
+    // state is discarded, but `init` runs again
+    assert_eq!(
+        call(&udf).await.as_ref(),
+        &Int64Array::from_iter([Some(101), Some(101), Some(101)]) as &dyn Array,
+    );
+    assert!(!udf.is_suspended().await);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_idle_ttl() {
+    const CODE: &str = "
+def foo() -> int:
+    return 1
+";
+
+    let udf = WasmScalarUdf::new(
+        python_component().await,
+        &WasmPermissions::new().with_idle_ttl(Duration::from_millis(100)),
+        Handle::current(),
+        &(Arc::new(UnboundedMemoryPool::default()) as _),
+        CODE.to_owned(),
+    )
+    .await
+    .unwrap()
+    .pop()
+    .unwrap();
+    assert!(!udf.is_suspended().await);
+
+    let mut suspended = false;
+    for _ in 0..100 {
+        if udf.is_suspended().await {
+            suspended = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    assert!(suspended, "guest was not suspended");
+
+    assert_eq!(
+        call(&udf).await.as_ref(),
+        &Int64Array::from_iter([Some(1), Some(1), Some(1)]) as &dyn Array,
+    );
+    assert!(!udf.is_suspended().await);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_context_labels() {
+    const CODE: &str = "
+def fail(x: int) -> int:
+    raise ValueError('nope')
+";
+
+    let observer = Arc::new(RecordingObserver::default());
+    let udf = WasmScalarUdf::new(
+        python_component().await,
+        &WasmPermissions::new()
+            .with_observer(Arc::clone(&observer) as _)
+            .with_context_labels(ContextLabels::new().with_label("tenant", "acme")),
+        Handle::current(),
+        &(Arc::new(UnboundedMemoryPool::default()) as _),
+        CODE.to_owned(),
+    )
+    .await
+    .unwrap()
+    .pop()
+    .unwrap();
+
+    let mut config_options = ConfigOptions::new();
+    config_options.extensions.insert(WasmUdfConfig::default());
+    config_options
+        .set("udf_wasm.context_labels", "query_id=42")
+        .unwrap();
+    let err = udf
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![ColumnarValue::Scalar(ScalarValue::Int64(Some(1)))],
+            arg_fields: vec![Arc::new(Field::new("x", DataType::Int64, true))],
+            number_rows: 1,
+            return_field: Arc::new(Field::new("r", DataType::Int64, true)),
+            config_options: Arc::new(config_options),
+        })
+        .await
+        .unwrap_err();
+
+    let DataFusionError::Context(context, _) = &err else {
+        panic!("{err}");
+    };
+    assert_eq!(context, "invoke `fail` [query_id=42, tenant=acme]");
+
+    assert_eq!(
+        observer.labels(),
+        [
+            ("created".to_owned(), "tenant=acme".to_owned()),
+            ("error".to_owned(), "query_id=42, tenant=acme".to_owned()),
+        ],
+    );
+}
+
+/// Records events of a single instance.
+#[derive(Debug, Default)]
+struct RecordingObserver {
+    /// Events with their labels.
+    events: Mutex<Vec<(String, String)>>,
+}
+
+impl RecordingObserver {
+    /// Recorded events.
+    fn events(&self) -> Vec<String> {
+        self.events
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(event, _labels)| event.clone())
+            .collect()
+    }
+
+    /// Recorded events with their labels, excluding VFS writes.
+    fn labels(&self) -> Vec<(String, String)> {
+        self.events
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(event, _labels)| !event.starts_with("vfs_write"))
+            .cloned()
+            .collect()
+    }
+
+    /// Record event.
+    fn record(&self, event: String, labels: &ContextLabels) {
+        self.events
+            .lock()
+            .unwrap()
+            .push((event, labels.to_string()));
+    }
+}
+
+impl SandboxObserver for RecordingObserver {
+    fn instance_created(&self, _instance: SandboxInstanceId, labels: &ContextLabels) {
+        self.record("created".to_owned(), labels);
+    }
+
+    fn instance_destroyed(&self, _instance: SandboxInstanceId, labels: &ContextLabels) {
+        self.record("destroyed".to_owned(), labels);
+    }
+
+    fn vfs_write(&self, _instance: SandboxInstanceId, labels: &ContextLabels, bytes: u64) {
+        self.record(format!("vfs_write: {bytes}"), labels);
+    }
+
+    fn limit_exceeded(
+        &self,
+        _instance: SandboxInstanceId,
+        labels: &ContextLabels,
+        kind: ResourceLimitKind,
+    ) {
+        self.record(format!("limit_exceeded: {kind:?}"), labels);
+    }
+
+    fn timeout(&self, _instance: SandboxInstanceId, labels: &ContextLabels) {
+        self.record("timeout".to_owned(), labels);
+    }
+
+    fn guest_error(
+        &self,
+        _instance: SandboxInstanceId,
+        labels: &ContextLabels,
+        _error: &DataFusionError,
+    ) {
+        self.record("error".to_owned(), labels);
+    }
+}
+
+async fn call(udf: &WasmScalarUdf) -> Arc<dyn Array> {
+    udf.invoke_async_with_args(ScalarFunctionArgs {
+        args: vec![],
+        arg_fields: vec![],
+        number_rows: 3,
+        return_field: Arc::new(Field::new("r", DataType::Int64, true)),
+        config_options: Arc::new(ConfigOptions::default()),
+    })
+    .await
+    .unwrap()
+    .unwrap_array()
+}
@@ -1,6 +1,12 @@
+mod clocks;
+mod config;
 mod dependencies;
 mod env;
 mod errors;
 mod fs;
 mod http;
+mod lifecycle;
 mod null_handling;
+mod preinit;
+mod random;
+mod volatility;
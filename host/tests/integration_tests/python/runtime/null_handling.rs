@@ -4,7 +4,7 @@ use arrow::{
     array::{Array, ArrayRef, Int64Array},
     datatypes::{DataType, Field},
 };
-use datafusion_common::config::ConfigOptions;
+use datafusion_common::{ScalarValue, config::ConfigOptions};
 use datafusion_expr::{ColumnarValue, ScalarFunctionArgs, async_udf::AsyncScalarUDFImpl};
 
 use crate::integration_tests::{
@@ -210,6 +210,35 @@ def add(x: Optional[int], y: Optional[int]) -> Optional[int]:
     );
 }
 
+/// Functions without optional parameters are null-strict, so the host skips NULL rows without calling the guest.
+#[tokio::test]
+async fn test_no_optionals_null_scalar() {
+    const CODE: &str = "
+def add(x: int, y: int) -> int:
+    return x + y
+";
+
+    let udf = python_scalar_udf(CODE).await.unwrap();
+    let scalar = udf
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![
+                ColumnarValue::Array(Arc::new(Int64Array::from_iter([Some(1), Some(2)]))),
+                ColumnarValue::Scalar(ScalarValue::Int64(None)),
+            ],
+            arg_fields: vec![
+                Arc::new(Field::new("x", DataType::Int64, true)),
+                Arc::new(Field::new("y", DataType::Int64, true)),
+            ],
+            number_rows: 2,
+            return_field: Arc::new(Field::new("r", DataType::Int64, true)),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+        .unwrap()
+        .unwrap_scalar();
+    assert_eq!(scalar, ScalarValue::Int64(None));
+}
+
 async fn xy_null_test(code: &str) -> ArrayRef {
     let udf = python_scalar_udf(code).await.unwrap();
     udf.invoke_async_with_args(ScalarFunctionArgs {
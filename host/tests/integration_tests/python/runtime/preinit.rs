@@ -0,0 +1,91 @@
+use std::sync::Arc;
+
+use arrow::{
+    array::{Array, Int64Array, StringArray},
+    datatypes::{DataType, Field},
+};
+use datafusion_common::config::ConfigOptions;
+use datafusion_execution::memory_pool::UnboundedMemoryPool;
+use datafusion_expr::{ColumnarValue, ScalarFunctionArgs, async_udf::AsyncScalarUDFImpl};
+use datafusion_udf_wasm_host::{WasmPermissions, WasmScalarUdf};
+use tokio::runtime::Handle;
+
+use crate::integration_tests::{
+    python::test_utils::python_preinit_component, test_utils::ColumnarValueExt,
+};
+
+#[tokio::test]
+async fn test_call() {
+    const CODE: &str = "
+def add_one(x: int) -> int:
+    return x + 1
+";
+
+    let udf = preinit_udf(CODE, WasmPermissions::new()).await;
+    let array = udf
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![ColumnarValue::Array(Arc::new(Int64Array::from_iter([
+                Some(1),
+                None,
+            ])))],
+            arg_fields: vec![Arc::new(Field::new("a1", DataType::Int64, true))],
+            number_rows: 2,
+            return_field: Arc::new(Field::new("r", DataType::Int64, true)),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+        .unwrap()
+        .unwrap_array();
+    assert_eq!(
+        array.as_ref(),
+        &Int64Array::from_iter([Some(2), None]) as &dyn Array,
+    );
+}
+
+#[tokio::test]
+async fn test_env_is_not_snapshotted() {
+    const CODE: &str = r#"
+import os
+
+def env() -> str:
+    return ",".join(f"{k}:{v}" for k, v in os.environ.items())
+"#;
+
+    for (k, v) in [("FOO", "BAR"), ("X", "Y")] {
+        let udf = preinit_udf(
+            CODE,
+            WasmPermissions::new().with_env(k.to_owned(), v.to_owned()),
+        )
+        .await;
+        let array = udf
+            .invoke_async_with_args(ScalarFunctionArgs {
+                args: vec![],
+                arg_fields: vec![],
+                number_rows: 1,
+                return_field: Arc::new(Field::new("r", DataType::Utf8, true)),
+                config_options: Arc::new(ConfigOptions::default()),
+            })
+            .await
+            .unwrap()
+            .unwrap_array();
+        assert_eq!(
+            array.as_ref(),
+            &StringArray::from_iter_values([format!("{k}:{v}")]) as &dyn Array,
+        );
+    }
+}
+
+/// Create UDF from the pre-initialized Python component.
+async fn preinit_udf(code: &str, permissions: WasmPermissions) -> WasmScalarUdf {
+    let udfs = WasmScalarUdf::new(
+        python_preinit_component().await,
+        &permissions,
+        Handle::current(),
+        &(Arc::new(UnboundedMemoryPool::default()) as _),
+        code.to_owned(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(udfs.len(), 1);
+    udfs.into_iter().next().unwrap()
+}
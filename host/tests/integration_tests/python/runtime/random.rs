@@ -0,0 +1,68 @@
+use std::sync::Arc;
+
+use arrow::datatypes::{DataType, Field};
+use datafusion_common::{cast::as_string_array, config::ConfigOptions};
+use datafusion_execution::memory_pool::UnboundedMemoryPool;
+use datafusion_expr::{ScalarFunctionArgs, async_udf::AsyncScalarUDFImpl};
+use datafusion_udf_wasm_host::{RandomPolicy, WasmPermissions, WasmScalarUdf};
+use tokio::runtime::Handle;
+
+use crate::integration_tests::{
+    python::test_utils::python_component, test_utils::ColumnarValueExt,
+};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_seeded_random_is_reproducible() {
+    let a = observe_random(RandomPolicy::Seeded(42)).await;
+    let b = observe_random(RandomPolicy::Seeded(42)).await;
+    let c = observe_random(RandomPolicy::Seeded(1337)).await;
+
+    assert_eq!(a, b);
+    assert_ne!(a, c);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_host_entropy_random_differs() {
+    let a = observe_random(RandomPolicy::HostEntropy).await;
+    let b = observe_random(RandomPolicy::HostEntropy).await;
+
+    assert_ne!(a, b);
+}
+
+async fn observe_random(policy: RandomPolicy) -> String {
+    const CODE: &str = r#"
+import os
+import random
+
+def rnd() -> str:
+    return f"{random.random()},{os.urandom(8).hex()}"
+"#;
+
+    let component = python_component().await;
+
+    let udfs = WasmScalarUdf::new(
+        component,
+        &WasmPermissions::default().with_random_policy(policy),
+        Handle::current(),
+        &(Arc::new(UnboundedMemoryPool::default()) as _),
+        CODE.to_owned(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(udfs.len(), 1);
+    let udf = udfs.into_iter().next().unwrap();
+
+    let array = udf
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![],
+            arg_fields: vec![],
+            number_rows: 1,
+            return_field: Arc::new(Field::new("r", DataType::Utf8, true)),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+        .unwrap()
+        .unwrap_array();
+
+    as_string_array(&array).unwrap().value(0).to_owned()
+}
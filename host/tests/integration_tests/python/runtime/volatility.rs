@@ -0,0 +1,413 @@
+use std::sync::Arc;
+
+use arrow::{
+    array::{Array, DictionaryArray, Int32Array, Int64Array, StringArray},
+    datatypes::{DataType, Field, Int32Type},
+};
+use datafusion_common::{
+    DataFusionError, ScalarValue, cast::as_string_array, config::ConfigOptions,
+};
+use datafusion_expr::{
+    ColumnarValue, ScalarFunctionArgs, ScalarUDFImpl, Volatility,
+    async_udf::AsyncScalarUDFImpl,
+    col,
+    execution_props::ExecutionProps,
+    lit,
+    simplify::{ExprSimplifyResult, SimplifyContext},
+};
+use datafusion_udf_wasm_host::WasmScalarUdf;
+
+use crate::integration_tests::{
+    python::test_utils::{python_scalar_udf, python_scalar_udfs},
+    test_utils::{ColumnarValueExt, FullError},
+};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_declared_volatility() {
+    const CODE: &str = r#"
+def f_default() -> int:
+    return 1
+
+def f_immutable() -> int:
+    return 1
+
+def f_stable() -> int:
+    return 1
+
+def f_volatile() -> int:
+    return 1
+
+f_immutable.volatility = "immutable"
+f_stable.volatility = "stable"
+f_volatile.volatility = "volatile"
+"#;
+
+    let udfs = python_scalar_udfs(CODE).await.unwrap();
+    let actual = udfs
+        .iter()
+        .map(|udf| (udf.name(), udf.signature().volatility))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        actual,
+        [
+            ("f_default", Volatility::Volatile),
+            ("f_immutable", Volatility::Immutable),
+            ("f_stable", Volatility::Stable),
+            ("f_volatile", Volatility::Volatile),
+        ],
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_constant_folding() {
+    const CODE: &str = r#"
+def f_immutable(x: int) -> int:
+    return x + 1
+
+def f_volatile(x: int) -> int:
+    return x + 1
+
+f_immutable.volatility = "immutable"
+"#;
+
+    let udfs = python_scalar_udfs(CODE).await.unwrap();
+    let props = ExecutionProps::new();
+    let info = SimplifyContext::new(&props);
+
+    // immutable UDFs are evaluated at planning time
+    let ExprSimplifyResult::Simplified(expr) = udfs[0].simplify(vec![lit(1i64)], &info).unwrap()
+    else {
+        panic!("immutable UDF was not folded");
+    };
+    assert_eq!(expr, lit(2i64));
+
+    // ... but only for constant arguments
+    assert!(matches!(
+        udfs[0].simplify(vec![col("x")], &info).unwrap(),
+        ExprSimplifyResult::Original(_),
+    ));
+
+    // volatile UDFs could tell the difference
+    assert!(matches!(
+        udfs[1].simplify(vec![lit(1i64)], &info).unwrap(),
+        ExprSimplifyResult::Original(_),
+    ));
+
+    // folding does NOT rely on synchronous invocation
+    let err = udfs[0]
+        .invoke_with_args(ScalarFunctionArgs {
+            args: vec![ColumnarValue::Scalar(ScalarValue::Int64(Some(1)))],
+            arg_fields: vec![Arc::new(Field::new("x", DataType::Int64, true))],
+            number_rows: 1,
+            return_field: Arc::new(Field::new("r", DataType::Int64, true)),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .unwrap_err();
+    insta::assert_snapshot!(
+        err,
+        @"This feature is not implemented: synchronous invocation of WasmScalarUdf is not supported, use invoke_async_with_args instead",
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_invalid_volatility() {
+    const CODE: &str = r#"
+def foo() -> int:
+    return 1
+
+foo.volatility = "pure"
+"#;
+
+    let err = python_scalar_udf(CODE).await.unwrap_err();
+    let err = err.to_string();
+    assert!(err.contains("inspect volatility of `foo`"), "{err}");
+    assert!(
+        err.contains("volatility must be one of `immutable`, `stable`, or `volatile`, got `pure`"),
+        "{err}",
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_immutable_clock_stands_still() {
+    const CODE: &str = r#"
+import time
+
+def now() -> str:
+    return f"{time.time()}"
+
+now.volatility = "immutable"
+"#;
+
+    let udf = python_scalar_udf(CODE).await.unwrap();
+    assert_eq!(invoke(&udf).await.unwrap(), "0.0");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_immutable_random_denied() {
+    const CODE: &str = r#"
+import os
+
+def rnd() -> str:
+    return os.urandom(8).hex()
+
+rnd.volatility = "immutable"
+"#;
+
+    let udf = python_scalar_udf(CODE).await.unwrap();
+    let err = FullError::new(invoke(&udf).await.unwrap_err()).to_string();
+    assert!(
+        err.contains("access to randomness denied for immutable UDF"),
+        "{err}",
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_override_volatility() {
+    const CODE: &str = r#"
+import time
+
+def now() -> str:
+    return f"{time.time()}"
+"#;
+
+    let udf = python_scalar_udf(CODE).await.unwrap();
+    assert_eq!(udf.signature().volatility, Volatility::Volatile);
+    assert_ne!(invoke(&udf).await.unwrap(), "0.0");
+
+    let udf = udf.with_volatility(Volatility::Immutable);
+    assert_eq!(udf.signature().volatility, Volatility::Immutable);
+    assert_eq!(invoke(&udf).await.unwrap(), "0.0");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_scalar_fast_path() {
+    const CODE: &str = r#"
+_calls = 0
+
+def count(x: int) -> int:
+    global _calls
+    _calls += 1
+    return _calls
+
+def count_immutable(x: int) -> int:
+    global _calls
+    _calls += 1
+    return _calls
+
+count_immutable.volatility = "immutable"
+"#;
+
+    let [count, count_immutable]: [_; 2] =
+        python_scalar_udfs(CODE).await.unwrap().try_into().unwrap();
+
+    let args = || ScalarFunctionArgs {
+        args: vec![ColumnarValue::Scalar(ScalarValue::Int64(Some(1)))],
+        arg_fields: vec![Arc::new(Field::new("x", DataType::Int64, true))],
+        number_rows: 3,
+        return_field: Arc::new(Field::new("r", DataType::Int64, true)),
+        config_options: Arc::new(ConfigOptions::default()),
+    };
+
+    // volatile UDFs are evaluated for every row
+    let array = count
+        .invoke_async_with_args(args())
+        .await
+        .unwrap()
+        .unwrap_array();
+    assert_eq!(
+        array.as_ref(),
+        &Int64Array::from_iter([Some(1), Some(2), Some(3)]) as &dyn Array,
+    );
+
+    // constant inputs of non-volatile UDFs are evaluated once
+    let scalar = count_immutable
+        .invoke_async_with_args(args())
+        .await
+        .unwrap()
+        .unwrap_scalar();
+    assert_eq!(scalar, ScalarValue::Int64(Some(4)));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_dictionary_values_only() {
+    const CODE: &str = r#"
+_calls = 0
+
+def upper(s: str) -> str:
+    global _calls
+    _calls += 1
+    return s.upper()
+
+def calls() -> int:
+    return _calls
+
+upper.volatility = "immutable"
+"#;
+
+    let [upper, calls]: [_; 2] = python_scalar_udfs(CODE).await.unwrap().try_into().unwrap();
+
+    let array = upper
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![ColumnarValue::Array(Arc::new(
+                ["a", "b", "a", "b", "a"]
+                    .into_iter()
+                    .map(Some)
+                    .chain([None])
+                    .collect::<DictionaryArray<Int32Type>>(),
+            ))],
+            arg_fields: vec![Arc::new(Field::new(
+                "s",
+                DataType::Dictionary(Box::new(DataType::Int32), Box::new(DataType::Utf8)),
+                true,
+            ))],
+            number_rows: 6,
+            return_field: Arc::new(Field::new("r", DataType::Utf8, true)),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+        .unwrap()
+        .unwrap_array();
+    assert_eq!(
+        array.as_ref(),
+        &StringArray::from_iter([Some("A"), Some("B"), Some("A"), Some("B"), Some("A"), None])
+            as &dyn Array,
+    );
+
+    let array = calls
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![],
+            arg_fields: vec![],
+            number_rows: 1,
+            return_field: Arc::new(Field::new("r", DataType::Int64, true)),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+        .unwrap()
+        .unwrap_array();
+    assert_eq!(
+        array.as_ref(),
+        &Int64Array::from_iter([Some(2)]) as &dyn Array,
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_dictionary_null_keys() {
+    const CODE: &str = r#"
+_calls = 0
+
+def label(s: str | None) -> str:
+    global _calls
+    _calls += 1
+    return "n/a" if s is None else s.upper()
+
+def calls() -> int:
+    return _calls
+
+label.volatility = "immutable"
+"#;
+
+    let [label, calls]: [_; 2] = python_scalar_udfs(CODE).await.unwrap().try_into().unwrap();
+
+    // the UDF is NOT null-strict, so NULL keys must see a NULL value
+    let array = label
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![ColumnarValue::Array(Arc::new(
+                [Some("a"), None, Some("b"), Some("a"), None, Some("b")]
+                    .into_iter()
+                    .collect::<DictionaryArray<Int32Type>>(),
+            ))],
+            arg_fields: vec![Arc::new(Field::new(
+                "s",
+                DataType::Dictionary(Box::new(DataType::Int32), Box::new(DataType::Utf8)),
+                true,
+            ))],
+            number_rows: 6,
+            return_field: Arc::new(Field::new("r", DataType::Utf8, true)),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+        .unwrap()
+        .unwrap_array();
+    assert_eq!(
+        array.as_ref(),
+        &StringArray::from_iter([
+            Some("A"),
+            Some("n/a"),
+            Some("B"),
+            Some("A"),
+            Some("n/a"),
+            Some("B"),
+        ]) as &dyn Array,
+    );
+
+    // two values plus one NULL
+    let array = calls
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![],
+            arg_fields: vec![],
+            number_rows: 1,
+            return_field: Arc::new(Field::new("r", DataType::Int64, true)),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+        .unwrap()
+        .unwrap_array();
+    assert_eq!(
+        array.as_ref(),
+        &Int64Array::from_iter([Some(3)]) as &dyn Array,
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_dictionary_unreferenced_values() {
+    const CODE: &str = r#"
+def parse(s: str) -> int:
+    return int(s)
+
+parse.volatility = "immutable"
+"#;
+
+    let parse = python_scalar_udf(CODE).await.unwrap();
+
+    // `oops` is NOT referenced by any key, so the UDF must NOT be called for it
+    let dictionary = DictionaryArray::<Int32Type>::try_new(
+        Int32Array::from_iter_values([0, 1, 0, 1, 0]),
+        Arc::new(StringArray::from_iter_values(["1", "2", "oops"])),
+    )
+    .unwrap();
+    let array = parse
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![ColumnarValue::Array(Arc::new(dictionary))],
+            arg_fields: vec![Arc::new(Field::new(
+                "s",
+                DataType::Dictionary(Box::new(DataType::Int32), Box::new(DataType::Utf8)),
+                true,
+            ))],
+            number_rows: 5,
+            return_field: Arc::new(Field::new("r", DataType::Int64, true)),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+        .unwrap()
+        .unwrap_array();
+    assert_eq!(
+        array.as_ref(),
+        &Int64Array::from_iter_values([1, 2, 1, 2, 1]) as &dyn Array,
+    );
+}
+
+/// Invoke UDF without arguments and return the resulting string.
+async fn invoke(udf: &WasmScalarUdf) -> Result<String, DataFusionError> {
+    let array = udf
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![],
+            arg_fields: vec![],
+            number_rows: 1,
+            return_field: Arc::new(Field::new("r", DataType::Utf8, true)),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await?
+        .unwrap_array();
+
+    Ok(as_string_array(&array).unwrap().value(0).to_owned())
+}
@@ -93,6 +93,41 @@ async fn test_precompile_is_stateless() {
     );
 }
 
+#[tokio::test]
+async fn test_module_state() {
+    const CODE: &str = "
+import math
+from functools import reduce
+
+SCALE = 10
+_calls = 0
+
+def _count() -> int:
+    global _calls
+    _calls += 1
+    return _calls
+
+def _scale(x: int) -> int:
+    return x * SCALE
+
+def g1() -> int:
+    return _scale(_count())
+
+def g2() -> int:
+    return -math.floor(_count())
+";
+
+    let [g1, g2]: [_; 2] = python_scalar_udfs(CODE).await.unwrap().try_into().unwrap();
+    assert_eq!(
+        call(&g1).await.as_ref(),
+        &Int64Array::from_iter([Some(10), Some(20), Some(30)]) as &dyn Array,
+    );
+    assert_eq!(
+        call(&g2).await.as_ref(),
+        &Int64Array::from_iter([Some(-4), Some(-5), Some(-6)]) as &dyn Array,
+    );
+}
+
 async fn udfs() -> [impl AsyncScalarUDFImpl; 2] {
     python_scalar_udfs(CODE).await.unwrap().try_into().unwrap()
 }
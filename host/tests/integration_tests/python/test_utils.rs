@@ -12,18 +12,39 @@ use crate::integration_tests::test_utils::FullError;
 const MEMORY_LIMIT: usize = 500_000_000;
 
 /// Static precompiled Python WASM component for tests
-static COMPONENT: OnceCell<WasmComponentPrecompiled> = OnceCell::const_new();
+static COMPONENT: OnceCell<Arc<WasmComponentPrecompiled>> = OnceCell::const_new();
 
 /// Returns a static reference to the precompiled Python WASM component.
-pub(crate) async fn python_component() -> &'static WasmComponentPrecompiled {
+pub(crate) async fn python_component() -> &'static Arc<WasmComponentPrecompiled> {
     COMPONENT
         .get_or_init(async || {
-            WasmComponentPrecompiled::compile(
-                datafusion_udf_wasm_bundle::BIN_PYTHON.into(),
-                &CompilationFlags::default(),
+            Arc::new(
+                WasmComponentPrecompiled::compile(
+                    datafusion_udf_wasm_bundle::BIN_PYTHON.into(),
+                    &CompilationFlags::default(),
+                )
+                .await
+                .unwrap(),
+            )
+        })
+        .await
+}
+
+/// Static precompiled Python WASM component with a pre-initialized interpreter for tests
+static COMPONENT_PREINIT: OnceCell<Arc<WasmComponentPrecompiled>> = OnceCell::const_new();
+
+/// Returns a static reference to the precompiled Python WASM component with a pre-initialized interpreter.
+pub(crate) async fn python_preinit_component() -> &'static Arc<WasmComponentPrecompiled> {
+    COMPONENT_PREINIT
+        .get_or_init(async || {
+            Arc::new(
+                WasmComponentPrecompiled::compile(
+                    datafusion_udf_wasm_bundle::BIN_PYTHON_PREINIT.into(),
+                    &CompilationFlags::default(),
+                )
+                .await
+                .unwrap(),
             )
-            .await
-            .unwrap()
         })
         .await
 }
@@ -1,7 +1,7 @@
 use std::sync::Arc;
 
 use arrow::{
-    array::BinaryArray,
+    array::{BinaryArray, FixedSizeBinaryArray, Int64Array},
     datatypes::{DataType, Field},
 };
 use datafusion_common::config::ConfigOptions;
@@ -110,3 +110,115 @@ def foo(x: bytes) -> bytes:
         @"Execution error: expected `bytes` but got `42` of type `int`",
     );
 }
+
+#[tokio::test]
+async fn test_fixed_size_ok() {
+    const CODE: &str = "
+from typing import Annotated
+
+def foo(x: Annotated[bytes, 2]) -> Annotated[bytes, 3]:
+    return x + b'!'
+";
+    let udf = python_scalar_udf(CODE).await.unwrap();
+
+    assert_eq!(
+        udf.signature(),
+        &Signature::exact(vec![DataType::FixedSizeBinary(2)], Volatility::Volatile),
+    );
+
+    assert_eq!(
+        udf.return_type(&[DataType::FixedSizeBinary(2)]).unwrap(),
+        DataType::FixedSizeBinary(3),
+    );
+
+    let array = udf
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![ColumnarValue::Array(Arc::new(
+                FixedSizeBinaryArray::try_from_sparse_iter_with_size(
+                    [Some(b"ab".as_slice()), None, Some(b"cd".as_slice())].into_iter(),
+                    2,
+                )
+                .unwrap(),
+            ))],
+            arg_fields: vec![Arc::new(Field::new(
+                "a1",
+                DataType::FixedSizeBinary(2),
+                true,
+            ))],
+            number_rows: 3,
+            return_field: Arc::new(Field::new("r", DataType::FixedSizeBinary(3), true)),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+        .unwrap()
+        .unwrap_array();
+    assert_eq!(
+        array.as_ref(),
+        &FixedSizeBinaryArray::try_from_sparse_iter_with_size(
+            [Some(b"ab!".as_slice()), None, Some(b"cd!".as_slice())].into_iter(),
+            3,
+        )
+        .unwrap(),
+    );
+}
+
+#[tokio::test]
+async fn test_fixed_size_arg_as_bytes() {
+    const CODE: &str = "
+def foo(x: bytes) -> int:
+    return len(x)
+";
+    let udf = python_scalar_udf(CODE).await.unwrap();
+
+    assert_eq!(
+        udf.return_type(&[DataType::FixedSizeBinary(16)]).unwrap(),
+        DataType::Int64,
+    );
+
+    let array = udf
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![ColumnarValue::Array(Arc::new(
+                FixedSizeBinaryArray::try_from_iter([[0u8; 16], [1u8; 16]].into_iter()).unwrap(),
+            ))],
+            arg_fields: vec![Arc::new(Field::new(
+                "a1",
+                DataType::FixedSizeBinary(16),
+                true,
+            ))],
+            number_rows: 2,
+            return_field: Arc::new(Field::new("r", DataType::Int64, true)),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+        .unwrap()
+        .unwrap_array();
+    assert_eq!(array.as_ref(), &Int64Array::from_iter([Some(16), Some(16)]),);
+}
+
+#[tokio::test]
+async fn test_fixed_size_return_wrong_length() {
+    const CODE: &str = "
+from typing import Annotated
+
+def foo(x: bytes) -> Annotated[bytes, 4]:
+    return x
+";
+    let udf = python_scalar_udf(CODE).await.unwrap();
+
+    let err = udf
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![ColumnarValue::Array(Arc::new(BinaryArray::from_iter([
+                Some(b"hello".as_slice()),
+            ])))],
+            arg_fields: vec![Arc::new(Field::new("a1", DataType::Binary, true))],
+            number_rows: 1,
+            return_field: Arc::new(Field::new("r", DataType::FixedSizeBinary(4), true)),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+        .unwrap_err();
+    insta::assert_snapshot!(
+        err,
+        @"Execution error: expected 4 bytes but got 5",
+    );
+}
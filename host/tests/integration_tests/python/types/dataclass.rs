@@ -0,0 +1,116 @@
+use std::sync::Arc;
+
+use arrow::{
+    array::{Array, Int64Array, StringArray, StructArray},
+    datatypes::{DataType, Field, Fields},
+};
+use datafusion_common::config::ConfigOptions;
+use datafusion_expr::{
+    ColumnarValue, ScalarFunctionArgs, ScalarUDFImpl, Signature, Volatility,
+    async_udf::AsyncScalarUDFImpl,
+};
+
+use crate::integration_tests::{
+    python::test_utils::python_scalar_udf, test_utils::ColumnarValueExt,
+};
+
+#[tokio::test]
+async fn test_ok() {
+    const CODE: &str = "
+from dataclasses import dataclass
+
+@dataclass
+class Version:
+    major: int
+    minor: int
+    label: str | None
+
+def bump(v: Version) -> Version:
+    return Version(v.major, v.minor + 1, v.label)
+";
+    let udf = python_scalar_udf(CODE).await.unwrap();
+
+    assert_eq!(
+        udf.signature(),
+        &Signature::exact(vec![DataType::Struct(fields())], Volatility::Volatile),
+    );
+    assert_eq!(
+        udf.return_type(&[DataType::Struct(fields())]).unwrap(),
+        DataType::Struct(fields()),
+    );
+
+    let array = udf
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![ColumnarValue::Array(Arc::new(StructArray::new(
+                fields(),
+                vec![
+                    Arc::new(Int64Array::from(vec![1, 2])),
+                    Arc::new(Int64Array::from(vec![0, 9])),
+                    Arc::new(StringArray::from(vec![Some("rc1"), None])),
+                ],
+                None,
+            )))],
+            arg_fields: vec![Arc::new(Field::new("a1", DataType::Struct(fields()), true))],
+            number_rows: 2,
+            return_field: Arc::new(Field::new("r", DataType::Struct(fields()), true)),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+        .unwrap()
+        .unwrap_array();
+    assert_eq!(
+        array.as_ref(),
+        &StructArray::new(
+            fields(),
+            vec![
+                Arc::new(Int64Array::from(vec![1, 2])),
+                Arc::new(Int64Array::from(vec![1, 10])),
+                Arc::new(StringArray::from(vec![Some("rc1"), None])),
+            ],
+            None,
+        ) as &dyn Array,
+    );
+}
+
+#[tokio::test]
+async fn test_returning_other_type_fails() {
+    const CODE: &str = "
+from dataclasses import dataclass
+
+@dataclass
+class Version:
+    major: int
+    minor: int
+    label: str | None
+
+def parse(s: str) -> Version:
+    return {'major': 1, 'minor': 0, 'label': None}
+";
+    let udf = python_scalar_udf(CODE).await.unwrap();
+
+    let err = udf
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![ColumnarValue::Array(Arc::new(StringArray::from(vec![
+                "1.0",
+            ])))],
+            arg_fields: vec![Arc::new(Field::new("a1", DataType::Utf8, true))],
+            number_rows: 1,
+            return_field: Arc::new(Field::new("r", DataType::Struct(fields()), true)),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+        .unwrap_err();
+    insta::assert_snapshot!(
+        err,
+        @"Execution error: expected instance of `<class '__main__.Version'>` of type `type` but got `{'major': 1, 'minor': 0, 'label': None}` of type `dict`",
+    );
+}
+
+/// Struct fields of `Version`.
+fn fields() -> Fields {
+    Fields::from(vec![
+        Field::new("major", DataType::Int64, false),
+        Field::new("minor", DataType::Int64, false),
+        Field::new("label", DataType::Utf8, true),
+    ])
+}
@@ -218,3 +218,148 @@ def foo(x: datetime) -> datetime:
         @"Execution error: expected `datetime` but got `1970-01-01` of type `date`",
     );
 }
+
+#[tokio::test]
+async fn test_tz_ok() {
+    const CODE: &str = "
+from datetime import datetime, timedelta
+from typing import Annotated
+
+def foo(x: Annotated[datetime, \"+01:00\"]) -> Annotated[datetime, \"UTC\"]:
+    assert x.utcoffset() == timedelta(hours=1)
+    return x + timedelta(days=1)
+";
+    let udf = python_scalar_udf(CODE).await.unwrap();
+
+    assert_eq!(
+        udf.signature(),
+        &Signature::exact(
+            vec![DataType::Timestamp(
+                TimeUnit::Microsecond,
+                Some("+01:00".into())
+            )],
+            Volatility::Volatile
+        ),
+    );
+
+    assert_eq!(
+        udf.return_type(&[DataType::Timestamp(
+            TimeUnit::Microsecond,
+            Some("+01:00".into())
+        )])
+        .unwrap(),
+        DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
+    );
+
+    let array = udf
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![ColumnarValue::Array(Arc::new(
+                TimestampMicrosecondArray::from_iter([
+                    Some(1),
+                    None,
+                    Some(1_757_520_791_123_456),
+                    Some(-100_000_000_000),
+                ])
+                .with_timezone("+01:00"),
+            ))],
+            arg_fields: vec![Arc::new(Field::new(
+                "a1",
+                DataType::Timestamp(TimeUnit::Microsecond, Some("+01:00".into())),
+                true,
+            ))],
+            number_rows: 4,
+            return_field: Arc::new(Field::new(
+                "r",
+                DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
+                true,
+            )),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+        .unwrap()
+        .unwrap_array();
+    assert_eq!(
+        array.as_ref(),
+        &TimestampMicrosecondArray::from_iter([
+            Some(86400000001),
+            None,
+            Some(1757607191123456),
+            Some(-13600000000)
+        ])
+        .with_timezone("UTC"),
+    );
+}
+
+#[tokio::test]
+async fn test_tz_pass_array_without_tz() {
+    const CODE: &str = "
+from datetime import datetime
+from typing import Annotated
+
+def foo(x: Annotated[datetime, \"UTC\"]) -> Annotated[datetime, \"UTC\"]:
+    return x
+";
+    let udf = python_scalar_udf(CODE).await.unwrap();
+
+    let err = udf
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![ColumnarValue::Array(Arc::new(
+                TimestampMicrosecondArray::from_iter([Some(1)]),
+            ))],
+            arg_fields: vec![Arc::new(Field::new(
+                "a1",
+                DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
+                true,
+            ))],
+            number_rows: 1,
+            return_field: Arc::new(Field::new(
+                "r",
+                DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
+                true,
+            )),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+        .unwrap_err();
+    insta::assert_snapshot!(
+        err,
+        @"Execution error: expected time zone UTC but got none",
+    );
+}
+
+#[tokio::test]
+async fn test_tz_return_naive() {
+    const CODE: &str = "
+from datetime import datetime
+from typing import Annotated
+
+def foo(x: Annotated[datetime, \"UTC\"]) -> Annotated[datetime, \"UTC\"]:
+    return x.replace(tzinfo=None)
+";
+    let udf = python_scalar_udf(CODE).await.unwrap();
+
+    let err = udf
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![ColumnarValue::Array(Arc::new(
+                TimestampMicrosecondArray::from_iter([Some(1)]).with_timezone("UTC"),
+            ))],
+            arg_fields: vec![Arc::new(Field::new(
+                "a1",
+                DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
+                true,
+            ))],
+            number_rows: 1,
+            return_field: Arc::new(Field::new(
+                "r",
+                DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
+                true,
+            )),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+        .unwrap_err();
+    insta::assert_snapshot!(
+        err,
+        @"Execution error: expected time zone aware `datetime` but got `1970-01-01 00:00:00.000001` of type `datetime`",
+    );
+}
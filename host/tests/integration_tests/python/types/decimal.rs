@@ -0,0 +1,181 @@
+use std::sync::Arc;
+
+use arrow::{
+    array::{Array, Decimal128Array, Decimal256Array, Int64Array},
+    datatypes::{DataType, Field, i256},
+};
+use datafusion_common::config::ConfigOptions;
+use datafusion_expr::{
+    ColumnarValue, ScalarFunctionArgs, ScalarUDFImpl, Signature, Volatility,
+    async_udf::AsyncScalarUDFImpl,
+};
+
+use crate::integration_tests::{
+    python::test_utils::python_scalar_udf, test_utils::ColumnarValueExt,
+};
+
+#[tokio::test]
+async fn test_ok() {
+    const CODE: &str = "
+from decimal import Decimal
+from typing import Annotated
+
+def foo(x: Annotated[Decimal, (10, 2)]) -> Annotated[Decimal, (12, 3)]:
+    return x * 2
+";
+    let udf = python_scalar_udf(CODE).await.unwrap();
+
+    assert_eq!(
+        udf.signature(),
+        &Signature::exact(vec![DataType::Decimal128(10, 2)], Volatility::Volatile),
+    );
+    assert_eq!(
+        udf.return_type(&[DataType::Decimal128(10, 2)]).unwrap(),
+        DataType::Decimal128(12, 3),
+    );
+
+    let array = udf
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![ColumnarValue::Array(Arc::new(
+                Decimal128Array::from(vec![Some(150), None, Some(-12_345), Some(0)])
+                    .with_precision_and_scale(10, 2)
+                    .unwrap(),
+            ))],
+            arg_fields: vec![Arc::new(Field::new(
+                "a1",
+                DataType::Decimal128(10, 2),
+                true,
+            ))],
+            number_rows: 4,
+            return_field: Arc::new(Field::new("r", DataType::Decimal128(12, 3), true)),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+        .unwrap()
+        .unwrap_array();
+    assert_eq!(
+        array.as_ref(),
+        &Decimal128Array::from(vec![Some(3_000), None, Some(-246_900), Some(0)])
+            .with_precision_and_scale(12, 3)
+            .unwrap() as &dyn Array,
+    );
+}
+
+#[tokio::test]
+async fn test_decimal256() {
+    const CODE: &str = "
+from decimal import Decimal
+from typing import Annotated
+
+def foo(x: Annotated[Decimal, (50, 2)]) -> Annotated[Decimal, (50, 2)]:
+    return x
+";
+    let udf = python_scalar_udf(CODE).await.unwrap();
+
+    assert_eq!(
+        udf.signature(),
+        &Signature::exact(vec![DataType::Decimal256(50, 2)], Volatility::Volatile),
+    );
+    assert_eq!(
+        udf.return_type(&[DataType::Decimal256(50, 2)]).unwrap(),
+        DataType::Decimal256(50, 2),
+    );
+
+    // exceeds the range of `i128`
+    let large = i256::from_i128(i128::MAX).wrapping_mul(i256::from_i128(1_000));
+    let values = vec![
+        Some(large),
+        None,
+        Some(large.wrapping_neg()),
+        Some(i256::ZERO),
+    ];
+
+    let array = udf
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![ColumnarValue::Array(Arc::new(
+                Decimal256Array::from(values.clone())
+                    .with_precision_and_scale(50, 2)
+                    .unwrap(),
+            ))],
+            arg_fields: vec![Arc::new(Field::new(
+                "a1",
+                DataType::Decimal256(50, 2),
+                true,
+            ))],
+            number_rows: 4,
+            return_field: Arc::new(Field::new("r", DataType::Decimal256(50, 2), true)),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+        .unwrap()
+        .unwrap_array();
+    assert_eq!(
+        array.as_ref(),
+        &Decimal256Array::from(values)
+            .with_precision_and_scale(50, 2)
+            .unwrap() as &dyn Array,
+    );
+}
+
+#[tokio::test]
+async fn test_rounding_fails() {
+    const CODE: &str = "
+from decimal import Decimal
+from typing import Annotated
+
+def foo(x: int) -> Annotated[Decimal, (10, 3)]:
+    return Decimal('1.2345')
+";
+    let err = invoke_err(CODE, DataType::Decimal128(10, 3)).await;
+    insta::assert_snapshot!(
+        err,
+        @"Execution error: `1.2345` of type `Decimal` cannot be represented with scale=3 without rounding",
+    );
+}
+
+#[tokio::test]
+async fn test_out_of_range_fails() {
+    const CODE: &str = "
+from decimal import Decimal
+from typing import Annotated
+
+def foo(x: int) -> Annotated[Decimal, (4, 2)]:
+    return Decimal('100')
+";
+    let err = invoke_err(CODE, DataType::Decimal128(4, 2)).await;
+    insta::assert_snapshot!(
+        err,
+        @"Execution error: `100` of type `Decimal` does not fit into precision=4 and scale=2",
+    );
+}
+
+#[tokio::test]
+async fn test_non_finite_fails() {
+    const CODE: &str = "
+from decimal import Decimal
+from typing import Annotated
+
+def foo(x: int) -> Annotated[Decimal, (10, 2)]:
+    return Decimal('NaN')
+";
+    let err = invoke_err(CODE, DataType::Decimal128(10, 2)).await;
+    insta::assert_snapshot!(
+        err,
+        @"Execution error: cannot convert non-finite `NaN` of type `Decimal` to Arrow",
+    );
+}
+
+/// Invoke UDF with a single `int` argument and return the error.
+async fn invoke_err(code: &str, return_type: DataType) -> String {
+    let udf = python_scalar_udf(code).await.unwrap();
+    udf.invoke_async_with_args(ScalarFunctionArgs {
+        args: vec![ColumnarValue::Array(Arc::new(Int64Array::from(vec![1])))],
+        arg_fields: vec![Arc::new(Field::new("a1", DataType::Int64, true))],
+        number_rows: 1,
+        return_field: Arc::new(Field::new("r", return_type, true)),
+        config_options: Arc::new(ConfigOptions::default()),
+    })
+    .await
+    .unwrap_err()
+    .to_string()
+}
@@ -0,0 +1,185 @@
+use std::sync::Arc;
+
+use arrow::{
+    array::{Array, Int64Builder, MapBuilder, MapFieldNames, StringArray, StringBuilder},
+    datatypes::{DataType, Field, Fields},
+};
+use datafusion_common::config::ConfigOptions;
+use datafusion_expr::{
+    ColumnarValue, ScalarFunctionArgs, ScalarUDFImpl, Signature, Volatility,
+    async_udf::AsyncScalarUDFImpl,
+};
+
+use crate::integration_tests::{
+    python::test_utils::python_scalar_udf, test_utils::ColumnarValueExt,
+};
+
+#[tokio::test]
+async fn test_ok() {
+    const CODE: &str = "
+def foo(x: dict[str, int]) -> dict[str, int | None]:
+    return {k: (v if v > 1 else None) for k, v in x.items()}
+";
+    let udf = python_scalar_udf(CODE).await.unwrap();
+
+    let input_type = map_type(false);
+    let output_type = map_type(true);
+    assert_eq!(
+        udf.signature(),
+        &Signature::exact(vec![input_type.clone()], Volatility::Volatile),
+    );
+    assert_eq!(
+        udf.return_type(std::slice::from_ref(&input_type)).unwrap(),
+        output_type,
+    );
+
+    let mut builder = map_builder(false);
+    builder.keys().append_value("a");
+    builder.values().append_value(1);
+    builder.keys().append_value("b");
+    builder.values().append_value(2);
+    builder.append(true).unwrap();
+    builder.append(false).unwrap();
+    builder.append(true).unwrap();
+    let input = builder.finish();
+
+    let array = udf
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![ColumnarValue::Array(Arc::new(input))],
+            arg_fields: vec![Arc::new(Field::new("a1", input_type, true))],
+            number_rows: 3,
+            return_field: Arc::new(Field::new("r", output_type, true)),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+        .unwrap()
+        .unwrap_array();
+
+    let mut builder = map_builder(true);
+    builder.keys().append_value("a");
+    builder.values().append_null();
+    builder.keys().append_value("b");
+    builder.values().append_value(2);
+    builder.append(true).unwrap();
+    builder.append(false).unwrap();
+    builder.append(true).unwrap();
+    let expected = builder.finish();
+
+    assert_eq!(array.as_ref(), &expected as &dyn Array);
+}
+
+#[tokio::test]
+async fn test_parse_query_string() {
+    const CODE: &str = "
+from urllib.parse import parse_qsl
+
+def parse_query(q: str) -> dict[str, str]:
+    return dict(parse_qsl(q))
+";
+    let udf = python_scalar_udf(CODE).await.unwrap();
+
+    let output_type = DataType::Map(
+        Arc::new(Field::new(
+            "entries",
+            DataType::Struct(Fields::from(vec![
+                Field::new("key", DataType::Utf8, false),
+                Field::new("value", DataType::Utf8, false),
+            ])),
+            false,
+        )),
+        false,
+    );
+    assert_eq!(udf.return_type(&[DataType::Utf8]).unwrap(), output_type,);
+
+    let array = udf
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![ColumnarValue::Array(Arc::new(StringArray::from(vec![
+                Some("a=1&b=x%20y"),
+                None,
+                Some(""),
+            ])))],
+            arg_fields: vec![Arc::new(Field::new("a1", DataType::Utf8, true))],
+            number_rows: 3,
+            return_field: Arc::new(Field::new("r", output_type, true)),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+        .unwrap()
+        .unwrap_array();
+
+    let mut builder = MapBuilder::new(
+        Some(MapFieldNames {
+            entry: "entries".to_owned(),
+            key: "key".to_owned(),
+            value: "value".to_owned(),
+        }),
+        StringBuilder::new(),
+        StringBuilder::new(),
+    )
+    .with_values_field(Field::new("value", DataType::Utf8, false));
+    builder.keys().append_value("a");
+    builder.values().append_value("1");
+    builder.keys().append_value("b");
+    builder.values().append_value("x y");
+    builder.append(true).unwrap();
+    builder.append(false).unwrap();
+    builder.append(true).unwrap();
+    let expected = builder.finish();
+
+    assert_eq!(array.as_ref(), &expected as &dyn Array);
+}
+
+#[tokio::test]
+async fn test_non_str_keys_fail() {
+    const CODE: &str = "
+def foo(x: int) -> dict[str, int]:
+    return {x: x}
+";
+    let udf = python_scalar_udf(CODE).await.unwrap();
+
+    let err = udf
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![ColumnarValue::Array(Arc::new(
+                arrow::array::Int64Array::from(vec![1]),
+            ))],
+            arg_fields: vec![Arc::new(Field::new("a1", DataType::Int64, true))],
+            number_rows: 1,
+            return_field: Arc::new(Field::new("r", map_type(false), true)),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+        .unwrap_err();
+    insta::assert_snapshot!(
+        err,
+        @"Execution error: expected `str` but got `1` of type `int`",
+    );
+}
+
+/// Arrow type of `dict[str, int]` (`nullable = false`) or `dict[str, int | None]` (`nullable = true`).
+fn map_type(nullable: bool) -> DataType {
+    DataType::Map(
+        Arc::new(Field::new(
+            "entries",
+            DataType::Struct(Fields::from(vec![
+                Field::new("key", DataType::Utf8, false),
+                Field::new("value", DataType::Int64, nullable),
+            ])),
+            false,
+        )),
+        false,
+    )
+}
+
+/// Builder for [`map_type`].
+fn map_builder(nullable: bool) -> MapBuilder<StringBuilder, Int64Builder> {
+    MapBuilder::new(
+        Some(MapFieldNames {
+            entry: "entries".to_owned(),
+            key: "key".to_owned(),
+            value: "value".to_owned(),
+        }),
+        StringBuilder::new(),
+        Int64Builder::new(),
+    )
+    .with_values_field(Field::new("value", DataType::Int64, nullable))
+}
@@ -1,7 +1,7 @@
 use std::sync::Arc;
 
 use arrow::{
-    array::{Array, Float64Array},
+    array::{Array, Float16Array, Float32Array, Float64Array},
     datatypes::{DataType, Field},
 };
 use datafusion_common::cast::as_float64_array;
@@ -11,6 +11,8 @@ use datafusion_expr::{
     async_udf::AsyncScalarUDFImpl,
 };
 
+use half::f16;
+
 use crate::integration_tests::{
     python::test_utils::python_scalar_udf, test_utils::ColumnarValueExt,
 };
@@ -61,6 +63,49 @@ def foo(x: float) -> float:
     assert_float_total_eq(&array, values);
 }
 
+#[tokio::test]
+async fn test_narrow_args_are_upcast() {
+    const CODE: &str = "
+def foo(x: float) -> float:
+    return x * 2
+";
+    let udf = python_scalar_udf(CODE).await.unwrap();
+
+    let cases = [
+        (
+            Arc::new(Float16Array::from(vec![
+                Some(f16::from_f32(1.5)),
+                None,
+                Some(f16::NEG_INFINITY),
+            ])) as Arc<dyn Array>,
+            DataType::Float16,
+        ),
+        (
+            Arc::new(Float32Array::from(vec![
+                Some(1.5),
+                None,
+                Some(f32::NEG_INFINITY),
+            ])),
+            DataType::Float32,
+        ),
+    ];
+
+    for (array, data_type) in cases {
+        let array = udf
+            .invoke_async_with_args(ScalarFunctionArgs {
+                args: vec![ColumnarValue::Array(array)],
+                arg_fields: vec![Arc::new(Field::new("a1", data_type, true))],
+                number_rows: 3,
+                return_field: Arc::new(Field::new("r", DataType::Float64, true)),
+                config_options: Arc::new(ConfigOptions::default()),
+            })
+            .await
+            .unwrap()
+            .unwrap_array();
+        assert_float_total_eq(&array, &[Some(3.0), None, Some(f64::NEG_INFINITY)]);
+    }
+}
+
 #[test]
 #[should_panic(expected = "Not equal")]
 fn test_assert_float_total_eq_uses_total_eq() {
@@ -0,0 +1,106 @@
+use std::sync::Arc;
+
+use arrow::{
+    array::{Array, Int64Array, IntervalMonthDayNanoArray},
+    datatypes::{DataType, Field, IntervalMonthDayNano, IntervalUnit},
+};
+use datafusion_common::config::ConfigOptions;
+use datafusion_expr::{
+    ColumnarValue, ScalarFunctionArgs, ScalarUDFImpl, Signature, Volatility,
+    async_udf::AsyncScalarUDFImpl,
+};
+
+use crate::integration_tests::{
+    python::test_utils::python_scalar_udf, test_utils::ColumnarValueExt,
+};
+
+const INTERVAL: DataType = DataType::Interval(IntervalUnit::MonthDayNano);
+
+#[tokio::test]
+async fn test_ok() {
+    const CODE: &str = r#"
+from typing import Annotated
+
+def foo(
+    x: Annotated[tuple[int, int, int], "interval"],
+) -> Annotated[tuple[int, int, int], "interval"]:
+    months, days, nanoseconds = x
+    return (months + 3, days * 2, -nanoseconds)
+"#;
+    let udf = python_scalar_udf(CODE).await.unwrap();
+
+    assert_eq!(
+        udf.signature(),
+        &Signature::exact(vec![INTERVAL], Volatility::Volatile),
+    );
+    assert_eq!(udf.return_type(&[INTERVAL]).unwrap(), INTERVAL);
+
+    let array = udf
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![ColumnarValue::Array(Arc::new(
+                IntervalMonthDayNanoArray::from(vec![
+                    Some(IntervalMonthDayNano::new(1, 45, 1_000)),
+                    None,
+                    Some(IntervalMonthDayNano::new(-2, 0, i64::MAX)),
+                ]),
+            ))],
+            arg_fields: vec![Arc::new(Field::new("a1", INTERVAL, true))],
+            number_rows: 3,
+            return_field: Arc::new(Field::new("r", INTERVAL, true)),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+        .unwrap()
+        .unwrap_array();
+    assert_eq!(
+        array.as_ref(),
+        &IntervalMonthDayNanoArray::from(vec![
+            Some(IntervalMonthDayNano::new(4, 90, -1_000)),
+            None,
+            Some(IntervalMonthDayNano::new(1, 0, -i64::MAX)),
+        ]) as &dyn Array,
+    );
+}
+
+#[tokio::test]
+async fn test_return_invalid_value() {
+    const CODE: &str = r#"
+from typing import Annotated
+
+def foo(x: int) -> Annotated[tuple[int, int, int], "interval"]:
+    return (1, 2)
+"#;
+    insta::assert_snapshot!(
+        invoke_err(CODE).await,
+        @"Execution error: expected `(months, days, nanoseconds)` tuple of `int` but got `(1, 2)` of type `tuple`",
+    );
+}
+
+#[tokio::test]
+async fn test_return_out_of_range() {
+    const CODE: &str = r#"
+from typing import Annotated
+
+def foo(x: int) -> Annotated[tuple[int, int, int], "interval"]:
+    return (2**31, 0, 0)
+"#;
+    insta::assert_snapshot!(
+        invoke_err(CODE).await,
+        @"Execution error: `(2147483648, 0, 0)` of type `tuple` does not fit into an interval, months and days must fit into 32 bits, nanoseconds into 64 bits",
+    );
+}
+
+/// Invoke UDF with a single `int` argument and return the error.
+async fn invoke_err(code: &str) -> String {
+    let udf = python_scalar_udf(code).await.unwrap();
+    udf.invoke_async_with_args(ScalarFunctionArgs {
+        args: vec![ColumnarValue::Array(Arc::new(Int64Array::from(vec![1])))],
+        arg_fields: vec![Arc::new(Field::new("a1", DataType::Int64, true))],
+        number_rows: 1,
+        return_field: Arc::new(Field::new("r", INTERVAL, true)),
+        config_options: Arc::new(ConfigOptions::default()),
+    })
+    .await
+    .unwrap_err()
+    .to_string()
+}
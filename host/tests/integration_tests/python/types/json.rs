@@ -0,0 +1,102 @@
+use std::sync::Arc;
+
+use arrow::{
+    array::{Array, StringArray},
+    datatypes::{DataType, Field},
+};
+use datafusion_common::config::ConfigOptions;
+use datafusion_expr::{
+    ColumnarValue, ScalarFunctionArgs, ScalarUDFImpl, Signature, Volatility,
+    async_udf::AsyncScalarUDFImpl,
+};
+
+use crate::integration_tests::{
+    python::test_utils::python_scalar_udf, test_utils::ColumnarValueExt,
+};
+
+#[tokio::test]
+async fn test_ok() {
+    const CODE: &str = r#"
+from typing import Annotated
+
+def foo(x: Annotated[dict, "json"]) -> Annotated[list[str], "json"]:
+    return sorted(x.get("tags", []))
+"#;
+    let udf = python_scalar_udf(CODE).await.unwrap();
+
+    assert_eq!(
+        udf.signature(),
+        &Signature::exact(vec![DataType::Utf8], Volatility::Volatile),
+    );
+    assert_eq!(udf.return_type(&[DataType::Utf8]).unwrap(), DataType::Utf8);
+
+    let array = invoke(
+        &udf,
+        StringArray::from_iter([
+            Some(r#"{"tags": ["b", "a"]}"#),
+            None,
+            Some(r#"{"other": 1}"#),
+        ]),
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        array.as_ref(),
+        &StringArray::from_iter([Some(r#"["a", "b"]"#), None, Some("[]")]) as &dyn Array,
+    );
+}
+
+#[tokio::test]
+async fn test_invalid_json() {
+    const CODE: &str = r#"
+from typing import Annotated
+
+def foo(x: Annotated[dict, "json"]) -> Annotated[dict, "json"]:
+    return x
+"#;
+    let udf = python_scalar_udf(CODE).await.unwrap();
+
+    let err = invoke(&udf, StringArray::from_iter([Some("{")]))
+        .await
+        .unwrap_err();
+    insta::assert_snapshot!(
+        err,
+        @"Execution error: cannot decode JSON: JSONDecodeError: Expecting property name enclosed in double quotes: line 1 column 2 (char 1)",
+    );
+}
+
+#[tokio::test]
+async fn test_return_nan() {
+    const CODE: &str = r#"
+from typing import Annotated
+
+def foo(x: Annotated[dict, "json"]) -> Annotated[dict, "json"]:
+    return {"x": float("nan")}
+"#;
+    let udf = python_scalar_udf(CODE).await.unwrap();
+
+    let err = invoke(&udf, StringArray::from_iter([Some("{}")]))
+        .await
+        .unwrap_err();
+    insta::assert_snapshot!(
+        err,
+        @"Execution error: cannot encode {'x': nan} as JSON: ValueError: Out of range float values are not JSON compliant: nan",
+    );
+}
+
+/// Invoke UDF with a single string argument.
+async fn invoke(
+    udf: &impl AsyncScalarUDFImpl,
+    array: StringArray,
+) -> datafusion_common::Result<Arc<dyn Array>> {
+    let number_rows = array.len();
+    udf.invoke_async_with_args(ScalarFunctionArgs {
+        args: vec![ColumnarValue::Array(Arc::new(array))],
+        arg_fields: vec![Arc::new(Field::new("a1", DataType::Utf8, true))],
+        number_rows,
+        return_field: Arc::new(Field::new("r", DataType::Utf8, true)),
+        config_options: Arc::new(ConfigOptions::default()),
+    })
+    .await
+    .map(|output| output.unwrap_array())
+}
@@ -0,0 +1,171 @@
+use std::sync::Arc;
+
+use arrow::{
+    array::{Array, Int64Array, ListArray, StringArray},
+    buffer::{NullBuffer, OffsetBuffer},
+    datatypes::{DataType, Field, Int64Type},
+};
+use datafusion_common::config::ConfigOptions;
+use datafusion_expr::{
+    ColumnarValue, ScalarFunctionArgs, ScalarUDFImpl, Signature, Volatility,
+    async_udf::AsyncScalarUDFImpl,
+};
+
+use crate::integration_tests::{
+    python::test_utils::python_scalar_udf, test_utils::ColumnarValueExt,
+};
+
+#[tokio::test]
+async fn test_ok() {
+    const CODE: &str = "
+def foo(x: list[int | None]) -> list[int]:
+    return [v * 2 for v in x if v is not None]
+";
+    let udf = python_scalar_udf(CODE).await.unwrap();
+
+    let input_type = DataType::List(Arc::new(Field::new("item", DataType::Int64, true)));
+    let output_type = DataType::List(Arc::new(Field::new("item", DataType::Int64, false)));
+    assert_eq!(
+        udf.signature(),
+        &Signature::exact(vec![input_type.clone()], Volatility::Volatile),
+    );
+    assert_eq!(
+        udf.return_type(std::slice::from_ref(&input_type)).unwrap(),
+        output_type,
+    );
+
+    let array = udf
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![ColumnarValue::Array(Arc::new(
+                ListArray::from_iter_primitive::<Int64Type, _, _>([
+                    Some(vec![Some(1), None, Some(3)]),
+                    None,
+                    Some(vec![]),
+                ]),
+            ))],
+            arg_fields: vec![Arc::new(Field::new("a1", input_type, true))],
+            number_rows: 3,
+            return_field: Arc::new(Field::new("r", output_type, true)),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+        .unwrap()
+        .unwrap_array();
+    assert_eq!(
+        array.as_ref(),
+        &ListArray::new(
+            Arc::new(Field::new("item", DataType::Int64, false)),
+            OffsetBuffer::from_lengths([2, 0, 0]),
+            Arc::new(Int64Array::from(vec![2, 6])),
+            Some(NullBuffer::from(vec![true, false, true])),
+        ) as &dyn Array,
+    );
+}
+
+#[tokio::test]
+async fn test_roundtrip_nullable() {
+    const CODE: &str = "
+def foo(x: list[str | None] | None) -> list[str | None] | None:
+    return x
+";
+    let udf = python_scalar_udf(CODE).await.unwrap();
+
+    let list_type = DataType::List(Arc::new(Field::new("item", DataType::Utf8, true)));
+    assert_eq!(
+        udf.signature(),
+        &Signature::exact(vec![list_type.clone()], Volatility::Volatile),
+    );
+    assert_eq!(
+        udf.return_type(std::slice::from_ref(&list_type)).unwrap(),
+        list_type,
+    );
+
+    let input = ListArray::new(
+        Arc::new(Field::new("item", DataType::Utf8, true)),
+        OffsetBuffer::from_lengths([3, 0, 0, 1]),
+        Arc::new(StringArray::from(vec![Some("a"), None, Some(""), None])),
+        Some(NullBuffer::from(vec![true, false, true, true])),
+    );
+    let array = udf
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![ColumnarValue::Array(Arc::new(input.clone()))],
+            arg_fields: vec![Arc::new(Field::new("a1", list_type.clone(), true))],
+            number_rows: 4,
+            return_field: Arc::new(Field::new("r", list_type, true)),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+        .unwrap()
+        .unwrap_array();
+    assert_eq!(array.as_ref(), &input as &dyn Array);
+}
+
+#[tokio::test]
+async fn test_nested() {
+    const CODE: &str = "
+def foo(x: int) -> list[list[int]]:
+    return [list(range(i)) for i in range(x)]
+";
+    let udf = python_scalar_udf(CODE).await.unwrap();
+
+    let inner_type = DataType::List(Arc::new(Field::new("item", DataType::Int64, false)));
+    let output_type = DataType::List(Arc::new(Field::new("item", inner_type.clone(), false)));
+    assert_eq!(udf.return_type(&[DataType::Int64]).unwrap(), output_type,);
+
+    let array = udf
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![ColumnarValue::Array(Arc::new(Int64Array::from(vec![
+                Some(3),
+                None,
+            ])))],
+            arg_fields: vec![Arc::new(Field::new("a1", DataType::Int64, true))],
+            number_rows: 2,
+            return_field: Arc::new(Field::new("r", output_type, true)),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+        .unwrap()
+        .unwrap_array();
+    assert_eq!(
+        array.as_ref(),
+        &ListArray::new(
+            Arc::new(Field::new("item", inner_type, false)),
+            OffsetBuffer::from_lengths([3, 0]),
+            Arc::new(ListArray::new(
+                Arc::new(Field::new("item", DataType::Int64, false)),
+                OffsetBuffer::from_lengths([0, 1, 2]),
+                Arc::new(Int64Array::from(vec![0, 0, 1])),
+                None,
+            )),
+            Some(NullBuffer::from(vec![true, false])),
+        ) as &dyn Array,
+    );
+}
+
+#[tokio::test]
+async fn test_returning_none_element_fails() {
+    const CODE: &str = "
+def foo(x: int) -> list[int]:
+    return [x, None]
+";
+    let udf = python_scalar_udf(CODE).await.unwrap();
+
+    let err = udf
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![ColumnarValue::Array(Arc::new(Int64Array::from(vec![1])))],
+            arg_fields: vec![Arc::new(Field::new("a1", DataType::Int64, true))],
+            number_rows: 1,
+            return_field: Arc::new(Field::new(
+                "r",
+                DataType::List(Arc::new(Field::new("item", DataType::Int64, false))),
+                true,
+            )),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+        .unwrap_err();
+    insta::assert_snapshot!(
+        err,
+        @"Execution error: method returned `None` for a non-optional type, annotate it as `T | None` to return NULLs",
+    );
+}
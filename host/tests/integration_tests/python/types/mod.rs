@@ -1,11 +1,20 @@
 mod bool;
 mod bytes;
+mod dataclass;
 mod date;
 mod datetime;
+mod decimal;
+mod dict;
 mod float;
 mod int;
+mod interval;
+mod json;
+mod list;
 mod none;
 mod str;
 mod time;
 mod timedelta;
+mod tuple;
+mod typed_dict;
 mod union;
+mod uuid;
@@ -1,7 +1,7 @@
 use std::sync::Arc;
 
 use arrow::{
-    array::{Array, StringArray},
+    array::{Array, LargeStringArray, StringArray, StringViewArray},
     datatypes::{DataType, Field},
 };
 use datafusion_common::config::ConfigOptions;
@@ -81,3 +81,35 @@ def foo(x: str) -> str:
         @"Execution error: expected `str` but got `b'hello'` of type `bytes`",
     );
 }
+
+#[tokio::test]
+async fn test_view_and_large() {
+    const CODE: &str = "
+def foo(x: str) -> str:
+    return x.upper()
+";
+    let udf = python_scalar_udf(CODE).await.unwrap();
+
+    assert_eq!(
+        udf.return_type(&[DataType::Utf8View]).unwrap(),
+        DataType::Utf8,
+    );
+
+    let array = udf
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![ColumnarValue::Array(Arc::new(StringViewArray::from_iter(
+                [Some("hello"), None],
+            )))],
+            arg_fields: vec![Arc::new(Field::new("a1", DataType::Utf8View, true))],
+            number_rows: 2,
+            return_field: Arc::new(Field::new("r", DataType::LargeUtf8, true)),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+        .unwrap()
+        .unwrap_array();
+    assert_eq!(
+        array.as_ref(),
+        &LargeStringArray::from_iter([Some("HELLO"), None]) as &dyn Array,
+    );
+}
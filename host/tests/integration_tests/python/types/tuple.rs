@@ -0,0 +1,105 @@
+use std::sync::Arc;
+
+use arrow::{
+    array::{Array, Int64Array, StringArray, StructArray},
+    buffer::NullBuffer,
+    datatypes::{DataType, Field, Fields},
+};
+use datafusion_common::config::ConfigOptions;
+use datafusion_expr::{
+    ColumnarValue, ScalarFunctionArgs, ScalarUDFImpl, Signature, Volatility,
+    async_udf::AsyncScalarUDFImpl,
+};
+
+use crate::integration_tests::{
+    python::test_utils::python_scalar_udf, test_utils::ColumnarValueExt,
+};
+
+#[tokio::test]
+async fn test_ok() {
+    const CODE: &str = "
+def foo(x: tuple[int, str | None]) -> tuple[str, int]:
+    return (x[1] or '', x[0] + 1)
+";
+    let udf = python_scalar_udf(CODE).await.unwrap();
+
+    let input_fields = Fields::from(vec![
+        Field::new("c0", DataType::Int64, false),
+        Field::new("c1", DataType::Utf8, true),
+    ]);
+    let output_fields = Fields::from(vec![
+        Field::new("c0", DataType::Utf8, false),
+        Field::new("c1", DataType::Int64, false),
+    ]);
+    let input_type = DataType::Struct(input_fields.clone());
+    let output_type = DataType::Struct(output_fields.clone());
+    assert_eq!(
+        udf.signature(),
+        &Signature::exact(vec![input_type.clone()], Volatility::Volatile),
+    );
+    assert_eq!(
+        udf.return_type(std::slice::from_ref(&input_type)).unwrap(),
+        output_type,
+    );
+
+    let array = udf
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![ColumnarValue::Array(Arc::new(StructArray::new(
+                input_fields,
+                vec![
+                    Arc::new(Int64Array::from(vec![1, 2, 3])),
+                    Arc::new(StringArray::from(vec![Some("a"), None, None])),
+                ],
+                Some(NullBuffer::from(vec![true, false, true])),
+            )))],
+            arg_fields: vec![Arc::new(Field::new("a1", input_type, true))],
+            number_rows: 3,
+            return_field: Arc::new(Field::new("r", output_type, true)),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+        .unwrap()
+        .unwrap_array();
+    assert_eq!(
+        array.as_ref(),
+        &StructArray::new(
+            output_fields,
+            vec![
+                Arc::new(StringArray::from(vec![Some("a"), None, Some("")])),
+                Arc::new(Int64Array::from(vec![Some(2), None, Some(4)])),
+            ],
+            Some(NullBuffer::from(vec![true, false, true])),
+        ) as &dyn Array,
+    );
+}
+
+#[tokio::test]
+async fn test_returning_wrong_length_fails() {
+    const CODE: &str = "
+def foo(x: int) -> tuple[int, int]:
+    return (x,)
+";
+    let udf = python_scalar_udf(CODE).await.unwrap();
+
+    let err = udf
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![ColumnarValue::Array(Arc::new(Int64Array::from(vec![1])))],
+            arg_fields: vec![Arc::new(Field::new("a1", DataType::Int64, true))],
+            number_rows: 1,
+            return_field: Arc::new(Field::new(
+                "r",
+                DataType::Struct(Fields::from(vec![
+                    Field::new("c0", DataType::Int64, false),
+                    Field::new("c1", DataType::Int64, false),
+                ])),
+                true,
+            )),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+        .unwrap_err();
+    insta::assert_snapshot!(
+        err,
+        @"Execution error: expected tuple with 2 elements but got `(1,)` of type `tuple`",
+    );
+}
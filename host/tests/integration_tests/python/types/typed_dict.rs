@@ -0,0 +1,147 @@
+use std::sync::Arc;
+
+use arrow::{
+    array::{Array, StringArray, StructArray},
+    buffer::NullBuffer,
+    datatypes::{DataType, Field, Fields},
+};
+use datafusion_common::config::ConfigOptions;
+use datafusion_expr::{
+    ColumnarValue, ScalarFunctionArgs, ScalarUDFImpl, Signature, Volatility,
+    async_udf::AsyncScalarUDFImpl,
+};
+
+use crate::integration_tests::{
+    python::test_utils::python_scalar_udf, test_utils::ColumnarValueExt,
+};
+
+#[tokio::test]
+async fn test_return() {
+    const CODE: &str = "
+from typing import TypedDict
+
+class UserAgent(TypedDict):
+    browser: str
+    os: str | None
+
+def parse_ua(ua: str) -> UserAgent:
+    browser, _, os = ua.partition('/')
+    return {'browser': browser, 'os': os or None}
+";
+    let udf = python_scalar_udf(CODE).await.unwrap();
+
+    assert_eq!(udf.name(), "parse_ua");
+    assert_eq!(
+        udf.signature(),
+        &Signature::exact(vec![DataType::Utf8], Volatility::Volatile),
+    );
+    assert_eq!(
+        udf.return_type(&[DataType::Utf8]).unwrap(),
+        DataType::Struct(fields()),
+    );
+
+    let array = udf
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![ColumnarValue::Array(Arc::new(StringArray::from(vec![
+                Some("firefox/linux"),
+                Some("curl"),
+                None,
+            ])))],
+            arg_fields: vec![Arc::new(Field::new("a1", DataType::Utf8, true))],
+            number_rows: 3,
+            return_field: Arc::new(Field::new("r", DataType::Struct(fields()), true)),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+        .unwrap()
+        .unwrap_array();
+    assert_eq!(
+        array.as_ref(),
+        &StructArray::new(
+            fields(),
+            vec![
+                Arc::new(StringArray::from(vec![Some("firefox"), Some("curl"), None])),
+                Arc::new(StringArray::from(vec![Some("linux"), None, None])),
+            ],
+            Some(NullBuffer::from(vec![true, true, false])),
+        ) as &dyn Array,
+    );
+}
+
+#[tokio::test]
+async fn test_parameter() {
+    const CODE: &str = "
+from typing import TypedDict
+
+class UserAgent(TypedDict):
+    browser: str
+    os: str | None
+
+def describe(ua: UserAgent) -> str:
+    return f\"{ua['browser']} on {ua['os'] or 'unknown'}\"
+";
+    let udf = python_scalar_udf(CODE).await.unwrap();
+
+    let array = udf
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![ColumnarValue::Array(Arc::new(StructArray::new(
+                fields(),
+                vec![
+                    Arc::new(StringArray::from(vec![Some("firefox"), Some("curl")])),
+                    Arc::new(StringArray::from(vec![Some("linux"), None])),
+                ],
+                None,
+            )))],
+            arg_fields: vec![Arc::new(Field::new("a1", DataType::Struct(fields()), true))],
+            number_rows: 2,
+            return_field: Arc::new(Field::new("r", DataType::Utf8, true)),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+        .unwrap()
+        .unwrap_array();
+    assert_eq!(
+        array.as_ref(),
+        &StringArray::from(vec!["firefox on linux", "curl on unknown"]) as &dyn Array,
+    );
+}
+
+#[tokio::test]
+async fn test_returning_wrong_keys_fails() {
+    const CODE: &str = "
+from typing import TypedDict
+
+class UserAgent(TypedDict):
+    browser: str
+    os: str | None
+
+def parse_ua(ua: str) -> UserAgent:
+    return {'browser': ua}
+";
+    let udf = python_scalar_udf(CODE).await.unwrap();
+
+    let err = udf
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![ColumnarValue::Array(Arc::new(StringArray::from(vec![
+                "curl",
+            ])))],
+            arg_fields: vec![Arc::new(Field::new("a1", DataType::Utf8, true))],
+            number_rows: 1,
+            return_field: Arc::new(Field::new("r", DataType::Struct(fields()), true)),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+        .unwrap_err();
+    insta::assert_snapshot!(
+        err,
+        @r#"Execution error: expected dict with keys ["browser", "os"] but got `{'browser': 'curl'}` of type `dict`"#,
+    );
+}
+
+/// Struct fields of `UserAgent`.
+fn fields() -> Fields {
+    Fields::from(vec![
+        Field::new("browser", DataType::Utf8, false),
+        Field::new("os", DataType::Utf8, true),
+    ])
+}
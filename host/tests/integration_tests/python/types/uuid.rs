@@ -0,0 +1,169 @@
+use std::sync::Arc;
+
+use arrow::{
+    array::{Array, FixedSizeBinaryArray, Int64Array, StringArray},
+    datatypes::{DataType, Field},
+};
+use datafusion_common::config::ConfigOptions;
+use datafusion_expr::{
+    ColumnarValue, ScalarFunctionArgs, ScalarUDFImpl, Signature, Volatility,
+    async_udf::AsyncScalarUDFImpl,
+};
+
+use crate::integration_tests::{
+    python::test_utils::python_scalar_udf, test_utils::ColumnarValueExt,
+};
+
+#[tokio::test]
+async fn test_ok() {
+    const CODE: &str = "
+from uuid import UUID
+
+def foo(x: UUID) -> UUID:
+    return UUID(int=x.int + 1)
+";
+    let udf = python_scalar_udf(CODE).await.unwrap();
+
+    assert_eq!(
+        udf.signature(),
+        &Signature::exact(vec![DataType::FixedSizeBinary(16)], Volatility::Volatile),
+    );
+    assert_eq!(
+        udf.return_type(&[DataType::FixedSizeBinary(16)]).unwrap(),
+        DataType::FixedSizeBinary(16),
+    );
+
+    let mut a = [0u8; 16];
+    a[15] = 1;
+    let mut b = [0xffu8; 16];
+    b[15] = 0xfe;
+    let array = udf
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![ColumnarValue::Array(Arc::new(
+                FixedSizeBinaryArray::try_from_sparse_iter_with_size(
+                    [Some(a.as_slice()), None, Some(b.as_slice())].into_iter(),
+                    16,
+                )
+                .unwrap(),
+            ))],
+            arg_fields: vec![Arc::new(Field::new(
+                "a1",
+                DataType::FixedSizeBinary(16),
+                true,
+            ))],
+            number_rows: 3,
+            return_field: Arc::new(Field::new("r", DataType::FixedSizeBinary(16), true)),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+        .unwrap()
+        .unwrap_array();
+
+    let mut a_next = a;
+    a_next[15] = 2;
+    let b_next = [0xffu8; 16];
+    assert_eq!(
+        array.as_ref(),
+        &FixedSizeBinaryArray::try_from_sparse_iter_with_size(
+            [Some(a_next.as_slice()), None, Some(b_next.as_slice())].into_iter(),
+            16,
+        )
+        .unwrap() as &dyn Array,
+    );
+}
+
+#[tokio::test]
+async fn test_str() {
+    const CODE: &str = "
+from typing import Annotated
+from uuid import UUID
+
+def foo(x: Annotated[UUID, str]) -> Annotated[UUID, str]:
+    return UUID(int=x.int + 1)
+";
+    let udf = python_scalar_udf(CODE).await.unwrap();
+
+    assert_eq!(
+        udf.signature(),
+        &Signature::exact(vec![DataType::Utf8], Volatility::Volatile),
+    );
+
+    let array = udf
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![ColumnarValue::Array(Arc::new(StringArray::from_iter([
+                Some("12345678-1234-5678-1234-567812345678"),
+                None,
+                Some("{12345678-1234-5678-1234-56781234567A}"),
+            ])))],
+            arg_fields: vec![Arc::new(Field::new("a1", DataType::Utf8, true))],
+            number_rows: 3,
+            return_field: Arc::new(Field::new("r", DataType::Utf8, true)),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+        .unwrap()
+        .unwrap_array();
+    assert_eq!(
+        array.as_ref(),
+        &StringArray::from_iter([
+            Some("12345678-1234-5678-1234-567812345679"),
+            None,
+            Some("12345678-1234-5678-1234-56781234567b"),
+        ]) as &dyn Array,
+    );
+}
+
+#[tokio::test]
+async fn test_invalid_str() {
+    const CODE: &str = "
+from typing import Annotated
+from uuid import UUID
+
+def foo(x: Annotated[UUID, str]) -> int:
+    return x.version
+";
+    let udf = python_scalar_udf(CODE).await.unwrap();
+
+    let err = udf
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![ColumnarValue::Array(Arc::new(StringArray::from_iter([
+                Some("not a uuid"),
+            ])))],
+            arg_fields: vec![Arc::new(Field::new("a1", DataType::Utf8, true))],
+            number_rows: 1,
+            return_field: Arc::new(Field::new("r", DataType::Int64, true)),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+        .unwrap_err();
+    insta::assert_snapshot!(
+        err,
+        @r#"Execution error: cannot create Python `UUID` from "not a uuid": ValueError: badly formed hexadecimal UUID string"#,
+    );
+}
+
+#[tokio::test]
+async fn test_return_str_for_uuid() {
+    const CODE: &str = "
+from uuid import UUID
+
+def foo(x: int) -> UUID:
+    return '12345678-1234-5678-1234-567812345678'
+";
+    let udf = python_scalar_udf(CODE).await.unwrap();
+
+    let err = udf
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![ColumnarValue::Array(Arc::new(Int64Array::from(vec![1])))],
+            arg_fields: vec![Arc::new(Field::new("a1", DataType::Int64, true))],
+            number_rows: 1,
+            return_field: Arc::new(Field::new("r", DataType::FixedSizeBinary(16), true)),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+        .unwrap_err();
+    insta::assert_snapshot!(
+        err,
+        @"Execution error: expected `UUID` but got `12345678-1234-5678-1234-567812345678` of type `str`",
+    );
+}
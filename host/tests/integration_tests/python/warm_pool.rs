@@ -0,0 +1,91 @@
+use std::sync::Arc;
+
+use arrow::{
+    array::{Array, Int64Array},
+    datatypes::{DataType, Field},
+};
+use datafusion_common::config::ConfigOptions;
+use datafusion_execution::memory_pool::UnboundedMemoryPool;
+use datafusion_expr::{ScalarFunctionArgs, async_udf::AsyncScalarUDFImpl};
+use datafusion_udf_wasm_host::{WarmPool, WasmPermissions, WasmScalarUdf};
+use tokio::runtime::Handle;
+
+use crate::integration_tests::{
+    python::test_utils::python_preinit_component, test_utils::ColumnarValueExt,
+};
+
+const CODE: &str = "
+count = 0
+
+def inc() -> int:
+    global count
+    count += 1
+    return count
+";
+
+#[tokio::test]
+async fn test_warm_pool() {
+    let pool = WarmPool::new(
+        python_preinit_component().await,
+        &WasmPermissions::new(),
+        Handle::current(),
+        &(Arc::new(UnboundedMemoryPool::default()) as _),
+        2,
+    );
+    assert_eq!(pool.available(), 0);
+
+    pool.fill().await.unwrap();
+    assert_eq!(pool.available(), 2);
+
+    let udfs_1 = pool.create_udfs(CODE.to_owned()).await.unwrap();
+    let udfs_2 = pool.create_udfs(CODE.to_owned()).await.unwrap();
+    assert_eq!(udfs_1.len(), 1);
+    assert_eq!(udfs_2.len(), 1);
+
+    // guests are NOT shared
+    assert_eq!(call(&udfs_1[0]).await, 1);
+    assert_eq!(call(&udfs_1[0]).await, 2);
+    assert_eq!(call(&udfs_2[0]).await, 1);
+
+    // the pool never grows beyond its size
+    pool.fill().await.unwrap();
+    assert!(pool.available() <= 2);
+}
+
+#[tokio::test]
+async fn test_empty_pool() {
+    let pool = WarmPool::new(
+        python_preinit_component().await,
+        &WasmPermissions::new(),
+        Handle::current(),
+        &(Arc::new(UnboundedMemoryPool::default()) as _),
+        0,
+    );
+    pool.fill().await.unwrap();
+    assert_eq!(pool.available(), 0);
+
+    // falls back to creating a new guest
+    let udfs = pool.create_udfs(CODE.to_owned()).await.unwrap();
+    assert_eq!(call(&udfs[0]).await, 1);
+    assert_eq!(pool.available(), 0);
+}
+
+/// Call UDF without arguments.
+async fn call(udf: &WasmScalarUdf) -> i64 {
+    let array = udf
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![],
+            arg_fields: vec![],
+            number_rows: 1,
+            return_field: Arc::new(Field::new("r", DataType::Int64, true)),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+        .unwrap()
+        .unwrap_array();
+    array
+        .as_any()
+        .downcast_ref::<Int64Array>()
+        .unwrap()
+        .value(0)
+}
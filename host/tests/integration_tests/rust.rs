@@ -1,19 +1,26 @@
 use std::{num::NonZeroUsize, sync::Arc};
 
 use arrow::{
-    array::{Array, Int64Array, StringArray},
+    array::{Array, Int64Array, StringArray, StringViewArray},
+    compute::SortOptions,
     datatypes::{DataType, Field},
 };
 use datafusion_common::ScalarValue;
 use datafusion_common::config::ConfigOptions;
-use datafusion_execution::memory_pool::{GreedyMemoryPool, UnboundedMemoryPool};
+use datafusion_execution::memory_pool::{
+    GreedyMemoryPool, MemoryConsumer, MemoryPool, UnboundedMemoryPool,
+};
 use datafusion_expr::{
     ColumnarValue, ScalarFunctionArgs, ScalarUDFImpl, Signature, Volatility,
     async_udf::AsyncScalarUDFImpl,
+    execution_props::ExecutionProps,
+    lit,
+    simplify::{ExprSimplifyResult, SimplifyContext},
+    sort_properties::{ExprProperties, SortProperties},
 };
 use datafusion_udf_wasm_host::{
-    CompilationFlags, StaticResourceLimits, WasmComponentPrecompiled, WasmPermissions,
-    WasmScalarUdf,
+    CompilationFlags, DynamicMemoryLimits, IpcCompression, NnModel, StaticResourceLimits,
+    WasmComponentPrecompiled, WasmPermissions, WasmScalarUdf, WitVersion,
 };
 use tokio::{runtime::Handle, sync::OnceCell};
 
@@ -21,11 +28,11 @@ use crate::integration_tests::test_utils::ColumnarValueExt;
 
 // FIXME: remove `multi_thread` flavor.
 //
-// This test relies on a non-exact function signature to verify error handling
-// in `return_type``. [WasmScalarUdf::return_type](ScalarUdfImpl::return_type)
-// is *not* async, and will need to compute the return type if the function
-// signature is not exact, which effectively means it will block; which is
-// incompatible with the current single-threaded tokio runtime used in tests.
+// This test passes argument types that the function signature does not list to
+// verify error handling in `return_type``. [WasmScalarUdf::return_type](ScalarUdfImpl::return_type)
+// is *not* async, and will need to compute the return type if it was not
+// pre-fetched, which effectively means it will block; which is incompatible with
+// the current single-threaded tokio runtime used in tests.
 #[tokio::test(flavor = "multi_thread")]
 async fn test_add_one() {
     let udf = udf_add_one().await;
@@ -80,13 +87,221 @@ async fn test_add_one() {
     assert_eq!(scalar, ScalarValue::Int64(Some(4)));
 }
 
+#[tokio::test]
+async fn test_simple() {
+    let udfs = WasmScalarUdf::new(
+        component_simple().await,
+        &Default::default(),
+        Handle::current(),
+        &(Arc::new(UnboundedMemoryPool::default()) as _),
+        "".to_owned(),
+    )
+    .await
+    .unwrap();
+    let [add_one, hello, safe_div] = udfs.try_into().unwrap();
+
+    assert_eq!(add_one.name(), "add_one");
+    assert_eq!(
+        add_one.signature(),
+        &Signature::exact(vec![DataType::Int64], Volatility::Immutable),
+    );
+    assert_eq!(
+        add_one.return_type(&[DataType::Int64]).unwrap(),
+        DataType::Int64,
+    );
+    let array = add_one
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![ColumnarValue::Array(Arc::new(Int64Array::from_iter([
+                Some(3),
+                None,
+                Some(i64::MAX),
+            ])))],
+            arg_fields: vec![Arc::new(Field::new("a1", DataType::Int64, true))],
+            number_rows: 3,
+            return_field: Arc::new(Field::new("r", DataType::Int64, true)),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+        .unwrap()
+        .unwrap_array();
+    assert_eq!(
+        array.as_ref(),
+        &Int64Array::from_iter([Some(4), None, None]) as &dyn Array,
+    );
+    let scalar = add_one
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![ColumnarValue::Scalar(ScalarValue::Int64(Some(3)))],
+            arg_fields: vec![Arc::new(Field::new("a1", DataType::Int64, true))],
+            number_rows: 3,
+            return_field: Arc::new(Field::new("r", DataType::Int64, true)),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+        .unwrap()
+        .unwrap_scalar();
+    assert_eq!(scalar, ScalarValue::Int64(Some(4)));
+
+    assert_eq!(hello.name(), "hello");
+    assert_eq!(
+        hello.return_type(&[DataType::Utf8]).unwrap(),
+        DataType::Utf8,
+    );
+    let array = hello
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![ColumnarValue::Array(Arc::new(StringArray::from_iter([
+                Some("foo"),
+                None,
+            ])))],
+            arg_fields: vec![Arc::new(Field::new("a1", DataType::Utf8, true))],
+            number_rows: 2,
+            return_field: Arc::new(Field::new("r", DataType::Utf8, true)),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+        .unwrap()
+        .unwrap_array();
+    assert_eq!(
+        array.as_ref(),
+        &StringArray::from_iter([Some("Hello, foo!"), Some("Hello, world!")]) as &dyn Array,
+    );
+
+    assert_eq!(safe_div.name(), "safe_div");
+    let args = |b: i64| ScalarFunctionArgs {
+        args: vec![
+            ColumnarValue::Array(Arc::new(Int64Array::from_iter([Some(6), None]))),
+            ColumnarValue::Scalar(ScalarValue::Int64(Some(b))),
+        ],
+        arg_fields: vec![
+            Arc::new(Field::new("a1", DataType::Int64, true)),
+            Arc::new(Field::new("a2", DataType::Int64, true)),
+        ],
+        number_rows: 2,
+        return_field: Arc::new(Field::new("r", DataType::Int64, true)),
+        config_options: Arc::new(ConfigOptions::default()),
+    };
+    let array = safe_div
+        .invoke_async_with_args(args(2))
+        .await
+        .unwrap()
+        .unwrap_array();
+    assert_eq!(
+        array.as_ref(),
+        &Int64Array::from_iter([Some(3), None]) as &dyn Array,
+    );
+    insta::assert_snapshot!(
+        safe_div.invoke_async_with_args(args(0)).await.unwrap_err(),
+        @"Execution error: division by zero",
+    );
+}
+
+#[tokio::test]
+async fn test_compatible_arg_types_are_cast() {
+    let udfs = WasmScalarUdf::new(
+        component_simple().await,
+        &Default::default(),
+        Handle::current(),
+        &(Arc::new(UnboundedMemoryPool::default()) as _),
+        "".to_owned(),
+    )
+    .await
+    .unwrap();
+    let [_add_one, hello, _safe_div] = udfs.try_into().unwrap();
+
+    // the guest only handles the declared `Utf8` layout
+    assert_eq!(
+        hello.return_type(&[DataType::Utf8View]).unwrap(),
+        DataType::Utf8,
+    );
+    let array = hello
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![ColumnarValue::Array(Arc::new(StringViewArray::from_iter(
+                [Some("foo"), None],
+            )))],
+            arg_fields: vec![Arc::new(Field::new("a1", DataType::Utf8View, true))],
+            number_rows: 2,
+            return_field: Arc::new(Field::new("r", DataType::Utf8, true)),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+        .unwrap()
+        .unwrap_array();
+    assert_eq!(
+        array.as_ref(),
+        &StringArray::from_iter([Some("Hello, foo!"), Some("Hello, world!")]) as &dyn Array,
+    );
+}
+
+#[tokio::test]
+async fn test_return_type_prefetched() {
+    let udf = udf_add_one().await;
+
+    // listed by the uniform signature, so this does NOT block
+    assert_eq!(
+        udf.return_type(&[DataType::Int64]).unwrap(),
+        DataType::Int64,
+    );
+}
+
+#[tokio::test]
+async fn test_planner_hints() {
+    let ordered =
+        |options| [ExprProperties::new_unknown().with_order(SortProperties::Ordered(options))];
+    let asc = SortOptions::default();
+    let desc = SortOptions {
+        descending: true,
+        nulls_first: false,
+    };
+
+    let add_one = udf_add_one().await;
+    assert!(!add_one.short_circuits());
+    assert_eq!(
+        add_one.output_ordering(&ordered(asc)).unwrap(),
+        SortProperties::Ordered(asc),
+    );
+    assert_eq!(
+        add_one.output_ordering(&ordered(desc)).unwrap(),
+        SortProperties::Ordered(desc),
+    );
+    assert_eq!(
+        add_one
+            .output_ordering(&[ExprProperties::new_unknown()])
+            .unwrap(),
+        SortProperties::Unordered,
+    );
+    assert!(add_one.preserves_lex_ordering(&ordered(asc)).unwrap());
+
+    // DataFusion plans with the registered UDF, so it must report the same hints
+    let registered = Arc::new(add_one).into_scalar_udf();
+    assert!(!registered.short_circuits());
+    assert_eq!(
+        registered.output_ordering(&ordered(desc)).unwrap(),
+        SortProperties::Ordered(desc),
+    );
+    assert!(registered.preserves_lex_ordering(&ordered(asc)).unwrap());
+
+    let sub_str = udf_sub_str().await;
+    assert!(!sub_str.short_circuits());
+    assert_eq!(
+        sub_str.output_ordering(&ordered(asc)).unwrap(),
+        SortProperties::Unordered,
+    );
+    assert!(!sub_str.preserves_lex_ordering(&ordered(asc)).unwrap());
+
+    let registered = Arc::new(sub_str).into_scalar_udf();
+    assert_eq!(
+        registered.output_ordering(&ordered(asc)).unwrap(),
+        SortProperties::Unordered,
+    );
+    assert!(!registered.preserves_lex_ordering(&ordered(asc)).unwrap());
+}
+
 // FIXME: remove `multi_thread` flavor.
 //
-// This test relies on a non-exact function signature to verify error handling
-// in `return_type``. [WasmScalarUdf::return_type](ScalarUdfImpl::return_type)
-// is *not* async, and will need to compute the return type if the function
-// signature is not exact, which effectively means it will block; which is
-// incompatible with the current single-threaded tokio runtime used in tests.
+// This test passes argument types that the function signature does not list to
+// verify error handling in `return_type``. [WasmScalarUdf::return_type](ScalarUdfImpl::return_type)
+// is *not* async, and will need to compute the return type if it was not
+// pre-fetched, which effectively means it will block; which is incompatible with
+// the current single-threaded tokio runtime used in tests.
 #[tokio::test(flavor = "multi_thread")]
 async fn test_sub_str() {
     let udf = udf_sub_str().await;
@@ -140,6 +355,32 @@ async fn test_sub_str() {
     assert_eq!(scalar, ScalarValue::Utf8(Some("bar".to_owned())));
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn test_simplify() {
+    let udf = Arc::new(udf_add_one().await).into_scalar_udf();
+    let props = ExecutionProps::new();
+
+    let ExprSimplifyResult::Simplified(expr) = udf
+        .simplify(vec![lit(3i64)], &SimplifyContext::new(&props))
+        .unwrap()
+    else {
+        panic!("call was not folded");
+    };
+    assert_eq!(expr, lit(4i64));
+}
+
+#[tokio::test]
+async fn test_simplify_current_thread_runtime() {
+    let udf = udf_add_one().await;
+    let props = ExecutionProps::new();
+
+    // cannot block in place, so the call is evaluated during execution instead
+    let res = udf
+        .simplify(vec![lit(3i64)], &SimplifyContext::new(&props))
+        .unwrap();
+    assert!(matches!(res, ExprSimplifyResult::Original(_)));
+}
+
 #[tokio::test]
 async fn test_invoke_with_args_returns_error() {
     let udf = udf_add_one().await;
@@ -231,6 +472,119 @@ async fn test_component_initial_mem_is_included_in_mem() {
     );
 }
 
+#[tokio::test]
+async fn test_dynamic_memory_limits() {
+    const LIMIT: usize = 100_000_000;
+
+    let component = component_add_one().await;
+    let create = async |limits: DynamicMemoryLimits, pool_limit: usize| {
+        WasmScalarUdf::new(
+            component,
+            &WasmPermissions::default().with_dynamic_memory_limits(limits),
+            Handle::current(),
+            &(Arc::new(GreedyMemoryPool::new(pool_limit)) as _),
+            "".to_owned(),
+        )
+        .await
+    };
+
+    // pre-reservation must fit into the pool
+    let err = create(
+        DynamicMemoryLimits {
+            prereserve_bytes: 2_000_000,
+            ..Default::default()
+        },
+        1_000_000,
+    )
+    .await
+    .unwrap_err();
+    insta::assert_snapshot!(
+        err,
+        @r"
+    pre-reserve memory
+    caused by
+    Resources exhausted: Failed to allocate additional 1953.1 KB for WASM UDF resources with 0.0 B already allocated for this reservation - 976.6 KB remain available for the total pool
+    "
+    );
+
+    // pool is under pressure, so the guest cannot grow
+    let err = create(
+        DynamicMemoryLimits {
+            pool_headroom_bytes: LIMIT,
+            ..Default::default()
+        },
+        LIMIT,
+    )
+    .await
+    .unwrap_err();
+    insta::assert_snapshot!(
+        err,
+        @r"
+    link WASM components
+    caused by
+    External error: initialize bindings
+    "
+    );
+
+    // pre-reserved memory is not affected by pool pressure
+    let udfs = create(
+        DynamicMemoryLimits {
+            prereserve_bytes: LIMIT / 2,
+            pool_headroom_bytes: LIMIT,
+        },
+        LIMIT,
+    )
+    .await
+    .unwrap();
+    let usage = udfs[0].resource_usage().await;
+    assert_eq!(usage.reserved_bytes, LIMIT / 2);
+    assert!(usage.linear_memory_bytes < LIMIT / 2);
+}
+
+#[tokio::test]
+async fn test_conversion_buffers_are_included_in_mem() {
+    const LIMIT: usize = 100_000_000;
+
+    let component = component_add_one().await;
+    let pool = Arc::new(GreedyMemoryPool::new(LIMIT));
+    let udf = WasmScalarUdf::new(
+        component,
+        &WasmPermissions::default(),
+        Handle::current(),
+        &(Arc::clone(&pool) as _),
+        "".to_owned(),
+    )
+    .await
+    .unwrap()
+    .pop()
+    .unwrap();
+
+    let args = || ScalarFunctionArgs {
+        args: vec![ColumnarValue::Array(Arc::new(Int64Array::from_iter(
+            (0..10_000).map(Some),
+        )))],
+        arg_fields: vec![Arc::new(Field::new("a1", DataType::Int64, true))],
+        number_rows: 10_000,
+        return_field: Arc::new(Field::new("r", DataType::Int64, true)),
+        config_options: Arc::new(ConfigOptions::default()),
+    };
+
+    // leave less space than the serialized input needs
+    let mut hog = MemoryConsumer::new("hog").register(&(Arc::clone(&pool) as _));
+    hog.try_grow(LIMIT - pool.reserved() - 1_000).unwrap();
+
+    let err = udf.invoke_async_with_args(args()).await.unwrap_err();
+    let err = err.to_string();
+    assert!(
+        err.contains("Failed to allocate additional")
+            && err.contains("for WASM UDF conversion buffers"),
+        "{err}",
+    );
+
+    drop(hog);
+    udf.invoke_async_with_args(args()).await.unwrap();
+}
+
 #[tokio::test]
 async fn test_limit_initial_n_instances() {
     let component = component_add_one().await;
@@ -361,7 +715,69 @@ async fn test_match_target() {
     let data = component.store().to_vec();
     // SAFETY: we just compiled that
     let res = unsafe { WasmComponentPrecompiled::load(data) };
-    res.unwrap();
+    assert_eq!(res.unwrap().digest(), component.digest());
+}
+
+#[tokio::test]
+async fn test_wit_version() {
+    let udf = udf_add_one().await;
+    assert_eq!(udf.wit_version(), WitVersion::HOST);
+
+    let description = component_add_one().await.describe().unwrap();
+    assert_eq!(description.wit_version, Some(WitVersion::HOST.to_string()),);
+}
+
+#[tokio::test]
+async fn test_describe() {
+    let component = component_add_one().await;
+    let description = component.describe().unwrap();
+
+    assert_eq!(description.wit_version.as_deref(), Some("0.9.0"));
+    assert!(
+        description
+            .exports
+            .iter()
+            .any(|name| name == "datafusion-udf-wasm:udf/types@0.9.0"),
+        "{description:?}",
+    );
+    // the example does not embed a runtime section
+    assert_eq!(description.runtime, None);
+    assert_eq!(description.precompiled_bytes, component.store().len());
+
+    // SAFETY: we just compiled that
+    let loaded = unsafe { WasmComponentPrecompiled::load(component.store().to_vec()) }.unwrap();
+    assert_eq!(loaded.describe().unwrap(), description);
+}
+
+#[tokio::test]
+async fn test_health_check() {
+    let component = component_add_one().await;
+
+    let health = component
+        .health_check(&WasmPermissions::default(), Handle::current())
+        .await
+        .unwrap();
+    assert_eq!(health.wit_version, WitVersion::HOST);
+    // the example does not embed a runtime section
+    assert_eq!(health.runtime, None);
+    // the example ignores the source code
+    assert_eq!(health.probe_udfs, 1);
+
+    let err = component
+        .health_check(
+            &WasmPermissions::default().with_max_udfs(0),
+            Handle::current(),
+        )
+        .await
+        .unwrap_err();
+    insta::assert_snapshot!(
+        err,
+        @"
+    probe
+    caused by
+    Resources exhausted: guest returned too many UDFs: got=1, limit=0
+    ",
+    );
 }
 
 #[cfg(feature = "all-arch")]
@@ -378,6 +794,9 @@ async fn test_mismatch_target() {
     .await
     .unwrap();
 
+    // the digest identifies the WASM payload, independent of the target
+    assert_eq!(component.digest(), component_add_one().await.digest());
+
     // instantiating doesn't work
     let err = WasmScalarUdf::new(
         &component,
@@ -452,6 +871,220 @@ async fn test_undersize_resource_cache() {
     );
 }
 
+#[tokio::test]
+async fn test_result_checksums() {
+    let component = component_add_one().await;
+    let udf = WasmScalarUdf::new(
+        component,
+        &WasmPermissions::default().with_result_checksums(true),
+        Handle::current(),
+        &(Arc::new(UnboundedMemoryPool::default()) as _),
+        "".to_owned(),
+    )
+    .await
+    .unwrap()
+    .into_iter()
+    .next()
+    .unwrap();
+
+    let array = udf
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![ColumnarValue::Array(Arc::new(Int64Array::from_iter([
+                Some(3),
+                None,
+            ])))],
+            arg_fields: vec![Arc::new(Field::new("a1", DataType::Int64, true))],
+            number_rows: 2,
+            return_field: Arc::new(Field::new("r", DataType::Int64, true)),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+        .unwrap()
+        .unwrap_array();
+    assert_eq!(
+        array.as_ref(),
+        &Int64Array::from_iter([Some(4), None]) as &dyn Array,
+    );
+
+    let scalar = udf
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![ColumnarValue::Scalar(ScalarValue::Int64(Some(3)))],
+            arg_fields: vec![Arc::new(Field::new("a1", DataType::Int64, true))],
+            number_rows: 2,
+            return_field: Arc::new(Field::new("r", DataType::Int64, true)),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+        .unwrap()
+        .unwrap_scalar();
+    assert_eq!(scalar, ScalarValue::Int64(Some(4)));
+}
+
+#[tokio::test]
+async fn test_ipc_compression() {
+    let component = component_add_one().await;
+
+    // the guest only supports LZ4, so the last case falls back to uncompressed payloads
+    for preferred in [
+        vec![IpcCompression::Lz4Frame],
+        vec![IpcCompression::Zstd, IpcCompression::Lz4Frame],
+        vec![IpcCompression::Zstd],
+    ] {
+        let udf = WasmScalarUdf::new(
+            component,
+            &WasmPermissions::default()
+                .with_ipc_compression(preferred)
+                .with_result_checksums(true),
+            Handle::current(),
+            &(Arc::new(UnboundedMemoryPool::default()) as _),
+            "".to_owned(),
+        )
+        .await
+        .unwrap()
+        .into_iter()
+        .next()
+        .unwrap();
+
+        let array = udf
+            .invoke_async_with_args(ScalarFunctionArgs {
+                args: vec![ColumnarValue::Array(Arc::new(Int64Array::from_iter(
+                    (0..1_000).map(|i| (i % 3 != 0).then_some(i)),
+                )))],
+                arg_fields: vec![Arc::new(Field::new("a1", DataType::Int64, true))],
+                number_rows: 1_000,
+                return_field: Arc::new(Field::new("r", DataType::Int64, true)),
+                config_options: Arc::new(ConfigOptions::default()),
+            })
+            .await
+            .unwrap()
+            .unwrap_array();
+        assert_eq!(
+            array.as_ref(),
+            &Int64Array::from_iter((0..1_000).map(|i| (i % 3 != 0).then_some(i + 1))) as &dyn Array,
+        );
+
+        let scalar = udf
+            .invoke_async_with_args(ScalarFunctionArgs {
+                args: vec![ColumnarValue::Scalar(ScalarValue::Int64(Some(3)))],
+                arg_fields: vec![Arc::new(Field::new("a1", DataType::Int64, true))],
+                number_rows: 2,
+                return_field: Arc::new(Field::new("r", DataType::Int64, true)),
+                config_options: Arc::new(ConfigOptions::default()),
+            })
+            .await
+            .unwrap()
+            .unwrap_scalar();
+        assert_eq!(scalar, ScalarValue::Int64(Some(4)));
+    }
+}
+
+#[tokio::test]
+async fn test_intra_batch_parallelism() {
+    let component = component_add_one().await;
+    let udf = WasmScalarUdf::new(
+        component,
+        &WasmPermissions::default().with_intra_batch_parallelism(NonZeroUsize::new(3).unwrap()),
+        Handle::current(),
+        &(Arc::new(UnboundedMemoryPool::default()) as _),
+        "".to_owned(),
+    )
+    .await
+    .unwrap()
+    .into_iter()
+    .next()
+    .unwrap();
+
+    // 7 rows are split into slices of 3, 3, and 1 rows
+    let array = udf
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![ColumnarValue::Array(Arc::new(Int64Array::from_iter([
+                Some(1),
+                None,
+                Some(3),
+                Some(4),
+                Some(5),
+                None,
+                Some(7),
+            ])))],
+            arg_fields: vec![Arc::new(Field::new("a1", DataType::Int64, true))],
+            number_rows: 7,
+            return_field: Arc::new(Field::new("r", DataType::Int64, true)),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+        .unwrap()
+        .unwrap_array();
+    assert_eq!(
+        array.as_ref(),
+        &Int64Array::from_iter([Some(2), None, Some(4), Some(5), Some(6), None, Some(8)])
+            as &dyn Array,
+    );
+
+    // fewer rows than instances
+    let array = udf
+        .invoke_async_with_args(ScalarFunctionArgs {
+            args: vec![ColumnarValue::Array(Arc::new(Int64Array::from_iter([
+                Some(1),
+                Some(2),
+            ])))],
+            arg_fields: vec![Arc::new(Field::new("a1", DataType::Int64, true))],
+            number_rows: 2,
+            return_field: Arc::new(Field::new("r", DataType::Int64, true)),
+            config_options: Arc::new(ConfigOptions::default()),
+        })
+        .await
+        .unwrap()
+        .unwrap_array();
+    assert_eq!(
+        array.as_ref(),
+        &Int64Array::from_iter([Some(2), Some(3)]) as &dyn Array,
+    );
+}
+
+#[tokio::test]
+async fn test_nn_models() {
+    let permissions =
+        WasmPermissions::new().with_nn([NnModel::onnx("scorer", "/this/path/does/not/exist")]);
+
+    let err = WasmScalarUdf::new(
+        component_add_one().await,
+        &permissions,
+        Handle::current(),
+        &(Arc::new(UnboundedMemoryPool::default()) as _),
+        "".to_owned(),
+    )
+    .await
+    .unwrap_err();
+
+    #[cfg(not(feature = "nn"))]
+    insta::assert_snapshot!(
+        err,
+        @r"
+    set up wasi-nn
+    caused by
+    This feature is not implemented: wasi-nn models require the `nn` feature
+    ",
+    );
+
+    #[cfg(feature = "nn")]
+    insta::assert_snapshot!(
+        err,
+        @r"
+    set up wasi-nn
+    caused by
+    load model `scorer` from `/this/path/does/not/exist`
+    caused by
+    IO error: No such file or directory (os error 2)
+    ",
+    );
+
+    // models are part of the fingerprint
+    assert_ne!(
+        permissions.fingerprint(),
+        WasmPermissions::new().fingerprint()
+    );
+}
+
 async fn component_add_one() -> &'static WasmComponentPrecompiled {
     static COMPONENT: OnceCell<WasmComponentPrecompiled> = OnceCell::const_new();
 
@@ -467,6 +1100,21 @@ async fn component_add_one() -> &'static WasmComponentPrecompiled {
         .await
 }
 
+async fn component_simple() -> &'static WasmComponentPrecompiled {
+    static COMPONENT: OnceCell<WasmComponentPrecompiled> = OnceCell::const_new();
+
+    COMPONENT
+        .get_or_init(async || {
+            WasmComponentPrecompiled::compile(
+                datafusion_udf_wasm_bundle::BIN_EXAMPLE_SIMPLE.into(),
+                &CompilationFlags::default(),
+            )
+            .await
+            .unwrap()
+        })
+        .await
+}
+
 async fn component_sub_str() -> &'static WasmComponentPrecompiled {
     static COMPONENT: OnceCell<WasmComponentPrecompiled> = OnceCell::const_new();
 
@@ -0,0 +1,22 @@
+use datafusion_udf_wasm_host::{SelfCheckStep, self_check};
+
+#[tokio::test]
+async fn test_self_check() {
+    let report = self_check().await;
+
+    assert!(report.is_ok(), "{report}");
+    assert_eq!(
+        report
+            .steps
+            .iter()
+            .map(|step| step.step)
+            .collect::<Vec<_>>(),
+        vec![
+            SelfCheckStep::Compile,
+            SelfCheckStep::Load,
+            SelfCheckStep::Invoke,
+            SelfCheckStep::MemoryLimit,
+            SelfCheckStep::EpochInterruption,
+        ],
+    );
+}
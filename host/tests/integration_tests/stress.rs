@@ -0,0 +1,258 @@
+//! Stress tests that hammer many UDF instances with concurrent, randomly cancelled invocations.
+//!
+//! These exercise the cancellation-safety promised by [`WasmScalarUdf`] and check that no resources are leaked:
+//!
+//! - **store mutex:** every instance is still usable after the storm, i.e. no cancelled call left the store locked
+//! - **epoch timer:** every instance spawns an epoch task on the I/O runtime, which must go away with the instance
+//! - **memory accounting:** all reservations are returned to the memory pool once the UDFs are dropped
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, SystemTime},
+};
+
+use arrow::{
+    array::{Array, Int64Array},
+    datatypes::{DataType, Field},
+};
+use datafusion_common::config::ConfigOptions;
+use datafusion_execution::memory_pool::{MemoryPool, UnboundedMemoryPool};
+use datafusion_expr::{ColumnarValue, ScalarFunctionArgs, async_udf::AsyncScalarUDFImpl};
+use datafusion_udf_wasm_host::{CompilationFlags, WasmComponentPrecompiled, WasmScalarUdf};
+use tokio::{runtime::Runtime, task::JoinSet};
+
+use crate::integration_tests::test_utils::ColumnarValueExt;
+
+/// Number of instances of the Rust `add_one` example.
+const N_RUST_INSTANCES: usize = 32;
+
+/// Number of Python instances.
+///
+/// These are considerably more expensive to create than the Rust ones.
+const N_PYTHON_INSTANCES: usize = 4;
+
+/// Total number of invocations, spread over all instances.
+const N_INVOCATIONS: usize = 4_000;
+
+/// Every n-th invocation is cancelled after a random delay.
+const CANCEL_EVERY: u64 = 3;
+
+/// Upper bound for the cancellation delay.
+const MAX_CANCEL_DELAY: Duration = Duration::from_micros(500);
+
+/// Upper bound for the whole stress run.
+///
+/// Exceeding it most likely means that a cancelled call left a store locked.
+const DEADLOCK_TIMEOUT: Duration = Duration::from_secs(120);
+
+const PYTHON_CODE: &str = "
+def add_one(x: int) -> int:
+    acc = 0
+    for i in range(1_000):
+        acc += i
+    return x + 1
+";
+
+#[test]
+fn test_concurrent_invocations_with_cancellation() {
+    let seed = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos() as u64;
+
+    let cpu_rt = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(4)
+        .enable_time()
+        .build()
+        .unwrap();
+    let io_rt = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(2)
+        .enable_time()
+        .build()
+        .unwrap();
+    assert_eq!(io_rt.metrics().num_alive_tasks(), 0);
+
+    let pool: Arc<dyn MemoryPool> = Arc::new(UnboundedMemoryPool::default());
+
+    let udfs = cpu_rt.block_on(create_udfs(&io_rt, &pool));
+    assert_eq!(udfs.len(), N_RUST_INSTANCES + N_PYTHON_INSTANCES);
+    assert_eq!(
+        io_rt.metrics().num_alive_tasks(),
+        udfs.len(),
+        "one epoch task per instance",
+    );
+
+    let stats = cpu_rt.block_on(async {
+        tokio::time::timeout(DEADLOCK_TIMEOUT, storm(&udfs, seed))
+            .await
+            .unwrap_or_else(|_| panic!("deadlock, seed={seed}"))
+    });
+    assert_eq!(
+        stats.completed + stats.cancelled,
+        N_INVOCATIONS as u64,
+        "every invocation must either complete or be cancelled, seed={seed}",
+    );
+    assert!(stats.completed > 0, "nothing completed, seed={seed}");
+    assert!(stats.cancelled > 0, "nothing cancelled, seed={seed}");
+
+    // every store must be unlocked again
+    cpu_rt.block_on(async {
+        tokio::time::timeout(DEADLOCK_TIMEOUT, async {
+            for udf in &udfs {
+                udf.invoke_async_with_args(args(1)).await.unwrap();
+            }
+        })
+        .await
+        .unwrap_or_else(|_| panic!("deadlock, seed={seed}"));
+    });
+
+    assert!(pool.reserved() > 0, "instances hold reservations");
+    drop(udfs);
+    assert_eq!(pool.reserved(), 0, "all reservations are returned");
+
+    // The epoch tasks notice that the engine is gone on their next tick, so wait a bit.
+    cpu_rt.block_on(async {
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while io_rt.metrics().num_alive_tasks() > 0 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("epoch tasks leaked");
+    });
+}
+
+/// Create [`N_RUST_INSTANCES`] + [`N_PYTHON_INSTANCES`] independent UDFs.
+async fn create_udfs(io_rt: &Runtime, pool: &Arc<dyn MemoryPool>) -> Vec<Arc<WasmScalarUdf>> {
+    let rust = compile(datafusion_udf_wasm_bundle::BIN_EXAMPLE_ADD_ONE).await;
+    let python = compile(datafusion_udf_wasm_bundle::BIN_PYTHON).await;
+
+    let mut tasks = JoinSet::new();
+    let instances = std::iter::repeat_n((Arc::clone(&rust), ""), N_RUST_INSTANCES).chain(
+        std::iter::repeat_n((Arc::clone(&python), PYTHON_CODE), N_PYTHON_INSTANCES),
+    );
+    for (component, code) in instances {
+        let io_rt = io_rt.handle().clone();
+        let pool = Arc::clone(pool);
+        tasks.spawn(async move {
+            let mut udfs = WasmScalarUdf::new(
+                &component,
+                &Default::default(),
+                io_rt,
+                &pool,
+                code.to_owned(),
+            )
+            .await
+            .unwrap();
+            assert_eq!(udfs.len(), 1);
+            Arc::new(udfs.pop().unwrap())
+        });
+    }
+
+    tasks.join_all().await
+}
+
+async fn compile(binary: &[u8]) -> Arc<WasmComponentPrecompiled> {
+    Arc::new(
+        WasmComponentPrecompiled::compile(binary.into(), &CompilationFlags::default())
+            .await
+            .unwrap(),
+    )
+}
+
+/// Outcome of [`storm`].
+#[derive(Debug, Default)]
+struct Stats {
+    /// Invocations that returned a correct result.
+    completed: u64,
+
+    /// Invocations that were cancelled before they finished.
+    cancelled: u64,
+}
+
+/// Fire [`N_INVOCATIONS`] concurrent invocations at random UDFs, cancelling some of them.
+///
+/// The choices are derived from the given seed.
+async fn storm(udfs: &[Arc<WasmScalarUdf>], seed: u64) -> Stats {
+    let rng = Rng::new(seed);
+    let mut tasks = JoinSet::new();
+    for _ in 0..N_INVOCATIONS {
+        let udf = Arc::clone(&udfs[rng.next_below(udfs.len() as u64) as usize]);
+        let x = rng.next_below(1_000) as i64;
+        let cancel_after = (rng.next_below(CANCEL_EVERY) == 0)
+            .then(|| Duration::from_nanos(rng.next_below(MAX_CANCEL_DELAY.as_nanos() as u64)));
+
+        tasks.spawn(async move {
+            let fut = async {
+                let array = udf
+                    .invoke_async_with_args(args(x))
+                    .await
+                    .unwrap_or_else(|e| panic!("invocation failed, seed={seed}: {e}"))
+                    .unwrap_array();
+                assert_eq!(
+                    array.as_ref(),
+                    &Int64Array::from_iter([Some(x + 1)]) as &dyn Array,
+                    "seed={seed}",
+                );
+            };
+
+            match cancel_after {
+                Some(delay) => tokio::time::timeout(delay, fut).await.is_ok(),
+                None => {
+                    fut.await;
+                    true
+                }
+            }
+        });
+    }
+
+    let mut stats = Stats::default();
+    for completed in tasks.join_all().await {
+        if completed {
+            stats.completed += 1;
+        } else {
+            stats.cancelled += 1;
+        }
+    }
+    stats
+}
+
+fn args(x: i64) -> ScalarFunctionArgs {
+    ScalarFunctionArgs {
+        args: vec![ColumnarValue::Array(Arc::new(Int64Array::from_iter([
+            Some(x),
+        ])))],
+        arg_fields: vec![Arc::new(Field::new("x", DataType::Int64, true))],
+        number_rows: 1,
+        return_field: Arc::new(Field::new("r", DataType::Int64, true)),
+        config_options: Arc::new(ConfigOptions::default()),
+    }
+}
+
+/// Minimal [SplitMix64](https://prng.di.unimi.it/splitmix64.c) generator.
+///
+/// Good enough to randomize the test and avoids pulling in a dependency.
+#[derive(Debug)]
+struct Rng(AtomicU64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Self(AtomicU64::new(seed))
+    }
+
+    fn next(&self) -> u64 {
+        let mut z = self
+            .0
+            .fetch_add(0x9E37_79B9_7F4A_7C15, Ordering::Relaxed)
+            .wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn next_below(&self, n: u64) -> u64 {
+        self.next() % n
+    }
+}
@@ -0,0 +1,192 @@
+//! Caching of UDFs across queries.
+
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    num::NonZeroUsize,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use datafusion_common::Result as DataFusionResult;
+use datafusion_udf_wasm_host::WasmScalarUdf;
+
+/// Limits of the [UDF cache](crate::UdfQueryParser::with_cache) and of the
+/// [codec cache](crate::codec::WasmUdfCodec::with_cache_limits).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UdfCacheLimits {
+    /// Maximum number of cached UDF definitions.
+    ///
+    /// Every `CREATE FUNCTION` body -- or every source code of a decoded plan -- counts as one entry, even if it defines
+    /// multiple UDFs. Once the limit is reached, the least recently used entry is evicted.
+    pub max_entries: NonZeroUsize,
+
+    /// Time after which a cached entry is discarded, counted from its creation.
+    pub ttl: Duration,
+}
+
+impl Default for UdfCacheLimits {
+    fn default() -> Self {
+        Self {
+            max_entries: NonZeroUsize::new(100).expect("valid value"),
+            ttl: Duration::from_secs(600),
+        }
+    }
+}
+
+/// Key of the [`UdfCache`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct UdfCacheKey {
+    /// Language name.
+    pub(crate) lang: String,
+
+    /// [Digest](datafusion_udf_wasm_host::WasmComponentPrecompiled::digest) of the component.
+    pub(crate) component_digest: u128,
+
+    /// [Fingerprint](datafusion_udf_wasm_host::WasmPermissions::fingerprint) of the permissions.
+    pub(crate) permissions_fingerprint: u64,
+
+    /// [Formatted](crate::format::UdfCodeFormatter) source code.
+    pub(crate) code: String,
+}
+
+/// Entry of a [`LruCache`].
+#[derive(Debug)]
+struct CacheEntry<V> {
+    /// Cached value.
+    value: V,
+
+    /// Creation time.
+    created: Instant,
+
+    /// Last lookup.
+    last_used: Instant,
+}
+
+/// Cache that evicts the least recently used entry and expires entries, see [`UdfCacheLimits`].
+#[derive(Debug)]
+pub(crate) struct LruCache<K, V> {
+    /// Limits.
+    limits: UdfCacheLimits,
+
+    /// Entries.
+    entries: Mutex<HashMap<K, CacheEntry<V>>>,
+}
+
+impl<K, V> LruCache<K, V>
+where
+    K: Clone + Eq + Hash,
+    V: Clone,
+{
+    /// Create empty cache.
+    pub(crate) fn new(limits: UdfCacheLimits) -> Self {
+        Self {
+            limits,
+            entries: Mutex::default(),
+        }
+    }
+
+    /// Get cached value.
+    pub(crate) fn get(&self, key: &K) -> Option<V> {
+        let now = Instant::now();
+        let mut entries = self.entries.lock().expect("not poisoned");
+        entries.retain(|_, entry| now.duration_since(entry.created) < self.limits.ttl);
+        let entry = entries.get_mut(key)?;
+        entry.last_used = now;
+        Some(entry.value.clone())
+    }
+
+    /// Insert value, unless there is one already.
+    ///
+    /// Returns the cached value.
+    pub(crate) fn insert(&self, key: K, value: V) -> V {
+        let now = Instant::now();
+        let mut entries = self.entries.lock().expect("not poisoned");
+        if !entries.contains_key(&key) && entries.len() >= self.limits.max_entries.get() {
+            let lru = entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone());
+            if let Some(lru) = lru {
+                entries.remove(&lru);
+            }
+        }
+        let entry = entries.entry(key).or_insert(CacheEntry {
+            value,
+            created: now,
+            last_used: now,
+        });
+        entry.value.clone()
+    }
+}
+
+/// Cache of UDFs, see [`UdfQueryParser::with_cache`](crate::UdfQueryParser::with_cache).
+#[derive(Debug)]
+pub(crate) struct UdfCache(LruCache<UdfCacheKey, Vec<Arc<WasmScalarUdf>>>);
+
+impl UdfCache {
+    /// Create empty cache.
+    pub(crate) fn new(limits: UdfCacheLimits) -> Self {
+        Self(LruCache::new(limits))
+    }
+
+    /// Get cached UDFs, creating them if required.
+    ///
+    /// The cache is NOT locked while the UDFs are created, so concurrent queries with the same code may both create
+    /// UDFs. Only one result is kept.
+    pub(crate) async fn get_or_create<F, Fut>(
+        &self,
+        key: UdfCacheKey,
+        create: F,
+    ) -> DataFusionResult<Vec<Arc<WasmScalarUdf>>>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = DataFusionResult<Vec<WasmScalarUdf>>>,
+    {
+        if let Some(udfs) = self.0.get(&key) {
+            return Ok(udfs);
+        }
+
+        let udfs = create()
+            .await?
+            .into_iter()
+            .map(Arc::new)
+            .collect::<Vec<_>>();
+        Ok(self.0.insert(key, udfs))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lru_cache_evicts_least_recently_used() {
+        let cache = LruCache::new(UdfCacheLimits {
+            max_entries: NonZeroUsize::new(2).unwrap(),
+            ttl: Duration::from_secs(600),
+        });
+        assert_eq!(cache.insert("a", 1), 1);
+        assert_eq!(cache.insert("b", 2), 2);
+
+        // existing entries are kept
+        assert_eq!(cache.insert("a", 10), 1);
+
+        // `b` is now the least recently used one
+        assert_eq!(cache.get(&"a"), Some(1));
+        assert_eq!(cache.insert("c", 3), 3);
+        assert_eq!(cache.get(&"a"), Some(1));
+        assert_eq!(cache.get(&"b"), None);
+        assert_eq!(cache.get(&"c"), Some(3));
+    }
+
+    #[test]
+    fn test_lru_cache_expires() {
+        let cache = LruCache::new(UdfCacheLimits {
+            max_entries: NonZeroUsize::new(2).unwrap(),
+            ttl: Duration::ZERO,
+        });
+        cache.insert("a", 1);
+        assert_eq!(cache.get(&"a"), None);
+    }
+}
@@ -0,0 +1,464 @@
+//! Serialization of WASM UDFs within DataFusion plans.
+//!
+//! See [`WasmUdfCodec`].
+
+use std::{collections::HashMap, sync::Arc};
+
+use datafusion_catalog::TableProvider;
+use datafusion_common::{
+    DataFusionError, Result as DataFusionResult, TableReference, arrow::datatypes::SchemaRef,
+};
+use datafusion_execution::{TaskContext, memory_pool::MemoryPool};
+use datafusion_expr::{Extension, LogicalPlan, ScalarUDF};
+use datafusion_physical_plan::ExecutionPlan;
+use datafusion_proto::{
+    logical_plan::{DefaultLogicalExtensionCodec, LogicalExtensionCodec},
+    physical_plan::{DefaultPhysicalExtensionCodec, PhysicalExtensionCodec},
+};
+use datafusion_udf_wasm_host::{WasmComponentPrecompiled, WasmPermissions, WasmScalarUdf};
+use prost::Message;
+use tokio::runtime::{Handle, RuntimeFlavor};
+
+use crate::cache::{LruCache, UdfCacheLimits};
+
+#[cfg(feature = "substrait")]
+pub use substrait::WASM_UDF_URI_SCHEME;
+
+#[cfg(feature = "substrait")]
+mod substrait;
+
+/// Prefix of every encoded WASM UDF.
+///
+/// This allows us to tell WASM UDFs apart from UDFs that are handled by the inner codec.
+const MAGIC: &[u8] = b"datafusion-udf-wasm:udf:v1:";
+
+/// Serialized form of a [`WasmScalarUdf`].
+#[derive(Clone, PartialEq, Message)]
+struct WasmUdfProto {
+    /// [Digest](WasmComponentPrecompiled::digest) of the component, little endian.
+    #[prost(bytes = "vec", tag = "1")]
+    component_digest: Vec<u8>,
+
+    /// Source code that the UDF was created from.
+    #[prost(string, tag = "2")]
+    source: String,
+
+    /// Name of the UDF.
+    #[prost(string, tag = "3")]
+    name: String,
+
+    /// [Fingerprint](WasmPermissions::fingerprint) of the permissions on the encoding side.
+    #[prost(uint64, tag = "4")]
+    permissions_fingerprint: u64,
+}
+
+/// Resolves components that were not [registered](WasmUdfCodec::with_component) upfront.
+///
+/// This allows decoding nodes to fetch components on demand, e.g. from a shared storage.
+pub trait WasmComponentResolver: std::fmt::Debug + Send + Sync {
+    /// Get component by [digest](WasmComponentPrecompiled::digest) and the permissions for UDFs created from it.
+    ///
+    /// Returns [`None`] if the component is unknown. This is called synchronously during decoding.
+    fn resolve(
+        &self,
+        component_digest: u128,
+    ) -> DataFusionResult<Option<(Arc<WasmComponentPrecompiled>, Arc<WasmPermissions>)>>;
+}
+
+/// Component that UDFs can be reconstructed from.
+#[derive(Debug, Clone)]
+struct RegisteredComponent {
+    /// Pre-compiled component.
+    component: Arc<WasmComponentPrecompiled>,
+
+    /// Permissions used for reconstructed UDFs.
+    permissions: Arc<WasmPermissions>,
+}
+
+/// Key for [already reconstructed UDFs](WasmUdfCodec::udfs).
+type UdfCacheKey = (u128, Arc<str>);
+
+/// [`LogicalExtensionCodec`] and [`PhysicalExtensionCodec`] that can ship WASM UDFs to other nodes.
+///
+/// A WASM UDF is encoded as a reference to its component, its source code, and its name. The decoding node looks up
+/// the component in its own registry -- see [`with_component`](Self::with_component) -- and re-creates the UDF from
+/// the source code.
+///
+/// # Permissions
+/// The permissions are NOT part of the encoded plan. Instead, every registered component comes with the permissions
+/// that the decoding node is willing to grant. This way a serialized plan can never escalate privileges.
+///
+/// The plan only carries a [fingerprint](WasmPermissions::fingerprint) of the permissions on the encoding side. If
+/// [requested](Self::with_matching_permissions), decoding fails if the decoding node uses different permissions.
+///
+/// # State
+/// UDFs that were created from the same source code share a single WASM VM on the encoding side. The same is true on
+/// the decoding side: reconstructed UDFs are cached by component and source code, so all UDFs of a plan -- and of
+/// later plans that use the same code -- share one VM. The cache is bounded, see
+/// [`with_cache_limits`](Self::with_cache_limits).
+///
+/// # Blocking
+/// DataFusion decodes UDFs synchronously, but creating a WASM UDF is async. Decoding therefore blocks in place, which
+/// only works within a multi-threaded tokio runtime.
+///
+/// # Substrait
+/// With the `substrait` feature enabled, WASM UDFs can also be shipped within [Substrait] plans, see
+/// `encode_substrait` and `decode_substrait`.
+///
+/// # Other Extensions
+/// Everything that is not a WASM UDF is handled by the inner codecs, see
+/// [`with_logical_codec`](Self::with_logical_codec) and [`with_physical_codec`](Self::with_physical_codec).
+///
+///
+/// [Substrait]: https://substrait.io/
+#[derive(Debug)]
+pub struct WasmUdfCodec {
+    /// Registered components, by [digest](WasmComponentPrecompiled::digest).
+    components: HashMap<u128, RegisteredComponent>,
+
+    /// Resolver for components that are not registered.
+    resolver: Option<Arc<dyn WasmComponentResolver>>,
+
+    /// Require that encoding and decoding side use the same permissions.
+    matching_permissions: bool,
+
+    /// Already reconstructed UDFs, by name.
+    udfs: LruCache<UdfCacheKey, HashMap<String, Arc<ScalarUDF>>>,
+
+    /// I/O runtime for reconstructed UDFs.
+    io_rt: Handle,
+
+    /// Memory pool for reconstructed UDFs.
+    memory_pool: Arc<dyn MemoryPool>,
+
+    /// Inner codec for logical plans.
+    logical_codec: Arc<dyn LogicalExtensionCodec>,
+
+    /// Inner codec for physical plans.
+    physical_codec: Arc<dyn PhysicalExtensionCodec>,
+}
+
+impl WasmUdfCodec {
+    /// Create new codec without any registered components.
+    ///
+    /// The I/O runtime and memory pool are used for reconstructed UDFs, see [`WasmScalarUdf::new`].
+    pub fn new(io_rt: Handle, memory_pool: Arc<dyn MemoryPool>) -> Self {
+        Self {
+            components: HashMap::new(),
+            resolver: None,
+            matching_permissions: false,
+            udfs: LruCache::new(UdfCacheLimits::default()),
+            io_rt,
+            memory_pool,
+            logical_codec: Arc::new(DefaultLogicalExtensionCodec {}),
+            physical_codec: Arc::new(DefaultPhysicalExtensionCodec {}),
+        }
+    }
+
+    /// Register component that UDFs can be reconstructed from.
+    ///
+    /// Reconstructed UDFs will use the given permissions. Registering the same component twice replaces the
+    /// permissions.
+    pub fn with_component(
+        mut self,
+        component: Arc<WasmComponentPrecompiled>,
+        permissions: Arc<WasmPermissions>,
+    ) -> Self {
+        self.components.insert(
+            component.digest(),
+            RegisteredComponent {
+                component,
+                permissions,
+            },
+        );
+        self
+    }
+
+    /// Set limits for the cache of reconstructed UDFs.
+    ///
+    /// Every cached source code keeps its WASM VM -- including its memory -- alive. UDFs that are still used by a plan
+    /// stay usable after they were evicted, but later plans get a new VM.
+    ///
+    /// Defaults to [`UdfCacheLimits::default`].
+    pub fn with_cache_limits(self, limits: UdfCacheLimits) -> Self {
+        Self {
+            udfs: LruCache::new(limits),
+            ..self
+        }
+    }
+
+    /// Set resolver for components that were not [registered](Self::with_component).
+    pub fn with_component_resolver(self, resolver: Arc<dyn WasmComponentResolver>) -> Self {
+        Self {
+            resolver: Some(resolver),
+            ..self
+        }
+    }
+
+    /// Require that the encoding and the decoding side use the same permissions, see
+    /// [`WasmPermissions::fingerprint`].
+    ///
+    /// Substrait plans do NOT carry a fingerprint and are therefore not checked.
+    ///
+    /// Defaults to `false`.
+    pub fn with_matching_permissions(self, enabled: bool) -> Self {
+        Self {
+            matching_permissions: enabled,
+            ..self
+        }
+    }
+
+    /// Set inner codec for logical plans.
+    pub fn with_logical_codec(self, codec: Arc<dyn LogicalExtensionCodec>) -> Self {
+        Self {
+            logical_codec: codec,
+            ..self
+        }
+    }
+
+    /// Set inner codec for physical plans.
+    pub fn with_physical_codec(self, codec: Arc<dyn PhysicalExtensionCodec>) -> Self {
+        Self {
+            physical_codec: codec,
+            ..self
+        }
+    }
+
+    /// Encode UDF if it is a WASM UDF.
+    ///
+    /// Returns `false` if the UDF is NOT a WASM UDF.
+    fn encode_udf(node: &ScalarUDF, buf: &mut Vec<u8>) -> bool {
+        let Some(udf) = WasmScalarUdf::from_scalar_udf(node) else {
+            return false;
+        };
+
+        let proto = WasmUdfProto {
+            component_digest: udf.component_digest().to_le_bytes().to_vec(),
+            source: udf.source().to_owned(),
+            name: node.name().to_owned(),
+            permissions_fingerprint: udf.permissions_fingerprint(),
+        };
+        buf.extend_from_slice(MAGIC);
+        proto.encode(buf).expect("Vec has unlimited capacity");
+        true
+    }
+
+    /// Decode UDF if the buffer contains a WASM UDF.
+    ///
+    /// Returns [`None`] if the buffer does NOT contain a WASM UDF.
+    fn decode_udf(&self, buf: &[u8]) -> Option<DataFusionResult<Arc<ScalarUDF>>> {
+        let buf = buf.strip_prefix(MAGIC)?;
+        Some(self.decode_udf_inner(buf))
+    }
+
+    /// Decode WASM UDF.
+    fn decode_udf_inner(&self, buf: &[u8]) -> DataFusionResult<Arc<ScalarUDF>> {
+        let WasmUdfProto {
+            component_digest,
+            source,
+            name,
+            permissions_fingerprint,
+        } = WasmUdfProto::decode(buf)
+            .map_err(|e| DataFusionError::Internal(format!("cannot decode WASM UDF: {e}")))?;
+        let component_digest = u128::from_le_bytes(
+            component_digest
+                .as_slice()
+                .try_into()
+                .map_err(|_| DataFusionError::Internal("invalid component digest".to_owned()))?,
+        );
+        self.udf(
+            component_digest,
+            Some(permissions_fingerprint),
+            source.into(),
+            &name,
+        )
+    }
+
+    /// Get reconstructed UDF, creating it if required.
+    fn udf(
+        &self,
+        component_digest: u128,
+        permissions_fingerprint: Option<u64>,
+        source: Arc<str>,
+        name: &str,
+    ) -> DataFusionResult<Arc<ScalarUDF>> {
+        // checked for every plan, since cached UDFs may have been created for a different encoding side
+        if self.matching_permissions
+            && let Some(permissions_fingerprint) = permissions_fingerprint
+            && self.component(component_digest)?.permissions.fingerprint()
+                != permissions_fingerprint
+        {
+            return Err(DataFusionError::Plan(format!(
+                "WASM component {component_digest:032x} uses different permissions than the encoding side"
+            )));
+        }
+
+        let key = (component_digest, source);
+
+        let udfs = match self.udfs.get(&key) {
+            Some(udfs) => udfs,
+            None => {
+                let udfs = self.create_udfs(key.0, &key.1)?;
+                self.udfs.insert(key, udfs)
+            }
+        };
+
+        udfs.get(name).map(Arc::clone).ok_or_else(|| {
+            DataFusionError::Plan(format!(
+                "WASM component did not re-create UDF '{name}' from the given source"
+            ))
+        })
+    }
+
+    /// Get registered or [resolved](Self::with_component_resolver) component.
+    fn component(&self, component_digest: u128) -> DataFusionResult<RegisteredComponent> {
+        if let Some(registered) = self.components.get(&component_digest) {
+            return Ok(registered.clone());
+        }
+
+        let resolved = match &self.resolver {
+            Some(resolver) => resolver
+                .resolve(component_digest)
+                .map_err(|e| e.context("resolve WASM component"))?,
+            None => None,
+        };
+        let Some((component, permissions)) = resolved else {
+            return Err(DataFusionError::Plan(format!(
+                "WASM component not registered: {component_digest:032x}"
+            )));
+        };
+        if component.digest() != component_digest {
+            return Err(DataFusionError::Plan(format!(
+                "resolver returned WASM component {:032x} instead of {component_digest:032x}",
+                component.digest()
+            )));
+        }
+
+        Ok(RegisteredComponent {
+            component,
+            permissions,
+        })
+    }
+
+    /// Create UDFs from the given component and source code.
+    fn create_udfs(
+        &self,
+        component_digest: u128,
+        source: &str,
+    ) -> DataFusionResult<HashMap<String, Arc<ScalarUDF>>> {
+        let RegisteredComponent {
+            component,
+            permissions,
+        } = self.component(component_digest)?;
+
+        let handle = Handle::try_current().map_err(|e| {
+            DataFusionError::External(Box::new(e))
+                .context("get tokio runtime for in-place blocking")
+        })?;
+        let flavor = handle.runtime_flavor();
+        if !matches!(flavor, RuntimeFlavor::MultiThread) {
+            return Err(DataFusionError::NotImplemented(format!(
+                "decoding WASM UDFs only works for tokio multi-thread runtimes, not for {flavor:?}"
+            )));
+        }
+
+        let udfs = tokio::task::block_in_place(|| {
+            handle.block_on(WasmScalarUdf::new(
+                &component,
+                &permissions,
+                self.io_rt.clone(),
+                &self.memory_pool,
+                source.to_owned(),
+            ))
+        })?;
+
+        Ok(udfs
+            .into_iter()
+            .map(|udf| {
+                let udf = Arc::new(udf).into_scalar_udf();
+                (udf.name().to_owned(), Arc::new(udf))
+            })
+            .collect())
+    }
+}
+
+impl LogicalExtensionCodec for WasmUdfCodec {
+    fn try_decode(
+        &self,
+        buf: &[u8],
+        inputs: &[LogicalPlan],
+        ctx: &TaskContext,
+    ) -> DataFusionResult<Extension> {
+        self.logical_codec.try_decode(buf, inputs, ctx)
+    }
+
+    fn try_encode(&self, node: &Extension, buf: &mut Vec<u8>) -> DataFusionResult<()> {
+        self.logical_codec.try_encode(node, buf)
+    }
+
+    fn try_decode_table_provider(
+        &self,
+        buf: &[u8],
+        table_ref: &TableReference,
+        schema: SchemaRef,
+        ctx: &TaskContext,
+    ) -> DataFusionResult<Arc<dyn TableProvider>> {
+        self.logical_codec
+            .try_decode_table_provider(buf, table_ref, schema, ctx)
+    }
+
+    fn try_encode_table_provider(
+        &self,
+        table_ref: &TableReference,
+        node: Arc<dyn TableProvider>,
+        buf: &mut Vec<u8>,
+    ) -> DataFusionResult<()> {
+        self.logical_codec
+            .try_encode_table_provider(table_ref, node, buf)
+    }
+
+    fn try_decode_udf(&self, name: &str, buf: &[u8]) -> DataFusionResult<Arc<ScalarUDF>> {
+        match self.decode_udf(buf) {
+            Some(res) => res,
+            None => self.logical_codec.try_decode_udf(name, buf),
+        }
+    }
+
+    fn try_encode_udf(&self, node: &ScalarUDF, buf: &mut Vec<u8>) -> DataFusionResult<()> {
+        if Self::encode_udf(node, buf) {
+            Ok(())
+        } else {
+            self.logical_codec.try_encode_udf(node, buf)
+        }
+    }
+}
+
+impl PhysicalExtensionCodec for WasmUdfCodec {
+    fn try_decode(
+        &self,
+        buf: &[u8],
+        inputs: &[Arc<dyn ExecutionPlan>],
+        ctx: &TaskContext,
+    ) -> DataFusionResult<Arc<dyn ExecutionPlan>> {
+        self.physical_codec.try_decode(buf, inputs, ctx)
+    }
+
+    fn try_encode(&self, node: Arc<dyn ExecutionPlan>, buf: &mut Vec<u8>) -> DataFusionResult<()> {
+        self.physical_codec.try_encode(node, buf)
+    }
+
+    fn try_decode_udf(&self, name: &str, buf: &[u8]) -> DataFusionResult<Arc<ScalarUDF>> {
+        match self.decode_udf(buf) {
+            Some(res) => res,
+            None => self.physical_codec.try_decode_udf(name, buf),
+        }
+    }
+
+    fn try_encode_udf(&self, node: &ScalarUDF, buf: &mut Vec<u8>) -> DataFusionResult<()> {
+        if Self::encode_udf(node, buf) {
+            Ok(())
+        } else {
+            self.physical_codec.try_encode_udf(node, buf)
+        }
+    }
+}
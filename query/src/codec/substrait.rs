@@ -0,0 +1,162 @@
+//! [Substrait] support for WASM UDFs.
+//!
+//!
+//! [Substrait]: https://substrait.io/
+
+use std::{collections::HashMap, fmt::Write};
+
+use datafusion_common::{
+    DataFusionError, Result as DataFusionResult,
+    tree_node::{TreeNode, TreeNodeRecursion},
+};
+use datafusion_expr::{Expr, LogicalPlan, expr::ScalarFunction, registry::FunctionRegistry};
+use datafusion_substrait::substrait::proto::{
+    Plan,
+    extensions::{SimpleExtensionUri, simple_extension_declaration::MappingType},
+};
+use datafusion_udf_wasm_host::WasmScalarUdf;
+
+use super::WasmUdfCodec;
+
+/// URI scheme of Substrait extensions that point to WASM UDFs.
+///
+/// The full URI is `wasm-udf:<component digest>:<source>`, where the [component digest] is 32 hex digits and the
+/// source code is hex-encoded UTF-8.
+///
+///
+/// [component digest]: datafusion_udf_wasm_host::WasmComponentPrecompiled::digest
+pub const WASM_UDF_URI_SCHEME: &str = "wasm-udf";
+
+impl WasmUdfCodec {
+    /// Point the extension functions of a Substrait plan that belong to WASM UDFs to the component registry.
+    ///
+    /// `logical_plan` is the DataFusion plan that `plan` was produced from. Every extension function that refers to a
+    /// WASM UDF in `logical_plan` gets its own extension URI using the [`WASM_UDF_URI_SCHEME`]. Other consumers will
+    /// treat these like any other unknown extension, while [`decode_substrait`](Self::decode_substrait) can use them
+    /// to re-create the UDFs.
+    pub fn encode_substrait(logical_plan: &LogicalPlan, plan: &mut Plan) -> DataFusionResult<()> {
+        let mut uris = HashMap::new();
+        logical_plan.apply_with_subqueries(|node| {
+            node.apply_expressions(|expr| {
+                expr.apply(|e| {
+                    if let Expr::ScalarFunction(ScalarFunction { func, .. }) = e
+                        && let Some(udf) = WasmScalarUdf::from_scalar_udf(func)
+                    {
+                        uris.insert(func.name().to_owned(), uri(&udf));
+                    }
+                    Ok(TreeNodeRecursion::Continue)
+                })
+            })
+        })?;
+        if uris.is_empty() {
+            return Ok(());
+        }
+
+        let mut next_anchor = plan
+            .extension_uris
+            .iter()
+            .map(|u| u.extension_uri_anchor)
+            .max()
+            .map_or(1, |anchor| anchor + 1);
+        for decl in &mut plan.extensions {
+            if let Some(MappingType::ExtensionFunction(f)) = &mut decl.mapping_type
+                && let Some(uri) = uris.get(&f.name)
+            {
+                plan.extension_uris.push(SimpleExtensionUri {
+                    extension_uri_anchor: next_anchor,
+                    uri: uri.clone(),
+                });
+                f.extension_uri_reference = next_anchor;
+                next_anchor += 1;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Re-create the WASM UDFs that a Substrait plan refers to and register them.
+    ///
+    /// Call this before consuming the plan with DataFusion, so that the function names can be resolved. See
+    /// [`encode_substrait`](Self::encode_substrait) for the producer side.
+    pub fn decode_substrait(
+        &self,
+        plan: &Plan,
+        registry: &mut dyn FunctionRegistry,
+    ) -> DataFusionResult<()> {
+        let uris = plan
+            .extension_uris
+            .iter()
+            .map(|u| (u.extension_uri_anchor, u.uri.as_str()))
+            .collect::<HashMap<_, _>>();
+
+        for decl in &plan.extensions {
+            let Some(MappingType::ExtensionFunction(f)) = &decl.mapping_type else {
+                continue;
+            };
+            let Some(uri) = uris.get(&f.extension_uri_reference) else {
+                continue;
+            };
+            let Some((component_digest, source)) = parse_uri(uri)? else {
+                continue;
+            };
+
+            let udf = self.udf(component_digest, None, source.into(), &f.name)?;
+            registry.register_udf(udf)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Create extension URI for given UDF.
+fn uri(udf: &WasmScalarUdf) -> String {
+    let mut uri = format!("{WASM_UDF_URI_SCHEME}:{:032x}:", udf.component_digest());
+    for b in udf.source().bytes() {
+        write!(uri, "{b:02x}").expect("write to string");
+    }
+    uri
+}
+
+/// Parse extension URI.
+///
+/// Returns [`None`] if this is NOT a WASM UDF URI.
+fn parse_uri(uri: &str) -> DataFusionResult<Option<(u128, String)>> {
+    let Some(rest) = uri
+        .strip_prefix(WASM_UDF_URI_SCHEME)
+        .and_then(|rest| rest.strip_prefix(':'))
+    else {
+        return Ok(None);
+    };
+    let invalid = || DataFusionError::Plan(format!("invalid WASM UDF URI: {uri}"));
+
+    let (component_digest, source) = rest.split_once(':').ok_or_else(invalid)?;
+    let component_digest = u128::from_str_radix(component_digest, 16).map_err(|_| invalid())?;
+    if !source.is_ascii() || !source.len().is_multiple_of(2) {
+        return Err(invalid());
+    }
+    let source = (0..source.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&source[i..i + 2], 16))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| invalid())?;
+    let source = String::from_utf8(source).map_err(|_| invalid())?;
+
+    Ok(Some((component_digest, source)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_uri() {
+        assert_eq!(parse_uri("https://example.com").unwrap(), None);
+        assert_eq!(
+            parse_uri("wasm-udf:000000000000000000000000000000ff:6869").unwrap(),
+            Some((255, "hi".to_owned())),
+        );
+        parse_uri("wasm-udf:ff:686").unwrap_err();
+        parse_uri("wasm-udf:ff").unwrap_err();
+        parse_uri("wasm-udf:xx:").unwrap_err();
+    }
+}
@@ -1,4 +1,14 @@
 //! Module for UDF code formatting implementations
+//!
+//! A [`Lang`](crate::Lang) applies a pipeline of formatters in order, so language-agnostic preprocessing -- e.g.
+//! [`FrontMatterFormatter`] and [`TabNormalizationFormatter`] -- can be combined with a language-specific formatter
+//! like [`PythonIndentationFormatter`].
+
+pub use preprocess::{DollarQuoteFormatter, FrontMatterFormatter, TabNormalizationFormatter};
+pub use python::PythonIndentationFormatter;
+
+mod preprocess;
+mod python;
 
 /// Trait for formatting UDF code before compilation allows for
 /// language-specific formatting or preprocessing.
@@ -18,6 +28,9 @@ impl UdfCodeFormatter for NoOpFormatter {
 }
 
 /// Code formatter that strips leading indentation
+///
+/// This treats every line the same, including lines within multi-line string literals. Use
+/// [`PythonIndentationFormatter`] for Python code.
 #[derive(Debug, Default, Clone, Copy)]
 pub struct StripIndentationFormatter;
 
@@ -27,6 +40,13 @@ impl UdfCodeFormatter for StripIndentationFormatter {
     }
 }
 
+/// Apply a pipeline of formatters in order.
+pub(crate) fn format_pipeline(formatters: &[Box<dyn UdfCodeFormatter>], code: String) -> String {
+    formatters
+        .iter()
+        .fold(code, |code, formatter| formatter.format(code))
+}
+
 /// Strips common leading indentation from all non-empty lines in the code string.
 fn strip_indentation(code: &str) -> String {
     let indent = code
@@ -0,0 +1,166 @@
+//! Language-agnostic preprocessing.
+
+use super::UdfCodeFormatter;
+
+/// Removes dollar quotes that enclose the entire code, e.g. `$$ ... $$` or `$py$ ... $py$`.
+///
+/// Dollar-quoted function bodies are supported natively, but some clients cannot send them and wrap them in a
+/// regular string literal instead, i.e. `AS '$$ ... $$'`. Code that is not enclosed in matching dollar quotes is
+/// returned unchanged.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DollarQuoteFormatter;
+
+impl UdfCodeFormatter for DollarQuoteFormatter {
+    fn format(&self, code: String) -> String {
+        match strip_dollar_quotes(&code) {
+            Some(inner) => inner.to_owned(),
+            None => code,
+        }
+    }
+}
+
+/// Returns the code within the enclosing dollar quotes, if there are any.
+fn strip_dollar_quotes(code: &str) -> Option<&str> {
+    let trimmed = code.trim();
+    let rest = trimmed.strip_prefix('$')?;
+    let tag_len = rest.find('$')?;
+    let tag = &rest[..tag_len];
+    if !tag.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        return None;
+    }
+
+    let delimiter = &trimmed[..tag_len + 2];
+    let inner = trimmed.strip_prefix(delimiter)?.strip_suffix(delimiter)?;
+    (!inner.contains(delimiter)).then_some(inner)
+}
+
+/// Replaces tabs in the leading indentation of every line with spaces.
+///
+/// Mixing tabs and spaces -- e.g. when code is pasted from different editors -- otherwise confuses indentation-aware
+/// formatters like [`PythonIndentationFormatter`](super::PythonIndentationFormatter) and indentation-sensitive
+/// languages. Tabs after the first non-whitespace character are kept.
+#[derive(Debug, Clone, Copy)]
+pub struct TabNormalizationFormatter {
+    /// Tabs advance to the next multiple of this width. Defaults to 8, like the Python tokenizer.
+    pub tab_width: usize,
+}
+
+impl Default for TabNormalizationFormatter {
+    fn default() -> Self {
+        Self { tab_width: 8 }
+    }
+}
+
+impl UdfCodeFormatter for TabNormalizationFormatter {
+    fn format(&self, code: String) -> String {
+        let tab_width = self.tab_width.max(1);
+        let mut out = String::with_capacity(code.len());
+
+        for line in code.split_inclusive('\n') {
+            let mut column = 0;
+            let mut chars = line.char_indices();
+            let rest = loop {
+                match chars.next() {
+                    Some((_, '\t')) => {
+                        let next = (column / tab_width + 1) * tab_width;
+                        out.extend(std::iter::repeat_n(' ', next - column));
+                        column = next;
+                    }
+                    Some((_, ' ')) => {
+                        out.push(' ');
+                        column += 1;
+                    }
+                    Some((idx, _)) => break &line[idx..],
+                    None => break "",
+                }
+            };
+            out.push_str(rest);
+        }
+
+        out
+    }
+}
+
+/// Removes a leading shebang line (`#!...`) and a leading front-matter block delimited by `---` lines.
+///
+/// Both are blanked out instead of removed, so line numbers in error messages of the guest still match the submitted
+/// code.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FrontMatterFormatter;
+
+impl UdfCodeFormatter for FrontMatterFormatter {
+    fn format(&self, code: String) -> String {
+        let mut lines = code.split_inclusive('\n').collect::<Vec<_>>();
+        let mut first = lines
+            .iter()
+            .position(|l| !l.trim().is_empty())
+            .unwrap_or(lines.len());
+        let mut blank = 0..0;
+
+        if lines
+            .get(first)
+            .is_some_and(|l| l.trim_start().starts_with("#!"))
+        {
+            blank = first..first + 1;
+            first += 1;
+        }
+
+        if lines.get(first).is_some_and(|l| l.trim() == "---")
+            && let Some(end) = lines[first + 1..].iter().position(|l| l.trim() == "---")
+        {
+            let start = if blank.is_empty() { first } else { blank.start };
+            blank = start..first + 1 + end + 1;
+        }
+
+        if blank.is_empty() {
+            return code;
+        }
+        for line in &mut lines[blank] {
+            *line = if line.ends_with('\n') { "\n" } else { "" };
+        }
+        lines.concat()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dollar_quotes() {
+        let f = DollarQuoteFormatter;
+        assert_eq!(f.format("$$x = 1$$".to_owned()), "x = 1");
+        assert_eq!(
+            f.format("\n  $py$\nx = '$'\n$py$\n".to_owned()),
+            "\nx = '$'\n"
+        );
+        assert_eq!(f.format("$$x$$ + $$y$$".to_owned()), "$$x$$ + $$y$$");
+        assert_eq!(f.format("$a$x$b$".to_owned()), "$a$x$b$");
+        assert_eq!(f.format("$ x $".to_owned()), "$ x $");
+        assert_eq!(f.format("x = 1".to_owned()), "x = 1");
+    }
+
+    #[test]
+    fn test_tab_normalization() {
+        let f = TabNormalizationFormatter::default();
+        assert_eq!(
+            f.format("\tx\n  \ty = '\t'\n\n".to_owned()),
+            "        x\n        y = '\t'\n\n",
+        );
+        let f = TabNormalizationFormatter { tab_width: 4 };
+        assert_eq!(f.format(" \t\tx".to_owned()), "        x");
+    }
+
+    #[test]
+    fn test_front_matter() {
+        let f = FrontMatterFormatter;
+        assert_eq!(
+            f.format("\n#!/usr/bin/env python\n---\nauthor: me\n---\nx = 1\n".to_owned()),
+            "\n\n\n\n\nx = 1\n",
+        );
+        assert_eq!(f.format("---\na: b\n---\nx".to_owned()), "\n\n\nx");
+        assert_eq!(f.format("#!python".to_owned()), "");
+        assert_eq!(f.format("---\nx = 1\n".to_owned()), "---\nx = 1\n");
+        assert_eq!(f.format("x = 1\n#!no\n".to_owned()), "x = 1\n#!no\n");
+    }
+}
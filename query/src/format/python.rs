@@ -0,0 +1,137 @@
+//! Python-specific formatting.
+
+use super::UdfCodeFormatter;
+
+/// Strips common leading indentation from Python code.
+///
+/// Unlike [`StripIndentationFormatter`](super::StripIndentationFormatter), lines that continue a triple-quoted string
+/// (`'''` or `"""`) are neither considered for the common indentation nor modified, so the content of multi-line
+/// strings -- e.g. docstrings or embedded templates -- is preserved.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct PythonIndentationFormatter;
+
+impl UdfCodeFormatter for PythonIndentationFormatter {
+    fn format(&self, code: String) -> String {
+        let lines = code
+            .lines()
+            .zip(continues_string(&code))
+            .collect::<Vec<_>>();
+
+        let indent = lines
+            .iter()
+            .filter(|(l, in_string)| !in_string && !l.trim().is_empty())
+            .map(|(l, _)| l.chars().take_while(|s| s.is_ascii_whitespace()).count())
+            .min()
+            .unwrap_or_default();
+
+        let mut out = String::with_capacity(code.len());
+        for (line, in_string) in lines {
+            if in_string {
+                out.push_str(line);
+            } else {
+                out.extend(line.chars().skip(indent));
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+/// For every line of the code, determine if it starts within a triple-quoted string.
+fn continues_string(code: &str) -> Vec<bool> {
+    let mut result = vec![];
+    let mut triple: Option<char> = None;
+
+    for line in code.lines() {
+        result.push(triple.is_some());
+
+        let chars = line.chars().collect::<Vec<_>>();
+        let is_triple = |i: usize, q: char| chars[i..].starts_with(&[q, q, q]);
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            match triple {
+                Some(q) => {
+                    if c == '\\' {
+                        i += 2;
+                    } else if is_triple(i, q) {
+                        triple = None;
+                        i += 3;
+                    } else {
+                        i += 1;
+                    }
+                }
+                None => match c {
+                    '#' => break,
+                    '\'' | '"' if is_triple(i, c) => {
+                        triple = Some(c);
+                        i += 3;
+                    }
+                    '\'' | '"' => {
+                        // single-line string, skip to the closing quote
+                        i += 1;
+                        while i < chars.len() {
+                            if chars[i] == '\\' {
+                                i += 2;
+                            } else if chars[i] == c {
+                                i += 1;
+                                break;
+                            } else {
+                                i += 1;
+                            }
+                        }
+                    }
+                    _ => {
+                        i += 1;
+                    }
+                },
+            }
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_triple_quoted_strings() {
+        let code = [
+            "    def f() -> str:",
+            "        '''",
+            "Docstring, not indented.",
+            "            Indented. \\''' still in string",
+            "        '''",
+            "        s = \"'''\"  # not a string start: '''",
+            "        return \"\"\"a",
+            "  b\"\"\"",
+        ]
+        .join("\n");
+
+        assert_eq!(
+            PythonIndentationFormatter.format(code),
+            [
+                "def f() -> str:",
+                "    '''",
+                "Docstring, not indented.",
+                "            Indented. \\''' still in string",
+                "    '''",
+                "    s = \"'''\"  # not a string start: '''",
+                "    return \"\"\"a",
+                "  b\"\"\"",
+                "",
+            ]
+            .join("\n"),
+        );
+    }
+
+    #[test]
+    fn test_no_strings() {
+        assert_eq!(
+            PythonIndentationFormatter.format("\n  x = 1\n\n    y = 2".to_owned()),
+            "\nx = 1\n\n  y = 2\n",
+        );
+    }
+}
@@ -2,22 +2,39 @@
 #![allow(unused_crate_dependencies)]
 
 use std::collections::HashMap;
+use std::ops::Range;
 use std::pin::Pin;
+use std::sync::{Arc, Mutex};
 
 use datafusion_common::{DataFusionError, Result as DataFusionResult};
 use datafusion_execution::TaskContext;
+use datafusion_expr::{ScalarUDFImpl, registry::FunctionRegistry};
 use datafusion_sql::parser::{DFParserBuilder, Statement};
-use sqlparser::ast::{CreateFunctionBody, Expr, Statement as SqlStatement, Value};
-use sqlparser::dialect::dialect_from_str;
-
-use datafusion_udf_wasm_host::{WasmComponentPrecompiled, WasmPermissions, WasmScalarUdf};
+use sqlparser::ast::{CreateFunctionBody, Expr, ObjectName, Statement as SqlStatement, Value};
+use sqlparser::dialect::{Dialect, dialect_from_str};
+use sqlparser::tokenizer::{Location, Token, Tokenizer};
+
+use datafusion_udf_wasm_host::{
+    SourceDiagnostic, WasmComponentPrecompiled, WasmPermissions, WasmScalarUdf,
+    WasmSourceValidator, WasmUdfConfig,
+};
 use tokio::runtime::Handle;
 
+use crate::cache::{UdfCache, UdfCacheKey, UdfCacheLimits};
 use crate::format::UdfCodeFormatter;
 
+/// Caching of UDFs across queries
+pub mod cache;
+
+/// Serialization of WASM UDFs within plans
+pub mod codec;
+
 /// Module for UDF code formatting implementations
 pub mod format;
 
+/// Optional planner rules for WASM UDFs
+pub mod optimizer;
+
 /// Inner type of [`ComponentFn`].
 ///
 /// This is deliberately NOT exposed directly to the user because:
@@ -89,25 +106,120 @@ impl<'a> std::fmt::Debug for ComponentFn<'a> {
 pub struct Lang<'a> {
     /// Pre-compiled WASM component for the language
     pub component: ComponentFn<'a>,
-    /// Code formatter for the language
-    pub formatter: Box<dyn UdfCodeFormatter>,
+    /// Code formatters for the language, applied in order
+    ///
+    /// See [`format`] for available formatters. Leave empty to use the code unchanged.
+    pub formatters: Vec<Box<dyn UdfCodeFormatter>>,
 }
 
 /// A [ParsedQuery] contains the extracted UDFs and SQL query string
 #[derive(Debug)]
 pub struct ParsedQuery {
     /// Extracted UDFs from the query
-    pub udfs: Vec<WasmScalarUdf>,
+    ///
+    /// These may be shared with other queries, see [`UdfQueryParser::with_cache`].
+    pub udfs: Vec<Arc<WasmScalarUdf>>,
+    /// UDF lifecycle statements, in query order
+    ///
+    /// Use [`register`](Self::register) to apply them to a [`FunctionRegistry`].
+    pub statements: Vec<UdfStatement>,
     /// SQL query string with UDF definitions removed
+    ///
+    /// The remaining statements are taken verbatim from the input, so comments, parameter placeholders (e.g. `$1`),
+    /// and dialect-specific syntax are preserved. This is empty if the query only manages UDFs.
     pub sql: String,
 }
 
+impl ParsedQuery {
+    /// Apply [statements](Self::statements) to the given registry, in query order.
+    ///
+    /// `CREATE FUNCTION` fails if a function with the same name already exists, `CREATE OR REPLACE FUNCTION` replaces
+    /// it. `DROP FUNCTION` fails if the function does not exist, unless `IF EXISTS` is used.
+    ///
+    /// Returns the remaining [SQL](Self::sql).
+    pub fn register(self, registry: &mut dyn FunctionRegistry) -> DataFusionResult<String> {
+        let Self {
+            udfs,
+            statements,
+            sql,
+        } = self;
+        let mut udfs = udfs
+            .into_iter()
+            .map(|udf| (udf.name().to_owned(), udf))
+            .collect::<HashMap<_, _>>();
+
+        for statement in statements {
+            match statement {
+                UdfStatement::Create {
+                    declared_name: _,
+                    names,
+                    or_replace,
+                } => {
+                    for name in names {
+                        if !or_replace && registry.udfs().contains(&name) {
+                            return Err(DataFusionError::Plan(format!(
+                                "function already exists: {name}"
+                            )));
+                        }
+                        let udf = udfs.remove(&name).ok_or_else(|| {
+                            DataFusionError::Internal(format!("unknown UDF: {name}"))
+                        })?;
+                        registry.register_udf(Arc::new(udf.into_scalar_udf()))?;
+                    }
+                }
+                UdfStatement::Drop { names, if_exists } => {
+                    for name in names {
+                        if registry.deregister_udf(&name)?.is_none() && !if_exists {
+                            return Err(DataFusionError::Plan(format!(
+                                "function does not exist: {name}"
+                            )));
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(sql)
+    }
+}
+
+/// A UDF lifecycle statement within a [ParsedQuery]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UdfStatement {
+    /// `CREATE [OR REPLACE] FUNCTION`
+    Create {
+        /// Function name that the statement declared
+        ///
+        /// This is always one of the [`names`](UdfStatement::Create::names).
+        declared_name: String,
+        /// Names of the UDFs that the function body defined, see [`ParsedQuery::udfs`]
+        names: Vec<String>,
+        /// Replace existing functions with the same names
+        or_replace: bool,
+    },
+    /// `DROP FUNCTION [IF EXISTS]`
+    Drop {
+        /// Names of the functions to remove
+        names: Vec<String>,
+        /// Do not fail if a function does not exist
+        if_exists: bool,
+    },
+}
+
 /// Handles the registration and invocation of UDF queries in DataFusion with a
 /// pre-compiled WASM component.
 pub struct UdfQueryParser<'a> {
-    /// Map of strings (eg "python") to supported UDF languages and their WASM
-    /// components
+    /// Map of lower-case strings (eg "python") to supported UDF languages and
+    /// their WASM components
     components: HashMap<String, Lang<'a>>,
+    /// Map of lower-case language aliases (eg "py") to keys of `components`,
+    /// see [`with_language_alias`](Self::with_language_alias)
+    aliases: HashMap<String, String>,
+    /// Cache of already created UDFs, see [`with_cache`](Self::with_cache)
+    cache: Option<UdfCache>,
+    /// Source validators by language and permissions fingerprint, see
+    /// [`validate_source`](Self::validate_source)
+    validators: Mutex<HashMap<(String, u64), Arc<WasmSourceValidator>>>,
 }
 
 impl std::fmt::Debug for UdfQueryParser<'_> {
@@ -115,17 +227,133 @@ impl std::fmt::Debug for UdfQueryParser<'_> {
         f.debug_struct("UdfQueryParser")
             .field("session_ctx", &"SessionContext { ... }")
             .field("components", &self.components)
+            .field("aliases", &self.aliases)
+            .field("cache", &self.cache)
+            .field("validators", &self.validators)
             .finish()
     }
 }
 
 impl<'a> UdfQueryParser<'a> {
     /// Registers the UDF query in DataFusion.
+    ///
+    /// Language names are matched case-insensitively, e.g. `LANGUAGE PYTHON` resolves to a component registered as
+    /// `python`.
     pub fn new(components: HashMap<String, Lang<'a>>) -> Self {
-        Self { components }
+        Self {
+            components: components
+                .into_iter()
+                .map(|(name, lang)| (name.to_lowercase(), lang))
+                .collect(),
+            aliases: HashMap::new(),
+            cache: None,
+            validators: Mutex::default(),
+        }
+    }
+
+    /// Register an alternative name for a language, e.g. `py` or `python3` for `python`.
+    ///
+    /// Aliases are matched case-insensitively, like language names. Registering an alias that is also a language
+    /// name has no effect, the language takes precedence.
+    pub fn with_language_alias(
+        mut self,
+        alias: impl Into<String>,
+        language: impl Into<String>,
+    ) -> Self {
+        self.aliases
+            .insert(alias.into().to_lowercase(), language.into().to_lowercase());
+        self
+    }
+
+    /// Look up language by name or alias.
+    ///
+    /// Returns the canonical language name along with the language.
+    fn lang(&self, name: &str) -> DataFusionResult<(&str, &Lang<'a>)> {
+        let name = name.to_lowercase();
+        let name = if self.components.contains_key(&name) {
+            &name
+        } else {
+            self.aliases.get(&name).unwrap_or(&name)
+        };
+
+        match self.components.get_key_value(name) {
+            Some((name, lang)) => Ok((name, lang)),
+            None => {
+                let mut registered = self
+                    .components
+                    .keys()
+                    .chain(self.aliases.keys())
+                    .map(|name| name.as_str())
+                    .collect::<Vec<_>>();
+                registered.sort_unstable();
+                Err(DataFusionError::Plan(format!(
+                    "no WASM component registered for language: {name:?}, registered languages: {}",
+                    registered.join(", ")
+                )))
+            }
+        }
+    }
+
+    /// Reuse UDFs across queries.
+    ///
+    /// Queries that define the same UDF code -- e.g. the same dashboard that is refreshed periodically -- then share
+    /// UDFs instead of creating new WASM VMs for every query. UDFs are cached by language, component, the
+    /// [fingerprint](WasmPermissions::fingerprint) of the permissions, and the [formatted](UdfCodeFormatter) code.
+    ///
+    /// Shared UDFs also share their state, e.g. global variables. They keep the I/O runtime and the memory pool of
+    /// the query that created them. A [poisoned](datafusion_udf_wasm_host::WasmUdfError::Poisoned) UDF stays in the
+    /// cache until it expires, consider [recycling](WasmPermissions::with_max_recycles).
+    pub fn with_cache(self, limits: UdfCacheLimits) -> Self {
+        Self {
+            cache: Some(UdfCache::new(limits)),
+            ..self
+        }
+    }
+
+    /// Checks UDF code for syntax errors without creating UDFs.
+    ///
+    /// This is much cheaper than [`parse`](Self::parse), e.g. to check code while a user types it. The code is
+    /// formatted like in [`parse`](Self::parse), so line numbers refer to the formatted code. One
+    /// [validator](WasmSourceValidator) is kept per language and permissions and reused by later calls. It keeps the
+    /// I/O runtime and the memory pool of the call that created it.
+    pub async fn validate_source(
+        &self,
+        lang: &str,
+        code: String,
+        permissions: &WasmPermissions,
+        io_rt: Handle,
+        task_ctx: &TaskContext,
+    ) -> DataFusionResult<Vec<SourceDiagnostic>> {
+        let (lang_name, lang) = self.lang(lang)?;
+        let code = format::format_pipeline(&lang.formatters, code);
+
+        let key = (lang_name.to_owned(), permissions.fingerprint());
+        let existing = self
+            .validators
+            .lock()
+            .expect("not poisoned")
+            .get(&key)
+            .map(Arc::clone);
+        let validator = match existing {
+            Some(validator) => validator,
+            None => {
+                let validator = Arc::new(WasmSourceValidator::new(
+                    lang.component.get().await,
+                    permissions,
+                    io_rt,
+                    task_ctx.memory_pool(),
+                ));
+                let mut validators = self.validators.lock().expect("not poisoned");
+                Arc::clone(validators.entry(key).or_insert(validator))
+            }
+        };
+
+        validator.validate(&code).await
     }
 
     /// Parses a SQL query that defines & uses UDFs into a [ParsedQuery].
+    ///
+    /// Defining UDFs can be disabled per session via [`WasmUdfConfig::enabled`].
     pub async fn parse(
         &self,
         udf_query: &str,
@@ -133,41 +361,94 @@ impl<'a> UdfQueryParser<'a> {
         io_rt: Handle,
         task_ctx: &TaskContext,
     ) -> DataFusionResult<ParsedQuery> {
-        let (code, sql) = Self::parse_inner(udf_query, task_ctx)?;
+        let (definitions, sql) = Self::parse_inner(udf_query, task_ctx)?;
+
+        let enabled = task_ctx
+            .session_config()
+            .options()
+            .extensions
+            .get::<WasmUdfConfig>()
+            .is_none_or(|config| config.enabled);
+        if !enabled && !definitions.is_empty() {
+            return Err(DataFusionError::Plan(
+                "WASM UDFs are disabled, see `udf_wasm.enabled`".to_string(),
+            ));
+        }
 
         let mut udfs = vec![];
-        for (lang, blocks) in code {
-            let lang = self.components.get(&lang).ok_or_else(|| {
-                DataFusionError::Plan(format!(
-                    "no WASM component registered for language: {:?}",
-                    lang
-                ))
-            })?;
-
-            for code in blocks {
-                let code = lang.formatter.format(code);
-                udfs.extend(
-                    WasmScalarUdf::new(
-                        lang.component.get().await,
-                        permissions,
-                        io_rt.clone(),
-                        task_ctx.memory_pool(),
-                        code,
-                    )
-                    .await?,
-                );
+        let mut statements = vec![];
+        for definition in definitions {
+            let (declared_name, lang_name, code, or_replace) = match definition {
+                Parsed::Udf {
+                    name,
+                    code,
+                    language,
+                    or_replace,
+                } => (name, language, code, or_replace),
+                Parsed::Drop { names, if_exists } => {
+                    statements.push(UdfStatement::Drop { names, if_exists });
+                    continue;
+                }
+                // filtered out by `parse_inner`
+                Parsed::Other => {
+                    continue;
+                }
+            };
+
+            let (lang_name, lang) = self.lang(&lang_name)?;
+
+            let code = format::format_pipeline(&lang.formatters, code);
+            let component = lang.component.get().await;
+            let create = async |code: String| {
+                WasmScalarUdf::new(
+                    component,
+                    permissions,
+                    io_rt.clone(),
+                    task_ctx.memory_pool(),
+                    code,
+                )
+                .await
+            };
+
+            let created = match &self.cache {
+                Some(cache) => {
+                    let key = UdfCacheKey {
+                        lang: lang_name.to_owned(),
+                        component_digest: component.digest(),
+                        permissions_fingerprint: permissions.fingerprint(),
+                        code: code.clone(),
+                    };
+                    cache.get_or_create(key, || create(code)).await?
+                }
+                None => create(code).await?.into_iter().map(Arc::new).collect(),
+            };
+            let names = created
+                .iter()
+                .map(|udf| udf.name().to_owned())
+                .collect::<Vec<_>>();
+            if !names.contains(&declared_name) {
+                return Err(DataFusionError::Plan(format!(
+                    "function body does not define declared function '{declared_name}', found: {names:?}"
+                )));
             }
+            statements.push(UdfStatement::Create {
+                declared_name,
+                names,
+                or_replace,
+            });
+            udfs.extend(created);
         }
 
-        Ok(ParsedQuery { udfs, sql })
+        Ok(ParsedQuery {
+            udfs,
+            statements,
+            sql,
+        })
     }
 
-    /// Parse the combined query to extract the chosen UDF language, UDF
-    /// definitions, and SQL statements.
-    fn parse_inner(
-        query: &str,
-        task_ctx: &TaskContext,
-    ) -> DataFusionResult<(HashMap<String, Vec<String>>, String)> {
+    /// Parse the combined query to extract the UDF definitions -- in query
+    /// order -- and SQL statements.
+    fn parse_inner(query: &str, task_ctx: &TaskContext) -> DataFusionResult<(Vec<Parsed>, String)> {
         let options = task_ctx.session_config().options();
 
         let dialect = dialect_from_str(options.sql_parser.dialect).expect("valid dialect");
@@ -178,30 +459,34 @@ impl<'a> UdfQueryParser<'a> {
             .with_recursion_limit(recursion_limit)
             .build()?
             .parse_statements()?;
+        let spans = statement_spans(query, dialect.as_ref())?;
+        if spans.len() != statements.len() {
+            return Err(DataFusionError::Internal(format!(
+                "found {} statements but {} statement spans",
+                statements.len(),
+                spans.len()
+            )));
+        }
 
         let mut sql = String::new();
-        let mut udf_blocks: HashMap<String, Vec<String>> = HashMap::new();
-        for s in statements {
+        let mut definitions = vec![];
+        for (s, span) in statements.into_iter().zip(spans) {
             match parse_udf(s)? {
-                Parsed::Udf { code, language } => {
-                    if let Some(existing) = udf_blocks.get_mut(&language) {
-                        existing.push(code);
-                    } else {
-                        udf_blocks.insert(language.clone(), vec![code]);
-                    }
-                }
-                Parsed::Other(statement) => {
-                    sql.push_str(&statement);
+                Parsed::Other => {
+                    sql.push_str(&query[span]);
                     sql.push_str(";\n");
                 }
+                definition => {
+                    definitions.push(definition);
+                }
             }
         }
 
-        if sql.is_empty() {
+        if sql.is_empty() && definitions.is_empty() {
             return Err(DataFusionError::Plan("no SQL query found".to_string()));
         }
 
-        Ok((udf_blocks, sql))
+        Ok((definitions, sql))
     }
 }
 
@@ -209,13 +494,24 @@ impl<'a> UdfQueryParser<'a> {
 enum Parsed {
     /// A UDF definition
     Udf {
+        /// Declared function name
+        name: String,
         /// UDF code
         code: String,
         /// UDF language
         language: String,
+        /// `CREATE OR REPLACE`
+        or_replace: bool,
+    },
+    /// Removal of UDFs
+    Drop {
+        /// Function names
+        names: Vec<String>,
+        /// `DROP FUNCTION IF EXISTS`
+        if_exists: bool,
     },
     /// Any other SQL statement
-    Other(String),
+    Other,
 }
 
 /// Parse a single SQL statement to extract a UDF
@@ -241,16 +537,91 @@ fn parse_udf(stmt: Statement) -> DataFusionResult<Parsed> {
                 }?;
 
                 Ok(Parsed::Udf {
+                    name: function_name(&cf.name)?,
                     code: code.to_string(),
                     language,
+                    or_replace: cf.or_replace,
                 })
             }
-            _ => Ok(Parsed::Other(stmt.to_string())),
+            SqlStatement::DropFunction {
+                if_exists,
+                func_desc,
+                ..
+            } => Ok(Parsed::Drop {
+                names: func_desc
+                    .iter()
+                    .map(|desc| function_name(&desc.name))
+                    .collect::<DataFusionResult<_>>()?,
+                if_exists,
+            }),
+            _ => Ok(Parsed::Other),
         },
-        _ => Ok(Parsed::Other(stmt.to_string())),
+        _ => Ok(Parsed::Other),
     }
 }
 
+/// Normalize a SQL function name to the name that the UDF is registered under.
+///
+/// Only the last part of a qualified name (e.g. `schema.func`) is used. Unquoted identifiers are lowercased, quoted
+/// identifiers are kept as-is. This matches how DataFusion resolves function calls.
+fn function_name(name: &ObjectName) -> DataFusionResult<String> {
+    let ident = name
+        .0
+        .last()
+        .and_then(|part| part.as_ident())
+        .ok_or_else(|| DataFusionError::Plan(format!("invalid function name: {name}")))?;
+    Ok(match ident.quote_style {
+        Some(_) => ident.value.clone(),
+        None => ident.value.to_ascii_lowercase(),
+    })
+}
+
+/// Byte ranges of all statements within the query.
+///
+/// Statements are separated by semicolons. The ranges exclude the separators as well as surrounding whitespace and
+/// comments, but include comments within the statement.
+fn statement_spans(query: &str, dialect: &dyn Dialect) -> DataFusionResult<Vec<Range<usize>>> {
+    let tokens = Tokenizer::new(dialect, query)
+        .tokenize_with_location()
+        .map_err(|e| DataFusionError::Plan(format!("cannot tokenize query: {e}")))?;
+
+    let mut spans = vec![];
+    let mut current: Option<(Location, Location)> = None;
+    for token in tokens {
+        match token.token {
+            Token::SemiColon => {
+                spans.extend(current.take());
+            }
+            Token::Whitespace(_) | Token::EOF => {}
+            _ => {
+                let start = current.map_or(token.span.start, |(start, _end)| start);
+                current = Some((start, token.span.end));
+            }
+        }
+    }
+    spans.extend(current);
+
+    Ok(spans
+        .into_iter()
+        .map(|(start, end)| byte_offset(query, start)..byte_offset(query, end))
+        .collect())
+}
+
+/// Convert tokenizer location -- 1-based line and character column -- into byte offset.
+fn byte_offset(query: &str, location: Location) -> usize {
+    let line_start = query
+        .split_inclusive('\n')
+        .take((location.line as usize).saturating_sub(1))
+        .map(str::len)
+        .sum::<usize>();
+    let column = query[line_start..]
+        .chars()
+        .take((location.column as usize).saturating_sub(1))
+        .map(char::len_utf8)
+        .sum::<usize>();
+    line_start + column
+}
+
 /// Extracts the code from the function body, adding it to `code`.
 fn extract_function_body(body: &CreateFunctionBody) -> DataFusionResult<&str> {
     match body {
@@ -271,6 +642,7 @@ fn expression_into_str(expr: &Expr) -> DataFusionResult<&str> {
     match expr {
         Expr::Value(v) => match &v.value {
             Value::SingleQuotedString(s) | Value::DoubleQuotedString(s) => Ok(s),
+            Value::DollarQuotedString(s) => Ok(&s.value),
             _ => Err(DataFusionError::Plan("expected string value".to_string())),
         },
         _ => Err(DataFusionError::Plan(
@@ -292,4 +664,74 @@ mod test {
     const _: () = assert_sync::<Lang<'static>>();
     const _: () = assert_send::<UdfQueryParser<'static>>();
     const _: () = assert_sync::<UdfQueryParser<'static>>();
+
+    #[test]
+    fn test_statement_spans() {
+        let query = "
+-- leading comment
+SELECT 1;
+CREATE FUNCTION f() LANGUAGE python AS 'x = 1; y = 2';
+
+SELECT /* inline */ $1, 'ä';  ;
+SELECT 2
+";
+
+        let spans = statement_spans(query, &sqlparser::dialect::GenericDialect {}).unwrap();
+        let statements = spans
+            .into_iter()
+            .map(|span| &query[span])
+            .collect::<Vec<_>>();
+        assert_eq!(
+            statements,
+            [
+                "SELECT 1",
+                "CREATE FUNCTION f() LANGUAGE python AS 'x = 1; y = 2'",
+                "SELECT /* inline */ $1, 'ä'",
+                "SELECT 2",
+            ],
+        );
+    }
+
+    /// Parse a single statement with the generic dialect.
+    fn parse(sql: &str) -> Parsed {
+        let mut statements = DFParserBuilder::new(sql)
+            .build()
+            .unwrap()
+            .parse_statements()
+            .unwrap();
+        assert_eq!(statements.len(), 1);
+        parse_udf(statements.pop_front().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_drop_function_names() {
+        let Parsed::Drop { names, if_exists } =
+            parse(r#"DROP FUNCTION IF EXISTS Foo, "Bar", my_schema.baz, my_schema."Qux""#)
+        else {
+            panic!("expected DROP");
+        };
+        assert!(if_exists);
+        assert_eq!(names, ["foo", "Bar", "baz", "Qux"]);
+    }
+
+    #[test]
+    fn test_create_function_name() {
+        for (sql, expected) in [
+            ("CREATE FUNCTION Foo() LANGUAGE python AS ''", "foo"),
+            (r#"CREATE FUNCTION "Foo"() LANGUAGE python AS ''"#, "Foo"),
+            (
+                "CREATE FUNCTION my_schema.foo() LANGUAGE python AS ''",
+                "foo",
+            ),
+            (
+                r#"CREATE FUNCTION my_schema."Foo"() LANGUAGE python AS ''"#,
+                "Foo",
+            ),
+        ] {
+            let Parsed::Udf { name, .. } = parse(sql) else {
+                panic!("expected CREATE: {sql}");
+            };
+            assert_eq!(name, expected, "{sql}");
+        }
+    }
 }
@@ -0,0 +1,540 @@
+//! Evaluate WASM UDFs that feed into aggregates as part of the aggregation input.
+
+use std::{any::Any, sync::Arc};
+
+use datafusion_common::{
+    Column, Result as DataFusionResult,
+    arrow::{
+        array::{RecordBatch, RecordBatchOptions},
+        datatypes::SchemaRef,
+    },
+    config::ConfigOptions,
+    internal_datafusion_err,
+    tree_node::{Transformed, TreeNode, TreeNodeRecursion},
+};
+use datafusion_execution::{SendableRecordBatchStream, TaskContext};
+use datafusion_expr::{
+    Aggregate, Expr, LogicalPlan, Projection, Volatility,
+    expr::{AggregateFunction, ScalarFunction},
+    expr_rewriter::NamePreserver,
+};
+use datafusion_optimizer::{OptimizerConfig, OptimizerRule, optimizer::ApplyOrder};
+use datafusion_physical_expr::{
+    async_scalar_function::AsyncFuncExpr, expressions::Column as PhysicalColumn,
+    projection::ProjectionExpr,
+};
+use datafusion_physical_optimizer::PhysicalOptimizerRule;
+use datafusion_physical_plan::{
+    DisplayAs, DisplayFormatType, ExecutionPlan, PlanProperties, aggregates::AggregateExec,
+    async_func::AsyncFuncExec, coalesce::LimitedBatchCoalescer,
+    coalesce_batches::CoalesceBatchesExec, projection::ProjectionExec,
+    stream::RecordBatchStreamAdapter,
+};
+use futures_util::{StreamExt, stream::try_unfold};
+
+use super::is_async_udf_call;
+
+/// [`OptimizerRule`] that moves WASM UDF calls out of aggregate arguments into a projection right below the
+/// aggregate.
+///
+/// Given a query like:
+///
+/// ```sql
+/// SELECT array_agg(my_udf(x)), count(DISTINCT my_udf(x))
+/// FROM t
+/// GROUP BY y;
+/// ```
+///
+/// the UDF is evaluated exactly once per input batch -- the chunk that the aggregate accumulates next -- and the
+/// resulting array is shared by all aggregate expressions that use the same call. Without this rule, every aggregate
+/// argument would be materialized on its own and async UDFs may not be usable within aggregates at all.
+///
+/// Calls within `CASE` are left in place, since moving them below the aggregate would evaluate them for rows that the
+/// condition excludes. The `FILTER` and `ORDER BY` clauses of aggregate functions are left alone as well, since they
+/// are not part of the aggregated values.
+///
+/// Only [immutable](Volatility::Immutable) calls are shared. All other calls are evaluated once per occurrence, since
+/// they may produce different results for the same input.
+///
+/// The output schema of the aggregate is preserved. Combine this rule with [`FuseWasmUdfAggregateInput`] to evaluate
+/// the UDFs on the chunks that the aggregate accumulates without coalescing and projecting the input separately.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct PushWasmUdfBelowAggregate;
+
+impl PushWasmUdfBelowAggregate {
+    /// Create new rule.
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl OptimizerRule for PushWasmUdfBelowAggregate {
+    fn name(&self) -> &str {
+        "push_wasm_udf_below_aggregate"
+    }
+
+    fn apply_order(&self) -> Option<ApplyOrder> {
+        Some(ApplyOrder::TopDown)
+    }
+
+    fn rewrite(
+        &self,
+        plan: LogicalPlan,
+        config: &dyn OptimizerConfig,
+    ) -> DataFusionResult<Transformed<LogicalPlan>> {
+        let LogicalPlan::Aggregate(aggregate) = plan else {
+            return Ok(Transformed::no(plan));
+        };
+
+        // Collect calls, outermost first. Only immutable calls are shared, every other call may produce a different
+        // result for the same input and is evaluated on its own.
+        let mut calls: Vec<Expr> = vec![];
+        let mut occurrences: Vec<usize> = vec![];
+        for expr in &aggregate.aggr_expr {
+            apply_input_calls(expr, &mut |e| {
+                let shared = is_immutable_call(e)
+                    .then(|| calls.iter().position(|call| call == e))
+                    .flatten();
+                let idx = shared.unwrap_or_else(|| {
+                    calls.push(e.clone());
+                    calls.len() - 1
+                });
+                occurrences.push(idx);
+            })?;
+        }
+        if calls.is_empty() {
+            return Ok(Transformed::no(LogicalPlan::Aggregate(aggregate)));
+        }
+
+        let Aggregate {
+            input,
+            group_expr,
+            aggr_expr,
+            ..
+        } = aggregate;
+
+        let aliases = calls
+            .iter()
+            .map(|_| config.alias_generator().next("__wasm_udf"))
+            .collect::<Vec<_>>();
+
+        // pass through all input columns so that group expressions stay valid
+        let projection_expr = input
+            .schema()
+            .columns()
+            .into_iter()
+            .map(Expr::Column)
+            .chain(
+                calls
+                    .iter()
+                    .zip(&aliases)
+                    .map(|(call, alias)| call.clone().alias(alias)),
+            )
+            .collect::<Vec<_>>();
+        let projection = Projection::try_new(projection_expr, input)?;
+
+        // visits the calls in the same order as above
+        let mut occurrences = occurrences.into_iter();
+        let name_preserver = NamePreserver::new_for_projection();
+        let aggr_expr = aggr_expr
+            .into_iter()
+            .map(|expr| {
+                let saved_name = name_preserver.save(&expr);
+                let expr = map_input_calls(expr, &mut |e| {
+                    let idx = occurrences
+                        .next()
+                        .ok_or_else(|| internal_datafusion_err!("call was not collected: {e}"))?;
+                    Ok(Expr::Column(Column::from_name(&aliases[idx])))
+                })?;
+                Ok(saved_name.restore(expr))
+            })
+            .collect::<DataFusionResult<Vec<_>>>()?;
+
+        let aggregate = Aggregate::try_new(
+            Arc::new(LogicalPlan::Projection(projection)),
+            group_expr,
+            aggr_expr,
+        )?;
+        Ok(Transformed::yes(LogicalPlan::Aggregate(aggregate)))
+    }
+}
+
+/// Visit the WASM UDF calls that can be evaluated as part of the aggregation input, outermost first.
+///
+/// Calls within `CASE` are skipped, since they are only evaluated for some rows -- moving them below the aggregate
+/// would evaluate them for all rows. The `FILTER` and `ORDER BY` clauses of aggregate functions are skipped as well,
+/// since they are not part of the aggregated values.
+fn apply_input_calls<'n>(expr: &'n Expr, f: &mut impl FnMut(&'n Expr)) -> DataFusionResult<()> {
+    if is_async_udf_call(expr) {
+        f(expr);
+        return Ok(());
+    }
+
+    match expr {
+        Expr::Case(_) => Ok(()),
+        Expr::AggregateFunction(AggregateFunction { params, .. }) => params
+            .args
+            .iter()
+            .try_for_each(|arg| apply_input_calls(arg, f)),
+        _ => expr
+            .apply_children(|child| {
+                apply_input_calls(child, f)?;
+                Ok(TreeNodeRecursion::Continue)
+            })
+            .map(|_| ()),
+    }
+}
+
+/// Replace the WASM UDF calls that [`apply_input_calls`] visits, in the same order.
+fn map_input_calls(
+    expr: Expr,
+    f: &mut impl FnMut(Expr) -> DataFusionResult<Expr>,
+) -> DataFusionResult<Expr> {
+    if is_async_udf_call(&expr) {
+        return f(expr);
+    }
+
+    match expr {
+        Expr::Case(_) => Ok(expr),
+        Expr::AggregateFunction(mut aggregate_function) => {
+            aggregate_function.params.args = aggregate_function
+                .params
+                .args
+                .into_iter()
+                .map(|arg| map_input_calls(arg, f))
+                .collect::<DataFusionResult<_>>()?;
+            Ok(Expr::AggregateFunction(aggregate_function))
+        }
+        _ => expr
+            .map_children(|child| map_input_calls(child, f).map(Transformed::yes))
+            .map(|transformed| transformed.data),
+    }
+}
+
+/// Check if the given expression is a call to an [immutable](Volatility::Immutable) function.
+fn is_immutable_call(expr: &Expr) -> bool {
+    matches!(
+        expr,
+        Expr::ScalarFunction(ScalarFunction { func, .. })
+            if func.signature().volatility == Volatility::Immutable
+    )
+}
+
+/// [`PhysicalOptimizerRule`] that fuses the evaluation of WASM UDFs into the input of aggregates.
+///
+/// [`PushWasmUdfBelowAggregate`] leaves a projection below the aggregate. DataFusion plans it as a
+/// [`ProjectionExec`] on top of an [`AsyncFuncExec`], which in turn usually reads from a [`CoalesceBatchesExec`]. So
+/// the input is coalesced twice and every chunk passes three operators. This rule replaces that chain with a single
+/// [`WasmUdfAggregateInputExec`].
+///
+/// Only projections that select columns -- like the ones created by [`PushWasmUdfBelowAggregate`] -- are fused.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FuseWasmUdfAggregateInput;
+
+impl FuseWasmUdfAggregateInput {
+    /// Create new rule.
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Fuse the input of an aggregate, if possible.
+    fn fuse(plan: &Arc<dyn ExecutionPlan>) -> DataFusionResult<Option<Arc<dyn ExecutionPlan>>> {
+        let Some(projection) = plan.as_any().downcast_ref::<ProjectionExec>() else {
+            return Ok(None);
+        };
+        let Some(async_func) = projection.input().as_any().downcast_ref::<AsyncFuncExec>() else {
+            return Ok(None);
+        };
+        if !projection
+            .expr()
+            .iter()
+            .all(|expr| expr.expr.as_any().is::<PhysicalColumn>())
+        {
+            return Ok(None);
+        }
+
+        // the fused plan coalesces the input itself
+        let input = match async_func
+            .input()
+            .as_any()
+            .downcast_ref::<CoalesceBatchesExec>()
+        {
+            Some(coalesce) if coalesce.fetch().is_none() => Arc::clone(coalesce.input()),
+            _ => Arc::clone(async_func.input()),
+        };
+
+        let fused = WasmUdfAggregateInputExec::try_new(
+            input,
+            async_func.async_exprs().to_vec(),
+            projection.expr().to_vec(),
+        )?;
+        Ok(Some(Arc::new(fused)))
+    }
+}
+
+impl PhysicalOptimizerRule for FuseWasmUdfAggregateInput {
+    fn optimize(
+        &self,
+        plan: Arc<dyn ExecutionPlan>,
+        _config: &ConfigOptions,
+    ) -> DataFusionResult<Arc<dyn ExecutionPlan>> {
+        plan.transform_up(|plan| {
+            let Some(aggregate) = plan.as_any().downcast_ref::<AggregateExec>() else {
+                return Ok(Transformed::no(plan));
+            };
+            let Some(input) = Self::fuse(aggregate.input())? else {
+                return Ok(Transformed::no(plan));
+            };
+            Ok(Transformed::yes(plan.with_new_children(vec![input])?))
+        })
+        .map(|t| t.data)
+    }
+
+    fn name(&self) -> &str {
+        "fuse_wasm_udf_aggregate_input"
+    }
+
+    fn schema_check(&self) -> bool {
+        true
+    }
+}
+
+/// [`ExecutionPlan`] that evaluates WASM UDFs on the chunks that an aggregate accumulates, see
+/// [`FuseWasmUdfAggregateInput`].
+///
+/// The input is coalesced into chunks of the configured batch size, like [`AsyncFuncExec`] does. The UDF results are
+/// NOT appended to the input but directly arranged into the output columns.
+#[derive(Debug)]
+pub struct WasmUdfAggregateInputExec {
+    /// Input plan.
+    input: Arc<dyn ExecutionPlan>,
+
+    /// UDF calls.
+    async_exprs: Vec<Arc<AsyncFuncExpr>>,
+
+    /// Output columns, referring to the input columns followed by the results of [`async_exprs`](Self::async_exprs).
+    exprs: Vec<ProjectionExpr>,
+
+    /// Column indices of [`exprs`](Self::exprs).
+    columns: Arc<[usize]>,
+
+    /// Plan properties, same as for the unfused plan.
+    cache: PlanProperties,
+}
+
+impl WasmUdfAggregateInputExec {
+    /// Create new plan.
+    fn try_new(
+        input: Arc<dyn ExecutionPlan>,
+        async_exprs: Vec<Arc<AsyncFuncExpr>>,
+        exprs: Vec<ProjectionExpr>,
+    ) -> DataFusionResult<Self> {
+        let columns = exprs
+            .iter()
+            .map(|expr| {
+                expr.expr
+                    .as_any()
+                    .downcast_ref::<PhysicalColumn>()
+                    .map(PhysicalColumn::index)
+                    .ok_or_else(|| internal_datafusion_err!("expected column, got {}", expr.expr))
+            })
+            .collect::<DataFusionResult<_>>()?;
+
+        let unfused = ProjectionExec::try_new(
+            exprs.clone(),
+            Arc::new(AsyncFuncExec::try_new(
+                async_exprs.clone(),
+                Arc::clone(&input),
+            )?),
+        )?;
+        let cache = unfused.properties().clone();
+
+        Ok(Self {
+            input,
+            async_exprs,
+            exprs,
+            columns,
+            cache,
+        })
+    }
+
+    /// Input plan.
+    pub fn input(&self) -> &Arc<dyn ExecutionPlan> {
+        &self.input
+    }
+
+    /// UDF calls.
+    pub fn async_exprs(&self) -> &[Arc<AsyncFuncExpr>] {
+        &self.async_exprs
+    }
+}
+
+impl DisplayAs for WasmUdfAggregateInputExec {
+    fn fmt_as(&self, t: DisplayFormatType, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let async_exprs = self
+            .async_exprs
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", ");
+        let exprs = self
+            .exprs
+            .iter()
+            .map(|expr| format!("{} as {}", expr.expr, expr.alias))
+            .collect::<Vec<_>>()
+            .join(", ");
+        match t {
+            DisplayFormatType::Default | DisplayFormatType::Verbose => write!(
+                f,
+                "WasmUdfAggregateInputExec: async_expr=[{async_exprs}], expr=[{exprs}]"
+            ),
+            DisplayFormatType::TreeRender => {
+                writeln!(f, "async_expr={async_exprs}")?;
+                writeln!(f, "expr={exprs}")
+            }
+        }
+    }
+}
+
+impl ExecutionPlan for WasmUdfAggregateInputExec {
+    fn name(&self) -> &str {
+        "WasmUdfAggregateInputExec"
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn properties(&self) -> &PlanProperties {
+        &self.cache
+    }
+
+    fn children(&self) -> Vec<&Arc<dyn ExecutionPlan>> {
+        vec![&self.input]
+    }
+
+    fn maintains_input_order(&self) -> Vec<bool> {
+        vec![true]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> DataFusionResult<Arc<dyn ExecutionPlan>> {
+        let [input] = <[_; 1]>::try_from(children)
+            .map_err(|_| internal_datafusion_err!("WasmUdfAggregateInputExec has one child"))?;
+        Ok(Arc::new(Self::try_new(
+            input,
+            self.async_exprs.clone(),
+            self.exprs.clone(),
+        )?))
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+        context: Arc<TaskContext>,
+    ) -> DataFusionResult<SendableRecordBatchStream> {
+        let state = ChunkState {
+            input: self.input.execute(partition, Arc::clone(&context))?,
+            coalescer: LimitedBatchCoalescer::new(
+                self.input.schema(),
+                context.session_config().batch_size(),
+                None,
+            ),
+            done: false,
+            evaluator: ChunkEvaluator {
+                async_exprs: self.async_exprs.clone(),
+                columns: Arc::clone(&self.columns),
+                schema: self.schema(),
+                config_options: Arc::clone(context.session_config().options()),
+            },
+        };
+
+        let stream = try_unfold(state, |mut state| async move {
+            loop {
+                if let Some(batch) = state.coalescer.next_completed_batch() {
+                    let batch = state.evaluator.evaluate(batch).await?;
+                    return Ok(Some((batch, state)));
+                }
+                if state.done {
+                    return Ok(None);
+                }
+                match state.input.next().await {
+                    Some(batch) => {
+                        state.coalescer.push_batch(batch?)?;
+                    }
+                    None => {
+                        state.done = true;
+                        state.coalescer.finish()?;
+                    }
+                }
+            }
+        });
+        Ok(Box::pin(RecordBatchStreamAdapter::new(
+            self.schema(),
+            stream,
+        )))
+    }
+}
+
+/// State of a [`WasmUdfAggregateInputExec`] stream.
+struct ChunkState {
+    /// Input stream.
+    input: SendableRecordBatchStream,
+
+    /// Coalesces input into chunks.
+    coalescer: LimitedBatchCoalescer,
+
+    /// Input is exhausted.
+    done: bool,
+
+    /// Evaluates UDFs on the chunks.
+    evaluator: ChunkEvaluator,
+}
+
+/// Evaluates the UDFs of a [`WasmUdfAggregateInputExec`] on a chunk.
+///
+/// This is separate from [`ChunkState`] because the input stream is NOT [`Sync`] and hence cannot be borrowed across
+/// await points.
+struct ChunkEvaluator {
+    /// UDF calls.
+    async_exprs: Vec<Arc<AsyncFuncExpr>>,
+
+    /// Output columns, see [`WasmUdfAggregateInputExec::exprs`].
+    columns: Arc<[usize]>,
+
+    /// Output schema.
+    schema: SchemaRef,
+
+    /// Config options passed to the UDFs.
+    config_options: Arc<ConfigOptions>,
+}
+
+impl ChunkEvaluator {
+    /// Evaluate UDFs on a chunk and arrange the output columns.
+    async fn evaluate(&self, chunk: RecordBatch) -> DataFusionResult<RecordBatch> {
+        let mut results = Vec::with_capacity(self.async_exprs.len());
+        for async_expr in &self.async_exprs {
+            let result = async_expr
+                .invoke_with_args(&chunk, Arc::clone(&self.config_options))
+                .await?;
+            results.push(result.to_array(chunk.num_rows())?);
+        }
+
+        let n_input_cols = chunk.num_columns();
+        let columns = self
+            .columns
+            .iter()
+            .map(|&idx| match idx.checked_sub(n_input_cols) {
+                None => Arc::clone(chunk.column(idx)),
+                Some(idx) => Arc::clone(&results[idx]),
+            })
+            .collect();
+        Ok(RecordBatch::try_new_with_options(
+            Arc::clone(&self.schema),
+            columns,
+            &RecordBatchOptions::new().with_row_count(Some(chunk.num_rows())),
+        )?)
+    }
+}
@@ -0,0 +1,31 @@
+//! Optional planner rules that improve how WASM UDFs are evaluated.
+//!
+//! None of these rules are enabled by default. Register them with your DataFusion session, e.g. via
+//! `SessionStateBuilder::with_optimizer_rule` or `SessionStateBuilder::with_physical_optimizer_rule`.
+
+use datafusion_expr::{Expr, async_udf::AsyncScalarUDF, expr::ScalarFunction};
+
+pub use aggregate::{
+    FuseWasmUdfAggregateInput, PushWasmUdfBelowAggregate, WasmUdfAggregateInputExec,
+};
+pub use physical::{ReorderWasmUdfEvaluation, WasmUdfCost};
+pub use predicates::OrderPredicatesByCost;
+
+mod aggregate;
+mod physical;
+mod predicates;
+
+/// Check if the given expression is a direct call to an async UDF.
+///
+/// [`WasmScalarUdf`](datafusion_udf_wasm_host::WasmScalarUdf) can only be registered as an [`AsyncScalarUDF`], so
+/// this is the closest we can get to "is a WASM UDF call" without holding references to the UDF objects themselves.
+fn is_async_udf_call(expr: &Expr) -> bool {
+    match expr {
+        Expr::ScalarFunction(ScalarFunction { func, .. }) => func
+            .inner()
+            .as_any()
+            .downcast_ref::<AsyncScalarUDF>()
+            .is_some(),
+        _ => false,
+    }
+}
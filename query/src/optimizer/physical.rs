@@ -0,0 +1,258 @@
+//! Reposition WASM UDF evaluation within physical plans.
+
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use datafusion_common::{
+    Result as DataFusionResult,
+    config::ConfigOptions,
+    tree_node::{Transformed, TreeNode},
+};
+use datafusion_expr::{JoinType, ScalarUDFImpl, Volatility};
+use datafusion_physical_expr::{
+    PhysicalExpr, ScalarFunctionExpr, expressions::Column, utils::collect_columns,
+};
+use datafusion_physical_optimizer::PhysicalOptimizerRule;
+use datafusion_physical_plan::{
+    ExecutionPlan, async_func::AsyncFuncExec, filter::FilterExec, joins::HashJoinExec,
+    projection::ProjectionExec,
+};
+use datafusion_udf_wasm_host::{WasmScalarUdf, WasmUdfCostEstimate};
+
+/// Estimated cost of evaluating a WASM UDF.
+///
+/// This uses the same linear model as the `udf_overhead` benchmark of the host crate, i.e. per batch:
+///
+/// ```text
+/// cost = cost_row * n_rows + cost_call
+/// ```
+///
+/// The defaults are a rough order of magnitude for the Python guest. They are only a cold-start fallback: rules that
+/// know the UDFs prefer the [cost learned from previous invocations](WasmUdfCostEstimate) once it has samples. Use the
+/// benchmark to measure your own constants if you use a different guest or hardware.
+#[derive(Debug, Clone, Copy)]
+pub struct WasmUdfCost {
+    /// Cost per row.
+    pub cost_row: Duration,
+
+    /// Cost per call, i.e. per batch.
+    pub cost_call: Duration,
+}
+
+impl Default for WasmUdfCost {
+    /// Fallback values for UDFs that were not invoked yet.
+    fn default() -> Self {
+        Self {
+            cost_row: Duration::from_micros(1),
+            cost_call: Duration::from_micros(50),
+        }
+    }
+}
+
+impl WasmUdfCost {
+    /// Estimate cost of evaluating `n_rows` rows in batches of `batch_size`.
+    pub fn estimate(&self, n_rows: usize, batch_size: usize) -> Duration {
+        let n_batches = n_rows.div_ceil(batch_size.max(1));
+        self.cost_row
+            .saturating_mul(n_rows.try_into().unwrap_or(u32::MAX))
+            .saturating_add(
+                self.cost_call
+                    .saturating_mul(n_batches.try_into().unwrap_or(u32::MAX)),
+            )
+    }
+}
+
+/// [`PhysicalOptimizerRule`] that moves WASM UDF evaluation to places in the plan where fewer rows pass through.
+///
+/// The following rewrites are performed:
+///
+/// - **Filters:** If a filter sits on top of an [`AsyncFuncExec`] and the predicate does not use any of the UDF
+///   results, then the filter is evaluated first. Filters never increase the number of rows, so this is always
+///   beneficial.
+/// - **Expanding Joins:** If an [`AsyncFuncExec`] sits on top of an inner or left [`HashJoinExec`] and the UDFs only
+///   use columns of the left join input, then the UDFs are evaluated before the join -- but only if the
+///   [estimated cost](WasmUdfCost) based on the row count statistics shows that the join expands the data.
+///
+/// The cost per row of a UDF is [learned from previous invocations](WasmUdfCostEstimate). UDFs that were not
+/// [registered](Self::with_udf) or have not been invoked yet use the [fallback cost](Self::with_cost).
+///
+/// Rows that a UDF would see are changed by these rewrites, hence UDFs that are declared as
+/// [volatile](Volatility::Volatile) are never moved.
+#[derive(Debug, Default, Clone)]
+pub struct ReorderWasmUdfEvaluation {
+    /// Cost estimates, by UDF name.
+    costs: HashMap<String, WasmUdfCostEstimate>,
+
+    /// Cost model for UDFs without an estimate.
+    cost: WasmUdfCost,
+}
+
+impl ReorderWasmUdfEvaluation {
+    /// Create new rule without any registered UDFs, using the default cost model.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register UDF so its learned cost can be used.
+    pub fn with_udf(mut self, udf: &WasmScalarUdf) -> Self {
+        self.costs
+            .insert(udf.name().to_owned(), udf.cost_estimate());
+        self
+    }
+
+    /// Set cost model for UDFs without an estimate.
+    pub fn with_cost(self, cost: WasmUdfCost) -> Self {
+        Self { cost, ..self }
+    }
+
+    /// Cost model for evaluating all UDFs of the given plan.
+    fn cost(&self, async_func: &AsyncFuncExec) -> WasmUdfCost {
+        async_func.async_exprs().iter().fold(
+            WasmUdfCost {
+                cost_row: Duration::ZERO,
+                cost_call: Duration::ZERO,
+            },
+            |acc, expr| {
+                let cost_row = expr
+                    .func
+                    .as_any()
+                    .downcast_ref::<ScalarFunctionExpr>()
+                    .and_then(|f| self.costs.get(f.name()))
+                    .and_then(WasmUdfCostEstimate::per_row)
+                    .unwrap_or(self.cost.cost_row);
+                WasmUdfCost {
+                    cost_row: acc.cost_row.saturating_add(cost_row),
+                    cost_call: acc.cost_call.saturating_add(self.cost.cost_call),
+                }
+            },
+        )
+    }
+
+    /// Move filter below the UDF evaluation.
+    fn filter_first(
+        plan: Arc<dyn ExecutionPlan>,
+    ) -> DataFusionResult<Transformed<Arc<dyn ExecutionPlan>>> {
+        let Some(filter) = plan.as_any().downcast_ref::<FilterExec>() else {
+            return Ok(Transformed::no(plan));
+        };
+        let Some(async_func) = filter.input().as_any().downcast_ref::<AsyncFuncExec>() else {
+            return Ok(Transformed::no(plan));
+        };
+        if filter.projection().is_some() || !non_volatile(async_func) {
+            return Ok(Transformed::no(plan));
+        }
+
+        // the UDF results are appended to the input columns
+        let n_input_cols = async_func.input().schema().fields().len();
+        if !only_uses_columns(filter.predicate(), n_input_cols) {
+            return Ok(Transformed::no(plan));
+        }
+
+        let new_filter =
+            Arc::clone(&plan).with_new_children(vec![Arc::clone(async_func.input())])?;
+        let new_async_func = Arc::clone(filter.input()).with_new_children(vec![new_filter])?;
+        Ok(Transformed::yes(new_async_func))
+    }
+
+    /// Move UDF evaluation below an expanding join.
+    fn before_join(
+        &self,
+        plan: Arc<dyn ExecutionPlan>,
+        config: &ConfigOptions,
+    ) -> DataFusionResult<Transformed<Arc<dyn ExecutionPlan>>> {
+        let Some(async_func) = plan.as_any().downcast_ref::<AsyncFuncExec>() else {
+            return Ok(Transformed::no(plan));
+        };
+        let Some(join) = async_func.input().as_any().downcast_ref::<HashJoinExec>() else {
+            return Ok(Transformed::no(plan));
+        };
+        if !matches!(join.join_type(), JoinType::Inner | JoinType::Left)
+            || join.contains_projection()
+            || !non_volatile(async_func)
+        {
+            return Ok(Transformed::no(plan));
+        }
+
+        let left = join.left();
+        let n_left_cols = left.schema().fields().len();
+        if !async_func
+            .async_exprs()
+            .iter()
+            .all(|expr| only_uses_columns(&expr.func, n_left_cols))
+        {
+            return Ok(Transformed::no(plan));
+        }
+
+        let (Some(&rows_left), Some(&rows_join)) = (
+            left.partition_statistics(None)?.num_rows.get_value(),
+            join.partition_statistics(None)?.num_rows.get_value(),
+        ) else {
+            return Ok(Transformed::no(plan));
+        };
+        let batch_size = config.execution.batch_size;
+        let cost = self.cost(async_func);
+        if cost.estimate(rows_left, batch_size) >= cost.estimate(rows_join, batch_size) {
+            return Ok(Transformed::no(plan));
+        }
+
+        let new_left = Arc::clone(&plan).with_new_children(vec![Arc::clone(left)])?;
+        let new_join = Arc::clone(async_func.input())
+            .with_new_children(vec![new_left, Arc::clone(join.right())])?;
+
+        // The join now emits `left ++ udfs ++ right` but the parent expects `left ++ right ++ udfs`.
+        let schema = new_join.schema();
+        let n_udfs = async_func.async_exprs().len();
+        let n_right_cols = join.right().schema().fields().len();
+        let order = (0..n_left_cols)
+            .chain((n_left_cols + n_udfs)..(n_left_cols + n_udfs + n_right_cols))
+            .chain(n_left_cols..(n_left_cols + n_udfs));
+        let exprs = order
+            .map(|idx| {
+                let name = schema.field(idx).name().clone();
+                (
+                    Arc::new(Column::new(&name, idx)) as Arc<dyn PhysicalExpr>,
+                    name,
+                )
+            })
+            .collect::<Vec<_>>();
+        let projection = ProjectionExec::try_new(exprs, new_join)?;
+        Ok(Transformed::yes(Arc::new(projection) as _))
+    }
+}
+
+impl PhysicalOptimizerRule for ReorderWasmUdfEvaluation {
+    fn optimize(
+        &self,
+        plan: Arc<dyn ExecutionPlan>,
+        config: &ConfigOptions,
+    ) -> DataFusionResult<Arc<dyn ExecutionPlan>> {
+        plan.transform_down(|plan| {
+            Self::filter_first(plan)?.transform_data(|plan| self.before_join(plan, config))
+        })
+        .map(|t| t.data)
+    }
+
+    fn name(&self) -> &str {
+        "reorder_wasm_udf_evaluation"
+    }
+
+    fn schema_check(&self) -> bool {
+        true
+    }
+}
+
+/// Check that none of the async UDFs are volatile.
+///
+/// Expressions that we cannot inspect are treated as volatile.
+fn non_volatile(async_func: &AsyncFuncExec) -> bool {
+    async_func.async_exprs().iter().all(|expr| {
+        expr.func
+            .as_any()
+            .downcast_ref::<ScalarFunctionExpr>()
+            .is_some_and(|f| f.fun().signature().volatility != Volatility::Volatile)
+    })
+}
+
+/// Check that the expression only uses the first `n_cols` columns of its input.
+fn only_uses_columns(expr: &Arc<dyn PhysicalExpr>, n_cols: usize) -> bool {
+    collect_columns(expr).iter().all(|col| col.index() < n_cols)
+}
@@ -0,0 +1,142 @@
+//! Order conjunctive predicates by their estimated cost.
+
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use datafusion_common::{
+    Result as DataFusionResult,
+    tree_node::{Transformed, TreeNode, TreeNodeRecursion},
+};
+use datafusion_expr::{
+    Expr, Filter, LogicalPlan, ScalarUDFImpl,
+    utils::{conjunction, split_conjunction_owned},
+};
+use datafusion_optimizer::{OptimizerConfig, OptimizerRule, optimizer::ApplyOrder};
+use datafusion_udf_wasm_host::{WasmScalarUdf, WasmUdfCostEstimate};
+
+use super::{WasmUdfCost, is_async_udf_call};
+
+/// [`OptimizerRule`] that evaluates cheap native predicates before expensive WASM UDF predicates.
+///
+/// A filter like:
+///
+/// ```sql
+/// WHERE my_udf(x) AND y > 1 AND my_other_udf(z)
+/// ```
+///
+/// is split into a stack of filters: first all predicates that do not call any WASM UDF, then every UDF predicate
+/// on its own, ordered by its estimated cost per row. This way an expensive UDF only sees rows that passed all
+/// cheaper predicates.
+///
+/// The cost of a UDF is [learned from previous invocations](WasmUdfCostEstimate). UDFs that were not
+/// [registered](Self::with_udf) or have not been invoked yet use the [default cost](WasmUdfCost::cost_row).
+///
+/// Filters that contain volatile expressions are left untouched.
+///
+/// # Rule Order
+/// DataFusion's own filter push-down merges adjacent filters, so this rule must run after it. This is the case when
+/// the rule is added via `SessionStateBuilder::with_optimizer_rule`.
+#[derive(Debug, Default, Clone)]
+pub struct OrderPredicatesByCost {
+    /// Cost estimates, by UDF name.
+    costs: HashMap<String, WasmUdfCostEstimate>,
+
+    /// Cost model for UDFs without an estimate.
+    default_cost: WasmUdfCost,
+}
+
+impl OrderPredicatesByCost {
+    /// Create new rule without any registered UDFs.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register UDF so its learned cost can be used.
+    pub fn with_udf(mut self, udf: &WasmScalarUdf) -> Self {
+        self.costs
+            .insert(udf.name().to_owned(), udf.cost_estimate());
+        self
+    }
+
+    /// Set cost model for UDFs without an estimate.
+    pub fn with_default_cost(self, cost: WasmUdfCost) -> Self {
+        Self {
+            default_cost: cost,
+            ..self
+        }
+    }
+
+    /// Estimated cost per row of the given expression.
+    ///
+    /// Only async UDF calls are considered, everything else is assumed to be free.
+    fn cost(&self, expr: &Expr) -> DataFusionResult<Duration> {
+        let mut cost = Duration::ZERO;
+        expr.apply(|e| {
+            if let Expr::ScalarFunction(f) = e
+                && is_async_udf_call(e)
+            {
+                let udf_cost = self
+                    .costs
+                    .get(f.func.name())
+                    .and_then(WasmUdfCostEstimate::per_row)
+                    .unwrap_or(self.default_cost.cost_row);
+                cost = cost.saturating_add(udf_cost);
+            }
+            Ok(TreeNodeRecursion::Continue)
+        })?;
+        Ok(cost)
+    }
+}
+
+impl OptimizerRule for OrderPredicatesByCost {
+    fn name(&self) -> &str {
+        "order_predicates_by_cost"
+    }
+
+    fn apply_order(&self) -> Option<ApplyOrder> {
+        Some(ApplyOrder::TopDown)
+    }
+
+    fn rewrite(
+        &self,
+        plan: LogicalPlan,
+        _config: &dyn OptimizerConfig,
+    ) -> DataFusionResult<Transformed<LogicalPlan>> {
+        let LogicalPlan::Filter(filter) = plan else {
+            return Ok(Transformed::no(plan));
+        };
+        if filter.predicate.is_volatile() {
+            return Ok(Transformed::no(LogicalPlan::Filter(filter)));
+        }
+
+        let predicates = split_conjunction_owned(filter.predicate.clone());
+        if predicates.len() < 2 {
+            return Ok(Transformed::no(LogicalPlan::Filter(filter)));
+        }
+
+        let mut native = vec![];
+        let mut udfs = vec![];
+        for predicate in predicates {
+            if predicate.exists(|e| Ok(is_async_udf_call(e)))? {
+                let cost = self.cost(&predicate)?;
+                udfs.push((cost, predicate));
+            } else {
+                native.push(predicate);
+            }
+        }
+        if udfs.is_empty() {
+            return Ok(Transformed::no(LogicalPlan::Filter(filter)));
+        }
+        // stable sort, so predicates of equal cost keep their original order
+        udfs.sort_by_key(|(cost, _)| *cost);
+
+        let mut plan = Arc::clone(&filter.input);
+        if let Some(predicate) = conjunction(native) {
+            plan = Arc::new(LogicalPlan::Filter(Filter::try_new(predicate, plan)?));
+        }
+        for (_cost, predicate) in udfs {
+            plan = Arc::new(LogicalPlan::Filter(Filter::try_new(predicate, plan)?));
+        }
+
+        Ok(Transformed::yes(Arc::unwrap_or_clone(plan)))
+    }
+}
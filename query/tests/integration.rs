@@ -5,23 +5,40 @@
     unused_crate_dependencies,
 )]
 
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, num::NonZeroUsize, sync::Arc, time::Duration};
 
 use datafusion::prelude::{DataFrame, SessionConfig, SessionContext};
 use datafusion_common::{
     Result as DataFusionResult, assert_batches_eq, test_util::batches_to_string,
 };
 use datafusion_execution::{memory_pool::UnboundedMemoryPool, runtime_env::RuntimeEnv};
-use datafusion_udf_wasm_host::WasmPermissions;
+use datafusion_proto::bytes::{
+    logical_plan_from_bytes_with_extension_codec, logical_plan_to_bytes_with_extension_codec,
+};
+use datafusion_udf_wasm_host::{
+    SourceDiagnostic, WasmComponentPrecompiled, WasmPermissions, WasmUdfConfig,
+};
 use datafusion_udf_wasm_query::{
-    ComponentFn, Lang, ParsedQuery, UdfQueryParser,
-    format::{NoOpFormatter, StripIndentationFormatter},
+    ComponentFn, Lang, ParsedQuery, UdfQueryParser, UdfStatement,
+    cache::UdfCacheLimits,
+    codec::{WasmComponentResolver, WasmUdfCodec},
+    format::{
+        FrontMatterFormatter, PythonIndentationFormatter, StripIndentationFormatter,
+        TabNormalizationFormatter,
+    },
+    optimizer::{
+        FuseWasmUdfAggregateInput, OrderPredicatesByCost, PushWasmUdfBelowAggregate,
+        ReorderWasmUdfEvaluation,
+    },
 };
 use tokio::runtime::Handle;
 
 mod integration_tests;
 
-use crate::integration_tests::python::test_utils::python_component;
+use crate::integration_tests::{
+    expr::test_utils::expr_component, python::test_utils::python_component,
+    rust::test_utils::add_ints_component,
+};
 
 /// A helper struct for invoking UDF queries and validating their results.
 struct UdfQueryInvocator;
@@ -32,7 +49,7 @@ impl UdfQueryInvocator {
         parsed_query: ParsedQuery,
     ) -> DataFusionResult<DataFrame> {
         for udf in parsed_query.udfs {
-            ctx.register_udf(udf.as_async_udf().into());
+            ctx.register_udf(udf.into_scalar_udf());
         }
 
         ctx.sql(&parsed_query.sql).await
@@ -53,13 +70,12 @@ SELECT add_one(1);
 "#;
 
     let ctx = session_ctx();
-    let formatter = Box::new(NoOpFormatter);
 
     let parser = UdfQueryParser::new(HashMap::from_iter([(
         "python".to_string(),
         Lang {
             component: ComponentFn::lazy(python_component),
-            formatter,
+            formatters: vec![],
         },
     )]));
     let parsed_query = parser
@@ -108,13 +124,12 @@ SELECT add_one(1), multiply_two(3);
 "#;
 
     let ctx = session_ctx();
-    let formatter = Box::new(NoOpFormatter);
 
     let parser = UdfQueryParser::new(HashMap::from_iter([(
         "python".to_string(),
         Lang {
             component: ComponentFn::lazy(python_component),
-            formatter,
+            formatters: vec![],
         },
     )]));
     let parsed_query = parser
@@ -159,13 +174,12 @@ SELECT add_one(1), multiply_two(3);
 "#;
 
     let ctx = session_ctx();
-    let formatter = Box::new(NoOpFormatter);
 
     let parser = UdfQueryParser::new(HashMap::from_iter([(
         "python".to_string(),
         Lang {
             component: ComponentFn::lazy(python_component),
-            formatter,
+            formatters: vec![],
         },
     )]));
     let parsed_query = parser
@@ -193,6 +207,52 @@ SELECT add_one(1), multiply_two(3);
     );
 }
 
+#[tokio::test]
+async fn test_expr_language() {
+    let query = r#"
+CREATE FUNCTION classify()
+LANGUAGE expr
+AS '
+classify(x: int) -> str = if x % 2 == 0 then "even" else "odd"
+';
+
+SELECT classify(1), classify(2);
+"#;
+
+    let ctx = session_ctx();
+
+    let parser = UdfQueryParser::new(HashMap::from_iter([(
+        "expr".to_string(),
+        Lang {
+            component: ComponentFn::lazy(expr_component),
+            formatters: vec![],
+        },
+    )]));
+    let parsed_query = parser
+        .parse(
+            query,
+            &WasmPermissions::new(),
+            Handle::current(),
+            ctx.task_ctx().as_ref(),
+        )
+        .await
+        .unwrap();
+
+    let df = UdfQueryInvocator::invoke(&ctx, parsed_query).await.unwrap();
+    let batch = df.collect().await.unwrap();
+
+    assert_batches_eq!(
+        [
+            "+--------------------+--------------------+",
+            "| classify(Int64(1)) | classify(Int64(2)) |",
+            "+--------------------+--------------------+",
+            "| odd                | even               |",
+            "+--------------------+--------------------+",
+        ],
+        &batch
+    );
+}
+
 #[tokio::test]
 async fn test_empty_string() {
     let query = r#"
@@ -204,13 +264,86 @@ SELECT add_one(1)
 "#;
 
     let ctx = session_ctx();
-    let formatter = Box::new(NoOpFormatter);
 
     let parser = UdfQueryParser::new(HashMap::from_iter([(
         "python".to_string(),
         Lang {
             component: ComponentFn::lazy(python_component),
-            formatter,
+            formatters: vec![],
+        },
+    )]));
+    let err = parser
+        .parse(
+            query,
+            &WasmPermissions::new(),
+            Handle::current(),
+            ctx.task_ctx().as_ref(),
+        )
+        .await
+        .unwrap_err();
+
+    insta::assert_snapshot!(
+        err,
+        @"Error during planning: function body does not define declared function 'add_one', found: []",
+    );
+}
+
+#[tokio::test]
+async fn test_name_mismatch() {
+    let query = r#"
+CREATE FUNCTION add_one()
+LANGUAGE python
+AS '
+def other_name(x: int) -> int:
+    return x + 1
+';
+
+SELECT add_one(1)
+"#;
+
+    let ctx = session_ctx();
+    let parser = UdfQueryParser::new(HashMap::from_iter([(
+        "python".to_string(),
+        Lang {
+            component: ComponentFn::lazy(python_component),
+            formatters: vec![],
+        },
+    )]));
+    let err = parser
+        .parse(
+            query,
+            &WasmPermissions::new(),
+            Handle::current(),
+            ctx.task_ctx().as_ref(),
+        )
+        .await
+        .unwrap_err();
+
+    insta::assert_snapshot!(
+        err,
+        @r#"Error during planning: function body does not define declared function 'add_one', found: ["other_name"]"#,
+    );
+}
+
+#[tokio::test]
+async fn test_declared_name_normalization() {
+    let query = r#"
+CREATE FUNCTION my_schema.Add_One()
+LANGUAGE python
+AS '
+def add_one(x: int) -> int:
+    return x + 1
+';
+
+SELECT add_one(1) AS y
+"#;
+
+    let ctx = session_ctx();
+    let parser = UdfQueryParser::new(HashMap::from_iter([(
+        "python".to_string(),
+        Lang {
+            component: ComponentFn::lazy(python_component),
+            formatters: vec![],
         },
     )]));
     let parsed_query = parser
@@ -223,11 +356,140 @@ SELECT add_one(1)
         .await
         .unwrap();
 
-    let r = UdfQueryInvocator::invoke(&ctx, parsed_query).await;
-    assert!(r.is_err());
+    let df = UdfQueryInvocator::invoke(&ctx, parsed_query).await.unwrap();
+    let batch = df.collect().await.unwrap();
+
+    assert_batches_eq!(["+---+", "| y |", "+---+", "| 2 |", "+---+"], &batch);
+
+    // quoted identifiers keep their case
+    let query = r#"
+CREATE FUNCTION "Add_One"()
+LANGUAGE python
+AS '
+def add_one(x: int) -> int:
+    return x + 1
+';
+"#;
+    let err = parser
+        .parse(
+            query,
+            &WasmPermissions::new(),
+            Handle::current(),
+            ctx.task_ctx().as_ref(),
+        )
+        .await
+        .unwrap_err();
+
+    insta::assert_snapshot!(
+        err,
+        @r#"Error during planning: function body does not define declared function 'Add_One', found: ["add_one"]"#,
+    );
+}
+
+#[tokio::test]
+async fn test_language_aliases() {
+    let ctx = session_ctx();
+    let parser = UdfQueryParser::new(HashMap::from_iter([(
+        "python".to_string(),
+        Lang {
+            component: ComponentFn::lazy(python_component),
+            formatters: vec![],
+        },
+    )]))
+    .with_language_alias("py", "python")
+    .with_language_alias("Python3", "python");
+
+    for language in ["python", "PYTHON", "py", "python3", "PyThOn3"] {
+        let query = format!(
+            r#"
+CREATE FUNCTION add_one()
+LANGUAGE {language}
+AS '
+def add_one(x: int) -> int:
+    return x + 1
+';
+
+SELECT add_one(1);
+"#
+        );
+        let parsed_query = parser
+            .parse(
+                &query,
+                &WasmPermissions::new(),
+                Handle::current(),
+                ctx.task_ctx().as_ref(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(parsed_query.udfs.len(), 1, "{language}");
+    }
+
+    let query = r#"
+CREATE FUNCTION add_one()
+LANGUAGE ruby
+AS 'def add_one(x) = x + 1';
+"#;
+    let err = parser
+        .parse(
+            query,
+            &WasmPermissions::new(),
+            Handle::current(),
+            ctx.task_ctx().as_ref(),
+        )
+        .await
+        .unwrap_err();
+
+    insta::assert_snapshot!(
+        err,
+        @r#"Error during planning: no WASM component registered for language: "ruby", registered languages: py, python, python3"#,
+    );
+}
+
+#[tokio::test]
+async fn test_validate_source() {
+    let ctx = session_ctx();
+    let parser = UdfQueryParser::new(HashMap::from_iter([(
+        "python".to_string(),
+        Lang {
+            component: ComponentFn::lazy(python_component),
+            formatters: vec![Box::new(StripIndentationFormatter)],
+        },
+    )]));
+
+    let diagnostics = parser
+        .validate_source(
+            "python",
+            "
+    def add_one(x: int) -> int:
+        return (x + 1))
+"
+            .to_owned(),
+            &WasmPermissions::new(),
+            Handle::current(),
+            ctx.task_ctx().as_ref(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        diagnostics,
+        [SourceDiagnostic {
+            message: "unmatched ')'".to_owned(),
+            line: 3,
+            column: 19,
+        }],
+    );
 
-    let err = r.err().unwrap();
-    assert!(err.message().contains("Invalid function 'add_one'"));
+    let diagnostics = parser
+        .validate_source(
+            "python",
+            "def add_one(x: int) -> int:\n    return x + 1\n".to_owned(),
+            &WasmPermissions::new(),
+            Handle::current(),
+            ctx.task_ctx().as_ref(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(diagnostics, []);
 }
 
 #[tokio::test]
@@ -244,13 +506,12 @@ EXPLAIN SELECT add_one(1);
 "#;
 
     let ctx = session_ctx();
-    let formatter = Box::new(NoOpFormatter);
 
     let parser = UdfQueryParser::new(HashMap::from_iter([(
         "python".to_string(),
         Lang {
             component: ComponentFn::lazy(python_component),
-            formatter,
+            formatters: vec![],
         },
     )]));
     let parsed_query = parser
@@ -299,13 +560,12 @@ async fn test_strip_indentation_everything_indented() {
     let query = query_lines.join("\n");
 
     let ctx = session_ctx();
-    let formatter = Box::new(StripIndentationFormatter);
 
     let parser = UdfQueryParser::new(HashMap::from_iter([(
         "python".to_string(),
         Lang {
             component: ComponentFn::lazy(python_component),
-            formatter,
+            formatters: vec![Box::new(StripIndentationFormatter)],
         },
     )]));
     let parsed_query = parser
@@ -333,6 +593,63 @@ async fn test_strip_indentation_everything_indented() {
     );
 }
 
+#[tokio::test]
+async fn test_formatter_pipeline_python_triple_quoted_string() {
+    let query_lines = &[
+        "  CREATE FUNCTION indent()",
+        "  LANGUAGE python",
+        "  AS $$",
+        "  #!/usr/bin/env python",
+        "  def indent(x: int) -> int:",
+        "  \t'''",
+        "  \tLength of this docstring must not change.",
+        "  \t'''",
+        "  \treturn x + len(indent.__doc__)",
+        "  $$;",
+        "",
+        "  SELECT indent(0);",
+    ];
+    let query = query_lines.join("\n");
+
+    let ctx = session_ctx();
+
+    let parser = UdfQueryParser::new(HashMap::from_iter([(
+        "python".to_string(),
+        Lang {
+            component: ComponentFn::lazy(python_component),
+            formatters: vec![
+                Box::new(FrontMatterFormatter),
+                Box::new(TabNormalizationFormatter { tab_width: 4 }),
+                Box::new(PythonIndentationFormatter),
+            ],
+        },
+    )]));
+    let parsed_query = parser
+        .parse(
+            &query,
+            &WasmPermissions::new(),
+            Handle::current(),
+            ctx.task_ctx().as_ref(),
+        )
+        .await
+        .unwrap();
+
+    let df = UdfQueryInvocator::invoke(&ctx, parsed_query).await.unwrap();
+    let batch = df.collect().await.unwrap();
+
+    // newline + 4 spaces + 41 characters + newline + 4 spaces
+    assert_batches_eq!(
+        [
+            "+------------------+",
+            "| indent(Int64(0)) |",
+            "+------------------+",
+            "| 51               |",
+            "+------------------+",
+        ],
+        &batch
+    );
+}
+
 #[tokio::test]
 async fn test_strip_indentation_empty_lines_not_indented() {
     let query_lines = &[
@@ -349,13 +666,12 @@ async fn test_strip_indentation_empty_lines_not_indented() {
     let query = query_lines.join("\n");
 
     let ctx = session_ctx();
-    let formatter = Box::new(StripIndentationFormatter);
 
     let parser = UdfQueryParser::new(HashMap::from_iter([(
         "python".to_string(),
         Lang {
             component: ComponentFn::lazy(python_component),
-            formatter,
+            formatters: vec![Box::new(StripIndentationFormatter)],
         },
     )]));
     let parsed_query = parser
@@ -398,13 +714,12 @@ async fn test_strip_indentation_python_further_indented() {
     let query = query_lines.join("\n");
 
     let ctx = session_ctx();
-    let formatter = Box::new(StripIndentationFormatter);
 
     let parser = UdfQueryParser::new(HashMap::from_iter([(
         "python".to_string(),
         Lang {
             component: ComponentFn::lazy(python_component),
-            formatter,
+            formatters: vec![Box::new(StripIndentationFormatter)],
         },
     )]));
     let parsed_query = parser
@@ -432,7 +747,998 @@ async fn test_strip_indentation_python_further_indented() {
     );
 }
 
-/// Get session context.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_constant_folding() {
+    let query = r#"
+CREATE FUNCTION add_one()
+LANGUAGE python
+AS '
+def add_one(x: int) -> int:
+    return x + 1
+
+add_one.volatility = "immutable"
+';
+
+EXPLAIN SELECT add_one(1) AS y;
+"#;
+
+    let ctx = session_ctx();
+
+    let parser = UdfQueryParser::new(HashMap::from_iter([(
+        "python".to_string(),
+        Lang {
+            component: ComponentFn::lazy(python_component),
+            formatters: vec![],
+        },
+    )]));
+    let parsed_query = parser
+        .parse(
+            query,
+            &WasmPermissions::new(),
+            Handle::current(),
+            ctx.task_ctx().as_ref(),
+        )
+        .await
+        .unwrap();
+
+    let df = UdfQueryInvocator::invoke(&ctx, parsed_query).await.unwrap();
+    let batch = df.collect().await.unwrap();
+    let plan = batches_to_string(&batch);
+
+    // the call was evaluated while planning, so there is nothing left to do for the UDF
+    assert!(plan.contains("Int64(2) AS y"), "{plan}");
+    assert!(!plan.contains("add_one"), "{plan}");
+    assert!(!plan.contains("AsyncFuncExec"), "{plan}");
+}
+
+#[tokio::test]
+async fn test_implicit_coercion() {
+    let query = r#"
+CREATE FUNCTION add_ints()
+LANGUAGE rust
+AS '';
+
+SELECT add_ints(CAST(x AS SMALLINT), CAST(y AS INT)) AS s
+FROM (VALUES (1, 2), (3, 4)) AS t(x, y)
+ORDER BY s;
+"#;
+
+    let ctx = session_ctx();
+
+    let parser = UdfQueryParser::new(HashMap::from_iter([(
+        "rust".to_string(),
+        Lang {
+            component: ComponentFn::lazy(add_ints_component),
+            formatters: vec![],
+        },
+    )]));
+    let parsed_query = parser
+        .parse(
+            query,
+            &WasmPermissions::new(),
+            Handle::current(),
+            ctx.task_ctx().as_ref(),
+        )
+        .await
+        .unwrap();
+
+    // the guest coerces both arguments to Int64
+    let df = UdfQueryInvocator::invoke(&ctx, parsed_query).await.unwrap();
+    let batch = df.collect().await.unwrap();
+
+    assert_batches_eq!(
+        ["+---+", "| s |", "+---+", "| 3 |", "| 7 |", "+---+",],
+        &batch
+    );
+}
+
+#[tokio::test]
+async fn test_aggregate_input() {
+    let query = r#"
+CREATE FUNCTION add_one()
+LANGUAGE python
+AS '
+def add_one(x: int) -> int:
+    return x + 1
+';
+
+SELECT array_agg(add_one(x)) AS a, sum(add_one(x)) AS s
+FROM (VALUES (1), (2), (3)) AS t(x);
+"#;
+
+    let ctx = session_ctx();
+    ctx.add_optimizer_rule(Arc::new(PushWasmUdfBelowAggregate::new()));
+
+    let parser = UdfQueryParser::new(HashMap::from_iter([(
+        "python".to_string(),
+        Lang {
+            component: ComponentFn::lazy(python_component),
+            formatters: vec![],
+        },
+    )]));
+    let parsed_query = parser
+        .parse(
+            query,
+            &WasmPermissions::new(),
+            Handle::current(),
+            ctx.task_ctx().as_ref(),
+        )
+        .await
+        .unwrap();
+
+    let df = UdfQueryInvocator::invoke(&ctx, parsed_query).await.unwrap();
+    let batch = df.collect().await.unwrap();
+
+    assert_batches_eq!(
+        [
+            "+-----------+---+",
+            "| a         | s |",
+            "+-----------+---+",
+            "| [2, 3, 4] | 9 |",
+            "+-----------+---+",
+        ],
+        &batch
+    );
+}
+
+#[tokio::test]
+async fn test_aggregate_input_fused() {
+    let query = r#"
+CREATE FUNCTION add_one()
+LANGUAGE python
+AS '
+def add_one(x: int) -> int:
+    return x + 1
+
+add_one.volatility = "immutable"
+';
+
+SELECT array_agg(add_one(x)) AS a, sum(add_one(x)) AS s
+FROM (VALUES (1), (2), (3)) AS t(x);
+"#;
+
+    let ctx = SessionContext::new_with_state(
+        datafusion::execution::SessionStateBuilder::new_from_existing(session_ctx().state())
+            .with_optimizer_rule(Arc::new(PushWasmUdfBelowAggregate::new()))
+            .with_physical_optimizer_rule(Arc::new(FuseWasmUdfAggregateInput::new()))
+            .build(),
+    );
+
+    let parser = UdfQueryParser::new(HashMap::from_iter([(
+        "python".to_string(),
+        Lang {
+            component: ComponentFn::lazy(python_component),
+            formatters: vec![],
+        },
+    )]));
+    let parsed_query = parser
+        .parse(
+            query,
+            &WasmPermissions::new(),
+            Handle::current(),
+            ctx.task_ctx().as_ref(),
+        )
+        .await
+        .unwrap();
+
+    let df = UdfQueryInvocator::invoke(&ctx, parsed_query).await.unwrap();
+    let plan = batches_to_string(
+        &df.clone()
+            .explain(false, false)
+            .unwrap()
+            .collect()
+            .await
+            .unwrap(),
+    );
+    let batch = df.collect().await.unwrap();
+
+    assert_batches_eq!(
+        [
+            "+-----------+---+",
+            "| a         | s |",
+            "+-----------+---+",
+            "| [2, 3, 4] | 9 |",
+            "+-----------+---+",
+        ],
+        &batch
+    );
+
+    // the immutable call is shared and evaluated by the fused plan
+    assert_eq!(plan.matches("async_expr(").count(), 1, "{plan}");
+    assert!(plan.contains("WasmUdfAggregateInputExec"), "{plan}");
+    assert!(!plan.contains("AsyncFuncExec"), "{plan}");
+}
+
+#[tokio::test]
+async fn test_aggregate_input_volatile() {
+    let query = r#"
+CREATE FUNCTION next_id()
+LANGUAGE python
+AS '
+_id = 0
+
+def next_id(x: int) -> int:
+    global _id
+    _id += 1
+    return _id
+';
+
+SELECT array_agg(next_id(x)) AS a, max(next_id(x)) AS m
+FROM (VALUES (1), (2), (3)) AS t(x);
+"#;
+
+    let ctx = SessionContext::new_with_state(
+        datafusion::execution::SessionStateBuilder::new_from_existing(session_ctx().state())
+            .with_optimizer_rule(Arc::new(PushWasmUdfBelowAggregate::new()))
+            .with_physical_optimizer_rule(Arc::new(FuseWasmUdfAggregateInput::new()))
+            .build(),
+    );
+
+    let parser = UdfQueryParser::new(HashMap::from_iter([(
+        "python".to_string(),
+        Lang {
+            component: ComponentFn::lazy(python_component),
+            formatters: vec![],
+        },
+    )]));
+    let parsed_query = parser
+        .parse(
+            query,
+            &WasmPermissions::new(),
+            Handle::current(),
+            ctx.task_ctx().as_ref(),
+        )
+        .await
+        .unwrap();
+
+    let df = UdfQueryInvocator::invoke(&ctx, parsed_query).await.unwrap();
+    let batch = df.collect().await.unwrap();
+
+    // volatile calls are NOT shared, so every call sees its own rows
+    assert_batches_eq!(
+        [
+            "+-----------+---+",
+            "| a         | m |",
+            "+-----------+---+",
+            "| [1, 2, 3] | 6 |",
+            "+-----------+---+",
+        ],
+        &batch
+    );
+}
+
+#[tokio::test]
+async fn test_aggregate_input_conditional() {
+    let query = r#"
+CREATE FUNCTION inv()
+LANGUAGE python
+AS '
+def inv(x: int) -> int:
+    return 10 // x
+';
+
+SELECT
+    sum(CASE WHEN x <> 0 THEN inv(x) ELSE 0 END) AS s,
+    array_agg(x ORDER BY inv(x)) AS a,
+    count(x) FILTER (WHERE inv(x) > 1) AS c
+FROM (VALUES (0), (1), (2)) AS t(x);
+"#;
+
+    let ctx = session_ctx();
+    ctx.add_optimizer_rule(Arc::new(PushWasmUdfBelowAggregate::new()));
+
+    let parser = UdfQueryParser::new(HashMap::from_iter([(
+        "python".to_string(),
+        Lang {
+            component: ComponentFn::lazy(python_component),
+            formatters: vec![],
+        },
+    )]));
+    let parsed_query = parser
+        .parse(
+            query,
+            &WasmPermissions::new(),
+            Handle::current(),
+            ctx.task_ctx().as_ref(),
+        )
+        .await
+        .unwrap();
+
+    let df = UdfQueryInvocator::invoke(&ctx, parsed_query).await.unwrap();
+    let plan = df
+        .into_optimized_plan()
+        .unwrap()
+        .display_indent()
+        .to_string();
+
+    // calls within CASE as well as the FILTER and ORDER BY clauses are NOT moved below the aggregate
+    assert!(!plan.contains("__wasm_udf"), "{plan}");
+}
+
+#[tokio::test]
+async fn test_reorder_evaluation() {
+    let query = r#"
+CREATE FUNCTION add_one()
+LANGUAGE python
+AS '
+def add_one(x: int) -> int:
+    return x + 1
+';
+
+SELECT y
+FROM (
+    SELECT add_one(x) AS y, x
+    FROM (VALUES (1), (2), (3)) AS t(x)
+)
+WHERE x > 1
+ORDER BY y;
+"#;
+
+    let ctx = SessionContext::new_with_state(
+        datafusion::execution::SessionStateBuilder::new_from_existing(session_ctx().state())
+            .with_physical_optimizer_rule(Arc::new(ReorderWasmUdfEvaluation::new()))
+            .build(),
+    );
+
+    let parser = UdfQueryParser::new(HashMap::from_iter([(
+        "python".to_string(),
+        Lang {
+            component: ComponentFn::lazy(python_component),
+            formatters: vec![],
+        },
+    )]));
+    let parsed_query = parser
+        .parse(
+            query,
+            &WasmPermissions::new(),
+            Handle::current(),
+            ctx.task_ctx().as_ref(),
+        )
+        .await
+        .unwrap();
+
+    let df = UdfQueryInvocator::invoke(&ctx, parsed_query).await.unwrap();
+    let batch = df.collect().await.unwrap();
+
+    assert_batches_eq!(
+        ["+---+", "| y |", "+---+", "| 3 |", "| 4 |", "+---+",],
+        &batch
+    );
+}
+
+#[tokio::test]
+async fn test_order_predicates_by_cost() {
+    let query = r#"
+CREATE FUNCTION is_odd()
+LANGUAGE python
+AS '
+def is_odd(x: int) -> bool:
+    return x % 2 == 1
+';
+
+SELECT x
+FROM (VALUES (1), (2), (3), (4), (5)) AS t(x)
+WHERE is_odd(x) AND x > 1
+ORDER BY x;
+"#;
+
+    let ctx = session_ctx();
+
+    let parser = UdfQueryParser::new(HashMap::from_iter([(
+        "python".to_string(),
+        Lang {
+            component: ComponentFn::lazy(python_component),
+            formatters: vec![],
+        },
+    )]));
+    let parsed_query = parser
+        .parse(
+            query,
+            &WasmPermissions::new(),
+            Handle::current(),
+            ctx.task_ctx().as_ref(),
+        )
+        .await
+        .unwrap();
+
+    let rule = parsed_query
+        .udfs
+        .iter()
+        .fold(OrderPredicatesByCost::new(), |rule, udf| rule.with_udf(udf));
+    let cost = parsed_query.udfs[0].cost_estimate();
+    ctx.add_optimizer_rule(Arc::new(rule));
+
+    let df = UdfQueryInvocator::invoke(&ctx, parsed_query).await.unwrap();
+    let batch = df.collect().await.unwrap();
+
+    assert_batches_eq!(
+        ["+---+", "| x |", "+---+", "| 3 |", "| 5 |", "+---+",],
+        &batch
+    );
+
+    // the UDF only sees rows that passed the native predicate
+    assert_eq!(cost.rows(), 4);
+}
+
+#[tokio::test]
+async fn test_config_extension() {
+    let query = r#"
+CREATE FUNCTION add_one()
+LANGUAGE python
+AS '
+def add_one(x: int) -> int:
+    return x + 1
+';
+
+SELECT add_one(x) AS y
+FROM (VALUES (1), (2), (3), (4), (5)) AS t(x)
+ORDER BY y;
+"#;
+
+    let ctx = SessionContext::new_with_config_rt(
+        SessionConfig::new().with_option_extension(WasmUdfConfig::default()),
+        Arc::new(RuntimeEnv {
+            memory_pool: Arc::new(UnboundedMemoryPool::default()),
+            ..Default::default()
+        }),
+    );
+    ctx.sql("SET udf_wasm.max_batch_rows = 2")
+        .await
+        .unwrap()
+        .collect()
+        .await
+        .unwrap();
+
+    let parser = UdfQueryParser::new(HashMap::from_iter([(
+        "python".to_string(),
+        Lang {
+            component: ComponentFn::lazy(python_component),
+            formatters: vec![],
+        },
+    )]));
+    let parsed_query = parser
+        .parse(
+            query,
+            &WasmPermissions::new(),
+            Handle::current(),
+            ctx.task_ctx().as_ref(),
+        )
+        .await
+        .unwrap();
+
+    let df = UdfQueryInvocator::invoke(&ctx, parsed_query).await.unwrap();
+    let batch = df.collect().await.unwrap();
+
+    assert_batches_eq!(
+        [
+            "+---+", "| y |", "+---+", "| 2 |", "| 3 |", "| 4 |", "| 5 |", "| 6 |", "+---+",
+        ],
+        &batch
+    );
+
+    ctx.sql("SET udf_wasm.enabled = false")
+        .await
+        .unwrap()
+        .collect()
+        .await
+        .unwrap();
+    let err = parser
+        .parse(
+            query,
+            &WasmPermissions::new(),
+            Handle::current(),
+            ctx.task_ctx().as_ref(),
+        )
+        .await
+        .unwrap_err();
+    insta::assert_snapshot!(
+        err,
+        @"Error during planning: WASM UDFs are disabled, see `udf_wasm.enabled`",
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_codec_roundtrip() {
+    let query = r#"
+CREATE FUNCTION add_one()
+LANGUAGE python
+AS '
+def add_one(x: int) -> int:
+    return x + 1
+';
+
+SELECT add_one(x) AS y
+FROM (VALUES (1), (2), (3)) AS t(x)
+ORDER BY y;
+"#;
+
+    let ctx = session_ctx();
+
+    let parser = UdfQueryParser::new(HashMap::from_iter([(
+        "python".to_string(),
+        Lang {
+            component: ComponentFn::lazy(python_component),
+            formatters: vec![],
+        },
+    )]));
+    let parsed_query = parser
+        .parse(
+            query,
+            &WasmPermissions::new(),
+            Handle::current(),
+            ctx.task_ctx().as_ref(),
+        )
+        .await
+        .unwrap();
+
+    let df = UdfQueryInvocator::invoke(&ctx, parsed_query).await.unwrap();
+    let plan = df.into_optimized_plan().unwrap();
+
+    // SAFETY: the data was produced by this very process
+    let component =
+        unsafe { WasmComponentPrecompiled::load(python_component().await.store().to_vec()) }
+            .unwrap();
+    let codec = WasmUdfCodec::new(Handle::current(), Arc::clone(ctx.task_ctx().memory_pool()))
+        .with_component(Arc::new(component), Arc::new(WasmPermissions::new()));
+    let bytes = logical_plan_to_bytes_with_extension_codec(&plan, &codec).unwrap();
+
+    // the other side does NOT have the UDF registered
+    let ctx = session_ctx();
+    let plan =
+        logical_plan_from_bytes_with_extension_codec(&bytes, ctx.task_ctx().as_ref(), &codec)
+            .unwrap();
+    let batch = ctx
+        .execute_logical_plan(plan)
+        .await
+        .unwrap()
+        .collect()
+        .await
+        .unwrap();
+
+    assert_batches_eq!(
+        [
+            "+---+", "| y |", "+---+", "| 2 |", "| 3 |", "| 4 |", "+---+",
+        ],
+        &batch
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_codec_resolver_and_permissions() {
+    /// Resolver that returns a fixed component.
+    #[derive(Debug)]
+    struct Resolver {
+        component: Arc<WasmComponentPrecompiled>,
+        permissions: Arc<WasmPermissions>,
+    }
+
+    impl WasmComponentResolver for Resolver {
+        fn resolve(
+            &self,
+            component_digest: u128,
+        ) -> DataFusionResult<Option<(Arc<WasmComponentPrecompiled>, Arc<WasmPermissions>)>>
+        {
+            Ok((component_digest == self.component.digest())
+                .then(|| (Arc::clone(&self.component), Arc::clone(&self.permissions))))
+        }
+    }
+
+    let query = r#"
+CREATE FUNCTION add_one()
+LANGUAGE python
+AS '
+def add_one(x: int) -> int:
+    return x + 1
+';
+
+SELECT add_one(x) AS y
+FROM (VALUES (1), (2), (3)) AS t(x)
+ORDER BY y;
+"#;
+
+    let ctx = session_ctx();
+    let parser = UdfQueryParser::new(HashMap::from_iter([(
+        "python".to_string(),
+        Lang {
+            component: ComponentFn::lazy(python_component),
+            formatters: vec![],
+        },
+    )]));
+    let parsed_query = parser
+        .parse(
+            query,
+            &WasmPermissions::new(),
+            Handle::current(),
+            ctx.task_ctx().as_ref(),
+        )
+        .await
+        .unwrap();
+    let df = UdfQueryInvocator::invoke(&ctx, parsed_query).await.unwrap();
+    let plan = df.into_optimized_plan().unwrap();
+
+    // SAFETY: the data was produced by this very process
+    let component = Arc::new(
+        unsafe { WasmComponentPrecompiled::load(python_component().await.store().to_vec()) }
+            .unwrap(),
+    );
+    let memory_pool = Arc::clone(ctx.task_ctx().memory_pool());
+    let bytes = logical_plan_to_bytes_with_extension_codec(
+        &plan,
+        &WasmUdfCodec::new(Handle::current(), Arc::clone(&memory_pool)),
+    )
+    .unwrap();
+
+    // the other side uses different permissions
+    let codec = WasmUdfCodec::new(Handle::current(), Arc::clone(&memory_pool))
+        .with_component_resolver(Arc::new(Resolver {
+            component: Arc::clone(&component),
+            permissions: Arc::new(WasmPermissions::new().with_max_udfs(1)),
+        }))
+        .with_matching_permissions(true);
+    let ctx = session_ctx();
+    let err = logical_plan_from_bytes_with_extension_codec(&bytes, ctx.task_ctx().as_ref(), &codec)
+        .unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("uses different permissions than the encoding side"),
+        "{err}",
+    );
+
+    // same permissions
+    let codec = WasmUdfCodec::new(Handle::current(), memory_pool)
+        .with_component_resolver(Arc::new(Resolver {
+            component,
+            permissions: Arc::new(WasmPermissions::new()),
+        }))
+        .with_matching_permissions(true);
+    let plan =
+        logical_plan_from_bytes_with_extension_codec(&bytes, ctx.task_ctx().as_ref(), &codec)
+            .unwrap();
+    let batch = ctx
+        .execute_logical_plan(plan)
+        .await
+        .unwrap()
+        .collect()
+        .await
+        .unwrap();
+
+    assert_batches_eq!(
+        [
+            "+---+", "| y |", "+---+", "| 2 |", "| 3 |", "| 4 |", "+---+",
+        ],
+        &batch
+    );
+}
+
+#[cfg(feature = "substrait")]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_substrait_roundtrip() {
+    use datafusion_substrait::logical_plan::{
+        consumer::from_substrait_plan, producer::to_substrait_plan,
+    };
+
+    let query = r#"
+CREATE FUNCTION add_one()
+LANGUAGE python
+AS '
+def add_one(x: int) -> int:
+    return x + 1
+';
+
+SELECT add_one(x) AS y
+FROM (VALUES (1), (2), (3)) AS t(x)
+ORDER BY y;
+"#;
+
+    let ctx = session_ctx();
+
+    let parser = UdfQueryParser::new(HashMap::from_iter([(
+        "python".to_string(),
+        Lang {
+            component: ComponentFn::lazy(python_component),
+            formatters: vec![],
+        },
+    )]));
+    let parsed_query = parser
+        .parse(
+            query,
+            &WasmPermissions::new(),
+            Handle::current(),
+            ctx.task_ctx().as_ref(),
+        )
+        .await
+        .unwrap();
+
+    let df = UdfQueryInvocator::invoke(&ctx, parsed_query).await.unwrap();
+    let logical_plan = df.into_optimized_plan().unwrap();
+    let mut plan = to_substrait_plan(&logical_plan, &ctx.state()).unwrap();
+    WasmUdfCodec::encode_substrait(&logical_plan, &mut plan).unwrap();
+
+    // SAFETY: the data was produced by this very process
+    let component =
+        unsafe { WasmComponentPrecompiled::load(python_component().await.store().to_vec()) }
+            .unwrap();
+    let codec = WasmUdfCodec::new(Handle::current(), Arc::clone(ctx.task_ctx().memory_pool()))
+        .with_component(Arc::new(component), Arc::new(WasmPermissions::new()));
+
+    // the other side does NOT have the UDF registered
+    let mut state = session_ctx().state();
+    codec.decode_substrait(&plan, &mut state).unwrap();
+    let logical_plan = from_substrait_plan(&state, &plan).await.unwrap();
+    let batch = SessionContext::new_with_state(state)
+        .execute_logical_plan(logical_plan)
+        .await
+        .unwrap()
+        .collect()
+        .await
+        .unwrap();
+
+    assert_batches_eq!(
+        [
+            "+---+", "| y |", "+---+", "| 2 |", "| 3 |", "| 4 |", "+---+",
+        ],
+        &batch
+    );
+}
+
+/// Get session context.
+#[tokio::test]
+async fn test_sql_is_preserved() {
+    let query = r#"
+CREATE FUNCTION add_one()
+LANGUAGE python
+AS '
+def add_one(x: int) -> int:
+    return x + 1
+';
+
+-- keep me
+SELECT add_one(x) /* and me */ FROM t WHERE y = $1;
+"#;
+
+    let ctx = session_ctx();
+    let parser = UdfQueryParser::new(HashMap::from_iter([(
+        "python".to_string(),
+        Lang {
+            component: ComponentFn::lazy(python_component),
+            formatters: vec![],
+        },
+    )]));
+    let parsed_query = parser
+        .parse(
+            query,
+            &WasmPermissions::new(),
+            Handle::current(),
+            ctx.task_ctx().as_ref(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(
+        parsed_query.sql,
+        "SELECT add_one(x) /* and me */ FROM t WHERE y = $1;\n",
+    );
+}
+
+#[tokio::test]
+async fn test_create_or_replace_and_drop() {
+    let ctx = session_ctx();
+    let parser = UdfQueryParser::new(HashMap::from_iter([(
+        "python".to_string(),
+        Lang {
+            component: ComponentFn::lazy(python_component),
+            formatters: vec![],
+        },
+    )]));
+    let run = async |query: &str| {
+        let parsed_query = parser
+            .parse(
+                query,
+                &WasmPermissions::new(),
+                Handle::current(),
+                ctx.task_ctx().as_ref(),
+            )
+            .await?;
+        let statements = parsed_query.statements.clone();
+        let sql = parsed_query.register(&mut *ctx.state_ref().write())?;
+        let batches = if sql.is_empty() {
+            vec![]
+        } else {
+            ctx.sql(&sql).await?.collect().await?
+        };
+        DataFusionResult::Ok((statements, batches_to_string(&batches)))
+    };
+
+    let (statements, _) = run(r#"
+CREATE FUNCTION f()
+LANGUAGE python
+AS '
+def f(x: int) -> int:
+    return x + 1
+';
+"#)
+    .await
+    .unwrap();
+    assert_eq!(
+        statements,
+        [UdfStatement::Create {
+            declared_name: "f".to_owned(),
+            names: vec!["f".to_owned()],
+            or_replace: false,
+        }],
+    );
+
+    let err = run(r#"
+CREATE FUNCTION f()
+LANGUAGE python
+AS '
+def f(x: int) -> int:
+    return x + 2
+';
+"#)
+    .await
+    .unwrap_err();
+    insta::assert_snapshot!(err, @"Error during planning: function already exists: f");
+
+    let (_, out) = run(r#"
+CREATE OR REPLACE FUNCTION f()
+LANGUAGE python
+AS '
+def f(x: int) -> int:
+    return x + 2
+';
+
+SELECT f(1) AS y;
+"#)
+    .await
+    .unwrap();
+    insta::assert_snapshot!(out, @r"
+    +---+
+    | y |
+    +---+
+    | 3 |
+    +---+
+    ");
+
+    let (statements, _) = run("DROP FUNCTION f;").await.unwrap();
+    assert_eq!(
+        statements,
+        [UdfStatement::Drop {
+            names: vec!["f".to_owned()],
+            if_exists: false,
+        }],
+    );
+    assert!(!ctx.state().scalar_functions().contains_key("f"));
+
+    let err = run("DROP FUNCTION f;").await.unwrap_err();
+    insta::assert_snapshot!(err, @"Error during planning: function does not exist: f");
+
+    run("DROP FUNCTION IF EXISTS f;").await.unwrap();
+}
+
+#[tokio::test]
+async fn test_cache() {
+    let query = |body: &str| {
+        format!(
+            r#"
+CREATE FUNCTION add_one()
+LANGUAGE python
+AS '
+def add_one(x: int) -> int:
+    return x + {body}
+';
+
+SELECT add_one(1);
+"#
+        )
+    };
+
+    let ctx = session_ctx();
+    let parser = UdfQueryParser::new(HashMap::from_iter([(
+        "python".to_string(),
+        Lang {
+            component: ComponentFn::lazy(python_component),
+            formatters: vec![Box::new(StripIndentationFormatter)],
+        },
+    )]))
+    .with_cache(UdfCacheLimits {
+        max_entries: NonZeroUsize::new(1).unwrap(),
+        ..Default::default()
+    });
+    let parse = async |query: &str, permissions: &WasmPermissions| {
+        parser
+            .parse(
+                query,
+                permissions,
+                Handle::current(),
+                ctx.task_ctx().as_ref(),
+            )
+            .await
+            .unwrap()
+            .udfs
+    };
+    let permissions = WasmPermissions::new();
+
+    let udfs_1 = parse(&query("1"), &permissions).await;
+    assert_eq!(udfs_1.len(), 1);
+
+    // same code => same UDF
+    let udfs_2 = parse(&query("1"), &permissions).await;
+    assert!(Arc::ptr_eq(&udfs_1[0], &udfs_2[0]));
+
+    // different permissions => different UDF
+    let udfs_3 = parse(&query("1"), &permissions.clone().with_max_udfs(1)).await;
+    assert!(!Arc::ptr_eq(&udfs_1[0], &udfs_3[0]));
+
+    // different code => different UDF
+    let udfs_4 = parse(&query("2"), &permissions).await;
+    assert!(!Arc::ptr_eq(&udfs_1[0], &udfs_4[0]));
+
+    // evicted since the cache only holds a single entry
+    let udfs_5 = parse(&query("1"), &permissions).await;
+    assert!(!Arc::ptr_eq(&udfs_1[0], &udfs_5[0]));
+
+    // cached UDFs still work
+    let df = UdfQueryInvocator::invoke(
+        &ctx,
+        ParsedQuery {
+            udfs: udfs_5,
+            statements: vec![],
+            sql: "SELECT add_one(1) AS y".to_owned(),
+        },
+    )
+    .await
+    .unwrap();
+    assert_batches_eq!(
+        ["+---+", "| y |", "+---+", "| 2 |", "+---+",],
+        &df.collect().await.unwrap()
+    );
+}
+
+#[tokio::test]
+async fn test_cache_ttl() {
+    let query = r#"
+CREATE FUNCTION add_one()
+LANGUAGE python
+AS '
+def add_one(x: int) -> int:
+    return x + 1
+';
+
+SELECT add_one(1);
+"#;
+
+    let ctx = session_ctx();
+    let parser = UdfQueryParser::new(HashMap::from_iter([(
+        "python".to_string(),
+        Lang {
+            component: ComponentFn::lazy(python_component),
+            formatters: vec![],
+        },
+    )]))
+    .with_cache(UdfCacheLimits {
+        ttl: Duration::ZERO,
+        ..Default::default()
+    });
+    let parse = async || {
+        parser
+            .parse(
+                query,
+                &WasmPermissions::new(),
+                Handle::current(),
+                ctx.task_ctx().as_ref(),
+            )
+            .await
+            .unwrap()
+            .udfs
+    };
+
+    // expired right away
+    let udfs_1 = parse().await;
+    let udfs_2 = parse().await;
+    assert!(!Arc::ptr_eq(&udfs_1[0], &udfs_2[0]));
+}
+
 fn session_ctx() -> SessionContext {
     SessionContext::new_with_config_rt(
         SessionConfig::new(),
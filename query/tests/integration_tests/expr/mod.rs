@@ -0,0 +1 @@
+pub(crate) mod test_utils;
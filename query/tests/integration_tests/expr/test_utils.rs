@@ -0,0 +1,19 @@
+use datafusion_udf_wasm_host::{CompilationFlags, WasmComponentPrecompiled};
+use tokio::sync::OnceCell;
+
+/// Static precompiled expression language WASM component for tests
+static COMPONENT: OnceCell<WasmComponentPrecompiled> = OnceCell::const_new();
+
+/// Returns a static reference to the precompiled expression language WASM component.
+pub(crate) async fn expr_component() -> &'static WasmComponentPrecompiled {
+    COMPONENT
+        .get_or_init(async || {
+            WasmComponentPrecompiled::compile(
+                datafusion_udf_wasm_bundle::BIN_EXPR.into(),
+                &CompilationFlags::default(),
+            )
+            .await
+            .unwrap()
+        })
+        .await
+}
@@ -1 +1,3 @@
+pub(crate) mod expr;
 pub(crate) mod python;
+pub(crate) mod rust;
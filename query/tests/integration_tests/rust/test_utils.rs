@@ -0,0 +1,19 @@
+use datafusion_udf_wasm_host::{CompilationFlags, WasmComponentPrecompiled};
+use tokio::sync::OnceCell;
+
+/// Static precompiled "add-ints" example WASM component for tests
+static ADD_INTS_COMPONENT: OnceCell<WasmComponentPrecompiled> = OnceCell::const_new();
+
+/// Returns a static reference to the precompiled "add-ints" example WASM component.
+pub(crate) async fn add_ints_component() -> &'static WasmComponentPrecompiled {
+    ADD_INTS_COMPONENT
+        .get_or_init(async || {
+            WasmComponentPrecompiled::compile(
+                datafusion_udf_wasm_bundle::BIN_EXAMPLE_ADD_INTS.into(),
+                &CompilationFlags::default(),
+            )
+            .await
+            .unwrap()
+        })
+        .await
+}